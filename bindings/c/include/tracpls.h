@@ -0,0 +1,45 @@
+/*
+ * C API of tracpls
+ *
+ * Returned strings are UTF-8 JSON text owned by the caller, and have to be
+ * freed with tracpls_string_free(). On failure NULL is returned, and the error
+ * message can be retrieved with tracpls_last_error().
+ *
+ * Chains are selected by name e.g. "bsc", "ethereum", "polygon", or ones
+ * registered in manifest of endpoints. API keys are read from the same
+ * environment variables as the cli e.g. TRACPLS_BSCSCAN_APIKEY.
+ */
+#ifndef TRACPLS_H
+#define TRACPLS_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Initialize library. It has to be called once before any other function.
+ * Returns 0 on success, otherwise -1. */
+int tracpls_init(void);
+
+/* Fetch verified source code of contract as JSON text in form of
+ * {"contract_name", "compiler_version", "is_multi_file", "files": [{"path", "content"}]} */
+char *tracpls_fetch_source_json(const char *address, const char *chain);
+
+/* Fetch contract ABI as JSON text. */
+char *tracpls_fetch_abi_json(const char *address, const char *chain);
+
+/* Fetch verification metadata of contract as JSON text. */
+char *tracpls_fetch_meta_json(const char *address, const char *chain);
+
+/* Get error message of the last failed call on the calling thread, or NULL if
+ * there is none. It is owned by the library, and valid until the next call on
+ * the same thread. */
+const char *tracpls_last_error(void);
+
+/* Free string returned by the library. NULL is accepted. */
+void tracpls_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* TRACPLS_H */
@@ -0,0 +1,155 @@
+//! C ABI of tracpls so non-Rust programs can embed fetching contracts without
+//! spawning process. See `include/tracpls.h` for the API.
+//!
+//! Returned strings are UTF-8 JSON text owned by the caller, and have to be
+//! freed with `tracpls_string_free()`. On failure `NULL` is returned, and the
+//! error message can be retrieved with `tracpls_last_error()`.
+
+use serde_json::json;
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+
+thread_local! {
+    /// Error of the last failed call on this thread
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Record error to be retrieved by `tracpls_last_error()`.
+///
+/// # Arguments
+/// * `message` - error message
+fn set_last_error(message: &str) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+/// Convert C string argument into `&str`.
+///
+/// # Arguments
+/// * `ptr` - pointer to NUL-terminated string
+/// * `name` - name of argument for error message
+///
+/// # Safety
+/// `ptr` has to be either `NULL`, or point to a valid NUL-terminated string.
+unsafe fn arg_str<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, String> {
+    if ptr.is_null() {
+        let err_msg = format!("Error argument '{}' is NULL", name);
+        return Err(err_msg);
+    }
+    match CStr::from_ptr(ptr).to_str() {
+        Ok(res) => Ok(res),
+        Err(e) => {
+            let err_msg = format!("Error argument '{}' is not valid UTF-8; err={}", name, e);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Run function returning text across C ABI; errors and panics are recorded
+/// as last error, and turned into `NULL`.
+///
+/// # Arguments
+/// * `f` - function to run
+fn return_string<F: FnOnce() -> Result<String, String>>(f: F) -> *mut c_char {
+    let result = match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(res) => res,
+        Err(_) => Err("Error internal panic".to_owned()),
+    };
+
+    match result.and_then(|text| CString::new(text).map_err(|e| format!("Error result contains NUL; err={}", e))) {
+        Ok(text) => text.into_raw(),
+        Err(e) => {
+            set_last_error(&e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Initialize library. It has to be called once before any other function.
+/// Returns 0 on success, otherwise -1.
+#[no_mangle]
+pub extern "C" fn tracpls_init() -> c_int {
+    match panic::catch_unwind(tracpls::init) {
+        Ok(Ok(_)) => 0,
+        Ok(Err(e)) => {
+            set_last_error(&e);
+            -1
+        },
+        Err(_) => {
+            set_last_error("Error internal panic");
+            -1
+        }
+    }
+}
+
+/// Fetch verified source code of contract as JSON text in form of
+/// `{"contract_name", "compiler_version", "is_multi_file", "files": [{"path", "content"}]}`.
+///
+/// # Safety
+/// `address`, and `chain` have to point to valid NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn tracpls_fetch_source_json(address: *const c_char, chain: *const c_char) -> *mut c_char {
+    return_string(|| {
+        let address = arg_str(address, "address")?;
+        let chain = arg_str(chain, "chain")?;
+        let source = tracpls::fetch_source(address, chain)?;
+        let json = json!({
+            "contract_name": source.contract_name,
+            "compiler_version": source.compiler_version,
+            "is_multi_file": source.is_multi_file,
+            "files": source.files.iter().map(|f| json!({ "path": f.path, "content": f.content })).collect::<Vec<serde_json::Value>>(),
+        });
+        Ok(json.to_string())
+    })
+}
+
+/// Fetch contract ABI as JSON text.
+///
+/// # Safety
+/// `address`, and `chain` have to point to valid NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn tracpls_fetch_abi_json(address: *const c_char, chain: *const c_char) -> *mut c_char {
+    return_string(|| {
+        let address = arg_str(address, "address")?;
+        let chain = arg_str(chain, "chain")?;
+        tracpls::fetch_abi(address, chain, false)
+    })
+}
+
+/// Fetch verification metadata of contract e.g. `ContractName`,
+/// `CompilerVersion`, `OptimizationUsed`, `Runs`, and `LicenseType` as JSON
+/// text.
+///
+/// # Safety
+/// `address`, and `chain` have to point to valid NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn tracpls_fetch_meta_json(address: *const c_char, chain: *const c_char) -> *mut c_char {
+    return_string(|| {
+        let address = arg_str(address, "address")?;
+        let chain = tracpls::parse_chain(arg_str(chain, "chain")?)?;
+        let meta = tracpls::explorer::get_source_meta(chain, address)?;
+        Ok(meta.to_string())
+    })
+}
+
+/// Get error message of the last failed call on the calling thread, or `NULL`
+/// if there is none. It is owned by the library, and valid until the next
+/// call on the same thread.
+#[no_mangle]
+pub extern "C" fn tracpls_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(std::ptr::null(), |m| m.as_ptr()))
+}
+
+/// Free string returned by the library.
+///
+/// # Safety
+/// `s` has to be `NULL`, or a string returned by the library which has not
+/// been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn tracpls_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
@@ -0,0 +1,98 @@
+//! Python bindings of tracpls.
+//!
+//! ```python
+//! import tracpls
+//! source = tracpls.fetch_source("0x1befe6f3f0e8edd2d4d15cae97baee01e51ea4a4", chain="bsc")
+//! for f in source.files:
+//!     print(f.path, len(f.content))
+//! ```
+
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+
+/// Single source file of verified contract.
+#[pyclass(name = "SourceFile")]
+#[derive(Clone)]
+struct SourceFile {
+    /// Path of file as submitted for verification
+    #[pyo3(get)]
+    path: String,
+
+    #[pyo3(get)]
+    content: String,
+}
+
+#[pymethods]
+impl SourceFile {
+    fn __repr__(&self) -> String {
+        format!("SourceFile(path='{}', {} bytes)", self.path, self.content.len())
+    }
+}
+
+/// Verified source code of contract.
+#[pyclass(name = "ContractSource")]
+struct ContractSource {
+    /// Name of the primary contract
+    #[pyo3(get)]
+    contract_name: String,
+
+    #[pyo3(get)]
+    compiler_version: String,
+
+    /// Whether source code was submitted as multiple files
+    #[pyo3(get)]
+    is_multi_file: bool,
+
+    #[pyo3(get)]
+    files: Vec<SourceFile>,
+}
+
+#[pymethods]
+impl ContractSource {
+    fn __repr__(&self) -> String {
+        format!("ContractSource(contract_name='{}', compiler_version='{}', {} file(s))", self.contract_name, self.compiler_version, self.files.len())
+    }
+}
+
+/// Fetch verified source code of contract.
+///
+/// # Arguments
+/// * `address` - contract address
+/// * `chain` - name of chain e.g. `bsc`
+#[pyfunction]
+#[pyo3(text_signature = "(address, chain='bsc')")]
+#[args(chain = "\"bsc\"")]
+fn fetch_source(py: Python, address: &str, chain: &str) -> PyResult<ContractSource> {
+    // don't hold GIL while waiting for network
+    let source = py.allow_threads(|| tracpls_core::fetch_source(address, chain)).map_err(PyRuntimeError::new_err)?;
+    Ok(ContractSource {
+        contract_name: source.contract_name,
+        compiler_version: source.compiler_version,
+        is_multi_file: source.is_multi_file,
+        files: source.files.into_iter().map(|f| SourceFile { path: f.path, content: f.content }).collect(),
+    })
+}
+
+/// Fetch contract ABI as JSON text.
+///
+/// # Arguments
+/// * `address` - contract address
+/// * `chain` - name of chain e.g. `bsc`
+/// * `pretty` - whether or not to pretty print
+#[pyfunction]
+#[pyo3(text_signature = "(address, chain='bsc', pretty=False)")]
+#[args(chain = "\"bsc\"", pretty = "false")]
+fn fetch_abi(py: Python, address: &str, chain: &str, pretty: bool) -> PyResult<String> {
+    py.allow_threads(|| tracpls_core::fetch_abi(address, chain, pretty)).map_err(PyRuntimeError::new_err)
+}
+
+#[pymodule]
+fn tracpls(_py: Python, m: &PyModule) -> PyResult<()> {
+    tracpls_core::init().map_err(PyRuntimeError::new_err)?;
+
+    m.add_class::<SourceFile>()?;
+    m.add_class::<ContractSource>()?;
+    m.add_function(wrap_pyfunction!(fetch_source, m)?)?;
+    m.add_function(wrap_pyfunction!(fetch_abi, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,60 @@
+//! Transport sending requests with `fetch()` of the host.
+
+use tracpls::transport::{AsyncTransport, HttpRequest, HttpResponse};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Request, RequestInit, Response};
+
+#[wasm_bindgen]
+extern "C" {
+    /// `fetch()` of global scope, so it's the same in browser, web worker, and
+    /// Node.js
+    #[wasm_bindgen(js_name = fetch)]
+    fn fetch_with_request(input: &Request) -> js_sys::Promise;
+}
+
+/// Describe value thrown by JavaScript e.g. `TypeError: Failed to fetch`.
+///
+/// # Arguments
+/// * `value` - thrown value
+pub fn describe(value: JsValue) -> String {
+    match value.dyn_ref::<js_sys::Error>() {
+        Some(e) => String::from(e.to_string()),
+        None => value.as_string().unwrap_or_else(|| format!("{:?}", value)),
+    }
+}
+
+/// Transport sending requests with `fetch()`; browser, web worker, or
+/// Node.js 18+. API platforms have to allow the origin by CORS when used in
+/// browser.
+pub struct FetchTransport;
+
+impl AsyncTransport for FetchTransport {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, String> {
+        let init = RequestInit::new();
+        init.set_method(request.method);
+        if !request.body.is_empty() {
+            init.set_body(&JsValue::from_str(&request.body));
+        }
+        let js_request = Request::new_with_str_and_init(&request.url, &init).map_err(describe)?;
+        for (name, value) in request.headers.iter() {
+            js_request.headers().set(name, value).map_err(describe)?;
+        }
+
+        let response: Response = JsFuture::from(fetch_with_request(&js_request)).await
+            .and_then(|r| r.dyn_into())
+            .map_err(describe)?;
+        let body = JsFuture::from(response.text().map_err(describe)?).await.map_err(describe)?;
+
+        // entries of Headers are pairs of name, and value
+        let mut headers = Vec::new();
+        if let Ok(Some(entries)) = js_sys::try_iter(response.headers().as_ref()) {
+            for entry in entries.flatten() {
+                let pair = js_sys::Array::from(&entry);
+                headers.push((pair.get(0).as_string().unwrap_or_default(), pair.get(1).as_string().unwrap_or_default()));
+            }
+        }
+        Ok(HttpResponse { status: response.status(), headers, body: body.as_string().unwrap_or_default() })
+    }
+}
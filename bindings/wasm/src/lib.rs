@@ -0,0 +1,100 @@
+//! WebAssembly bindings of tracpls so web tools, and editor extensions e.g.
+//! of VS Code can embed fetching contracts. Requests are sent with `fetch()`
+//! of the host (see `fetch`), built with `wasm-pack build --target web`, or
+//! `--target nodejs`.
+//!
+//! ```js
+//! import init, { fetchSource, fetchAbi } from "./pkg/tracpls_wasm.js";
+//! await init();
+//! const source = await fetchSource("0x1befe6f3f0e8edd2d4d15cae97baee01e51ea4a4", "bsc", apiKey);
+//! for (const f of source.files) {
+//!     console.log(f.path, f.content.length);
+//! }
+//! const abi = await fetchAbi("0x1befe6f3f0e8edd2d4d15cae97baee01e51ea4a4", "bsc", apiKey);
+//! ```
+//!
+//! Errors are thrown as `Error`.
+
+// bindings are only of wasm32 where fetch() is
+#![cfg(target_arch = "wasm32")]
+
+mod fetch;
+
+use fetch::FetchTransport;
+use serde_json::json;
+use std::sync::Once;
+use wasm_bindgen::prelude::*;
+
+/// Chains are registered once before the first call
+static INIT: Once = Once::new();
+
+/// Register built-in chains, and ones of embedded registry of chains.
+fn init() -> Result<(), JsError> {
+    let mut result = Ok(());
+    INIT.call_once(|| result = tracpls::init());
+    result.map_err(|e| JsError::new(&e))
+}
+
+/// Convert JSON into JavaScript value.
+///
+/// # Arguments
+/// * `text` - JSON text
+fn to_js(text: &str) -> Result<JsValue, JsError> {
+    js_sys::JSON::parse(text).map_err(|e| JsError::new(&fetch::describe(e)))
+}
+
+/// Register chains from manifest of endpoints in the same form as
+/// `backends.json` e.g. of chains not in registry, or enterprise instances.
+/// `apikey_env` is ignored as there is no environment; pass API key to each
+/// call instead.
+///
+/// # Arguments
+/// * `manifest` - manifest of endpoints as JSON text
+#[wasm_bindgen(js_name = registerChains)]
+pub fn register_chains(manifest: &str) -> Result<(), JsError> {
+    init()?;
+    let entries: Vec<tracpls::backend::ManifestEntry> = match serde_json::from_str(manifest) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error parsing manifest of endpoints; err={}", e);
+            return Err(JsError::new(&err_msg));
+        }
+    };
+    tracpls::backend::register_entries(entries);
+    Ok(())
+}
+
+/// Fetch verified source code of contract as object in form of
+/// `{contractName, compilerVersion, isMultiFile, files: [{path, content}]}`.
+///
+/// # Arguments
+/// * `address` - contract address
+/// * `chain` - name of chain e.g. `bsc`, or chain id
+/// * `apikey` - API key of API platform
+#[wasm_bindgen(js_name = fetchSource)]
+pub async fn fetch_source(address: String, chain: String, apikey: Option<String>) -> Result<JsValue, JsError> {
+    init()?;
+    let source = tracpls::fetch_source_with(&FetchTransport, &address, &chain, apikey.as_deref()).await
+        .map_err(|e| JsError::new(&e))?;
+    let json = json!({
+        "contractName": source.contract_name,
+        "compilerVersion": source.compiler_version,
+        "isMultiFile": source.is_multi_file,
+        "files": source.files.iter().map(|f| json!({ "path": f.path, "content": f.content })).collect::<Vec<serde_json::Value>>(),
+    });
+    to_js(&json.to_string())
+}
+
+/// Fetch contract ABI as array of its entries.
+///
+/// # Arguments
+/// * `address` - contract address
+/// * `chain` - name of chain e.g. `bsc`, or chain id
+/// * `apikey` - API key of API platform
+#[wasm_bindgen(js_name = fetchAbi)]
+pub async fn fetch_abi(address: String, chain: String, apikey: Option<String>) -> Result<JsValue, JsError> {
+    init()?;
+    let abi = tracpls::fetch_abi_with(&FetchTransport, &address, &chain, apikey.as_deref(), false).await
+        .map_err(|e| JsError::new(&e))?;
+    to_js(&abi)
+}
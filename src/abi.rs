@@ -0,0 +1,603 @@
+//! Contract ABI model, and helpers to compute signatures and selectors.
+
+use crate::backend::Chain;
+use serde::Deserialize;
+use tiny_keccak::{Hasher, Keccak};
+
+/// Parameter of function, event, or error.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Param {
+    #[serde(default)]
+    pub name: String,
+
+    #[serde(rename="type")]
+    pub kind: String,
+
+    #[serde(default, rename="internalType")]
+    pub internal_type: Option<String>,
+
+    #[serde(default)]
+    pub components: Vec<Param>,
+
+    /// Whether or not event parameter is indexed
+    #[serde(default)]
+    pub indexed: bool,
+}
+
+impl Param {
+    /// Get canonical type as used in signature.
+    /// Tuple will be expanded into its components e.g. `(address,uint256)[]`.
+    pub fn canonical_type(&self) -> String {
+        match self.kind.strip_prefix("tuple") {
+            Some(suffix) => {
+                let inner: Vec<String> = self.components.iter().map(|c| c.canonical_type()).collect();
+                format!("({}){}", inner.join(","), suffix)
+            },
+            None => self.kind.clone(),
+        }
+    }
+}
+
+/// Entry of ABI; function, constructor, event, error, fallback, or receive.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Entry {
+    #[serde(rename="type", default="default_entry_kind")]
+    pub kind: String,
+
+    #[serde(default)]
+    pub name: String,
+
+    #[serde(default)]
+    pub inputs: Vec<Param>,
+
+    #[serde(default)]
+    pub outputs: Vec<Param>,
+
+    #[serde(default, rename="stateMutability")]
+    pub state_mutability: String,
+
+    /// Legacy field prior to `stateMutability`
+    #[serde(default)]
+    pub constant: bool,
+
+    /// Legacy field prior to `stateMutability`
+    #[serde(default)]
+    pub payable: bool,
+
+    /// Whether or not event is anonymous, emitted without topic of its
+    /// signature
+    #[serde(default)]
+    pub anonymous: bool,
+}
+
+fn default_entry_kind() -> String {
+    "function".to_owned()
+}
+
+impl Entry {
+    /// Get canonical signature e.g. `transfer(address,uint256)`.
+    pub fn signature(&self) -> String {
+        let types: Vec<String> = self.inputs.iter().map(|p| p.canonical_type()).collect();
+        format!("{}({})", self.name, types.join(","))
+    }
+
+    /// Get 4-byte selector of function or error as hex string with `0x` prefix.
+    pub fn selector(&self) -> String {
+        selector_of(&self.signature())
+    }
+
+    /// Get state mutability, derived from legacy fields if necessary.
+    /// Possible values are `pure`, `view`, `nonpayable`, and `payable`.
+    pub fn mutability(&self) -> &str {
+        if !self.state_mutability.is_empty() {
+            &self.state_mutability
+        }
+        else if self.payable {
+            "payable"
+        }
+        else if self.constant {
+            "view"
+        }
+        else {
+            "nonpayable"
+        }
+    }
+
+    /// Whether or not this entry is a read-only function.
+    pub fn is_read_only(&self) -> bool {
+        matches!(self.mutability(), "view" | "pure")
+    }
+
+    /// Get human readable declaration e.g.
+    /// `transfer(address to, uint256 amount) returns (bool)`.
+    pub fn declaration(&self) -> String {
+        let format_params = |params: &[Param]| -> String {
+            params.iter()
+                .map(|p| {
+                    let mut param = p.canonical_type();
+                    if p.indexed {
+                        param.push_str(" indexed");
+                    }
+                    if !p.name.is_empty() {
+                        param.push(' ');
+                        param.push_str(&p.name);
+                    }
+                    param
+                })
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+
+        let mut declaration = format!("{}({})", self.name, format_params(&self.inputs));
+        if !self.outputs.is_empty() {
+            declaration.push_str(&format!(" returns ({})", format_params(&self.outputs)));
+        }
+        declaration
+    }
+}
+
+/// Whole contract ABI.
+#[derive(Debug, Clone)]
+pub struct Abi {
+    pub entries: Vec<Entry>,
+}
+
+impl Abi {
+    /// Parse ABI from its JSON text.
+    ///
+    /// # Arguments
+    /// * `text` - ABI in JSON
+    pub fn parse(text: &str) -> Result<Abi, String> {
+        match serde_json::from_str::<Vec<Entry>>(text) {
+            Ok(entries) => Ok(Abi { entries }),
+            Err(e) => {
+                let err_msg = format!("Error parsing contract ABI; err={}", e);
+                Err(err_msg)
+            }
+        }
+    }
+
+    /// Iterate over all functions.
+    pub fn functions(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter().filter(|e| e.kind == "function")
+    }
+
+    /// Iterate over all events.
+    pub fn events(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter().filter(|e| e.kind == "event")
+    }
+
+    /// Iterate over all custom errors.
+    pub fn errors(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter().filter(|e| e.kind == "error")
+    }
+
+    /// Find function by its 4-byte selector.
+    ///
+    /// # Arguments
+    /// * `selector` - selector as hex string with `0x` prefix, case-insensitive
+    pub fn function_by_selector(&self, selector: &str) -> Option<&Entry> {
+        let selector = selector.to_lowercase();
+        self.functions().find(|f| f.selector() == selector)
+    }
+}
+
+/// Fetch contract ABI from API platform then parse it.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+pub fn fetch_abi(chain: &Chain, address: &str) -> Result<Abi, String> {
+    match crate::explorer::get_abi(chain, address, false) {
+        Ok(abi) => Abi::parse(&abi),
+        Err(e) => {
+            let err_msg = format!("Error getting contract ABI of {}; err={}", address, e);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Get 4-byte selector of canonical signature e.g. `owner()` as hex string
+/// with `0x` prefix.
+///
+/// # Arguments
+/// * `signature` - canonical signature
+pub fn selector_of(signature: &str) -> String {
+    let hash = keccak256(signature.as_bytes());
+    format!("0x{}", to_hex(&hash[..4]))
+}
+
+/// Compute keccak256 hash.
+///
+/// # Arguments
+/// * `bytes` - input bytes
+pub fn keccak256(bytes: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak::v256();
+    let mut output = [0u8; 32];
+    hasher.update(bytes);
+    hasher.finalize(&mut output);
+    output
+}
+
+/// Encode bytes into lowercase hex string without `0x` prefix.
+///
+/// # Arguments
+/// * `bytes` - bytes to encode
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decode hex string with or without `0x` prefix into bytes.
+///
+/// # Arguments
+/// * `text` - hex string
+pub fn from_hex(text: &str) -> Result<Vec<u8>, String> {
+    let hex = text.trim().trim_start_matches("0x");
+    hex.as_bytes().chunks(2).enumerate().map(|(i, pair)| {
+        let byte = std::str::from_utf8(pair).ok().filter(|p| p.len() == 2).and_then(|p| u8::from_str_radix(p, 16).ok());
+        match byte {
+            Some(res) => Ok(res),
+            None => {
+                let err_msg = format!("Error decoding hex string at {}; '{}' is not a hex byte", i * 2, String::from_utf8_lossy(pair));
+                Err(err_msg)
+            }
+        }
+    }).collect()
+}
+
+/// Decode return data of function returning `string`. Legacy tokens
+/// returning `bytes32` instead are decoded as well. Return `None` if it is
+/// neither.
+///
+/// # Arguments
+/// * `data` - return data
+pub fn decode_string(data: &[u8]) -> Option<String> {
+    let word = |at: usize| decode_offset(data.get(at..at.checked_add(32)?)?);
+
+    if data.len() == 32 {
+        let text: Vec<u8> = data.iter().copied().take_while(|b| *b != 0).collect();
+        return String::from_utf8(text).ok();
+    }
+    let offset = word(0)?;
+    let len = word(offset)?;
+    let start = offset.checked_add(32)?;
+    let bytes = data.get(start..start.checked_add(len)?)?;
+    String::from_utf8(bytes.to_vec()).ok()
+}
+
+/// Decode 32-byte ABI-encoded word of offset, or length. Returns `None` if
+/// it's too large to be one.
+///
+/// # Arguments
+/// * `word` - 32-byte word
+pub fn decode_offset(word: &[u8]) -> Option<usize> {
+    // larger values can't be valid offset, or length anyway
+    if word.len() != 32 || word[..24].iter().any(|b| *b != 0) {
+        return None;
+    }
+    usize::try_from(word[24..].iter().fold(0u64, |acc, b| (acc << 8) | *b as u64)).ok()
+}
+
+/// Convert big-endian unsigned integer into decimal string.
+///
+/// # Arguments
+/// * `bytes` - big-endian unsigned integer of any length
+pub fn to_decimal(bytes: &[u8]) -> String {
+    // repeatedly divide by 10, collecting remainders as digits
+    let mut number: Vec<u8> = bytes.iter().copied().skip_while(|b| *b == 0).collect();
+    let mut digits: Vec<u8> = Vec::new();
+    while !number.is_empty() {
+        let mut remainder = 0u32;
+        for byte in number.iter_mut() {
+            let value = (remainder << 8) | *byte as u32;
+            *byte = (value / 10) as u8;
+            remainder = value % 10;
+        }
+        digits.push(b'0' + remainder as u8);
+        let zeros = number.iter().take_while(|b| **b == 0).count();
+        number.drain(..zeros);
+    }
+    if digits.is_empty() {
+        return "0".to_owned();
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap_or_default()
+}
+
+/// Decode 32-byte ABI-encoded word of value type into human readable form
+/// e.g. address, integer, or bool. Value of other types is returned as hex.
+///
+/// # Arguments
+/// * `kind` - Solidity type e.g. `uint256`, `address payable`, or
+///   `contract IERC20`
+/// * `word` - 32-byte word
+pub fn decode_word(kind: &str, word: &[u8]) -> String {
+    if word.len() != 32 {
+        return format!("0x{}", to_hex(word));
+    }
+
+    let base = kind.split_whitespace().next().unwrap_or("");
+    match base {
+        "address" | "contract" => to_checksum_address(&format!("0x{}", to_hex(&word[12..]))),
+        "bool" => (word[31] != 0).to_string(),
+        _ if base.starts_with("uint") || base == "enum" => to_decimal(word),
+        _ if base.starts_with("int") => {
+            if word[0] & 0x80 == 0 {
+                to_decimal(word)
+            }
+            else {
+                // two's complement of negative value
+                let mut negated: Vec<u8> = word.iter().map(|b| !b).collect();
+                for byte in negated.iter_mut().rev() {
+                    let (sum, overflow) = byte.overflowing_add(1);
+                    *byte = sum;
+                    if !overflow {
+                        break;
+                    }
+                }
+                format!("-{}", to_decimal(&negated))
+            }
+        },
+        _ if base.starts_with("bytes") => {
+            let len = base[5..].parse::<usize>().unwrap_or(32).min(32);
+            format!("0x{}", to_hex(&word[..len]))
+        },
+        _ => format!("0x{}", to_hex(word)),
+    }
+}
+
+/// Encode human readable value of value type into 32-byte ABI-encoded word
+/// e.g. address, integer in decimal or hex, bool, or `bytesN` in hex. It is
+/// the counterpart of `decode_word`.
+///
+/// # Arguments
+/// * `kind` - Solidity type e.g. `uint256`, or `address`
+/// * `value` - value
+pub fn encode_word(kind: &str, value: &str) -> Result<[u8; 32], String> {
+    let invalid = || format!("Error invalid value '{}' of type {}", value, kind);
+    let mut word = [0u8; 32];
+    match kind {
+        "address" => {
+            let bytes = from_hex(value).map_err(|_| invalid())?;
+            if bytes.len() != 20 {
+                return Err(invalid());
+            }
+            word[12..].copy_from_slice(&bytes);
+        },
+        "bool" => match value {
+            "true" => word[31] = 1,
+            "false" => (),
+            _ => return Err(invalid()),
+        },
+        _ if kind.starts_with("uint") || kind.starts_with("int") => {
+            let signed = kind.starts_with("int");
+            let bits = match kind.trim_start_matches('u').trim_start_matches("int") {
+                "" => 256,
+                n => n.parse::<usize>().ok().filter(|n| *n > 0 && *n <= 256 && n % 8 == 0).ok_or_else(invalid)?,
+            };
+            let (negative, digits) = match value.strip_prefix('-') {
+                Some(res) => (true, res),
+                None => (false, value),
+            };
+            let magnitude = match digits.strip_prefix("0x") {
+                Some(hex) => from_hex(&format!("{:0>1$}", hex, hex.len() + hex.len() % 2)).map_err(|_| invalid())?,
+                None => from_decimal(digits).ok_or_else(invalid)?,
+            };
+            let magnitude: Vec<u8> = magnitude.into_iter().skip_while(|b| *b == 0).collect();
+            if negative && !signed && !magnitude.is_empty() {
+                let err_msg = format!("Error invalid value '{}' of type {}; it must not be negative", value, kind);
+                return Err(err_msg);
+            }
+
+            // number of bits of magnitude, and whether it is a power of two
+            // as -2^(N-1) is the only negative value of N bits
+            let magnitude_bits = magnitude.first().map_or(0, |b| magnitude.len() * 8 - b.leading_zeros() as usize);
+            let is_power_of_two = magnitude.iter().map(|b| b.count_ones()).sum::<u32>() == 1;
+            let fits = match (signed, negative) {
+                (false, _) => magnitude_bits <= bits,
+                (true, false) => magnitude_bits < bits,
+                (true, true) => magnitude_bits < bits || (magnitude_bits == bits && is_power_of_two),
+            };
+            if !fits {
+                let err_msg = format!("Error value '{}' is out of range of {}; it must be within [{}, {}]", value, kind, int_min(signed, bits), int_max(signed, bits));
+                return Err(err_msg);
+            }
+            word[32 - magnitude.len()..].copy_from_slice(&magnitude);
+            if negative {
+                // two's complement
+                for byte in word.iter_mut() {
+                    *byte = !*byte;
+                }
+                for byte in word.iter_mut().rev() {
+                    let (sum, overflow) = byte.overflowing_add(1);
+                    *byte = sum;
+                    if !overflow {
+                        break;
+                    }
+                }
+            }
+        },
+        _ if kind.starts_with("bytes") && kind.len() > 5 => {
+            let len = kind[5..].parse::<usize>().map_err(|_| invalid())?;
+            let bytes = from_hex(value).map_err(|_| invalid())?;
+            if len > 32 || bytes.len() > len {
+                return Err(invalid());
+            }
+            word[..bytes.len()].copy_from_slice(&bytes);
+        },
+        _ => {
+            let err_msg = format!("Error encoding value of type {} is not supported; only value types are", kind);
+            return Err(err_msg);
+        }
+    }
+    Ok(word)
+}
+
+/// Get the smallest value of integer type in decimal.
+///
+/// # Arguments
+/// * `signed` - whether type is `intN` rather than `uintN`
+/// * `bits` - N of type, a multiple of 8
+fn int_min(signed: bool, bits: usize) -> String {
+    if !signed {
+        return "0".to_owned();
+    }
+    let mut bytes = vec![0u8; bits / 8];
+    bytes[0] = 0x80;
+    format!("-{}", to_decimal(&bytes))
+}
+
+/// Get the largest value of integer type in decimal.
+///
+/// # Arguments
+/// * `signed` - whether type is `intN` rather than `uintN`
+/// * `bits` - N of type, a multiple of 8
+fn int_max(signed: bool, bits: usize) -> String {
+    let mut bytes = vec![0xffu8; bits / 8];
+    if signed {
+        bytes[0] = 0x7f;
+    }
+    to_decimal(&bytes)
+}
+
+/// Convert decimal string into big-endian unsigned integer, or `None` if it
+/// is not one.
+///
+/// # Arguments
+/// * `digits` - decimal digits
+fn from_decimal(digits: &str) -> Option<Vec<u8>> {
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    // repeatedly multiply by 10, adding each digit
+    let mut number: Vec<u8> = Vec::new();
+    for digit in digits.bytes() {
+        let mut carry = (digit - b'0') as u32;
+        for byte in number.iter_mut().rev() {
+            let value = *byte as u32 * 10 + carry;
+            *byte = value as u8;
+            carry = value >> 8;
+        }
+        while carry > 0 {
+            number.insert(0, carry as u8);
+            carry >>= 8;
+        }
+    }
+    Some(number)
+}
+
+/// Convert address into EIP-55 mixed-case checksum form.
+/// Input is returned as-is if it is not a valid 20-byte hex address.
+///
+/// # Arguments
+/// * `address` - address with `0x` prefix
+pub fn to_checksum_address(address: &str) -> String {
+    let lower = address.trim_start_matches("0x").to_lowercase();
+    if lower.len() != 40 || !lower.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return address.to_owned();
+    }
+
+    let hash = to_hex(&keccak256(lower.as_bytes()));
+    let checksummed: String = lower.chars().zip(hash.chars()).map(|(c, h)| {
+        if c.is_ascii_alphabetic() && h.to_digit(16).unwrap_or(0) >= 8 { c.to_ascii_uppercase() } else { c }
+    }).collect();
+    format!("0x{}", checksummed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// ABI-encoded word of number.
+    fn word(value: u64) -> Vec<u8> {
+        let mut word = vec![0u8; 24];
+        word.extend_from_slice(&value.to_be_bytes());
+        word
+    }
+
+    #[test]
+    fn decode_word_of_value_types() {
+        let address = from_hex("0x0000000000000000000000005aaeb6053f3e94c9b9a09f33669435e7ef1beaed").unwrap();
+        assert_eq!(decode_word("address", &address), "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed");
+        assert_eq!(decode_word("contract IERC20", &address), "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed");
+        assert_eq!(decode_word("bool", &word(1)), "true");
+        assert_eq!(decode_word("uint256", &word(1_000_000)), "1000000");
+        assert_eq!(decode_word("uint256", &[0xff; 32]), "115792089237316195423570985008687907853269984665640564039457584007913129639935");
+        assert_eq!(decode_word("int256", &[0xff; 32]), "-1");
+        let mut min = [0u8; 32];
+        min[0] = 0x80;
+        assert_eq!(decode_word("int256", &min), "-57896044618658097711785492504343953926634992332820282019728792003956564819968");
+        let mut bytes4 = [0u8; 32];
+        bytes4[..4].copy_from_slice(&[0xca, 0xfe, 0xba, 0xbe]);
+        assert_eq!(decode_word("bytes4", &bytes4), "0xcafebabe");
+        assert_eq!(decode_word("uint256", &[0x01, 0x02]), "0x0102");
+    }
+
+    #[test]
+    fn encode_word_of_value_types() {
+        let address = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        assert_eq!(to_hex(&encode_word("address", address).unwrap()), "0000000000000000000000005aaeb6053f3e94c9b9a09f33669435e7ef1beaed");
+        assert_eq!(encode_word("bool", "true").unwrap().to_vec(), word(1));
+        assert_eq!(encode_word("uint256", "1000000").unwrap().to_vec(), word(1_000_000));
+        assert_eq!(encode_word("uint64", "0xff").unwrap().to_vec(), word(255));
+        assert_eq!(encode_word("int256", "-1").unwrap(), [0xff; 32]);
+        assert_eq!(to_hex(&encode_word("bytes4", "0xcafebabe").unwrap()[..4]), "cafebabe");
+        assert!(encode_word("address", "0x1234").is_err());
+        assert!(encode_word("bool", "yes").is_err());
+        assert!(encode_word("string", "text").is_err());
+
+        // round trip through decode_word
+        for (kind, value) in [("int8", "-128"), ("int8", "127"), ("uint8", "255"), ("int256", "-57896044618658097711785492504343953926634992332820282019728792003956564819968")] {
+            assert_eq!(decode_word(kind, &encode_word(kind, value).unwrap()), value);
+        }
+    }
+
+    #[test]
+    fn encode_word_of_integers_out_of_range() {
+        let err = encode_word("uint8", "256").unwrap_err();
+        assert!(err.contains("out of range of uint8; it must be within [0, 255]"), "{}", err);
+        let err = encode_word("int8", "128").unwrap_err();
+        assert!(err.contains("out of range of int8; it must be within [-128, 127]"), "{}", err);
+        let err = encode_word("int8", "-129").unwrap_err();
+        assert!(err.contains("out of range of int8"), "{}", err);
+        let err = encode_word("int16", "-0x8001").unwrap_err();
+        assert!(err.contains("it must be within [-32768, 32767]"), "{}", err);
+        assert!(encode_word("uint256", "115792089237316195423570985008687907853269984665640564039457584007913129639936").is_err());
+        assert!(encode_word("int256", "57896044618658097711785492504343953926634992332820282019728792003956564819968").is_err());
+        assert!(encode_word("uint7", "1").is_err());
+        assert!(encode_word("uint264", "1").is_err());
+    }
+
+    #[test]
+    fn encode_word_of_negative_uint() {
+        let err = encode_word("uint256", "-1").unwrap_err();
+        assert!(err.contains("it must not be negative"), "{}", err);
+        assert!(encode_word("uint8", "-0x01").is_err());
+    }
+
+    #[test]
+    fn decode_string_of_string_and_bytes32() {
+        let mut data = [word(0x20), word(4)].concat();
+        data.extend_from_slice(b"USDT");
+        data.resize(96, 0);
+        assert_eq!(decode_string(&data), Some("USDT".to_owned()));
+
+        let mut legacy = b"MKR".to_vec();
+        legacy.resize(32, 0);
+        assert_eq!(decode_string(&legacy), Some("MKR".to_owned()));
+    }
+
+    #[test]
+    fn decode_string_of_malformed_offsets() {
+        // offset, and length overflowing when added up
+        assert_eq!(decode_string(&[word(u64::MAX), word(0)].concat()), None);
+        assert_eq!(decode_string(&[word(0x20), word(u64::MAX)].concat()), None);
+
+        // offset too large to be one, and past the end of data
+        assert_eq!(decode_string(&[vec![0xff; 32], word(0)].concat()), None);
+        assert_eq!(decode_string(&[word(0x200), word(0)].concat()), None);
+
+        // length past the end of data
+        assert_eq!(decode_string(&[word(0x20), word(64)].concat()), None);
+    }
+}
@@ -0,0 +1,223 @@
+//! `addresses` command: list addresses hardcoded in verified source code, and
+//! runtime bytecode of contract, resolved against explorer, as a table of its
+//! hardcoded dependencies.
+
+use crate::abi;
+use crate::bytecode;
+use crate::explorer;
+use crate::multicall;
+use crate::source::{self, SourceFile};
+use crate::TargetArgs;
+use clap::Args;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Debug, Args)]
+pub struct AddressesArgs {
+    #[clap(flatten)]
+    pub target: TargetArgs,
+
+    /// Output as JSON instead of human readable table
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+}
+
+/// Selector of `name()`
+const NAME_SELECTOR: &str = "0x06fdde03";
+
+/// Selector of `symbol()`
+const SYMBOL_SELECTOR: &str = "0x95d89b41";
+
+/// Whether or not address is a placeholder rather than a real one e.g. zero
+/// address, or all-ones mask used to clean upper bits of address.
+///
+/// # Arguments
+/// * `address` - address in lowercase hex without `0x` prefix
+fn is_placeholder(address: &str) -> bool {
+    address.bytes().all(|b| b == b'0') || address.bytes().all(|b| b == b'f')
+}
+
+/// Find byte ranges of address literals e.g.
+/// `0x1befe6f3f0e8edd2d4d15cae97baee01e51ea4a4` in text including their `0x`
+/// prefix, in order they appear.
+///
+/// # Arguments
+/// * `text` - text e.g. source code
+pub fn address_spans(text: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while let Some(found) = text[i..].find("0x") {
+        let start = i + found + 2;
+        let len = bytes[start..].iter().take_while(|b| b.is_ascii_hexdigit()).count();
+        let preceded_by_word = i + found > 0 && (bytes[i + found - 1].is_ascii_alphanumeric() || bytes[i + found - 1] == b'_');
+        if len == 40 && !preceded_by_word && !is_placeholder(&text[start..start + 40].to_lowercase()) {
+            spans.push((start - 2, start + 40));
+        }
+        i = start + len;
+    }
+    spans
+}
+
+/// Find address literals e.g. `0x1befe6f3f0e8edd2d4d15cae97baee01e51ea4a4` in
+/// text. Returned in lowercase with `0x` prefix.
+///
+/// # Arguments
+/// * `text` - text e.g. source code
+pub fn text_addresses(text: &str) -> BTreeSet<String> {
+    address_spans(text).into_iter().map(|(start, end)| text[start..end].to_lowercase()).collect()
+}
+
+/// Find address literals in source code. Returned in lowercase with `0x`
+/// prefix.
+///
+/// # Arguments
+/// * `files` - source files
+pub fn source_addresses(files: &[SourceFile]) -> BTreeSet<String> {
+    files.iter().flat_map(|f| text_addresses(&f.content)).collect()
+}
+
+/// Get address held by 32-byte word if it looks like one i.e. upper 12 bytes
+/// are zero, and it's too large to be a mere number.
+///
+/// # Arguments
+/// * `word` - 32-byte word
+pub fn word_address(word: &[u8]) -> Option<String> {
+    if word.len() != 32 || word[..12].iter().any(|b| *b != 0) || word[12..16].iter().all(|b| *b == 0) {
+        return None;
+    }
+    let address = abi::to_hex(&word[12..]);
+    if is_placeholder(&address) {
+        return None;
+    }
+    Some(format!("0x{}", address))
+}
+
+/// Find addresses pushed as constants in runtime bytecode, either `PUSH20`,
+/// or `PUSH32` of address e.g. immutable variables. Returned in lowercase
+/// with `0x` prefix.
+///
+/// # Arguments
+/// * `code` - runtime bytecode
+pub fn bytecode_addresses(code: &[u8]) -> BTreeSet<String> {
+    let mut addresses = BTreeSet::new();
+    for instruction in bytecode::disassemble(bytecode::strip_metadata(code)).iter() {
+        let address = match instruction.data.len() {
+            20 => Some(abi::to_hex(&instruction.data)).filter(|a| !is_placeholder(a)).map(|a| format!("0x{}", a)),
+            32 => word_address(&instruction.data),
+            _ => None,
+        };
+        if let Some(address) = address {
+            addresses.insert(address);
+        }
+    }
+    addresses
+}
+
+/// Get token names of addresses as `name (symbol)`, either of them, or
+/// `None` if neither can be called. Calls are batched.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `addresses` - addresses
+fn token_names(chain: &crate::backend::Chain, addresses: &[&String]) -> Vec<Option<String>> {
+    let calls: Vec<(String, String)> = addresses.iter()
+        .flat_map(|a| [(a.to_string(), NAME_SELECTOR.to_owned()), (a.to_string(), SYMBOL_SELECTOR.to_owned())])
+        .collect();
+    let decode = |returned: &Option<Vec<u8>>| returned.as_ref().and_then(|data| abi::decode_string(data)).filter(|s| !s.is_empty());
+    multicall::call_many(chain, &calls).chunks(2).map(|pair| {
+        match (decode(&pair[0]), decode(&pair[1])) {
+            (Some(name), Some(symbol)) => Some(format!("{} ({})", name, symbol)),
+            (name, symbol) => name.or(symbol),
+        }
+    }).collect()
+}
+
+/// Resolve address against explorer into what it is e.g. contract name, name
+/// tag, and token name.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - address
+/// * `token` - token name of address
+fn resolve(chain: &crate::backend::Chain, address: &str, token: Option<&String>) -> Result<Value, String> {
+    let code = explorer::get_code(chain, address)?;
+    let name_tag = explorer::get_name_tag(chain, address).map(|t| t.describe());
+    if code.is_empty() {
+        return Ok(json!({ "kind": "account", "verified": null, "contractName": null, "token": null, "nameTag": name_tag }));
+    }
+
+    let meta = explorer::get_source_meta(chain, address)?;
+    let verified = !meta["SourceCode"].as_str().unwrap_or("").is_empty();
+    let contract_name = meta["ContractName"].as_str().filter(|n| !n.is_empty());
+    Ok(json!({ "kind": "contract", "verified": verified, "contractName": contract_name, "token": token, "nameTag": name_tag }))
+}
+
+/// Execute `addresses` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &AddressesArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.target.chain)?;
+    let address = args.target.address.to_lowercase();
+
+    let sources = source::fetch_sources(chain, &address)?;
+    let code = explorer::get_code(chain, &address)?;
+
+    let mut found: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for a in source_addresses(&sources.files) {
+        found.entry(a).or_default().push("source");
+    }
+    for a in bytecode_addresses(&code) {
+        found.entry(a).or_default().push("bytecode");
+    }
+    found.remove(&address);
+
+    let tokens = token_names(chain, &found.keys().collect::<Vec<&String>>());
+    let mut entries: Vec<Value> = Vec::new();
+    for ((a, found_in), token) in found.iter().zip(tokens.iter()) {
+        let mut entry = match resolve(chain, a, token.as_ref()) {
+            Ok(res) => res,
+            Err(e) => json!({ "kind": null, "verified": null, "contractName": null, "token": null, "nameTag": null, "error": e }),
+        };
+        entry["address"] = json!(abi::to_checksum_address(a));
+        entry["foundIn"] = json!(found_in);
+        entries.push(entry);
+    }
+
+    if args.json {
+        match serde_json::to_string_pretty(&entries) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing addresses to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+        return Ok(());
+    }
+
+    if entries.is_empty() {
+        println!("No hardcoded addresses found");
+        return Ok(());
+    }
+
+    let text = |v: &Value| v.as_str().unwrap_or("-").to_owned();
+    println!("{:<42} {:<15} {:<8} {:<8} {:<24} {:<28} TOKEN", "ADDRESS", "FOUND IN", "KIND", "VERIFIED", "CONTRACT NAME", "NAME TAG");
+    for entry in entries.iter() {
+        let found_in: Vec<String> = entry["foundIn"].as_array().map_or(Vec::new(), |f| f.iter().map(text).collect());
+        let verified = entry["verified"].as_bool().map_or("-", |v| if v { "yes" } else { "no" });
+        let token = match entry.get("error") {
+            Some(e) => format!("[failed to resolve] {}", text(e)),
+            None => text(&entry["token"]),
+        };
+        println!("{:<42} {:<15} {:<8} {:<8} {:<24} {:<28} {}",
+            text(&entry["address"]),
+            found_in.join(","),
+            text(&entry["kind"]),
+            verified,
+            text(&entry["contractName"]),
+            text(&entry["nameTag"]),
+            token);
+    }
+    Ok(())
+}
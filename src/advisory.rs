@@ -0,0 +1,346 @@
+//! `advisory` command: cross-reference version of solc used for verification
+//! against known bugs of Solidity compiler, and warn about ones relevant to
+//! compiler settings, and language features contract uses.
+//!
+//! Bugs come from `bugs.json` published by Solidity. A subset of it is
+//! embedded, use `--bugs` with the full list downloaded from
+//! https://github.com/ethereum/solidity/blob/develop/docs/bugs.json for
+//! newer ones. Bug applies if version is within `introduced`, and `fixed`,
+//! its `conditions` hold for settings used, and its `regex-source` check, if
+//! any, matches source code outside of comments, and strings. Conditions not
+//! understood are taken as held, so relevant bugs aren't missed.
+//!
+//! `pragma solidity` of each file is checked against version of solc used as
+//! well, reporting ranges not allowing it, unbounded ones, and floating ones
+//! which allow older versions than it e.g. `^0.6.0` built with 0.6.2.
+
+use crate::explorer;
+use crate::solc;
+use crate::solidity;
+use crate::source;
+use crate::TargetArgs;
+use clap::Args;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// Known bugs embedded at build time
+const EMBEDDED: &str = include_str!("solc_bugs.json");
+
+/// EVM versions in order of hard forks, to evaluate conditions on EVM version
+const EVM_VERSIONS: &[&str] = &[
+    "homestead", "tangerinewhistle", "spuriousdragon", "byzantium", "constantinople", "petersburg",
+    "istanbul", "berlin", "london", "paris", "shanghai", "cancun", "prague", "osaka",
+];
+
+#[derive(Debug, Args)]
+pub struct AdvisoryArgs {
+    #[clap(flatten)]
+    pub target: TargetArgs,
+
+    /// Path of bugs.json of Solidity to use instead of embedded list
+    #[clap(long="bugs", required=false)]
+    pub bugs: Option<String>,
+
+    /// Output as JSON instead of human readable table
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+}
+
+/// Known bug of Solidity compiler as of `bugs.json`.
+#[derive(Debug, Deserialize)]
+struct Bug {
+    name: String,
+    summary: String,
+
+    /// First affected version, or `None` if it affects all versions up to
+    /// fixed one
+    introduced: Option<String>,
+
+    /// First version with bug fixed
+    fixed: String,
+
+    severity: String,
+    conditions: Option<serde_json::Map<String, Value>>,
+    check: Option<Value>,
+}
+
+/// Compiler settings, and language features bug conditions are evaluated
+/// against.
+struct Features {
+    optimizer: bool,
+    yul_optimizer: bool,
+    abi_encoder_v2: bool,
+    via_ir: bool,
+    evm_version: String,
+}
+
+/// Version pragma of source file checked against version of solc used.
+struct PragmaCheck {
+    file: String,
+    range: String,
+
+    /// `mismatch`, `unbounded`, `floating`, `ok`, or `unparsed`
+    status: &'static str,
+
+    detail: String,
+}
+
+/// Check version range of pragma against version of solc used.
+///
+/// # Arguments
+/// * `range` - version range of pragma
+/// * `version` - plain version of solc
+fn check_pragma(range: &str, version: &str) -> (&'static str, String) {
+    let intervals = match solc::version_range(range) {
+        Ok(res) => res,
+        Err(e) => return ("unparsed", e),
+    };
+    let vt = solc::version_tuple(version);
+    match intervals.iter().find(|(lower, upper)| *lower <= vt && upper.is_none_or(|u| vt < u)) {
+        None => ("mismatch", format!("doesn't allow solc {} used for verification", version)),
+        Some((_, None)) => ("unbounded", "allows any newer version including breaking ones".to_owned()),
+        Some((lower, _)) if *lower < vt => ("floating", format!("allows {}.{}.{} older than solc {} used", lower.0, lower.1, lower.2, version)),
+        Some(_) => ("ok", String::new()),
+    }
+}
+
+/// Read known bugs.
+///
+/// # Arguments
+/// * `path` - path of bugs.json, or `None` for embedded list
+fn read_bugs(path: Option<&String>) -> Result<Vec<Bug>, String> {
+    let content = match path {
+        Some(path) => match std::fs::read_to_string(path) {
+            Ok(res) => res,
+            Err(e) => {
+                let err_msg = format!("Error reading bugs.json at '{}'; err={}", path, e);
+                return Err(err_msg);
+            }
+        },
+        None => EMBEDDED.to_owned(),
+    };
+    match serde_json::from_str(&content) {
+        Ok(res) => Ok(res),
+        Err(e) => {
+            let err_msg = format!("Error parsing bugs.json at '{}'; err={}", path.map_or("embedded", |p| p.as_str()), e);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Get position of EVM version in order of hard forks.
+///
+/// # Arguments
+/// * `evm_version` - EVM version e.g. `london`
+fn evm_rank(evm_version: &str) -> Option<usize> {
+    let evm_version = evm_version.to_lowercase();
+    EVM_VERSIONS.iter().position(|v| *v == evm_version)
+}
+
+/// Check whether EVM version satisfies constraint e.g. `>=constantinople`.
+/// Unknown EVM versions satisfy any constraint.
+///
+/// # Arguments
+/// * `evm_version` - EVM version targeted
+/// * `constraint` - constraint of bug condition
+fn evm_satisfies(evm_version: &str, constraint: &str) -> bool {
+    let op_len = constraint.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(0);
+    let (op, target) = constraint.split_at(op_len);
+    let (version, target) = match (evm_rank(evm_version), evm_rank(target)) {
+        (Some(version), Some(target)) => (version, target),
+        _ => return true,
+    };
+    match op.trim() {
+        ">=" => version >= target,
+        ">" => version > target,
+        "<=" => version <= target,
+        "<" => version < target,
+        _ => version == target,
+    }
+}
+
+/// Get reason why bug doesn't apply given features, or `None` if it does.
+///
+/// # Arguments
+/// * `bug` - known bug
+/// * `features` - compiler settings, and language features used
+/// * `sources` - source code with comments, and strings blanked out
+fn not_applicable(bug: &Bug, features: &Features, sources: &[String]) -> Option<String> {
+    for (key, expected) in bug.conditions.iter().flatten() {
+        let held = match (key.as_str(), expected) {
+            ("optimizer", Value::Bool(b)) => features.optimizer == *b,
+            ("yulOptimizer", Value::Bool(b)) => features.yul_optimizer == *b,
+            ("ABIEncoderV2", Value::Bool(b)) => features.abi_encoder_v2 == *b,
+            ("viaIR", Value::Bool(b)) => features.via_ir == *b,
+            ("evmVersion", Value::String(constraint)) => evm_satisfies(&features.evm_version, constraint),
+            _ => true,
+        };
+        if !held {
+            return Some(format!("condition {}={} doesn't hold", key, expected));
+        }
+    }
+
+    // patterns unsupported by regex crate e.g. with lookaround are taken as matched
+    let pattern = bug.check.as_ref().and_then(|c| c["regex-source"].as_str());
+    if let Some(re) = pattern.and_then(|p| Regex::new(p).ok()) {
+        if !sources.iter().any(|s| re.is_match(s)) {
+            return Some("source code doesn't use affected feature".to_owned());
+        }
+    }
+    None
+}
+
+/// Get compiler settings, and language features of verified contract.
+///
+/// # Arguments
+/// * `meta` - verification metadata
+/// * `version` - plain version of solc
+/// * `sources` - source code with comments, and strings blanked out
+fn features_of(meta: &Value, version: &str, sources: &[String]) -> Features {
+    let settings = solc::verified_settings(meta);
+    let optimizer = settings["optimizer"]["enabled"].as_bool().unwrap_or(false);
+
+    // Yul optimizer runs along with optimizer since 0.6.0 unless disabled
+    let yul_optimizer = settings["optimizer"]["details"]["yul"].as_bool()
+        .unwrap_or(optimizer && solc::version_tuple(version) >= (0, 6, 0));
+
+    // ABI coder v2 is default since 0.8.0
+    let v2_re = Regex::new(r"pragma\s+(experimental\s+ABIEncoderV2|abicoder\s+v2)\b").unwrap();
+    let v1_re = Regex::new(r"pragma\s+abicoder\s+v1\b").unwrap();
+    let abi_encoder_v2 = sources.iter().any(|s| v2_re.is_match(s))
+        || (solc::version_tuple(version) >= (0, 8, 0) && !sources.iter().all(|s| v1_re.is_match(s)));
+
+    Features {
+        optimizer,
+        yul_optimizer,
+        abi_encoder_v2,
+        via_ir: settings["viaIR"].as_bool().unwrap_or(false),
+        evm_version: settings["evmVersion"].as_str().unwrap_or(solc::default_evm_version(version)).to_lowercase(),
+    }
+}
+
+/// Execute `advisory` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &AdvisoryArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.target.chain)?;
+    let address = args.target.address.to_lowercase();
+    let bugs = read_bugs(args.bugs.as_ref())?;
+    let meta = explorer::get_source_meta(chain, &address)?;
+
+    let compiler_version = meta["CompilerVersion"].as_str().unwrap_or("");
+    if compiler_version.is_empty() {
+        let err_msg = format!("Error {} is not verified", address);
+        return Err(err_msg);
+    }
+    if compiler_version.to_lowercase().starts_with("vyper") {
+        let err_msg = format!("Error {} is compiled with {}; only bugs of Solidity compiler are known", address, compiler_version);
+        return Err(err_msg);
+    }
+
+    let version = solc::plain_version(compiler_version);
+    let contract_source = source::sources_from_meta(&address, &meta)?;
+    let sources: Vec<String> = contract_source.files.iter()
+        .map(|f| solidity::strip_comments_and_strings(&f.content))
+        .collect();
+    let features = features_of(&meta, &version, &sources);
+
+    let mut pragmas: Vec<PragmaCheck> = Vec::new();
+    for file in contract_source.files.iter() {
+        for range in solidity::pragmas(&file.content) {
+            let (status, detail) = check_pragma(&range, &version);
+            pragmas.push(PragmaCheck { file: file.path.clone(), range, status, detail });
+        }
+    }
+
+    let vt = solc::version_tuple(&version);
+    let affecting: Vec<&Bug> = bugs.iter()
+        .filter(|b| b.introduced.as_deref().is_none_or(|i| solc::version_tuple(i) <= vt))
+        .filter(|b| vt < solc::version_tuple(&b.fixed))
+        .collect();
+    let mut relevant: Vec<&Bug> = Vec::new();
+    let mut irrelevant: Vec<(&Bug, String)> = Vec::new();
+    for bug in affecting {
+        match not_applicable(bug, &features, &sources) {
+            Some(reason) => irrelevant.push((bug, reason)),
+            None => relevant.push(bug),
+        }
+    }
+    let rank = |severity: &str| match severity { "high" => 0, "medium/high" => 1, "medium" => 2, "low/medium" => 3, "low" => 4, _ => 5 };
+    relevant.sort_by_key(|b| rank(&b.severity));
+
+    if args.json {
+        let entry = |b: &Bug| json!({
+            "name": b.name,
+            "severity": b.severity,
+            "summary": b.summary,
+            "introduced": b.introduced,
+            "fixed": b.fixed,
+        });
+        let out = json!({
+            "address": address,
+            "compiler": version,
+            "features": {
+                "optimizer": features.optimizer,
+                "yulOptimizer": features.yul_optimizer,
+                "ABIEncoderV2": features.abi_encoder_v2,
+                "viaIR": features.via_ir,
+                "evmVersion": features.evm_version,
+            },
+            "pragmas": pragmas.iter().map(|p| json!({
+                "file": p.file,
+                "range": p.range,
+                "status": p.status,
+                "detail": p.detail,
+            })).collect::<Vec<Value>>(),
+            "relevant": relevant.iter().map(|b| entry(b)).collect::<Vec<Value>>(),
+            "notApplicable": irrelevant.iter().map(|(b, reason)| {
+                let mut e = entry(b);
+                e["reason"] = json!(reason);
+                e
+            }).collect::<Vec<Value>>(),
+        });
+        match serde_json::to_string_pretty(&out) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing advisory to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+    }
+    else {
+        println!("solc {}, optimizer: {}, yul optimizer: {}, ABI coder v2: {}, via-IR: {}, EVM: {}\n",
+            version, features.optimizer, features.yul_optimizer, features.abi_encoder_v2, features.via_ir, features.evm_version);
+        if relevant.is_empty() {
+            println!("No known bugs relevant to {}", address);
+        }
+        else {
+            println!("{:<12} {:<50} {:<8} SUMMARY", "SEVERITY", "BUG", "FIXED");
+            for bug in relevant.iter() {
+                println!("{:<12} {:<50} {:<8} {}", bug.severity, bug.name, bug.fixed, bug.summary);
+            }
+        }
+        if !irrelevant.is_empty() {
+            println!("\n{} other known bug(s) of solc {} don't apply given settings, and features used", irrelevant.len(), version);
+        }
+
+        let flagged: Vec<&PragmaCheck> = pragmas.iter().filter(|p| p.status != "ok").collect();
+        if !flagged.is_empty() {
+            println!("\n{:<40} {:<20} {:<10} DETAIL", "FILE", "PRAGMA", "STATUS");
+            for p in flagged.iter() {
+                println!("{:<40} {:<20} {:<10} {}", p.file, p.range, p.status, p.detail);
+            }
+        }
+    }
+
+    for p in pragmas.iter().filter(|p| p.status == "mismatch") {
+        eprintln!("Warning: pragma '{}' of {} {}", p.range, p.file, p.detail);
+    }
+
+    if !relevant.is_empty() {
+        eprintln!("Warning: {} was compiled with solc {} affected by {} known bug(s)", address, version, relevant.len());
+    }
+    Ok(())
+}
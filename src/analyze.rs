@@ -0,0 +1,193 @@
+//! `analyze` command: run external linters over verified source code.
+
+use crate::source;
+use crate::workspace::TempWorkspace;
+use crate::TargetArgs;
+use clap::Args;
+use serde_json::{json, Value};
+use std::process::Command;
+
+#[derive(Debug, Args)]
+pub struct AnalyzeArgs {
+    #[clap(flatten)]
+    pub target: TargetArgs,
+
+    /// Linter to run. Possible values are 'solhint', and 'semgrep'.
+    #[clap(long="tool", short='t', required=true)]
+    pub tool: String,
+
+    /// Config file for solhint, or rules for semgrep (file, directory, or
+    /// registry id). Defaults are 'solhint:recommended', and 'p/smart-contracts'
+    /// respectively.
+    #[clap(long="config", required=false)]
+    pub config: Option<String>,
+
+    /// Output as JSON instead of human readable table
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+}
+
+/// Finding reported by linter, normalized across tools.
+struct Finding {
+    tool: &'static str,
+    rule: String,
+    /// One of `error`, `warning`, or `info`
+    severity: String,
+    file: String,
+    line: u64,
+    column: u64,
+    message: String,
+}
+
+/// Execute linter and return its stdout. Linters exit with non-zero code when
+/// they found something, so exit code alone is not treated as an error.
+///
+/// # Arguments
+/// * `cmd` - command to execute
+/// * `name` - name of linter for error message
+fn execute(cmd: &mut Command, name: &str) -> Result<String, String> {
+    match cmd.output() {
+        Ok(output) => {
+            if output.stdout.is_empty() && !output.status.success() {
+                let err_msg = format!("Error {} failed; stderr={}", name, String::from_utf8_lossy(&output.stderr));
+                return Err(err_msg);
+            }
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        },
+        Err(e) => {
+            let err_msg = format!("Error executing {}, make sure it is installed; err={}", name, e);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Run solhint over project directory then normalize its findings.
+///
+/// # Arguments
+/// * `dir` - project directory containing source files
+/// * `config` - path to solhint config file
+fn run_solhint(dir: &str, config: Option<&String>) -> Result<Vec<Finding>, String> {
+    let config_path = match config {
+        Some(res) => res.clone(),
+        None => {
+            let path = crate::combine_two_path_components(dir, ".solhint.json")?;
+            crate::write_file(&path, "{ \"extends\": \"solhint:recommended\" }")?;
+            path
+        }
+    };
+
+    let stdout = execute(Command::new("solhint").current_dir(dir).args(["-f", "json", "-c", &config_path, "**/*.sol"]), "solhint")?;
+    let reports: Value = match serde_json::from_str(&stdout) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error parsing solhint output; err={}", e);
+            return Err(err_msg);
+        }
+    };
+
+    let mut findings = Vec::new();
+    for report in reports.as_array().unwrap_or(&Vec::new()) {
+        let file = report["filePath"].as_str().unwrap_or("");
+        let file = file.strip_prefix(dir).unwrap_or(file).trim_start_matches('/').to_owned();
+        for m in report["messages"].as_array().unwrap_or(&Vec::new()) {
+            findings.push(Finding {
+                tool: "solhint",
+                rule: m["ruleId"].as_str().unwrap_or("").to_owned(),
+                severity: if m["severity"].as_u64() == Some(2) { "error".to_owned() } else { "warning".to_owned() },
+                file: file.clone(),
+                line: m["line"].as_u64().unwrap_or(0),
+                column: m["column"].as_u64().unwrap_or(0),
+                message: m["message"].as_str().unwrap_or("").to_owned(),
+            });
+        }
+    }
+    Ok(findings)
+}
+
+/// Run semgrep over project directory then normalize its findings.
+///
+/// # Arguments
+/// * `dir` - project directory containing source files
+/// * `rules` - semgrep rules
+fn run_semgrep(dir: &str, rules: Option<&String>) -> Result<Vec<Finding>, String> {
+    let rules = rules.map_or("p/smart-contracts", |r| r.as_str());
+    let stdout = execute(Command::new("semgrep").current_dir(dir).args(["--config", rules, "--json", "--quiet", "."]), "semgrep")?;
+    let output: Value = match serde_json::from_str(&stdout) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error parsing semgrep output; err={}", e);
+            return Err(err_msg);
+        }
+    };
+
+    let mut findings = Vec::new();
+    for r in output["results"].as_array().unwrap_or(&Vec::new()) {
+        let severity = match r["extra"]["severity"].as_str().unwrap_or("") {
+            "ERROR" => "error",
+            "WARNING" => "warning",
+            _ => "info",
+        };
+        findings.push(Finding {
+            tool: "semgrep",
+            rule: r["check_id"].as_str().unwrap_or("").to_owned(),
+            severity: severity.to_owned(),
+            file: r["path"].as_str().unwrap_or("").trim_start_matches("./").to_owned(),
+            line: r["start"]["line"].as_u64().unwrap_or(0),
+            column: r["start"]["col"].as_u64().unwrap_or(0),
+            message: r["extra"]["message"].as_str().unwrap_or("").trim().to_owned(),
+        });
+    }
+    Ok(findings)
+}
+
+/// Execute `analyze` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &AnalyzeArgs) -> Result<(), String> {
+    let tool = args.tool.to_lowercase();
+    if tool != "solhint" && tool != "semgrep" {
+        return Err("Error invalid value for --tool.\nPossible values are 'solhint', or 'semgrep'.".to_owned());
+    }
+
+    let chain = crate::parse_chain(&args.target.chain)?;
+    let contract_source = source::fetch_sources(chain, &args.target.address)?;
+
+    // lay out sources as a temporary project for linters to run on
+    let workspace = TempWorkspace::new("analyze")?;
+    let dir = workspace.path_str();
+    let result = source::write_sources(dir, &contract_source.files).and_then(|_| {
+        if tool == "solhint" { run_solhint(dir, args.config.as_ref()) } else { run_semgrep(dir, args.config.as_ref()) }
+    });
+    drop(workspace);
+    let mut findings = result?;
+    findings.sort_by(|a, b| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)));
+
+    if args.json {
+        let json: Vec<Value> = findings.iter().map(|f| json!({
+            "tool": f.tool,
+            "rule": f.rule,
+            "severity": f.severity,
+            "file": f.file,
+            "line": f.line,
+            "column": f.column,
+            "message": f.message,
+        })).collect();
+
+        match serde_json::to_string_pretty(&json) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing findings to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+        return Ok(());
+    }
+
+    for f in findings.iter() {
+        println!("{}:{}:{}: {} [{}] {}", f.file, f.line, f.column, f.severity, f.rule, f.message);
+    }
+    println!("{} finding(s) by {}", findings.len(), tool);
+
+    Ok(())
+}
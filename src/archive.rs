@@ -0,0 +1,593 @@
+//! Deterministic archive of output files, so fetching the same contract
+//! twice gives byte-identical archive.
+//!
+//! Entries are ordered by path, every file has mode `0644` owned by uid/gid 0,
+//! and timestamp is taken from `SOURCE_DATE_EPOCH` if defined, otherwise it is
+//! the unix epoch.
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+/// Size of tar block
+const BLOCK_SIZE: usize = 512;
+
+/// Earliest time representable in zip i.e. 1980-01-01 00:00:00 UTC
+const ZIP_MIN_TIMESTAMP: i64 = 315532800;
+
+/// Format of archive.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Format {
+    /// POSIX ustar
+    Tar,
+
+    /// Zip with stored (uncompressed) entries
+    Zip,
+}
+
+/// Get archive format from extension of archive path. `.zip` gives zip,
+/// otherwise tar.
+///
+/// # Arguments
+/// * `path` - path of archive
+fn format_of(path: &str) -> Format {
+    if path.to_lowercase().ends_with(".zip") { Format::Zip } else { Format::Tar }
+}
+
+/// Get timestamp to stamp on every entry.
+fn source_date_epoch() -> Result<i64, String> {
+    parse_source_date_epoch(std::env::var("SOURCE_DATE_EPOCH").ok().as_deref())
+}
+
+/// Parse value of `SOURCE_DATE_EPOCH` as timestamp, the unix epoch if it's
+/// not defined.
+///
+/// # Arguments
+/// * `value` - value of `SOURCE_DATE_EPOCH` if defined
+fn parse_source_date_epoch(value: Option<&str>) -> Result<i64, String> {
+    match value {
+        Some(value) => match value.trim().parse::<i64>() {
+            Ok(res) if res >= 0 => Ok(res),
+            _ => {
+                let err_msg = format!("Error invalid value of SOURCE_DATE_EPOCH; value={}", value);
+                Err(err_msg)
+            }
+        },
+        None => Ok(0),
+    }
+}
+
+/// Sort files by path, and reject duplicate ones.
+///
+/// # Arguments
+/// * `files` - pairs of relative path and content
+fn sorted_files(files: &[(String, String)]) -> Result<BTreeMap<&str, &str>, String> {
+    let mut sorted = BTreeMap::new();
+    for (path, content) in files.iter() {
+        if sorted.insert(path.as_str(), content.as_str()).is_some() {
+            let err_msg = format!("Error duplicate path in archive; path={}", path);
+            return Err(err_msg);
+        }
+    }
+    Ok(sorted)
+}
+
+/// Write number as zero-padded octal string terminated with NUL into field.
+/// Number which doesn't fit e.g. size of file of 8 GiB, or more is rejected
+/// instead of corrupting header.
+///
+/// # Arguments
+/// * `field` - header field to write into
+/// * `value` - number to write
+/// * `what` - what the number is, for error message
+fn write_octal(field: &mut [u8], value: u64, what: &str) -> Result<(), String> {
+    let digits = format!("{:0width$o}", value, width = field.len() - 1);
+    if digits.len() > field.len() - 1 {
+        let err_msg = format!("Error {} is too large to be stored in tar archive; value={}", what, value);
+        return Err(err_msg);
+    }
+    field[..digits.len()].copy_from_slice(digits.as_bytes());
+    field[field.len() - 1] = 0;
+    Ok(())
+}
+
+/// Build ustar header of regular file.
+///
+/// # Arguments
+/// * `path` - relative path of file
+/// * `size` - size of file in bytes
+/// * `mtime` - modification time
+fn tar_header(path: &str, size: u64, mtime: i64) -> Result<[u8; BLOCK_SIZE], String> {
+    // path longer than name field is split into prefix and name at a separator
+    let (prefix, name) = if path.len() <= 100 {
+        ("", path)
+    }
+    else {
+        match path.char_indices().filter(|&(i, c)| c == '/' && i <= 155 && path.len() - i - 1 <= 100).map(|(i, _)| i).next() {
+            Some(i) => (&path[..i], &path[i + 1..]),
+            None => {
+                let err_msg = format!("Error path is too long to be stored in tar archive; path={}", path);
+                return Err(err_msg);
+            }
+        }
+    };
+
+    let mut header = [0u8; BLOCK_SIZE];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    write_octal(&mut header[100..108], 0o644, "mode")?;
+    write_octal(&mut header[108..116], 0, "uid")?;
+    write_octal(&mut header[116..124], 0, "gid")?;
+    if let Err(e) = write_octal(&mut header[124..136], size, "size") {
+        let err_msg = format!("{}, path={}", e, path);
+        return Err(err_msg);
+    }
+    write_octal(&mut header[136..148], mtime as u64, "timestamp")?;
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+
+    // checksum is computed with its own field filled with spaces
+    header[148..156].copy_from_slice(b"        ");
+    let checksum: u64 = header.iter().map(|&b| b as u64).sum();
+    let digits = format!("{:06o}", checksum);
+    header[148..154].copy_from_slice(digits.as_bytes());
+    header[154] = 0;
+    header[155] = b' ';
+
+    Ok(header)
+}
+
+/// Writer of tar archive file by file into underlying writer e.g. stdin of
+/// compressor, so archive of many files isn't held in memory. Files are
+/// written in order they're appended; it's up to caller to keep it stable.
+#[derive(Debug)]
+pub struct TarWriter<W: Write> {
+    inner: W,
+
+    /// Modification time of every entry
+    mtime: i64,
+}
+
+impl<W: Write> TarWriter<W> {
+    /// Create writer stamping every entry with the same timestamp as
+    /// `write_archive` would.
+    ///
+    /// # Arguments
+    /// * `inner` - writer to write archive into
+    pub fn new(inner: W) -> Result<Self, String> {
+        Ok(Self::with_mtime(inner, source_date_epoch()?))
+    }
+
+    /// Create writer stamping every entry with timestamp.
+    ///
+    /// # Arguments
+    /// * `inner` - writer to write archive into
+    /// * `mtime` - modification time of every entry
+    fn with_mtime(inner: W, mtime: i64) -> Self {
+        Self { inner, mtime }
+    }
+
+    /// Write bytes into underlying writer.
+    ///
+    /// # Arguments
+    /// * `bytes` - bytes to write
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), String> {
+        match self.inner.write_all(bytes) {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                let err_msg = format!("Error writing tar archive; err={}", e);
+                Err(err_msg)
+            }
+        }
+    }
+
+    /// Append regular file.
+    ///
+    /// # Arguments
+    /// * `path` - relative path of file
+    /// * `content` - content of file
+    pub fn append(&mut self, path: &str, content: &[u8]) -> Result<(), String> {
+        let header = tar_header(path, content.len() as u64, self.mtime)?;
+        self.write_all(&header)?;
+        self.write_all(content)?;
+        let padding = (BLOCK_SIZE - content.len() % BLOCK_SIZE) % BLOCK_SIZE;
+        self.write_all(&[0u8; BLOCK_SIZE][..padding])
+    }
+
+    /// Mark end of archive, flush, then give back underlying writer.
+    pub fn finish(mut self) -> Result<W, String> {
+        // end of archive is marked by two zero blocks
+        self.write_all(&[0u8; BLOCK_SIZE * 2])?;
+        if let Err(e) = self.inner.flush() {
+            let err_msg = format!("Error writing tar archive; err={}", e);
+            return Err(err_msg);
+        }
+        Ok(self.inner)
+    }
+}
+
+/// Build tar archive of files.
+///
+/// # Arguments
+/// * `files` - pairs of relative path and content
+/// * `mtime` - modification time of every entry
+fn tar(files: &[(String, String)], mtime: i64) -> Result<Vec<u8>, String> {
+    let mut writer = TarWriter::with_mtime(Vec::new(), mtime);
+    for (path, content) in sorted_files(files)? {
+        writer.append(path, content.as_bytes())?;
+    }
+    writer.finish()
+}
+
+/// Read octal number of header field terminated by NUL, or space.
+///
+/// # Arguments
+/// * `field` - header field
+fn read_octal(field: &[u8]) -> Result<u64, String> {
+    let text = String::from_utf8_lossy(field);
+    let digits = text.trim_matches(|c: char| c == '\0' || c == ' ');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    match u64::from_str_radix(digits, 8) {
+        Ok(res) => Ok(res),
+        Err(e) => {
+            let err_msg = format!("Error invalid number in tar header; value={}, err={}", digits, e);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Reader of regular files of tar archive one by one from underlying reader
+/// e.g. stdout of decompressor, so archive of many files isn't held in
+/// memory. Other kinds of entries are skipped.
+#[derive(Debug)]
+pub struct TarReader<R: Read> {
+    inner: R,
+
+    /// Whether end of archive is reached
+    done: bool,
+}
+
+impl<R: Read> TarReader<R> {
+    /// Create reader.
+    ///
+    /// # Arguments
+    /// * `inner` - reader to read archive from
+    pub fn new(inner: R) -> Self {
+        Self { inner, done: false }
+    }
+
+    /// Skip bytes of underlying reader, returning number of bytes skipped.
+    ///
+    /// # Arguments
+    /// * `count` - number of bytes to skip
+    fn skip(&mut self, count: u64) -> Result<u64, String> {
+        match std::io::copy(&mut (&mut self.inner).take(count), &mut std::io::sink()) {
+            Ok(res) => Ok(res),
+            Err(e) => {
+                let err_msg = format!("Error reading tar archive; err={}", e);
+                Err(err_msg)
+            }
+        }
+    }
+
+    /// Read next regular file as pair of relative path and content, or `None`
+    /// at end of archive.
+    pub fn next_file(&mut self) -> Result<Option<(String, String)>, String> {
+        let field = |header: &[u8], start: usize, end: usize| {
+            let raw = &header[start..end];
+            let len = raw.iter().position(|&b| b == 0).unwrap_or(raw.len());
+            String::from_utf8_lossy(&raw[..len]).into_owned()
+        };
+
+        while !self.done {
+            let mut header = [0u8; BLOCK_SIZE];
+            match self.inner.read_exact(&mut header) {
+                Ok(_) => (),
+                // archive not ending with zero blocks is taken as ended
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => {
+                    let err_msg = format!("Error reading tar archive; err={}", e);
+                    return Err(err_msg);
+                }
+            }
+            // end of archive
+            if header.iter().all(|&b| b == 0) {
+                break;
+            }
+
+            let name = field(&header, 0, 100);
+            let prefix = field(&header, 345, 500);
+            let path = if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) };
+            let size = read_octal(&header[124..136])?;
+            let padding = size.div_ceil(BLOCK_SIZE as u64) * BLOCK_SIZE as u64 - size;
+            if header[156] != b'0' && header[156] != 0 {
+                if self.skip(size)? < size {
+                    let err_msg = format!("Error tar archive is truncated at '{}'", path);
+                    return Err(err_msg);
+                }
+                self.skip(padding)?;
+                continue;
+            }
+
+            // not allocated upfront by size in header, as it may be broken
+            let mut content = Vec::new();
+            if let Err(e) = (&mut self.inner).take(size).read_to_end(&mut content) {
+                let err_msg = format!("Error reading '{}' of tar archive; err={}", path, e);
+                return Err(err_msg);
+            }
+            if (content.len() as u64) < size {
+                let err_msg = format!("Error tar archive is truncated at '{}'", path);
+                return Err(err_msg);
+            }
+            self.skip(padding)?;
+            return match String::from_utf8(content) {
+                Ok(res) => Ok(Some((path, res))),
+                Err(e) => {
+                    let err_msg = format!("Error content of '{}' in tar archive is not text; err={}", path, e);
+                    Err(err_msg)
+                }
+            };
+        }
+        self.done = true;
+        Ok(None)
+    }
+}
+
+/// Read regular files of tar archive as pairs of relative path and content.
+/// Other kinds of entries are skipped.
+///
+/// # Arguments
+/// * `bytes` - tar archive
+pub fn read_tar(bytes: &[u8]) -> Result<Vec<(String, String)>, String> {
+    let mut reader = TarReader::new(bytes);
+    let mut files = Vec::new();
+    while let Some(file) = reader.next_file()? {
+        files.push(file);
+    }
+    Ok(files)
+}
+
+/// Compute CRC-32 (IEEE) checksum of data.
+///
+/// # Arguments
+/// * `data` - data to compute checksum of
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffffffffu32;
+    for &b in data.iter() {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// Build zip archive of files with stored (uncompressed) entries.
+///
+/// # Arguments
+/// * `files` - pairs of relative path and content
+/// * `mtime` - modification time of every entry
+fn zip(files: &[(String, String)], mtime: i64) -> Result<Vec<u8>, String> {
+    // MS-DOS date time cannot go before 1980
+    let (year, month, day, hour, minute, second) = crate::display::civil_from_timestamp(mtime.max(ZIP_MIN_TIMESTAMP));
+    if year - 1980 > 0x7f {
+        let err_msg = format!("Error timestamp is too late to be stored in zip archive; timestamp={}", mtime);
+        return Err(err_msg);
+    }
+    let dos_time = ((hour << 11) | (minute << 5) | (second / 2)) as u16;
+    let dos_date = (((year - 1980) << 9) | (month << 5) | day) as u16;
+
+    let files = sorted_files(files)?;
+    if files.len() > 0xffff {
+        return Err("Error too many files to be stored in zip archive".to_owned());
+    }
+
+    // sizes, and offsets are of 32 bits as zip64 isn't supported
+    let to_u32 = |value: usize, what: &str| match u32::try_from(value) {
+        Ok(res) => Ok(res),
+        Err(_) => {
+            let err_msg = format!("Error {} is too large to be stored in zip archive; value={}", what, value);
+            Err(err_msg)
+        }
+    };
+
+    let mut bytes: Vec<u8> = Vec::new();
+    let mut central: Vec<u8> = Vec::new();
+    for (path, content) in files.iter() {
+        let path_len = match u16::try_from(path.len()) {
+            Ok(res) => res,
+            Err(_) => {
+                let err_msg = format!("Error path is too long to be stored in zip archive; path={}", path);
+                return Err(err_msg);
+            }
+        };
+        let offset = to_u32(bytes.len(), "offset of file")?;
+        let crc = crc32(content.as_bytes());
+        let size = match to_u32(content.len(), "size") {
+            Ok(res) => res,
+            Err(e) => {
+                let err_msg = format!("{}, path={}", e, path);
+                return Err(err_msg);
+            }
+        };
+
+        // local file header
+        bytes.extend_from_slice(&0x04034b50u32.to_le_bytes());
+        bytes.extend_from_slice(&20u16.to_le_bytes());
+        // flags: UTF-8 file name
+        bytes.extend_from_slice(&0x0800u16.to_le_bytes());
+        bytes.extend_from_slice(&0u16.to_le_bytes());
+        bytes.extend_from_slice(&dos_time.to_le_bytes());
+        bytes.extend_from_slice(&dos_date.to_le_bytes());
+        bytes.extend_from_slice(&crc.to_le_bytes());
+        bytes.extend_from_slice(&size.to_le_bytes());
+        bytes.extend_from_slice(&size.to_le_bytes());
+        bytes.extend_from_slice(&path_len.to_le_bytes());
+        bytes.extend_from_slice(&0u16.to_le_bytes());
+        bytes.extend_from_slice(path.as_bytes());
+        bytes.extend_from_slice(content.as_bytes());
+
+        // central directory header, made by unix so permissions are kept
+        central.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        central.extend_from_slice(&0x0314u16.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes());
+        central.extend_from_slice(&0x0800u16.to_le_bytes());
+        central.extend_from_slice(&0u16.to_le_bytes());
+        central.extend_from_slice(&dos_time.to_le_bytes());
+        central.extend_from_slice(&dos_date.to_le_bytes());
+        central.extend_from_slice(&crc.to_le_bytes());
+        central.extend_from_slice(&size.to_le_bytes());
+        central.extend_from_slice(&size.to_le_bytes());
+        central.extend_from_slice(&path_len.to_le_bytes());
+        central.extend_from_slice(&0u16.to_le_bytes());
+        central.extend_from_slice(&0u16.to_le_bytes());
+        central.extend_from_slice(&0u16.to_le_bytes());
+        central.extend_from_slice(&0u16.to_le_bytes());
+        central.extend_from_slice(&((0o100644u32) << 16).to_le_bytes());
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(path.as_bytes());
+    }
+
+    // end of central directory record
+    let central_offset = to_u32(bytes.len(), "offset of central directory")?;
+    let central_size = to_u32(central.len(), "size of central directory")?;
+    bytes.extend_from_slice(&central);
+    bytes.extend_from_slice(&0x06054b50u32.to_le_bytes());
+    bytes.extend_from_slice(&0u16.to_le_bytes());
+    bytes.extend_from_slice(&0u16.to_le_bytes());
+    bytes.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(&central_size.to_le_bytes());
+    bytes.extend_from_slice(&central_offset.to_le_bytes());
+    bytes.extend_from_slice(&0u16.to_le_bytes());
+
+    Ok(bytes)
+}
+
+/// Write files into archive at the path. Format is selected by its extension.
+///
+/// # Arguments
+/// * `path` - path of archive to write
+/// * `files` - pairs of path relative to archive root and content
+pub fn write_archive(path: &str, files: &[(String, String)]) -> Result<(), String> {
+    let mtime = source_date_epoch()?;
+    let bytes = match format_of(path) {
+        Format::Tar => tar(files, mtime)?,
+        Format::Zip => zip(files, mtime)?,
+    };
+
+    crate::create_intermediate_dirs(path)?;
+    match std::fs::write(path, bytes) {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            let err_msg = format!("Error writing archive at '{}'; err={}", path, e);
+            Err(err_msg)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Files in order not sorted by path, including long path to be split
+    /// into prefix, and name, empty file, and text not of ASCII.
+    fn files() -> Vec<(String, String)> {
+        let long = format!("{}/{}/Long.sol", "a".repeat(90), "b".repeat(90));
+        vec![
+            ("src/B.sol".to_owned(), "contract B {}\n".to_owned()),
+            ("A.sol".to_owned(), "// café 😀\ncontract A {}\n".to_owned()),
+            (long, "x".repeat(BLOCK_SIZE)),
+            ("empty.txt".to_owned(), String::new()),
+        ]
+    }
+
+    #[test]
+    fn archives_are_byte_identical_regardless_of_order() {
+        let mut reversed = files();
+        reversed.reverse();
+        for mtime in [0, 1700000000] {
+            assert_eq!(tar(&files(), mtime).unwrap(), tar(&reversed, mtime).unwrap());
+            assert_eq!(zip(&files(), mtime).unwrap(), zip(&reversed, mtime).unwrap());
+        }
+        assert_ne!(tar(&files(), 0).unwrap(), tar(&files(), 1700000000).unwrap());
+        assert_ne!(zip(&files(), 0).unwrap(), zip(&files(), 1700000000).unwrap());
+
+        let duplicated = vec![("A.sol".to_owned(), String::new()), ("A.sol".to_owned(), String::new())];
+        assert!(tar(&duplicated, 0).is_err());
+        assert!(zip(&duplicated, 0).is_err());
+    }
+
+    #[test]
+    fn source_date_epoch_is_parsed_as_timestamp() {
+        assert_eq!(parse_source_date_epoch(None), Ok(0));
+        assert_eq!(parse_source_date_epoch(Some("1700000000")), Ok(1700000000));
+        assert_eq!(parse_source_date_epoch(Some(" 42\n")), Ok(42));
+        assert!(parse_source_date_epoch(Some("-1")).is_err());
+        assert!(parse_source_date_epoch(Some("yesterday")).is_err());
+    }
+
+    #[test]
+    fn tar_header_has_ustar_fields() {
+        let header = tar_header("src/A.sol", 10, 1700000000).unwrap();
+        assert_eq!(&header[..10], b"src/A.sol\0");
+        assert_eq!(&header[100..108], b"0000644\0");
+        assert_eq!(&header[108..124], b"0000000\x000000000\0");
+        assert_eq!(&header[124..136], b"00000000012\0");
+        assert_eq!(&header[136..148], b"14524770400\0");
+        assert_eq!(header[156], b'0');
+        assert_eq!(&header[257..265], b"ustar\x0000");
+
+        let checksum: u64 = header.iter().enumerate().map(|(i, &b)| if (148..156).contains(&i) { b' ' as u64 } else { b as u64 }).sum();
+        assert_eq!(read_octal(&header[148..156]).unwrap(), checksum);
+    }
+
+    #[test]
+    fn tar_archive_is_read_back() {
+        let bytes = tar(&files(), 0).unwrap();
+        assert_eq!(bytes.len() % BLOCK_SIZE, 0);
+        let mut expected = files();
+        expected.sort();
+        assert_eq!(read_tar(&bytes).unwrap(), expected);
+
+        assert!(read_tar(&bytes[..BLOCK_SIZE + 10]).is_err());
+        assert_eq!(read_tar(&[0u8; BLOCK_SIZE * 2]).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn values_not_fitting_in_headers_are_rejected() {
+        // size field holds 11 octal digits, so up to 8 GiB - 1
+        assert!(tar_header("A.sol", (8 << 30) - 1, 0).is_ok());
+        let err = tar_header("A.sol", 8 << 30, 0).unwrap_err();
+        assert!(err.contains("size is too large"), "{}", err);
+        assert!(tar_header(&"a".repeat(101), 0, 0).is_err());
+
+        let long = vec![("a".repeat(0x10000), String::new())];
+        let err = zip(&long, 0).unwrap_err();
+        assert!(err.contains("path is too long"), "{}", err);
+        assert!(zip(&[("a".repeat(0xffff), String::new())], 0).is_ok());
+    }
+
+    #[test]
+    fn zip_archive_has_stored_entries() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+
+        let bytes = zip(&[("A.sol".to_owned(), "contract A {}\n".to_owned())], 0).unwrap();
+        assert_eq!(&bytes[..4], &0x04034b50u32.to_le_bytes());
+        // 1980-01-01 00:00:00 at the earliest
+        assert_eq!(&bytes[10..14], &[0, 0, 0x21, 0]);
+        assert_eq!(&bytes[14..18], &crc32(b"contract A {}\n").to_le_bytes());
+        assert_eq!(&bytes[18..26], &[14, 0, 0, 0, 14, 0, 0, 0]);
+        assert_eq!(&bytes[30..35], b"A.sol");
+
+        // end of central directory record with 1 entry
+        let end = &bytes[bytes.len() - 22..];
+        assert_eq!(&end[..4], &0x06054b50u32.to_le_bytes());
+        assert_eq!(&end[8..12], &[1, 0, 1, 0]);
+        let central_offset = u32::from_le_bytes([end[16], end[17], end[18], end[19]]) as usize;
+        assert_eq!(central_offset, 30 + 5 + 14);
+        assert_eq!(&bytes[central_offset..central_offset + 4], &0x02014b50u32.to_le_bytes());
+    }
+}
@@ -0,0 +1,126 @@
+//! Compiler artifacts of fetched contract shaped as Hardhat, or Truffle
+//! writes them, so tooling expecting compiler artifacts can consume on-chain
+//! contracts directly.
+//!
+//! Bytecode is taken from chain rather than recompiling; deployed bytecode
+//! is the runtime code at address, and bytecode is input of the deploying
+//! transaction without constructor arguments. The latter is left empty for
+//! contracts deployed through factories.
+
+use crate::backend::Chain;
+use crate::explorer;
+use serde_json::{json, Value};
+
+/// Shape of artifact.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ArtifactFormat {
+    /// `artifacts/<ContractName>.json` as of `hh-sol-artifact-1`
+    Hardhat,
+
+    /// `build/contracts/<ContractName>.json`
+    Truffle,
+}
+
+/// Parse value of `--artifact-format` flag option.
+///
+/// # Arguments
+/// * `value` - value of flag option
+pub fn parse_artifact_format(value: &str) -> Result<ArtifactFormat, String> {
+    match value.to_lowercase().as_str() {
+        "hardhat" => Ok(ArtifactFormat::Hardhat),
+        "truffle" => Ok(ArtifactFormat::Truffle),
+        _ => Err("Error invalid value for --artifact-format.\nPossible values are 'hardhat', or 'truffle'.".to_owned()),
+    }
+}
+
+/// Get creation bytecode of contract without constructor arguments, or
+/// `None` if it wasn't deployed directly by a transaction.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+/// * `meta` - verification metadata
+fn creation_bytecode(chain: &Chain, address: &str, meta: &Value) -> Result<Option<Vec<u8>>, String> {
+    let creation = match explorer::get_creation(chain, address)? {
+        Some(res) => res,
+        None => return Ok(None),
+    };
+    let mut input = match explorer::get_deployment_input(chain, &creation.tx_hash)? {
+        Some(res) => res,
+        None => return Ok(None),
+    };
+
+    let args = crate::abi::from_hex(meta["ConstructorArguments"].as_str().unwrap_or("")).unwrap_or_default();
+    if !args.is_empty() && input.ends_with(&args) {
+        input.truncate(input.len() - args.len());
+    }
+    Ok(Some(input))
+}
+
+/// Get artifact file of contract as pair of relative path, and content.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+/// * `format` - shape of artifact
+/// * `meta` - verification metadata
+/// * `settings` - compiler settings used for verification
+/// * `source_name` - path of source file declaring the contract as placed
+pub fn artifact_file(chain: &Chain, address: &str, format: ArtifactFormat, meta: &Value, settings: &Value, source_name: &str) -> Result<(String, String), String> {
+    let contract_name = meta["ContractName"].as_str().unwrap_or("").to_owned();
+    let abi: Value = match serde_json::from_str(meta["ABI"].as_str().unwrap_or("")) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error parsing contract ABI of {}; err={}", address, e);
+            return Err(err_msg);
+        }
+    };
+    let deployed_bytecode = format!("0x{}", crate::abi::to_hex(&explorer::get_code(chain, address)?));
+    // not every explorer tells creation of contract
+    let bytecode = match creation_bytecode(chain, address, meta) {
+        Ok(res) => format!("0x{}", crate::abi::to_hex(&res.unwrap_or_default())),
+        Err(e) => {
+            eprintln!("Warning: bytecode of {} left empty; {}", address, e);
+            "0x".to_owned()
+        }
+    };
+
+    let version = crate::solc::plain_version(meta["CompilerVersion"].as_str().unwrap_or(""));
+    let metadata = json!({
+        "compiler": { "version": meta["CompilerVersion"].as_str().unwrap_or("").trim_start_matches('v') },
+        "language": "Solidity",
+        "settings": settings,
+    }).to_string();
+
+    let (path, artifact) = match format {
+        ArtifactFormat::Hardhat => (format!("artifacts/{}.json", contract_name), json!({
+            "_format": "hh-sol-artifact-1",
+            "contractName": contract_name,
+            "sourceName": source_name,
+            "abi": abi,
+            "bytecode": bytecode,
+            "deployedBytecode": deployed_bytecode,
+            "linkReferences": {},
+            "deployedLinkReferences": {},
+            "metadata": metadata,
+        })),
+        ArtifactFormat::Truffle => (format!("build/contracts/{}.json", contract_name), json!({
+            "contractName": contract_name,
+            "abi": abi,
+            "metadata": metadata,
+            "bytecode": bytecode,
+            "deployedBytecode": deployed_bytecode,
+            "sourcePath": source_name,
+            "compiler": { "name": "solc", "version": version },
+            "networks": {},
+            "schemaVersion": "3.4.16",
+        })),
+    };
+    match serde_json::to_string_pretty(&artifact) {
+        Ok(res) => Ok((path, format!("{}\n", res))),
+        Err(e) => {
+            let err_msg = format!("Error serializing artifact of {}; err={}", address, e);
+            Err(err_msg)
+        }
+    }
+}
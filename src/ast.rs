@@ -0,0 +1,172 @@
+//! `ast` command: dump compact AST JSON of verified source compiled with solc.
+
+use crate::solc;
+use crate::source;
+use crate::TargetArgs;
+use clap::Args;
+use serde_json::{json, Value};
+
+#[derive(Debug, Args)]
+pub struct AstArgs {
+    #[clap(flatten)]
+    pub target: TargetArgs,
+
+    /// Source file to dump AST of. It can be either full path as verified, or
+    /// just its filename. If not specified, AST of all source files are dumped
+    /// keyed by their paths.
+    #[clap(long="file", short='f', required=false)]
+    pub file: Option<String>,
+
+    /// Only dump AST node of this contract
+    #[clap(long="contract", required=false)]
+    pub contract: Option<String>,
+
+    /// Path to solc binary to use instead of finding one matching the verified
+    /// compiler version
+    #[clap(long="solc", required=false)]
+    pub solc: Option<String>,
+
+    /// Pretty print output
+    #[clap(long="pretty", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub pretty: bool,
+}
+
+/// Whether or not source path matches the file selected by users.
+///
+/// # Arguments
+/// * `path` - path of source as verified
+/// * `file` - file selected by users
+pub fn matches_file(path: &str, file: &str) -> bool {
+    path == file || path.rsplit('/').next() == Some(file)
+}
+
+/// Collect all nodes of given type under AST node.
+///
+/// # Arguments
+/// * `node` - AST node to search in
+/// * `node_type` - value of `nodeType` e.g. `FunctionCall`
+/// * `found` - collected nodes
+pub fn collect<'a>(node: &'a Value, node_type: &str, found: &mut Vec<&'a Value>) {
+    match node {
+        Value::Object(map) => {
+            if map.get("nodeType").and_then(|t| t.as_str()) == Some(node_type) {
+                found.push(node);
+            }
+            for value in map.values() {
+                collect(value, node_type, found);
+            }
+        },
+        Value::Array(items) => {
+            for item in items.iter() {
+                collect(item, node_type, found);
+            }
+        },
+        _ => (),
+    }
+}
+
+/// Compile verified source of contract with solc, and return AST of each
+/// source unit keyed by its path.
+///
+/// # Arguments
+/// * `target` - target contract address and chain
+/// * `solc_path` - user supplied path to solc binary
+pub fn compile_asts(target: &TargetArgs, solc_path: Option<&String>) -> Result<serde_json::Map<String, Value>, String> {
+    let chain = crate::parse_chain(&target.chain)?;
+    let contract_source = source::fetch_sources(chain, &target.address)?;
+    let solc_path = solc::find_solc(&contract_source.compiler_version, solc_path)?;
+
+    let input = solc::build_input(&contract_source.files, json!({
+        "outputSelection": { "*": { "": ["ast"] } }
+    }));
+    let mut output = solc::compile(&solc_path, &input)?;
+
+    let mut asts = serde_json::Map::new();
+    if let Some(sources) = output["sources"].as_object_mut() {
+        for (path, unit) in sources.iter_mut() {
+            asts.insert(path.clone(), unit["ast"].take());
+        }
+    }
+    Ok(asts)
+}
+
+/// Find `ContractDefinition` node of the named contract in AST of source unit.
+///
+/// # Arguments
+/// * `ast` - AST of source unit
+/// * `name` - contract name
+fn find_contract<'a>(ast: &'a Value, name: &str) -> Option<&'a Value> {
+    ast["nodes"].as_array()?.iter().find(|n| n["nodeType"].as_str() == Some("ContractDefinition") && n["name"].as_str() == Some(name))
+}
+
+/// Execute `ast` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &AstArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.target.chain)?;
+    let contract_source = source::fetch_sources(chain, &args.target.address)?;
+    let solc_path = solc::find_solc(&contract_source.compiler_version, args.solc.as_ref())?;
+
+    if let Some(file) = args.file.as_ref() {
+        if !contract_source.files.iter().any(|f| matches_file(&f.path, file)) {
+            let paths: Vec<&str> = contract_source.files.iter().map(|f| f.path.as_str()).collect();
+            let err_msg = format!("Error file '{}' not found. Available files are\n  {}", file, paths.join("\n  "));
+            return Err(err_msg);
+        }
+    }
+
+    let input = solc::build_input(&contract_source.files, json!({
+        "outputSelection": { "*": { "": ["ast"] } }
+    }));
+    let output = solc::compile(&solc_path, &input)?;
+
+    let mut result = serde_json::Map::new();
+    if let Some(sources) = output["sources"].as_object() {
+        for (path, unit) in sources.iter() {
+            if let Some(file) = args.file.as_ref() {
+                if !matches_file(path, file) {
+                    continue;
+                }
+            }
+
+            let ast = &unit["ast"];
+            match args.contract.as_ref() {
+                Some(name) => {
+                    if let Some(node) = find_contract(ast, name) {
+                        result.insert(path.clone(), node.clone());
+                    }
+                },
+                None => {
+                    result.insert(path.clone(), ast.clone());
+                }
+            }
+        }
+    }
+
+    if result.is_empty() {
+        if let Some(name) = args.contract.as_ref() {
+            let err_msg = format!("Error contract '{}' not found in selected source files", name);
+            return Err(err_msg);
+        }
+    }
+
+    // selected single file is output without being keyed by its path
+    let dump = if args.file.is_some() && result.len() == 1 {
+        result.into_iter().next().map(|(_, v)| v).unwrap_or(Value::Null)
+    }
+    else {
+        Value::Object(result)
+    };
+
+    let content = if args.pretty { serde_json::to_string_pretty(&dump) } else { serde_json::to_string(&dump) };
+    match content {
+        Ok(res) => println!("{}", res),
+        Err(e) => {
+            let err_msg = format!("Error serializing AST; err={}", e);
+            return Err(err_msg);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,185 @@
+//! `audit-meta` command: report compiler versions, optimizer settings, and
+//! deployment dates across contracts tracked by a sync manifest, flagging
+//! ancient solc versions, and unoptimized builds.
+//!
+//! API platforms don't report when contract was verified, so date of its
+//! deployment from `getcontractcreation` is reported instead as verification
+//! can't predate it. It's left as `unknown` if explorer doesn't tell.
+
+use crate::display;
+use crate::explorer;
+use crate::progress;
+use crate::solc;
+use crate::sync;
+use clap::Args;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Args)]
+pub struct AuditMetaArgs {
+    /// Path of manifest of tracked contracts as of `sync` command
+    #[clap(required=true)]
+    pub manifest: String,
+
+    /// Flag contracts compiled with solc older than this version
+    #[clap(long="min-solc", required=false, default_value="0.8.0")]
+    pub min_solc: String,
+
+    /// Output as JSON instead of human readable table
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+}
+
+/// Compiler metadata of a single tracked contract.
+struct Audit {
+    name: Option<String>,
+    address: String,
+    chain: String,
+
+    /// Plain version of solc e.g. `0.8.17`, or compiler version as-is for
+    /// Vyper
+    compiler: String,
+
+    optimizer: bool,
+    runs: u64,
+    evm_version: String,
+
+    /// Deployment date as `YYYY-MM-DD`, or `None` if unknown
+    deployed: Option<String>,
+
+    flags: Vec<&'static str>,
+}
+
+/// Audit compiler metadata of a single tracked contract.
+///
+/// # Arguments
+/// * `contract` - tracked contract
+/// * `chain_name` - chain of contract as specified in manifest
+/// * `min_solc` - minimum version of solc not flagged as ancient
+fn audit_contract(contract: &sync::TrackedContract, chain_name: &str, min_solc: (u64, u64, u64)) -> Result<Audit, String> {
+    let chain = crate::parse_chain(chain_name)?;
+    let address = contract.address.to_lowercase();
+    let meta = explorer::get_source_meta(chain, &address)?;
+    let compiler_version = meta["CompilerVersion"].as_str().unwrap_or("");
+    if compiler_version.is_empty() {
+        let err_msg = format!("Error {} is not verified", address);
+        return Err(err_msg);
+    }
+
+    let settings = solc::verified_settings(&meta);
+    let optimizer = settings["optimizer"]["enabled"].as_bool().unwrap_or(false);
+
+    let mut flags = Vec::new();
+    let compiler = if compiler_version.to_lowercase().starts_with("vyper") {
+        compiler_version.to_lowercase()
+    }
+    else {
+        let version = solc::plain_version(compiler_version);
+        if solc::version_tuple(&version) < min_solc {
+            flags.push("ancient-solc");
+        }
+        version
+    };
+    if !optimizer {
+        flags.push("unoptimized");
+    }
+
+    // deployment date is informative only, so failing to get it isn't fatal
+    let deployed = explorer::get_creation(chain, &address).ok().flatten()
+        .and_then(|c| c.timestamp)
+        .map(|t| {
+            let (year, month, day, _, _, _) = display::civil_from_timestamp(t);
+            format!("{:04}-{:02}-{:02}", year, month, day)
+        });
+
+    Ok(Audit {
+        name: contract.name.clone(),
+        address,
+        chain: chain_name.to_owned(),
+        compiler,
+        optimizer,
+        runs: settings["optimizer"]["runs"].as_u64().unwrap_or(200),
+        evm_version: settings["evmVersion"].as_str().unwrap_or("default").to_lowercase(),
+        deployed,
+        flags,
+    })
+}
+
+/// Execute `audit-meta` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &AuditMetaArgs) -> Result<(), String> {
+    let manifest = sync::read_sync_manifest(&args.manifest)?;
+    let min_solc = solc::version_tuple(args.min_solc.trim().trim_start_matches('v'));
+
+    let mut audits: Vec<Audit> = Vec::new();
+    let mut failed = 0;
+    for contract in manifest.contracts.iter() {
+        let result = contract.chain.as_ref().or(manifest.chain.as_ref())
+            .ok_or_else(|| format!("Error no chain specified for {}", contract.address))
+            .and_then(|c| audit_contract(contract, c, min_solc));
+        match result {
+            Ok(res) => audits.push(res),
+            Err(e) => {
+                // failed contracts are reported via exit code, see `progress::tally()`
+                failed += 1;
+                progress::error(Some(&contract.address), &e);
+                eprintln!("{} failed; {}", contract.address, e);
+            }
+        }
+    }
+
+    let mut by_compiler: BTreeMap<&str, usize> = BTreeMap::new();
+    for audit in audits.iter() {
+        *by_compiler.entry(&audit.compiler).or_insert(0) += 1;
+    }
+    let count_flag = |flag: &str| audits.iter().filter(|a| a.flags.contains(&flag)).count();
+    let (ancient, unoptimized) = (count_flag("ancient-solc"), count_flag("unoptimized"));
+
+    if args.json {
+        let out = json!({
+            "manifest": args.manifest,
+            "minSolc": args.min_solc,
+            "contracts": audits.iter().map(|a| json!({
+                "name": a.name,
+                "address": a.address,
+                "chain": a.chain,
+                "compiler": a.compiler,
+                "optimizer": a.optimizer,
+                "runs": a.runs,
+                "evmVersion": a.evm_version,
+                "deployed": a.deployed,
+                "flags": a.flags,
+            })).collect::<Vec<Value>>(),
+            "summary": {
+                "compilers": by_compiler,
+                "ancientSolc": ancient,
+                "unoptimized": unoptimized,
+                "failed": failed,
+            },
+        });
+        match serde_json::to_string_pretty(&out) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing audit to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+        return Ok(());
+    }
+
+    println!("{:<20} {:<42} {:<10} {:<14} {:<10} {:<10} {:<10} FLAGS", "NAME", "ADDRESS", "CHAIN", "COMPILER", "OPTIMIZER", "EVM", "DEPLOYED");
+    for a in audits.iter() {
+        let optimizer = if a.optimizer { format!("{} runs", a.runs) } else { "off".to_owned() };
+        println!("{:<20} {:<42} {:<10} {:<14} {:<10} {:<10} {:<10} {}",
+            a.name.as_deref().unwrap_or("-"), a.address, a.chain, a.compiler, optimizer, a.evm_version,
+            a.deployed.as_deref().unwrap_or("unknown"), a.flags.join(","));
+    }
+    println!();
+    for (compiler, count) in by_compiler.iter() {
+        println!("{}: {} contract(s)", compiler, count);
+    }
+    println!("audited: {}, ancient solc (< {}): {}, unoptimized: {}, failed: {}", audits.len(), args.min_solc, ancient, unoptimized, failed);
+    Ok(())
+}
@@ -0,0 +1,599 @@
+//! Explorer backends which requests of API platform are sent to, and registry
+//! of chains served by them.
+//!
+//! Built-in chains are registered as selected by cargo features (`bsc`,
+//! `ethereum`, and `polygon`; all enabled by default). Additional chains, or
+//! replacements of built-in ones e.g. enterprise instances, can be registered
+//! at runtime from a JSON manifest of endpoints at path of environment variable
+//! `TRACPLS_BACKENDS`, or `~/.config/tracpls/backends.json` if exists.
+//!
+//! ```json
+//! [
+//!   {
+//!     "chain": "arbitrum",
+//!     "chain_id": 42161,
+//!     "name": "arbiscan",
+//!     "url": "https://api.arbiscan.io/api",
+//!     "apikey_env": "TRACPLS_ARBISCAN_APIKEY",
+//!     "native_symbol": "ETH",
+//!     "rate_limit": 5,
+//!     "cooldown_secs": 5,
+//!     "breaker_threshold": 5,
+//!     "breaker_cooldown_secs": 30,
+//!     "fallback": "arbitrum-blockscout",
+//!     "failover": ["arbitrum-blockscout"]
+//!   }
+//! ]
+//! ```
+//!
+//! Chains of other EVM networks are registered from registry of `chains`
+//! in between, so manifest only needs entries of chains not known there, or
+//! to be replaced.
+//!
+//! Environment variable of API key can hold multiple comma-separated keys to
+//! be rotated between requests; see `keypool`. `rate_limit` is maximum
+//! requests per second of each key (5 by default as of free plans, 0 for no
+//! limit), and `cooldown_secs` is how long a key is left unused after being
+//! rate limited.
+//!
+//! Once `breaker_threshold` requests in a row fail with server errors, or
+//! can't connect (5 by default, 0 to never trip), requests to the backend are
+//! paused for `breaker_cooldown_secs`; see `breaker`. While paused, they're
+//! sent to backend of `fallback` chain instead if set e.g. a Blockscout
+//! instance of the same network registered as chain of its own.
+//!
+//! `failover` lists chains whose backends `batch`, and `sync` try in order for
+//! a contract which can't be fetched from this one e.g. isn't verified there,
+//! or it's down. Output manifest records which backend served each contract.
+
+use crate::breaker::CircuitBreaker;
+use crate::http_log;
+use crate::keypool::{self, KeyPool};
+use crate::transport::{self, HttpRequest};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::sync::Mutex;
+
+/// Backend which requests of API platform are sent to.
+pub trait ExplorerBackend: Send + Sync {
+    /// Name of backend e.g. `bscscan`
+    fn name(&self) -> &str;
+
+    /// Send request with query parameters then return its response body.
+    ///
+    /// # Arguments
+    /// * `params` - query parameters as pairs of key and value
+    fn send(&self, params: &[(&str, &str)]) -> Result<String, String>;
+
+    /// Build request with query parameters without sending it e.g. to send it
+    /// through `transport::AsyncTransport`. Unlike `send()`, API key isn't
+    /// taken from environment variable, nor rotated.
+    ///
+    /// # Arguments
+    /// * `params` - query parameters as pairs of key and value
+    /// * `apikey` - API key
+    fn request(&self, params: &[(&str, &str)], apikey: Option<&str>) -> HttpRequest;
+}
+
+/// Chain, and explorer backend serving it.
+pub struct Chain {
+    /// Name of chain as accepted by chain flag option e.g. `bsc`
+    pub name: String,
+
+    /// Symbol of native currency e.g. `BNB`
+    pub native_symbol: String,
+
+    /// EIP-155 chain id e.g. `56` if known
+    pub chain_id: Option<u64>,
+
+    pub backend: Box<dyn ExplorerBackend>,
+
+    /// Names of chains whose backends are tried in order when fetching from
+    /// this one fails e.g. Blockscout instance of the same network
+    pub failover: Vec<String>,
+}
+
+impl std::fmt::Debug for Chain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Chain({}, {})", self.name, self.backend.name())
+    }
+}
+
+/// Backend speaking the API shape of etherscan.io which is shared by its
+/// family of API platforms, and compatible ones.
+pub struct EtherscanCompatible {
+    pub name: String,
+
+    /// Base url of API e.g. `https://api.bscscan.com/api`
+    pub url: String,
+
+    /// Name of environment variable holding API key(s), if API key is needed
+    pub apikey_env: Option<String>,
+
+    /// Pool of API keys rotated between requests
+    pub keys: KeyPool,
+
+    /// Circuit breaker pausing requests while API platform keeps failing
+    pub breaker: CircuitBreaker,
+
+    /// Name of chain whose backend requests are sent to instead while
+    /// breaker is open
+    pub fallback: Option<String>,
+}
+
+/// Percent-encode text to be safely used as part of query string.
+///
+/// # Arguments
+/// * `text` - text to encode
+pub fn encode_query_value(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Extra header of requests to explorers.
+#[derive(Debug, Clone)]
+pub struct Header {
+    /// Host header is only sent to. Without it, header is sent to backend of
+    /// chain requested only, never to its fallback, nor chains it fails over
+    /// to so credentials don't leak to other explorers.
+    pub host: Option<String>,
+
+    pub name: String,
+
+    pub value: String,
+}
+
+/// Extra settings of requests to explorers e.g. for ones behind auth proxies.
+#[derive(Debug, Clone, Default)]
+pub struct RequestSettings {
+    /// Extra headers
+    pub headers: Vec<Header>,
+
+    /// Path to client certificate in PEM format for mutual TLS
+    pub client_cert: Option<String>,
+
+    /// Path to private key of client certificate in PEM format
+    pub client_key: Option<String>,
+
+    /// Path to bundle of CA certificates in PEM format to trust instead of
+    /// system ones
+    pub ca_cert: Option<String>,
+}
+
+/// Settings applied to requests to explorers
+static REQUEST_SETTINGS: Mutex<Option<RequestSettings>> = Mutex::new(None);
+
+/// Set settings applied to requests to explorers. It has to be set before
+/// sending any request as HTTP client is created once.
+///
+/// # Arguments
+/// * `settings` - request settings
+pub fn set_request_settings(settings: RequestSettings) {
+    if let Ok(mut current) = REQUEST_SETTINGS.lock() {
+        *current = Some(settings);
+    }
+}
+
+/// Get settings applied to requests to explorers.
+pub fn request_settings() -> RequestSettings {
+    REQUEST_SETTINGS.lock().ok().and_then(|s| s.clone()).unwrap_or_default()
+}
+
+/// Parse value of `--header` flag option in form of `Name: value`, or
+/// `host=Name: value` to send it only to the host.
+///
+/// # Arguments
+/// * `value` - value of flag option
+pub fn parse_header(value: &str) -> Result<Header, String> {
+    // '=' before the colon separates host, otherwise it's part of value
+    let (host, header) = match value.split_once('=') {
+        Some((host, header)) if !host.contains(':') => (Some(host.trim()), header),
+        _ => (None, value),
+    };
+    match header.split_once(':') {
+        Some((name, header_value)) if !name.trim().is_empty() && host.is_none_or(|h| !h.is_empty()) => Ok(Header {
+            host: host.map(|h| h.to_lowercase()),
+            name: name.trim().to_owned(),
+            value: header_value.trim().to_owned(),
+        }),
+        _ => {
+            let err_msg = format!("Error invalid value for --header '{}'.\nIt must be in form of 'Name: value', or 'host=Name: value'.", value);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Get host of url without port, lowercased.
+///
+/// # Arguments
+/// * `url` - url
+fn url_host(url: &str) -> String {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if !port.is_empty() && port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    };
+    host.to_lowercase()
+}
+
+/// Get extra headers to send with request to url as pairs of name, and
+/// value. Headers without host are left out of requests to fallback, and
+/// failover backends.
+///
+/// # Arguments
+/// * `headers` - extra headers
+/// * `url` - url of request
+fn headers_for(headers: &[Header], url: &str) -> Vec<(String, String)> {
+    let secondary = IN_FALLBACK.with(|f| f.get()) || IN_FAILOVER.with(|f| f.get());
+    let host = url_host(url);
+    headers.iter()
+        .filter(|h| match h.host.as_ref() {
+            Some(scope) => *scope == host,
+            None => !secondary,
+        })
+        .map(|h| (h.name.clone(), h.value.clone()))
+        .collect()
+}
+
+thread_local! {
+    /// Whether request is being sent to fallback backend, so fallbacks
+    /// pointing to each other don't bounce request back, and forth
+    static IN_FALLBACK: Cell<bool> = const { Cell::new(false) };
+
+    /// Whether request is being sent to chain failed over to
+    static IN_FAILOVER: Cell<bool> = const { Cell::new(false) };
+}
+
+impl EtherscanCompatible {
+    /// Create backend with default circuit breaker, and no fallback.
+    ///
+    /// # Arguments
+    /// * `name` - name of backend
+    /// * `url` - base url of API
+    /// * `apikey_env` - name of environment variable holding API key
+    /// * `keys` - pool of API keys
+    pub fn new(name: &str, url: &str, apikey_env: Option<&str>, keys: KeyPool) -> Self {
+        Self {
+            name: name.to_owned(),
+            url: url.to_owned(),
+            apikey_env: apikey_env.map(|e| e.to_owned()),
+            keys,
+            breaker: CircuitBreaker::new(DEFAULT_BREAKER_THRESHOLD, DEFAULT_BREAKER_COOLDOWN_SECS),
+            fallback: None,
+        }
+    }
+
+    /// Build full request url with query parameters, and API key if any.
+    ///
+    /// Environment variable `TRACPLS_API_URL` overrides base url e.g. to use
+    /// `tracpls mock-server`, with which API key is optional.
+    ///
+    /// # Arguments
+    /// * `params` - query parameters as pairs of key and value
+    /// * `apikey` - API key
+    pub fn build_url(&self, params: &[(&str, &str)], apikey: Option<&str>) -> String {
+        let mut url = std::env::var("TRACPLS_API_URL").unwrap_or_else(|_| self.url.clone());
+        url.push('?');
+        let mut query: Vec<String> = params.iter().map(|(key, value)| format!("{}={}", key, encode_query_value(value))).collect();
+        if let Some(apikey) = apikey {
+            query.push(format!("apikey={}", encode_query_value(apikey)));
+        }
+        url.push_str(&query.join("&"));
+        url
+    }
+}
+
+impl EtherscanCompatible {
+    /// Send request to API platform then return status, and body of its
+    /// response.
+    ///
+    /// # Arguments
+    /// * `params` - query parameters as pairs of key and value
+    fn send_to_platform(&self, params: &[(&str, &str)]) -> Result<(u16, String), String> {
+        let apikey_optional = std::env::var("TRACPLS_API_URL").is_ok();
+        let mut retries = 0;
+        loop {
+            let (index, apikey) = self.keys.acquire(self.apikey_env.as_deref(), apikey_optional)?;
+            let request = self.request(params, apikey.as_deref());
+
+            let started = std::time::SystemTime::now();
+            let timer = std::time::Instant::now();
+            let response = match transport::send(&request) {
+                Ok(res) => res,
+                Err(e) => {
+                    let err_msg = format!("Error sending request to API platform; err={}", e);
+                    return Err(err_msg);
+                }
+            };
+
+            crate::perf::record_request(timer.elapsed(), request.url.len(), response.body.len());
+            if http_log::is_enabled() {
+                http_log::log(&http_log::Exchange {
+                    method: request.method,
+                    url: &request.url,
+                    request_headers: &request.headers,
+                    status: response.status,
+                    response_headers: response.headers.clone(),
+                    body: &response.body,
+                    started,
+                    elapsed: timer.elapsed(),
+                });
+            }
+
+            // rotate to other key, but give up once every key got rate limited
+            // more than once
+            if apikey.is_some() && keypool::is_rate_limited(response.status, &response.body) && retries < self.keys.len() * 2 {
+                self.keys.cool_down(index);
+                retries += 1;
+                continue;
+            }
+            return Ok((response.status, response.body));
+        }
+    }
+
+    /// Get backend of fallback chain if there is one, and request isn't
+    /// already being sent to a fallback.
+    fn fallback_backend(&self) -> Option<&'static dyn ExplorerBackend> {
+        if IN_FALLBACK.with(|f| f.get()) {
+            return None;
+        }
+        let chain = find(self.fallback.as_deref()?)?;
+        Some(chain.backend.as_ref())
+    }
+}
+
+impl ExplorerBackend for EtherscanCompatible {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn send(&self, params: &[(&str, &str)]) -> Result<String, String> {
+        let admission = loop {
+            match self.breaker.admit() {
+                Ok(res) => break res,
+                Err(left) => {
+                    if let Some(fallback) = self.fallback_backend() {
+                        IN_FALLBACK.with(|f| f.set(true));
+                        let result = fallback.send(params);
+                        IN_FALLBACK.with(|f| f.set(false));
+                        return result;
+                    }
+                    // wait until breaker lets a request probe API platform,
+                    // or another one probing it is answered
+                    std::thread::sleep(left);
+                }
+            }
+        };
+
+        let result = self.send_to_platform(params);
+        // server errors, and failed connections mean API platform is down,
+        // unlike errors reported in response body
+        if matches!(result, Ok((status, _)) if status < 500) {
+            self.breaker.record_success(admission);
+        }
+        else if let Some(cooldown) = self.breaker.record_failure(admission) {
+            let failed = if admission.is_probe() { "is still failing".to_owned() } else { format!("failed {} times in a row", self.breaker.threshold) };
+            match self.fallback_backend() {
+                Some(fallback) => eprintln!("Warning: {} {}; sending requests to {} for {}s", self.name, failed, fallback.name(), cooldown.as_secs()),
+                None => eprintln!("Warning: {} {}; pausing requests to it for {}s", self.name, failed, cooldown.as_secs()),
+            }
+        }
+        result.map(|(_, body)| body)
+    }
+
+    fn request(&self, params: &[(&str, &str)], apikey: Option<&str>) -> HttpRequest {
+        let url = self.build_url(params, apikey);
+        let headers = headers_for(&request_settings().headers, &url);
+        HttpRequest { headers, ..HttpRequest::new("GET", &url) }
+    }
+}
+
+/// Registered chains. They live until the end of process.
+static REGISTRY: Mutex<Vec<&'static Chain>> = Mutex::new(Vec::new());
+
+/// Register chain, replacing the one with the same name, or chain id if any.
+///
+/// # Arguments
+/// * `chain` - chain to register
+pub fn register(chain: Chain) {
+    let chain: &'static Chain = Box::leak(Box::new(chain));
+    if let Ok(mut registry) = REGISTRY.lock() {
+        registry.retain(|c| c.name != chain.name && (chain.chain_id.is_none() || c.chain_id != chain.chain_id));
+        registry.push(chain);
+    }
+}
+
+/// Find registered chain by name, case-insensitive.
+///
+/// # Arguments
+/// * `name` - name of chain
+pub fn find(name: &str) -> Option<&'static Chain> {
+    let registry = REGISTRY.lock().ok()?;
+    registry.iter().find(|c| c.name.eq_ignore_ascii_case(name)).copied()
+}
+
+/// Find registered chain by EIP-155 chain id.
+///
+/// # Arguments
+/// * `chain_id` - chain id
+pub fn find_by_id(chain_id: u64) -> Option<&'static Chain> {
+    let registry = REGISTRY.lock().ok()?;
+    registry.iter().find(|c| c.chain_id == Some(chain_id)).copied()
+}
+
+/// Get all registered chains in order of registration.
+pub fn chains() -> Vec<&'static Chain> {
+    REGISTRY.lock().map_or(Vec::new(), |registry| registry.clone())
+}
+
+/// Get names of all registered chains.
+pub fn chain_names() -> Vec<String> {
+    REGISTRY.lock().map_or(Vec::new(), |registry| registry.iter().map(|c| c.name.clone()).collect())
+}
+
+/// Run operation against chain, then against chains it fails over to in
+/// order until one succeeds. Returns result along with chain which served it.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `op` - operation to run against each chain
+pub fn with_failover<'a, T, F: FnMut(&'a Chain) -> Result<T, String>>(chain: &'a Chain, mut op: F) -> Result<(T, &'a Chain), String> {
+    let mut errors: Vec<(&str, String)> = Vec::new();
+    let candidates = std::iter::once(chain).chain(chain.failover.iter().filter_map(|name| find(name)));
+    for (i, candidate) in candidates.enumerate() {
+        let in_failover = IN_FAILOVER.with(|f| f.replace(f.get() || i > 0));
+        let result = op(candidate);
+        IN_FAILOVER.with(|f| f.set(in_failover));
+        match result {
+            Ok(res) => return Ok((res, candidate)),
+            Err(e) => errors.push((candidate.backend.name(), e)),
+        }
+    }
+
+    // without failover, keep error as-is
+    if errors.len() == 1 {
+        return Err(errors.remove(0).1);
+    }
+    let each: Vec<String> = errors.iter().map(|(name, e)| format!("{}: {}", name, e)).collect();
+    let err_msg = format!("Error every backend of {} failed; {}", chain.name, each.join("; "));
+    Err(err_msg)
+}
+
+/// Register etherscan compatible chain.
+///
+/// # Arguments
+/// * `chain` - name of chain
+/// * `chain_id` - EIP-155 chain id
+/// * `native_symbol` - symbol of native currency
+/// * `backend` - backend serving chain
+/// * `failover` - names of chains to fail over to in order
+fn register_etherscan_compatible(chain: &str, chain_id: Option<u64>, native_symbol: &str, backend: EtherscanCompatible, failover: Vec<String>) {
+    register(Chain {
+        name: chain.to_owned(),
+        native_symbol: native_symbol.to_owned(),
+        chain_id,
+        backend: Box::new(backend),
+        failover,
+    });
+}
+
+/// Default maximum requests per second of each API key, as of free plans
+const DEFAULT_RATE_LIMIT: f64 = 5.0;
+
+/// Default seconds which a rate limited API key is left unused
+const DEFAULT_COOLDOWN_SECS: u64 = 5;
+
+/// Default number of failed requests in a row to trip circuit breaker
+const DEFAULT_BREAKER_THRESHOLD: u32 = 5;
+
+/// Default seconds which requests are paused after circuit breaker trips
+const DEFAULT_BREAKER_COOLDOWN_SECS: u64 = 30;
+
+/// Register built-in chains enabled at compile time.
+pub fn register_builtin() {
+    #[cfg(feature = "bsc")]
+    register_etherscan_compatible("bsc", Some(56), "BNB", EtherscanCompatible::new("bscscan", "https://api.bscscan.com/api", Some("TRACPLS_BSCSCAN_APIKEY"), KeyPool::new(DEFAULT_RATE_LIMIT, DEFAULT_COOLDOWN_SECS)), Vec::new());
+    #[cfg(feature = "ethereum")]
+    register_etherscan_compatible("ethereum", Some(1), "ETH", EtherscanCompatible::new("etherscan", "https://api.etherscan.io/api", Some("TRACPLS_ETHERSCAN_APIKEY"), KeyPool::new(DEFAULT_RATE_LIMIT, DEFAULT_COOLDOWN_SECS)), Vec::new());
+    #[cfg(feature = "polygon")]
+    register_etherscan_compatible("polygon", Some(137), "MATIC", EtherscanCompatible::new("polygonscan", "https://api.polygonscan.com/api", Some("TRACPLS_POLYGONSCAN_APIKEY"), KeyPool::new(DEFAULT_RATE_LIMIT, DEFAULT_COOLDOWN_SECS)), Vec::new());
+}
+
+/// Endpoint entry of manifest, or registry of chains.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ManifestEntry {
+    pub chain: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    pub url: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub apikey_env: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub native_symbol: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<f64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cooldown_secs: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub breaker_threshold: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub breaker_cooldown_secs: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failover: Option<Vec<String>>,
+}
+
+/// Register chains of manifest entries, replacing ones with the same name.
+///
+/// # Arguments
+/// * `entries` - manifest entries
+pub fn register_entries(entries: Vec<ManifestEntry>) {
+    for entry in entries {
+        let chain = entry.chain.to_lowercase();
+        let name = entry.name.unwrap_or_else(|| chain.clone());
+        let mut backend = EtherscanCompatible::new(&name, &entry.url, entry.apikey_env.as_deref(),
+            KeyPool::new(entry.rate_limit.unwrap_or(DEFAULT_RATE_LIMIT), entry.cooldown_secs.unwrap_or(DEFAULT_COOLDOWN_SECS)));
+        backend.breaker = CircuitBreaker::new(entry.breaker_threshold.unwrap_or(DEFAULT_BREAKER_THRESHOLD), entry.breaker_cooldown_secs.unwrap_or(DEFAULT_BREAKER_COOLDOWN_SECS));
+        backend.fallback = entry.fallback.map(|f| f.to_lowercase());
+        let failover = entry.failover.unwrap_or_default().iter().map(|f| f.to_lowercase()).collect();
+        register_etherscan_compatible(&chain, entry.chain_id, entry.native_symbol.as_deref().unwrap_or("ETH"), backend, failover);
+    }
+}
+
+/// Register chains from manifest of endpoints if there is one.
+pub fn register_from_manifest() -> Result<(), String> {
+    let path = match std::env::var("TRACPLS_BACKENDS") {
+        Ok(res) => res,
+        Err(_) => {
+            let home = match std::env::var("HOME") {
+                Ok(res) => res,
+                Err(_) => return Ok(()),
+            };
+            let path = crate::combine_two_path_components(&home, ".config/tracpls/backends.json")?;
+            if !std::path::Path::new(&path).exists() {
+                return Ok(());
+            }
+            path
+        }
+    };
+
+    let content = match std::fs::read_to_string(&path) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error reading backends manifest at '{}'; err={}", path, e);
+            return Err(err_msg);
+        }
+    };
+    let entries: Vec<ManifestEntry> = match serde_json::from_str(&content) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error parsing backends manifest at '{}'; err={}", path, e);
+            return Err(err_msg);
+        }
+    };
+
+    register_entries(entries);
+    Ok(())
+}
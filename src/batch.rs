@@ -0,0 +1,887 @@
+//! `batch` command: fetch verified source code of many contracts listed in a
+//! file, and record what happened to each of them in a summary.
+//!
+//! Contracts go through a pipeline of fetch, transform, and write stages
+//! running concurrently, so writing files of one contract overlaps with
+//! fetching others.
+//!
+//! With `--abi-only`, only ABIs are fetched concurrently, and written as
+//! `<out-dir>/<chain>/<address>.json` under a single manifest.
+
+use crate::explorer;
+use crate::fetch;
+use crate::layout;
+use crate::license::{self, LicensePolicy};
+use crate::manifest::OutDirWriter;
+use crate::progress;
+use crate::quota;
+use clap::Args;
+use serde_json::{json, Value};
+use crate::backend::Chain;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::time::Instant;
+
+/// Filename of failures of batch inside output directory
+pub const FAILURES_FILENAME: &str = "failures.json";
+
+/// Filename of report of every contract of batch written into output
+/// directory
+pub const REPORT_FILENAME: &str = "report.json";
+
+#[derive(Debug, Args)]
+pub struct BatchArgs {
+    /// File listing target contract addresses, one per line. Empty lines, and
+    /// lines starting with '#' are ignored. 'failures.json' of previous batch
+    /// can be used to retry only contracts which failed.
+    #[clap(long="input", short='i', required_unless_present="import-csv", conflicts_with="import-csv")]
+    pub input: Option<String>,
+
+    /// CSV file exported from analytics platform e.g. Dune, or BigQuery to
+    /// read target contract addresses from, as an alternative to --input.
+    /// Addresses are normalized, and deduplicated.
+    #[clap(long="import-csv", required=false)]
+    pub import_csv: Option<String>,
+
+    /// Column of --import-csv holding addresses e.g. 'contract_address'.
+    /// Default is the first column whose name contains 'address'.
+    #[clap(long="column", required=false, requires="import-csv")]
+    pub column: Option<String>,
+
+    /// Which chain to work with, by name e.g. 'bsc', 'ethereum', 'polygon',
+    /// and 'arbitrum', or chain id e.g. '56'. See 'tracpls chains' for all.
+    #[clap(long="chain", short='c', required=true, multiple_values=false)]
+    pub chain: String,
+
+    /// Output directory. Files of each contract are written into its
+    /// directory named by --name-template, and 'summary.json' into the output
+    /// directory.
+    #[clap(long="out-dir", required=true)]
+    pub out_dir_path: String,
+
+    /// Template of directory of each contract relative to output directory.
+    /// '{chain}', and '{address}' are replaced with chain, and address of
+    /// contract.
+    #[clap(long="name-template", required=false, default_value="{chain}/{address}")]
+    pub name_template: String,
+
+    /// Write files of each contract into a new dated subdirectory of its
+    /// directory e.g. '2024-06-01T12-30Z/' instead of overwriting the previous
+    /// fetch, and point 'latest' next to it to the most recent one.
+    #[clap(long="snapshot", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub snapshot: bool,
+
+    /// Directory layout to place files of packages in.
+    /// Possible values are 'flat' (as verified), 'node_modules', 'lib', 'ape',
+    /// and 'brownie'.
+    #[clap(long="layout", required=false, default_value="flat")]
+    pub layout: String,
+
+    /// Don't clean CR/LF character codes of source code
+    #[clap(long="no-clean-crlf", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub no_clean_crlf: bool,
+
+    /// Format Solidity source code before output; re-indent by nesting with
+    /// spaces, keep at most one blank line in a row, and strip trailing
+    /// whitespace as 'forge fmt' does by default.
+    #[clap(long="fmt", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub fmt: bool,
+
+    /// Replace tabs in indentation of Solidity source code with spaces
+    #[clap(long="tabs-to-spaces", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub tabs_to_spaces: bool,
+
+    /// Number of spaces of each indentation level for --fmt, and
+    /// --tabs-to-spaces. Default is 4.
+    #[clap(long="indent-width", required=false)]
+    pub indent_width: Option<usize>,
+
+    /// Canonicalize output so that independently fetched copies of the same
+    /// code diff empty; LF newlines, no trailing whitespace, JSON pretty
+    /// printed with sorted keys, and Solidity source code formatted as with
+    /// --fmt.
+    #[clap(long="canonical", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub canonical: bool,
+
+    /// Comma-separated SPDX identifiers of licenses to allow e.g. 'MIT,Apache-2.0'.
+    /// Contracts declaring any other license don't pass the policy.
+    #[clap(long="license-allow", required=false)]
+    pub license_allow: Option<String>,
+
+    /// Comma-separated SPDX identifiers of licenses to deny e.g. 'UNLICENSED'.
+    #[clap(long="license-deny", required=false)]
+    pub license_deny: Option<String>,
+
+    /// What to do with contracts not passing the license policy.
+    /// Possible values are 'skip', and 'fail'.
+    #[clap(long="license-action", required=false, default_value="skip")]
+    pub license_action: String,
+
+    /// Daily limit of requests of API key. It is used to warn before starting
+    /// when the batch is projected to exceed it, if API platform doesn't
+    /// expose remaining quota by itself.
+    #[clap(long="daily-limit", required=false)]
+    pub daily_limit: Option<u64>,
+
+    /// Maximum total size in bytes of files written for a single contract.
+    /// Contract exceeding it is skipped, and recorded as such in report.
+    #[clap(long="max-bytes", required=false)]
+    pub max_bytes: Option<usize>,
+
+    /// Maximum number of files written for a single contract. Contract
+    /// exceeding it is skipped, and recorded as such in report.
+    #[clap(long="max-files", required=false)]
+    pub max_files: Option<usize>,
+
+    /// Fetch only ABIs, and write them as '<out-dir>/<chain>/<address>.json'
+    /// instead of source code of each contract into its own directory.
+    #[clap(long="abi-only", multiple_values=false, default_missing_value="true", takes_value=false,
+        conflicts_with_all=&["snapshot", "name-template", "license-allow", "license-deny"])]
+    pub abi_only: bool,
+
+    /// Number of contracts fetched concurrently. As many workers decode,
+    /// and transform source code of fetched contracts into files.
+    #[clap(long="jobs", short='j', required=false, default_value="4")]
+    pub jobs: usize,
+
+    /// Number of contracts whose files are written concurrently, while others
+    /// are being fetched. ABIs with --abi-only share a single manifest, thus
+    /// are written one at a time.
+    #[clap(long="write-jobs", required=false, default_value="2")]
+    pub write_jobs: usize,
+
+    /// Keep going with the rest of contracts when one fails, recording it in
+    /// failures.json. This is the default; the last of --keep-going, and
+    /// --no-keep-going given wins.
+    #[clap(long="keep-going", multiple_values=false, default_missing_value="true", takes_value=false, overrides_with="no-keep-going")]
+    pub keep_going: bool,
+
+    /// Stop at the first contract which fails instead of keeping going with
+    /// the rest of them.
+    #[clap(long="no-keep-going", multiple_values=false, default_missing_value="true", takes_value=false, overrides_with="keep-going")]
+    pub no_keep_going: bool,
+
+    /// Whether or not to print meta information during execution.
+    #[clap(long="silence", short='s', multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub silence: bool,
+}
+
+/// Read addresses from input file, removing duplicates while keeping order.
+/// Input file is either a plain list of addresses, or failures, or report of
+/// previous batch; only failed contracts of report are read.
+///
+/// # Arguments
+/// * `path` - path of input file
+fn read_addresses(path: &str) -> Result<Vec<String>, String> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error reading input file '{}'; err={}", path, e);
+            return Err(err_msg);
+        }
+    };
+
+    let lines: Vec<String> = if content.trim_start().starts_with('{') {
+        let failures: Value = match serde_json::from_str(&content) {
+            Ok(res) => res,
+            Err(e) => {
+                let err_msg = format!("Error parsing failures at '{}'; err={}", path, e);
+                return Err(err_msg);
+            }
+        };
+        let failed = match failures["failures"].as_array() {
+            Some(res) => res.iter().collect(),
+            None => failures["entries"].as_array().map_or(Vec::new(), |es| es.iter().filter(|e| e["status"] == "failed").collect()),
+        };
+        failed.iter().filter_map(|f| f["address"].as_str().map(|a| a.to_owned())).collect()
+    }
+    else {
+        content.lines().map(|l| l.to_owned()).collect()
+    };
+
+    let mut addresses: Vec<String> = Vec::new();
+    for line in lines.iter() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if !addresses.iter().any(|a| a.eq_ignore_ascii_case(line)) {
+            addresses.push(line.to_owned());
+        }
+    }
+    Ok(addresses)
+}
+
+/// Write summary of batch into output directory, along with failures if any
+/// so they can be retried. Stale failures of previous batch are removed.
+///
+/// # Arguments
+/// * `out_dir` - output directory
+/// * `chain` - value of chain flag option
+/// * `entries` - summary entry of each address
+fn write_summary(out_dir: &str, chain: &str, entries: &[Value]) -> Result<(), String> {
+    let count = |status: &str| entries.iter().filter(|e| e["status"] == status).count();
+    let summary = json!({
+        "chain": chain.to_lowercase(),
+        "fetched": count("fetched"),
+        "skipped": count("skipped"),
+        "failed": count("failed"),
+        "entries": entries,
+    });
+
+    let content = match serde_json::to_string_pretty(&summary) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error serializing batch summary to JSON; err={}", e);
+            return Err(err_msg);
+        }
+    };
+    let filepath = crate::combine_two_path_components(out_dir, "summary.json")?;
+    let _lock = crate::lock::DirLock::acquire(out_dir)?;
+    crate::write_file(&filepath, &format!("{}\n", content))?;
+
+    let failures_path = crate::combine_two_path_components(out_dir, FAILURES_FILENAME)?;
+    let failures: Vec<Value> = entries.iter()
+        .filter(|e| e["status"] == "failed")
+        .map(|e| json!({ "address": e["address"], "reason": e["reason"] }))
+        .collect();
+    if failures.is_empty() {
+        if std::path::Path::new(&failures_path).exists() {
+            if let Err(e) = std::fs::remove_file(&failures_path) {
+                let err_msg = format!("Error removing stale failures at '{}'; err={}", failures_path, e);
+                return Err(err_msg);
+            }
+        }
+        return Ok(());
+    }
+
+    let failures = json!({ "chain": chain.to_lowercase(), "failures": failures });
+    let content = match serde_json::to_string_pretty(&failures) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error serializing batch failures to JSON; err={}", e);
+            return Err(err_msg);
+        }
+    };
+    crate::write_file(&failures_path, &format!("{}\n", content))
+}
+
+/// Write report of every contract of batch into output directory, and print
+/// it as table unless silenced.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+/// * `entries` - summary entry of each address
+fn write_report(args: &BatchArgs, entries: &[Value]) -> Result<(), String> {
+    let total = |key: &str| entries.iter().map(|e| e[key].as_u64().unwrap_or(0)).sum::<u64>();
+    let count = |status: &str| entries.iter().filter(|e| e["status"] == status).count();
+    let report = json!({
+        "chain": args.chain.to_lowercase(),
+        "fetched": count("fetched"),
+        "skipped": count("skipped"),
+        "failed": count("failed"),
+        "files": total("files"),
+        "bytes": total("bytes"),
+        "durationMs": total("durationMs"),
+        "entries": entries.iter().map(|e| json!({
+            "address": e["address"],
+            "chain": args.chain.to_lowercase(),
+            "status": e["status"],
+            "files": e["files"].as_u64().unwrap_or(0),
+            "bytes": e["bytes"].as_u64().unwrap_or(0),
+            "durationMs": e["durationMs"],
+            "reason": e["reason"],
+        })).collect::<Vec<Value>>(),
+    });
+
+    let content = match serde_json::to_string_pretty(&report) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error serializing batch report to JSON; err={}", e);
+            return Err(err_msg);
+        }
+    };
+    let filepath = crate::combine_two_path_components(&args.out_dir_path, REPORT_FILENAME)?;
+    {
+        let _lock = crate::lock::DirLock::acquire(&args.out_dir_path)?;
+        crate::write_file(&filepath, &format!("{}\n", content))?;
+    }
+    if crate::progress::silenced(args.silence) {
+        return Ok(());
+    }
+
+    let seconds = |e: &Value| format!("{:.1}s", e["durationMs"].as_u64().unwrap_or(0) as f64 / 1000.0);
+    println!("\n{:<42} {:<10} {:<8} {:>5} {:>10} {:>8}", "ADDRESS", "CHAIN", "STATUS", "FILES", "BYTES", "DURATION");
+    for entry in report["entries"].as_array().map_or(&[][..], |e| e.as_slice()).iter() {
+        println!("{:<42} {:<10} {:<8} {:>5} {:>10} {:>8}", entry["address"].as_str().unwrap_or(""), entry["chain"].as_str().unwrap_or(""),
+            entry["status"].as_str().unwrap_or(""), entry["files"].as_u64().unwrap_or(0), entry["bytes"].as_u64().unwrap_or(0), seconds(entry));
+    }
+    println!("{} fetched, {} skipped, {} failed; {} file(s), {} bytes in {}; see {}", report["fetched"], report["skipped"], report["failed"],
+        report["files"], report["bytes"], seconds(&report), filepath);
+    Ok(())
+}
+
+/// Get directory of contract relative to output directory as per name
+/// template.
+///
+/// # Arguments
+/// * `template` - name template
+/// * `chain` - chain
+/// * `address` - contract address
+fn contract_dir(template: &str, chain: &crate::backend::Chain, address: &str) -> Result<String, String> {
+    if !template.contains("{address}") {
+        return Err("Error --name-template must contain '{address}' so contracts don't overwrite each other".to_owned());
+    }
+    let name = template.replace("{chain}", &chain.name).replace("{address}", address);
+    Ok(crate::source::safe_relative_path(&name))
+}
+
+/// Outcome of writing ABI of contract into directory of chain.
+enum Fetched {
+    /// Number of files, number of those which were up to date, and total
+    /// size of files in bytes
+    Written(usize, usize, usize),
+
+    /// Nothing is written as files exceed --max-bytes, or --max-files
+    OverBudget(String),
+}
+
+/// Check files of contract against --max-bytes, and --max-files. Return
+/// reason if exceeding either.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+/// * `files` - pairs of relative path, and content of files
+fn exceeded_budget(args: &BatchArgs, files: &[(String, String)]) -> Option<String> {
+    let bytes: usize = files.iter().map(|(_, content)| content.len()).sum();
+    if let Some(max_files) = args.max_files.filter(|max| files.len() > *max) {
+        return Some(format!("{} file(s) exceed --max-files {}", files.len(), max_files));
+    }
+    if let Some(max_bytes) = args.max_bytes.filter(|max| bytes > *max) {
+        return Some(format!("{} bytes exceed --max-bytes {}", bytes, max_bytes));
+    }
+    None
+}
+
+/// Summary entry of contract which failed.
+///
+/// # Arguments
+/// * `address` - contract address
+/// * `license` - declared license if checked
+/// * `reason` - why it failed
+fn failed_entry(address: &str, license: Option<&str>, reason: &str) -> Value {
+    json!({ "address": address, "status": "failed", "license": license, "reason": reason })
+}
+
+/// Summary entry of contract which is skipped, printing why unless silenced.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+/// * `address` - contract address
+/// * `license` - declared license if checked
+/// * `reason` - why it is skipped
+fn skipped_entry(args: &BatchArgs, address: &str, license: Option<&str>, reason: &str) -> Value {
+    progress::emit("skipped", json!({ "address": address, "reason": reason }));
+    if !crate::progress::silenced(args.silence) {
+        println!("{} skipped; {}", address, reason);
+    }
+    json!({ "address": address, "status": "skipped", "license": license, "reason": reason })
+}
+
+/// Outcome of a stage of pipeline for contract.
+enum Step<T> {
+    /// Passed on to the next stage
+    Next(T),
+
+    /// Done with summary entry without going further e.g. skipped
+    Done(Value),
+}
+
+/// Verification metadata of contract fetched by fetch stage of pipeline, to
+/// be transformed into files.
+struct Downloaded {
+    /// Index of contract in input
+    index: usize,
+    started: Instant,
+
+    /// Declared license if checked
+    license: Option<String>,
+
+    /// Response body of `getsourcecode` endpoint
+    body: String,
+
+    /// Chain of backend which served it
+    serving: &'static Chain,
+}
+
+/// Files of contract transformed by transform stage of pipeline, to be
+/// written by write stage.
+struct Transformed {
+    /// Index of contract in input
+    index: usize,
+    started: Instant,
+
+    /// Declared license if checked
+    license: Option<String>,
+
+    /// Pairs of relative path, and content
+    files: Vec<(String, String)>,
+
+    /// Chain of backend which served it
+    serving: &'static Chain,
+}
+
+/// Summary entries of contracts finishing pipeline in any order, kept in
+/// order of input.
+struct Outcomes {
+    entries: Mutex<Vec<Option<Value>>>,
+
+    /// Error of the first contract failing with --no-keep-going, after which
+    /// no more contract is fetched
+    stopped: Mutex<Option<String>>,
+}
+
+impl Outcomes {
+    fn new(count: usize) -> Self {
+        Outcomes { entries: Mutex::new(vec![None; count]), stopped: Mutex::new(None) }
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.stopped.lock().map_or(true, |s| s.is_some())
+    }
+
+    /// Record summary entry of contract along with how long it took since
+    /// its fetch started.
+    ///
+    /// # Arguments
+    /// * `args` - arguments of the command
+    /// * `index` - index of contract in input
+    /// * `address` - contract address
+    /// * `started` - when fetch of contract started
+    /// * `result` - summary entry, or failed entry along with error
+    fn record(&self, args: &BatchArgs, index: usize, address: &str, started: Instant, result: Result<Value, (Value, String)>) {
+        let mut entry = match result {
+            Ok(res) => res,
+            Err((entry, e)) => {
+                progress::error(Some(address), &e);
+                if !crate::progress::silenced(args.silence) {
+                    println!("{} failed; {}", address, entry["reason"].as_str().unwrap_or(&e));
+                }
+                if args.no_keep_going {
+                    if let Ok(mut stopped) = self.stopped.lock() {
+                        stopped.get_or_insert(e);
+                    }
+                }
+                entry
+            }
+        };
+        entry["durationMs"] = json!(started.elapsed().as_millis() as u64);
+        if let Ok(mut entries) = self.entries.lock() {
+            entries[index] = Some(entry);
+        }
+    }
+
+    /// Get summary entries of contracts which finished in order of input,
+    /// along with error which stopped the batch if any.
+    fn into_entries(self) -> (Vec<Value>, Option<String>) {
+        let entries = self.entries.into_inner().unwrap_or_default().into_iter().flatten().collect();
+        (entries, self.stopped.into_inner().unwrap_or_default())
+    }
+}
+
+/// Fetch stage of pipeline; check license of contract before fetching any
+/// source code, then fetch its verification metadata.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+/// * `chain` - chain
+/// * `index` - index of contract in input
+/// * `address` - contract address
+/// * `started` - when fetch of contract started
+/// * `policy` - license policy
+/// * `fail_on_license` - whether or not contract violating license policy fails
+fn download_contract(args: &BatchArgs, chain: &'static Chain, index: usize, address: &str, started: Instant, policy: &LicensePolicy, fail_on_license: bool) -> Result<Step<Downloaded>, (Value, String)> {
+    let mut declared = None;
+    if !policy.is_empty() {
+        let meta = explorer::get_source_meta(chain, address).map_err(|e| (failed_entry(address, None, &e), e))?;
+        let license = license::declared_license(&meta);
+
+        if let Err(reason) = policy.check(&license) {
+            if fail_on_license {
+                let err_msg = format!("Error license policy is violated by {}; {}", address, reason);
+                return Err((failed_entry(address, Some(&license), &reason), err_msg));
+            }
+            return Ok(Step::Done(skipped_entry(args, address, Some(&license), &reason)));
+        }
+        declared = Some(license);
+    }
+
+    let (body, serving) = crate::backend::with_failover(chain, |c| {
+        let body = explorer::get_source_meta_text(c, address)?;
+        if !explorer::parse_source_meta_raw(address, &body)?.is_verified() {
+            let err_msg = format!("Error {} is not verified", address);
+            return Err(err_msg);
+        }
+        Ok(body)
+    }).map_err(|e| (failed_entry(address, declared.as_deref(), &e), e))?;
+    Ok(Step::Next(Downloaded { index, started, license: declared, body, serving }))
+}
+
+/// Transform stage of pipeline; decode source code from verification
+/// metadata, and transform it into files as per fetch options. Files are
+/// checked against budget before any of them is written.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+/// * `address` - contract address
+/// * `downloaded` - verification metadata of contract
+/// * `options` - fetch options
+fn transform_contract(args: &BatchArgs, address: &str, downloaded: Downloaded, options: &fetch::FetchOptions) -> Result<Step<Transformed>, (Value, String)> {
+    let license = downloaded.license.as_deref();
+    let failed = |e: String| (failed_entry(address, license, &e), e);
+    let raw = explorer::parse_source_meta_raw(address, &downloaded.body).map_err(failed)?;
+    let mut files: Vec<(String, String)> = Vec::new();
+    fetch::fetch_each_raw(downloaded.serving, address, &raw, options, |path, content| {
+        files.push((path.to_owned(), content.to_owned()));
+        Ok(())
+    }).map_err(failed)?;
+
+    if let Some(reason) = exceeded_budget(args, &files) {
+        return Ok(Step::Done(skipped_entry(args, address, license, &reason)));
+    }
+    let Downloaded { index, started, license, serving, .. } = downloaded;
+    Ok(Step::Next(Transformed { index, started, license, files, serving }))
+}
+
+/// Write files of contract into its directory. Returns number of files,
+/// number of those which were up to date, and total size of files in bytes.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+/// * `chain` - chain
+/// * `address` - contract address
+/// * `transformed` - files of contract
+fn write_files(args: &BatchArgs, chain: &Chain, address: &str, transformed: &Transformed) -> Result<(usize, usize, usize), String> {
+    let mut address_dir = crate::combine_two_path_components(&args.out_dir_path, &contract_dir(&args.name_template, chain, address)?)?;
+    if args.snapshot {
+        address_dir = crate::snapshot::create(&address_dir)?;
+    }
+    let mut writer = OutDirWriter::open(&address_dir, address, &chain.name, true)?;
+    for (path, content) in transformed.files.iter() {
+        writer.write(path, content)?;
+    }
+    writer.set_backend(transformed.serving.backend.name());
+    let unchanged = writer.unchanged;
+    writer.finish()?;
+    if args.snapshot {
+        crate::snapshot::update_latest(&address_dir)?;
+    }
+    let bytes = transformed.files.iter().map(|(_, content)| content.len()).sum();
+    Ok((transformed.files.len(), unchanged, bytes))
+}
+
+/// Write stage of pipeline; write files of contract into its directory.
+/// Returns its summary entry, or failed entry along with error.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+/// * `chain` - chain
+/// * `address` - contract address
+/// * `transformed` - files of contract
+fn write_contract(args: &BatchArgs, chain: &Chain, address: &str, transformed: &Transformed) -> Result<Value, (Value, String)> {
+    let license = transformed.license.as_deref();
+    let (count, unchanged, bytes) = write_files(args, chain, address, transformed).map_err(|e| (failed_entry(address, license, &e), e))?;
+    progress::emit("fetch_done", json!({ "address": address, "chain": chain.name, "files": count }));
+    if !crate::progress::silenced(args.silence) {
+        if unchanged == count {
+            println!("{} up to date; {} file(s)", address, count);
+        }
+        else if unchanged > 0 {
+            println!("{} fetched; {} file(s), {} up to date", address, count, unchanged);
+        }
+        else {
+            println!("{} fetched; {} file(s)", address, count);
+        }
+    }
+    Ok(json!({ "address": address, "status": "fetched", "license": license, "files": count, "unchanged": unchanged, "bytes": bytes }))
+}
+
+/// Fetch source code of contracts into their directories by pipeline of
+/// three stages connected by bounded channels; --jobs workers fetch
+/// verification metadata, as many transform it into files, and --write-jobs
+/// workers write them, so writing many small files overlaps with network
+/// requests. Returns summary entry of each contract in order of input, along
+/// with error which stopped the batch with --no-keep-going if any.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+/// * `chain` - chain
+/// * `addresses` - contract addresses
+/// * `options` - fetch options
+/// * `policy` - license policy
+/// * `fail_on_license` - whether or not contract violating license policy fails
+fn fetch_contracts(args: &BatchArgs, chain: &'static Chain, addresses: &[String], options: &fetch::FetchOptions, policy: &LicensePolicy, fail_on_license: bool) -> (Vec<Value>, Option<String>) {
+    let outcomes = Outcomes::new(addresses.len());
+    let next = AtomicUsize::new(0);
+    let jobs = args.jobs.clamp(1, addresses.len().max(1));
+    let write_jobs = args.write_jobs.clamp(1, addresses.len().max(1));
+
+    // bounded so fetching doesn't run ahead of writing holding everything in memory
+    let (downloaded_sender, downloaded_receiver) = mpsc::sync_channel::<Downloaded>(jobs);
+    let (transformed_sender, transformed_receiver) = mpsc::sync_channel::<Transformed>(write_jobs);
+    let downloaded_receiver = Mutex::new(downloaded_receiver);
+    let transformed_receiver = Mutex::new(transformed_receiver);
+    let (outcomes_ref, next, downloaded_receiver, transformed_receiver) = (&outcomes, &next, &downloaded_receiver, &transformed_receiver);
+
+    std::thread::scope(|scope| {
+        for _ in 0..jobs {
+            let sender = downloaded_sender.clone();
+            scope.spawn(move || while !outcomes_ref.is_stopped() {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let address = match addresses.get(index) {
+                    Some(res) => res,
+                    None => break,
+                };
+                progress::emit("fetch_start", json!({ "address": address, "chain": chain.name }));
+                let started = Instant::now();
+                match download_contract(args, chain, index, address, started, policy, fail_on_license) {
+                    Ok(Step::Next(downloaded)) => {
+                        if sender.send(downloaded).is_err() {
+                            break;
+                        }
+                    },
+                    Ok(Step::Done(entry)) => outcomes_ref.record(args, index, address, started, Ok(entry)),
+                    Err(failed) => outcomes_ref.record(args, index, address, started, Err(failed)),
+                }
+            });
+        }
+        drop(downloaded_sender);
+
+        for _ in 0..jobs {
+            let sender = transformed_sender.clone();
+            scope.spawn(move || loop {
+                let received = match downloaded_receiver.lock() {
+                    Ok(receiver) => receiver.recv(),
+                    Err(_) => break,
+                };
+                let downloaded = match received {
+                    Ok(res) => res,
+                    Err(_) => break,
+                };
+                let (index, started) = (downloaded.index, downloaded.started);
+                let address = &addresses[index];
+                match transform_contract(args, address, downloaded, options) {
+                    Ok(Step::Next(transformed)) => {
+                        if sender.send(transformed).is_err() {
+                            break;
+                        }
+                    },
+                    Ok(Step::Done(entry)) => outcomes_ref.record(args, index, address, started, Ok(entry)),
+                    Err(failed) => outcomes_ref.record(args, index, address, started, Err(failed)),
+                }
+            });
+        }
+        drop(transformed_sender);
+
+        for _ in 0..write_jobs {
+            scope.spawn(move || loop {
+                let received = match transformed_receiver.lock() {
+                    Ok(receiver) => receiver.recv(),
+                    Err(_) => break,
+                };
+                let transformed = match received {
+                    Ok(res) => res,
+                    Err(_) => break,
+                };
+                let address = &addresses[transformed.index];
+                let result = write_contract(args, chain, address, &transformed);
+                outcomes_ref.record(args, transformed.index, address, transformed.started, result);
+            });
+        }
+    });
+    outcomes.into_entries()
+}
+
+/// Fetch ABI of contract from its verification metadata, which is cached with
+/// --cache-dir unlike response of `getabi` endpoint. Returns it pretty
+/// printed.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+fn fetch_abi(chain: &crate::backend::Chain, address: &str) -> Result<String, String> {
+    let (meta, _) = crate::backend::with_failover(chain, |c| explorer::get_source_meta(c, address))?;
+    let abi_text = meta["ABI"].as_str().unwrap_or("");
+    let abi: Value = match serde_json::from_str(abi_text) {
+        Ok(res) => res,
+        Err(_) => {
+            let err_msg = format!("Error {} is not verified; {}", address, abi_text);
+            return Err(err_msg);
+        }
+    };
+    match serde_json::to_string_pretty(&abi) {
+        Ok(res) => Ok(format!("{}\n", res)),
+        Err(e) => {
+            let err_msg = format!("Error serializing ABI of {} to JSON; err={}", address, e);
+            Err(err_msg)
+        }
+    }
+}
+
+/// ABI fetched by worker, along with how long it took.
+struct FetchedAbi {
+    result: Result<String, String>,
+    duration_ms: u64,
+}
+
+/// Fetch ABIs of contracts concurrently by --jobs workers, and write each
+/// into directory of chain as soon as it arrives. They share a single
+/// manifest, thus are written one at a time. Returns summary entry of each
+/// address in order of input, along with error which stopped the batch with
+/// --no-keep-going if any.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+/// * `chain` - chain
+/// * `addresses` - contract addresses
+fn fetch_abis(args: &BatchArgs, chain: &crate::backend::Chain, addresses: &[String]) -> Result<(Vec<Value>, Option<String>), String> {
+    let chain_dir = crate::combine_two_path_components(&args.out_dir_path, &chain.name)?;
+    let mut writer = OutDirWriter::open(&chain_dir, "", &chain.name, true)?;
+    // ABIs of contracts of other batches share the directory
+    writer.keep_unwritten();
+    let mut entries: Vec<Option<Value>> = vec![None; addresses.len()];
+    let mut stopped = None;
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let jobs = args.jobs.clamp(1, addresses.len().max(1));
+    std::thread::scope(|scope| -> Result<(), String> {
+        // receiver is dropped on return, so workers blocked on sending give up
+        let (sender, receiver) = mpsc::sync_channel::<(usize, FetchedAbi)>(jobs);
+        for _ in 0..jobs {
+            let sender = sender.clone();
+            let (next, stop) = (&next, &stop);
+            scope.spawn(move || while !stop.load(Ordering::Relaxed) {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let address = match addresses.get(index) {
+                    Some(res) => res,
+                    None => break,
+                };
+                progress::emit("fetch_start", json!({ "address": address, "chain": chain.name }));
+                let started = Instant::now();
+                let result = fetch_abi(chain, address);
+                if sender.send((index, FetchedAbi { result, duration_ms: started.elapsed().as_millis() as u64 })).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        for (index, FetchedAbi { result, duration_ms }) in receiver.iter() {
+            let address = &addresses[index];
+            let path = format!("{}.json", address.to_lowercase());
+            let written = result.and_then(|abi| match exceeded_budget(args, &[(path.clone(), abi.clone())]) {
+                Some(reason) => Ok(Fetched::OverBudget(reason)),
+                None => {
+                    let unchanged = writer.unchanged;
+                    writer.write(&path, &abi)?;
+                    Ok(Fetched::Written(1, writer.unchanged - unchanged, abi.len()))
+                }
+            });
+            match written {
+                Ok(Fetched::Written(count, unchanged, bytes)) => {
+                    progress::emit("fetch_done", json!({ "address": address, "chain": chain.name, "files": count }));
+                    if !crate::progress::silenced(args.silence) {
+                        println!("{} {}; {}", address, if unchanged > 0 { "up to date" } else { "fetched" }, path);
+                    }
+                    entries[index] = Some(json!({ "address": address, "status": "fetched", "files": count, "unchanged": unchanged, "bytes": bytes, "durationMs": duration_ms }));
+                },
+                Ok(Fetched::OverBudget(reason)) => {
+                    progress::emit("skipped", json!({ "address": address, "reason": reason }));
+                    if !crate::progress::silenced(args.silence) {
+                        println!("{} skipped; {}", address, reason);
+                    }
+                    entries[index] = Some(json!({ "address": address, "status": "skipped", "reason": reason, "durationMs": duration_ms }));
+                },
+                Err(e) => {
+                    progress::error(Some(address), &e);
+                    if !crate::progress::silenced(args.silence) {
+                        println!("{} failed; {}", address, e);
+                    }
+                    entries[index] = Some(json!({ "address": address, "status": "failed", "reason": e, "durationMs": duration_ms }));
+                    if args.no_keep_going {
+                        stop.store(true, Ordering::Relaxed);
+                        stopped = Some(e);
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(())
+    })?;
+    if stopped.is_none() {
+        writer.set_backend(chain.backend.name());
+    }
+    writer.finish()?;
+    Ok((entries.into_iter().flatten().collect(), stopped))
+}
+
+/// Execute `batch` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &BatchArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.chain)?;
+    let out_layout = layout::parse_layout(&args.layout)?;
+    let format = crate::formatter::parse_format_options(args.fmt || args.canonical, args.tabs_to_spaces, args.indent_width)?;
+    let fail_on_license = match args.license_action.to_lowercase().as_str() {
+        "skip" => false,
+        "fail" => true,
+        _ => return Err("Error invalid value for --license-action.\nPossible values are 'skip', or 'fail'.".to_owned()),
+    };
+    // fail before fetching anything
+    contract_dir(&args.name_template, chain, "")?;
+    let policy = LicensePolicy::new(args.license_allow.as_ref(), args.license_deny.as_ref());
+    let addresses = match (args.input.as_ref(), args.import_csv.as_ref()) {
+        (_, Some(csv)) => crate::import_csv::read_addresses(csv, args.column.as_deref())?,
+        (Some(input), None) => read_addresses(input)?,
+        (None, None) => return Err("Error either --input, or --import-csv is required".to_owned()),
+    };
+
+    // license policy needs metadata fetched separately before source code
+    let requests_per_address = if policy.is_empty() { 1 } else { 2 };
+    quota::warn_if_exceeding(chain, addresses.len() as u64 * requests_per_address, args.daily_limit);
+
+    let options = fetch::FetchOptions {
+        abi_only: false,
+        abi_pretty_print: true,
+        clean_crlf: !args.no_clean_crlf,
+        layout: out_layout,
+        project_files: true,
+        artifact_format: None,
+        order: crate::source::Order::Path,
+        format,
+        canonical: args.canonical,
+        raw_response: false,
+        interfaces_only: false,
+        prune_unreachable: false,
+        contract: None,
+    };
+
+    let (entries, stopped) = if args.abi_only {
+        fetch_abis(args, chain, &addresses)?
+    }
+    else {
+        fetch_contracts(args, chain, &addresses, &options, &policy, fail_on_license)
+    };
+    write_summary(&args.out_dir_path, &args.chain, &entries)?;
+    write_report(args, &entries)?;
+    if let Some(e) = stopped {
+        return Err(e);
+    }
+    let failed = entries.iter().filter(|e| e["status"] == "failed").count();
+    if !crate::progress::silenced(args.silence) && failed > 0 {
+        let filepath = crate::combine_two_path_components(&args.out_dir_path, FAILURES_FILENAME)?;
+        println!("{} contract(s) failed; see {}", failed, filepath);
+    }
+    Ok(())
+}
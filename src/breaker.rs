@@ -0,0 +1,242 @@
+//! Circuit breaker of a backend. Once requests to API platform fail a number
+//! of times in a row e.g. it is down mid-batch, the breaker trips, and
+//! requests to it are paused for a while instead of each contract spending
+//! its own time on failing. The first request after the pause probes whether
+//! API platform is back while the others keep waiting; failing again trips the
+//! breaker for twice as long. Outcomes of requests sent before the breaker
+//! tripped e.g. in flight with `--jobs` don't count, so a single outage trips
+//! it only once.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Longest pause after repeated trips
+const MAX_COOLDOWN: Duration = Duration::from_secs(300);
+
+/// How long requests wait for the one probing API platform before checking
+/// again
+const PROBE_WAIT: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    /// Number of failed requests in a row
+    failures: u32,
+
+    /// Time until which requests are paused
+    open_until: Option<Instant>,
+
+    /// Whether request probing API platform after pause is in flight
+    probing: bool,
+
+    /// Number of trips in a row without success in between
+    trips: u32,
+
+    /// Incremented on every trip, so outcomes of requests admitted before
+    /// are told apart
+    generation: u64,
+}
+
+/// Permission to send request given by circuit breaker, to record its outcome
+/// with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Admission {
+    /// Generation of breaker the request is admitted in
+    generation: u64,
+
+    /// Whether request probes API platform after pause
+    probe: bool,
+}
+
+impl Admission {
+    /// Whether request probes API platform after pause.
+    pub fn is_probe(&self) -> bool {
+        self.probe
+    }
+}
+
+/// Circuit breaker of a backend.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    /// Number of failed requests in a row to trip, 0 to never trip
+    pub threshold: u32,
+
+    /// How long requests are paused after the first trip
+    pub cooldown: Duration,
+
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    /// Create circuit breaker.
+    ///
+    /// # Arguments
+    /// * `threshold` - number of failed requests in a row to trip, 0 to never trip
+    /// * `cooldown_secs` - seconds which requests are paused after the first trip
+    pub fn new(threshold: u32, cooldown_secs: u64) -> Self {
+        Self {
+            threshold,
+            cooldown: Duration::from_secs(cooldown_secs),
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    /// Admit request to be sent, or get how long to wait before asking again
+    /// while requests are paused, or another one is probing API platform.
+    pub fn admit(&self) -> Result<Admission, Duration> {
+        self.admit_at(Instant::now())
+    }
+
+    /// Same as `admit()` as of the time.
+    ///
+    /// # Arguments
+    /// * `now` - current time
+    fn admit_at(&self, now: Instant) -> Result<Admission, Duration> {
+        let mut state = match self.state.lock() {
+            Ok(res) => res,
+            // never block requests on poisoned state
+            Err(_) => return Ok(Admission { generation: u64::MAX, probe: false }),
+        };
+        match state.open_until {
+            None => Ok(Admission { generation: state.generation, probe: false }),
+            Some(until) if now < until => Err(until - now),
+            Some(_) if state.probing => Err(PROBE_WAIT),
+            Some(_) => {
+                state.probing = true;
+                Ok(Admission { generation: state.generation, probe: true })
+            }
+        }
+    }
+
+    /// Record successful request, closing the breaker.
+    ///
+    /// # Arguments
+    /// * `admission` - admission the request is sent with
+    pub fn record_success(&self, admission: Admission) {
+        if let Ok(mut state) = self.state.lock() {
+            if admission.generation == state.generation {
+                *state = BreakerState { generation: state.generation, ..Default::default() };
+            }
+        }
+    }
+
+    /// Record failed request. Returns duration of pause if the breaker trips
+    /// by it.
+    ///
+    /// # Arguments
+    /// * `admission` - admission the request is sent with
+    pub fn record_failure(&self, admission: Admission) -> Option<Duration> {
+        self.record_failure_at(admission, Instant::now())
+    }
+
+    /// Same as `record_failure()` as of the time.
+    ///
+    /// # Arguments
+    /// * `admission` - admission the request is sent with
+    /// * `now` - current time
+    fn record_failure_at(&self, admission: Admission, now: Instant) -> Option<Duration> {
+        if self.threshold == 0 {
+            return None;
+        }
+        let mut state = self.state.lock().ok()?;
+        // sent before the breaker tripped, or closed since
+        if admission.generation != state.generation {
+            return None;
+        }
+        if !admission.probe {
+            state.failures += 1;
+            if state.failures < self.threshold {
+                return None;
+            }
+        }
+
+        // probe after pause failed, or threshold is reached for the first time
+        let cooldown = self.cooldown.saturating_mul(1 << state.trips.min(8)).min(MAX_COOLDOWN);
+        state.trips += 1;
+        state.failures = 0;
+        state.probing = false;
+        state.open_until = Some(now + cooldown);
+        state.generation += 1;
+        Some(cooldown)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breaker_trips_at_threshold() {
+        let breaker = CircuitBreaker::new(3, 10);
+        let now = Instant::now();
+        for _ in 0..2 {
+            let admission = breaker.admit_at(now).unwrap();
+            assert_eq!(breaker.record_failure_at(admission, now), None);
+        }
+
+        // success in between starts counting again
+        breaker.record_success(breaker.admit_at(now).unwrap());
+        for _ in 0..2 {
+            assert_eq!(breaker.record_failure_at(breaker.admit_at(now).unwrap(), now), None);
+        }
+        let admission = breaker.admit_at(now).unwrap();
+        assert!(!admission.is_probe());
+        assert_eq!(breaker.record_failure_at(admission, now), Some(Duration::from_secs(10)));
+        assert_eq!(breaker.admit_at(now + Duration::from_secs(4)), Err(Duration::from_secs(6)));
+
+        let never = CircuitBreaker::new(0, 10);
+        for _ in 0..100 {
+            assert_eq!(never.record_failure_at(never.admit_at(now).unwrap(), now), None);
+        }
+    }
+
+    #[test]
+    fn breaker_ignores_requests_in_flight_when_tripped() {
+        let breaker = CircuitBreaker::new(2, 10);
+        let now = Instant::now();
+        let in_flight: Vec<Admission> = (0..4).map(|_| breaker.admit_at(now).unwrap()).collect();
+        assert_eq!(breaker.record_failure_at(in_flight[0], now), None);
+        assert_eq!(breaker.record_failure_at(in_flight[1], now), Some(Duration::from_secs(10)));
+
+        // the rest failing later neither count, nor double the pause
+        for admission in in_flight[2..].iter() {
+            assert_eq!(breaker.record_failure_at(*admission, now), None);
+        }
+        breaker.record_success(in_flight[3]);
+        assert_eq!(breaker.admit_at(now), Err(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn breaker_backs_off_while_probes_fail() {
+        let breaker = CircuitBreaker::new(1, 10);
+        let mut now = Instant::now();
+        assert_eq!(breaker.record_failure_at(breaker.admit_at(now).unwrap(), now), Some(Duration::from_secs(10)));
+
+        for expected in [20, 40, 80, 160, 300, 300] {
+            now += Duration::from_secs(300);
+            let probe = breaker.admit_at(now).unwrap();
+            assert!(probe.is_probe());
+            // only one probe at a time
+            assert_eq!(breaker.admit_at(now), Err(PROBE_WAIT));
+            assert_eq!(breaker.record_failure_at(probe, now), Some(Duration::from_secs(expected)));
+        }
+    }
+
+    #[test]
+    fn breaker_closes_when_probe_succeeds() {
+        let breaker = CircuitBreaker::new(2, 10);
+        let mut now = Instant::now();
+        breaker.record_failure_at(breaker.admit_at(now).unwrap(), now);
+        breaker.record_failure_at(breaker.admit_at(now).unwrap(), now);
+
+        now += Duration::from_secs(10);
+        let probe = breaker.admit_at(now).unwrap();
+        assert!(probe.is_probe());
+        breaker.record_success(probe);
+        let admission = breaker.admit_at(now).unwrap();
+        assert!(!admission.is_probe());
+
+        // trips again after threshold, for cooldown of the first trip
+        assert_eq!(breaker.record_failure_at(admission, now), None);
+        assert_eq!(breaker.record_failure_at(breaker.admit_at(now).unwrap(), now), Some(Duration::from_secs(10)));
+    }
+}
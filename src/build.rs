@@ -0,0 +1,169 @@
+//! `build` command: recompile verified source with its verification settings
+//! and report compiler warnings.
+
+use crate::explorer;
+use crate::solc;
+use crate::source;
+use crate::TargetArgs;
+use clap::Args;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Args)]
+pub struct BuildArgs {
+    #[clap(flatten)]
+    pub target: TargetArgs,
+
+    /// Path to solc binary to use instead of finding one matching the verified
+    /// compiler version
+    #[clap(long="solc", required=false)]
+    pub solc: Option<String>,
+
+    /// Output as JSON instead of human readable report
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+}
+
+/// Compiler diagnostic located in source.
+pub struct Diagnostic {
+    pub severity: String,
+    pub error_code: String,
+    pub message: String,
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Result of recompiling verified source.
+pub struct BuildOutput {
+    /// Name of the primary contract
+    pub contract_name: String,
+
+    pub solc_version: String,
+    pub settings: Value,
+    /// Standard JSON output of solc
+    pub output: Value,
+    pub warnings: Vec<Diagnostic>,
+
+    /// Source files compiled
+    pub files: Vec<source::SourceFile>,
+}
+
+/// Recompile verified source of contract with settings used for verification.
+///
+/// # Arguments
+/// * `target` - target contract address and chain
+/// * `solc_path` - user supplied path to solc binary
+/// * `output_selection` - value of `outputSelection` of standard JSON input
+pub fn build(target: &TargetArgs, solc_path: Option<&String>, output_selection: Value) -> Result<BuildOutput, String> {
+    let chain = crate::parse_chain(&target.chain)?;
+    let contract_source = source::fetch_sources(chain, &target.address)?;
+    let meta = explorer::get_source_meta(chain, &target.address)?;
+
+    let solc_path = solc::find_solc(&contract_source.compiler_version, solc_path)?;
+    let mut settings = solc::verified_settings(&meta);
+    settings["outputSelection"] = output_selection;
+
+    let input = solc::build_input(&contract_source.files, settings.clone());
+    let output = solc::compile(&solc_path, &input)?;
+
+    let mut warnings = Vec::new();
+    for e in output["errors"].as_array().unwrap_or(&Vec::new()) {
+        let file = e["sourceLocation"]["file"].as_str().unwrap_or("").to_owned();
+        let start = e["sourceLocation"]["start"].as_u64().unwrap_or(0) as usize;
+        let (line, column) = match contract_source.files.iter().find(|f| f.path == file) {
+            Some(f) => solc::offset_to_line_col(&f.content, start),
+            None => (0, 0),
+        };
+
+        warnings.push(Diagnostic {
+            severity: e["severity"].as_str().unwrap_or("").to_owned(),
+            error_code: e["errorCode"].as_str().unwrap_or("").to_owned(),
+            message: e["message"].as_str().unwrap_or("").to_owned(),
+            file,
+            line,
+            column,
+        });
+    }
+
+    if let Some(s) = settings.as_object_mut() {
+        s.remove("outputSelection");
+    }
+    Ok(BuildOutput {
+        contract_name: contract_source.contract_name.clone(),
+        solc_version: solc::plain_version(&contract_source.compiler_version),
+        settings,
+        output,
+        warnings,
+        files: contract_source.files,
+    })
+}
+
+/// Execute `build` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &BuildArgs) -> Result<(), String> {
+    let built = build(&args.target, args.solc.as_ref(), json!({ "*": { "*": ["evm.deployedBytecode.object"] } }))?;
+
+    let mut contracts: Vec<(String, usize)> = Vec::new();
+    if let Some(files) = built.output["contracts"].as_object() {
+        for (file, file_contracts) in files.iter() {
+            for (name, contract) in file_contracts.as_object().unwrap_or(&serde_json::Map::new()).iter() {
+                let size = contract["evm"]["deployedBytecode"]["object"].as_str().unwrap_or("").len() / 2;
+                contracts.push((format!("{}:{}", file, name), size));
+            }
+        }
+    }
+
+    if args.json {
+        let json = json!({
+            "solcVersion": built.solc_version,
+            "settings": built.settings,
+            "contracts": contracts.iter().map(|(name, size)| json!({ "name": name, "deployedSize": size })).collect::<Vec<Value>>(),
+            "warnings": built.warnings.iter().map(|w| json!({
+                "severity": w.severity,
+                "errorCode": w.error_code,
+                "message": w.message,
+                "file": w.file,
+                "line": w.line,
+                "column": w.column,
+            })).collect::<Vec<Value>>(),
+        });
+
+        match serde_json::to_string_pretty(&json) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing build report to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+        return Ok(());
+    }
+
+    println!("Compiled {} contract(s) with solc {}; settings={}", contracts.len(), built.solc_version, built.settings);
+    for (name, size) in contracts.iter() {
+        println!("  {} ({} bytes deployed)", name, size);
+    }
+
+    if built.warnings.is_empty() {
+        println!("\nNo compiler warnings");
+        return Ok(());
+    }
+
+    // group the same kind of warning together
+    let mut groups: BTreeMap<(String, String), Vec<&Diagnostic>> = BTreeMap::new();
+    for w in built.warnings.iter() {
+        groups.entry((w.error_code.clone(), w.message.clone())).or_default().push(w);
+    }
+
+    println!("\n{} compiler warning(s)", built.warnings.len());
+    for ((error_code, message), diagnostics) in groups.iter() {
+        println!("\n[{}] {} ({})", error_code, message, diagnostics[0].severity);
+        for d in diagnostics {
+            println!("  {}:{}:{}", d.file, d.line, d.column);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,237 @@
+//! `bytecode-diff` command: compare runtime bytecode of two deployments, to
+//! confirm whether they are the same build.
+
+use crate::bytecode::{self, Instruction};
+use crate::explorer;
+use clap::Args;
+
+#[derive(Debug, Args)]
+pub struct BytecodeDiffArgs {
+    /// Address of the first contract
+    #[clap(long="address-a", short='a', required=true)]
+    pub address_a: String,
+
+    /// Address of the second contract
+    #[clap(long="address-b", short='b', required=true)]
+    pub address_b: String,
+
+    /// Which chain to work with, by name e.g. 'bsc', 'ethereum', 'polygon',
+    /// and 'arbitrum', or chain id e.g. '56'. See 'tracpls chains' for all.
+    #[clap(long="chain", short='c', required=true, multiple_values=false)]
+    pub chain: String,
+
+    /// Show opcode-level diff
+    #[clap(long="opcodes", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub opcodes: bool,
+}
+
+/// Percentage of similarity from which bytecodes are reported as similar
+const SIMILAR_THRESHOLD: f64 = 90.0;
+
+/// Edit distance beyond which opcode-level diff is not computed
+const MAX_EDIT_DISTANCE: usize = 2000;
+
+/// Lines of unchanged instructions to show around changes
+const CONTEXT_LINES: usize = 3;
+
+/// Step of turning one sequence e.g. of instructions, or lines into another.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Edit {
+    /// Element at index of both sequences is the same
+    Keep(usize, usize),
+
+    /// Element at index of the first sequence is removed
+    Remove(usize),
+
+    /// Element at index of the second sequence is inserted
+    Insert(usize),
+}
+
+/// Compute shortest edit script between two sequences with Myers' algorithm,
+/// or `None` if edit distance exceeds `max_distance`.
+///
+/// # Arguments
+/// * `a` - first sequence
+/// * `b` - second sequence
+/// * `max_distance` - maximum edit distance to compute up to
+pub fn diff<T: PartialEq>(a: &[T], b: &[T], max_distance: usize) -> Option<Vec<Edit>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let limit = max_distance.min(a.len() + b.len()) as isize;
+    let offset = limit + 1;
+    let mut v = vec![0isize; (2 * limit + 3) as usize];
+
+    // furthest reaching x of each diagonal k before each step d, only
+    // diagonals within reach are kept
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    for d in 0..=limit {
+        trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let i = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && v[i - 1] < v[i + 1]) { v[i + 1] } else { v[i - 1] + 1 };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[i] = x;
+
+            if x >= n && y >= m {
+                return Some(backtrack(&trace, n, m));
+            }
+        }
+    }
+    None
+}
+
+/// Walk back trace of Myers' algorithm into edit script in order.
+///
+/// # Arguments
+/// * `trace` - furthest reaching x of diagonals before each step
+/// * `n` - length of first sequence
+/// * `m` - length of second sequence
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize) -> Vec<Edit> {
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| v[(k + d + 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) { k + 1 } else { k - 1 };
+        let prev_x = if d == 0 { 0 } else { at(prev_k) };
+        let prev_y = if d == 0 { 0 } else { prev_x - prev_k };
+
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            edits.push(Edit::Keep(x as usize, y as usize));
+        }
+        if d > 0 {
+            if x == prev_x {
+                edits.push(Edit::Insert((y - 1) as usize));
+            }
+            else {
+                edits.push(Edit::Remove((x - 1) as usize));
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    edits.reverse();
+    edits
+}
+
+/// Compute length of longest common subsequence with quadratic time, but
+/// linear memory, for sequences too different for `diff()`.
+///
+/// # Arguments
+/// * `a` - first sequence
+/// * `b` - second sequence
+fn lcs_len<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    let mut prev = vec![0usize; b.len() + 1];
+    let mut curr = vec![0usize; b.len() + 1];
+    for x in a.iter() {
+        for (j, y) in b.iter().enumerate() {
+            curr[j + 1] = if x == y { prev[j] + 1 } else { curr[j].max(prev[j + 1]) };
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev[b.len()]
+}
+
+/// Fetch runtime bytecode of contract, failing if there is none.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+fn fetch_code(chain: &crate::backend::Chain, address: &str) -> Result<Vec<u8>, String> {
+    let code = explorer::get_code(chain, address)?;
+    if code.is_empty() {
+        let err_msg = format!("Error no contract code at {}", address);
+        return Err(err_msg);
+    }
+    Ok(code)
+}
+
+/// Print line of instruction in diff.
+///
+/// # Arguments
+/// * `sign` - sign of line e.g. `+`
+/// * `instruction` - instruction
+fn print_line(sign: char, instruction: &Instruction) {
+    println!("{} {:#06x} {}", sign, instruction.offset, instruction.text());
+}
+
+/// Print changed instructions along with a few unchanged ones around them.
+///
+/// # Arguments
+/// * `edits` - edit script
+/// * `a` - instructions of the first contract
+/// * `b` - instructions of the second contract
+fn print_diff(edits: &[Edit], a: &[Instruction], b: &[Instruction]) {
+    let changed: Vec<usize> = edits.iter().enumerate().filter(|(_, e)| !matches!(e, Edit::Keep(_, _))).map(|(i, _)| i).collect();
+    let is_shown = |i: usize| changed.iter().any(|&c| c.abs_diff(i) <= CONTEXT_LINES);
+
+    let mut last_shown: Option<usize> = None;
+    for (i, edit) in edits.iter().enumerate() {
+        if !is_shown(i) {
+            continue;
+        }
+        if matches!(last_shown, Some(last) if last + 1 != i) {
+            println!("...");
+        }
+        match *edit {
+            Edit::Keep(x, _) => print_line(' ', &a[x]),
+            Edit::Remove(x) => print_line('-', &a[x]),
+            Edit::Insert(y) => print_line('+', &b[y]),
+        }
+        last_shown = Some(i);
+    }
+}
+
+/// Execute `bytecode-diff` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &BytecodeDiffArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.chain)?;
+    let code_a = fetch_code(chain, &args.address_a)?;
+    let code_b = fetch_code(chain, &args.address_b)?;
+
+    // metadata hash differs across builds of the same source e.g. by path
+    let stripped_a = bytecode::strip_metadata(&code_a);
+    let stripped_b = bytecode::strip_metadata(&code_b);
+    println!("a: {} ({} bytes, {} without metadata)", args.address_a, code_a.len(), stripped_a.len());
+    println!("b: {} ({} bytes, {} without metadata)", args.address_b, code_b.len(), stripped_b.len());
+
+    if stripped_a == stripped_b {
+        if code_a == code_b {
+            println!("identical");
+        }
+        else {
+            println!("identical (metadata differs)");
+        }
+        return Ok(());
+    }
+
+    let instructions_a = bytecode::disassemble(stripped_a);
+    let instructions_b = bytecode::disassemble(stripped_b);
+    let keys_a: Vec<(u8, &[u8])> = instructions_a.iter().map(|i| (i.opcode, i.data.as_slice())).collect();
+    let keys_b: Vec<(u8, &[u8])> = instructions_b.iter().map(|i| (i.opcode, i.data.as_slice())).collect();
+
+    let edits = diff(&keys_a, &keys_b, MAX_EDIT_DISTANCE);
+    let common = match edits.as_ref() {
+        Some(edits) => edits.iter().filter(|e| matches!(e, Edit::Keep(_, _))).count(),
+        None => lcs_len(&keys_a, &keys_b),
+    };
+    let similarity = 200.0 * common as f64 / (keys_a.len() + keys_b.len()) as f64;
+    let verdict = if similarity >= SIMILAR_THRESHOLD { "similar" } else { "different" };
+    println!("{} ({:.2}% of instructions in common)", verdict, similarity);
+
+    if args.opcodes {
+        match edits.as_ref() {
+            Some(edits) => print_diff(edits, &instructions_a, &instructions_b),
+            None => println!("Too many differences to show opcode-level diff"),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,827 @@
+//! Cache of verification metadata of contracts, so API platform is asked for
+//! source code of each contract only once.
+//!
+//! Local cache at `--cache-dir` holds responses of `getsourcecode` as
+//! `<chain>/<address>.json`. Only responses of verified contracts are cached
+//! as unverified ones may be verified later. With `--remote-cache`, a team
+//! shares one pool of fetched contracts over HTTP in the same layout; local
+//! misses are read through from it, and responses from API platform are
+//! written back with `PUT` authorized by bearer token in environment variable
+//! `TRACPLS_REMOTE_CACHE_TOKEN` if set. Without token, remote cache is read
+//! only.
+//!
+//! Public name tags, and labels of addresses (see `explorer::get_name_tag`)
+//! are kept in index `tags.json` at root of local cache, so `search` finds
+//! contracts by them too.
+//!
+//! Remote cache is best effort; failing to reach it is warned about, and
+//! fetching goes on. Its responses are checked the same way as ones of API
+//! platform before cached locally. Local entries are written to temporary
+//! file then renamed, so concurrent runs sharing the cache never read a
+//! partially written one.
+//!
+//! `cache export`, and `cache import` move local cache between machines as
+//! tar bundle along with index of its entries, and index of name tags which is
+//! merged into existing one on import. Bundle is compressed by
+//! extension with external `zstd`, `gzip`, or `xz` e.g. `bundle.tar.zst`. It's
+//! streamed through compressor entry by entry, so cache of a big crawl isn't
+//! held in memory.
+
+use crate::explorer::{self, NameTag};
+use crate::transport::{self, HttpRequest};
+use clap::{Args, Subcommand};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Environment variable holding token to write into remote cache
+const REMOTE_TOKEN_ENV: &str = "TRACPLS_REMOTE_CACHE_TOKEN";
+
+/// Filename of index of entries inside bundle
+const INDEX_FILENAME: &str = "index.json";
+
+/// Filename of index of name tags at root of local cache
+const NAME_TAGS_FILENAME: &str = "tags.json";
+
+/// Name of directory of index of `search` at root of local cache (see
+/// `search_index`), which isn't a directory of entries of chain
+pub const SEARCH_INDEX_DIRNAME: &str = "search-index";
+
+/// Number of temporary files of entries created so far, so threads writing
+/// the same entry don't share one
+static TEMP_FILES: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Args)]
+pub struct CacheArgs {
+    #[clap(subcommand)]
+    pub action: CacheAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CacheAction {
+    /// Bundle local cache at --cache-dir into a tar file, compressed by its extension e.g. '.tar.zst'
+    #[clap(name="export")]
+    Export(BundleArgs),
+
+    /// Add contracts of bundle made by 'cache export' into local cache at --cache-dir
+    #[clap(name="import")]
+    Import(BundleArgs),
+}
+
+/// Arguments shared by export, and import.
+#[derive(Debug, Args)]
+pub struct BundleArgs {
+    /// Path of bundle. Extension '.zst', '.gz', or '.xz' compresses it with
+    /// zstd, gzip, or xz which has to be installed.
+    #[clap(value_name="BUNDLE", required=true)]
+    pub path: String,
+}
+
+/// Settings of cache.
+#[derive(Debug, Clone, Default)]
+pub struct CacheSettings {
+    /// Directory of local cache
+    pub dir: String,
+
+    /// Base url of remote cache
+    pub remote_url: Option<String>,
+}
+
+/// Cache settings applied to all requests, or `None` if caching is disabled
+static CACHE: Mutex<Option<CacheSettings>> = Mutex::new(None);
+
+/// Enable, or disable cache.
+///
+/// # Arguments
+/// * `settings` - cache settings, or `None` to disable
+pub fn set_cache(settings: Option<CacheSettings>) {
+    if let Ok(mut current) = CACHE.lock() {
+        *current = settings;
+    }
+}
+
+/// Get cache settings if caching is enabled.
+pub fn settings() -> Option<CacheSettings> {
+    CACHE.lock().ok().and_then(|c| c.clone())
+}
+
+/// Get address of contract if request is for its verification metadata, and
+/// so is cacheable.
+///
+/// # Arguments
+/// * `params` - query parameters as pairs of key and value
+pub fn cacheable_address<'a>(params: &[(&str, &'a str)]) -> Option<&'a str> {
+    let param = |name: &str| params.iter().find(|(key, _)| *key == name).map(|(_, value)| *value);
+    if param("module") != Some("contract") || param("action") != Some("getsourcecode") {
+        return None;
+    }
+    param("address")
+}
+
+/// Get path of cache entry relative to root of cache.
+///
+/// # Arguments
+/// * `chain` - name of chain
+/// * `address` - contract address
+pub fn entry_path(chain: &str, address: &str) -> String {
+    crate::source::safe_relative_path(&format!("{}/{}.json", chain, address.to_lowercase()))
+}
+
+/// Read index of name tags keyed by `<chain>/<address>`. Missing index is
+/// empty.
+///
+/// # Arguments
+/// * `dir` - directory of local cache
+pub fn name_tags(dir: &str) -> Result<BTreeMap<String, NameTag>, String> {
+    let path = crate::combine_two_path_components(dir, NAME_TAGS_FILENAME)?;
+    let content = match std::fs::read_to_string(&path) {
+        Ok(res) => res,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(e) => {
+            let err_msg = format!("Error reading index of name tags at '{}'; err={}", path, e);
+            return Err(err_msg);
+        }
+    };
+    match serde_json::from_str(&content) {
+        Ok(res) => Ok(res),
+        Err(e) => {
+            let err_msg = format!("Error parsing index of name tags at '{}'; err={}", path, e);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Get cached name tag of address, or `None` if it's not asked for yet.
+///
+/// # Arguments
+/// * `dir` - directory of local cache
+/// * `chain` - name of chain
+/// * `address` - address
+pub fn cached_name_tag(dir: &str, chain: &str, address: &str) -> Option<NameTag> {
+    name_tags(dir).ok()?.remove(&format!("{}/{}", chain, address.to_lowercase()))
+}
+
+/// Add name tag of address into index while holding lock against other
+/// processes.
+///
+/// # Arguments
+/// * `dir` - directory of local cache
+/// * `chain` - name of chain
+/// * `address` - address
+/// * `tag` - name tag, empty if explorer has none
+pub fn store_name_tag(dir: &str, chain: &str, address: &str, tag: &NameTag) -> Result<(), String> {
+    let _lock = crate::lock::DirLock::acquire(dir)?;
+    let mut tags = name_tags(dir)?;
+    tags.insert(format!("{}/{}", chain, address.to_lowercase()), tag.clone());
+    write_name_tags(dir, &tags)
+}
+
+/// Merge name tags e.g. of bundle into index while holding lock against other
+/// processes. Empty ones don't replace ones explorer gave before. Returns
+/// number of name tags added, or changed.
+///
+/// # Arguments
+/// * `dir` - directory of local cache
+/// * `merged` - name tags keyed by `<chain>/<address>`
+pub fn merge_name_tags(dir: &str, merged: BTreeMap<String, NameTag>) -> Result<usize, String> {
+    let _lock = crate::lock::DirLock::acquire(dir)?;
+    let mut tags = name_tags(dir)?;
+    let mut changed = 0;
+    for (key, tag) in merged {
+        let kept = tags.get(&key).is_some_and(|t| *t == tag || (tag.is_empty() && !t.is_empty()));
+        if !kept {
+            tags.insert(key, tag);
+            changed += 1;
+        }
+    }
+    if changed == 0 {
+        return Ok(0);
+    }
+    write_name_tags(dir, &tags)?;
+    Ok(changed)
+}
+
+/// Write index of name tags.
+///
+/// # Arguments
+/// * `dir` - directory of local cache
+/// * `tags` - name tags keyed by `<chain>/<address>`
+fn write_name_tags(dir: &str, tags: &BTreeMap<String, NameTag>) -> Result<(), String> {
+    let content = match serde_json::to_string_pretty(tags) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error serializing index of name tags; err={}", e);
+            return Err(err_msg);
+        }
+    };
+    crate::write_file(&crate::combine_two_path_components(dir, NAME_TAGS_FILENAME)?, &format!("{}\n", content))
+}
+
+/// Read cache entry from remote cache.
+///
+/// # Arguments
+/// * `url` - url of entry
+fn remote_get(url: &str) -> Result<Option<String>, String> {
+    let response = match transport::send(&HttpRequest::new("GET", url)) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error reading remote cache at '{}'; err={}", url, e);
+            return Err(err_msg);
+        }
+    };
+    if response.status == 404 {
+        return Ok(None);
+    }
+    if !response.is_success() {
+        let err_msg = format!("Error reading remote cache at '{}'; status={}", url, response.status);
+        return Err(err_msg);
+    }
+    Ok(Some(response.body))
+}
+
+/// Write cache entry into remote cache.
+///
+/// # Arguments
+/// * `url` - url of entry
+/// * `token` - bearer token authorizing write
+/// * `body` - content of entry
+fn remote_put(url: &str, token: &str, body: &str) -> Result<(), String> {
+    let request = HttpRequest::new("PUT", url)
+        .header("Authorization", &format!("Bearer {}", token))
+        .header("Content-Type", "application/json")
+        .body(body.to_owned());
+    let response = match transport::send(&request) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error writing remote cache at '{}'; err={}", url, e);
+            return Err(err_msg);
+        }
+    };
+    if !response.is_success() {
+        let err_msg = format!("Error writing remote cache at '{}'; status={}", url, response.status);
+        return Err(err_msg);
+    }
+    Ok(())
+}
+
+/// Write entry, or index into local cache atomically; into temporary file
+/// next to it first, then renamed over it.
+///
+/// # Arguments
+/// * `path` - path of entry
+/// * `body` - content of entry
+pub fn write_entry(path: &str, body: &str) -> Result<(), String> {
+    crate::create_intermediate_dirs(path)?;
+    let temp_path = format!("{}.tmp.{}.{}", path, std::process::id(), TEMP_FILES.fetch_add(1, Ordering::Relaxed));
+    crate::write_file(&temp_path, body)?;
+    if let Err(e) = std::fs::rename(crate::safe_path::fs_path(&temp_path), crate::safe_path::fs_path(path)) {
+        let _ = std::fs::remove_file(crate::safe_path::fs_path(&temp_path));
+        let err_msg = format!("Error writing cache entry at '{}'; err={}", path, e);
+        return Err(err_msg);
+    }
+    Ok(())
+}
+
+/// Get verification metadata response of contract from cache, or from API
+/// platform by `fetch` then cache it if contract is verified.
+///
+/// # Arguments
+/// * `settings` - cache settings
+/// * `chain` - name of chain
+/// * `address` - contract address
+/// * `fetch` - function to get response from API platform
+pub fn read_through<F: FnOnce() -> Result<String, String>>(settings: &CacheSettings, chain: &str, address: &str, fetch: F) -> Result<String, String> {
+    let relative_path = entry_path(chain, address);
+    let local_path = crate::combine_two_path_components(&settings.dir, &relative_path)?;
+    if let Ok(body) = std::fs::read_to_string(&local_path) {
+        crate::perf::record_cache(true);
+        return Ok(body);
+    }
+
+    let remote_url = settings.remote_url.as_ref().map(|url| format!("{}/{}", url.trim_end_matches('/'), relative_path));
+    if let Some(url) = remote_url.as_ref() {
+        match remote_get(url) {
+            Ok(Some(body)) if explorer::parse_source_meta_raw(address, &body).is_ok_and(|raw| raw.is_verified()) => {
+                crate::perf::record_cache(true);
+                write_entry(&local_path, &body)?;
+                return Ok(body);
+            },
+            Ok(Some(_)) => eprintln!("Warning: ignoring entry of remote cache at '{}' as it's not verified source code of {}", url, address),
+            Ok(None) => (),
+            Err(e) => eprintln!("Warning: {}", e),
+        }
+    }
+
+    crate::perf::record_cache(false);
+    let body = fetch()?;
+    if !explorer::parse_source_meta_raw(address, &body).is_ok_and(|raw| raw.is_verified()) {
+        return Ok(body);
+    }
+    write_entry(&local_path, &body)?;
+    if let (Some(url), Ok(token)) = (remote_url.as_ref(), std::env::var(REMOTE_TOKEN_ENV)) {
+        if let Err(e) = remote_put(url, &token, &body) {
+            eprintln!("Warning: {}", e);
+        }
+    }
+    Ok(body)
+}
+
+/// Get external compressor of bundle from its extension, or `None` if bundle
+/// is plain tar.
+///
+/// # Arguments
+/// * `path` - path of bundle
+fn compressor_of(path: &str) -> Option<&'static str> {
+    let lower = path.to_lowercase();
+    if lower.ends_with(".zst") {
+        Some("zstd")
+    }
+    else if lower.ends_with(".gz") {
+        Some("gzip")
+    }
+    else if lower.ends_with(".xz") {
+        Some("xz")
+    }
+    else {
+        None
+    }
+}
+
+/// Spawn external compressor of bundle streaming from stdin to stdout.
+///
+/// # Arguments
+/// * `program` - compressor e.g. `zstd`
+/// * `decompress` - whether to decompress instead
+/// * `stdin` - where to read from
+/// * `stdout` - where to write into
+fn spawn_compressor(program: &str, decompress: bool, stdin: Stdio, stdout: Stdio) -> Result<Child, String> {
+    let args: &[&str] = if decompress { &["-d", "-c"] } else { &["-c"] };
+    match Command::new(program).args(args).stdin(stdin).stdout(stdout).stderr(Stdio::piped()).spawn() {
+        Ok(res) => Ok(res),
+        Err(e) => {
+            let err_msg = format!("Error executing {}, make sure it is installed, or use plain '.tar' bundle; err={}", program, e);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Wait for external compressor to exit, and check it succeeded.
+///
+/// # Arguments
+/// * `program` - compressor e.g. `zstd`
+/// * `child` - process of compressor
+fn wait_compressor(program: &str, child: Child) -> Result<(), String> {
+    let output = match child.wait_with_output() {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error executing {}; err={}", program, e);
+            return Err(err_msg);
+        }
+    };
+    if !output.status.success() {
+        let err_msg = format!("Error {} failed; stderr={}", program, String::from_utf8_lossy(&output.stderr).trim());
+        return Err(err_msg);
+    }
+    Ok(())
+}
+
+/// Entry of local cache.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CachedEntry {
+    pub chain: String,
+    pub address: String,
+
+    /// Path relative to cache directory e.g. `bsc/0xabc.json`
+    pub relative_path: String,
+
+    pub full_path: std::path::PathBuf,
+}
+
+impl CachedEntry {
+    /// Read cached response of API platform.
+    pub fn read(&self) -> Result<String, String> {
+        match std::fs::read_to_string(&self.full_path) {
+            Ok(res) => Ok(res),
+            Err(e) => {
+                let err_msg = format!("Error reading cache entry '{}'; err={}", self.full_path.display(), e);
+                Err(err_msg)
+            }
+        }
+    }
+}
+
+/// Get entries of local cache sorted by path.
+///
+/// # Arguments
+/// * `dir` - directory of local cache
+pub fn cached_entries(dir: &str) -> Result<Vec<CachedEntry>, String> {
+    let read_dir = |path: &std::path::Path| match std::fs::read_dir(path) {
+        Ok(res) => Ok(res.filter_map(|e| e.ok()).collect::<Vec<std::fs::DirEntry>>()),
+        Err(e) => {
+            let err_msg = format!("Error reading cache directory '{}'; err={}", path.display(), e);
+            Err(err_msg)
+        }
+    };
+
+    let mut entries = Vec::new();
+    let is_chain_dir = |e: &std::fs::DirEntry| e.file_type().is_ok_and(|t| t.is_dir()) && e.file_name() != SEARCH_INDEX_DIRNAME;
+    for chain_dir in read_dir(std::path::Path::new(dir))?.into_iter().filter(is_chain_dir) {
+        for file in read_dir(&chain_dir.path())?.into_iter().filter(|e| e.file_type().is_ok_and(|t| t.is_file())) {
+            let chain = chain_dir.file_name().to_string_lossy().into_owned();
+            let filename = file.file_name().to_string_lossy().into_owned();
+            if let Some(address) = filename.strip_suffix(".json") {
+                entries.push(CachedEntry {
+                    relative_path: format!("{}/{}", chain, filename),
+                    address: address.to_owned(),
+                    chain,
+                    full_path: file.path(),
+                });
+            }
+        }
+    }
+    entries.sort();
+    Ok(entries)
+}
+
+/// Write bundle of entries of local cache, and its index of name tags as tar
+/// archive. Index goes first, so entries are checked against it as they're
+/// read on import.
+///
+/// # Arguments
+/// * `writer` - writer of bundle
+/// * `entries` - entries of local cache
+/// * `tags` - content of index of name tags, empty if there is none
+fn write_bundle<W: Write>(writer: W, entries: &[CachedEntry], tags: &str) -> Result<(), String> {
+    // read twice not to hold whole cache in memory; once for index, then
+    // for archive
+    let mut index: Vec<Value> = Vec::new();
+    for entry in entries.iter() {
+        let content = entry.read()?;
+        let cached_at = std::fs::metadata(&entry.full_path).and_then(|m| m.modified()).ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_secs());
+        index.push(json!({
+            "chain": entry.chain,
+            "address": entry.address,
+            "path": entry.relative_path,
+            "keccak256": crate::abi::to_hex(&crate::abi::keccak256(content.as_bytes())),
+            "size": content.len(),
+            "cachedAt": cached_at,
+        }));
+    }
+
+    let mut tar = crate::archive::TarWriter::new(writer)?;
+    let mut index = json!({ "version": 1, "entries": index });
+    if !tags.is_empty() {
+        index["nameTags"] = json!({
+            "path": NAME_TAGS_FILENAME,
+            "keccak256": crate::abi::to_hex(&crate::abi::keccak256(tags.as_bytes())),
+            "size": tags.len(),
+        });
+    }
+    tar.append(INDEX_FILENAME, format!("{:#}\n", index).as_bytes())?;
+    if !tags.is_empty() {
+        tar.append(NAME_TAGS_FILENAME, tags.as_bytes())?;
+    }
+    for (entry, indexed) in entries.iter().zip(index["entries"].as_array().into_iter().flatten()) {
+        let content = entry.read()?;
+        if indexed["keccak256"].as_str() != Some(crate::abi::to_hex(&crate::abi::keccak256(content.as_bytes())).as_str()) {
+            let err_msg = format!("Error cache entry '{}' changed while exporting, try again", entry.full_path.display());
+            return Err(err_msg);
+        }
+        tar.append(&entry.relative_path, content.as_bytes())?;
+    }
+    tar.finish()?;
+    Ok(())
+}
+
+/// Bundle local cache into file, streamed through compressor if any.
+/// Returns number of exported entries, and name tags.
+///
+/// # Arguments
+/// * `dir` - directory of local cache
+/// * `path` - path of bundle
+fn export_bundle(dir: &str, path: &str) -> Result<(usize, usize), String> {
+    let entries = cached_entries(dir)?;
+    // read as a whole while holding lock, so it's not of half way merging
+    let tags = {
+        let _lock = crate::lock::DirLock::acquire(dir)?;
+        let tags = name_tags(dir)?;
+        if tags.is_empty() {
+            (String::new(), 0)
+        }
+        else {
+            match serde_json::to_string_pretty(&tags) {
+                Ok(res) => (format!("{}\n", res), tags.len()),
+                Err(e) => {
+                    let err_msg = format!("Error serializing index of name tags; err={}", e);
+                    return Err(err_msg);
+                }
+            }
+        }
+    };
+    crate::create_intermediate_dirs(path)?;
+    let file = match std::fs::File::create(crate::safe_path::fs_path(path)) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error writing bundle at '{}'; err={}", path, e);
+            return Err(err_msg);
+        }
+    };
+
+    let written = match compressor_of(path) {
+        Some(program) => match spawn_compressor(program, false, Stdio::piped(), Stdio::from(file)) {
+            Ok(mut child) => {
+                // stdin is closed once written so compressor finishes
+                let written = match child.stdin.take() {
+                    Some(stdin) => write_bundle(BufWriter::new(stdin), &entries, &tags.0),
+                    None => Err(format!("Error opening stdin of {}", program)),
+                };
+                // failure of compressor is the cause of failing to write into it
+                wait_compressor(program, child).and(written)
+            },
+            Err(e) => Err(e),
+        },
+        None => write_bundle(BufWriter::new(file), &entries, &tags.0),
+    };
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(crate::safe_path::fs_path(path));
+        return Err(e);
+    }
+    Ok((entries.len(), tags.1))
+}
+
+/// Add entry of bundle into local cache unless it's already cached. Returns
+/// whether or not it's added.
+///
+/// # Arguments
+/// * `dir` - directory of local cache
+/// * `entries` - entries of index of bundle
+/// * `relative_path` - path of entry relative to cache directory
+/// * `content` - content of entry
+fn import_entry(dir: &str, entries: &[Value], relative_path: &str, content: &str) -> Result<bool, String> {
+    let entry = match entries.iter().find(|e| e["path"].as_str() == Some(relative_path)) {
+        Some(res) => res,
+        None => {
+            let err_msg = format!("Error '{}' of bundle is not listed in its index", relative_path);
+            return Err(err_msg);
+        }
+    };
+    let hash = crate::abi::to_hex(&crate::abi::keccak256(content.as_bytes()));
+    if entry["keccak256"].as_str() != Some(hash.as_str()) {
+        let err_msg = format!("Error '{}' of bundle doesn't match its hash in index", relative_path);
+        return Err(err_msg);
+    }
+
+    let local_path = crate::combine_two_path_components(dir, &crate::source::safe_relative_path(relative_path))?;
+    if std::fs::read_to_string(&local_path).is_ok_and(|c| c == content) {
+        return Ok(false);
+    }
+    write_entry(&local_path, content)?;
+
+    // keep time it was originally cached at
+    if let Some(secs) = entry["cachedAt"].as_u64().filter(|s| *s > 0) {
+        if let Ok(file) = std::fs::File::options().write(true).open(&local_path) {
+            let _ = file.set_modified(UNIX_EPOCH + Duration::from_secs(secs));
+        }
+    }
+    Ok(true)
+}
+
+/// Merge index of name tags of bundle into one of local cache. Returns number
+/// of name tags added, or changed.
+///
+/// # Arguments
+/// * `dir` - directory of local cache
+/// * `index` - index of bundle
+/// * `content` - content of index of name tags of bundle
+fn import_name_tags(dir: &str, index: &Value, content: &str) -> Result<usize, String> {
+    let hash = crate::abi::to_hex(&crate::abi::keccak256(content.as_bytes()));
+    if index["nameTags"]["keccak256"].as_str() != Some(hash.as_str()) {
+        let err_msg = format!("Error '{}' of bundle doesn't match its hash in index", NAME_TAGS_FILENAME);
+        return Err(err_msg);
+    }
+    match serde_json::from_str(content) {
+        Ok(res) => merge_name_tags(dir, res),
+        Err(e) => {
+            let err_msg = format!("Error parsing '{}' of bundle; err={}", NAME_TAGS_FILENAME, e);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Read bundle from tar archive, adding its entries into local cache one by
+/// one, and merging its name tags. Returns number of added entries, ones
+/// already cached, and name tags added, or changed.
+///
+/// # Arguments
+/// * `dir` - directory of local cache
+/// * `path` - path of bundle
+/// * `reader` - reader of bundle
+fn read_bundle<R: Read>(dir: &str, path: &str, reader: R) -> Result<(usize, usize, usize), String> {
+    let mut tar = crate::archive::TarReader::new(reader);
+    let mut index: Option<Value> = None;
+    // entries before index e.g. in bundle of earlier version with files
+    // sorted by path, held until index is read
+    let mut pending: Vec<(String, String)> = Vec::new();
+    let mut added = 0;
+    let mut unchanged = 0;
+    let mut tags = 0;
+    while let Some((relative_path, content)) = tar.next_file()? {
+        if relative_path == INDEX_FILENAME {
+            index = match serde_json::from_str(&content) {
+                Ok(res) => Some(res),
+                Err(e) => {
+                    let err_msg = format!("Error parsing index of bundle '{}'; err={}", path, e);
+                    return Err(err_msg);
+                }
+            };
+        }
+        else {
+            pending.push((relative_path, content));
+        }
+
+        if let Some(index) = index.as_ref() {
+            let empty = Vec::new();
+            let entries = index["entries"].as_array().unwrap_or(&empty);
+            for (relative_path, content) in pending.drain(..) {
+                if relative_path == NAME_TAGS_FILENAME {
+                    tags += import_name_tags(dir, index, &content)?;
+                }
+                else if import_entry(dir, entries, &relative_path, &content)? {
+                    added += 1;
+                }
+                else {
+                    unchanged += 1;
+                }
+            }
+        }
+    }
+
+    if index.is_none() {
+        let err_msg = format!("Error '{}' is not a bundle made by 'cache export'; {} is missing", path, INDEX_FILENAME);
+        return Err(err_msg);
+    }
+    Ok((added, unchanged, tags))
+}
+
+/// Add entries, and name tags of bundle into local cache, streamed through
+/// decompressor if any. Returns number of added entries, ones already cached,
+/// and name tags added, or changed.
+///
+/// # Arguments
+/// * `dir` - directory of local cache
+/// * `path` - path of bundle
+fn import_bundle(dir: &str, path: &str) -> Result<(usize, usize, usize), String> {
+    let file = match std::fs::File::open(crate::safe_path::fs_path(path)) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error reading bundle at '{}'; err={}", path, e);
+            return Err(err_msg);
+        }
+    };
+
+    let program = match compressor_of(path) {
+        Some(res) => res,
+        None => return read_bundle(dir, path, BufReader::new(file)),
+    };
+    let mut child = spawn_compressor(program, true, Stdio::from(file), Stdio::piped())?;
+    let read = match child.stdout.take() {
+        Some(stdout) => read_bundle(dir, path, BufReader::new(stdout)),
+        None => Err(format!("Error opening stdout of {}", program)),
+    };
+    match (read, wait_compressor(program, child)) {
+        (Ok(res), Ok(_)) => Ok(res),
+        (Err(e), Ok(_)) | (Ok(_), Err(e)) => Err(e),
+        // bundle read is broken as decompressing it failed
+        (Err(e), Err(cause)) => Err(format!("{}; {}", e, cause)),
+    }
+}
+
+/// Execute `cache` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &CacheArgs) -> Result<(), String> {
+    let dir = match settings() {
+        Some(res) => res.dir,
+        None => return Err("Error --cache-dir is required to locate local cache".to_owned()),
+    };
+
+    match &args.action {
+        CacheAction::Export(args) => {
+            let (count, tags) = export_bundle(&dir, &args.path)?;
+            eprintln!("Exported {} contract(s), and {} name tag(s) to {}", count, tags, args.path);
+        },
+        CacheAction::Import(args) => {
+            let (added, unchanged, tags) = import_bundle(&dir, &args.path)?;
+            eprintln!("Imported {} contract(s), {} already cached, and {} name tag(s)", added, unchanged, tags);
+        },
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::TempWorkspace;
+
+    /// Write entries into local cache.
+    fn write_entries(dir: &str, entries: &[(&str, &str)]) {
+        for (relative_path, content) in entries {
+            write_entry(&crate::combine_two_path_components(dir, relative_path).unwrap(), content).unwrap();
+        }
+    }
+
+    /// Build tar archive of files in order given.
+    fn tar_of(files: &[(String, String)]) -> Vec<u8> {
+        let mut tar = crate::archive::TarWriter::new(Vec::new()).unwrap();
+        for (path, content) in files {
+            tar.append(path, content.as_bytes()).unwrap();
+        }
+        tar.finish().unwrap()
+    }
+
+    /// Read entries of local cache as pairs of relative path and content.
+    fn read_entries(dir: &str) -> Vec<(String, String)> {
+        cached_entries(dir).unwrap().into_iter().map(|e| (e.relative_path.clone(), e.read().unwrap())).collect()
+    }
+
+    #[test]
+    fn bundle_round_trips_entries() {
+        let from = TempWorkspace::new("test-cache").unwrap();
+        let to = TempWorkspace::new("test-cache").unwrap();
+        write_entries(from.path_str(), &[("bsc/0xaa.json", "{\"a\":1}"), ("ethereum/0xbb.json", "{\"b\":2}"), ("polygon/0xcc.json", "{}")]);
+        write_entries(to.path_str(), &[("ethereum/0xbb.json", "{\"b\":2}")]);
+
+        let mut bundles = vec!["bundle.tar"];
+        if Command::new("gzip").arg("--version").output().is_ok() {
+            bundles.push("bundle.tar.gz");
+        }
+        for (i, name) in bundles.into_iter().enumerate() {
+            let bundle = from.path().join(name).to_string_lossy().into_owned();
+            assert_eq!(export_bundle(from.path_str(), &bundle).unwrap(), (3, 0));
+            let counts = import_bundle(to.path_str(), &bundle).unwrap();
+            assert_eq!(counts, if i == 0 { (2, 1, 0) } else { (0, 3, 0) }, "{}", name);
+            assert_eq!(read_entries(to.path_str()), read_entries(from.path_str()));
+        }
+    }
+
+    #[test]
+    fn bundle_is_streamed_with_index_first() {
+        let from = TempWorkspace::new("test-cache").unwrap();
+        write_entries(from.path_str(), &[("bsc/0xaa.json", "{\"a\":1}"), ("polygon/0xcc.json", "{}")]);
+        let bundle = from.path().join("bundle.tar").to_string_lossy().into_owned();
+        export_bundle(from.path_str(), &bundle).unwrap();
+
+        let files = crate::archive::read_tar(&std::fs::read(&bundle).unwrap()).unwrap();
+        let paths: Vec<&str> = files.iter().map(|(p, _)| p.as_str()).collect();
+        assert_eq!(paths, vec![INDEX_FILENAME, "bsc/0xaa.json", "polygon/0xcc.json"]);
+
+        // bundle of files sorted by path with index in between is read too
+        let mut sorted = files.clone();
+        sorted.sort();
+        let to = TempWorkspace::new("test-cache").unwrap();
+        assert_eq!(read_bundle(to.path_str(), "sorted.tar", &tar_of(&sorted)[..]).unwrap(), (2, 0, 0));
+        assert_eq!(read_entries(to.path_str()), read_entries(from.path_str()));
+
+        // content not matching index is rejected
+        let mut tampered = files;
+        tampered[1].1 = "{\"a\":2}".to_owned();
+        let err = read_bundle(to.path_str(), "tampered.tar", &tar_of(&tampered)[..]).unwrap_err();
+        assert!(err.contains("doesn't match its hash"), "{}", err);
+    }
+
+    #[test]
+    fn bundle_round_trips_name_tags() {
+        let tag = |name: &str, labels: &[&str]| NameTag { name_tag: name.to_owned(), labels: labels.iter().map(|l| l.to_string()).collect() };
+        let from = TempWorkspace::new("test-cache").unwrap();
+        let to = TempWorkspace::new("test-cache").unwrap();
+        write_entries(from.path_str(), &[("bsc/0xaa.json", "{}")]);
+        store_name_tag(from.path_str(), "bsc", "0xAA", &tag("Binance: Hot Wallet", &["Exchange"])).unwrap();
+        store_name_tag(from.path_str(), "bsc", "0xbb", &tag("PancakeSwap: Router", &[])).unwrap();
+        store_name_tag(from.path_str(), "bsc", "0xcc", &NameTag::default()).unwrap();
+
+        // ones of local cache not in bundle are kept, and empty ones of bundle
+        // don't replace ones explorer gave
+        store_name_tag(to.path_str(), "bsc", "0xcc", &tag("Kept", &[])).unwrap();
+        store_name_tag(to.path_str(), "ethereum", "0xdd", &tag("Local", &["Only"])).unwrap();
+        store_name_tag(to.path_str(), "bsc", "0xbb", &tag("Stale", &[])).unwrap();
+
+        let bundle = from.path().join("bundle.tar").to_string_lossy().into_owned();
+        assert_eq!(export_bundle(from.path_str(), &bundle).unwrap(), (1, 3));
+        assert_eq!(import_bundle(to.path_str(), &bundle).unwrap(), (1, 0, 2));
+        assert_eq!(import_bundle(to.path_str(), &bundle).unwrap(), (0, 1, 0));
+
+        let dir = to.path_str();
+        assert_eq!(cached_name_tag(dir, "bsc", "0xaa"), Some(tag("Binance: Hot Wallet", &["Exchange"])));
+        assert_eq!(cached_name_tag(dir, "bsc", "0xBB"), Some(tag("PancakeSwap: Router", &[])));
+        assert_eq!(cached_name_tag(dir, "bsc", "0xcc"), Some(tag("Kept", &[])));
+        assert_eq!(cached_name_tag(dir, "ethereum", "0xdd"), Some(tag("Local", &["Only"])));
+        assert_eq!(cached_name_tag(dir, "bsc", "0xee"), None);
+    }
+}
@@ -0,0 +1,270 @@
+//! Call trace of transaction as tree of frames, one per call, or creation.
+//!
+//! Trace comes from `debug_traceTransaction` with `callTracer` of node at
+//! `ETH_RPC_URL` when it serves the chain, and supports debug namespace.
+//! Otherwise it is pieced together from the transaction, and its internal
+//! transactions listed by API platform, which lack input, and output of
+//! internal calls, and may leave out calls without value on some platforms.
+
+use crate::abi;
+use crate::backend::Chain;
+use crate::explorer;
+use crate::foundry;
+use serde_json::{json, Value};
+
+/// Frame of call trace.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// Kind of frame e.g. `CALL`, `STATICCALL`, `DELEGATECALL`, `CREATE`,
+    /// `CREATE2`, or `SELFDESTRUCT`
+    pub kind: String,
+
+    pub from: String,
+
+    /// Address called, or created
+    pub to: String,
+
+    /// Value sent in wei as decimal string
+    pub value: String,
+
+    /// Input of call, or empty if not known
+    pub input: Vec<u8>,
+
+    /// Return, or revert data of call, or empty if not known
+    pub output: Vec<u8>,
+
+    /// Gas given to frame
+    pub gas: u64,
+
+    /// Gas used by frame including its subcalls
+    pub gas_used: u64,
+
+    /// Error of frame e.g. `execution reverted`, or `None` if it succeeded
+    pub error: Option<String>,
+
+    pub calls: Vec<Frame>,
+}
+
+impl Frame {
+    /// Visit frame and all of its subcalls depth-first along with their depth.
+    ///
+    /// # Arguments
+    /// * `depth` - depth of this frame
+    /// * `visit` - closure called with each frame, and its depth
+    pub fn walk<'a>(&'a self, depth: usize, visit: &mut dyn FnMut(&'a Frame, usize)) {
+        visit(self, depth);
+        for call in self.calls.iter() {
+            call.walk(depth + 1, visit);
+        }
+    }
+}
+
+/// Call trace of transaction.
+#[derive(Debug, Clone)]
+pub struct Trace {
+    /// Frame of transaction itself
+    pub root: Frame,
+
+    /// Whether trace came from `debug_traceTransaction` of node, thus
+    /// complete, rather than internal transactions of API platform
+    pub from_node: bool,
+
+    /// Addresses, and storage slots in access list of transaction
+    pub access_list: Vec<(String, Vec<String>)>,
+}
+
+/// Parse JSON-RPC quantity e.g. `0x5208`, or decimal string into integer.
+///
+/// # Arguments
+/// * `value` - quantity
+fn parse_quantity(value: &Value) -> u64 {
+    match value.as_str() {
+        Some(text) if text.starts_with("0x") => u64::from_str_radix(&text[2..], 16).unwrap_or(0),
+        Some(text) => text.parse().unwrap_or(0),
+        None => value.as_u64().unwrap_or(0),
+    }
+}
+
+/// Convert JSON-RPC quantity, or decimal string into decimal string.
+///
+/// # Arguments
+/// * `value` - quantity
+fn to_decimal(value: &Value) -> String {
+    match value.as_str() {
+        Some(text) if text.starts_with("0x") => {
+            let hex = &text[2..];
+            let padded = if hex.len() % 2 == 1 { format!("0{}", hex) } else { hex.to_owned() };
+            abi::from_hex(&padded).map_or("0".to_owned(), |b| abi::to_decimal(&b))
+        },
+        Some(text) if !text.is_empty() => text.to_owned(),
+        _ => "0".to_owned(),
+    }
+}
+
+/// Parse hex data, or empty if it's missing or malformed.
+///
+/// # Arguments
+/// * `value` - hex string
+fn to_bytes(value: &Value) -> Vec<u8> {
+    value.as_str().and_then(|t| abi::from_hex(t).ok()).unwrap_or_default()
+}
+
+/// Parse frame of `callTracer` along with its subcalls.
+///
+/// # Arguments
+/// * `frame` - frame as of `callTracer`
+fn parse_frame(frame: &Value) -> Frame {
+    Frame {
+        kind: frame["type"].as_str().unwrap_or("CALL").to_uppercase(),
+        from: frame["from"].as_str().unwrap_or("").to_lowercase(),
+        to: frame["to"].as_str().unwrap_or("").to_lowercase(),
+        value: to_decimal(&frame["value"]),
+        input: to_bytes(&frame["input"]),
+        output: to_bytes(&frame["output"]),
+        gas: parse_quantity(&frame["gas"]),
+        gas_used: parse_quantity(&frame["gasUsed"]),
+        error: frame["error"].as_str().map(|e| e.to_owned()),
+        calls: frame["calls"].as_array().map_or_else(Vec::new, |calls| calls.iter().map(parse_frame).collect()),
+    }
+}
+
+/// Trace transaction by `debug_traceTransaction` of node. Returns `None` if
+/// no node serving the chain is set, or it doesn't support tracing.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `tx_hash` - transaction hash
+fn trace_by_node(chain: &Chain, tx_hash: &str) -> Option<Frame> {
+    let response = match foundry::request(chain, "debug_traceTransaction", json!([tx_hash, { "tracer": "callTracer" }]))? {
+        Ok(res) => res,
+        Err(e) => {
+            eprintln!("Warning: {}; using internal transactions of API platform instead", e);
+            return None;
+        }
+    };
+    if !response["result"].is_object() {
+        eprintln!("Warning: {} can't trace transaction; error={}; using internal transactions of API platform instead", foundry::RPC_URL_ENV, response["error"]);
+        return None;
+    }
+    Some(parse_frame(&response["result"]))
+}
+
+/// Piece trace of transaction together from internal transactions listed by
+/// API platform, nesting them by their trace id e.g. `0_1`.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `tx_hash` - transaction hash
+/// * `root` - frame of transaction itself
+fn trace_by_explorer(chain: &Chain, tx_hash: &str, mut root: Frame) -> Result<Frame, String> {
+    let result = explorer::query(chain, &[
+        ("module", "account"),
+        ("action", "txlistinternal"),
+        ("txhash", tx_hash),
+    ])?;
+    let txs = match result.as_array() {
+        Some(res) => res,
+        None => {
+            let err_msg = format!("Error unexpected response for internal transactions of {}; result={}", tx_hash, result);
+            return Err(err_msg);
+        }
+    };
+
+    let field = |tx: &Value, name: &str| tx[name].as_str().unwrap_or("").to_owned();
+    let mut entries: Vec<(Vec<usize>, Frame)> = txs.iter().enumerate().map(|(i, tx)| {
+        // creation has empty `to` but has `contractAddress`
+        let to = if field(tx, "to").is_empty() { field(tx, "contractAddress") } else { field(tx, "to") };
+        let kind = match field(tx, "callType") {
+            call_type if !call_type.is_empty() => call_type,
+            _ => field(tx, "type"),
+        };
+        let path: Vec<usize> = match tx["traceId"].as_str().filter(|t| !t.is_empty()) {
+            Some(trace_id) => trace_id.split('_').filter_map(|n| n.parse().ok()).collect(),
+            None => vec![i],
+        };
+        let frame = Frame {
+            kind: if kind.is_empty() { "CALL".to_owned() } else { kind.to_uppercase() },
+            from: field(tx, "from").to_lowercase(),
+            to: to.to_lowercase(),
+            value: to_decimal(&tx["value"]),
+            input: to_bytes(&tx["input"]),
+            output: Vec::new(),
+            gas: parse_quantity(&tx["gas"]),
+            gas_used: parse_quantity(&tx["gasUsed"]),
+            error: if field(tx, "isError") == "1" { Some(field(tx, "errCode")).filter(|e| !e.is_empty()).or_else(|| Some("failed".to_owned())) } else { None },
+            calls: Vec::new(),
+        };
+        (path, frame)
+    }).collect();
+
+    // in order of trace id, each frame follows its parent, so frames on stack
+    // are ancestors of the next one until it isn't prefixed by their trace id
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut stack: Vec<(Vec<usize>, Frame)> = Vec::new();
+    let attach = |stack: &mut Vec<(Vec<usize>, Frame)>, root: &mut Frame| {
+        if let Some((_, frame)) = stack.pop() {
+            match stack.last_mut() {
+                Some((_, parent)) => parent.calls.push(frame),
+                None => root.calls.push(frame),
+            }
+        }
+    };
+    for (path, frame) in entries {
+        while stack.last().is_some_and(|(p, _)| !path.starts_with(p)) {
+            attach(&mut stack, &mut root);
+        }
+        stack.push((path, frame));
+    }
+    while !stack.is_empty() {
+        attach(&mut stack, &mut root);
+    }
+    Ok(root)
+}
+
+/// Get call trace of transaction.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `tx_hash` - transaction hash
+pub fn get_trace(chain: &Chain, tx_hash: &str) -> Result<Trace, String> {
+    let tx = explorer::query(chain, &[
+        ("module", "proxy"),
+        ("action", "eth_getTransactionByHash"),
+        ("txhash", tx_hash),
+    ])?;
+    if !tx.is_object() {
+        let err_msg = format!("Error transaction {} not found on {}", tx_hash, chain.name);
+        return Err(err_msg);
+    }
+    let access_list: Vec<(String, Vec<String>)> = tx["accessList"].as_array().map_or_else(Vec::new, |entries| entries.iter()
+        .map(|e| (
+            e["address"].as_str().unwrap_or("").to_lowercase(),
+            e["storageKeys"].as_array().map_or_else(Vec::new, |keys| keys.iter().filter_map(|k| k.as_str().map(|k| k.to_owned())).collect()),
+        ))
+        .collect());
+
+    if let Some(root) = trace_by_node(chain, tx_hash) {
+        return Ok(Trace { root, from_node: true, access_list });
+    }
+
+    let receipt = explorer::query(chain, &[
+        ("module", "proxy"),
+        ("action", "eth_getTransactionReceipt"),
+        ("txhash", tx_hash),
+    ])?;
+    let created = receipt["contractAddress"].as_str().unwrap_or("").to_lowercase();
+    let root = Frame {
+        kind: if tx["to"].is_null() { "CREATE".to_owned() } else { "CALL".to_owned() },
+        from: tx["from"].as_str().unwrap_or("").to_lowercase(),
+        to: tx["to"].as_str().map_or(created, |t| t.to_lowercase()),
+        value: to_decimal(&tx["value"]),
+        input: to_bytes(&tx["input"]),
+        output: Vec::new(),
+        gas: parse_quantity(&tx["gas"]),
+        gas_used: parse_quantity(&receipt["gasUsed"]),
+        error: if receipt["status"].as_str() == Some("0x0") { Some("execution reverted".to_owned()) } else { None },
+        calls: Vec::new(),
+    };
+    let root = trace_by_explorer(chain, tx_hash, root)?;
+    Ok(Trace { root, from_node: false, access_list })
+}
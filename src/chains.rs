@@ -0,0 +1,237 @@
+//! Registry of EVM chains beyond built-in ones so they can be selected by
+//! name, or chain id without configuring their explorers by hand.
+//!
+//! Registry is embedded at build time, and can be extended with `chains
+//! --refresh` which derives entries from chainlist into
+//! `~/.config/tracpls/chains.json`. Embedded entries win over derived ones
+//! as their API urls are known to work, and entries of manifest of endpoints
+//! (see `backend`) win over both.
+
+use crate::backend::{self, ManifestEntry};
+use crate::transport::{self, HttpRequest};
+use clap::Args;
+use serde_json::{json, Value};
+
+/// Registry embedded at build time
+const EMBEDDED: &str = include_str!("chains.json");
+
+/// Url of chainlist to refresh registry from
+const CHAINLIST_URL: &str = "https://chainid.network/chains.json";
+
+#[derive(Debug, Args)]
+pub struct ChainsArgs {
+    /// Download chainlist, and derive registry of chains from explorers listed
+    /// there into '~/.config/tracpls/chains.json' before listing. Url can be
+    /// overridden by environment variable TRACPLS_CHAINLIST_URL.
+    #[clap(long="refresh", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub refresh: bool,
+
+    /// Output as JSON instead of human readable table
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+}
+
+/// Get path of registry derived from chainlist, or `None` if home directory
+/// isn't known.
+fn cache_path() -> Option<String> {
+    let home = std::env::var("HOME").ok()?;
+    crate::combine_two_path_components(&home, ".config/tracpls/chains.json").ok()
+}
+
+/// Parse registry of chains.
+///
+/// # Arguments
+/// * `content` - registry as JSON text
+/// * `source` - where registry came from, for error message
+fn parse_registry(content: &str, source: &str) -> Result<Vec<ManifestEntry>, String> {
+    match serde_json::from_str(content) {
+        Ok(res) => Ok(res),
+        Err(e) => {
+            let err_msg = format!("Error parsing registry of chains at '{}'; err={}", source, e);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Register chains of embedded registry, and one derived from chainlist if
+/// refreshed before. Chains whose name, or chain id is already registered are
+/// left as they are.
+pub fn register_registry() -> Result<(), String> {
+    let mut entries = parse_registry(EMBEDDED, "embedded")?;
+    if let Some(path) = cache_path().filter(|p| std::path::Path::new(p).exists()) {
+        let content = match std::fs::read_to_string(&path) {
+            Ok(res) => res,
+            Err(e) => {
+                let err_msg = format!("Error reading registry of chains at '{}'; err={}", path, e);
+                return Err(err_msg);
+            }
+        };
+        entries.extend(parse_registry(&content, &path)?);
+    }
+
+    let mut registering: Vec<ManifestEntry> = Vec::new();
+    for entry in entries {
+        let taken_id = entry.chain_id.is_some_and(|id| backend::find_by_id(id).is_some() || registering.iter().any(|e| e.chain_id == Some(id)));
+        let taken_name = backend::find(&entry.chain).is_some() || registering.iter().any(|e| e.chain.eq_ignore_ascii_case(&entry.chain));
+        if !taken_id && !taken_name {
+            registering.push(entry);
+        }
+    }
+    backend::register_entries(registering);
+    Ok(())
+}
+
+/// Turn text into lowercase name joined by `-` e.g. `Arbitrum Nova` into
+/// `arbitrum-nova`.
+///
+/// # Arguments
+/// * `text` - text
+fn slug(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-")
+}
+
+/// Derive registry entry of chain from its chainlist entry, or `None` if
+/// none of its explorers is known to serve etherscan compatible API.
+///
+/// Etherscan family serves API at `api.<domain>`, or `api-<network>.<domain>`
+/// for explorer at `<network>.<domain>`; Blockscout serves it at `/api` of
+/// explorer.
+///
+/// # Arguments
+/// * `chain` - chainlist entry
+fn derive_entry(chain: &Value) -> Option<ManifestEntry> {
+    let chain_id = chain["chainId"].as_u64()?;
+    let name = slug(chain["name"].as_str()?);
+    let native_symbol = chain["nativeCurrency"]["symbol"].as_str().map(|s| s.to_owned());
+
+    for explorer in chain["explorers"].as_array()?.iter() {
+        let url = explorer["url"].as_str().unwrap_or("").trim_end_matches('/');
+        let host = url.trim_start_matches("https://").trim_start_matches("http://").trim_start_matches("www.");
+        if host.is_empty() || host.contains('/') {
+            continue;
+        }
+
+        let labels: Vec<&str> = host.split('.').collect();
+        if explorer["name"].as_str().unwrap_or("").to_lowercase().contains("blockscout") {
+            return Some(ManifestEntry {
+                chain: name,
+                chain_id: Some(chain_id),
+                name: Some("blockscout".to_owned()),
+                url: format!("{}/api", url),
+                apikey_env: None,
+                native_symbol,
+                rate_limit: None,
+                cooldown_secs: None,
+                breaker_threshold: None,
+                breaker_cooldown_secs: None,
+                fallback: None,
+                failover: None,
+            });
+        }
+        if labels.len() >= 2 && labels[labels.len() - 2].ends_with("scan") {
+            let domain = labels[labels.len() - 2..].join(".");
+            let api_host = match labels.len() {
+                2 => format!("api.{}", domain),
+                _ => format!("api-{}.{}", labels[..labels.len() - 2].join("-"), domain),
+            };
+            let backend_name = labels[labels.len() - 2].to_owned();
+            return Some(ManifestEntry {
+                chain: name,
+                chain_id: Some(chain_id),
+                apikey_env: Some(format!("TRACPLS_{}_APIKEY", backend_name.to_uppercase())),
+                name: Some(backend_name),
+                url: format!("https://{}/api", api_host),
+                native_symbol,
+                rate_limit: None,
+                cooldown_secs: None,
+                breaker_threshold: None,
+                breaker_cooldown_secs: None,
+                fallback: None,
+                failover: None,
+            });
+        }
+    }
+    None
+}
+
+/// Download chainlist, and write registry derived from it. Returns number of
+/// chains derived.
+fn refresh() -> Result<usize, String> {
+    let url = std::env::var("TRACPLS_CHAINLIST_URL").unwrap_or_else(|_| CHAINLIST_URL.to_owned());
+    let response = match transport::send(&HttpRequest::new("GET", &url)) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error downloading chainlist from '{}'; err={}", url, e);
+            return Err(err_msg);
+        }
+    };
+    if !response.is_success() {
+        let err_msg = format!("Error downloading chainlist from '{}'; status={}", url, response.status);
+        return Err(err_msg);
+    }
+
+    let chains: Vec<Value> = match serde_json::from_str(&response.body) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error parsing chainlist from '{}'; err={}", url, e);
+            return Err(err_msg);
+        }
+    };
+    let entries: Vec<ManifestEntry> = chains.iter().filter_map(derive_entry).collect();
+
+    let path = match cache_path() {
+        Some(res) => res,
+        None => return Err("Error HOME is not set to write registry of chains into".to_owned()),
+    };
+    let content = match serde_json::to_string_pretty(&entries) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error serializing registry of chains to JSON; err={}", e);
+            return Err(err_msg);
+        }
+    };
+    crate::create_intermediate_dirs(&path)?;
+    crate::write_file(&path, &format!("{}\n", content))?;
+    Ok(entries.len())
+}
+
+/// Execute `chains` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &ChainsArgs) -> Result<(), String> {
+    if args.refresh {
+        let count = refresh()?;
+        eprintln!("{} chain(s) derived from chainlist", count);
+        register_registry()?;
+    }
+
+    let chains = backend::chains();
+    if args.json {
+        let out: Vec<Value> = chains.iter().map(|c| json!({
+            "chain": c.name,
+            "chainId": c.chain_id,
+            "nativeSymbol": c.native_symbol,
+            "explorer": c.backend.name(),
+        })).collect();
+        match serde_json::to_string_pretty(&out) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing chains to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+        return Ok(());
+    }
+
+    println!("{:<28} {:>10} {:<8} EXPLORER", "CHAIN", "CHAIN ID", "NATIVE");
+    for chain in chains.iter() {
+        let chain_id = chain.chain_id.map_or("-".to_owned(), |id| id.to_string());
+        println!("{:<28} {:>10} {:<8} {}", chain.name, chain_id, chain.native_symbol, chain.backend.name());
+    }
+    Ok(())
+}
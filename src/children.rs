@@ -0,0 +1,144 @@
+//! `children` command: list contracts created by a factory e.g. pairs of DEX,
+//! or markets of lending protocol, and optionally fetch verified ones.
+//!
+//! Children are found from internal transactions of factory, along with
+//! contracts its transactions deploy directly when it's an account. With
+//! `--out-dir`, source code of each verified child is written to
+//! `<out-dir>/<address>/`.
+
+use crate::display;
+use crate::explorer::{self, Created};
+use crate::fetch;
+use crate::progress;
+use crate::TargetArgs;
+use clap::Args;
+use serde_json::{json, Value};
+
+#[derive(Debug, Args)]
+pub struct ChildrenArgs {
+    #[clap(flatten)]
+    pub target: TargetArgs,
+
+    /// Block number to look for children created from
+    #[clap(long="start-block", required=false)]
+    pub start_block: Option<u64>,
+
+    /// List only children with verified source code along with their names
+    #[clap(long="verified-only", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub verified_only: bool,
+
+    /// Fetch source code of verified children into this directory
+    #[clap(long="out-dir", short='o', required=false)]
+    pub out_dir: Option<String>,
+
+    /// Output as JSON instead of human readable table
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+}
+
+/// Child contract along with its name if checked for verification.
+struct Child {
+    created: Created,
+
+    /// Name of verified contract, empty if not verified, or `None` if not
+    /// checked
+    name: Option<String>,
+
+    /// Number of files fetched, if fetched
+    files: Option<usize>,
+}
+
+/// Execute `children` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &ChildrenArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.target.chain)?;
+    let factory = args.target.address.to_lowercase();
+    let options = fetch::FetchOptions {
+        abi_only: false,
+        abi_pretty_print: true,
+        clean_crlf: true,
+        layout: crate::layout::Layout::Flat,
+        project_files: true,
+        artifact_format: None,
+        order: crate::source::Order::Path,
+        format: None,
+        canonical: false,
+        raw_response: false,
+        interfaces_only: false,
+        prune_unreachable: false,
+        contract: None,
+    };
+
+    let created = explorer::get_created(chain, &factory, args.start_block.unwrap_or(0))?;
+    let check = args.verified_only || args.out_dir.is_some();
+    let mut children: Vec<Child> = Vec::new();
+    let mut failed = 0;
+    for c in created {
+        if !check {
+            children.push(Child { created: c, name: None, files: None });
+            continue;
+        }
+        match fetch::check_verified(chain, &c.address, args.out_dir.as_ref(), &options) {
+            Ok((name, files)) => {
+                if let Some(count) = files.filter(|_| !args.json) {
+                    eprintln!("fetched {} {} file(s) of {}", name, count, c.address);
+                }
+                if !args.verified_only || !name.is_empty() {
+                    children.push(Child { created: c, name: Some(name), files });
+                }
+            },
+            Err(e) => {
+                // failed children are reported via exit code, see `progress::tally()`
+                failed += 1;
+                progress::error(Some(&c.address), &e);
+                eprintln!("{} failed; {}", c.address, e);
+            }
+        }
+    }
+
+    if args.json {
+        let out: Vec<Value> = children.iter().map(|c| json!({
+            "address": c.created.address,
+            "kind": c.created.kind,
+            "blockNumber": c.created.block_number,
+            "time": display::format_timestamp(&c.created.timestamp.to_string()),
+            "txHash": c.created.tx_hash,
+            "verified": c.name.as_ref().map(|n| !n.is_empty()),
+            "name": c.name.as_ref().filter(|n| !n.is_empty()),
+            "files": c.files,
+        })).collect();
+        match serde_json::to_string_pretty(&out) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing children to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+        return Ok(());
+    }
+
+    if children.is_empty() {
+        println!("No children of {} found", factory);
+        return Ok(());
+    }
+    println!("{:<42} {:<8} {:<10} {:<19} NAME", "ADDRESS", "KIND", "BLOCK", "TIME (UTC)");
+    for c in children.iter() {
+        let name = match c.name.as_deref() {
+            Some("") => "(not verified)",
+            Some(name) => name,
+            None => "-",
+        };
+        println!("{:<42} {:<8} {:<10} {:<19} {}",
+            c.created.address, c.created.kind, c.created.block_number, display::format_timestamp(&c.created.timestamp.to_string()), name);
+    }
+    let fetched = children.iter().filter(|c| c.files.is_some()).count();
+    if args.out_dir.is_some() {
+        println!("\nchildren: {}, fetched: {}, failed: {}", children.len(), fetched, failed);
+    }
+    else {
+        println!("\nchildren: {}, failed: {}", children.len(), failed);
+    }
+    Ok(())
+}
@@ -0,0 +1,247 @@
+//! `clipwatch` command: watch clipboard for copied addresses, and summarize
+//! each in one line; contract name, whether verified, implementation if
+//! proxy, and public name tag. Pressing Enter fetches source code of the last
+//! one shown, along with its implementation.
+//!
+//! Chain of address is taken from what was copied along with it; explorer
+//! link e.g. `https://bscscan.com/address/0x...`, chain prefix e.g.
+//! `bsc:0x...`, or CAIP-10 e.g. `eip155:56:0x...`, otherwise `--chain`.
+//!
+//! Clipboard is read by `pbpaste` on macOS, PowerShell on Windows, and
+//! `wl-paste`, `xclip`, or `xsel` elsewhere, or `--paste-command`.
+
+use crate::addresses;
+use crate::backend::Chain;
+use crate::enrich::{self, Summary};
+use crate::fetch;
+use clap::Args;
+use std::io::BufRead;
+use std::process::Command;
+use std::sync::mpsc;
+
+/// Hosts of explorer websites, and chain ids of chains they serve
+const EXPLORER_HOSTS: [(&str, u64); 17] = [
+    ("sepolia.etherscan.io", 11155111),
+    ("holesky.etherscan.io", 17000),
+    ("optimistic.etherscan.io", 10),
+    ("etherscan.io", 1),
+    ("testnet.bscscan.com", 97),
+    ("bscscan.com", 56),
+    ("polygonscan.com", 137),
+    ("nova.arbiscan.io", 42170),
+    ("arbiscan.io", 42161),
+    ("basescan.org", 8453),
+    ("snowtrace.io", 43114),
+    ("ftmscan.com", 250),
+    ("gnosisscan.io", 100),
+    ("lineascan.build", 59144),
+    ("scrollscan.com", 534352),
+    ("blastscan.io", 81457),
+    ("celoscan.io", 42220),
+];
+
+#[derive(Debug, Args)]
+pub struct ClipwatchArgs {
+    /// Chain of addresses copied without any hint of their chain, by name
+    /// e.g. 'bsc', 'ethereum', or chain id e.g. '56'
+    #[clap(long="chain", short='c', required=false, default_value="bsc")]
+    pub chain: String,
+
+    /// Directory to fetch source code into under `<out-dir>/<address>/`
+    #[clap(long="out-dir", short='o', required=false, default_value=".")]
+    pub out_dir: String,
+
+    /// Milliseconds to wait between reads of clipboard
+    #[clap(long="interval", required=false, default_value="500")]
+    pub interval: u64,
+
+    /// Command printing content of clipboard to stdout e.g.
+    /// 'xclip -o -selection clipboard'
+    #[clap(long="paste-command", required=false)]
+    pub paste_command: Option<String>,
+}
+
+/// Get commands which may print content of clipboard on this platform, in
+/// order to try.
+fn paste_commands() -> Vec<Vec<String>> {
+    let commands: Vec<&[&str]> = if cfg!(target_os = "macos") {
+        vec![&["pbpaste"]]
+    }
+    else if cfg!(windows) {
+        vec![&["powershell", "-NoProfile", "-Command", "Get-Clipboard"]]
+    }
+    else {
+        vec![&["wl-paste", "--no-newline"], &["xclip", "-o", "-selection", "clipboard"], &["xsel", "--clipboard", "--output"]]
+    };
+    commands.iter().map(|c| c.iter().map(|s| s.to_string()).collect()).collect()
+}
+
+/// Read content of clipboard.
+///
+/// # Arguments
+/// * `command` - command printing content of clipboard, and its arguments
+fn read_clipboard(command: &[String]) -> Result<String, String> {
+    let output = match Command::new(&command[0]).args(&command[1..]).output() {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error running '{}' to read clipboard; err={}", command.join(" "), e);
+            return Err(err_msg);
+        }
+    };
+    // empty clipboard is reported as failure by some tools
+    if !output.status.success() && !output.stdout.is_empty() {
+        let err_msg = format!("Error reading clipboard by '{}'; stderr={}", command.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+        return Err(err_msg);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Find command reading clipboard; given one, or the first one which works
+/// on this platform.
+///
+/// # Arguments
+/// * `paste_command` - value of `--paste-command`
+fn find_paste_command(paste_command: Option<&String>) -> Result<Vec<String>, String> {
+    if let Some(command) = paste_command {
+        let command: Vec<String> = command.split_whitespace().map(|s| s.to_owned()).collect();
+        if command.is_empty() {
+            return Err("Error --paste-command is empty".to_owned());
+        }
+        read_clipboard(&command)?;
+        return Ok(command);
+    }
+    match paste_commands().into_iter().find(|c| read_clipboard(c).is_ok()) {
+        Some(res) => Ok(res),
+        None => Err("Error no command to read clipboard found; install wl-clipboard, xclip, or xsel, or pass --paste-command".to_owned()),
+    }
+}
+
+/// Find the first address in copied text along with chain hinted by text
+/// around it, or `None` if text has no address.
+///
+/// # Arguments
+/// * `text` - content of clipboard
+/// * `default_chain` - chain if text hints none
+fn detect(text: &str, default_chain: &'static Chain) -> Option<(&'static Chain, String)> {
+    let (start, end) = addresses::address_spans(text).into_iter().next()?;
+    let address = text[start..end].to_lowercase();
+    let before = &text[..start];
+
+    // prefix e.g. `eip155:56:`, or `bsc:` right before address
+    let word_start = before.rfind(|c: char| c.is_whitespace() || c == '/' || c == '(' || c == '"' || c == '\'').map_or(0, |i| i + 1);
+    let prefix: Vec<&str> = before[word_start..].split(':').filter(|p| !p.is_empty()).collect();
+    let by_prefix = match prefix.as_slice() {
+        ["eip155", chain_id] => chain_id.parse().ok().and_then(crate::backend::find_by_id),
+        [name] => crate::parse_chain(name).ok(),
+        _ => None,
+    };
+    if let Some(chain) = by_prefix {
+        return Some((chain, address));
+    }
+
+    let lowered = before.to_lowercase();
+    let by_host = EXPLORER_HOSTS.iter()
+        .find(|(host, _)| lowered.contains(&format!("://{}/", host)) || lowered.contains(&format!("://www.{}/", host)))
+        .and_then(|(_, chain_id)| crate::backend::find_by_id(*chain_id));
+    Some((by_host.unwrap_or(default_chain), address))
+}
+
+/// Fetch source code of address, and of its implementation if proxy, into
+/// their own directories.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `summary` - what address is
+/// * `out_dir` - output directory
+fn fetch_summarized(chain: &Chain, summary: &Summary, out_dir: &str) -> Result<(), String> {
+    let options = fetch::FetchOptions {
+        abi_only: false,
+        abi_pretty_print: true,
+        clean_crlf: true,
+        layout: crate::layout::Layout::Flat,
+        project_files: true,
+        artifact_format: None,
+        order: crate::source::Order::Path,
+        format: None,
+        canonical: false,
+        raw_response: false,
+        interfaces_only: false,
+        prune_unreachable: false,
+        contract: None,
+    };
+    let mut targets = vec![summary.address.clone()];
+    if let Some((implementation, _)) = summary.implementation.as_ref() {
+        targets.push(implementation.clone());
+    }
+    for address in targets {
+        let contract_dir = crate::combine_two_path_components(out_dir, &address)?;
+        match fetch::fetch_verified_into(chain, &address, &contract_dir, &options)? {
+            Some((name, count)) => println!("fetched {} {} file(s) of {} into {}", name, count, address, contract_dir),
+            None => println!("{} is not verified; nothing to fetch", address),
+        }
+    }
+    Ok(())
+}
+
+/// Execute `clipwatch` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &ClipwatchArgs) -> Result<(), String> {
+    let default_chain = crate::parse_chain(&args.chain)?;
+    let command = find_paste_command(args.paste_command.as_ref())?;
+
+    // keys are read line by line on another thread so clipboard is polled meanwhile
+    let (sender, receiver) = mpsc::channel::<String>();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines().map_while(Result::ok) {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    println!("watching clipboard by '{}'; copy an address, press Enter to fetch the last one shown, or q then Enter to quit", command.join(" "));
+    let mut last_text = read_clipboard(&command).unwrap_or_default();
+    let mut current: Option<(&'static Chain, Summary)> = None;
+    let mut warned = false;
+    loop {
+        while let Ok(line) = receiver.try_recv() {
+            match (line.trim(), current.as_ref()) {
+                ("q", _) => return Ok(()),
+                ("", Some((chain, summary))) | ("f", Some((chain, summary))) => {
+                    if let Err(e) = fetch_summarized(chain, summary, &args.out_dir) {
+                        eprintln!("{}", e);
+                    }
+                },
+                ("", None) | ("f", None) => println!("no address copied yet"),
+                (_, _) => println!("press Enter to fetch the last address shown, or q then Enter to quit"),
+            }
+        }
+
+        match read_clipboard(&command) {
+            Ok(text) if text != last_text => {
+                if let Some((chain, address)) = detect(&text, default_chain) {
+                    match enrich::summarize(chain, &address) {
+                        Ok(summary) => {
+                            println!("{} {}: {}", chain.name, address, summary.describe());
+                            current = Some((chain, summary));
+                        },
+                        Err(e) => eprintln!("{} {}: {}", chain.name, address, e),
+                    }
+                }
+                last_text = text;
+                warned = false;
+            },
+            Ok(_) => (),
+            Err(e) => {
+                // clipboard may be briefly unavailable e.g. while being written
+                if !warned {
+                    eprintln!("Warning: {}", e);
+                    warned = true;
+                }
+            }
+        }
+        std::thread::sleep(std::time::Duration::from_millis(args.interval));
+    }
+}
@@ -0,0 +1,462 @@
+//! `decode` command: decode raw data pasted from elsewhere into human
+//! readable form.
+//!
+//! `decode revert` decodes revert data of failed call; `Error(string)` of
+//! `require`, and `revert`, `Panic(uint256)` of failed assertion, overflow,
+//! and the like, or custom error declared in ABI of contract if given, or
+//! known in the local database of selectors (see `selectors`).
+
+use crate::abi::{self, Param};
+use crate::selectors;
+use clap::{Args, Subcommand};
+use serde_json::{json, Value};
+
+/// Selector of `Error(string)`
+const ERROR_SELECTOR: &str = "0x08c379a0";
+
+/// Selector of `Panic(uint256)`
+const PANIC_SELECTOR: &str = "0x4e487b71";
+
+#[derive(Debug, Args)]
+pub struct DecodeArgs {
+    #[clap(subcommand)]
+    pub action: DecodeAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DecodeAction {
+    /// Decode revert data into Error(string), Panic(uint256), or custom error
+    #[clap(name="revert")]
+    Revert(DecodeRevertArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct DecodeRevertArgs {
+    /// Revert data as hex string e.g. '0x08c379a0...'
+    #[clap(value_name="DATA", required=true)]
+    pub data: String,
+
+    /// Contract which reverted, whose ABI declares custom errors
+    #[clap(long="address", short='a', required=false, requires="chain")]
+    pub address: Option<String>,
+
+    /// Chain of contract, by name, or chain id
+    #[clap(long="chain", short='c', required=false)]
+    pub chain: Option<String>,
+
+    /// Output as JSON instead of human readable text
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+}
+
+/// Get meaning of panic code as of Solidity documentation.
+///
+/// # Arguments
+/// * `code` - panic code
+fn panic_reason(code: u64) -> &'static str {
+    match code {
+        0x00 => "generic compiler inserted panic",
+        0x01 => "assertion failed",
+        0x11 => "arithmetic overflow, or underflow",
+        0x12 => "division, or modulo by zero",
+        0x21 => "conversion into invalid enum value",
+        0x22 => "incorrectly encoded storage byte array",
+        0x31 => "pop() on empty array",
+        0x32 => "array index out of bounds",
+        0x41 => "too much memory allocated, or array too large",
+        0x51 => "call to zero-initialized internal function",
+        _ => "unknown panic code",
+    }
+}
+
+/// Split parameter types of canonical signature e.g. `Foo(uint256,(address,bool))`
+/// at top-level commas.
+///
+/// # Arguments
+/// * `signature` - canonical signature
+pub fn signature_types(signature: &str) -> Vec<String> {
+    let inner = signature.split_once('(').map_or("", |(_, rest)| rest.strip_suffix(')').unwrap_or(rest));
+    let mut types = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    for c in inner.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                types.push(std::mem::take(&mut current));
+                continue;
+            },
+            _ => (),
+        }
+        current.push(c);
+    }
+    if !current.is_empty() {
+        types.push(current);
+    }
+    types
+}
+
+/// Split array type e.g. `uint256[2][]` into type of its elements, and its
+/// length if fixed. Returns `None` if it's not an array type.
+///
+/// # Arguments
+/// * `kind` - canonical type
+fn split_array(kind: &str) -> Option<(&str, Option<&str>)> {
+    let (element, len) = kind.strip_suffix(']')?.rsplit_once('[')?;
+    Some((element, (!len.is_empty()).then_some(len)))
+}
+
+/// Whether values of type are encoded in tail of ABI encoding, with only
+/// their offset in head.
+///
+/// # Arguments
+/// * `kind` - canonical type
+fn is_dynamic(kind: &str) -> bool {
+    match split_array(kind) {
+        Some((_, None)) => true,
+        Some((element, Some(_))) => is_dynamic(element),
+        None if kind.starts_with('(') => signature_types(kind).iter().any(|k| is_dynamic(k)),
+        None => kind == "string" || kind == "bytes",
+    }
+}
+
+/// Get size in bytes of value of type in head of ABI encoding; a word for
+/// value types, and offsets of dynamic types, or the whole encoding of
+/// static tuples, and fixed-size arrays. Returns `None` if it doesn't fit.
+///
+/// # Arguments
+/// * `kind` - canonical type
+fn head_size(kind: &str) -> Option<usize> {
+    if is_dynamic(kind) {
+        return Some(32);
+    }
+    match split_array(kind) {
+        Some((element, Some(len))) => len.parse::<usize>().ok()?.checked_mul(head_size(element)?),
+        _ if kind.starts_with('(') => signature_types(kind).iter().try_fold(0usize, |acc, k| acc.checked_add(head_size(k)?)),
+        _ => Some(32),
+    }
+}
+
+/// Decode ABI-encoded value in head at position into human readable form.
+/// Value types, `string`, and `bytes` are decoded. Static tuples, and
+/// fixed-size arrays are left as hex of their encoding in head, and other
+/// dynamic types as hex of their encoding in tail up to the end of data as
+/// its size isn't known without decoding it.
+///
+/// # Arguments
+/// * `kind` - canonical type
+/// * `data` - ABI-encoded values
+/// * `position` - position of value in head
+fn decode_at(kind: &str, data: &[u8], position: usize) -> Option<String> {
+    let is_composite = kind.starts_with('(') || kind.ends_with(']');
+    if !is_dynamic(kind) {
+        let size = head_size(kind)?;
+        let head = data.get(position..position.checked_add(size)?)?;
+        if is_composite {
+            return Some(format!("0x{}", abi::to_hex(head)));
+        }
+        return Some(abi::decode_word(kind, head));
+    }
+
+    let offset = abi::decode_offset(data.get(position..position.checked_add(32)?)?)?;
+    if is_composite {
+        return Some(format!("0x{}", abi::to_hex(data.get(offset..)?)));
+    }
+    let start = offset.checked_add(32)?;
+    let len = abi::decode_offset(data.get(offset..start)?)?;
+    let bytes = data.get(start..start.checked_add(len)?)?;
+    match kind {
+        "string" => Some(format!("{:?}", String::from_utf8_lossy(bytes))),
+        _ => Some(format!("0x{}", abi::to_hex(bytes))),
+    }
+}
+
+/// Decode ABI-encoded values of types into human readable form; see
+/// `decode_at()`. Values which can't be decoded e.g. as data is too short
+/// are `None`.
+///
+/// # Arguments
+/// * `kinds` - canonical types of values in order
+/// * `data` - ABI-encoded values e.g. parameters without selector
+pub fn decode_params<S: AsRef<str>>(kinds: &[S], data: &[u8]) -> Vec<Option<String>> {
+    let mut position = Some(0usize);
+    kinds.iter().map(|kind| {
+        let value = position.and_then(|p| decode_at(kind.as_ref(), data, p));
+        position = position.zip(head_size(kind.as_ref())).and_then(|(p, size)| p.checked_add(size));
+        value
+    }).collect()
+}
+
+/// Find custom error by selector in ABI of contract, or in the local database
+/// of selectors. Returns its signature, and parameters.
+///
+/// # Arguments
+/// * `selector` - error selector
+/// * `target` - contract which reverted, along with its chain
+fn find_error(selector: &str, target: Option<(&str, &str)>) -> Result<Option<(String, Vec<Param>)>, String> {
+    if let Some((address, chain)) = target {
+        let chain = crate::parse_chain(chain)?;
+        let contract_abi = abi::fetch_abi(chain, address)?;
+        let found = contract_abi.errors().find(|e| e.selector() == selector).map(|e| (e.signature(), e.inputs.clone()));
+        if found.is_some() {
+            return Ok(found);
+        }
+    }
+    Ok(selectors::lookup(selector).into_iter().next().map(|signature| {
+        let params = signature_types(&signature).into_iter().map(|kind| Param {
+            name: String::new(),
+            kind,
+            internal_type: None,
+            components: Vec::new(),
+            indexed: false,
+        }).collect();
+        (signature, params)
+    }))
+}
+
+/// Decoded revert data.
+pub struct Revert {
+    /// One of `empty`, `error`, `panic`, `custom`, or `unknown`
+    pub kind: &'static str,
+
+    /// Signature of error, empty if not known
+    pub signature: String,
+
+    /// Human readable message, empty for custom error
+    pub message: String,
+
+    /// Arguments of custom error as pairs of name, and value
+    pub args: Vec<(String, String)>,
+}
+
+/// Decode revert data.
+///
+/// # Arguments
+/// * `data` - revert data
+/// * `target` - contract which reverted, along with its chain
+pub fn decode_revert(data: &[u8], target: Option<(&str, &str)>) -> Result<Revert, String> {
+    if data.is_empty() {
+        return Ok(Revert {
+            kind: "empty",
+            signature: String::new(),
+            message: "reverted without reason e.g. by revert(), require() without message, or out of gas".to_owned(),
+            args: Vec::new(),
+        });
+    }
+    if data.len() < 4 {
+        let err_msg = format!("Error revert data of {} byte(s) is too short to hold a selector", data.len());
+        return Err(err_msg);
+    }
+
+    let selector = format!("0x{}", abi::to_hex(&data[..4]));
+    let body = &data[4..];
+    match selector.as_str() {
+        ERROR_SELECTOR => {
+            let message = decode_params(&["string"], body).remove(0).unwrap_or_else(|| format!("0x{}", abi::to_hex(body)));
+            Ok(Revert { kind: "error", signature: "Error(string)".to_owned(), message, args: Vec::new() })
+        },
+        PANIC_SELECTOR => {
+            let code = body.get(..32).map_or(0, |w| w[24..].iter().fold(0u64, |acc, b| (acc << 8) | *b as u64));
+            Ok(Revert { kind: "panic", signature: "Panic(uint256)".to_owned(), message: format!("{:#04x} {}", code, panic_reason(code)), args: Vec::new() })
+        },
+        _ => match find_error(&selector, target)? {
+            Some((signature, params)) => {
+                let kinds: Vec<String> = params.iter().map(|p| p.canonical_type()).collect();
+                let args = params.iter().zip(decode_params(&kinds, body)).enumerate().map(|(i, (p, value))| {
+                    let name = if p.name.is_empty() { format!("arg{}", i) } else { p.name.clone() };
+                    (name, value.unwrap_or_else(|| "<missing>".to_owned()))
+                }).collect();
+                Ok(Revert { kind: "custom", signature, message: String::new(), args })
+            },
+            None => Ok(Revert {
+                kind: "unknown",
+                signature: String::new(),
+                message: format!("unknown error selector {}; pass -a, and -c of the contract to decode its custom errors", selector),
+                args: Vec::new(),
+            }),
+        },
+    }
+}
+
+/// Execute `decode revert` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+fn run_revert(args: &DecodeRevertArgs) -> Result<(), String> {
+    let data = abi::from_hex(&args.data)?;
+    let address = args.address.as_ref().map(|a| a.to_lowercase());
+    let target = address.as_deref().zip(args.chain.as_deref());
+    let revert = decode_revert(&data, target)?;
+
+    if args.json {
+        let out = json!({
+            "kind": revert.kind,
+            "signature": revert.signature,
+            "message": revert.message,
+            "args": revert.args.iter().map(|(name, value)| json!({ "name": name, "value": value })).collect::<Vec<Value>>(),
+        });
+        match serde_json::to_string_pretty(&out) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing revert to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+        return Ok(());
+    }
+
+    match (revert.signature.is_empty(), revert.message.is_empty()) {
+        (true, _) => println!("{}", revert.message),
+        (false, true) => println!("{}", revert.signature),
+        (false, false) => println!("{}: {}", revert.signature, revert.message),
+    }
+    for (name, value) in revert.args.iter() {
+        println!("  {}: {}", name, value);
+    }
+    Ok(())
+}
+
+/// Execute `decode` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &DecodeArgs) -> Result<(), String> {
+    match &args.action {
+        DecodeAction::Revert(args) => run_revert(args),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// ABI-encoded word of number.
+    fn word(value: u64) -> Vec<u8> {
+        let mut word = vec![0u8; 24];
+        word.extend_from_slice(&value.to_be_bytes());
+        word
+    }
+
+    /// ABI-encoded tail of `string`, or `bytes`; length, and padded bytes.
+    fn tail(bytes: &[u8]) -> Vec<u8> {
+        let mut tail = word(bytes.len() as u64);
+        tail.extend_from_slice(bytes);
+        tail.resize(32 + bytes.len().div_ceil(32) * 32, 0);
+        tail
+    }
+
+    #[test]
+    fn decode_params_of_value_types() {
+        let data = [word(42), word(1), word(u64::MAX)].concat();
+        let values = decode_params(&["uint256", "bool", "int64"], &data);
+        assert_eq!(values, vec![Some("42".to_owned()), Some("true".to_owned()), Some("18446744073709551615".to_owned())]);
+    }
+
+    #[test]
+    fn decode_params_of_string_and_bytes() {
+        let data = [word(0x40), word(0x80), tail(b"hello"), tail(&[0xde, 0xad])].concat();
+        let values = decode_params(&["string", "bytes"], &data);
+        assert_eq!(values, vec![Some("\"hello\"".to_owned()), Some("0xdead".to_owned())]);
+    }
+
+    #[test]
+    fn decode_params_after_static_tuple_and_fixed_array() {
+        let data = [word(1), word(1), word(2), word(3), word(7)].concat();
+        let values = decode_params(&["(uint256,bool)", "uint8[2]", "uint256"], &data);
+        assert_eq!(values[0], Some(format!("0x{}", abi::to_hex(&[word(1), word(1)].concat()))));
+        assert_eq!(values[1], Some(format!("0x{}", abi::to_hex(&[word(2), word(3)].concat()))));
+        assert_eq!(values[2], Some("7".to_owned()));
+    }
+
+    #[test]
+    fn decode_params_of_dynamic_array_as_hex() {
+        let data = [word(0x40), word(5), word(1), word(9)].concat();
+        let values = decode_params(&["uint256[]", "uint256"], &data);
+        assert_eq!(values[0], Some(format!("0x{}", abi::to_hex(&[word(1), word(9)].concat()))));
+        assert_eq!(values[1], Some("5".to_owned()));
+    }
+
+    #[test]
+    fn head_size_of_types() {
+        assert!(is_dynamic("string"));
+        assert!(is_dynamic("uint256[]"));
+        assert!(is_dynamic("string[2]"));
+        assert!(is_dynamic("(uint256,bytes)"));
+        assert!(!is_dynamic("bytes32"));
+        assert!(!is_dynamic("(uint256,address)[3]"));
+        assert_eq!(head_size("(uint256,address)[3]"), Some(192));
+        assert_eq!(head_size("(uint256,bytes)"), Some(32));
+    }
+
+    #[test]
+    fn decode_params_of_malformed_offsets() {
+        // offset too large to be one
+        let data = [vec![0xff; 32]].concat();
+        assert_eq!(decode_params(&["string"], &data), vec![None]);
+
+        // offset, and length overflowing when added up
+        let data = [word(u64::MAX)].concat();
+        assert_eq!(decode_params(&["bytes"], &data), vec![None]);
+        let data = [word(0x20), word(u64::MAX)].concat();
+        assert_eq!(decode_params(&["bytes"], &data), vec![None]);
+
+        // offset past the end of data
+        let data = [word(0x200)].concat();
+        assert_eq!(decode_params(&["string"], &data), vec![None]);
+    }
+
+    #[test]
+    fn decode_params_of_missing_values() {
+        let data = word(1);
+        assert_eq!(decode_params(&["uint256", "address"], &data), vec![Some("1".to_owned()), None]);
+
+        // size of head overflowing
+        let values = decode_params(&["uint256[18446744073709551615]", "uint256"], &data);
+        assert_eq!(values, vec![None, None]);
+    }
+
+    #[test]
+    fn decode_revert_of_error() {
+        let data = [abi::from_hex(ERROR_SELECTOR).unwrap(), word(0x20), tail(b"Ownable: caller is not the owner")].concat();
+        let revert = decode_revert(&data, None).unwrap();
+        assert_eq!(revert.kind, "error");
+        assert_eq!(revert.signature, "Error(string)");
+        assert_eq!(revert.message, "\"Ownable: caller is not the owner\"");
+
+        // malformed message is left as hex
+        let data = [abi::from_hex(ERROR_SELECTOR).unwrap(), word(u64::MAX)].concat();
+        let revert = decode_revert(&data, None).unwrap();
+        assert_eq!(revert.message, format!("0x{}", abi::to_hex(&word(u64::MAX))));
+    }
+
+    #[test]
+    fn decode_revert_of_panic() {
+        let data = [abi::from_hex(PANIC_SELECTOR).unwrap(), word(0x11)].concat();
+        let revert = decode_revert(&data, None).unwrap();
+        assert_eq!(revert.kind, "panic");
+        assert_eq!(revert.signature, "Panic(uint256)");
+        assert_eq!(revert.message, "0x11 arithmetic overflow, or underflow");
+    }
+
+    #[test]
+    fn decode_revert_of_custom_error() {
+        selectors::index_abi(r#"[{"type":"error","name":"TracplsTestShortfall","inputs":[{"name":"needed","type":"uint256"},{"name":"reason","type":"string"}]}]"#);
+        let selector = abi::selector_of("TracplsTestShortfall(uint256,string)");
+        let data = [abi::from_hex(&selector).unwrap(), word(100), word(0x40), tail(b"low")].concat();
+        let revert = decode_revert(&data, None).unwrap();
+        assert_eq!(revert.kind, "custom");
+        assert_eq!(revert.signature, "TracplsTestShortfall(uint256,string)");
+        assert_eq!(revert.args, vec![("arg0".to_owned(), "100".to_owned()), ("arg1".to_owned(), "\"low\"".to_owned())]);
+
+        // arguments missing from data
+        let revert = decode_revert(&data[..36], None).unwrap();
+        assert_eq!(revert.args[1].1, "<missing>");
+    }
+
+    #[test]
+    fn decode_revert_of_empty_and_short_data() {
+        assert_eq!(decode_revert(&[], None).unwrap().kind, "empty");
+        assert!(decode_revert(&[0x08, 0xc3, 0x79], None).is_err());
+    }
+}
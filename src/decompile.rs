@@ -0,0 +1,222 @@
+//! `decompile` command: reconstruct pseudo-Solidity of unverified contract
+//! from its runtime bytecode by external decompiler heimdall-rs.
+//!
+//! Output is clearly marked as reconstructed, and functions are annotated
+//! with their selectors, along with signatures known for them in the local
+//! database of selectors (see `selectors`). If heimdall isn't installed, a
+//! skeleton interface of functions found in dispatcher of bytecode is
+//! printed instead.
+
+use crate::abi;
+use crate::bytecode;
+use crate::explorer;
+use crate::selectors;
+use crate::workspace::TempWorkspace;
+use crate::TargetArgs;
+use clap::Args;
+use std::process::Command;
+
+#[derive(Debug, Args)]
+pub struct DecompileArgs {
+    #[clap(flatten)]
+    pub target: TargetArgs,
+
+    /// Path of heimdall executable
+    #[clap(long="heimdall", required=false, default_value="heimdall")]
+    pub heimdall: String,
+
+    /// Decompile even if source code of contract is verified
+    #[clap(long="force", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub force: bool,
+
+    /// Filepath to write reconstructed source code to. If not specified, it
+    /// will be printed to stdout.
+    #[clap(long="out", short='o', required=false)]
+    pub out_path: Option<String>,
+}
+
+/// Find function selectors compared against in dispatcher of bytecode, in
+/// order of appearance; `PUSH4` immediately followed by `EQ`, or preceded by
+/// `DUP1` as solc emits.
+///
+/// # Arguments
+/// * `code` - runtime bytecode
+pub fn dispatcher_selectors(code: &[u8]) -> Vec<String> {
+    let instructions = bytecode::disassemble(bytecode::strip_metadata(code));
+    let mut found: Vec<String> = Vec::new();
+    for (i, ins) in instructions.iter().enumerate() {
+        if ins.opcode != 0x63 || ins.data.len() != 4 {
+            continue;
+        }
+        let compared = instructions.get(i + 1).is_some_and(|n| n.opcode == 0x14);
+        let duplicated = i > 0 && instructions[i - 1].opcode == 0x80;
+        let selector = format!("0x{}", abi::to_hex(&ins.data));
+        if (compared || duplicated) && !found.contains(&selector) {
+            found.push(selector);
+        }
+    }
+    found
+}
+
+/// Header marking output as reconstructed.
+///
+/// # Arguments
+/// * `address` - contract address
+/// * `chain` - chain name
+/// * `by` - what reconstructed it
+fn header(address: &str, chain: &str, by: &str) -> String {
+    format!("\
+// RECONSTRUCTED by {} from runtime bytecode of {} on {}.
+// This is NOT verified source code; names, types, and control flow are
+// approximations, and may be wrong.
+", by, address, chain)
+}
+
+/// Annotate reconstructed source code with selectors, and signatures known
+/// for them above each function either named after selector e.g.
+/// `Unresolved_a9059cbb`, or resolved to name of known signature.
+///
+/// # Arguments
+/// * `source` - reconstructed source code
+/// * `selectors` - selectors found in bytecode
+fn annotate(source: &str, selectors: &[String]) -> String {
+    let mut out = String::with_capacity(source.len());
+    for line in source.lines() {
+        if let Some(declared) = line.trim_start().strip_prefix("function ") {
+            let lower = line.to_lowercase();
+            let name = format!("{}(", declared.split('(').next().unwrap_or("").trim());
+            let selector = selectors.iter()
+                .find(|s| lower.contains(&s[2..]))
+                .or_else(|| selectors.iter().find(|s| selectors::lookup(s).iter().any(|sig| sig.starts_with(&name))));
+            if let Some(selector) = selector {
+                let indent = &line[..line.len() - line.trim_start().len()];
+                out.push_str(&format!("{}// selector {}{}\n", indent, selector, describe(selector)));
+            }
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// Describe signatures known for selector, or empty if none is known.
+///
+/// # Arguments
+/// * `selector` - function selector
+fn describe(selector: &str) -> String {
+    let signatures = selectors::lookup(selector);
+    if signatures.is_empty() {
+        String::new()
+    }
+    else {
+        format!(" is {}", signatures.join(", or "))
+    }
+}
+
+/// Build skeleton interface of functions found in dispatcher.
+///
+/// # Arguments
+/// * `selectors` - selectors found in bytecode
+fn skeleton(selectors: &[String]) -> String {
+    let mut out = String::from("interface Reconstructed {\n");
+    for selector in selectors.iter() {
+        let signatures = selectors::lookup(selector);
+        match signatures.first() {
+            Some(signature) => {
+                out.push_str(&format!("    // selector {}{}\n", selector, describe(selector)));
+                out.push_str(&format!("    function {} external;\n", signature));
+            },
+            None => {
+                out.push_str(&format!("    // selector {}, parameters unknown\n", selector));
+                out.push_str(&format!("    function Unresolved_{}() external;\n", &selector[2..]));
+            }
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Decompile bytecode by heimdall, and return reconstructed source code.
+///
+/// # Arguments
+/// * `heimdall` - path of heimdall executable
+/// * `code` - runtime bytecode
+fn run_heimdall(heimdall: &str, code: &[u8]) -> Result<String, std::io::Error> {
+    let workspace = TempWorkspace::new("decompile").map_err(std::io::Error::other)?;
+    let output = Command::new(heimdall)
+        .arg("decompile")
+        .arg(format!("0x{}", abi::to_hex(code)))
+        .args(["--include-sol", "--default", "--output"])
+        .arg(workspace.path())
+        .output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+        return Err(std::io::Error::other(format!("heimdall failed; stderr={}", stderr.trim())));
+    }
+    let found = find_sol(workspace.path()).ok_or_else(|| std::io::Error::other("heimdall wrote no .sol file"))?;
+    std::fs::read_to_string(found)
+}
+
+/// Find the first `.sol` file under directory, as heimdall nests output
+/// under directories of its own.
+///
+/// # Arguments
+/// * `dir` - directory
+fn find_sol(dir: &std::path::Path) -> Option<std::path::PathBuf> {
+    for entry in std::fs::read_dir(dir).ok()?.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if let Some(found) = find_sol(&path) {
+                return Some(found);
+            }
+        }
+        else if path.extension().is_some_and(|e| e == "sol") {
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// Execute `decompile` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &DecompileArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.target.chain)?;
+    let address = args.target.address.to_lowercase();
+
+    if !args.force {
+        let body = explorer::get_source_meta_text(chain, &address)?;
+        if explorer::parse_source_meta_raw(&address, &body)?.is_verified() {
+            let err_msg = format!("Error {} is verified; fetch its source code instead, or use --force to decompile anyway", address);
+            return Err(err_msg);
+        }
+    }
+
+    let code = explorer::get_code(chain, &address)?;
+    if code.is_empty() {
+        let err_msg = format!("Error {} has no code on {}", address, chain.name);
+        return Err(err_msg);
+    }
+    let selectors = dispatcher_selectors(&code);
+
+    let out = match run_heimdall(&args.heimdall, &code) {
+        Ok(source) => format!("{}\n{}", header(&address, &chain.name, "heimdall"), annotate(&source, &selectors)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            eprintln!("Warning: heimdall is not installed at '{}'; printing only functions found in dispatcher", args.heimdall);
+            format!("{}\n{}", header(&address, &chain.name, "tracpls"), skeleton(&selectors))
+        },
+        Err(e) => {
+            let err_msg = format!("Error decompiling {}; err={}", address, e);
+            return Err(err_msg);
+        }
+    };
+
+    match args.out_path.as_ref() {
+        Some(path) => crate::write_file(path, &out),
+        None => {
+            print!("{}", out);
+            Ok(())
+        }
+    }
+}
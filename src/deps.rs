@@ -0,0 +1,193 @@
+//! Recursive fetch of contracts referenced by the target contract e.g.
+//! immutable addresses, constructor-configured components, and hardcoded
+//! addresses in source code. Each verified one is written into
+//! `<out-dir>/deps/<address>/` next to the target contract, up to a depth.
+//!
+//! Linked libraries are part of the build, so they are always fetched the same
+//! way regardless of depth.
+
+use crate::addresses;
+use crate::backend::Chain;
+use crate::explorer;
+use crate::fetch::{self, FetchOptions};
+use crate::manifest::OutDirWriter;
+use crate::progress;
+use serde_json::json;
+use std::collections::{BTreeSet, VecDeque};
+
+/// Directory of dependencies inside output directory
+pub const DEPS_DIR: &str = "deps";
+
+/// External library linked into contract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkedLibrary {
+    pub name: String,
+
+    /// Address in lowercase with `0x` prefix
+    pub address: String,
+}
+
+/// Get libraries linked into contract from `Library` field of verification
+/// metadata e.g. `SafeMath:0x...;Utils:0x...`, and `libraries` of compiler
+/// settings of standard JSON submission.
+///
+/// # Arguments
+/// * `meta` - verification metadata
+/// * `settings` - compiler settings used for verification
+pub fn linked_libraries(meta: &serde_json::Value, settings: &serde_json::Value) -> Vec<LinkedLibrary> {
+    let normalize = |address: &str| format!("0x{}", address.trim().trim_start_matches("0x").to_lowercase());
+
+    let mut libraries: Vec<LinkedLibrary> = Vec::new();
+    for entry in meta["Library"].as_str().unwrap_or("").split(';') {
+        if let Some((name, address)) = entry.split_once(':') {
+            libraries.push(LinkedLibrary { name: name.trim().to_owned(), address: normalize(address) });
+        }
+    }
+
+    // keyed by source file, or directly by name in older submissions
+    if let Some(by_file) = settings["libraries"].as_object() {
+        for (key, value) in by_file.iter() {
+            match value {
+                serde_json::Value::String(address) => libraries.push(LinkedLibrary { name: key.clone(), address: normalize(address) }),
+                serde_json::Value::Object(by_name) => {
+                    for (name, address) in by_name.iter() {
+                        if let Some(address) = address.as_str() {
+                            libraries.push(LinkedLibrary { name: name.clone(), address: normalize(address) });
+                        }
+                    }
+                },
+                _ => (),
+            }
+        }
+    }
+
+    libraries.retain(|l| l.address.len() == 42);
+    libraries.sort_by(|a, b| a.address.cmp(&b.address));
+    libraries.dedup_by(|a, b| a.address == b.address);
+    libraries
+}
+
+/// Contracts referenced by fetched contract.
+#[derive(Debug, Clone)]
+struct References {
+    /// Referenced addresses in lowercase with `0x` prefix, but of libraries
+    addresses: BTreeSet<String>,
+
+    libraries: Vec<LinkedLibrary>,
+}
+
+/// Addresses held by constructor arguments of verification metadata.
+///
+/// # Arguments
+/// * `meta` - verification metadata
+pub fn constructor_addresses(meta: &serde_json::Value) -> BTreeSet<String> {
+    let args = crate::abi::from_hex(meta["ConstructorArguments"].as_str().unwrap_or("")).unwrap_or_default();
+    args.chunks(32).filter_map(addresses::word_address).collect()
+}
+
+/// Fetch verified source code of contract into output directory, and return
+/// addresses it references along with its linked libraries. Return `None` if
+/// it has no verified source code.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+/// * `code` - runtime bytecode of contract if references are needed
+/// * `options` - fetch options
+/// * `out_dir` - output directory of contract
+/// * `silence` - whether or not to print meta information
+fn fetch_one(chain: &Chain, address: &str, code: Option<&[u8]>, options: &FetchOptions, out_dir: &str, silence: bool) -> Result<Option<References>, String> {
+    let body = explorer::get_source_meta_text(chain, address)?;
+    let raw = explorer::parse_source_meta_raw(address, &body)?;
+    if !raw.is_verified() {
+        return Ok(None);
+    }
+
+    let mut references = constructor_addresses(&raw.meta);
+    let mut writer = OutDirWriter::open(out_dir, address, &chain.name, silence)?;
+    let written = fetch::fetch_each_raw(chain, address, &raw, options, |path, content| {
+        references.extend(addresses::text_addresses(content));
+        writer.write(path, content)
+    })?;
+    writer.finish()?;
+    progress::emit("fetch_done", json!({ "address": address, "chain": chain.name, "files": written.count }));
+
+    let libraries = linked_libraries(&raw.meta, &written.settings);
+    if !progress::silenced(silence) {
+        for library in libraries.iter() {
+            println!("library {} at {}", library.name, library.address);
+        }
+    }
+
+    references.extend(code.map_or(BTreeSet::new(), addresses::bytecode_addresses));
+    references.remove(&address.to_lowercase());
+    Ok(Some(References { addresses: references, libraries }))
+}
+
+/// Fetch verified source code of contract into output directory, then ones
+/// of its linked libraries, and contracts it references recursively into
+/// `deps/` of it. Only linked libraries are fetched with depth of 0.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+/// * `options` - fetch options
+/// * `out_dir` - output directory
+/// * `depth` - how many levels of references to follow
+/// * `silence` - whether or not to print meta information
+pub fn fetch_recursive(chain: &Chain, address: &str, options: &FetchOptions, out_dir: &str, depth: u32, silence: bool) -> Result<(), String> {
+    let address = address.to_lowercase();
+    let mut visited: BTreeSet<String> = BTreeSet::from([address.clone()]);
+    let mut queue: VecDeque<(String, u32)> = VecDeque::new();
+
+    let code = if depth > 0 { Some(explorer::get_code(chain, &address)?) } else { None };
+    match fetch_one(chain, &address, code.as_deref(), options, out_dir, silence)? {
+        Some(references) => {
+            queue.extend(references.libraries.into_iter().map(|l| (l.address, 0)));
+            queue.extend(references.addresses.into_iter().map(|a| (a, 1)));
+        },
+        None => {
+            let err_msg = format!("Error no verified source code of {}", address);
+            return Err(err_msg);
+        }
+    }
+
+    let deps_dir = crate::combine_two_path_components(out_dir, DEPS_DIR)?;
+    while let Some((dep, level)) = queue.pop_front() {
+        if level > depth || !visited.insert(dep.clone()) {
+            continue;
+        }
+
+        // referenced addresses can be accounts as well
+        let code = match explorer::get_code(chain, &dep) {
+            Ok(res) if res.is_empty() => continue,
+            Ok(res) => res,
+            Err(e) => {
+                progress::error(Some(&dep), &e);
+                eprintln!("{}", e);
+                continue;
+            }
+        };
+
+        // failing dependency doesn't stop the rest of them
+        progress::emit("fetch_start", json!({ "address": dep, "chain": chain.name }));
+        let dep_dir = crate::combine_two_path_components(&deps_dir, &dep)?;
+        match fetch_one(chain, &dep, Some(&code), options, &dep_dir, silence) {
+            Ok(Some(references)) => {
+                queue.extend(references.libraries.into_iter().map(|l| (l.address, level)));
+                queue.extend(references.addresses.into_iter().map(|a| (a, level + 1)));
+            },
+            Ok(None) => {
+                progress::emit("skipped", json!({ "address": dep, "reason": "no verified source code" }));
+                if !progress::silenced(silence) {
+                    println!("{} skipped; no verified source code", dep);
+                }
+            },
+            Err(e) => {
+                progress::error(Some(&dep), &e);
+                eprintln!("{}", e);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,287 @@
+//! `diagram` command: render class diagram of verified source, in the way of
+//! sol2uml, showing contracts, their state variables, functions, and
+//! inheritance from AST of solc.
+//!
+//! It is emitted as PlantUML, or DOT. SVG is rendered from DOT by Graphviz's
+//! `dot` which needs to be installed.
+
+use crate::ast::{self, collect};
+use crate::TargetArgs;
+use clap::Args;
+use serde_json::Value;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[derive(Debug, Args)]
+pub struct DiagramArgs {
+    #[clap(flatten)]
+    pub target: TargetArgs,
+
+    /// Output file. Its extension decides the format; '.puml' for PlantUML,
+    /// '.dot' for DOT, or '.svg' for SVG. PlantUML is printed to stdout if
+    /// not supplied.
+    #[clap(long="out", short='o', required=false)]
+    pub out: Option<String>,
+
+    /// Path to solc binary to use instead of finding one matching the verified
+    /// compiler version
+    #[clap(long="solc", required=false)]
+    pub solc: Option<String>,
+}
+
+/// Format of diagram.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    PlantUml,
+    Dot,
+    Svg,
+}
+
+/// Contract as a class of diagram.
+#[derive(Debug)]
+struct Class {
+    id: u64,
+    name: String,
+
+    /// `interface`, `library`, `abstract`, or empty for concrete contract
+    stereotype: String,
+
+    /// State variables e.g. `- owner: address`
+    variables: Vec<String>,
+
+    /// Functions e.g. `+ deposit(uint256) payable`
+    functions: Vec<String>,
+
+    /// Ids of base contracts
+    bases: Vec<u64>,
+}
+
+/// Get format of diagram from extension of output file.
+///
+/// # Arguments
+/// * `out` - output file
+fn format_of(out: Option<&String>) -> Result<Format, String> {
+    let out = match out {
+        Some(res) => res,
+        None => return Ok(Format::PlantUml),
+    };
+    let extension = std::path::Path::new(out).extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    match extension.as_str() {
+        "puml" | "plantuml" => Ok(Format::PlantUml),
+        "dot" | "gv" => Ok(Format::Dot),
+        "svg" => Ok(Format::Svg),
+        _ => {
+            let err_msg = format!("Error unsupported extension of output file '{}'.\nPossible extensions are '.puml', '.dot', or '.svg'.", out);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Get UML marker of visibility.
+///
+/// # Arguments
+/// * `visibility` - `visibility` of declaration
+fn visibility_marker(visibility: &str) -> &'static str {
+    match visibility {
+        "public" => "+",
+        "private" => "-",
+        "external" => "~",
+        _ => "#",
+    }
+}
+
+/// Get classes of contracts along with their members.
+///
+/// # Arguments
+/// * `contracts` - `ContractDefinition` nodes
+fn classes_of(contracts: &[&Value]) -> Vec<Class> {
+    let text = |v: &Value| v.as_str().unwrap_or("").to_owned();
+    let mut classes = Vec::new();
+    for contract in contracts.iter() {
+        let kind = contract["contractKind"].as_str().unwrap_or("contract");
+        let stereotype = match (kind, contract["abstract"].as_bool().unwrap_or(false)) {
+            ("contract", true) => "abstract".to_owned(),
+            ("contract", false) => String::new(),
+            _ => kind.to_owned(),
+        };
+
+        let mut variables = Vec::new();
+        let mut functions = Vec::new();
+        for node in contract["nodes"].as_array().map_or(&[][..], |n| n.as_slice()).iter() {
+            let marker = visibility_marker(node["visibility"].as_str().unwrap_or(""));
+            match node["nodeType"].as_str().unwrap_or("") {
+                "VariableDeclaration" if node["stateVariable"].as_bool().unwrap_or(false) => {
+                    let mut variable = format!("{} {}: {}", marker, text(&node["name"]), text(&node["typeDescriptions"]["typeString"]));
+                    match node["mutability"].as_str() {
+                        Some("constant") => variable.push_str(" (constant)"),
+                        Some("immutable") => variable.push_str(" (immutable)"),
+                        _ => (),
+                    }
+                    variables.push(variable);
+                },
+                "FunctionDefinition" => {
+                    let name = match node["name"].as_str() {
+                        Some(name) if !name.is_empty() => name.to_owned(),
+                        // constructor, fallback, and receive have no name
+                        _ => text(&node["kind"]),
+                    };
+                    let parameters: Vec<String> = node["parameters"]["parameters"].as_array().map_or(Vec::new(), |p| {
+                        p.iter().map(|p| text(&p["typeDescriptions"]["typeString"])).collect()
+                    });
+                    let mut function = format!("{} {}({})", marker, name, parameters.join(", "));
+                    match node["stateMutability"].as_str() {
+                        Some("nonpayable") | None => (),
+                        Some(mutability) => function.push_str(&format!(" {}", mutability)),
+                    }
+                    functions.push(function);
+                },
+                _ => (),
+            }
+        }
+
+        classes.push(Class {
+            id: contract["id"].as_u64().unwrap_or(0),
+            name: text(&contract["name"]),
+            stereotype,
+            variables,
+            functions,
+            bases: contract["baseContracts"].as_array().map_or(Vec::new(), |b| {
+                b.iter().filter_map(|b| b["baseName"]["referencedDeclaration"].as_u64()).collect()
+            }),
+        });
+    }
+    classes
+}
+
+/// Render classes as PlantUML class diagram.
+///
+/// # Arguments
+/// * `classes` - classes
+fn render_plantuml(classes: &[Class]) -> String {
+    let mut out = String::from("@startuml\n");
+    for class in classes.iter() {
+        let header = match class.stereotype.as_str() {
+            "" => format!("class {}", class.name),
+            "abstract" => format!("abstract class {}", class.name),
+            "interface" => format!("interface {}", class.name),
+            stereotype => format!("class {} <<{}>>", class.name, stereotype),
+        };
+        out.push_str(&format!("{} {{\n", header));
+        for member in class.variables.iter().chain(class.functions.iter()) {
+            out.push_str(&format!("  {}\n", member));
+        }
+        out.push_str("}\n");
+    }
+    for class in classes.iter() {
+        for base in class.bases.iter() {
+            if let Some(base) = classes.iter().find(|c| c.id == *base) {
+                out.push_str(&format!("{} <|-- {}\n", base.name, class.name));
+            }
+        }
+    }
+    out.push_str("@enduml\n");
+    out
+}
+
+/// Render classes as DOT with a record node for each class.
+///
+/// # Arguments
+/// * `classes` - classes
+fn render_dot(classes: &[Class]) -> String {
+    // characters with special meaning in record label
+    let escape = |s: &str| {
+        s.chars().fold(String::new(), |mut acc, c| {
+            if "{}|<>\"\\".contains(c) {
+                acc.push('\\');
+            }
+            acc.push(c);
+            acc
+        })
+    };
+    let lines = |members: &[String]| members.iter().map(|m| format!("{}\\l", escape(m))).collect::<String>();
+
+    let mut out = String::from("digraph diagram {\n");
+    out.push_str("  rankdir=BT;\n");
+    out.push_str("  node [shape=record, fontname=\"monospace\"];\n");
+    for class in classes.iter() {
+        let title = match class.stereotype.as_str() {
+            "" => escape(&class.name),
+            stereotype => format!("\\<\\<{}\\>\\>\\n{}", stereotype, escape(&class.name)),
+        };
+        out.push_str(&format!("  n{} [label=\"{{{}|{}|{}}}\"];\n", class.id, title, lines(&class.variables), lines(&class.functions)));
+    }
+    for class in classes.iter() {
+        for base in class.bases.iter() {
+            if classes.iter().any(|c| c.id == *base) {
+                out.push_str(&format!("  n{} -> n{} [arrowhead=empty];\n", class.id, base));
+            }
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Render DOT as SVG with Graphviz's `dot`.
+///
+/// # Arguments
+/// * `dot` - DOT to render
+fn render_svg(dot: &str) -> Result<String, String> {
+    let mut child = match Command::new("dot").arg("-Tsvg").stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn() {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error executing dot, make sure Graphviz is installed, or output as '.dot', or '.puml' instead; err={}", e);
+            return Err(err_msg);
+        }
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        if let Err(e) = stdin.write_all(dot.as_bytes()) {
+            let err_msg = format!("Error writing DOT to dot; err={}", e);
+            return Err(err_msg);
+        }
+    }
+
+    match child.wait_with_output() {
+        Ok(output) if output.status.success() => Ok(String::from_utf8_lossy(&output.stdout).into_owned()),
+        Ok(output) => {
+            let err_msg = format!("Error dot failed; stderr={}", String::from_utf8_lossy(&output.stderr));
+            Err(err_msg)
+        },
+        Err(e) => {
+            let err_msg = format!("Error executing dot; err={}", e);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Execute `diagram` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &DiagramArgs) -> Result<(), String> {
+    // fail before compiling anything
+    let format = format_of(args.out.as_ref())?;
+
+    let asts = ast::compile_asts(&args.target, args.solc.as_ref())?;
+    let mut contracts = Vec::new();
+    for unit in asts.values() {
+        collect(unit, "ContractDefinition", &mut contracts);
+    }
+    let classes = classes_of(&contracts);
+
+    let content = match format {
+        Format::PlantUml => render_plantuml(&classes),
+        Format::Dot => render_dot(&classes),
+        Format::Svg => render_svg(&render_dot(&classes))?,
+    };
+
+    match args.out.as_ref() {
+        Some(out) => {
+            crate::create_intermediate_dirs(out)?;
+            crate::write_file(out, &content)?;
+            println!("{}", out);
+        },
+        None => print!("{}", content),
+    }
+    Ok(())
+}
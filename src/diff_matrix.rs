@@ -0,0 +1,293 @@
+//! `diff-matrix` command: pairwise similarity of source code, and runtime
+//! bytecode among many contracts e.g. a family of forked tokens, along with
+//! groups of similar ones, for fork genealogy at a glance.
+//!
+//! Source similarity is Dice coefficient of lines of source code with
+//! comments, and string literals blanked, and whitespace collapsed, counted
+//! as multisets so file paths, and order don't matter. Bytecode similarity is
+//! the same over 4-instruction windows of runtime bytecode without metadata.
+//! Contracts are grouped by single linkage of pairs at least as similar as
+//! `--threshold`.
+
+use crate::backend;
+use crate::bytecode;
+use crate::explorer;
+use crate::export;
+use crate::progress;
+use crate::solidity;
+use crate::source;
+use clap::Args;
+use std::collections::HashMap;
+
+/// Number of instructions of each window compared for bytecode similarity
+const WINDOW: usize = 4;
+
+#[derive(Debug, Args)]
+pub struct DiffMatrixArgs {
+    /// Addresses of contracts to compare
+    #[clap(value_name="ADDRESS", required=false)]
+    pub addresses: Vec<String>,
+
+    /// Path of file listing addresses to compare, one per line
+    #[clap(long="input", short='i', required=false)]
+    pub input: Option<String>,
+
+    /// Which chain to work with, by name e.g. 'bsc', 'ethereum', 'polygon',
+    /// and 'arbitrum', or chain id e.g. '56'. See 'tracpls chains' for all.
+    #[clap(long="chain", short='c', required=true, multiple_values=false)]
+    pub chain: String,
+
+    /// Similarity in percent from which contracts are grouped together
+    #[clap(long="threshold", required=false, default_value="90")]
+    pub threshold: f64,
+
+    /// Similarity to group contracts by. Possible values are 'source', and
+    /// 'bytecode'.
+    #[clap(long="by", required=false, default_value="source")]
+    pub by: String,
+
+    /// Output format. Possible values are 'table', and 'csv' with a row for
+    /// each pair.
+    #[clap(long="format", required=false, default_value="table")]
+    pub format: String,
+}
+
+/// Contract compared, with what it's compared by.
+struct Subject {
+    address: String,
+    name: String,
+
+    /// Normalized lines of source code with their counts, or `None` if not
+    /// verified
+    lines: Option<HashMap<String, usize>>,
+
+    /// Windows of instructions with their counts, or `None` if there is no
+    /// code
+    windows: Option<HashMap<Vec<u8>, usize>>,
+}
+
+/// Count lines of source code with comments, and string literals blanked,
+/// and whitespace collapsed. Blank lines, and lines of only braces are left
+/// out as they are common to any code.
+///
+/// # Arguments
+/// * `files` - source files
+fn count_lines(files: &[source::SourceFile]) -> HashMap<String, usize> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for f in files.iter() {
+        for line in solidity::strip_comments_and_strings(&f.content).lines() {
+            let line = line.split_whitespace().collect::<Vec<&str>>().join(" ");
+            if line.chars().all(|c| c == '{' || c == '}' || c == ' ') {
+                continue;
+            }
+            *counts.entry(line).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+/// Count windows of consecutive instructions of runtime bytecode without
+/// metadata.
+///
+/// # Arguments
+/// * `code` - runtime bytecode
+fn count_windows(code: &[u8]) -> HashMap<Vec<u8>, usize> {
+    let keys: Vec<Vec<u8>> = bytecode::disassemble(bytecode::strip_metadata(code)).iter()
+        .map(|i| {
+            let mut key = vec![i.opcode];
+            key.extend_from_slice(&i.data);
+            key
+        })
+        .collect();
+    let mut counts: HashMap<Vec<u8>, usize> = HashMap::new();
+    for window in keys.windows(WINDOW.min(keys.len().max(1))) {
+        *counts.entry(window.concat()).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Compute Dice coefficient of two multisets in percent.
+///
+/// # Arguments
+/// * `a` - counts of the first multiset
+/// * `b` - counts of the second multiset
+fn dice<K: std::hash::Hash + Eq>(a: &HashMap<K, usize>, b: &HashMap<K, usize>) -> f64 {
+    let total: usize = a.values().sum::<usize>() + b.values().sum::<usize>();
+    if total == 0 {
+        return 100.0;
+    }
+    let common: usize = a.iter().map(|(k, n)| (*n).min(b.get(k).copied().unwrap_or(0))).sum();
+    200.0 * common as f64 / total as f64
+}
+
+/// Load contract to compare; its verified source code, and runtime bytecode.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+fn load_subject(chain: &backend::Chain, address: &str) -> Result<Subject, String> {
+    let meta = explorer::get_source_meta(chain, address)?;
+    let (name, lines) = if meta["SourceCode"].as_str().unwrap_or("").trim().is_empty() {
+        (String::new(), None)
+    }
+    else {
+        let contract_source = source::sources_from_meta(address, &meta)?;
+        (contract_source.contract_name, Some(count_lines(&contract_source.files)))
+    };
+
+    let code = explorer::get_code(chain, address)?;
+    let windows = (!code.is_empty()).then(|| count_windows(&code));
+    if lines.is_none() && windows.is_none() {
+        let err_msg = format!("Error {} has neither verified source code, nor code", address);
+        return Err(err_msg);
+    }
+
+    Ok(Subject {
+        address: address.to_owned(),
+        name,
+        lines,
+        windows,
+    })
+}
+
+/// Group contracts by single linkage of similar pairs. Returns group number
+/// of each contract, numbered by order of their first member.
+///
+/// # Arguments
+/// * `n` - number of contracts
+/// * `similar` - whether or not pair of contracts by index is similar
+fn group(n: usize, similar: impl Fn(usize, usize) -> bool) -> Vec<usize> {
+    let mut groups: Vec<Option<usize>> = vec![None; n];
+    let mut next = 0;
+    for start in 0..n {
+        if groups[start].is_some() {
+            continue;
+        }
+        groups[start] = Some(next);
+        let mut stack = vec![start];
+        while let Some(i) = stack.pop() {
+            for (j, g) in groups.iter_mut().enumerate() {
+                if g.is_none() && similar(i, j) {
+                    *g = Some(next);
+                    stack.push(j);
+                }
+            }
+        }
+        next += 1;
+    }
+    groups.into_iter().map(|g| g.unwrap_or(0)).collect()
+}
+
+/// Format similarity in percent, or `-` if not comparable.
+///
+/// # Arguments
+/// * `similarity` - similarity in percent
+fn format_similarity(similarity: Option<f64>) -> String {
+    similarity.map_or_else(|| "-".to_owned(), |s| format!("{:.1}", s))
+}
+
+/// Execute `diff-matrix` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &DiffMatrixArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.chain)?;
+    if args.by != "source" && args.by != "bytecode" {
+        let err_msg = format!("Error unknown value '{}' of --by; possible values are 'source', and 'bytecode'", args.by);
+        return Err(err_msg);
+    }
+    if args.format != "table" && args.format != "csv" {
+        let err_msg = format!("Error unknown value '{}' of --format; possible values are 'table', and 'csv'", args.format);
+        return Err(err_msg);
+    }
+
+    let mut addresses: Vec<String> = Vec::new();
+    let listed = match args.input.as_ref() {
+        Some(path) => match std::fs::read_to_string(path) {
+            Ok(res) => res.lines().map(|l| l.trim().to_owned()).collect(),
+            Err(e) => {
+                let err_msg = format!("Error reading input file '{}'; err={}", path, e);
+                return Err(err_msg);
+            }
+        },
+        None => Vec::new(),
+    };
+    for address in args.addresses.iter().cloned().chain(listed) {
+        let address = address.to_lowercase();
+        if !address.is_empty() && !address.starts_with('#') && !addresses.contains(&address) {
+            addresses.push(address);
+        }
+    }
+    if addresses.len() < 2 {
+        return Err("Error at least two addresses are needed to compare".to_owned());
+    }
+
+    let mut subjects: Vec<Subject> = Vec::new();
+    for address in addresses.iter() {
+        match load_subject(chain, address) {
+            Ok(res) => subjects.push(res),
+            Err(e) => {
+                // failed contracts are reported via exit code, see `progress::tally()`
+                progress::error(Some(address), &e);
+                eprintln!("{} failed; {}", address, e);
+            }
+        }
+    }
+
+    let n = subjects.len();
+    let source_similarity = |i: usize, j: usize| match (subjects[i].lines.as_ref(), subjects[j].lines.as_ref()) {
+        (Some(a), Some(b)) => Some(dice(a, b)),
+        _ => None,
+    };
+    let bytecode_similarity = |i: usize, j: usize| match (subjects[i].windows.as_ref(), subjects[j].windows.as_ref()) {
+        (Some(a), Some(b)) => Some(dice(a, b)),
+        _ => None,
+    };
+    let source: Vec<Vec<Option<f64>>> = (0..n).map(|i| (0..n).map(|j| source_similarity(i, j)).collect()).collect();
+    let code: Vec<Vec<Option<f64>>> = (0..n).map(|i| (0..n).map(|j| bytecode_similarity(i, j)).collect()).collect();
+    let by = if args.by == "source" { &source } else { &code };
+    let groups = group(n, |i, j| by[i][j].is_some_and(|s| s >= args.threshold));
+
+    if args.format == "csv" {
+        println!("address_a,name_a,address_b,name_b,source_similarity,bytecode_similarity,same_group");
+        for i in 0..n {
+            for j in i + 1..n {
+                let field = |s: Option<f64>| s.map_or_else(String::new, |s| format!("{:.1}", s));
+                println!("{},{},{},{},{},{},{}",
+                    subjects[i].address, export::csv_field(&subjects[i].name), subjects[j].address, export::csv_field(&subjects[j].name),
+                    field(source[i][j]), field(code[i][j]), groups[i] == groups[j]);
+            }
+        }
+        return Ok(());
+    }
+
+    for (i, s) in subjects.iter().enumerate() {
+        println!("[{}] {} {}", i + 1, s.address, if s.name.is_empty() { "(not verified)" } else { &s.name });
+    }
+    for (title, matrix) in [("source similarity (%)", &source), ("bytecode similarity (%)", &code)] {
+        println!("\n{}", title);
+        print!("{:>5}", "");
+        for j in 0..n {
+            print!(" {:>6}", format!("[{}]", j + 1));
+        }
+        println!();
+        for (i, row) in matrix.iter().enumerate() {
+            print!("{:>5}", format!("[{}]", i + 1));
+            for similarity in row.iter() {
+                print!(" {:>6}", format_similarity(*similarity));
+            }
+            println!();
+        }
+    }
+
+    println!("\ngroups by {} similarity >= {}%", args.by, args.threshold);
+    let mut members: Vec<Vec<usize>> = vec![Vec::new(); groups.iter().max().map_or(0, |g| g + 1)];
+    for (i, g) in groups.iter().enumerate() {
+        members[*g].push(i);
+    }
+    for (g, indexes) in members.iter().enumerate() {
+        let listed: Vec<String> = indexes.iter().map(|i| format!("[{}]", i + 1)).collect();
+        println!("group {}: {}", g + 1, listed.join(" "));
+    }
+    Ok(())
+}
@@ -0,0 +1,107 @@
+//! Helpers to format on-chain values for display on terminal.
+
+use crate::backend::Chain;
+
+/// Get symbol of native currency of the selected chain.
+///
+/// # Arguments
+/// * `chain` - chain
+pub fn native_symbol(chain: &Chain) -> &str {
+    &chain.native_symbol
+}
+
+/// Format integer value in its smallest unit (e.g. wei) into decimal
+/// representation with the specified number of decimals.
+/// Trailing zeros of fractional part are trimmed.
+///
+/// If `value` is not a valid unsigned integer, it will be returned as-is.
+///
+/// # Arguments
+/// * `value` - unsigned integer in decimal string
+/// * `decimals` - number of decimals e.g. 18 for native currency
+pub fn format_units(value: &str, decimals: usize) -> String {
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return value.to_owned();
+    }
+
+    let digits = value.trim_start_matches('0');
+    if digits.is_empty() {
+        return "0".to_owned();
+    }
+
+    // pad with leading zeros so there is always at least one integer digit
+    let padded = if digits.len() <= decimals {
+        format!("{}{}", "0".repeat(decimals - digits.len() + 1), digits)
+    }
+    else {
+        digits.to_owned()
+    };
+
+    let (integer, fraction) = padded.split_at(padded.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        integer.to_owned()
+    }
+    else {
+        format!("{}.{}", integer, fraction)
+    }
+}
+
+/// Convert unix timestamp (in seconds) into UTC date time as tuple of
+/// year, month, day, hour, minute, and second.
+///
+/// # Arguments
+/// * `secs` - unix timestamp in seconds
+pub fn civil_from_timestamp(secs: i64) -> (i64, i64, i64, i64, i64, i64) {
+    let days = secs.div_euclid(86400);
+    let secs_of_day = secs.rem_euclid(86400);
+
+    // convert days since epoch to civil date
+    // see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day, secs_of_day / 3600, (secs_of_day % 3600) / 60, secs_of_day % 60)
+}
+
+/// Convert UTC date into unix timestamp (in seconds) at its midnight. It is
+/// the inverse of `civil_from_timestamp`.
+///
+/// # Arguments
+/// * `year` - year
+/// * `month` - month, 1 to 12
+/// * `day` - day of month
+pub fn timestamp_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    // see http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    (era * 146097 + doe - 719468) * 86400
+}
+
+/// Format unix timestamp (in seconds) into UTC date time string in form of
+/// `YYYY-MM-DD HH:MM:SS`.
+///
+/// If `timestamp` is not a valid number, it will be returned as-is.
+///
+/// # Arguments
+/// * `timestamp` - unix timestamp in decimal string
+pub fn format_timestamp(timestamp: &str) -> String {
+    let secs = match timestamp.parse::<i64>() {
+        Ok(res) => res,
+        Err(_) => return timestamp.to_owned(),
+    };
+
+    let (year, month, day, hour, minute, second) = civil_from_timestamp(secs);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, hour, minute, second)
+}
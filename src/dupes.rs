@@ -0,0 +1,186 @@
+//! `dupes` command: group contracts in local cache (see `cache`) which are
+//! copies of each other e.g. identical tokens deployed over and over by the
+//! same deployer.
+//!
+//! Contracts are grouped by hash of normalized source code; comments, and
+//! content of string literals are blanked, whitespace is dropped, and file
+//! paths are ignored, so copies differing only in names given as strings, or
+//! comments still group together. With `--bytecode`, they're also grouped by
+//! hash of runtime bytecode without metadata appended by compiler, which
+//! needs a request to API platform for each contract.
+
+use crate::abi;
+use crate::bytecode;
+use crate::cache;
+use crate::explorer;
+use crate::solidity;
+use crate::source;
+use clap::Args;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Args)]
+pub struct DupesArgs {
+    /// Only group contracts of this chain, by name, or chain id
+    #[clap(long="chain", short='c', required=false)]
+    pub chain: Option<String>,
+
+    /// Also group contracts by runtime bytecode without metadata. It sends a
+    /// request to API platform for each contract.
+    #[clap(long="bytecode", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub bytecode: bool,
+
+    /// Minimum number of contracts of a cluster to report
+    #[clap(long="min-size", required=false, default_value="2")]
+    pub min_size: usize,
+
+    /// Output as JSON instead of human readable text
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+}
+
+/// Contract belonging to clusters.
+#[derive(Debug, Clone)]
+struct Member {
+    chain: String,
+    address: String,
+    contract_name: String,
+}
+
+/// Normalize source code so that formatting doesn't matter; comments, and
+/// content of string literals are blanked, and whitespace is dropped except
+/// between two words.
+///
+/// # Arguments
+/// * `content` - source code
+fn normalize(content: &str) -> String {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    let stripped = solidity::strip_comments_and_strings(content);
+    let mut out = String::with_capacity(stripped.len());
+    for token in stripped.split_whitespace() {
+        if out.ends_with(is_word) && token.starts_with(is_word) {
+            out.push(' ');
+        }
+        out.push_str(token);
+    }
+    out
+}
+
+/// Compute hash of normalized source code of cached contract.
+///
+/// # Arguments
+/// * `address` - contract address
+/// * `raw` - verification metadata
+fn source_hash(address: &str, raw: &explorer::RawSourceMeta) -> Result<String, String> {
+    let mut normalized: Vec<String> = Vec::new();
+    source::for_each_source_raw(address, raw, |f| {
+        normalized.push(normalize(&f.content));
+        Ok(())
+    })?;
+    // paths, and order of files don't matter
+    normalized.sort();
+    Ok(abi::to_hex(&abi::keccak256(normalized.join("\n").as_bytes())))
+}
+
+/// Print, or output clusters as JSON.
+///
+/// # Arguments
+/// * `clusters` - pairs of kind of hash, and members by hash
+/// * `min_size` - minimum number of members
+/// * `as_json` - whether to output as JSON
+fn print_clusters(clusters: &[(&str, BTreeMap<String, Vec<Member>>)], min_size: usize, as_json: bool) -> Result<(), String> {
+    let mut reported: Vec<(&str, &String, &Vec<Member>)> = Vec::new();
+    for (kind, by_hash) in clusters.iter() {
+        let mut sorted: Vec<(&String, &Vec<Member>)> = by_hash.iter().filter(|(_, m)| m.len() >= min_size).collect();
+        // biggest clusters first
+        sorted.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(b.0)));
+        reported.extend(sorted.into_iter().map(|(hash, members)| (*kind, hash, members)));
+    }
+
+    if as_json {
+        let out: Vec<Value> = reported.iter().map(|(kind, hash, members)| json!({
+            "kind": kind,
+            "hash": hash,
+            "size": members.len(),
+            "contracts": members.iter().map(|m| json!({ "chain": m.chain, "address": m.address, "contractName": m.contract_name })).collect::<Vec<Value>>(),
+        })).collect();
+        match serde_json::to_string_pretty(&out) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing clusters to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+        return Ok(());
+    }
+
+    for (kind, hash, members) in reported.iter() {
+        println!("{} 0x{} ({} contracts)", kind, hash, members.len());
+        for m in members.iter() {
+            println!("  {} ({}) {}", m.address, m.chain, m.contract_name);
+        }
+    }
+    if reported.is_empty() {
+        eprintln!("No cluster of {} or more identical contracts", min_size);
+    }
+    Ok(())
+}
+
+/// Execute `dupes` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &DupesArgs) -> Result<(), String> {
+    let dir = match cache::settings() {
+        Some(res) => res.dir,
+        None => return Err("Error --cache-dir is required to locate local cache".to_owned()),
+    };
+    let chain = match args.chain.as_ref() {
+        Some(chain) => Some(crate::parse_chain(chain)?.name.clone()),
+        None => None,
+    };
+    if args.min_size < 2 {
+        return Err("Error --min-size must be at least 2".to_owned());
+    }
+
+    let mut by_source: BTreeMap<String, Vec<Member>> = BTreeMap::new();
+    let mut by_bytecode: BTreeMap<String, Vec<Member>> = BTreeMap::new();
+    for entry in cache::cached_entries(&dir)?.iter().filter(|e| chain.as_ref().is_none_or(|c| *c == e.chain)) {
+        let body = entry.read()?;
+        let raw = match explorer::parse_source_meta_raw(&entry.address, &body) {
+            Ok(res) => res,
+            Err(e) => {
+                eprintln!("Warning: skipped {}; {}", entry.relative_path, e);
+                continue;
+            }
+        };
+        let member = Member {
+            chain: entry.chain.clone(),
+            address: entry.address.clone(),
+            contract_name: raw.meta["ContractName"].as_str().unwrap_or("").to_owned(),
+        };
+
+        match source_hash(&entry.address, &raw) {
+            Ok(hash) => by_source.entry(hash).or_default().push(member.clone()),
+            Err(e) => eprintln!("Warning: skipped source code of {}; {}", entry.relative_path, e),
+        }
+
+        if args.bytecode {
+            let code = crate::parse_chain(&entry.chain).and_then(|c| explorer::get_code(c, &entry.address));
+            match code {
+                Ok(code) if !code.is_empty() => {
+                    let hash = abi::to_hex(&abi::keccak256(bytecode::strip_metadata(&code)));
+                    by_bytecode.entry(hash).or_default().push(member);
+                },
+                Ok(_) => (),
+                Err(e) => eprintln!("Warning: skipped bytecode of {}; {}", entry.relative_path, e),
+            }
+        }
+    }
+
+    let mut clusters = vec![("source", by_source)];
+    if args.bytecode {
+        clusters.push(("bytecode", by_bytecode));
+    }
+    print_clusters(&clusters, args.min_size, args.json)
+}
@@ -0,0 +1,231 @@
+//! `enrich` command: annotate addresses found in arbitrary text e.g. draft of
+//! incident write-up with what they are; contract name, whether verified,
+//! implementation if proxy, and public name tag.
+//!
+//! Text is echoed back with each address either followed by its annotation
+//! where it first appears, or marked with number of footnote listed in a
+//! table at the end.
+
+use crate::addresses;
+use crate::explorer;
+use crate::progress;
+use crate::proxy;
+use clap::Args;
+use serde_json::{json, Value};
+use std::io::Read;
+
+#[derive(Debug, Args)]
+pub struct EnrichArgs {
+    /// File to read text from; stdin if not given, or '-'
+    #[clap(value_name="FILE", required=false)]
+    pub file: Option<String>,
+
+    /// Which chain to work with, by name e.g. 'bsc', 'ethereum', 'polygon',
+    /// and 'arbitrum', or chain id e.g. '56'. See 'tracpls chains' for all.
+    #[clap(long="chain", short='c', required=true, multiple_values=false)]
+    pub chain: String,
+
+    /// How to annotate addresses. Possible values are 'footnote' to mark
+    /// them with numbers of footnotes listed at the end, and 'inline' to
+    /// follow each with its annotation where it first appears.
+    #[clap(long="format", required=false, default_value="footnote")]
+    pub format: String,
+
+    /// Output annotations of addresses as JSON instead of the text
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+}
+
+/// What address is.
+pub struct Summary {
+    pub address: String,
+
+    /// Whether address has code
+    pub is_contract: bool,
+
+    /// Name of verified contract, empty if not verified
+    pub name: String,
+
+    /// Address, and name of verified implementation, empty if not
+    /// verified, if address is proxy
+    pub implementation: Option<(String, String)>,
+
+    /// Public name tag
+    pub name_tag: Option<String>,
+}
+
+impl Summary {
+    /// Describe what address is in one line e.g.
+    /// `TransparentUpgradeableProxy (verified); proxy -> 0x... FiatTokenV2_2`.
+    pub fn describe(&self) -> String {
+        let mut parts: Vec<String> = Vec::new();
+        if !self.is_contract {
+            parts.push("account".to_owned());
+        }
+        else if self.name.is_empty() {
+            parts.push("contract (not verified)".to_owned());
+        }
+        else {
+            parts.push(format!("{} (verified)", self.name));
+        }
+        if let Some((address, name)) = self.implementation.as_ref() {
+            let name = if name.is_empty() { "(not verified)" } else { name };
+            parts.push(format!("proxy -> {} {}", address, name));
+        }
+        if let Some(tag) = self.name_tag.as_ref() {
+            parts.push(tag.clone());
+        }
+        parts.join("; ")
+    }
+}
+
+/// Get name of verified contract, or empty if not verified.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+fn verified_name(chain: &crate::backend::Chain, address: &str) -> Result<String, String> {
+    let meta = explorer::get_source_meta(chain, address)?;
+    if meta["SourceCode"].as_str().unwrap_or("").is_empty() {
+        return Ok(String::new());
+    }
+    Ok(meta["ContractName"].as_str().unwrap_or("").to_owned())
+}
+
+/// Summarize what address is.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - address
+pub fn summarize(chain: &crate::backend::Chain, address: &str) -> Result<Summary, String> {
+    let address = address.to_lowercase();
+    let code = explorer::get_code(chain, &address)?;
+    let name_tag = explorer::get_name_tag(chain, &address).map(|t| t.describe());
+    if code.is_empty() {
+        return Ok(Summary { address, is_contract: false, name: String::new(), implementation: None, name_tag });
+    }
+
+    let name = verified_name(chain, &address)?;
+    let implementation = match proxy::resolve_implementation(chain, &address)? {
+        Some(implementation) => {
+            let name = verified_name(chain, &implementation.address)?;
+            Some((implementation.address, name))
+        },
+        None => None,
+    };
+    Ok(Summary { address, is_contract: true, name, implementation, name_tag })
+}
+
+/// Read text from file, or stdin.
+///
+/// # Arguments
+/// * `file` - path of file, or `None`, or `-` for stdin
+fn read_text(file: Option<&String>) -> Result<String, String> {
+    match file.filter(|f| f.as_str() != "-") {
+        Some(path) => match std::fs::read_to_string(path) {
+            Ok(res) => Ok(res),
+            Err(e) => {
+                let err_msg = format!("Error reading input file '{}'; err={}", path, e);
+                Err(err_msg)
+            }
+        },
+        None => {
+            let mut text = String::new();
+            match std::io::stdin().read_to_string(&mut text) {
+                Ok(_) => Ok(text),
+                Err(e) => {
+                    let err_msg = format!("Error reading stdin; err={}", e);
+                    Err(err_msg)
+                }
+            }
+        }
+    }
+}
+
+/// Execute `enrich` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &EnrichArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.chain)?;
+    if args.format != "footnote" && args.format != "inline" {
+        let err_msg = format!("Error unknown value '{}' of --format; possible values are 'footnote', and 'inline'", args.format);
+        return Err(err_msg);
+    }
+    let text = read_text(args.file.as_ref())?;
+
+    // addresses in order they first appear, along with their annotations
+    let spans = addresses::address_spans(&text);
+    let mut found: Vec<String> = Vec::new();
+    for (start, end) in spans.iter() {
+        let address = text[*start..*end].to_lowercase();
+        if !found.contains(&address) {
+            found.push(address);
+        }
+    }
+    let annotations: Vec<Result<Summary, String>> = found.iter().map(|address| {
+        let summary = summarize(chain, address);
+        if let Err(e) = summary.as_ref() {
+            // failed addresses are reported via exit code, see `progress::tally()`
+            progress::error(Some(address), e);
+        }
+        summary
+    }).collect();
+    let describe = |i: usize| match &annotations[i] {
+        Ok(summary) => summary.describe(),
+        Err(e) => format!("failed to resolve; {}", e),
+    };
+
+    if args.json {
+        let out: Vec<Value> = found.iter().zip(annotations.iter()).map(|(address, summary)| match summary {
+            Ok(s) => json!({
+                "address": address,
+                "kind": if s.is_contract { "contract" } else { "account" },
+                "verified": Some(!s.name.is_empty()).filter(|_| s.is_contract),
+                "contractName": Some(&s.name).filter(|n| !n.is_empty()),
+                "implementation": s.implementation.as_ref().map(|(a, _)| a),
+                "implementationName": s.implementation.as_ref().map(|(_, n)| n).filter(|n| !n.is_empty()),
+                "nameTag": s.name_tag,
+                "description": s.describe(),
+            }),
+            Err(e) => json!({ "address": address, "error": e }),
+        }).collect();
+        match serde_json::to_string_pretty(&out) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing annotations to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+        return Ok(());
+    }
+
+    let mut enriched = String::new();
+    let mut last = 0;
+    let mut annotated = vec![false; found.len()];
+    for (start, end) in spans.iter() {
+        let i = found.iter().position(|a| text[*start..*end].eq_ignore_ascii_case(a)).unwrap_or(0);
+        enriched.push_str(&text[last..*end]);
+        if args.format == "footnote" {
+            enriched.push_str(&format!(" [{}]", i + 1));
+        }
+        else if !annotated[i] {
+            enriched.push_str(&format!(" [{}]", describe(i)));
+            annotated[i] = true;
+        }
+        last = *end;
+    }
+    enriched.push_str(&text[last..]);
+    print!("{}", enriched);
+
+    if args.format == "footnote" && !found.is_empty() {
+        if !enriched.ends_with('\n') {
+            println!();
+        }
+        println!("\n---");
+        for (i, address) in found.iter().enumerate() {
+            println!("[{}] {}: {}", i + 1, address, describe(i));
+        }
+    }
+    Ok(())
+}
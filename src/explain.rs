@@ -0,0 +1,105 @@
+//! `explain` command: print structured outline of verified contract.
+
+use crate::abi;
+use crate::explorer;
+use crate::solidity;
+use crate::source;
+use crate::TargetArgs;
+use clap::Args;
+use regex::Regex;
+
+#[derive(Debug, Args)]
+pub struct ExplainArgs {
+    #[clap(flatten)]
+    pub target: TargetArgs,
+}
+
+/// Notable pattern to look for in source code.
+struct Pattern {
+    name: &'static str,
+    description: &'static str,
+    regex: &'static str,
+}
+
+const PATTERNS: &[Pattern] = &[
+    Pattern { name: "ownable", description: "single owner controls privileged functions", regex: r"\bonlyOwner\b|\bOwnable\b" },
+    Pattern { name: "access-control", description: "role-based access control", regex: r"\bonlyRole\b|\bAccessControl\w*\b|\bhasRole\s*\(" },
+    Pattern { name: "pausable", description: "functions can be paused", regex: r"\bwhenNotPaused\b|\bPausable\w*\b|\b_pause\s*\(" },
+    Pattern { name: "upgradeable", description: "proxy or upgradeable implementation", regex: r"\bInitializable\b|\binitializer\b|\bUUPSUpgradeable\b|\bupgradeTo\w*\s*\(|_IMPLEMENTATION_SLOT" },
+    Pattern { name: "reentrancy-guard", description: "reentrancy guard in use", regex: r"\bnonReentrant\b|\bReentrancyGuard\w*\b" },
+    Pattern { name: "delegatecall", description: "uses delegatecall", regex: r"\.delegatecall\s*\(|\bdelegatecall\s*\(" },
+    Pattern { name: "selfdestruct", description: "can self-destruct", regex: r"\bselfdestruct\s*\(|\bsuicide\s*\(" },
+    Pattern { name: "tx-origin", description: "uses tx.origin", regex: r"\btx\.origin\b" },
+    Pattern { name: "inline-assembly", description: "contains inline assembly", regex: r"\bassembly\s*(\(|\{)" },
+];
+
+/// Execute `explain` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &ExplainArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.target.chain)?;
+    let contract_source = source::fetch_sources(chain, &args.target.address)?;
+    let contract_abi = abi::fetch_abi(chain, &args.target.address)?;
+
+    let contracts: Vec<solidity::ContractDef> = contract_source.files.iter()
+        .flat_map(|f| solidity::parse_contracts(&f.path, &f.content))
+        .collect();
+
+    match explorer::get_name_tag(chain, &args.target.address) {
+        Some(tag) => println!("{} ({}) at {} on {}", contract_source.contract_name, tag.describe(), args.target.address, args.target.chain.to_lowercase()),
+        None => println!("{} at {} on {}", contract_source.contract_name, args.target.address, args.target.chain.to_lowercase()),
+    }
+    println!("compiler {}, {} file(s)", contract_source.compiler_version, contract_source.files.len());
+
+    println!("\n## Contracts and inheritance");
+    for c in contracts.iter() {
+        let mut line = format!("{} {}", c.kind, c.name);
+        if !c.bases.is_empty() {
+            line.push_str(&format!(" is {}", c.bases.join(", ")));
+        }
+        let marker = if c.name == contract_source.contract_name { "*" } else { " " };
+        println!("{} {}  ({} functions; {})", marker, line, c.functions.len(), c.file);
+    }
+
+    println!("\n## External/public functions");
+    for mutability in ["payable", "nonpayable", "view", "pure"] {
+        let functions: Vec<&abi::Entry> = contract_abi.functions().filter(|f| f.mutability() == mutability).collect();
+        if functions.is_empty() {
+            continue;
+        }
+        println!("{}:", mutability);
+        for f in functions {
+            println!("  {}", f.declaration());
+        }
+    }
+
+    println!("\n## Modifiers");
+    for c in contracts.iter().filter(|c| !c.modifiers.is_empty()) {
+        println!("  {}: {}", c.name, c.modifiers.join(", "));
+    }
+
+    println!("\n## Events");
+    for e in contract_abi.events() {
+        println!("  {}", e.declaration());
+    }
+
+    println!("\n## Errors");
+    for e in contract_abi.errors() {
+        println!("  {}", e.declaration());
+    }
+
+    println!("\n## Notable patterns");
+    for pattern in PATTERNS {
+        let re = Regex::new(pattern.regex).unwrap();
+        let found_in: Vec<&str> = contracts.iter()
+            .filter(|c| re.is_match(&c.body) || c.bases.iter().any(|b| re.is_match(b)))
+            .map(|c| c.name.as_str())
+            .collect();
+        if !found_in.is_empty() {
+            println!("  {} - {} ({})", pattern.name, pattern.description, found_in.join(", "));
+        }
+    }
+
+    Ok(())
+}
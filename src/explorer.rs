@@ -0,0 +1,711 @@
+//! Access to explorer API endpoints through backend of the selected chain.
+//!
+//! All of supported API platforms (bscscan.com, etherscan.io, and
+//! polygonscan.com) share the same API shape, so only base url differs.
+
+use crate::backend::{self, Chain};
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Mode of capturing responses of API platform into fixture directory, or
+/// replaying them from there.
+#[derive(Debug, Clone)]
+pub enum FixtureMode {
+    /// Write every response into the directory
+    Record(String),
+
+    /// Read responses from the directory instead of sending requests
+    Replay(String),
+}
+
+/// Fixture mode applied to all requests of the running process
+static FIXTURE_MODE: Mutex<Option<FixtureMode>> = Mutex::new(None);
+
+/// Block whose state is read by `get_code`, `call`, and `get_storage_at` as
+/// block number in hex with `0x` prefix, or tag e.g. `finalized`. The latest
+/// block if `None`.
+static BLOCK_TAG: Mutex<Option<String>> = Mutex::new(None);
+
+/// Number of requests sent to API platform of each chain by the running process
+static REQUEST_COUNTS: Mutex<Vec<(String, u64)>> = Mutex::new(Vec::new());
+
+/// Get number of requests sent to API platform of each chain so far, as pairs
+/// of chain name and count.
+pub fn request_counts() -> Vec<(String, u64)> {
+    REQUEST_COUNTS.lock().map_or(Vec::new(), |counts| counts.clone())
+}
+
+/// Send request through backend of the chain, and count it, or to node at
+/// `ETH_RPC_URL` if it serves the request (see `foundry`).
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `params` - query parameters as pairs of key and value
+fn send(chain: &Chain, params: &[(&str, &str)]) -> Result<String, String> {
+    if let Some(result) = crate::foundry::send_rpc(chain, params) {
+        return result;
+    }
+    if let Ok(mut counts) = REQUEST_COUNTS.lock() {
+        match counts.iter_mut().find(|(name, _)| *name == chain.name) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((chain.name.clone(), 1)),
+        }
+    }
+    chain.backend.send(params)
+}
+
+/// Parse block given by user into block tag of JSON-RPC; block number in
+/// decimal, or hex with `0x` prefix, or one of tags `latest`, `earliest`,
+/// `pending`, `safe`, and `finalized`.
+///
+/// # Arguments
+/// * `block` - block number, or tag
+pub fn parse_block_tag(block: &str) -> Result<String, String> {
+    let block = block.trim().to_lowercase();
+    if ["latest", "earliest", "pending", "safe", "finalized"].contains(&block.as_str()) {
+        return Ok(block);
+    }
+    let number = match block.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => block.parse::<u64>().ok(),
+    };
+    match number {
+        Some(res) => Ok(format!("{:#x}", res)),
+        None => {
+            let err_msg = format!("Error invalid value for --block; '{}' is neither block number, nor one of 'latest', 'earliest', 'pending', 'safe', and 'finalized'", block);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Set block whose state is read by all following reads of state.
+///
+/// # Arguments
+/// * `tag` - block tag as parsed by `parse_block_tag`, or `None` for the
+///   latest block
+pub fn set_block_tag(tag: Option<String>) {
+    if let Ok(mut current) = BLOCK_TAG.lock() {
+        *current = tag;
+    }
+}
+
+/// Get block tag whose state is read e.g. `latest`, or `0x10d4f`.
+pub fn block_tag() -> String {
+    BLOCK_TAG.lock().ok().and_then(|t| t.clone()).unwrap_or_else(|| "latest".to_owned())
+}
+
+/// Set fixture mode applied to all following requests.
+///
+/// # Arguments
+/// * `mode` - fixture mode, or `None` to send requests normally
+pub fn set_fixture_mode(mode: Option<FixtureMode>) {
+    if let Ok(mut current) = FIXTURE_MODE.lock() {
+        *current = mode;
+    }
+}
+
+/// Query explorer API then return the whole response body as text, recording
+/// or replaying it as of fixture mode, or through cache if enabled.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `params` - query parameters as pairs of key and value
+pub fn query_text(chain: &Chain, params: &[(&str, &str)]) -> Result<String, String> {
+    let fixture_mode = FIXTURE_MODE.lock().ok().and_then(|m| m.clone());
+    let body = match fixture_mode {
+        Some(FixtureMode::Replay(dir)) => {
+            let path = fixture_path(&dir, chain, params)?;
+            match std::fs::read_to_string(&path) {
+                Ok(res) => res,
+                Err(e) => {
+                    let err_msg = format!("Error reading recorded response at '{}'; err={}", path, e);
+                    return Err(err_msg);
+                }
+            }
+        },
+        Some(FixtureMode::Record(dir)) => {
+            let body = send(chain, params)?;
+            let path = fixture_path(&dir, chain, params)?;
+            let _lock = crate::lock::DirLock::acquire(&dir)?;
+            crate::write_file(&path, &body)?;
+            body
+        },
+        None => match (crate::cache::settings(), crate::cache::cacheable_address(params)) {
+            (Some(settings), Some(address)) => crate::cache::read_through(&settings, &chain.name, address, || send(chain, params))?,
+            _ => send(chain, params)?,
+        },
+    };
+    Ok(body)
+}
+
+/// Query explorer API then return value of `result` field from the response.
+///
+/// Response in case of "no records found" is treated as success with an empty
+/// array. Responses from `proxy` module follow JSON-RPC shape, and are handled
+/// here as well.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `params` - query parameters as pairs of key and value
+pub fn query(chain: &Chain, params: &[(&str, &str)]) -> Result<serde_json::Value, String> {
+    let body = query_text(chain, params)?;
+    // don't hold raw text of large payloads alongside its parsed form
+    parse_body(&body)
+}
+
+/// Parse response body from API platform then return value of its `result`
+/// field as of `query()`.
+///
+/// # Arguments
+/// * `body` - response body
+pub fn parse_body(body: &str) -> Result<serde_json::Value, String> {
+    let _timer = crate::perf::time(crate::perf::Phase::Decode);
+    let json: serde_json::Value = match serde_json::from_str(body) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error parsing response from API platform as JSON; err={}", e);
+            return Err(err_msg);
+        }
+    };
+    parse_response(json)
+}
+
+/// Get path of fixture file for request. Its filename is derived from chain,
+/// and query parameters regardless of their order, but not API key, so
+/// recorded fixtures can be shared.
+///
+/// # Arguments
+/// * `dir` - fixture directory
+/// * `chain` - chain
+/// * `params` - query parameters as pairs of key and value
+fn fixture_path(dir: &str, chain: &Chain, params: &[(&str, &str)]) -> Result<String, String> {
+    let mut sorted_params: Vec<String> = params.iter().map(|(key, value)| format!("{}={}", key, backend::encode_query_value(value))).collect();
+    sorted_params.sort();
+    let key = format!("{}?{}", chain.name, sorted_params.join("&"));
+    let hash = crate::abi::to_hex(&crate::abi::keccak256(key.as_bytes())[..8]);
+
+    let param = |name: &str| params.iter().find(|(key, _)| *key == name).map_or("", |(_, value)| value);
+    let filename = format!("{}-{}-{}-{}.json", chain.name, param("module"), param("action"), hash);
+    crate::combine_two_path_components(dir, &filename)
+}
+
+/// Validate response from API platform then extract its `result` field.
+///
+/// # Arguments
+/// * `json` - whole response as JSON
+fn parse_response(mut json: serde_json::Value) -> Result<serde_json::Value, String> {
+    // JSON-RPC shape from `proxy` module
+    if json.get("jsonrpc").is_some() {
+        if let Some(error) = json.get("error") {
+            let err_msg = format!("Error from API platform; err={}", error);
+            return Err(err_msg);
+        }
+        return Ok(json["result"].take());
+    }
+
+    let status = json["status"].as_str().unwrap_or("0").to_owned();
+    let message = json["message"].as_str().unwrap_or("").to_owned();
+    if status == "1" {
+        return Ok(json["result"].take());
+    }
+
+    // no records is not an error, just nothing to return
+    if message.starts_with("No transactions found") || message.starts_with("No records found") || message.starts_with("No data found") {
+        return Ok(serde_json::Value::Array(Vec::new()));
+    }
+
+    let err_msg = format!("Error from API platform; message={}, result={}", message, json["result"]);
+    Err(err_msg)
+}
+
+/// Get verification metadata of contract e.g. `ContractName`, `CompilerVersion`,
+/// `OptimizationUsed`, `Runs`, `EVMVersion`, and `LicenseType` as returned by
+/// `getsourcecode` endpoint.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+pub fn get_source_meta(chain: &Chain, address: &str) -> Result<serde_json::Value, String> {
+    source_meta_of(address, query(chain, &source_meta_params(address))?)
+}
+
+/// Get query parameters of `getsourcecode` endpoint for contract.
+///
+/// # Arguments
+/// * `address` - contract address
+pub fn source_meta_params(address: &str) -> [(&str, &str); 3] {
+    [("module", "contract"), ("action", "getsourcecode"), ("address", address)]
+}
+
+/// Get verification metadata of contract out of `result` field of response
+/// from `getsourcecode` endpoint.
+///
+/// # Arguments
+/// * `address` - contract address
+/// * `result` - `result` field of response
+pub fn source_meta_of(address: &str, mut result: serde_json::Value) -> Result<serde_json::Value, String> {
+    match result.get_mut(0) {
+        Some(res) => Ok(res.take()),
+        None => {
+            let err_msg = format!("Error no source code information of {}", address);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Verification metadata of contract with its source code left undecoded,
+/// borrowed from response body of `getsourcecode` endpoint.
+#[derive(Debug)]
+pub struct RawSourceMeta<'a> {
+    /// Verification metadata as of `get_source_meta()` except `SourceCode`
+    pub meta: serde_json::Value,
+
+    /// `SourceCode` as JSON string literal
+    pub source_code: &'a RawValue,
+
+    /// Result object of contract exactly as served by API platform
+    pub entry: &'a RawValue,
+}
+
+impl RawSourceMeta<'_> {
+    /// Whether or not contract has verified source code.
+    pub fn is_verified(&self) -> bool {
+        self.source_code.get() != "\"\""
+    }
+}
+
+/// Get response body of `getsourcecode` endpoint as text, to be parsed with
+/// `parse_source_meta_raw()` without decoding source code as a whole.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+pub fn get_source_meta_text(chain: &Chain, address: &str) -> Result<String, String> {
+    query_text(chain, &[
+        ("module", "contract"),
+        ("action", "getsourcecode"),
+        ("address", address),
+    ])
+}
+
+/// Parse response body of `getsourcecode` endpoint into verification metadata
+/// while leaving source code undecoded, as it can be tens of MB.
+///
+/// # Arguments
+/// * `address` - contract address
+/// * `body` - response body
+pub fn parse_source_meta_raw<'a>(address: &str, body: &'a str) -> Result<RawSourceMeta<'a>, String> {
+    #[derive(Deserialize)]
+    struct Envelope<'a> {
+        status: Option<String>,
+        #[serde(borrow)]
+        result: Option<&'a RawValue>,
+    }
+
+    let _timer = crate::perf::time(crate::perf::Phase::Decode);
+    let envelope: Envelope = match serde_json::from_str(body) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error parsing response from API platform as JSON; err={}", e);
+            return Err(err_msg);
+        }
+    };
+
+    // let the usual path report whatever else it is; it is small
+    let result = match (envelope.status.as_deref(), envelope.result) {
+        (Some("1"), Some(result)) => result,
+        _ => {
+            let json: serde_json::Value = serde_json::from_str(body).unwrap_or_default();
+            parse_response(json)?;
+            let err_msg = format!("Error no source code information of {}", address);
+            return Err(err_msg);
+        }
+    };
+
+    let entries: Vec<&'a RawValue> = match serde_json::from_str(result.get()) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error parsing source code information of {}; err={}", address, e);
+            return Err(err_msg);
+        }
+    };
+    let raw_entry = entries.first().copied();
+    let (meta, source_code) = match raw_entry {
+        Some(raw) => {
+            let entry: BTreeMap<String, &'a RawValue> = match serde_json::from_str(raw.get()) {
+                Ok(res) => res,
+                Err(e) => {
+                    let err_msg = format!("Error parsing source code information of {}; err={}", address, e);
+                    return Err(err_msg);
+                }
+            };
+            let mut meta = serde_json::Map::new();
+            let mut source_code = None;
+            for (key, value) in entry {
+                if key == "SourceCode" {
+                    source_code = Some(value);
+                }
+                else {
+                    meta.insert(key, serde_json::from_str(value.get()).unwrap_or_default());
+                }
+            }
+            (meta, source_code)
+        },
+        None => (serde_json::Map::new(), None),
+    };
+
+    match source_code.zip(raw_entry) {
+        Some((source_code, entry)) => Ok(RawSourceMeta { meta: serde_json::Value::Object(meta), source_code, entry }),
+        None => {
+            let err_msg = format!("Error no source code information of {}", address);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Get runtime bytecode deployed at address as of block tag (see
+/// `set_block_tag`). It is empty if there is no contract at address.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+pub fn get_code(chain: &Chain, address: &str) -> Result<Vec<u8>, String> {
+    let tag = block_tag();
+    let result = query(chain, &[
+        ("module", "proxy"),
+        ("action", "eth_getCode"),
+        ("address", address),
+        ("tag", &tag),
+    ])?;
+
+    match result.as_str() {
+        Some(res) => crate::abi::from_hex(res),
+        None => {
+            let err_msg = format!("Error unexpected response for code of {}; result={}", address, result);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Call contract read-only as of block tag (see `set_block_tag`), and return
+/// its return data.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `to` - contract address
+/// * `data` - calldata as hex string with `0x` prefix
+pub fn call(chain: &Chain, to: &str, data: &str) -> Result<Vec<u8>, String> {
+    let tag = block_tag();
+    let result = query(chain, &[
+        ("module", "proxy"),
+        ("action", "eth_call"),
+        ("to", to),
+        ("data", data),
+        ("tag", &tag),
+    ])?;
+
+    match result.as_str() {
+        Some(res) => crate::abi::from_hex(res),
+        None => {
+            let err_msg = format!("Error unexpected response for call to {}; result={}", to, result);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Get 32-byte value of storage slot of contract as of block tag (see
+/// `set_block_tag`).
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+/// * `slot` - storage slot as hex string with `0x` prefix
+pub fn get_storage_at(chain: &Chain, address: &str, slot: &str) -> Result<Vec<u8>, String> {
+    let tag = block_tag();
+    let result = query(chain, &[
+        ("module", "proxy"),
+        ("action", "eth_getStorageAt"),
+        ("address", address),
+        ("position", slot),
+        ("tag", &tag),
+    ])?;
+
+    match result.as_str() {
+        Some(res) => crate::abi::from_hex(res),
+        None => {
+            let err_msg = format!("Error unexpected response for storage of {} at {}; result={}", address, slot, result);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Creation of contract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Creation {
+    /// Address which deployed contract, either account, or factory contract
+    pub creator: String,
+
+    /// Hash of transaction which deployed contract
+    pub tx_hash: String,
+
+    /// Unix timestamp (in seconds) of deployment, or `None` if explorer
+    /// doesn't report it
+    pub timestamp: Option<i64>,
+}
+
+/// Get creation of contract, or `None` if explorer doesn't know about any.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+pub fn get_creation(chain: &Chain, address: &str) -> Result<Option<Creation>, String> {
+    let result = query(chain, &[
+        ("module", "contract"),
+        ("action", "getcontractcreation"),
+        ("contractaddresses", address),
+    ])?;
+
+    let entry = match result.get(0) {
+        Some(res) => res,
+        None => return Ok(None),
+    };
+    Ok(Some(Creation {
+        creator: entry["contractCreator"].as_str().unwrap_or("").to_lowercase(),
+        tx_hash: entry["txHash"].as_str().unwrap_or("").to_owned(),
+        timestamp: entry["timestamp"].as_str().and_then(|t| t.parse().ok()).or_else(|| entry["timestamp"].as_i64()),
+    }))
+}
+
+/// Contract created by an address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Created {
+    /// Address of contract created
+    pub address: String,
+
+    pub block_number: u64,
+    pub tx_hash: String,
+
+    /// Unix timestamp (in seconds) of creation
+    pub timestamp: i64,
+
+    /// `CREATE`, or `CREATE2` by contract, or `deploy` by transaction of
+    /// account without recipient
+    pub kind: String,
+}
+
+/// Get contracts created by address from block onwards in order of creation;
+/// ones deployed by its transactions directly, and ones it created as
+/// contract e.g. factory.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - address of deployer, or factory
+/// * `start_block` - block number to list creations from
+pub fn get_created(chain: &Chain, address: &str, start_block: u64) -> Result<Vec<Created>, String> {
+    let address = address.to_lowercase();
+    let start_block = start_block.to_string();
+    let field = |tx: &serde_json::Value, name: &str| tx[name].as_str().unwrap_or("").to_owned();
+    let mut created: Vec<Created> = Vec::new();
+    for action in ["txlist", "txlistinternal"] {
+        let result = query(chain, &[
+            ("module", "account"),
+            ("action", action),
+            ("address", &address),
+            ("startblock", &start_block),
+            ("endblock", "99999999999"),
+            ("sort", "asc"),
+        ])?;
+        let txs = match result.as_array() {
+            Some(res) => res,
+            None => {
+                let err_msg = format!("Error unexpected response for transactions of {}; result={}", address, result);
+                return Err(err_msg);
+            }
+        };
+
+        for tx in txs.iter() {
+            let contract_address = field(tx, "contractAddress").to_lowercase();
+            if contract_address.is_empty() || !field(tx, "to").is_empty() || field(tx, "isError") == "1" {
+                continue;
+            }
+            let kind = if action == "txlist" {
+                "deploy".to_owned()
+            }
+            else if field(tx, "from").eq_ignore_ascii_case(&address) {
+                field(tx, "type").to_uppercase()
+            }
+            else {
+                continue;
+            };
+            created.push(Created {
+                address: contract_address,
+                block_number: field(tx, "blockNumber").parse().unwrap_or(0),
+                tx_hash: field(tx, "hash"),
+                timestamp: field(tx, "timeStamp").parse().unwrap_or(0),
+                kind,
+            });
+        }
+    }
+    created.sort_by_key(|c| c.block_number);
+    let mut seen = std::collections::HashSet::new();
+    created.retain(|c| seen.insert(c.address.clone()));
+    Ok(created)
+}
+
+/// Get input of transaction if it deploys contract directly i.e. it has no
+/// recipient, then input is creation bytecode followed by constructor
+/// arguments. Return `None` for any other transaction e.g. call to factory.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `tx_hash` - transaction hash
+pub fn get_deployment_input(chain: &Chain, tx_hash: &str) -> Result<Option<Vec<u8>>, String> {
+    let result = query(chain, &[
+        ("module", "proxy"),
+        ("action", "eth_getTransactionByHash"),
+        ("txhash", tx_hash),
+    ])?;
+
+    if !result["to"].is_null() {
+        return Ok(None);
+    }
+    match result["input"].as_str() {
+        Some(res) => crate::abi::from_hex(res).map(Some),
+        None => {
+            let err_msg = format!("Error unexpected response for transaction {}; result={}", tx_hash, result);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Get contract ABI as JSON text.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+/// * `pretty` - whether or not to pretty print
+pub fn get_abi(chain: &Chain, address: &str, pretty: bool) -> Result<String, String> {
+    let abi = abi_of(address, query(chain, &abi_params(address))?)?;
+    crate::selectors::index_abi(&abi);
+    if !pretty {
+        return Ok(abi);
+    }
+    pretty_abi(address, &abi)
+}
+
+/// Get query parameters of `getabi` endpoint for contract.
+///
+/// # Arguments
+/// * `address` - contract address
+pub fn abi_params(address: &str) -> [(&str, &str); 3] {
+    [("module", "contract"), ("action", "getabi"), ("address", address)]
+}
+
+/// Get contract ABI as JSON text out of `result` field of response from
+/// `getabi` endpoint.
+///
+/// # Arguments
+/// * `address` - contract address
+/// * `result` - `result` field of response
+pub fn abi_of(address: &str, result: serde_json::Value) -> Result<String, String> {
+    match result.as_str() {
+        Some(res) => Ok(res.to_owned()),
+        None => {
+            let err_msg = format!("Error no contract ABI of {}", address);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Pretty print contract ABI.
+///
+/// # Arguments
+/// * `address` - contract address
+/// * `abi` - contract ABI as JSON text
+pub fn pretty_abi(address: &str, abi: &str) -> Result<String, String> {
+    match serde_json::from_str::<serde_json::Value>(abi).map(|v| serde_json::to_string_pretty(&v)) {
+        Ok(Ok(res)) => Ok(res),
+        _ => {
+            let err_msg = format!("Error parsing contract ABI of {}", address);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Public name tag, and labels explorer gives to address e.g.
+/// "Binance: Hot Wallet" labelled "Exchange".
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NameTag {
+    #[serde(rename="nameTag", default)]
+    pub name_tag: String,
+
+    #[serde(default)]
+    pub labels: Vec<String>,
+}
+
+impl NameTag {
+    /// Whether explorer gives neither name tag, nor labels.
+    pub fn is_empty(&self) -> bool {
+        self.name_tag.is_empty() && self.labels.is_empty()
+    }
+
+    /// Describe as text e.g. `Binance: Hot Wallet [Exchange]`.
+    pub fn describe(&self) -> String {
+        match (self.name_tag.is_empty(), self.labels.is_empty()) {
+            (false, false) => format!("{} [{}]", self.name_tag, self.labels.join(", ")),
+            (false, true) => self.name_tag.clone(),
+            _ => format!("[{}]", self.labels.join(", ")),
+        }
+    }
+}
+
+/// Chains whose API platform failed to give name tag, so it isn't asked again
+/// by the running process
+static NAME_TAG_UNSUPPORTED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Get public name tag, and labels of address, or `None` if explorer has none.
+///
+/// Not every API platform exposes them, so failure is not an error but
+/// `None`, and such platform isn't asked again. If cache is enabled, answers
+/// are kept in its index of name tags (see `cache`), including lack of them.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - address
+pub fn get_name_tag(chain: &Chain, address: &str) -> Option<NameTag> {
+    let address = address.to_lowercase();
+    let cache_dir = crate::cache::settings().map(|s| s.dir);
+    if let Some(tag) = cache_dir.as_ref().and_then(|dir| crate::cache::cached_name_tag(dir, &chain.name, &address)) {
+        return Some(tag).filter(|t| !t.is_empty());
+    }
+    if NAME_TAG_UNSUPPORTED.lock().is_ok_and(|u| u.contains(&chain.name)) {
+        return None;
+    }
+
+    let result = match query(chain, &[
+        ("module", "nametag"),
+        ("action", "getaddresstag"),
+        ("address", &address),
+    ]) {
+        Ok(res) => res,
+        Err(_) => {
+            if let Ok(mut unsupported) = NAME_TAG_UNSUPPORTED.lock() {
+                unsupported.push(chain.name.clone());
+            }
+            return None;
+        }
+    };
+    let entry = result.get(0).unwrap_or(&serde_json::Value::Null);
+    let tag = NameTag {
+        name_tag: entry["nametag"].as_str().unwrap_or("").to_owned(),
+        labels: entry["labels"].as_array().map_or(Vec::new(), |l| l.iter().filter_map(|l| l.as_str()).map(|l| l.to_owned()).collect()),
+    };
+    if let Some(dir) = cache_dir.as_ref() {
+        if let Err(e) = crate::cache::store_name_tag(dir, &chain.name, &address, &tag) {
+            eprintln!("Warning: {}", e);
+        }
+    }
+    Some(tag).filter(|t| !t.is_empty())
+}
@@ -0,0 +1,248 @@
+//! `export` command: dump fetched contracts under a directory as a dataset
+//! for analysis, one row per file along with its contract as recorded in
+//! manifest of output directory.
+//!
+//! Every directory holding manifest (see `manifest`) is exported e.g. output
+//! directory of `batch`, or `sync`. Symlinks are not followed, so `latest` of
+//! snapshots doesn't duplicate rows.
+
+use crate::manifest::{self, Manifest};
+use crate::parquet::{self, Column};
+use clap::Args;
+use serde_json::json;
+use std::io::Write;
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// Directory of fetched contracts e.g. output directory of `batch`
+    #[clap(value_name="DIR", required=true)]
+    pub dir: String,
+
+    /// Output format.
+    /// Possible values are 'jsonl', 'csv', and 'parquet'.
+    #[clap(long="format", required=false, default_value="jsonl")]
+    pub format: String,
+
+    /// Filepath to write dataset to. If not specified, it will be printed to
+    /// stdout. Required for 'parquet'.
+    #[clap(long="out", short='o', required=false)]
+    pub out_path: Option<String>,
+}
+
+/// Format of exported dataset.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Format {
+    Jsonl,
+    Csv,
+    Parquet,
+}
+
+/// Single file of fetched contract.
+#[derive(Debug)]
+struct Row {
+    /// Directory of contract relative to exported directory
+    dir: String,
+
+    address: String,
+    chain: String,
+    backend: String,
+
+    /// Path of file relative to directory of contract
+    path: String,
+
+    keccak256: String,
+    size: u64,
+    content: String,
+}
+
+/// Columns of dataset in order
+const COLUMNS: [&str; 8] = ["dir", "address", "chain", "backend", "path", "keccak256", "size", "content"];
+
+/// Parse value of `--format`.
+///
+/// # Arguments
+/// * `format` - value of format flag option
+fn parse_format(format: &str) -> Result<Format, String> {
+    match format.to_lowercase().as_str() {
+        "jsonl" => Ok(Format::Jsonl),
+        "csv" => Ok(Format::Csv),
+        "parquet" => Ok(Format::Parquet),
+        _ => Err("Error invalid value for --format.\nPossible values are 'jsonl', 'csv', or 'parquet'.".to_owned()),
+    }
+}
+
+/// Find directories holding manifest under directory, sorted by path.
+///
+/// # Arguments
+/// * `dir` - directory to search
+/// * `found` - found directories are appended to it
+fn find_contract_dirs(dir: &std::path::Path, found: &mut Vec<std::path::PathBuf>) -> Result<(), String> {
+    if dir.join(manifest::MANIFEST_FILENAME).is_file() {
+        found.push(dir.to_path_buf());
+    }
+
+    let read_dir = match std::fs::read_dir(dir) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error reading directory '{}'; err={}", dir.display(), e);
+            return Err(err_msg);
+        }
+    };
+    let mut subdirs: Vec<std::path::PathBuf> = read_dir
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|entry| entry.path())
+        .collect();
+    subdirs.sort();
+    for subdir in subdirs.iter() {
+        find_contract_dirs(subdir, found)?;
+    }
+    Ok(())
+}
+
+/// Collect rows of every file of contracts under directory. Returns rows,
+/// and number of contracts.
+///
+/// # Arguments
+/// * `dir` - exported directory
+fn collect_rows(dir: &str) -> Result<(Vec<Row>, usize), String> {
+    let root = std::path::Path::new(dir);
+    if !root.is_dir() {
+        let err_msg = format!("Error '{}' is not a directory", dir);
+        return Err(err_msg);
+    }
+    let mut contract_dirs = Vec::new();
+    find_contract_dirs(root, &mut contract_dirs)?;
+
+    let mut rows = Vec::new();
+    for contract_dir in contract_dirs.iter() {
+        let contract_dir_str = contract_dir.to_string_lossy();
+        let Manifest { address, chain, backend, files, .. } = match manifest::read_manifest(&contract_dir_str)? {
+            Some(res) => res,
+            None => continue,
+        };
+        let relative_dir = contract_dir.strip_prefix(root).map_or(String::new(), |p| p.to_string_lossy().into_owned());
+
+        for (path, entry) in files.into_iter() {
+            let filepath = contract_dir.join(&path);
+            // source code written by tracpls is text, but file might have been
+            // replaced since
+            let content = match std::fs::read(&filepath) {
+                Ok(res) => String::from_utf8_lossy(&res).into_owned(),
+                Err(e) => {
+                    let err_msg = format!("Error reading '{}' listed in manifest; err={}", filepath.display(), e);
+                    return Err(err_msg);
+                }
+            };
+            rows.push(Row {
+                dir: relative_dir.clone(),
+                address: address.clone(),
+                chain: chain.clone(),
+                backend: backend.clone(),
+                path,
+                keccak256: entry.keccak256,
+                size: entry.size,
+                content,
+            });
+        }
+    }
+    Ok((rows, contract_dirs.len()))
+}
+
+/// Quote field of CSV if needed as of RFC 4180.
+///
+/// # Arguments
+/// * `field` - value of field
+pub fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    }
+    else {
+        field.to_owned()
+    }
+}
+
+/// Encode rows in format.
+///
+/// # Arguments
+/// * `rows` - rows
+/// * `format` - format
+fn encode(rows: &[Row], format: Format) -> Result<Vec<u8>, String> {
+    match format {
+        Format::Jsonl => {
+            let mut out = String::new();
+            for row in rows.iter() {
+                let line = json!({
+                    "dir": row.dir,
+                    "address": row.address,
+                    "chain": row.chain,
+                    "backend": row.backend,
+                    "path": row.path,
+                    "keccak256": row.keccak256,
+                    "size": row.size,
+                    "content": row.content,
+                });
+                out.push_str(&line.to_string());
+                out.push('\n');
+            }
+            Ok(out.into_bytes())
+        },
+        Format::Csv => {
+            let mut out = format!("{}\r\n", COLUMNS.join(","));
+            for row in rows.iter() {
+                let size = row.size.to_string();
+                let fields = [&row.dir, &row.address, &row.chain, &row.backend, &row.path, &row.keccak256, &size, &row.content];
+                out.push_str(&fields.iter().map(|f| csv_field(f)).collect::<Vec<String>>().join(","));
+                out.push_str("\r\n");
+            }
+            Ok(out.into_bytes())
+        },
+        Format::Parquet => {
+            let text = |field: fn(&Row) -> &String| Column::Utf8(rows.iter().map(|r| field(r).clone()).collect());
+            let columns = [
+                ("dir", text(|r| &r.dir)),
+                ("address", text(|r| &r.address)),
+                ("chain", text(|r| &r.chain)),
+                ("backend", text(|r| &r.backend)),
+                ("path", text(|r| &r.path)),
+                ("keccak256", text(|r| &r.keccak256)),
+                ("size", Column::Int64(rows.iter().map(|r| r.size as i64).collect())),
+                ("content", text(|r| &r.content)),
+            ];
+            parquet::encode(&columns, &format!("tracpls version {}", env!("CARGO_PKG_VERSION")))
+        },
+    }
+}
+
+/// Execute `export` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &ExportArgs) -> Result<(), String> {
+    let format = parse_format(&args.format)?;
+    if format == Format::Parquet && args.out_path.is_none() {
+        return Err("Error --out is required for 'parquet' format".to_owned());
+    }
+
+    let (rows, contracts) = collect_rows(&args.dir)?;
+    let file_count = rows.len();
+    let bytes = encode(&rows, format)?;
+
+    match args.out_path.as_ref() {
+        Some(out_path) => {
+            crate::create_intermediate_dirs(out_path)?;
+            if let Err(e) = std::fs::write(out_path, &bytes) {
+                let err_msg = format!("Error writing dataset at '{}'; err={}", out_path, e);
+                return Err(err_msg);
+            }
+            eprintln!("Exported {} file(s) of {} contract(s) to {}", file_count, contracts, out_path);
+        },
+        None => {
+            if let Err(e) = std::io::stdout().write_all(&bytes) {
+                let err_msg = format!("Error writing dataset to stdout; err={}", e);
+                return Err(err_msg);
+            }
+        }
+    }
+    Ok(())
+}
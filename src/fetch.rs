@@ -0,0 +1,416 @@
+//! Fetch verified source code or ABI of contract as list of files to output.
+
+use crate::artifact::{self, ArtifactFormat};
+use crate::explorer;
+use crate::formatter::{self, FormatOptions};
+use crate::interfaces;
+use crate::layout::{self, Layout};
+use crate::safe_path;
+use crate::solc;
+use crate::source::{self, Order};
+use crate::transform_log::{self, FileTransforms};
+use crate::backend::Chain;
+use std::collections::HashSet;
+
+/// Options of what to fetch and how to lay it out.
+#[derive(Debug, Clone)]
+pub struct FetchOptions {
+    /// Get only contract ABI
+    pub abi_only: bool,
+
+    /// Pretty print contract ABI
+    pub abi_pretty_print: bool,
+
+    /// Clean CR/LF of content as per platform
+    pub clean_crlf: bool,
+
+    /// Directory layout to place files of packages in
+    pub layout: Layout,
+
+    /// Also include project files i.e. `remappings.txt`, and toolchain files
+    /// of the layout. These only make sense when written to disk.
+    pub project_files: bool,
+
+    /// Also include compiler artifact of contract shaped for the tooling.
+    /// It's only included along with project files.
+    pub artifact_format: Option<ArtifactFormat>,
+
+    /// Format Solidity source code, or keep it as verified if `None`
+    pub format: Option<FormatOptions>,
+
+    /// Order of source files of multi-file submission
+    pub order: Order,
+
+    /// Canonicalize newlines, trailing whitespace, and JSON of source files,
+    /// and ABI so that copies of the same code diff empty
+    pub canonical: bool,
+
+    /// Also include result object of contract exactly as served by API
+    /// platform. It's only included along with project files.
+    pub raw_response: bool,
+
+    /// Keep only interface, and abstract contract files, generating
+    /// interface of the primary contract from its ABI
+    pub interfaces_only: bool,
+
+    /// Drop source files not reachable from the primary contract through
+    /// imports
+    pub prune_unreachable: bool,
+
+    /// Contract to treat as primary by name, or fully qualified name e.g.
+    /// `contracts/Vault.sol:Vault` instead of verified one
+    pub contract: Option<String>,
+}
+
+/// Filename of result object of contract exactly as served by API platform
+pub const RAW_RESPONSE_FILENAME: &str = "explorer-response.json";
+
+/// Files fetched for contract.
+#[derive(Debug, Clone)]
+pub struct Fetched {
+    /// Pairs of relative path to output at, and content
+    pub files: Vec<(String, String)>,
+
+    /// Whether source code was submitted as multiple files
+    pub is_multi_file: bool,
+}
+
+/// Outcome of handing fetched files to output one by one.
+#[derive(Debug, Clone)]
+pub struct WrittenEach {
+    /// Number of files written
+    pub count: usize,
+
+    /// Compiler settings used for verification
+    pub settings: serde_json::Value,
+}
+
+/// Get project files i.e. `remappings.txt`, and toolchain files of the layout.
+///
+/// # Arguments
+/// * `options` - fetch options
+/// * `meta` - verification metadata
+/// * `settings` - compiler settings used for verification
+/// * `is_multi_file` - whether source code was submitted as multiple files
+/// * `paths` - paths of source files as verified
+/// * `placed_paths` - paths of source files as placed by the layout
+fn project_files(options: &FetchOptions, meta: &serde_json::Value, settings: &serde_json::Value, is_multi_file: bool, paths: &[&str], placed_paths: &[String]) -> Vec<(String, String)> {
+    let mut files = Vec::new();
+
+    // let imports of packages resolve in placed directories
+    let remappings = if is_multi_file { layout::remappings(options.layout, paths) } else { Vec::new() };
+    if !remappings.is_empty() {
+        files.push(("remappings.txt".to_owned(), format!("{}\n", remappings.join("\n"))));
+    }
+
+    // pin toolchain to what was used for verification
+    if options.layout != Layout::Flat {
+        files.extend(layout::toolchain_files(options.layout, meta, settings, placed_paths, &remappings));
+    }
+    files
+}
+
+/// Clean CR/LF, canonicalize, and format source file as per fetch options,
+/// recording each pass into transform log if enabled. Only Solidity source
+/// code is formatted.
+///
+/// # Arguments
+/// * `options` - fetch options
+/// * `address` - contract address
+/// * `path` - path of source file as verified
+/// * `placed` - path of source file as placed by the layout
+/// * `content` - content of source file as decoded from response
+fn transform(options: &FetchOptions, address: &str, path: &str, placed: &str, content: String) -> String {
+    let _timer = crate::perf::time(crate::perf::Phase::Transform);
+    let mut log = transform_log::is_enabled().then(|| FileTransforms::new(address, path, &content));
+    let mut content = content;
+    let mut run_pass = |name: &str, content: &mut String, f: &dyn Fn(&str) -> String| {
+        let after = f(content);
+        if let Some(log) = log.as_mut() {
+            log.pass(name, content, &after);
+        }
+        *content = after;
+    };
+
+    if options.clean_crlf {
+        run_pass("clean-crlf", &mut content, &crate::clean_crlf);
+    }
+    if options.canonical {
+        run_pass("canonicalize", &mut content, &|c| formatter::canonicalize(path, c));
+    }
+    match options.format.as_ref() {
+        Some(format) if formatter::is_formattable(path) => run_pass("format", &mut content, &|c| formatter::format_source(c, format)),
+        _ => (),
+    }
+
+    if let Some(log) = log {
+        log.finish(placed, &content);
+    }
+    content
+}
+
+/// Put source files in order, then select ones to output as per fetch
+/// options. Returns the primary contract by name, or fully qualified name.
+///
+/// # Arguments
+/// * `options` - fetch options
+/// * `address` - contract address
+/// * `files` - source files
+/// * `contract_name` - name of verified contract
+/// * `abi_text` - ABI of verified contract in JSON
+fn select_files(options: &FetchOptions, address: &str, files: &mut Vec<source::SourceFile>, contract_name: &str, abi_text: &str) -> Result<String, String> {
+    let primary = match options.contract.as_ref() {
+        Some(selector) => source::select_primary(files, selector)?,
+        None => contract_name.to_owned(),
+    };
+    source::order_files(files, &primary, options.order);
+
+    if options.prune_unreachable {
+        let total = files.len();
+        let dropped = source::prune_unreachable(files, &primary);
+        if dropped > 0 {
+            eprintln!("Pruned {} of {} file(s) of {} not reachable from {}", dropped, total, address, primary);
+        }
+    }
+    if options.interfaces_only {
+        // ABI served belongs to verified contract only
+        let abi_text = (source::contract_of(&primary) == contract_name).then_some(abi_text);
+        interfaces::keep_interfaces(files, address, source::contract_of(&primary), abi_text);
+    }
+    Ok(primary)
+}
+
+/// Fetch verified source code, or only ABI of contract as files to output.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+/// * `options` - fetch options
+pub fn fetch(chain: &Chain, address: &str, options: &FetchOptions) -> Result<Fetched, String> {
+    let mut files: Vec<(String, String)> = Vec::new();
+
+    if options.abi_only {
+        let abi = explorer::get_abi(chain, address, options.abi_pretty_print)?;
+        files.push(("abi.json".to_owned(), transform(options, address, "abi.json", "abi.json", abi)));
+        return Ok(Fetched { files, is_multi_file: false });
+    }
+
+    // keep bytes of result object as served, before they are parsed
+    let mut raw_entry = None;
+    let meta = if options.raw_response && options.project_files {
+        let body = explorer::get_source_meta_text(chain, address)?;
+        let entry = explorer::parse_source_meta_raw(address, &body)?.entry.get().to_owned();
+        let meta = match serde_json::from_str(&entry) {
+            Ok(res) => res,
+            Err(e) => {
+                let err_msg = format!("Error parsing source code information of {}; err={}", address, e);
+                return Err(err_msg);
+            }
+        };
+        raw_entry = Some(entry);
+        meta
+    }
+    else {
+        explorer::get_source_meta(chain, address)?
+    };
+    let mut contract_source = {
+        let _timer = crate::perf::time(crate::perf::Phase::Decode);
+        source::sources_from_meta(address, &meta)?
+    };
+    select_files(options, address, &mut contract_source.files, &contract_source.contract_name, meta["ABI"].as_str().unwrap_or(""))?;
+    let mut taken: HashSet<String> = HashSet::new();
+    for f in contract_source.files.iter() {
+        let placed = layout::disambiguate_case(&safe_path::escape_relative_path(&layout::place(options.layout, &f.path)), &mut taken);
+        let content = transform(options, address, &f.path, &placed, f.content.clone());
+        files.push((placed, content));
+    }
+
+    if options.project_files {
+        let paths: Vec<&str> = contract_source.files.iter().map(|f| f.path.as_str()).collect();
+        let placed_paths: Vec<String> = files.iter().map(|(path, _)| path.clone()).collect();
+        let settings = solc::verified_settings(&meta);
+        files.extend(project_files(options, &meta, &settings, contract_source.is_multi_file, &paths, &placed_paths));
+
+        if let Some(format) = options.artifact_format {
+            let source_name = files.iter().find(|(_, content)| source::declares(content, &contract_source.contract_name)).map_or("", |(path, _)| path.as_str());
+            files.push(artifact::artifact_file(chain, address, format, &meta, &settings, source_name)?);
+        }
+        if let Some(entry) = raw_entry {
+            files.push((RAW_RESPONSE_FILENAME.to_owned(), entry));
+        }
+    }
+
+    Ok(Fetched { files, is_multi_file: contract_source.is_multi_file })
+}
+
+/// Fetch verified source code, or only ABI of contract handing each file to
+/// output to `write` in order of fetch options. Source code is decoded
+/// straight from the response body without building JSON values of it. In
+/// the default order, files are decoded twice; once to find the order, then
+/// one by one as written, so peak memory stays around twice the size of the
+/// response even for source code of tens of MB. Ordering by dependencies,
+/// selecting contract, pruning, and keeping only interfaces need content of
+/// every file at once, so files are held in memory until written then.
+/// Returns number of files written.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+/// * `options` - fetch options
+/// * `write` - function to write file with relative path, and content
+pub fn fetch_each<F: FnMut(&str, &str) -> Result<(), String>>(chain: &Chain, address: &str, options: &FetchOptions, mut write: F) -> Result<usize, String> {
+    if options.abi_only {
+        let abi = explorer::get_abi(chain, address, options.abi_pretty_print)?;
+        write("abi.json", &transform(options, address, "abi.json", "abi.json", abi))?;
+        return Ok(1);
+    }
+
+    let body = explorer::get_source_meta_text(chain, address)?;
+    let raw = explorer::parse_source_meta_raw(address, &body)?;
+    fetch_each_raw(chain, address, &raw, options, write).map(|w| w.count)
+}
+
+/// Same as `fetch_each()` but from verification metadata already fetched,
+/// returning compiler settings as well.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+/// * `raw` - verification metadata with source code left undecoded
+/// * `options` - fetch options
+/// * `write` - function to write file with relative path, and content
+pub fn fetch_each_raw<F: FnMut(&str, &str) -> Result<(), String>>(chain: &Chain, address: &str, raw: &explorer::RawSourceMeta, options: &FetchOptions, mut write: F) -> Result<WrittenEach, String> {
+    crate::selectors::index_abi(raw.meta["ABI"].as_str().unwrap_or(""));
+    let contract_name = raw.meta["ContractName"].as_str().unwrap_or("");
+
+    let mut paths: Vec<String> = Vec::new();
+    let mut placed_paths: Vec<String> = Vec::new();
+    let mut source_name = String::new();
+    let mut taken: HashSet<String> = HashSet::new();
+    let mut write_source = |f: source::SourceFile, write: &mut F| -> Result<(), String> {
+        let placed = layout::disambiguate_case(&safe_path::escape_relative_path(&layout::place(options.layout, &f.path)), &mut taken);
+        if source_name.is_empty() && source::declares(&f.content, contract_name) {
+            source_name = placed.clone();
+        }
+        write(&placed, &transform(options, address, &f.path, &placed, f.content))?;
+        paths.push(f.path);
+        placed_paths.push(placed);
+        Ok(())
+    };
+
+    let needs_all = options.order != Order::Path || options.contract.is_some() || options.prune_unreachable || options.interfaces_only;
+    let visited = if needs_all {
+        // files are held to be put in order before written
+        let mut files = Vec::new();
+        let visited = {
+            let _timer = crate::perf::time(crate::perf::Phase::Decode);
+            source::for_each_source_raw(address, raw, |f| {
+                files.push(f);
+                Ok(())
+            })?
+        };
+        select_files(options, address, &mut files, contract_name, raw.meta["ABI"].as_str().unwrap_or(""))?;
+        for f in files.into_iter() {
+            write_source(f, &mut write)?;
+        }
+        visited
+    }
+    else {
+        // plain search rules out most files before the slower regex
+        let declaration = source::declaration_regex(contract_name);
+        let mut declaring = Vec::new();
+        let index = {
+            let _timer = crate::perf::time(crate::perf::Phase::Decode);
+            source::index_sources_raw(address, raw, |f| declaring.push(f.content.contains(contract_name) && declaration.is_match(&f.content)))?
+        };
+
+        // same order as of `source::order_files()`; file declaring the
+        // contract first, then the rest sorted by path
+        let mut order: Vec<usize> = (0..index.paths.len()).collect();
+        order.sort_by(|a, b| index.paths[*a].cmp(&index.paths[*b]));
+        if let Some(at) = order.iter().position(|i| declaring[*i]) {
+            let primary = order.remove(at);
+            order.insert(0, primary);
+        }
+        for i in order {
+            let f = {
+                let _timer = crate::perf::time(crate::perf::Phase::Decode);
+                index.read(address, raw, i)?
+            };
+            write_source(f, &mut write)?;
+        }
+        index.visited
+    };
+
+    let mut count = paths.len();
+    let settings = solc::settings_of(&raw.meta, visited.settings);
+    if options.project_files {
+        let paths: Vec<&str> = paths.iter().map(|p| p.as_str()).collect();
+        for (path, content) in project_files(options, &raw.meta, &settings, visited.is_multi_file, &paths, &placed_paths) {
+            write(&path, &content)?;
+            count += 1;
+        }
+
+        if let Some(format) = options.artifact_format {
+            let (path, content) = artifact::artifact_file(chain, address, format, &raw.meta, &settings, &source_name)?;
+            write(&path, &content)?;
+            count += 1;
+        }
+        if options.raw_response {
+            write(RAW_RESPONSE_FILENAME, raw.entry.get())?;
+            count += 1;
+        }
+    }
+    Ok(WrittenEach { count, settings })
+}
+
+/// Fetch verified source code of contract into its own output directory,
+/// recording it in manifest of the directory. Returns name of contract, and
+/// number of files written, or `None` if contract isn't verified yet.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+/// * `out_dir` - output directory of contract
+/// * `options` - fetch options
+pub fn fetch_verified_into(chain: &Chain, address: &str, out_dir: &str, options: &FetchOptions) -> Result<Option<(String, usize)>, String> {
+    let body = explorer::get_source_meta_text(chain, address)?;
+    let raw = explorer::parse_source_meta_raw(address, &body)?;
+    if !raw.is_verified() {
+        return Ok(None);
+    }
+
+    crate::progress::emit("fetch_start", serde_json::json!({ "address": address, "chain": chain.name }));
+    let mut writer = crate::manifest::OutDirWriter::open(out_dir, address, &chain.name, true)?;
+    let count = fetch_each_raw(chain, address, &raw, options, |path, content| writer.write(path, content))?.count;
+    writer.finish()?;
+    crate::progress::emit("fetch_done", serde_json::json!({ "address": address, "chain": chain.name, "files": count }));
+    Ok(Some((raw.meta["ContractName"].as_str().unwrap_or("").to_owned(), count)))
+}
+
+/// Check whether contract is verified, fetching it into its own directory
+/// under output directory if given. Returns name of contract, empty if not
+/// verified, and number of files fetched if fetched.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+/// * `out_dir` - output directory to fetch contract into under `<out-dir>/<address>/`
+/// * `options` - fetch options
+pub fn check_verified(chain: &Chain, address: &str, out_dir: Option<&String>, options: &FetchOptions) -> Result<(String, Option<usize>), String> {
+    match out_dir {
+        Some(out_dir) => {
+            let contract_dir = crate::combine_two_path_components(out_dir, address)?;
+            match fetch_verified_into(chain, address, &contract_dir, options)? {
+                Some((name, count)) => Ok((name, Some(count))),
+                None => Ok((String::new(), None)),
+            }
+        },
+        None => {
+            let body = explorer::get_source_meta_text(chain, address)?;
+            let raw = explorer::parse_source_meta_raw(address, &body)?;
+            let name = if raw.is_verified() { raw.meta["ContractName"].as_str().unwrap_or("").to_owned() } else { String::new() };
+            Ok((name, None))
+        }
+    }
+}
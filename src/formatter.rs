@@ -0,0 +1,244 @@
+//! Built-in formatter of Solidity source code normalizing whitespace in the
+//! default style of `forge fmt`; indentation by nesting of braces, brackets,
+//! and parentheses with 4 spaces, at most one blank line in a row, and no
+//! trailing whitespace.
+//!
+//! It works line by line without parsing, so tokens are neither re-spaced,
+//! nor lines re-wrapped. Comments, and string literals are kept as-is.
+
+/// Options of formatting source code.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Re-indent, and normalize whitespace as `forge fmt` would
+    pub reformat: bool,
+
+    /// Replace tabs in indentation with spaces
+    pub tabs_to_spaces: bool,
+
+    /// Number of spaces of each indentation level
+    pub indent_width: usize,
+}
+
+/// Default number of spaces of each indentation level
+pub const DEFAULT_INDENT_WIDTH: usize = 4;
+
+/// Get format options from values of flag options, or `None` if source code
+/// is to be kept as-is.
+///
+/// # Arguments
+/// * `fmt` - whether `--fmt` exists
+/// * `tabs_to_spaces` - whether `--tabs-to-spaces` exists
+/// * `indent_width` - value of `--indent-width`
+pub fn parse_format_options(fmt: bool, tabs_to_spaces: bool, indent_width: Option<usize>) -> Result<Option<FormatOptions>, String> {
+    if !fmt && !tabs_to_spaces {
+        if indent_width.is_some() {
+            return Err("Error --indent-width can only be used when --fmt or --tabs-to-spaces exists".to_owned());
+        }
+        return Ok(None);
+    }
+    let indent_width = indent_width.unwrap_or(DEFAULT_INDENT_WIDTH);
+    if indent_width == 0 || indent_width > 16 {
+        return Err("Error invalid value for --indent-width.\nIt must be between 1 and 16.".to_owned());
+    }
+    Ok(Some(FormatOptions { reformat: fmt, tabs_to_spaces, indent_width }))
+}
+
+/// Whether or not file at path is Solidity source code to be formatted.
+///
+/// # Arguments
+/// * `path` - path of file
+pub fn is_formattable(path: &str) -> bool {
+    path.ends_with(".sol")
+}
+
+/// Lexical state carried from one line to the next.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Lex {
+    Code,
+    BlockComment,
+}
+
+/// Scan line of code updating nesting depth, and lexical state. Brackets in
+/// comments, and string literals don't count.
+///
+/// # Arguments
+/// * `line` - line of code
+/// * `lex` - lexical state at start of line
+/// * `depth` - nesting depth at start of line
+fn scan(line: &str, mut lex: Lex, mut depth: usize) -> (Lex, usize) {
+    let bytes = line.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if lex == Lex::BlockComment {
+            match line[i..].find("*/") {
+                Some(end) => {
+                    lex = Lex::Code;
+                    i += end + 2;
+                    continue;
+                },
+                None => break,
+            }
+        }
+
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => break,
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                lex = Lex::BlockComment;
+                i += 2;
+                continue;
+            },
+            quote @ (b'"' | b'\'') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+            },
+            b'{' | b'(' | b'[' => depth += 1,
+            b'}' | b')' | b']' => depth = depth.saturating_sub(1),
+            _ => (),
+        }
+        i += 1;
+    }
+    (lex, depth)
+}
+
+/// Replace tabs in indentation of line with spaces up to the next tab stop.
+///
+/// # Arguments
+/// * `line` - line of code
+/// * `indent_width` - number of spaces between tab stops
+fn expand_indentation(line: &str, indent_width: usize) -> String {
+    let code = line.trim_start_matches([' ', '\t']);
+    let mut column = 0;
+    for c in line[..line.len() - code.len()].chars() {
+        column = if c == '\t' { (column / indent_width + 1) * indent_width } else { column + 1 };
+    }
+    format!("{}{}", " ".repeat(column), code)
+}
+
+/// Format Solidity source code.
+///
+/// # Arguments
+/// * `content` - source code
+/// * `options` - format options
+pub fn format_source(content: &str, options: &FormatOptions) -> String {
+    let newline = if content.contains("\r\n") { "\r\n" } else { "\n" };
+
+    if !options.reformat {
+        return content.split(newline)
+            .map(|line| expand_indentation(line, options.indent_width))
+            .collect::<Vec<String>>()
+            .join(newline);
+    }
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut lex = Lex::Code;
+    let mut depth = 0;
+    for line in content.lines() {
+        let code = line.trim();
+
+        if lex == Lex::BlockComment && !code.is_empty() {
+            // continued lines of doc comments line up their leading '*'
+            let align = if code.starts_with('*') { " " } else { "" };
+            lines.push(format!("{}{}{}", " ".repeat(depth * options.indent_width), align, code));
+        }
+        else if code.is_empty() {
+            if !matches!(lines.last(), Some(l) if !l.is_empty()) {
+                continue;
+            }
+            lines.push(String::new());
+        }
+        else {
+            // closing brackets at start of line belong to the outer level
+            let closers = code.bytes().take_while(|b| matches!(b, b'}' | b')' | b']')).count();
+            lines.push(format!("{}{}", " ".repeat(depth.saturating_sub(closers) * options.indent_width), code));
+        }
+
+        let (next_lex, next_depth) = scan(code, lex, depth);
+        lex = next_lex;
+        depth = next_depth;
+    }
+
+    while matches!(lines.last(), Some(l) if l.is_empty()) {
+        lines.pop();
+    }
+    let mut formatted = lines.join(newline);
+    formatted.push_str(newline);
+    formatted
+}
+
+/// Canonicalize content of file so independently fetched copies of the same
+/// code are byte-identical; newlines become LF, trailing whitespace is
+/// stripped, file ends with exactly one newline, and JSON is pretty printed
+/// with keys of objects sorted.
+///
+/// # Arguments
+/// * `path` - path of file
+/// * `content` - content of file
+pub fn canonicalize(path: &str, content: &str) -> String {
+    let text = content.replace("\r\n", "\n").replace('\r', "\n");
+
+    // map of serde_json keeps keys sorted
+    if path.ends_with(".json") {
+        if let Ok(Ok(res)) = serde_json::from_str::<serde_json::Value>(&text).map(|v| serde_json::to_string_pretty(&v)) {
+            return format!("{}\n", res);
+        }
+    }
+
+    let mut lines: Vec<&str> = text.lines().map(|l| l.trim_end()).collect();
+    while matches!(lines.last(), Some(l) if l.is_empty()) {
+        lines.pop();
+    }
+    format!("{}\n", lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FMT: FormatOptions = FormatOptions { reformat: true, tabs_to_spaces: false, indent_width: DEFAULT_INDENT_WIDTH };
+
+    #[test]
+    fn format_source_reindents_by_nesting() {
+        let content = "contract A {\n\t  function f(\n  uint a\n) public {\n\t\tif (a > 0) { revert(); }\n        }\n}\n";
+        let expected = "contract A {\n    function f(\n        uint a\n    ) public {\n        if (a > 0) { revert(); }\n    }\n}\n";
+        assert_eq!(format_source(content, &FMT), expected);
+    }
+
+    #[test]
+    fn format_source_collapses_blank_lines() {
+        let content = "\n\npragma solidity ^0.8.0;   \n\n\n\ncontract A {}\n\n\n";
+        assert_eq!(format_source(content, &FMT), "pragma solidity ^0.8.0;\n\ncontract A {}\n");
+    }
+
+    #[test]
+    fn format_source_ignores_brackets_in_comments_and_strings() {
+        let content = "contract A {\n// {\nstring s = \"{(\";\nbytes1 c = '}';\n/* {\n* [\n*/\nuint x;\n}\n";
+        let expected = "contract A {\n    // {\n    string s = \"{(\";\n    bytes1 c = '}';\n    /* {\n     * [\n     */\n    uint x;\n}\n";
+        assert_eq!(format_source(content, &FMT), expected);
+    }
+
+    #[test]
+    fn format_source_keeps_crlf_and_indent_width() {
+        let options = FormatOptions { indent_width: 2, ..FMT };
+        assert_eq!(format_source("contract A {\r\nuint x;\r\n}", &options), "contract A {\r\n  uint x;\r\n}\r\n");
+    }
+
+    #[test]
+    fn format_source_expands_tabs_only() {
+        let options = FormatOptions { reformat: false, tabs_to_spaces: true, indent_width: 4 };
+        assert_eq!(format_source("\tuint x;\n  \tuint y;\t// a\tb\n", &options), "    uint x;\n    uint y;\t// a\tb\n");
+        let options = FormatOptions { indent_width: 2, ..options };
+        assert_eq!(format_source("\t\tx;\r\n", &options), "    x;\r\n");
+    }
+
+    #[test]
+    fn parse_format_options_of_flags() {
+        assert_eq!(parse_format_options(false, false, None), Ok(None));
+        assert_eq!(parse_format_options(true, false, None), Ok(Some(FMT)));
+        assert_eq!(parse_format_options(false, true, Some(2)), Ok(Some(FormatOptions { reformat: false, tabs_to_spaces: true, indent_width: 2 })));
+        assert!(parse_format_options(false, false, Some(2)).is_err());
+        assert!(parse_format_options(true, false, Some(0)).is_err());
+        assert!(parse_format_options(true, false, Some(17)).is_err());
+    }
+}
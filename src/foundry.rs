@@ -0,0 +1,188 @@
+//! Compatibility with conventions of Foundry (`forge`, and `cast`), so
+//! tracpls drops into Foundry-centric workflows without new configuration.
+//!
+//! * Chain aliases of Foundry e.g. `mainnet`, `sepolia`, or `arbitrum-sepolia`
+//!   are accepted wherever chain is, resolved to registered chain by chain id.
+//! * `ETHERSCAN_API_KEY` is used as API key of backend whose own environment
+//!   variable e.g. `TRACPLS_ETHERSCAN_APIKEY` isn't set.
+//! * `ETH_RPC_URL` serves JSON-RPC requests e.g. `eth_getCode`, and
+//!   `eth_call` from the node directly instead of `proxy` module of API
+//!   platform, for chain whose chain id matches the node's.
+
+use crate::backend::Chain;
+use crate::transport::{self, HttpRequest};
+use serde_json::{json, Value};
+use std::sync::Mutex;
+
+/// Environment variable holding API key as of Foundry
+pub const APIKEY_ENV: &str = "ETHERSCAN_API_KEY";
+
+/// Environment variable holding url of JSON-RPC node as of Foundry
+pub const RPC_URL_ENV: &str = "ETH_RPC_URL";
+
+/// Chain aliases of Foundry, and their chain ids
+const ALIASES: [(&str, u64); 27] = [
+    ("mainnet", 1),
+    ("sepolia", 11155111),
+    ("holesky", 17000),
+    ("goerli", 5),
+    ("optimism", 10),
+    ("optimism-sepolia", 11155420),
+    ("arbitrum", 42161),
+    ("arbitrum-one", 42161),
+    ("arbitrum-sepolia", 421614),
+    ("arbitrum-nova", 42170),
+    ("polygon", 137),
+    ("polygon-amoy", 80002),
+    ("polygon-zkevm", 1101),
+    ("bsc", 56),
+    ("bnb-smart-chain", 56),
+    ("bsc-testnet", 97),
+    ("base", 8453),
+    ("base-sepolia", 84532),
+    ("avalanche", 43114),
+    ("fantom", 250),
+    ("gnosis", 100),
+    ("linea", 59144),
+    ("scroll", 534352),
+    ("zksync", 324),
+    ("blast", 81457),
+    ("mantle", 5000),
+    ("celo", 42220),
+];
+
+/// Get chain id of Foundry chain alias, case-insensitive.
+///
+/// # Arguments
+/// * `alias` - chain alias e.g. `mainnet`
+pub fn chain_id_of_alias(alias: &str) -> Option<u64> {
+    ALIASES.iter().find(|(a, _)| a.eq_ignore_ascii_case(alias)).map(|(_, id)| *id)
+}
+
+/// Chain id of node at `ETH_RPC_URL` once asked, `None` inside if asking it
+/// failed
+static RPC_CHAIN_ID: Mutex<Option<Option<u64>>> = Mutex::new(None);
+
+/// Chains already warned about not matching node at `ETH_RPC_URL`
+static WARNED_CHAINS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Send JSON-RPC request to node, and return whole response body.
+///
+/// # Arguments
+/// * `url` - url of node
+/// * `method` - JSON-RPC method
+/// * `params` - JSON-RPC parameters
+fn post_rpc(url: &str, method: &str, params: Value) -> Result<String, String> {
+    let payload = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }).to_string();
+    let sent = payload.len();
+    let request = HttpRequest::new("POST", url).header("Content-Type", "application/json").body(payload);
+    let timer = std::time::Instant::now();
+    let response = match transport::send(&request) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error sending request to {}; err={}", RPC_URL_ENV, e);
+            return Err(err_msg);
+        }
+    };
+    if !response.is_success() {
+        let err_msg = format!("Error from {}; status={}", RPC_URL_ENV, response.status);
+        return Err(err_msg);
+    }
+    crate::perf::record_request(timer.elapsed(), sent, response.body.len());
+    Ok(response.body)
+}
+
+/// Get chain id of node, asking it only once.
+///
+/// # Arguments
+/// * `url` - url of node
+fn rpc_chain_id(url: &str) -> Option<u64> {
+    let mut cached = RPC_CHAIN_ID.lock().ok()?;
+    if let Some(chain_id) = *cached {
+        return chain_id;
+    }
+    let chain_id = match post_rpc(url, "eth_chainId", json!([])) {
+        Ok(body) => serde_json::from_str::<Value>(&body).ok()
+            .and_then(|v| v["result"].as_str().and_then(|r| u64::from_str_radix(r.trim_start_matches("0x"), 16).ok())),
+        Err(e) => {
+            eprintln!("Warning: {}; using API platform instead", e);
+            None
+        }
+    };
+    *cached = Some(chain_id);
+    chain_id
+}
+
+/// Translate request of `proxy` module of API platform into JSON-RPC method,
+/// and parameters, or `None` if it is not one.
+///
+/// # Arguments
+/// * `params` - query parameters as pairs of key and value
+fn to_rpc(params: &[(&str, &str)]) -> Option<(String, Value)> {
+    let param = |name: &str| params.iter().find(|(key, _)| *key == name).map(|(_, value)| *value);
+    if param("module") != Some("proxy") {
+        return None;
+    }
+    let tag = param("tag").unwrap_or("latest");
+    let method = param("action")?;
+    let rpc_params = match method {
+        "eth_getCode" => json!([param("address")?, tag]),
+        "eth_call" => json!([{ "to": param("to")?, "data": param("data")? }, tag]),
+        "eth_getStorageAt" => json!([param("address")?, param("position")?, tag]),
+        "eth_getTransactionByHash" | "eth_getTransactionReceipt" => json!([param("txhash")?]),
+        "eth_blockNumber" => json!([]),
+        _ => return None,
+    };
+    Some((method.to_owned(), rpc_params))
+}
+
+/// Get url of node at `ETH_RPC_URL` if it serves the chain.
+///
+/// # Arguments
+/// * `chain` - chain
+fn node_url(chain: &Chain) -> Option<String> {
+    let url = std::env::var(RPC_URL_ENV).ok().filter(|u| !u.is_empty())?;
+    let node_chain_id = rpc_chain_id(&url)?;
+    if chain.chain_id != Some(node_chain_id) {
+        if let Ok(mut warned) = WARNED_CHAINS.lock() {
+            if !warned.contains(&chain.name) {
+                eprintln!("Warning: {} serves chain id {}, not the one of {}; using API platform instead", RPC_URL_ENV, node_chain_id, chain.name);
+                warned.push(chain.name.clone());
+            }
+        }
+        return None;
+    }
+    Some(url)
+}
+
+/// Send request to node at `ETH_RPC_URL` instead of API platform if it is a
+/// JSON-RPC request, and node serves the chain. Returns `None` if request is
+/// to be sent to API platform.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `params` - query parameters as pairs of key and value
+pub fn send_rpc(chain: &Chain, params: &[(&str, &str)]) -> Option<Result<String, String>> {
+    let (method, rpc_params) = to_rpc(params)?;
+    let url = node_url(chain)?;
+    Some(post_rpc(&url, &method, rpc_params))
+}
+
+/// Send JSON-RPC request which API platform can't serve e.g. `eth_call` with
+/// sender, or state overrides, to node at `ETH_RPC_URL`. Returns whole
+/// response as JSON, or `None` if no node serving the chain is set.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `method` - JSON-RPC method
+/// * `params` - JSON-RPC parameters
+pub fn request(chain: &Chain, method: &str, params: Value) -> Option<Result<Value, String>> {
+    let url = node_url(chain)?;
+    Some(post_rpc(&url, method, params).and_then(|body| match serde_json::from_str(&body) {
+        Ok(res) => Ok(res),
+        Err(e) => {
+            let err_msg = format!("Error parsing response from {} as JSON; err={}", RPC_URL_ENV, e);
+            Err(err_msg)
+        }
+    }))
+}
@@ -0,0 +1,172 @@
+//! `gas` command: report gas usage per method from recent transactions.
+
+use crate::abi;
+use crate::explorer;
+use crate::selectors;
+use crate::TargetArgs;
+use clap::Args;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Args)]
+pub struct GasArgs {
+    #[clap(flatten)]
+    pub target: TargetArgs,
+
+    /// Number of recent transactions to sample. Maximum is 10000.
+    #[clap(long="samples", required=false, default_value="1000")]
+    pub samples: u32,
+
+    /// Output as JSON instead of human readable table
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+}
+
+/// Gas usage statistics of a single method.
+struct MethodStats {
+    selector: String,
+    name: String,
+    gas_used: Vec<u64>,
+    failed: usize,
+}
+
+impl MethodStats {
+    fn min(&self) -> u64 {
+        self.gas_used.iter().copied().min().unwrap_or(0)
+    }
+
+    fn max(&self) -> u64 {
+        self.gas_used.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Median of gas used. `gas_used` needs to be sorted beforehand.
+    fn median(&self) -> u64 {
+        let len = self.gas_used.len();
+        if len == 0 {
+            0
+        }
+        else if len % 2 == 1 {
+            self.gas_used[len / 2]
+        }
+        else {
+            (self.gas_used[len / 2 - 1] + self.gas_used[len / 2]) / 2
+        }
+    }
+}
+
+/// Execute `gas` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &GasArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.target.chain)?;
+    let address = args.target.address.to_lowercase();
+
+    if args.samples == 0 || args.samples > 10000 {
+        return Err("Error --samples must be between 1 and 10000".to_owned());
+    }
+
+    // contract might not be verified, in which case only selectors are reported
+    let contract_abi = abi::fetch_abi(chain, &address).ok();
+
+    let samples = args.samples.to_string();
+    let result = explorer::query(chain, &[
+        ("module", "account"),
+        ("action", "txlist"),
+        ("address", &address),
+        ("startblock", "0"),
+        ("endblock", "99999999999"),
+        ("page", "1"),
+        ("offset", &samples),
+        ("sort", "desc"),
+    ])?;
+
+    let txs = match result.as_array() {
+        Some(res) => res,
+        None => {
+            let err_msg = format!("Error unexpected response for transactions; result={}", result);
+            return Err(err_msg);
+        }
+    };
+
+    let mut stats: BTreeMap<String, MethodStats> = BTreeMap::new();
+    let mut sampled = 0;
+    for tx in txs.iter() {
+        // only consider transactions calling into the contract
+        if tx["to"].as_str().unwrap_or("").to_lowercase() != address {
+            continue;
+        }
+        sampled += 1;
+
+        let input = tx["input"].as_str().unwrap_or("0x");
+        let selector = if input.len() >= 10 { input[..10].to_lowercase() } else { "0x".to_owned() };
+
+        let entry = stats.entry(selector.clone()).or_insert_with(|| {
+            let name = if selector == "0x" {
+                "(receive/fallback)".to_owned()
+            }
+            else if let Some(f) = contract_abi.as_ref().and_then(|a| a.function_by_selector(&selector)) {
+                f.signature()
+            }
+            // seen in ABI of another contract before
+            else if let Some(signature) = selectors::lookup(&selector).into_iter().next() {
+                signature
+            }
+            else {
+                // explorer might already know it from its own signature database
+                match tx["functionName"].as_str() {
+                    Some(res) if !res.is_empty() => res.to_owned(),
+                    _ => "(unknown)".to_owned(),
+                }
+            };
+            MethodStats { selector: selector.clone(), name, gas_used: Vec::new(), failed: 0 }
+        });
+
+        if tx["isError"].as_str() == Some("1") {
+            entry.failed += 1;
+            continue;
+        }
+        if let Some(gas_used) = tx["gasUsed"].as_str().and_then(|v| v.parse::<u64>().ok()) {
+            entry.gas_used.push(gas_used);
+        }
+    }
+
+    let mut methods: Vec<MethodStats> = stats.into_values().collect();
+    for m in methods.iter_mut() {
+        m.gas_used.sort_unstable();
+    }
+    // most called methods come first
+    methods.sort_by_key(|m| std::cmp::Reverse(m.gas_used.len() + m.failed));
+
+    if args.json {
+        let json: Vec<serde_json::Value> = methods.iter().map(|m| serde_json::json!({
+            "selector": m.selector,
+            "method": m.name,
+            "calls": m.gas_used.len(),
+            "failed": m.failed,
+            "min": m.min(),
+            "median": m.median(),
+            "max": m.max(),
+        })).collect();
+
+        match serde_json::to_string_pretty(&json) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing gas report to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+        return Ok(());
+    }
+
+    println!("Sampled {} transactions into the contract out of {} recent ones", sampled, txs.len());
+    if methods.is_empty() {
+        return Ok(());
+    }
+
+    println!("{:<10} {:>7} {:>7} {:>10} {:>10} {:>10}  METHOD", "SELECTOR", "CALLS", "FAILED", "MIN", "MEDIAN", "MAX");
+    for m in methods.iter() {
+        println!("{:<10} {:>7} {:>7} {:>10} {:>10} {:>10}  {}", m.selector, m.gas_used.len(), m.failed, m.min(), m.median(), m.max(), m.name);
+    }
+
+    Ok(())
+}
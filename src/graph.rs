@@ -0,0 +1,214 @@
+//! `graph` command: emit function call graph, or contract inheritance
+//! hierarchy of verified source as DOT, or Mermaid, from AST of solc.
+
+use crate::ast::{self, collect};
+use crate::TargetArgs;
+use clap::Args;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Debug, Args)]
+pub struct GraphArgs {
+    #[clap(flatten)]
+    pub target: TargetArgs,
+
+    /// What to graph.
+    /// Possible values are 'calls', and 'inheritance'.
+    #[clap(long="kind", required=false, default_value="calls")]
+    pub kind: String,
+
+    /// Output format.
+    /// Possible values are 'dot', and 'mermaid'.
+    #[clap(long="format", required=false, default_value="dot")]
+    pub format: String,
+
+    /// Only graph functions of this contract, and ones they call
+    #[clap(long="contract", required=false)]
+    pub contract: Option<String>,
+
+    /// Path to solc binary to use instead of finding one matching the verified
+    /// compiler version
+    #[clap(long="solc", required=false)]
+    pub solc: Option<String>,
+}
+
+/// Directed graph keyed by AST node id.
+#[derive(Debug, Default)]
+struct Graph {
+    /// Label of each node
+    nodes: BTreeMap<u64, String>,
+
+    edges: BTreeSet<(u64, u64)>,
+}
+
+/// Get label of function, or modifier e.g. `Vault.deposit`.
+///
+/// # Arguments
+/// * `contract` - name of contract defining it
+/// * `function` - `FunctionDefinition`, or `ModifierDefinition` node
+fn function_label(contract: &str, function: &Value) -> String {
+    let name = match function["name"].as_str() {
+        Some(name) if !name.is_empty() => name,
+        // constructor, fallback, and receive have no name
+        _ => function["kind"].as_str().unwrap_or("function"),
+    };
+    format!("{}.{}", contract, name)
+}
+
+/// Build inheritance graph with edges from derived contract to its bases.
+///
+/// # Arguments
+/// * `contracts` - `ContractDefinition` nodes
+fn inheritance_graph(contracts: &[&Value]) -> Graph {
+    let mut graph = Graph::default();
+    for contract in contracts.iter() {
+        let id = contract["id"].as_u64().unwrap_or(0);
+        let kind = contract["contractKind"].as_str().unwrap_or("contract");
+        let is_abstract = contract["abstract"].as_bool().unwrap_or(false);
+        let name = contract["name"].as_str().unwrap_or("");
+        let label = match (kind, is_abstract) {
+            ("contract", false) => name.to_owned(),
+            ("contract", true) => format!("{} (abstract)", name),
+            _ => format!("{} ({})", name, kind),
+        };
+        graph.nodes.insert(id, label);
+
+        for base in contract["baseContracts"].as_array().unwrap_or(&Vec::new()).iter() {
+            if let Some(base_id) = base["baseName"]["referencedDeclaration"].as_u64() {
+                graph.edges.insert((id, base_id));
+            }
+        }
+    }
+    graph
+}
+
+/// Build call graph with edges from function, or modifier to functions it
+/// calls, and modifiers it invokes. Calls to functions without definition in
+/// source code e.g. builtins are left out.
+///
+/// # Arguments
+/// * `contracts` - `ContractDefinition` nodes
+fn call_graph(contracts: &[&Value]) -> Graph {
+    let mut graph = Graph::default();
+    let mut callables: Vec<(u64, &Value)> = Vec::new();
+    for contract in contracts.iter() {
+        let name = contract["name"].as_str().unwrap_or("");
+        for node in contract["nodes"].as_array().map_or(&[][..], |n| n.as_slice()).iter() {
+            let node_type = node["nodeType"].as_str().unwrap_or("");
+            if node_type == "FunctionDefinition" || node_type == "ModifierDefinition" {
+                let id = node["id"].as_u64().unwrap_or(0);
+                graph.nodes.insert(id, function_label(name, node));
+                callables.push((id, node));
+            }
+        }
+    }
+
+    for (id, callable) in callables.iter() {
+        let mut calls = Vec::new();
+        collect(callable, "FunctionCall", &mut calls);
+        let callees = calls.iter().filter_map(|c| c["expression"]["referencedDeclaration"].as_u64());
+
+        let mut invocations = Vec::new();
+        collect(callable, "ModifierInvocation", &mut invocations);
+        let modifiers = invocations.iter().filter_map(|m| m["modifierName"]["referencedDeclaration"].as_u64());
+
+        for callee in callees.chain(modifiers) {
+            if graph.nodes.contains_key(&callee) {
+                graph.edges.insert((*id, callee));
+            }
+        }
+    }
+    graph
+}
+
+/// Keep only nodes reachable from nodes whose label matches.
+///
+/// # Arguments
+/// * `graph` - graph
+/// * `matches` - whether or not node of label is to start from
+fn reachable_from<F: Fn(&str) -> bool>(graph: Graph, matches: F) -> Graph {
+    let mut kept: BTreeSet<u64> = graph.nodes.iter().filter(|(_, l)| matches(l)).map(|(id, _)| *id).collect();
+    let mut pending: Vec<u64> = kept.iter().copied().collect();
+    while let Some(id) = pending.pop() {
+        for (_, to) in graph.edges.range((id, 0)..=(id, u64::MAX)) {
+            if kept.insert(*to) {
+                pending.push(*to);
+            }
+        }
+    }
+
+    Graph {
+        nodes: graph.nodes.into_iter().filter(|(id, _)| kept.contains(id)).collect(),
+        edges: graph.edges.into_iter().filter(|(from, _)| kept.contains(from)).collect(),
+    }
+}
+
+/// Render graph as DOT, or Mermaid.
+///
+/// # Arguments
+/// * `graph` - graph
+/// * `name` - name of graph
+/// * `format` - output format
+fn render(graph: &Graph, name: &str, format: &str) -> Result<String, String> {
+    let mut out = String::new();
+    match format.to_lowercase().as_str() {
+        "dot" => {
+            out.push_str(&format!("digraph {} {{\n", name));
+            out.push_str("  rankdir=LR;\n");
+            out.push_str("  node [shape=box];\n");
+            for (id, label) in graph.nodes.iter() {
+                out.push_str(&format!("  n{} [label={}];\n", id, json!(label)));
+            }
+            for (from, to) in graph.edges.iter() {
+                out.push_str(&format!("  n{} -> n{};\n", from, to));
+            }
+            out.push_str("}\n");
+        },
+        "mermaid" => {
+            out.push_str("graph LR\n");
+            for (id, label) in graph.nodes.iter() {
+                out.push_str(&format!("  n{}[\"{}\"]\n", id, label.replace('"', "#quot;")));
+            }
+            for (from, to) in graph.edges.iter() {
+                out.push_str(&format!("  n{} --> n{}\n", from, to));
+            }
+        },
+        _ => return Err("Error invalid value for --format.\nPossible values are 'dot', or 'mermaid'.".to_owned()),
+    }
+    Ok(out)
+}
+
+/// Execute `graph` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &GraphArgs) -> Result<(), String> {
+    let kind = args.kind.to_lowercase();
+    if kind != "calls" && kind != "inheritance" {
+        return Err("Error invalid value for --kind.\nPossible values are 'calls', or 'inheritance'.".to_owned());
+    }
+    // fail before compiling anything
+    render(&Graph::default(), &kind, &args.format)?;
+
+    let asts = ast::compile_asts(&args.target, args.solc.as_ref())?;
+    let mut contracts = Vec::new();
+    for unit in asts.values() {
+        collect(unit, "ContractDefinition", &mut contracts);
+    }
+
+    let graph = if kind == "calls" { call_graph(&contracts) } else { inheritance_graph(&contracts) };
+    let graph = match args.contract.as_ref() {
+        Some(name) if kind == "calls" => reachable_from(graph, |l| l.starts_with(&format!("{}.", name))),
+        Some(name) => reachable_from(graph, |l| l == name || l.starts_with(&format!("{} (", name))),
+        None => graph,
+    };
+    if graph.nodes.is_empty() {
+        if let Some(name) = args.contract.as_ref() {
+            let err_msg = format!("Error contract '{}' not found in verified source", name);
+            return Err(err_msg);
+        }
+    }
+
+    print!("{}", render(&graph, &kind, &args.format)?);
+    Ok(())
+}
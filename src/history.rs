@@ -0,0 +1,326 @@
+//! History of invocations of tracpls; command, target contract, and outcome
+//! of each, so past fetches can be looked up, and re-run with `history`.
+//!
+//! History is appended as JSON Lines to path of environment variable
+//! `TRACPLS_HISTORY`, or `~/.config/tracpls/history.jsonl`. Setting
+//! `TRACPLS_HISTORY` to empty disables it. Values of `--bearer-token`, and
+//! `--header` are redacted before recorded.
+
+use crate::display;
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+/// Flag options whose values are redacted
+const SECRET_FLAGS: [&str; 2] = ["--bearer-token", "--header"];
+
+#[derive(Debug, Args)]
+pub struct HistoryArgs {
+    /// Only list invocations targeting this address
+    #[clap(long="address", short='a', required=false)]
+    pub address: Option<String>,
+
+    /// Only list invocations on this chain, by name as given
+    #[clap(long="chain", short='c', required=false)]
+    pub chain: Option<String>,
+
+    /// Only list invocations of this command e.g. 'fetch', or 'batch'
+    #[clap(long="command", required=false)]
+    pub command: Option<String>,
+
+    /// Only list invocations on, or after this date (UTC) in form of
+    /// 'YYYY-MM-DD'
+    #[clap(long="since", required=false)]
+    pub since: Option<String>,
+
+    /// Only list invocations on, or before this date (UTC) in form of
+    /// 'YYYY-MM-DD'
+    #[clap(long="until", required=false)]
+    pub until: Option<String>,
+
+    /// Only list failed invocations
+    #[clap(long="failed", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub failed: bool,
+
+    /// Maximum number of the latest invocations to list
+    #[clap(long="limit", required=false)]
+    pub limit: Option<usize>,
+
+    /// Output as JSON instead of human readable text
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+
+    /// Re-run invocation of this id as listed, with the same arguments
+    #[clap(long="rerun", required=false, conflicts_with_all=&["address", "chain", "command", "since", "until", "failed", "limit", "json"])]
+    pub rerun: Option<usize>,
+}
+
+/// Single invocation of tracpls.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Unix timestamp in seconds of when it started
+    pub time: i64,
+
+    /// Name of command, or `fetch` for the default one
+    pub command: String,
+
+    #[serde(default)]
+    pub address: Option<String>,
+
+    #[serde(default)]
+    pub chain: Option<String>,
+
+    /// Command line arguments without program name
+    pub args: Vec<String>,
+
+    pub ok: bool,
+
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Get path of history file, or `None` if it's disabled, or home directory
+/// isn't known.
+fn history_path() -> Option<String> {
+    if let Ok(path) = std::env::var("TRACPLS_HISTORY") {
+        return Some(path).filter(|p| !p.is_empty());
+    }
+    let home = std::env::var("HOME").ok()?;
+    crate::combine_two_path_components(&home, ".config/tracpls/history.jsonl").ok()
+}
+
+/// Get value of flag option from command line arguments, either as separate
+/// argument, or joined with `=`.
+///
+/// # Arguments
+/// * `args` - command line arguments
+/// * `names` - names of flag option e.g. `["--address", "-a"]`
+fn flag_value(args: &[String], names: &[&str]) -> Option<String> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if names.contains(&arg.as_str()) {
+            return iter.next().cloned();
+        }
+        if let Some(value) = names.iter().find_map(|n| arg.strip_prefix(n).and_then(|v| v.strip_prefix('='))) {
+            return Some(value.to_owned());
+        }
+    }
+    None
+}
+
+/// Quote argument for shell if needed, so listed command can be pasted.
+///
+/// # Arguments
+/// * `arg` - command line argument
+fn shell_quote(arg: &str) -> String {
+    if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=@,+%".contains(c)) {
+        arg.to_owned()
+    }
+    else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Redact values of flag options holding secrets.
+///
+/// # Arguments
+/// * `args` - command line arguments
+fn redact(args: &[String]) -> Vec<String> {
+    let mut redacted = Vec::new();
+    let mut secret_next = false;
+    for arg in args.iter() {
+        if secret_next {
+            redacted.push("REDACTED".to_owned());
+            secret_next = false;
+            continue;
+        }
+        match SECRET_FLAGS.iter().find(|f| arg.starts_with(&format!("{}=", f))) {
+            Some(flag) => redacted.push(format!("{}=REDACTED", flag)),
+            None => {
+                secret_next = SECRET_FLAGS.contains(&arg.as_str());
+                redacted.push(arg.clone());
+            }
+        }
+    }
+    redacted
+}
+
+/// Record invocation into history. Failing to record is only warned about.
+///
+/// # Arguments
+/// * `command` - name of command, or `fetch` for the default one
+/// * `args` - command line arguments without program name
+/// * `started` - unix timestamp in seconds of when it started
+/// * `error` - error if it failed
+pub fn record(command: &str, args: &[String], started: i64, error: Option<&str>) {
+    let path = match history_path() {
+        Some(res) => res,
+        None => return,
+    };
+    let entry = HistoryEntry {
+        time: started,
+        command: command.to_owned(),
+        address: flag_value(args, &["--address", "-a"]).map(|a| a.to_lowercase()),
+        chain: flag_value(args, &["--chain", "-c", "--chain-id"]),
+        args: redact(args),
+        ok: error.is_none(),
+        error: error.map(|e| e.to_owned()),
+    };
+
+    let line = match serde_json::to_string(&entry) {
+        Ok(res) => res,
+        Err(e) => {
+            eprintln!("Warning: failed to serialize history entry; err={}", e);
+            return;
+        }
+    };
+    // single write of whole line keeps concurrent invocations from interleaving
+    let written = crate::create_intermediate_dirs(&path).and_then(|_| {
+        std::fs::OpenOptions::new().create(true).append(true).open(&path)
+            .and_then(|mut file| file.write_all(format!("{}\n", line).as_bytes()))
+            .map_err(|e| e.to_string())
+    });
+    if let Err(e) = written {
+        eprintln!("Warning: failed to record history at '{}'; err={}", path, e);
+    }
+}
+
+/// Read history with id of each entry, starting from 1 for the oldest.
+/// Lines which can't be parsed are skipped.
+///
+/// # Arguments
+/// * `path` - path of history file
+fn read_history(path: &str) -> Result<Vec<(usize, HistoryEntry)>, String> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(res) => res,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            let err_msg = format!("Error reading history at '{}'; err={}", path, e);
+            return Err(err_msg);
+        }
+    };
+    Ok(content.lines()
+        .enumerate()
+        .filter_map(|(i, line)| serde_json::from_str(line).ok().map(|entry| (i + 1, entry)))
+        .collect())
+}
+
+/// Parse date in form of `YYYY-MM-DD` into unix timestamp at its midnight
+/// (UTC).
+///
+/// # Arguments
+/// * `flag` - name of flag option
+/// * `date` - date
+fn parse_date(flag: &str, date: &str) -> Result<i64, String> {
+    let parts: Vec<i64> = date.split('-').filter_map(|p| p.parse::<i64>().ok()).collect();
+    match parts.as_slice() {
+        [year, month, day] if (1..=12).contains(month) && (1..=31).contains(day) => Ok(display::timestamp_from_civil(*year, *month, *day)),
+        _ => {
+            let err_msg = format!("Error invalid value for --{}; '{}' is not in form of 'YYYY-MM-DD'", flag, date);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Re-run invocation with the same arguments by current executable.
+///
+/// # Arguments
+/// * `id` - id of invocation
+/// * `entry` - invocation
+fn rerun(id: usize, entry: &HistoryEntry) -> Result<(), String> {
+    if entry.args.iter().any(|a| a.contains("REDACTED")) {
+        eprintln!("Warning: secrets of #{} were redacted; pass them again by environment, or run it by hand", id);
+    }
+    let exe = match std::env::current_exe() {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error locating tracpls executable; err={}", e);
+            return Err(err_msg);
+        }
+    };
+    match std::process::Command::new(exe).args(&entry.args).status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => {
+            let err_msg = format!("Error re-run of #{} failed; {}", id, status);
+            Err(err_msg)
+        },
+        Err(e) => {
+            let err_msg = format!("Error re-running #{}; err={}", id, e);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Execute `history` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &HistoryArgs) -> Result<(), String> {
+    let path = match history_path() {
+        Some(res) => res,
+        None => return Err("Error history is disabled, or neither TRACPLS_HISTORY, nor HOME is set to locate it".to_owned()),
+    };
+    let history = read_history(&path)?;
+
+    if let Some(id) = args.rerun {
+        return match history.iter().find(|(i, _)| *i == id) {
+            Some((_, entry)) => rerun(id, entry),
+            None => {
+                let err_msg = format!("Error no invocation #{} in history", id);
+                Err(err_msg)
+            }
+        };
+    }
+
+    let since = match args.since.as_ref() {
+        Some(date) => Some(parse_date("since", date)?),
+        None => None,
+    };
+    // the whole day of --until is included
+    let until = match args.until.as_ref() {
+        Some(date) => Some(parse_date("until", date)? + 86400),
+        None => None,
+    };
+    let address = args.address.as_ref().map(|a| a.to_lowercase());
+
+    let mut listed: Vec<&(usize, HistoryEntry)> = history.iter()
+        .filter(|(_, e)| address.is_none() || e.address == address)
+        .filter(|(_, e)| args.chain.as_ref().is_none_or(|c| e.chain.as_ref().is_some_and(|ec| ec.eq_ignore_ascii_case(c))))
+        .filter(|(_, e)| args.command.as_ref().is_none_or(|c| e.command == *c))
+        .filter(|(_, e)| since.is_none_or(|s| e.time >= s))
+        .filter(|(_, e)| until.is_none_or(|u| e.time < u))
+        .filter(|(_, e)| !args.failed || !e.ok)
+        .collect();
+    if let Some(limit) = args.limit {
+        listed.drain(..listed.len().saturating_sub(limit));
+    }
+
+    if args.json {
+        let out: Vec<serde_json::Value> = listed.iter().map(|(id, e)| {
+            let mut value = serde_json::to_value(e).unwrap_or_default();
+            value["id"] = serde_json::json!(id);
+            value
+        }).collect();
+        match serde_json::to_string_pretty(&out) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing history to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+        return Ok(());
+    }
+
+    for (id, e) in listed.iter() {
+        let outcome = if e.ok { "ok" } else { "failed" };
+        println!("{:<5} {} {:<10} {:<10} {:<42} {:<6} tracpls {}",
+            id,
+            display::format_timestamp(&e.time.to_string()),
+            e.command,
+            e.chain.as_deref().unwrap_or("-"),
+            e.address.as_deref().unwrap_or("-"),
+            outcome,
+            e.args.iter().map(|a| shell_quote(a)).collect::<Vec<String>>().join(" "));
+    }
+    Ok(())
+}
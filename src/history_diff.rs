@@ -0,0 +1,241 @@
+//! `history-diff` command: diff two snapshots of the same contract taken with
+//! `--snapshot`, picked by date, entirely offline.
+//!
+//! Snapshots are found by their manifests under `--dir`. `--from`, and `--to`
+//! take `latest`, `earliest`, name of snapshot, or date e.g. `2024-01` or
+//! `2024-01-01`, picking the last snapshot taken on or before it.
+
+use crate::bytecode_diff::{self, Edit};
+use crate::manifest::{self, Manifest};
+use crate::snapshot;
+use clap::Args;
+use std::path::{Path, PathBuf};
+
+/// Levels of subdirectories searched for snapshots e.g. `<out-dir>/<chain>/<address>/<snapshot>`
+const SEARCH_DEPTH: u32 = 4;
+
+/// Edit distance beyond which line-level diff of a file is not computed
+const MAX_EDIT_DISTANCE: usize = 5000;
+
+/// Lines of unchanged source code to show around changes
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Args)]
+pub struct HistoryDiffArgs {
+    /// Contract address
+    #[clap(long="address", short='a', required=true)]
+    pub address: String,
+
+    /// Directory to search for snapshots of contract
+    #[clap(long="dir", short='d', required=false, default_value=".")]
+    pub dir: String,
+
+    /// Snapshot to diff from; `latest`, `earliest`, name of snapshot, or date
+    /// e.g. '2024-01-01'
+    #[clap(long="from", required=false, default_value="earliest")]
+    pub from: String,
+
+    /// Snapshot to diff to; `latest`, `earliest`, name of snapshot, or date
+    /// e.g. '2024-06-30'
+    #[clap(long="to", required=false, default_value="latest")]
+    pub to: String,
+
+    /// List changed files only without their diffs
+    #[clap(long="stat", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub stat: bool,
+}
+
+/// Get name of snapshot directory.
+///
+/// # Arguments
+/// * `path` - path of snapshot directory
+fn name_of(path: &Path) -> String {
+    path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+/// Pick snapshot by `latest`, `earliest`, name of snapshot, or date. Date
+/// picks the last snapshot taken on or before it. Returns index of snapshot.
+///
+/// # Arguments
+/// * `snapshots` - paths of snapshots in order they were taken
+/// * `selector` - value of `--from`, or `--to`
+/// * `fall_back` - whether to pick the earliest snapshot if none was taken by date
+fn select(snapshots: &[PathBuf], selector: &str, fall_back: bool) -> Result<usize, String> {
+    match selector {
+        "latest" => return Ok(snapshots.len() - 1),
+        "earliest" => return Ok(0),
+        _ => (),
+    }
+    if let Some(i) = snapshots.iter().position(|p| name_of(p) == selector) {
+        return Ok(i);
+    }
+
+    let re = regex::Regex::new(r"^\d{4}(-\d{2}(-\d{2})?)?$").unwrap();
+    if !re.is_match(selector) {
+        let err_msg = format!("Error '{}' is neither 'latest', 'earliest', name of snapshot, nor date in form of YYYY-MM-DD", selector);
+        return Err(err_msg);
+    }
+    match snapshots.iter().rposition(|p| name_of(p).get(..selector.len()).is_some_and(|taken| taken <= selector)) {
+        Some(i) => Ok(i),
+        None if fall_back => {
+            eprintln!("Warning: no snapshot was taken on or before {}; using the earliest one {}", selector, name_of(&snapshots[0]));
+            Ok(0)
+        },
+        None => {
+            let err_msg = format!("Error no snapshot was taken on or before {}; the earliest one is {}", selector, name_of(&snapshots[0]));
+            Err(err_msg)
+        }
+    }
+}
+
+/// Read manifest of snapshot.
+///
+/// # Arguments
+/// * `path` - path of snapshot directory
+fn read_snapshot(path: &Path) -> Result<Manifest, String> {
+    match manifest::read_manifest(&path.to_string_lossy())? {
+        Some(res) => Ok(res),
+        None => {
+            let err_msg = format!("Error no manifest in snapshot '{}'", path.display());
+            Err(err_msg)
+        }
+    }
+}
+
+/// Read file of snapshot, or empty content if it isn't there.
+///
+/// # Arguments
+/// * `dir` - path of snapshot directory
+/// * `file` - path of file relative to snapshot directory
+fn read_file(dir: &Path, file: &str) -> Result<String, String> {
+    let path = dir.join(file);
+    if !path.exists() {
+        return Ok(String::new());
+    }
+    match std::fs::read(&path) {
+        Ok(res) => Ok(String::from_utf8_lossy(&res).into_owned()),
+        Err(e) => {
+            let err_msg = format!("Error reading file '{}'; err={}", path.display(), e);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Print changed lines of file along with a few unchanged ones around them
+/// in unified format.
+///
+/// # Arguments
+/// * `file` - path of file
+/// * `a` - content as of the first snapshot
+/// * `b` - content as of the second snapshot
+fn print_file_diff(file: &str, a: &str, b: &str) {
+    let lines_a: Vec<&str> = a.lines().collect();
+    let lines_b: Vec<&str> = b.lines().collect();
+    println!("--- a/{}", file);
+    println!("+++ b/{}", file);
+
+    let edits = match bytecode_diff::diff(&lines_a, &lines_b, MAX_EDIT_DISTANCE) {
+        Some(res) => res,
+        None => {
+            println!("Too many differences to show line-level diff");
+            return;
+        }
+    };
+    let changed: Vec<usize> = edits.iter().enumerate().filter(|(_, e)| !matches!(e, Edit::Keep(_, _))).map(|(i, _)| i).collect();
+
+    // group changes close enough to share context into hunks
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &i in changed.iter() {
+        let start = i.saturating_sub(CONTEXT_LINES);
+        let end = (i + CONTEXT_LINES + 1).min(edits.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    for (start, end) in hunks {
+        let hunk = &edits[start..end];
+
+        // line numbers of hunk in each file are where the first line of either is
+        let mut line_a = edits[..start].iter().filter(|e| !matches!(e, Edit::Insert(_))).count();
+        let mut line_b = edits[..start].iter().filter(|e| !matches!(e, Edit::Remove(_))).count();
+        let len_a = hunk.iter().filter(|e| !matches!(e, Edit::Insert(_))).count();
+        let len_b = hunk.iter().filter(|e| !matches!(e, Edit::Remove(_))).count();
+        if len_a > 0 {
+            line_a += 1;
+        }
+        if len_b > 0 {
+            line_b += 1;
+        }
+        println!("@@ -{},{} +{},{} @@", line_a, len_a, line_b, len_b);
+        for edit in hunk.iter() {
+            match *edit {
+                Edit::Keep(x, _) => println!(" {}", lines_a[x]),
+                Edit::Remove(x) => println!("-{}", lines_a[x]),
+                Edit::Insert(y) => println!("+{}", lines_b[y]),
+            }
+        }
+    }
+}
+
+/// Execute `history-diff` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &HistoryDiffArgs) -> Result<(), String> {
+    let address = args.address.to_lowercase();
+    let mut snapshots: Vec<PathBuf> = Vec::new();
+    snapshot::find(Path::new(&args.dir), &address, SEARCH_DEPTH, &mut snapshots)?;
+    if snapshots.is_empty() {
+        let err_msg = format!("Error no snapshot of {} found under '{}'; fetch with --snapshot to take them", address, args.dir);
+        return Err(err_msg);
+    }
+
+    let from = select(&snapshots, &args.from, true)?;
+    let to = select(&snapshots, &args.to, false)?;
+    if from > to {
+        let err_msg = format!("Error --from selects snapshot {} taken after {} selected by --to", name_of(&snapshots[from]), name_of(&snapshots[to]));
+        return Err(err_msg);
+    }
+    let (dir_a, dir_b) = (&snapshots[from], &snapshots[to]);
+    println!("from: {} ({})", name_of(dir_a), dir_a.display());
+    println!("to:   {} ({})", name_of(dir_b), dir_b.display());
+    if from == to {
+        println!("No changes; both select the same snapshot");
+        return Ok(());
+    }
+
+    let manifest_a = read_snapshot(dir_a)?;
+    let manifest_b = read_snapshot(dir_b)?;
+    let mut changes: Vec<(char, &String)> = Vec::new();
+    for (file, entry) in manifest_a.files.iter() {
+        match manifest_b.files.get(file) {
+            None => changes.push(('D', file)),
+            Some(other) if other.keccak256 != entry.keccak256 => changes.push(('M', file)),
+            Some(_) => (),
+        }
+    }
+    for file in manifest_b.files.keys().filter(|f| !manifest_a.files.contains_key(*f)) {
+        changes.push(('A', file));
+    }
+    changes.sort_by_key(|(_, file)| *file);
+
+    if changes.is_empty() {
+        println!("No changes");
+        return Ok(());
+    }
+    println!();
+    for (kind, file) in changes.iter() {
+        println!("{} {}", kind, file);
+    }
+    if args.stat {
+        return Ok(());
+    }
+
+    for (_, file) in changes.iter() {
+        println!();
+        print_file_diff(file, &read_file(dir_a, file)?, &read_file(dir_b, file)?);
+    }
+    Ok(())
+}
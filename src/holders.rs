@@ -0,0 +1,203 @@
+//! `holders` command: snapshot of top holders of token along with their
+//! labels, and how concentrated its supply is, for token due-diligence next
+//! to source code, and `risk` report.
+//!
+//! Holders come from `topholders` endpoint, falling back to
+//! `tokenholderlist` where it isn't available, both of which may need a paid
+//! plan of API platform. Name, symbol, decimals, and total supply are read
+//! over RPC. Holders are labelled by public name tag, or name of their
+//! verified contract e.g. pair, or vesting wallet.
+
+use crate::abi;
+use crate::display;
+use crate::explorer;
+use crate::multicall;
+use crate::TargetArgs;
+use clap::Args;
+use serde_json::{json, Value};
+
+/// Addresses holding burnt tokens
+const BURN_ADDRESSES: [&str; 2] = [
+    "0x0000000000000000000000000000000000000000",
+    "0x000000000000000000000000000000000000dead",
+];
+
+#[derive(Debug, Args)]
+pub struct HoldersArgs {
+    #[clap(flatten)]
+    pub target: TargetArgs,
+
+    /// Number of top holders to list
+    #[clap(long="top", required=false, default_value="20")]
+    pub top: usize,
+
+    /// Output as JSON instead of human readable table
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+}
+
+/// Token holder.
+struct Holder {
+    address: String,
+
+    /// Balance in smallest unit as decimal string
+    quantity: String,
+
+    /// Name tag, or contract name, or empty if neither
+    label: String,
+}
+
+/// Get top holders of token sorted by balance descending.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `token` - token address
+/// * `top` - number of holders to get
+fn get_holders(chain: &crate::backend::Chain, token: &str, top: usize) -> Result<Vec<Holder>, String> {
+    let offset = top.to_string();
+    let result = explorer::query(chain, &[
+        ("module", "token"),
+        ("action", "topholders"),
+        ("contractaddress", token),
+        ("offset", &offset),
+    ]).or_else(|_| explorer::query(chain, &[
+        ("module", "token"),
+        ("action", "tokenholderlist"),
+        ("contractaddress", token),
+        ("page", "1"),
+        ("offset", "10000"),
+    ]))?;
+    let entries = match result.as_array() {
+        Some(res) => res,
+        None => {
+            let err_msg = format!("Error unexpected response for holders of {}; result={}", token, result);
+            return Err(err_msg);
+        }
+    };
+
+    let text = |entry: &Value, names: &[&str]| names.iter().find_map(|n| entry[*n].as_str()).unwrap_or("").to_owned();
+    let mut holders: Vec<Holder> = entries.iter()
+        .map(|e| Holder {
+            address: text(e, &["TokenHolderAddress", "address"]).to_lowercase(),
+            quantity: text(e, &["TokenHolderQuantity", "value"]),
+            label: String::new(),
+        })
+        .filter(|h| !h.address.is_empty())
+        .collect();
+
+    // digit strings without leading zeros compare by length first
+    holders.sort_by(|a, b| (b.quantity.len(), &b.quantity).cmp(&(a.quantity.len(), &a.quantity)));
+    holders.truncate(top);
+    Ok(holders)
+}
+
+/// Label holder by public name tag, or name of its verified contract.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - address of holder
+fn label_of(chain: &crate::backend::Chain, address: &str) -> String {
+    if BURN_ADDRESSES.contains(&address) {
+        return "(burn)".to_owned();
+    }
+    if let Some(tag) = explorer::get_name_tag(chain, address) {
+        return tag.describe();
+    }
+
+    // labels are informative only, so failing to get them isn't fatal
+    explorer::get_source_meta(chain, address).ok()
+        .and_then(|meta| meta["ContractName"].as_str().map(|n| n.to_owned()))
+        .filter(|n| !n.is_empty())
+        .map_or_else(String::new, |n| format!("contract {}", n))
+}
+
+/// Compute share of quantity in percent of total supply.
+///
+/// # Arguments
+/// * `quantity` - quantity as decimal string
+/// * `total_supply` - total supply as decimal string
+fn share(quantity: &str, total_supply: &str) -> Option<f64> {
+    let total: f64 = total_supply.parse().ok().filter(|t| *t > 0.0)?;
+    Some(100.0 * quantity.parse::<f64>().ok()? / total)
+}
+
+/// Execute `holders` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &HoldersArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.target.chain)?;
+    let token = args.target.address.to_lowercase();
+
+    let calls: Vec<(String, String)> = ["name()", "symbol()", "decimals()", "totalSupply()"].iter()
+        .map(|s| (token.clone(), abi::selector_of(s)))
+        .collect();
+    let returned = multicall::call_many(chain, &calls);
+    let name = returned[0].as_ref().and_then(|d| abi::decode_string(d)).unwrap_or_default();
+    let symbol = returned[1].as_ref().and_then(|d| abi::decode_string(d)).unwrap_or_default();
+    let decimals = returned[2].as_ref().filter(|d| d.len() >= 32).map_or(0, |d| d[31] as usize);
+    let total_supply = match returned[3].as_ref().filter(|d| d.len() >= 32) {
+        Some(res) => abi::to_decimal(&res[..32]),
+        None => {
+            let err_msg = format!("Error {} doesn't look like token; totalSupply() can't be called", token);
+            return Err(err_msg);
+        }
+    };
+
+    let mut holders = get_holders(chain, &token, args.top)?;
+    for h in holders.iter_mut() {
+        h.label = label_of(chain, &h.address);
+    }
+    let top_share = |n: usize| -> Option<f64> {
+        if n == 0 || n > holders.len() {
+            return None;
+        }
+        holders.iter().take(n).map(|h| share(&h.quantity, &total_supply)).sum()
+    };
+
+    if args.json {
+        let out = json!({
+            "token": token,
+            "name": name,
+            "symbol": symbol,
+            "decimals": decimals,
+            "totalSupply": total_supply,
+            "holders": holders.iter().enumerate().map(|(i, h)| json!({
+                "rank": i + 1,
+                "address": h.address,
+                "quantity": h.quantity,
+                "quantityFormatted": display::format_units(&h.quantity, decimals),
+                "share": share(&h.quantity, &total_supply),
+                "label": h.label,
+            })).collect::<Vec<Value>>(),
+            "top1Share": top_share(1),
+            "top10Share": top_share(10),
+            "topShare": top_share(holders.len()),
+        });
+        match serde_json::to_string_pretty(&out) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing holders to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+        return Ok(());
+    }
+
+    println!("{} ({}) total supply: {} {}\n", name, symbol, display::format_units(&total_supply, decimals), symbol);
+    println!("{:<4} {:<42} {:>32} {:>8} LABEL", "#", "ADDRESS", "BALANCE", "SHARE");
+    for (i, h) in holders.iter().enumerate() {
+        let percent = share(&h.quantity, &total_supply).map_or("-".to_owned(), |s| format!("{:.2}%", s));
+        println!("{:<4} {:<42} {:>32} {:>8} {}", i + 1, h.address, display::format_units(&h.quantity, decimals), percent, h.label);
+    }
+    let mut tops: Vec<usize> = vec![1, 10, holders.len()];
+    tops.sort();
+    tops.dedup();
+    let shares: Vec<String> = tops.iter()
+        .filter_map(|n| top_share(*n).map(|s| format!("top {}: {:.2}%", n, s)))
+        .collect();
+    if !shares.is_empty() {
+        println!("\n{}", shares.join(", "));
+    }
+    Ok(())
+}
@@ -0,0 +1,197 @@
+//! Debugging of requests to API platforms; summaries of requests, and
+//! responses printed to stderr with `--debug-http`, and the whole session
+//! recorded as HAR with `--har` to share with support of explorer.
+//!
+//! API keys in query string, and values of headers carrying credentials are
+//! redacted in both.
+
+use serde_json::{json, Value};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+/// Replacement of redacted secrets
+const REDACTED: &str = "REDACTED";
+
+/// Maximum number of bytes of response body printed with `--debug-http`
+const MAX_BODY_PREVIEW: usize = 500;
+
+/// Whether or not to print summaries of requests, and responses
+static DEBUG: Mutex<bool> = Mutex::new(false);
+
+/// Recorded HAR entries, or `None` if not recording
+static HAR_ENTRIES: Mutex<Option<Vec<Value>>> = Mutex::new(None);
+
+/// Exchange of request, and response with API platform.
+#[derive(Debug, Clone)]
+pub struct Exchange<'a> {
+    pub method: &'a str,
+    pub url: &'a str,
+
+    /// Headers of request as pairs of name, and value
+    pub request_headers: &'a [(String, String)],
+
+    pub status: u16,
+
+    /// Headers of response as pairs of name, and value
+    pub response_headers: Vec<(String, String)>,
+
+    pub body: &'a str,
+
+    /// When request was sent
+    pub started: SystemTime,
+
+    /// Time taken until whole response body was read
+    pub elapsed: Duration,
+}
+
+/// Set whether or not to print summaries of requests, and responses.
+///
+/// # Arguments
+/// * `debug` - whether to print
+pub fn set_debug(debug: bool) {
+    if let Ok(mut current) = DEBUG.lock() {
+        *current = debug;
+    }
+}
+
+/// Start recording requests, and responses to be written as HAR.
+pub fn start_recording() {
+    if let Ok(mut entries) = HAR_ENTRIES.lock() {
+        *entries = Some(Vec::new());
+    }
+}
+
+/// Whether or not exchanges are logged at all, so callers can skip collecting
+/// them.
+pub fn is_enabled() -> bool {
+    DEBUG.lock().is_ok_and(|d| *d) || HAR_ENTRIES.lock().is_ok_and(|e| e.is_some())
+}
+
+/// Get url with value of `apikey` query parameter redacted.
+///
+/// # Arguments
+/// * `url` - url
+pub fn redact_url(url: &str) -> String {
+    let (base, query) = match url.split_once('?') {
+        Some(res) => res,
+        None => return url.to_owned(),
+    };
+    let params: Vec<String> = query.split('&').map(|p| match p.split_once('=') {
+        Some((key, _)) if key.eq_ignore_ascii_case("apikey") => format!("{}={}", key, REDACTED),
+        _ => p.to_owned(),
+    }).collect();
+    format!("{}?{}", base, params.join("&"))
+}
+
+/// Get value of header redacted if its name suggests it carries credentials.
+///
+/// # Arguments
+/// * `name` - name of header
+/// * `value` - value of header
+fn redact_header(name: &str, value: &str) -> String {
+    let lower = name.to_lowercase();
+    let secret = ["auth", "token", "key", "secret", "cookie", "password"].iter().any(|w| lower.contains(w));
+    if secret { REDACTED.to_owned() } else { value.to_owned() }
+}
+
+/// Format time as ISO 8601 in UTC with milliseconds.
+///
+/// # Arguments
+/// * `time` - time
+fn iso8601(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+    let (year, month, day, hour, minute, second) = crate::display::civil_from_timestamp(since_epoch.as_secs() as i64);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z", year, month, day, hour, minute, second, since_epoch.subsec_millis())
+}
+
+/// Log exchange with API platform as enabled.
+///
+/// # Arguments
+/// * `exchange` - request, and response
+pub fn log(exchange: &Exchange) {
+    let url = redact_url(exchange.url);
+    let request_headers: Vec<(String, String)> = exchange.request_headers.iter().map(|(n, v)| (n.clone(), redact_header(n, v))).collect();
+    let response_headers: Vec<(String, String)> = exchange.response_headers.iter().map(|(n, v)| (n.clone(), redact_header(n, v))).collect();
+
+    if DEBUG.lock().is_ok_and(|d| *d) {
+        eprintln!("> {} {}", exchange.method, url);
+        for (name, value) in request_headers.iter() {
+            eprintln!("> {}: {}", name, value);
+        }
+        eprintln!("< {} ({} bytes in {}ms)", exchange.status, exchange.body.len(), exchange.elapsed.as_millis());
+        for (name, value) in response_headers.iter() {
+            eprintln!("< {}: {}", name, value);
+        }
+        let mut end = exchange.body.len().min(MAX_BODY_PREVIEW);
+        while !exchange.body.is_char_boundary(end) {
+            end -= 1;
+        }
+        let ellipsis = if end < exchange.body.len() { "..." } else { "" };
+        eprintln!("< {}{}\n", &exchange.body[..end], ellipsis);
+    }
+
+    if let Ok(mut entries) = HAR_ENTRIES.lock() {
+        if let Some(entries) = entries.as_mut() {
+            let to_har = |headers: &[(String, String)]| headers.iter().map(|(n, v)| json!({ "name": n, "value": v })).collect::<Vec<Value>>();
+            let query: Vec<Value> = url.split_once('?').map_or("", |(_, q)| q).split('&').filter(|p| !p.is_empty())
+                .map(|p| {
+                    let (name, value) = p.split_once('=').unwrap_or((p, ""));
+                    json!({ "name": name, "value": value })
+                })
+                .collect();
+            let mime_type = exchange.response_headers.iter().find(|(n, _)| n.eq_ignore_ascii_case("content-type")).map_or("", |(_, v)| v.as_str());
+            let millis = exchange.elapsed.as_millis() as u64;
+            entries.push(json!({
+                "startedDateTime": iso8601(exchange.started),
+                "time": millis,
+                "request": {
+                    "method": exchange.method,
+                    "url": url,
+                    "httpVersion": "HTTP/1.1",
+                    "cookies": [],
+                    "headers": to_har(&request_headers),
+                    "queryString": query,
+                    "headersSize": -1,
+                    "bodySize": 0,
+                },
+                "response": {
+                    "status": exchange.status,
+                    "statusText": "",
+                    "httpVersion": "HTTP/1.1",
+                    "cookies": [],
+                    "headers": to_har(&response_headers),
+                    "content": { "size": exchange.body.len(), "mimeType": mime_type, "text": exchange.body },
+                    "redirectURL": "",
+                    "headersSize": -1,
+                    "bodySize": exchange.body.len(),
+                },
+                "cache": {},
+                "timings": { "send": 0, "wait": millis, "receive": 0 },
+            }));
+        }
+    }
+}
+
+/// Write recorded exchanges as HAR file.
+///
+/// # Arguments
+/// * `path` - path of HAR file
+pub fn write_har(path: &str) -> Result<(), String> {
+    let entries = HAR_ENTRIES.lock().ok().and_then(|e| e.clone()).unwrap_or_default();
+    let har = json!({
+        "log": {
+            "version": "1.2",
+            "creator": { "name": "tracpls", "version": env!("CARGO_PKG_VERSION") },
+            "entries": entries,
+        }
+    });
+    let content = match serde_json::to_string_pretty(&har) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error serializing HAR; err={}", e);
+            return Err(err_msg);
+        }
+    };
+    crate::create_intermediate_dirs(path)?;
+    crate::write_file(path, &format!("{}\n", content))
+}
@@ -0,0 +1,120 @@
+//! `immutables` command: decode values of immutable variables of contract.
+//!
+//! Values of immutable variables are embedded into runtime bytecode at
+//! deployment, so they are not visible in source code, nor in storage. Their
+//! positions are taken from `immutableReferences` of recompiled verified
+//! source, then read from runtime bytecode deployed on chain.
+
+use crate::abi;
+use crate::build;
+use crate::explorer;
+use crate::TargetArgs;
+use clap::Args;
+use serde_json::{json, Value};
+
+#[derive(Debug, Args)]
+pub struct ImmutablesArgs {
+    #[clap(flatten)]
+    pub target: TargetArgs,
+
+    /// Path to solc binary to use instead of finding one matching the verified
+    /// compiler version
+    #[clap(long="solc", required=false)]
+    pub solc: Option<String>,
+
+    /// Output as JSON instead of human readable table
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+}
+
+/// Find AST node by its id.
+///
+/// # Arguments
+/// * `node` - AST node to search in
+/// * `id` - id of node
+fn find_node(node: &Value, id: u64) -> Option<&Value> {
+    match node {
+        Value::Object(map) => {
+            if map.get("id").and_then(|i| i.as_u64()) == Some(id) && map.contains_key("nodeType") {
+                return Some(node);
+            }
+            map.values().find_map(|v| find_node(v, id))
+        },
+        Value::Array(items) => items.iter().find_map(|v| find_node(v, id)),
+        _ => None,
+    }
+}
+
+/// Execute `immutables` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &ImmutablesArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.target.chain)?;
+    let built = build::build(&args.target, args.solc.as_ref(), json!({
+        "*": { "*": ["evm.deployedBytecode.immutableReferences"], "": ["ast"] }
+    }))?;
+
+    let contract = built.output["contracts"].as_object()
+        .and_then(|files| files.values().find_map(|f| f.get(&built.contract_name)));
+    let references = match contract.and_then(|c| c["evm"]["deployedBytecode"]["immutableReferences"].as_object()) {
+        Some(res) => res,
+        None => {
+            let err_msg = format!("Error contract '{}' not found in compiled output", built.contract_name);
+            return Err(err_msg);
+        }
+    };
+
+    let code = explorer::get_code(chain, &args.target.address)?;
+    let sources: Vec<&Value> = built.output["sources"].as_object().map_or(Vec::new(), |s| s.values().map(|u| &u["ast"]).collect());
+
+    let mut immutables: Vec<Value> = Vec::new();
+    for (id, positions) in references.iter() {
+        let declaration = id.parse::<u64>().ok().and_then(|id| sources.iter().find_map(|ast| find_node(ast, id)));
+        let name = declaration.and_then(|d| d["name"].as_str()).unwrap_or("");
+        let kind = declaration.and_then(|d| d["typeDescriptions"]["typeString"].as_str()).unwrap_or("");
+
+        // every position holds the same value, the first one is enough
+        let start = positions[0]["start"].as_u64().unwrap_or(0) as usize;
+        let length = positions[0]["length"].as_u64().unwrap_or(32) as usize;
+        let word = match code.get(start..start + length) {
+            Some(res) => res,
+            None => {
+                let err_msg = format!("Error immutable '{}' at {} is out of deployed bytecode of {} bytes; deployed bytecode doesn't match verified source", name, start, code.len());
+                return Err(err_msg);
+            }
+        };
+
+        immutables.push(json!({
+            "name": name,
+            "type": kind,
+            "offset": start,
+            "value": abi::decode_word(kind, word),
+            "raw": format!("0x{}", abi::to_hex(word)),
+        }));
+    }
+    immutables.sort_by_key(|i| i["offset"].as_u64());
+
+    if args.json {
+        match serde_json::to_string_pretty(&immutables) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing immutables to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+        return Ok(());
+    }
+
+    if immutables.is_empty() {
+        println!("No immutable variables found");
+        return Ok(());
+    }
+
+    let text = |v: &Value| v.as_str().unwrap_or("").to_owned();
+    println!("{:<32} {:<24} VALUE", "NAME", "TYPE");
+    for immutable in immutables.iter() {
+        println!("{:<32} {:<24} {}", text(&immutable["name"]), text(&immutable["type"]), text(&immutable["value"]));
+    }
+    Ok(())
+}
@@ -0,0 +1,129 @@
+//! Read addresses out of CSV exported by analytics platforms e.g. Dune, or
+//! BigQuery, for `batch --import-csv`.
+//!
+//! Such exports vary, so parsing is tolerant; byte order mark, CRLF, quoted
+//! fields, and tab-separated values are accepted, and addresses are
+//! normalized from forms like `"0xAbC..."`, `\xabc...` (bytea), `0XABC...`,
+//! no prefix, or left-padded to 32 bytes. Duplicates are removed keeping order.
+
+use std::collections::HashSet;
+
+/// Split CSV content into records of fields as of RFC 4180.
+///
+/// # Arguments
+/// * `content` - CSV content
+/// * `delimiter` - field delimiter
+fn parse_records(content: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            },
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            _ if quoted => field.push(c),
+            _ if c == delimiter => record.push(std::mem::take(&mut field)),
+            '\r' => (),
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            },
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    // blank lines are not records
+    records.retain(|r| r.iter().any(|f| !f.trim().is_empty()));
+    records
+}
+
+/// Normalize address as exported into lowercase with `0x` prefix, or `None`
+/// if it is not an address.
+///
+/// # Arguments
+/// * `value` - value of field
+pub fn normalize_address(value: &str) -> Option<String> {
+    let value = value.trim().trim_matches(|c| c == '"' || c == '\'').trim();
+    let hex = ["0x", "0X", "\\x"].iter().find_map(|p| value.strip_prefix(p)).unwrap_or(value);
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    // 32-byte word of address e.g. from topics of logs
+    let hex = match hex.len() {
+        40 => hex,
+        64 if hex[..24].bytes().all(|b| b == b'0') => &hex[24..],
+        _ => return None,
+    };
+    Some(format!("0x{}", hex.to_lowercase()))
+}
+
+/// Read distinct addresses from column of CSV file, in order of their first
+/// appearance. Values which are not addresses are skipped with a warning.
+///
+/// # Arguments
+/// * `path` - path of CSV file
+/// * `column` - name of column holding addresses, case-insensitive. If
+///   `None`, the first column whose name contains "address" is used.
+pub fn read_addresses(path: &str, column: Option<&str>) -> Result<Vec<String>, String> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error reading CSV file '{}'; err={}", path, e);
+            return Err(err_msg);
+        }
+    };
+    let content = content.trim_start_matches('\u{feff}');
+    let header_line = content.lines().next().unwrap_or("");
+    let delimiter = if header_line.contains('\t') && !header_line.contains(',') { '\t' } else { ',' };
+
+    let records = parse_records(content, delimiter);
+    let (header, rows) = match records.split_first() {
+        Some(res) => res,
+        None => {
+            let err_msg = format!("Error CSV file '{}' is empty", path);
+            return Err(err_msg);
+        }
+    };
+    let names: Vec<String> = header.iter().map(|h| h.trim().to_lowercase()).collect();
+    let index = match column {
+        Some(column) => names.iter().position(|n| *n == column.trim().to_lowercase()),
+        None => names.iter().position(|n| n.contains("address")),
+    };
+    let index = match index {
+        Some(res) => res,
+        None => {
+            let err_msg = format!("Error no column {} in CSV file '{}'; columns are {}",
+                column.map_or("named with 'address'".to_owned(), |c| format!("'{}'", c)),
+                path,
+                header.iter().map(|h| format!("'{}'", h.trim())).collect::<Vec<String>>().join(", "));
+            return Err(err_msg);
+        }
+    };
+
+    let mut addresses: Vec<String> = Vec::new();
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut invalid = 0;
+    for row in rows.iter() {
+        match row.get(index).and_then(|v| normalize_address(v)) {
+            Some(address) => {
+                if seen.insert(address.clone()) {
+                    addresses.push(address);
+                }
+            },
+            None => invalid += 1,
+        }
+    }
+    if invalid > 0 {
+        eprintln!("Warning: skipped {} row(s) of '{}' without address in column '{}'", invalid, path, header[index].trim());
+    }
+    Ok(addresses)
+}
@@ -0,0 +1,217 @@
+//! Interface-only extraction of verified source code with
+//! `--interfaces-only`, for projects which only call the protocol instead of
+//! vendoring its implementation.
+//!
+//! Files declaring nothing but interfaces, and abstract contracts are kept,
+//! along with files they import so they still compile. Interface of the
+//! primary contract is generated from its ABI as `I<ContractName>.sol`
+//! unless one by that name is already kept.
+
+use crate::abi::{Abi, Entry, Param};
+use crate::solidity;
+use crate::source::{self, SourceFile};
+
+/// Keep only interface, and abstract contract files, then add interface of
+/// the primary contract generated from its ABI if needed.
+///
+/// # Arguments
+/// * `files` - source files in order
+/// * `address` - contract address
+/// * `contract_name` - name of the primary contract
+/// * `abi_text` - ABI of the primary contract in JSON, or `None` if not known
+pub fn keep_interfaces(files: &mut Vec<SourceFile>, address: &str, contract_name: &str, abi_text: Option<&str>) {
+    let roots: Vec<usize> = files.iter().enumerate()
+        .filter(|(_, f)| {
+            let contracts = solidity::parse_contracts(&f.path, &f.content);
+            !contracts.is_empty() && contracts.iter().all(|c| c.kind == "interface" || c.kind == "abstract contract")
+        })
+        .map(|(i, _)| i)
+        .collect();
+    let mut kept = source::reachable(files, roots);
+    kept.sort();
+
+    let mut taken: Vec<Option<SourceFile>> = files.drain(..).map(Some).collect();
+    files.extend(kept.iter().filter_map(|i| taken[*i].take()));
+
+    let name = format!("I{}", contract_name);
+    if contract_name.is_empty() || files.iter().any(|f| source::declares(&f.content, &name)) {
+        return;
+    }
+    let abi_text = match abi_text {
+        Some(res) => res,
+        None => {
+            eprintln!("Warning: interface of {} is not generated; ABI is only known for verified contract", contract_name);
+            return;
+        }
+    };
+    match Abi::parse(abi_text) {
+        Ok(abi) => files.insert(0, SourceFile {
+            path: format!("{}.sol", name),
+            content: generate(&name, &abi, contract_name, address),
+        }),
+        Err(e) => eprintln!("Warning: interface of {} is not generated; {}", contract_name, e),
+    }
+}
+
+/// Get name of struct of tuple parameter from its internal type e.g. `Order`
+/// of `struct Exchange.Order[]`, or `None` if it isn't known.
+///
+/// # Arguments
+/// * `param` - tuple parameter
+fn struct_name(param: &Param) -> Option<String> {
+    let internal_type = param.internal_type.as_deref()?.strip_prefix("struct ")?;
+    let name = internal_type.split('[').next().unwrap_or(internal_type);
+    Some(name.rsplit('.').next().unwrap_or(name).to_owned())
+}
+
+/// Struct of tuple parameters to be declared in interface.
+struct StructDef {
+    name: String,
+
+    /// Canonical type of tuple without array suffix e.g. `(address,uint256)`,
+    /// to name tuples without internal type
+    canonical: String,
+
+    definition: String,
+}
+
+/// Get canonical type of tuple parameter without its array suffix.
+///
+/// # Arguments
+/// * `param` - tuple parameter
+fn tuple_type(param: &Param) -> String {
+    let inner: Vec<String> = param.components.iter().map(|c| c.canonical_type()).collect();
+    format!("({})", inner.join(","))
+}
+
+/// Collect struct definitions of tuple parameters, nested ones first.
+///
+/// # Arguments
+/// * `params` - parameters
+/// * `structs` - structs collected so far
+fn collect_structs(params: &[Param], structs: &mut Vec<StructDef>) {
+    for param in params.iter().filter(|p| p.kind.starts_with("tuple")) {
+        collect_structs(&param.components, structs);
+
+        let canonical = tuple_type(param);
+        let name = struct_name(param).unwrap_or_else(|| format!("Struct{}", structs.len()));
+        if structs.iter().any(|s| s.name == name || (struct_name(param).is_none() && s.canonical == canonical)) {
+            continue;
+        }
+        let mut definition = format!("    struct {} {{\n", name);
+        for (i, component) in param.components.iter().enumerate() {
+            let field = if component.name.is_empty() { format!("field{}", i) } else { component.name.clone() };
+            definition.push_str(&format!("        {} {};\n", type_of(component, structs), field));
+        }
+        definition.push_str("    }\n");
+        structs.push(StructDef { name, canonical, definition });
+    }
+}
+
+/// Get type of parameter as written in Solidity. Tuples are named after
+/// their structs, contracts, and enums are taken as their ABI types.
+///
+/// # Arguments
+/// * `param` - parameter
+/// * `structs` - structs collected
+fn type_of(param: &Param, structs: &[StructDef]) -> String {
+    match param.kind.strip_prefix("tuple") {
+        Some(suffix) => {
+            let name = struct_name(param).unwrap_or_else(|| {
+                let canonical = tuple_type(param);
+                structs.iter().find(|s| s.canonical == canonical).map_or_else(|| "Struct".to_owned(), |s| s.name.clone())
+            });
+            format!("{}{}", name, suffix)
+        },
+        None => param.kind.clone(),
+    }
+}
+
+/// Format parameters of function, event, or error.
+///
+/// # Arguments
+/// * `params` - parameters
+/// * `location` - data location of reference types, or empty for none
+/// * `structs` - structs collected
+fn format_params(params: &[Param], location: &str, structs: &[StructDef]) -> String {
+    params.iter()
+        .map(|p| {
+            let mut param = type_of(p, structs);
+            let is_reference = p.kind.ends_with(']') || p.kind.starts_with("tuple") || p.kind == "string" || p.kind == "bytes";
+            if is_reference && !location.is_empty() {
+                param.push(' ');
+                param.push_str(location);
+            }
+            if p.indexed {
+                param.push_str(" indexed");
+            }
+            if !p.name.is_empty() {
+                param.push(' ');
+                param.push_str(&p.name);
+            }
+            param
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Format declaration of ABI entry within interface, or `None` if it isn't
+/// part of interface e.g. constructor.
+///
+/// # Arguments
+/// * `entry` - ABI entry
+/// * `structs` - structs collected
+fn declaration(entry: &Entry, structs: &[StructDef]) -> Option<String> {
+    let mutability = match entry.mutability() {
+        "nonpayable" => String::new(),
+        m => format!(" {}", m),
+    };
+    match entry.kind.as_str() {
+        "function" => {
+            let mut out = format!("function {}({}) external{}", entry.name, format_params(&entry.inputs, "calldata", structs), mutability);
+            if !entry.outputs.is_empty() {
+                out.push_str(&format!(" returns ({})", format_params(&entry.outputs, "memory", structs)));
+            }
+            Some(format!("{};", out))
+        },
+        "event" => Some(format!("event {}({}){};", entry.name, format_params(&entry.inputs, "", structs), if entry.anonymous { " anonymous" } else { "" })),
+        "error" => Some(format!("error {}({});", entry.name, format_params(&entry.inputs, "", structs))),
+        "fallback" => Some(format!("fallback() external{};", mutability)),
+        "receive" => Some("receive() external payable;".to_owned()),
+        _ => None,
+    }
+}
+
+/// Generate Solidity interface from ABI.
+///
+/// # Arguments
+/// * `name` - name of interface
+/// * `abi` - ABI
+/// * `contract_name` - name of contract ABI is of
+/// * `address` - contract address
+pub fn generate(name: &str, abi: &Abi, contract_name: &str, address: &str) -> String {
+    let mut structs: Vec<StructDef> = Vec::new();
+    for entry in abi.entries.iter().filter(|e| e.kind != "constructor") {
+        collect_structs(&entry.inputs, &mut structs);
+        collect_structs(&entry.outputs, &mut structs);
+    }
+
+    // custom errors need 0.8.4
+    let pragma = if abi.errors().next().is_some() { "^0.8.4" } else { "^0.8.0" };
+    let mut out = format!("// SPDX-License-Identifier: UNLICENSED\n// Generated by tracpls from ABI of {} at {}\npragma solidity {};\n\ninterface {} {{\n", contract_name, address, pragma, name);
+    for s in structs.iter() {
+        out.push_str(&s.definition);
+        out.push('\n');
+    }
+    for kind in ["error", "event", "fallback", "receive", "function"] {
+        let declarations: Vec<String> = abi.entries.iter()
+            .filter(|e| e.kind == kind)
+            .filter_map(|e| declaration(e, &structs))
+            .collect();
+        for d in declarations.iter() {
+            out.push_str(&format!("    {}\n", d));
+        }
+    }
+    out.push_str("}\n");
+    out
+}
@@ -0,0 +1,122 @@
+//! `internal-txs` command: list internal transactions of an address.
+
+use crate::display;
+use crate::explorer;
+use crate::TargetArgs;
+use clap::Args;
+
+#[derive(Debug, Args)]
+pub struct InternalTxsArgs {
+    #[clap(flatten)]
+    pub target: TargetArgs,
+
+    /// Starting block number to list internal transactions from
+    #[clap(long="start-block", required=false)]
+    pub start_block: Option<u64>,
+
+    /// Ending block number to list internal transactions until
+    #[clap(long="end-block", required=false)]
+    pub end_block: Option<u64>,
+
+    /// List from the oldest transactions first instead of the latest
+    #[clap(long="asc", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub asc: bool,
+
+    /// Output as JSON instead of human readable table
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+}
+
+/// Get string value of field from JSON object, or empty string if not exist.
+fn field<'a>(tx: &'a serde_json::Value, name: &str) -> &'a str {
+    tx[name].as_str().unwrap_or("")
+}
+
+/// Execute `internal-txs` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &InternalTxsArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.target.chain)?;
+    let address = args.target.address.to_lowercase();
+
+    let start_block = args.start_block.unwrap_or(0).to_string();
+    let end_block = args.end_block.map_or("99999999999".to_owned(), |v| v.to_string());
+    let sort = if args.asc { "asc" } else { "desc" };
+
+    let result = explorer::query(chain, &[
+        ("module", "account"),
+        ("action", "txlistinternal"),
+        ("address", &address),
+        ("startblock", &start_block),
+        ("endblock", &end_block),
+        ("sort", sort),
+    ])?;
+
+    let txs = match result.as_array() {
+        Some(res) => res,
+        None => {
+            let err_msg = format!("Error unexpected response for internal transactions; result={}", result);
+            return Err(err_msg);
+        }
+    };
+
+    let symbol = display::native_symbol(chain);
+    let decoded: Vec<serde_json::Value> = txs.iter().map(|tx| {
+        let from = field(tx, "from").to_lowercase();
+        // contract creation has empty `to` but has `contractAddress`
+        let to = if field(tx, "to").is_empty() { field(tx, "contractAddress").to_lowercase() } else { field(tx, "to").to_lowercase() };
+        let direction = if from == address { "out" } else if to == address { "in" } else { "-" };
+
+        serde_json::json!({
+            "blockNumber": field(tx, "blockNumber"),
+            "time": display::format_timestamp(field(tx, "timeStamp")),
+            "hash": field(tx, "hash"),
+            "type": field(tx, "type"),
+            "direction": direction,
+            "from": from,
+            "to": to,
+            "value": field(tx, "value"),
+            "valueFormatted": format!("{} {}", display::format_units(field(tx, "value"), 18), symbol),
+            "gasUsed": field(tx, "gasUsed"),
+            "isError": field(tx, "isError") == "1",
+            "errCode": field(tx, "errCode"),
+        })
+    }).collect();
+
+    if args.json {
+        match serde_json::to_string_pretty(&decoded) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing internal transactions to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+        return Ok(());
+    }
+
+    if decoded.is_empty() {
+        println!("No internal transactions found");
+        return Ok(());
+    }
+
+    println!("{:<10} {:<19} {:<8} {:<3} {:<42} {:<42} {:>28} TX HASH", "BLOCK", "TIME (UTC)", "TYPE", "DIR", "FROM", "TO", "VALUE");
+    for tx in decoded.iter() {
+        let mut value = tx["valueFormatted"].as_str().unwrap_or("").to_owned();
+        if tx["isError"].as_bool().unwrap_or(false) {
+            value = format!("[failed] {}", value);
+        }
+
+        println!("{:<10} {:<19} {:<8} {:<3} {:<42} {:<42} {:>28} {}",
+            field(tx, "blockNumber"),
+            field(tx, "time"),
+            field(tx, "type"),
+            field(tx, "direction"),
+            field(tx, "from"),
+            field(tx, "to"),
+            value,
+            field(tx, "hash"));
+    }
+
+    Ok(())
+}
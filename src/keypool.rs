@@ -0,0 +1,174 @@
+//! Pool of API keys of a backend. Keys are used in round-robin, each no more
+//! often than its rate limit, and a key is put to cool down for a while after
+//! API platform reports it being rate limited.
+//!
+//! Multiple keys are configured as comma-separated value of environment
+//! variable holding API key e.g. `TRACPLS_BSCSCAN_APIKEY=KEY1,KEY2,KEY3`.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// State of a single API key.
+#[derive(Debug)]
+struct KeyState {
+    key: String,
+
+    /// Earliest time the key can be used again to stay within its rate limit
+    next_use: Instant,
+
+    /// Time until which the key is cooling down after being rate limited
+    cooldown_until: Option<Instant>,
+}
+
+#[derive(Debug, Default)]
+struct PoolState {
+    /// Keys read from environment variable on first use
+    keys: Option<Vec<KeyState>>,
+
+    /// Index of key to try first on next use
+    next: usize,
+}
+
+/// Pool of API keys rotated between requests.
+#[derive(Debug)]
+pub struct KeyPool {
+    /// Minimum interval between two uses of the same key
+    pub min_interval: Duration,
+
+    /// How long a key is not used after being rate limited
+    pub cooldown: Duration,
+
+    state: Mutex<PoolState>,
+}
+
+impl KeyPool {
+    /// Create pool of keys.
+    ///
+    /// # Arguments
+    /// * `rate_limit` - maximum requests per second of each key, or 0 for no limit
+    /// * `cooldown_secs` - seconds which a rate limited key is not used
+    pub fn new(rate_limit: f64, cooldown_secs: u64) -> Self {
+        let min_interval = if rate_limit > 0.0 { Duration::from_secs_f64(1.0 / rate_limit) } else { Duration::ZERO };
+        Self {
+            min_interval,
+            cooldown: Duration::from_secs(cooldown_secs),
+            state: Mutex::new(PoolState::default()),
+        }
+    }
+
+    /// Number of keys in the pool. It is 0 until keys are read on first use.
+    pub fn len(&self) -> usize {
+        self.state.lock().map_or(0, |s| s.keys.as_ref().map_or(0, |k| k.len()))
+    }
+
+    /// Whether or not the pool has no keys.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Take the next key to use, waiting for its rate limit or cool-down if
+    /// needed. Returns index of key to report back with `cool_down()`, and the
+    /// key itself, or `None` if there are no keys but API key is optional.
+    ///
+    /// # Arguments
+    /// * `apikey_env` - name of environment variable holding API key(s)
+    /// * `optional` - whether or not API key is optional
+    pub fn acquire(&self, apikey_env: Option<&str>, optional: bool) -> Result<(usize, Option<String>), String> {
+        loop {
+            let wait;
+            let mut acquired = None;
+            {
+                let mut state = match self.state.lock() {
+                    Ok(res) => res,
+                    Err(_) => return Err("Error API key pool is poisoned".to_owned()),
+                };
+                if state.keys.is_none() {
+                    state.keys = Some(read_keys(apikey_env, optional)?);
+                }
+
+                let next = state.next;
+                let keys = state.keys.as_mut().unwrap();
+                if keys.is_empty() {
+                    return Ok((0, None));
+                }
+
+                let now = Instant::now();
+                let count = keys.len();
+                let ready = (0..count).map(|i| (next + i) % count).find(|&i| match keys[i].cooldown_until {
+                    Some(until) => until <= now,
+                    None => true,
+                });
+
+                match ready {
+                    Some(index) => {
+                        let key = &mut keys[index];
+                        key.cooldown_until = None;
+                        wait = key.next_use.saturating_duration_since(now);
+                        key.next_use = now + wait + self.min_interval;
+                        acquired = Some((index, Some(key.key.clone())));
+                        state.next = (index + 1) % count;
+                    },
+                    None => {
+                        // all keys are cooling down, wait for the earliest one
+                        let earliest = keys.iter().filter_map(|k| k.cooldown_until).min().unwrap_or(now);
+                        wait = earliest.saturating_duration_since(now);
+                    }
+                }
+            }
+
+            std::thread::sleep(wait);
+            if let Some(res) = acquired {
+                return Ok(res);
+            }
+        }
+    }
+
+    /// Put key to cool down after it was rate limited.
+    ///
+    /// # Arguments
+    /// * `index` - index of key as returned from `acquire()`
+    pub fn cool_down(&self, index: usize) {
+        if let Ok(mut state) = self.state.lock() {
+            if let Some(key) = state.keys.as_mut().and_then(|k| k.get_mut(index)) {
+                key.cooldown_until = Some(Instant::now() + self.cooldown);
+            }
+        }
+    }
+}
+
+/// Read comma-separated API keys from environment variable.
+///
+/// # Arguments
+/// * `apikey_env` - name of environment variable holding API key(s)
+/// * `optional` - whether or not API key is optional
+fn read_keys(apikey_env: Option<&str>, optional: bool) -> Result<Vec<KeyState>, String> {
+    let name = match apikey_env {
+        Some(res) => res,
+        None => return Ok(Vec::new()),
+    };
+    // API key of Foundry is shared by backends without their own
+    let value = match std::env::var(name).or_else(|_| std::env::var(crate::foundry::APIKEY_ENV)) {
+        Ok(res) => res,
+        Err(_) if optional => return Ok(Vec::new()),
+        Err(_) => {
+            let err_msg = format!("Error required environment variable '{}', or '{}' to be defined", name, crate::foundry::APIKEY_ENV);
+            return Err(err_msg);
+        }
+    };
+
+    let now = Instant::now();
+    Ok(value.split(',')
+        .map(|k| k.trim())
+        .filter(|k| !k.is_empty())
+        .map(|k| KeyState { key: k.to_owned(), next_use: now, cooldown_until: None })
+        .collect())
+}
+
+/// Whether or not response tells that API key is rate limited.
+///
+/// # Arguments
+/// * `status` - HTTP status code
+/// * `body` - response body
+pub fn is_rate_limited(status: u16, body: &str) -> bool {
+    status == 429 || body.to_lowercase().contains("rate limit reached")
+}
@@ -0,0 +1,257 @@
+//! Directory layout of multi-file source output, and its remappings.
+
+use std::collections::{BTreeSet, HashSet};
+
+/// Whether or not filesystem of platform is case-insensitive by default
+const CASE_INSENSITIVE_FS: bool = cfg!(any(windows, target_os = "macos"));
+
+/// Directory layout to place source files of dependencies in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Layout {
+    /// Keep paths as verified
+    Flat,
+
+    /// Place packages under `node_modules/` e.g. for Hardhat
+    NodeModules,
+
+    /// Place packages under `lib/` e.g. for Foundry
+    Lib,
+
+    /// Place everything under `contracts/` for Ape
+    Ape,
+
+    /// Place everything under `contracts/` for Brownie
+    Brownie,
+}
+
+/// Parse value of `--layout` flag option.
+///
+/// # Arguments
+/// * `value` - value of flag option
+pub fn parse_layout(value: &str) -> Result<Layout, String> {
+    match value.to_lowercase().as_str() {
+        "flat" => Ok(Layout::Flat),
+        "node_modules" | "node-modules" => Ok(Layout::NodeModules),
+        "lib" => Ok(Layout::Lib),
+        "ape" => Ok(Layout::Ape),
+        "brownie" => Ok(Layout::Brownie),
+        _ => Err("Error invalid value for --layout.
+Possible values are 'flat', 'node_modules', 'lib', 'ape', or 'brownie'.".to_owned()),
+    }
+}
+
+/// Get scoped package prefix of path e.g. `@openzeppelin/contracts/` of
+/// `@openzeppelin/contracts/access/Ownable.sol`.
+///
+/// # Arguments
+/// * `path` - path of source file as verified
+pub fn package_prefix(path: &str) -> Option<String> {
+    if !path.starts_with('@') {
+        return None;
+    }
+
+    let mut components = path.splitn(3, '/');
+    let scope = components.next()?;
+    let package = components.next()?;
+    // package itself must be a directory
+    components.next()?;
+    Some(format!("{}/{}/", scope, package))
+}
+
+/// Get directory that package is placed in according to layout.
+///
+/// # Arguments
+/// * `layout` - directory layout
+/// * `prefix` - package prefix e.g. `@openzeppelin/contracts/`
+fn package_dir(layout: Layout, prefix: &str) -> String {
+    match layout {
+        Layout::Flat => prefix.to_owned(),
+        Layout::NodeModules => format!("node_modules/{}", prefix),
+        Layout::Lib => {
+            // e.g. @openzeppelin/contracts/ -> lib/openzeppelin-contracts/
+            let name = prefix.trim_start_matches('@').trim_end_matches('/').replace('/', "-");
+            format!("lib/{}/", name)
+        },
+        Layout::Ape | Layout::Brownie => format!("contracts/{}", prefix),
+    }
+}
+
+/// Get path to place source file at according to layout.
+///
+/// # Arguments
+/// * `layout` - directory layout
+/// * `path` - path of source file as verified
+pub fn place(layout: Layout, path: &str) -> String {
+    match (package_prefix(path), layout) {
+        (Some(prefix), _) => format!("{}{}", package_dir(layout, &prefix), &path[prefix.len()..]),
+        // Python frameworks only compile what is in contracts folder
+        (None, Layout::Ape | Layout::Brownie) if !path.starts_with("contracts/") => format!("contracts/{}", path),
+        (None, _) => path.to_owned(),
+    }
+}
+
+/// Disambiguate placed path colliding with one already taken when compared
+/// case-insensitively e.g. `Token.sol` after `token.sol`, so it doesn't
+/// silently overwrite the other on case-insensitive filesystem. On such
+/// platform, its file stem is suffixed e.g. `token_2.sol`; elsewhere it's
+/// kept as is with a warning that output isn't portable.
+///
+/// # Arguments
+/// * `placed` - path of source file after placed according to layout
+/// * `taken` - lowercased paths taken so far, to be added with returned one
+pub fn disambiguate_case(placed: &str, taken: &mut HashSet<String>) -> String {
+    if taken.insert(placed.to_lowercase()) {
+        return placed.to_owned();
+    }
+    if !CASE_INSENSITIVE_FS {
+        eprintln!("Warning: '{}' differs only by case from another file; output can't be checked out on case-insensitive filesystems e.g. of macOS, or Windows", placed);
+        return placed.to_owned();
+    }
+
+    let (dir, name) = match placed.rsplit_once('/') {
+        Some((dir, name)) => (format!("{}/", dir), name),
+        None => (String::new(), placed),
+    };
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) => (stem, format!(".{}", ext)),
+        None => (name, String::new()),
+    };
+    let mut n = 2;
+    loop {
+        let renamed = format!("{}{}_{}{}", dir, stem, n, ext);
+        if taken.insert(renamed.to_lowercase()) {
+            eprintln!("Warning: '{}' collides with another file on case-insensitive filesystem; written as '{}', imports of it need to be updated", placed, renamed);
+            return renamed;
+        }
+        n += 1;
+    }
+}
+
+/// Get common top directory of project's own source files (not packages)
+/// e.g. `contracts`, or `.` if there is none.
+///
+/// # Arguments
+/// * `placed_paths` - paths of source files after placed according to layout
+fn sources_dir(placed_paths: &[String]) -> String {
+    let mut dirs = placed_paths.iter()
+        .filter(|p| !p.starts_with("lib/") && !p.starts_with("node_modules/") && !p.starts_with('@'))
+        .map(|p| p.split('/').next().unwrap_or("").to_owned());
+
+    match dirs.next() {
+        Some(first) if dirs.all(|d| d == first) && placed_paths.iter().any(|p| p.starts_with(&format!("{}/", first))) => first,
+        _ => ".".to_owned(),
+    }
+}
+
+/// Get toolchain files pinning compiler version and settings used for
+/// verification, for Foundry (`lib`), Hardhat (`node_modules`), Ape, or
+/// Brownie layout. Returned as list of pairs of filename and its content.
+///
+/// # Arguments
+/// * `layout` - directory layout
+/// * `meta` - verification metadata returned by `getsourcecode` endpoint
+/// * `settings` - compiler settings used for verification
+/// * `placed_paths` - paths of source files after placed according to layout
+/// * `remappings` - remappings of packages, which Python frameworks take in
+///   their config instead of `remappings.txt`
+pub fn toolchain_files(layout: Layout, meta: &serde_json::Value, settings: &serde_json::Value, placed_paths: &[String], remappings: &[String]) -> Vec<(String, String)> {
+    let version = crate::solc::plain_version(meta["CompilerVersion"].as_str().unwrap_or(""));
+    let optimizer = settings["optimizer"]["enabled"].as_bool().unwrap_or(false);
+    let runs = settings["optimizer"]["runs"].as_u64().unwrap_or(200);
+    let evm_version = settings["evmVersion"].as_str();
+    let via_ir = settings["viaIR"].as_bool().unwrap_or(false);
+    let src = sources_dir(placed_paths);
+
+    let mut files = vec![(".solc-version".to_owned(), format!("{}\n", version))];
+    match layout {
+        Layout::Flat => return Vec::new(),
+        Layout::Lib => {
+            let mut config = String::from("[profile.default]\n");
+            config.push_str(&format!("src = \"{}\"\n", src));
+            config.push_str("libs = [\"lib\"]\n");
+            config.push_str(&format!("solc = \"{}\"\n", version));
+            config.push_str(&format!("optimizer = {}\n", optimizer));
+            config.push_str(&format!("optimizer_runs = {}\n", runs));
+            if let Some(evm_version) = evm_version {
+                config.push_str(&format!("evm_version = \"{}\"\n", evm_version));
+            }
+            if via_ir {
+                config.push_str("via_ir = true\n");
+            }
+            files.push(("foundry.toml".to_owned(), config));
+        },
+        Layout::NodeModules => {
+            let mut config = String::from("module.exports = {\n");
+            config.push_str("  solidity: {\n");
+            config.push_str(&format!("    version: \"{}\",\n", version));
+            config.push_str("    settings: {\n");
+            config.push_str(&format!("      optimizer: {{ enabled: {}, runs: {} }},\n", optimizer, runs));
+            if let Some(evm_version) = evm_version {
+                config.push_str(&format!("      evmVersion: \"{}\",\n", evm_version));
+            }
+            if via_ir {
+                config.push_str("      viaIR: true,\n");
+            }
+            config.push_str("    },\n");
+            config.push_str("  },\n");
+            let sources = if src == "." { "./".to_owned() } else { format!("./{}", src) };
+            config.push_str(&format!("  paths: {{ sources: \"{}\" }},\n", sources));
+            config.push_str("};\n");
+            files.push(("hardhat.config.js".to_owned(), config));
+        },
+        Layout::Ape => {
+            let mut config = format!("name: {}\n", meta["ContractName"].as_str().unwrap_or("contract"));
+            config.push_str("contracts_folder: contracts\n");
+            config.push_str("solidity:\n");
+            config.push_str(&format!("  version: \"{}\"\n", version));
+            config.push_str(&format!("  optimize: {}\n", optimizer));
+            config.push_str(&format!("  optimization_runs: {}\n", runs));
+            if let Some(evm_version) = evm_version {
+                config.push_str(&format!("  evm_version: \"{}\"\n", evm_version));
+            }
+            if via_ir {
+                config.push_str("  via_ir: true\n");
+            }
+            if !remappings.is_empty() {
+                config.push_str("  import_remapping:\n");
+                for remapping in remappings.iter() {
+                    config.push_str(&format!("    - \"{}\"\n", remapping));
+                }
+            }
+            files.push(("ape-config.yaml".to_owned(), config));
+        },
+        Layout::Brownie => {
+            let mut config = String::from("compiler:\n");
+            if let Some(evm_version) = evm_version {
+                config.push_str(&format!("  evm_version: \"{}\"\n", evm_version));
+            }
+            config.push_str("  solc:\n");
+            config.push_str(&format!("    version: \"{}\"\n", version));
+            config.push_str("    optimizer:\n");
+            config.push_str(&format!("      enabled: {}\n", optimizer));
+            config.push_str(&format!("      runs: {}\n", runs));
+            if via_ir {
+                config.push_str("    viaIR: true\n");
+            }
+            if !remappings.is_empty() {
+                config.push_str("    remappings:\n");
+                for remapping in remappings.iter() {
+                    config.push_str(&format!("      - \"{}\"\n", remapping));
+                }
+            }
+            files.push(("brownie-config.yaml".to_owned(), config));
+        },
+    }
+    files
+}
+
+/// Get remappings for packages found in source paths, so imports resolve
+/// after files are placed according to layout.
+///
+/// # Arguments
+/// * `layout` - directory layout
+/// * `paths` - paths of source files as verified
+pub fn remappings(layout: Layout, paths: &[&str]) -> Vec<String> {
+    let prefixes: BTreeSet<String> = paths.iter().filter_map(|p| package_prefix(p)).collect();
+    prefixes.iter().map(|prefix| format!("{}={}", prefix, package_dir(layout, prefix))).collect()
+}
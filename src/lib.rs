@@ -0,0 +1,330 @@
+//! Get smart contract code and its ABI from API platforms of EVM-based chains,
+//! and tools built on top of them.
+//!
+//! Commands of `tracpls` cli are built on this library, and it can be used by
+//! other programs as well.
+
+pub mod abi;
+pub mod addresses;
+pub mod advisory;
+pub mod analyze;
+pub mod archive;
+pub mod artifact;
+pub mod audit_meta;
+pub mod ast;
+pub mod backend;
+pub mod batch;
+pub mod breaker;
+pub mod build;
+pub mod bytecode;
+pub mod bytecode_diff;
+pub mod cache;
+pub mod call_trace;
+pub mod chains;
+pub mod children;
+pub mod clipwatch;
+pub mod decode;
+pub mod decompile;
+pub mod deps;
+pub mod diagram;
+pub mod diff_matrix;
+pub mod dupes;
+pub mod display;
+pub mod enrich;
+pub mod explain;
+pub mod explorer;
+pub mod export;
+pub mod fetch;
+pub mod formatter;
+pub mod foundry;
+pub mod gas;
+pub mod graph;
+pub mod history;
+pub mod history_diff;
+pub mod holders;
+pub mod http_log;
+pub mod immutables;
+pub mod import_csv;
+pub mod interfaces;
+pub mod internal_txs;
+pub mod keypool;
+pub mod layout;
+pub mod license;
+pub mod lock;
+pub mod manifest;
+pub mod mock_server;
+pub mod multicall;
+pub mod owners;
+pub mod parquet;
+pub mod pc;
+pub mod perf;
+pub mod progress;
+pub mod proxy;
+pub mod quota;
+pub mod repl;
+pub mod risk;
+pub mod safe_path;
+pub mod scaffold;
+pub mod schema;
+// tantivy indexing cache for search doesn't build for wasm32
+#[cfg(not(target_arch = "wasm32"))]
+pub mod search;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod search_index;
+pub mod selectors;
+pub mod settings_diff;
+pub mod simulate;
+pub mod snapshot;
+pub mod solc;
+pub mod solidity;
+pub mod source;
+pub mod standard_json;
+pub mod status;
+pub mod storage_check;
+pub mod sync;
+pub mod trace;
+pub mod trace_deps;
+pub mod transform_log;
+pub mod transport;
+pub mod upgrade_check;
+pub mod watch_deployer;
+pub mod workspace;
+
+use clap::Args;
+use std::path::PathBuf;
+
+/// Initialize library by registering built-in chains, ones from registry of
+/// chains, and from manifest of endpoints. It has to be called before
+/// selecting any chain.
+pub fn init() -> Result<(), String> {
+    backend::register_builtin();
+    chains::register_registry()?;
+    backend::register_from_manifest()
+}
+
+/// Fetch verified source code of contract as list of files.
+///
+/// # Arguments
+/// * `address` - contract address
+/// * `chain` - name of chain e.g. `bsc`
+pub fn fetch_source(address: &str, chain: &str) -> Result<source::ContractSource, String> {
+    let chain = parse_chain(chain)?;
+    source::fetch_sources(chain, address)
+}
+
+/// Fetch contract ABI as JSON text.
+///
+/// # Arguments
+/// * `address` - contract address
+/// * `chain` - name of chain e.g. `bsc`
+/// * `pretty` - whether or not to pretty print
+pub fn fetch_abi(address: &str, chain: &str, pretty: bool) -> Result<String, String> {
+    let chain = parse_chain(chain)?;
+    explorer::get_abi(chain, address, pretty)
+}
+
+/// Send request to API platform of chain through asynchronous transport, and
+/// return value of `result` field of its response.
+///
+/// # Arguments
+/// * `transport` - transport
+/// * `chain` - name of chain e.g. `bsc`
+/// * `params` - query parameters as pairs of key and value
+/// * `apikey` - API key
+async fn query_with<T: transport::AsyncTransport>(transport: &T, chain: &str, params: &[(&str, &str)], apikey: Option<&str>) -> Result<serde_json::Value, String> {
+    let request = parse_chain(chain)?.backend.request(params, apikey);
+    let response = match transport.send(request).await {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error sending request to API platform; err={}", e);
+            return Err(err_msg);
+        }
+    };
+    explorer::parse_body(&response.body)
+}
+
+/// Same as `fetch_source()` but sending request through asynchronous
+/// transport e.g. `fetch()` of the host on wasm32, where API key can't be
+/// taken from environment variable.
+///
+/// # Arguments
+/// * `transport` - transport
+/// * `address` - contract address
+/// * `chain` - name of chain e.g. `bsc`
+/// * `apikey` - API key
+pub async fn fetch_source_with<T: transport::AsyncTransport>(transport: &T, address: &str, chain: &str, apikey: Option<&str>) -> Result<source::ContractSource, String> {
+    let result = query_with(transport, chain, &explorer::source_meta_params(address), apikey).await?;
+    source::sources_from_meta(address, &explorer::source_meta_of(address, result)?)
+}
+
+/// Same as `fetch_abi()` but sending request through asynchronous transport
+/// e.g. `fetch()` of the host on wasm32, where API key can't be taken from
+/// environment variable.
+///
+/// # Arguments
+/// * `transport` - transport
+/// * `address` - contract address
+/// * `chain` - name of chain e.g. `bsc`
+/// * `apikey` - API key
+/// * `pretty` - whether or not to pretty print
+pub async fn fetch_abi_with<T: transport::AsyncTransport>(transport: &T, address: &str, chain: &str, apikey: Option<&str>, pretty: bool) -> Result<String, String> {
+    let result = query_with(transport, chain, &explorer::abi_params(address), apikey).await?;
+    let abi = explorer::abi_of(address, result)?;
+    if !pretty {
+        return Ok(abi);
+    }
+    explorer::pretty_abi(address, &abi)
+}
+
+/// Arguments to target an address on a chain, shared by commands.
+#[derive(Debug, Args)]
+pub struct TargetArgs {
+    /// Target contract address
+    #[clap(long="address", short='a', required=true)]
+    pub address: String,
+
+    /// Which chain to work with, by name e.g. 'bsc', 'ethereum', 'polygon',
+    /// and 'arbitrum', or chain id e.g. '56'. See 'tracpls chains' for all.
+    #[clap(long="chain", short='c', required=true, multiple_values=false)]
+    pub chain: String,
+}
+
+/// Clean CR/LF as necessary as per platform running the application.
+///
+/// # Arguments
+/// * `text` - text to be cleaned if necessary
+///
+/// # Returned
+/// New instance of `String`.
+pub fn clean_crlf(text: &str) -> String {
+    let os = std::env::consts::OS;
+
+    // actually this would depend on the upstream source file itself
+    // for which platform developers edit file on, but we can clean it
+    // in (all) cases.
+
+    // on Linux/Unix uses only line feed (\n)
+    if os == "linux" {
+        str::replace(&str::replace(text, "\r\n", "\n"), "\r", "\n")
+    }
+    // on macOS, it uses only carriage return (\r)
+    else if os == "macos" {
+        str::replace(&str::replace(text, "\r\n", "\r"), "\n", "\r")
+    }
+    // otherwise don't clean anything
+    // e.g. Windows uses both CR/LF
+    else {
+        text.to_owned()
+    }
+}
+
+/// Combine two path components together and return str version of it.
+///
+/// # Arguments
+/// * `path_a` - first path component
+/// * `path_b` - second path component
+pub fn combine_two_path_components(path_a: &str, path_b: &str) -> Result<String, String> {
+    let mut path = PathBuf::from(path_a);
+    path.push(path_b);
+
+    match path.as_path().to_str() {
+        Some(res) => Ok(res.to_owned()),
+        None => {
+            let err_msg = format!("Error converting PathBuf to str from result of concatenation of {} and {}", path_a, path_b);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Create intermediate directories.
+/// It internally handles whether the path is file, or directory. So supplying
+/// the actual filepath here is fine.
+///
+/// # Arguments
+/// * `path` - path to create intermerdiate directories
+pub fn create_intermediate_dirs(path: &str) -> Result<(), String> {
+    let _timer = perf::time(perf::Phase::Write);
+    let mut ppath = PathBuf::from(path);
+    // pop the last component out to get only directory path
+    if ppath.file_name().is_some() {
+        ppath.pop();
+    }
+
+    // get path string
+    let ppath_str = match ppath.as_path().to_str() {
+        Some(res) => res,
+        None => {
+            let err_msg = format!("Error getting path string from PathBuf ('{}')", path);
+            return Err(err_msg);
+        }
+    };
+
+    // create all directories leading up to what we will
+    match std::fs::create_dir_all(safe_path::fs_path(ppath_str)) {
+        Ok(_) => (),
+        Err(e) => {
+            let err_msg = format!("Error creating intermediate directories; err={}", e);
+            return Err(err_msg);
+        }
+    }
+
+    Ok(())
+}
+
+/// Write content to file.
+///
+/// # Arguments
+/// * `filepath` - filepath to write file to, ensure path includes the filename
+/// * `content` - content of file
+pub fn write_file(filepath: &str, content: &str) -> Result<(), String> {
+    let _timer = perf::time(perf::Phase::Write);
+    match std::fs::write(safe_path::fs_path(filepath), content) {
+        Ok(_) => (),
+        Err(e) => {
+            let err_msg = format!("Error writing file at '{}'; err={}", filepath, e);
+            return Err(err_msg);
+        }
+    }
+
+    Ok(())
+}
+
+/// Write content to file at the path relative to output directory, then print
+/// its path unless silenced.
+///
+/// # Arguments
+/// * `out_dir` - output directory
+/// * `relative_path` - path of file relative to output directory
+/// * `content` - content of file
+/// * `silence` - whether or not to print the written filepath
+pub fn write_out_file(out_dir: &str, relative_path: &str, content: &str, silence: bool) -> Result<(), String> {
+    let write_filepath = combine_two_path_components(out_dir, relative_path)?;
+    create_intermediate_dirs(&write_filepath)?;
+    write_file(&write_filepath, content)?;
+    if !progress::silenced(silence) {
+        println!("{}", &write_filepath);
+    }
+    Ok(())
+}
+
+/// Parse value of chain flag option into chain registered with its explorer
+/// backend.
+///
+/// # Arguments
+/// * `value` - value of chain flag option as name, or chain alias of Foundry
+///   e.g. `mainnet`, case-insensitive, or chain id
+pub fn parse_chain(value: &str) -> Result<&'static backend::Chain, String> {
+    let found = match value.parse::<u64>() {
+        Ok(chain_id) => backend::find_by_id(chain_id),
+        Err(_) => backend::find(value).or_else(|| foundry::chain_id_of_alias(value).and_then(backend::find_by_id)),
+    };
+    match found {
+        Some(res) => Ok(res),
+        None => {
+            // registry may hold hundreds of chains
+            let names: Vec<String> = backend::chain_names().iter().take(3).map(|n| format!("'{}'", n)).collect();
+            let err_msg = format!("Error invalid value for --chain.\nPossible values are names listed by 'tracpls chains' e.g. {}, or their chain ids.", names.join(", "));
+            Err(err_msg)
+        }
+    }
+}
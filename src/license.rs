@@ -0,0 +1,103 @@
+//! Declared license of verified contract, and policy to allow or deny it.
+
+use serde_json::Value;
+
+/// Convert license name as shown by API platform into SPDX identifier.
+/// `None` is returned if no license is declared.
+///
+/// # Arguments
+/// * `name` - value of `LicenseType` field of verification metadata
+fn explorer_license_to_spdx(name: &str) -> Option<String> {
+    let spdx = match name.trim() {
+        "" | "None" => return None,
+        "GNU GPLv2" => "GPL-2.0",
+        "GNU GPLv3" => "GPL-3.0",
+        "GNU LGPLv2.1" => "LGPL-2.1",
+        "GNU LGPLv3" => "LGPL-3.0",
+        "GNU AGPLv3" => "AGPL-3.0",
+        "BSL 1.1" => "BUSL-1.1",
+        other => other,
+    };
+    Some(spdx.to_owned())
+}
+
+/// Get declared license of contract as SPDX identifier.
+///
+/// License selected at verification is preferred, then the first
+/// `SPDX-License-Identifier` found in source code. `UNLICENSED` is returned if
+/// neither declares one.
+///
+/// # Arguments
+/// * `meta` - verification metadata returned by `getsourcecode` endpoint
+pub fn declared_license(meta: &Value) -> String {
+    if let Some(spdx) = explorer_license_to_spdx(meta["LicenseType"].as_str().unwrap_or("")) {
+        return spdx;
+    }
+
+    let source_code = meta["SourceCode"].as_str().unwrap_or("");
+    if let Some(pos) = source_code.find("SPDX-License-Identifier:") {
+        let rest = &source_code[pos + "SPDX-License-Identifier:".len()..];
+        // source code of multi-file submission is JSON-escaped text
+        let id: String = rest.trim_start().chars().take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '.' || *c == '+').collect();
+        if !id.is_empty() {
+            return id;
+        }
+    }
+
+    "UNLICENSED".to_owned()
+}
+
+/// Policy of licenses to allow, or deny.
+#[derive(Debug, Clone, Default)]
+pub struct LicensePolicy {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+/// Whether license matches SPDX identifier in policy. Variants e.g.
+/// `GPL-3.0-or-later` match `GPL-3.0`.
+///
+/// # Arguments
+/// * `license` - declared license
+/// * `id` - SPDX identifier in policy
+fn matches(license: &str, id: &str) -> bool {
+    license.eq_ignore_ascii_case(id) || license.to_lowercase().starts_with(&format!("{}-", id.to_lowercase()))
+}
+
+impl LicensePolicy {
+    /// Create policy from comma-separated lists of SPDX identifiers.
+    ///
+    /// # Arguments
+    /// * `allow` - licenses to allow, any other license is rejected
+    /// * `deny` - licenses to reject
+    pub fn new(allow: Option<&String>, deny: Option<&String>) -> Self {
+        let split = |list: Option<&String>| -> Vec<String> {
+            list.map_or(Vec::new(), |l| l.split(',').map(|id| id.trim().to_owned()).filter(|id| !id.is_empty()).collect())
+        };
+        Self {
+            allow: split(allow),
+            deny: split(deny),
+        }
+    }
+
+    /// Whether there is nothing to check.
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Check license against policy. Reason of rejection is returned as `Err`.
+    ///
+    /// # Arguments
+    /// * `license` - declared license
+    pub fn check(&self, license: &str) -> Result<(), String> {
+        if self.deny.iter().any(|id| matches(license, id)) {
+            let reason = format!("license {} is denied", license);
+            return Err(reason);
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|id| matches(license, id)) {
+            let reason = format!("license {} is not allowed", license);
+            return Err(reason);
+        }
+        Ok(())
+    }
+}
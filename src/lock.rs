@@ -0,0 +1,238 @@
+//! Advisory locking of directories shared between tracpls processes e.g.
+//! parallel CI jobs writing into the same output or fixture directory, so
+//! their manifests and fixtures don't get corrupted.
+//!
+//! Lock is an OS file lock (`flock()` on Unix, `LockFileEx()` on Windows) of
+//! `.tracpls.lock` file inside the directory, so it's released by the OS when
+//! its owner exits, even when crashed, and lock is never left behind. The file
+//! holds pid of its last owner only for reporting; it's never removed, as
+//! other processes may be waiting on it.
+
+use std::fs::{File, TryLockError};
+use std::io::{Seek, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::thread::ThreadId;
+use std::time::{Duration, Instant};
+
+/// Filename of lock inside locked directory
+pub const LOCK_FILENAME: &str = ".tracpls.lock";
+
+/// Default seconds to wait for lock held by another process
+pub const DEFAULT_WAIT_SECS: u64 = 30;
+
+/// Seconds to wait for lock held by another process
+static WAIT_SECS: Mutex<u64> = Mutex::new(DEFAULT_WAIT_SECS);
+
+/// Directories locked by this process, by canonical path
+static HELD: Mutex<Vec<Held>> = Mutex::new(Vec::new());
+
+/// Directory locked by this process.
+#[derive(Debug)]
+struct Held {
+    dir: PathBuf,
+
+    /// Thread holding the lock, which may acquire it again
+    thread: ThreadId,
+
+    /// Number of `DirLock` of the directory alive
+    count: usize,
+
+    /// Lock file locked, unlocked when closed
+    file: File,
+}
+
+/// Set how long to wait for lock held by another process before giving up.
+///
+/// # Arguments
+/// * `secs` - seconds to wait, 0 to give up immediately
+pub fn set_wait_secs(secs: u64) {
+    if let Ok(mut wait) = WAIT_SECS.lock() {
+        *wait = secs;
+    }
+}
+
+/// Lock of directory held until dropped. Acquiring lock of directory already
+/// held by the same thread e.g. `--record` into `--out-dir` shares it instead
+/// of waiting for itself; the lock is released when the last one is dropped.
+#[derive(Debug)]
+pub struct DirLock {
+    /// Canonical path of locked directory
+    dir: PathBuf,
+}
+
+/// Whether or not process of pid runs, or `None` if it can't be told on this
+/// platform. Only known where `/proc` exists e.g. Linux.
+///
+/// # Arguments
+/// * `pid` - process id
+pub fn process_alive(pid: u32) -> Option<bool> {
+    if !std::path::Path::new("/proc/self").exists() {
+        return None;
+    }
+    Some(std::path::Path::new(&format!("/proc/{}", pid)).exists())
+}
+
+impl DirLock {
+    /// Lock directory, creating it if needed. Wait for lock held by another
+    /// process, or another thread of this one up to the time set by
+    /// `set_wait_secs()`.
+    ///
+    /// # Arguments
+    /// * `dir` - directory to lock
+    pub fn acquire(dir: &str) -> Result<Self, String> {
+        let wait = Duration::from_secs(WAIT_SECS.lock().map_or(DEFAULT_WAIT_SECS, |w| *w));
+        Self::acquire_within(dir, wait)
+    }
+
+    /// Lock directory, creating it if needed. Wait for lock held by another
+    /// process, or another thread of this one up to `wait`.
+    ///
+    /// # Arguments
+    /// * `dir` - directory to lock
+    /// * `wait` - how long to wait before giving up
+    pub fn acquire_within(dir: &str, wait: Duration) -> Result<Self, String> {
+        let path = crate::combine_two_path_components(dir, LOCK_FILENAME)?;
+        crate::create_intermediate_dirs(&path)?;
+        let canonical = match std::fs::canonicalize(crate::safe_path::fs_path(dir)) {
+            Ok(res) => res,
+            Err(e) => {
+                let err_msg = format!("Error resolving path of '{}'; err={}", dir, e);
+                return Err(err_msg);
+            }
+        };
+        let file = match std::fs::OpenOptions::new().read(true).write(true).create(true).truncate(false).open(crate::safe_path::fs_path(&path)) {
+            Ok(res) => res,
+            Err(e) => {
+                let err_msg = format!("Error creating lock at '{}'; err={}", path, e);
+                return Err(err_msg);
+            }
+        };
+
+        let deadline = Instant::now() + wait;
+        loop {
+            // locking, and registering it is done at once so another thread
+            // of this process can't tell it's held by itself in between
+            let mut held = match HELD.lock() {
+                Ok(res) => res,
+                Err(_) => return Err("Error lock registry is poisoned".to_owned()),
+            };
+            if let Some(h) = held.iter_mut().find(|h| h.dir == canonical && h.thread == std::thread::current().id()) {
+                h.count += 1;
+                return Ok(Self { dir: canonical });
+            }
+
+            match file.try_lock() {
+                Ok(()) => {
+                    // owner is only informative, so failing to write it is fine
+                    let _ = file.set_len(0);
+                    let _ = (&file).rewind();
+                    let _ = write!(&file, "{}", std::process::id());
+                    held.push(Held { dir: canonical.clone(), thread: std::thread::current().id(), count: 1, file });
+                    return Ok(Self { dir: canonical });
+                },
+                Err(TryLockError::WouldBlock) => {
+                    drop(held);
+                    if Instant::now() >= deadline {
+                        let owner = std::fs::read_to_string(crate::safe_path::fs_path(&path))
+                            .ok()
+                            .and_then(|c| c.trim().parse::<u32>().ok())
+                            .map_or(String::new(), |pid| format!(" (pid {})", pid));
+                        let err_msg = format!("Error '{}' is locked by another tracpls process{}; waited {} seconds, see --wait-lock", dir, owner, wait.as_secs());
+                        return Err(err_msg);
+                    }
+                    std::thread::sleep(Duration::from_millis(50));
+                },
+                Err(TryLockError::Error(e)) => {
+                    let err_msg = format!("Error locking '{}'; err={}", path, e);
+                    return Err(err_msg);
+                }
+            }
+        }
+    }
+}
+
+impl Drop for DirLock {
+    fn drop(&mut self) {
+        if let Ok(mut held) = HELD.lock() {
+            if let Some(index) = held.iter().position(|h| h.dir == self.dir) {
+                held[index].count -= 1;
+                if held[index].count > 0 {
+                    return;
+                }
+                // unlocked as closed; file is left for processes waiting on it
+                let released = held.remove(index);
+                let _ = released.file.unlock();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::TempWorkspace;
+
+    #[test]
+    fn dir_lock_is_reentrant_on_the_same_thread() {
+        let workspace = TempWorkspace::new("test-lock").unwrap();
+        let dir = workspace.path_str();
+        let outer = DirLock::acquire_within(dir, Duration::ZERO).unwrap();
+        let inner = DirLock::acquire_within(dir, Duration::ZERO).unwrap();
+        drop(outer);
+
+        // still held by inner one
+        let other = std::thread::scope(|s| s.spawn(|| DirLock::acquire_within(dir, Duration::ZERO).is_ok()).join().unwrap());
+        assert!(!other);
+        drop(inner);
+        let other = std::thread::scope(|s| s.spawn(|| DirLock::acquire_within(dir, Duration::ZERO).is_ok()).join().unwrap());
+        assert!(other);
+    }
+
+    #[test]
+    fn dir_lock_waits_for_other_thread() {
+        let workspace = TempWorkspace::new("test-lock").unwrap();
+        let dir = workspace.path_str();
+        let lock = DirLock::acquire_within(dir, Duration::ZERO).unwrap();
+        std::thread::scope(|s| {
+            let err = s.spawn(|| DirLock::acquire_within(dir, Duration::ZERO).unwrap_err()).join().unwrap();
+            assert!(err.contains("is locked by another tracpls process"), "{}", err);
+            assert!(err.contains(&format!("(pid {})", std::process::id())), "{}", err);
+
+            let waiter = s.spawn(|| {
+                let started = Instant::now();
+                let lock = DirLock::acquire_within(dir, Duration::from_secs(10));
+                (lock.is_ok(), started.elapsed())
+            });
+            std::thread::sleep(Duration::from_millis(300));
+            drop(lock);
+            let (acquired, waited) = waiter.join().unwrap();
+            assert!(acquired);
+            assert!(waited >= Duration::from_millis(250), "{:?}", waited);
+        });
+    }
+
+    #[test]
+    fn dir_lock_takes_over_lock_left_behind() {
+        let workspace = TempWorkspace::new("test-lock").unwrap();
+        let dir = workspace.path_str();
+        let path = workspace.path().join(LOCK_FILENAME);
+
+        // left empty, with garbage, or with pid of process which no longer
+        // runs by crashed owners; only whether it's locked matters
+        for content in ["", "garbage", "4294967295"] {
+            std::fs::write(&path, content).unwrap();
+            let lock = DirLock::acquire_within(dir, Duration::ZERO).unwrap();
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), std::process::id().to_string());
+            drop(lock);
+        }
+
+        // lock released by OS as its owner exits, as if its handle is closed
+        let owner = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+        owner.lock().unwrap();
+        assert!(DirLock::acquire_within(dir, Duration::ZERO).is_err());
+        drop(owner);
+        assert!(DirLock::acquire_within(dir, Duration::ZERO).is_ok());
+        assert!(path.exists());
+    }
+}
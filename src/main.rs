@@ -1,23 +1,47 @@
-use ::evmscan::evmscan;
-use ::evmscan::environ::Context;
-use ::evmscan::prelude::*;
-use clap::Parser;
-use std::path::PathBuf;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use serde_json::json;
+use tracpls::*;
 
 #[derive(Debug, Parser)]
 #[clap(author="Wasin Thonkaew (wasin@wasin.io)")]
 #[clap(name="tracpls")]
 #[clap(about="cli tool to get smart contract code and its ABI for ease of viewing on terminal")]
+#[clap(subcommand_negates_reqs=true)]
 struct CommandlineArgs {
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+
     /// Target contract address to get its smart contract code or ABI from
     #[clap(long="address", short='a', required=true)]
-    pub address: String,
+    pub address: Option<String>,
 
     /// Make sure to clean CR/LF character codes to make it suitable to view
     /// the content on the platform running the application.
     #[clap(long="no-clean-crlf", multiple_values=false, default_missing_value="true", takes_value=false)]
     pub no_clean_crlf: bool,
 
+    /// Format Solidity source code before output; re-indent by nesting with
+    /// spaces, keep at most one blank line in a row, and strip trailing
+    /// whitespace as 'forge fmt' does by default.
+    #[clap(long="fmt", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub fmt: bool,
+
+    /// Replace tabs in indentation of Solidity source code with spaces
+    #[clap(long="tabs-to-spaces", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub tabs_to_spaces: bool,
+
+    /// Number of spaces of each indentation level for --fmt, and
+    /// --tabs-to-spaces. Default is 4.
+    #[clap(long="indent-width", required=false)]
+    pub indent_width: Option<usize>,
+
+    /// Canonicalize output so that independently fetched copies of the same
+    /// code diff empty; LF newlines, no trailing whitespace, JSON pretty
+    /// printed with sorted keys, and Solidity source code formatted as with
+    /// --fmt.
+    #[clap(long="canonical", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub canonical: bool,
+
     /// Get only contract ABI
     #[clap(long="abi-only", multiple_values=false, default_missing_value="true", takes_value=false)]
     pub abi_only: bool,
@@ -33,302 +57,734 @@ struct CommandlineArgs {
     #[clap(long="out-dir", required=false)]
     pub out_dir_path: Option<String>,
 
+    /// Directory layout to place files of packages e.g. '@openzeppelin/contracts/'
+    /// in. Possible values are 'flat' (as verified), 'node_modules', 'lib',
+    /// 'ape', and 'brownie'. The last two place everything under 'contracts/'.
+    /// A 'remappings.txt' is also written when such packages are found.
+    /// It can only be used if --out-dir or --archive exists.
+    #[clap(long="layout", required=false, default_value="flat")]
+    pub layout: String,
+
+    /// Also write compiler artifact of the contract with ABI, bytecode, and
+    /// deployed bytecode taken from chain, for tooling expecting one.
+    /// Possible values are 'hardhat' ('artifacts/<ContractName>.json'), and
+    /// 'truffle' ('build/contracts/<ContractName>.json').
+    /// It can only be used if --out-dir or --archive exists.
+    #[clap(long="artifact-format", required=false)]
+    pub artifact_format: Option<String>,
+
+    /// Also write result object of the contract exactly as served by API
+    /// platform into 'explorer-response.json', untouched, for debugging odd
+    /// verifications. It can only be used if --out-dir or --archive exists.
+    #[clap(long="raw-response", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub raw_response: bool,
+
+    /// Output only files declaring interfaces, and abstract contracts along
+    /// with files they import, plus interface of the contract generated from
+    /// its ABI as 'I<ContractName>.sol', for projects which only call it.
+    /// It can't be used with --abi-only, or --artifact-format.
+    #[clap(long="interfaces-only", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub interfaces_only: bool,
+
+    /// Drop source files not reachable from the contract through import
+    /// directives e.g. rest of a monorepo verified along, for much smaller
+    /// output. It can't be used with --abi-only.
+    #[clap(long="prune-unreachable", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub prune_unreachable: bool,
+
+    /// Contract to treat as primary instead of the verified one, for order
+    /// of files, --prune-unreachable, and --interfaces-only. Name, or fully
+    /// qualified name e.g. 'contracts/Vault.sol:VaultV2' if the name is
+    /// declared in more than one file. It can't be used with --abi-only.
+    #[clap(long="contract", required=false)]
+    pub contract: Option<String>,
+
+    /// Order of source files of multi-file submission both printed, and
+    /// written. Possible values are 'path' (sorted by path), and 'deps' (files
+    /// imported by the primary contract first). File of the primary contract
+    /// always comes first.
+    #[clap(long="order", required=false, default_value="path")]
+    pub order: String,
+
+    /// Archive path to write all files into, as would be written with --out-dir.
+    /// It is a zip archive if path ends with '.zip', otherwise a tar archive.
+    /// Entries are sorted by path with fixed permissions and timestamp taken
+    /// from SOURCE_DATE_EPOCH (or unix epoch if not defined), so the same
+    /// contract always gives byte-identical archive.
+    #[clap(long="archive", required=false)]
+    pub archive_path: Option<String>,
+
+    /// Write files into a new dated subdirectory of --out-dir e.g.
+    /// '2024-06-01T12-30Z/' instead of overwriting the previous fetch, and
+    /// point 'latest' in --out-dir to it.
+    /// It can only be used if --out-dir exists.
+    #[clap(long="snapshot", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub snapshot: bool,
+
+    /// Also fetch verified source code of contracts the target contract
+    /// references e.g. immutable addresses, constructor arguments, and
+    /// addresses in source code, into 'deps/<address>/' of --out-dir.
+    #[clap(long="recursive", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub recursive: bool,
+
+    /// How many levels of references to follow with --recursive
+    #[clap(long="depth", required=false)]
+    pub depth: Option<u32>,
+
     /// Whether or not to print meta information during execution.
     #[clap(long="silence", short='s', multiple_values=false, default_missing_value="true", takes_value=false)]
     pub silence: bool,
 
-    /// Which chain to work with.
-    /// Possible values are 'bsc', 'ethereum', and 'polygon'.
-    #[clap(long="chain", short='c', required=true, multiple_values=false)]
-    pub chain: String,
+    /// Which chain to work with, by name e.g. 'bsc', 'ethereum', 'polygon',
+    /// and 'arbitrum', or chain id e.g. '56'. See 'tracpls chains' for all.
+    #[clap(long="chain", short='c', required_unless_present="chain-id", multiple_values=false)]
+    pub chain: Option<String>,
+
+    /// Which chain to work with by its chain id e.g. '42161', as an
+    /// alternative to --chain.
+    #[clap(long="chain-id", required=false, conflicts_with="chain")]
+    pub chain_id: Option<u64>,
+
+    /// Directory to record every response of API platform into as fixtures,
+    /// to be replayed later with --replay.
+    #[clap(long="record", required=false, global=true, conflicts_with="replay-dir")]
+    pub record_dir: Option<String>,
+
+    /// Directory of fixtures recorded with --record to replay responses of
+    /// API platform from, instead of sending requests. No API key is needed.
+    #[clap(long="replay", required=false, global=true)]
+    pub replay_dir: Option<String>,
+
+    /// Directory to cache verification metadata of verified contracts in, so
+    /// API platform is asked for each contract only once.
+    #[clap(long="cache-dir", required=false, global=true)]
+    pub cache_dir: Option<String>,
+
+    /// Base url of remote cache shared over HTTP which local cache reads
+    /// through, and writes back to with bearer token of environment variable
+    /// TRACPLS_REMOTE_CACHE_TOKEN.
+    #[clap(long="remote-cache", required=false, global=true, requires="cache-dir")]
+    pub remote_cache: Option<String>,
+
+    /// Print number of requests sent to API platform, and remaining quota of
+    /// API key if API platform exposes it, to stderr at the end.
+    #[clap(long="show-quota", global=true, multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub show_quota: bool,
+
+    /// Extra header in form of 'Name: value' sent with requests to API
+    /// platform of the chain e.g. for explorers behind auth proxies, but not
+    /// to its fallback, nor chains it fails over to. Prefix it with host as
+    /// 'host=Name: value' to send it only to the host instead. It can be
+    /// repeated.
+    #[clap(long="header", required=false, global=true, multiple_occurrences=true, number_of_values=1)]
+    pub headers: Vec<String>,
+
+    /// Token sent as 'Authorization: Bearer <token>' header with requests to
+    /// API platform of the chain, but not to its fallback, nor chains it fails
+    /// over to
+    #[clap(long="bearer-token", required=false, global=true)]
+    pub bearer_token: Option<String>,
+
+    /// Path to client certificate in PEM format for mutual TLS with API
+    /// platform
+    #[clap(long="client-cert", required=false, global=true)]
+    pub client_cert: Option<String>,
+
+    /// Path to private key of --client-cert in PEM format, if it's not
+    /// bundled in the certificate file
+    #[clap(long="client-key", required=false, global=true, requires="client-cert")]
+    pub client_key: Option<String>,
+
+    /// Path to bundle of CA certificates in PEM format to trust instead of
+    /// system ones e.g. for explorers with private CA
+    #[clap(long="ca-cert", required=false, global=true)]
+    pub ca_cert: Option<String>,
+
+    /// Print summaries of requests to API platform, and their responses to
+    /// stderr with API keys, and credentials in headers redacted.
+    #[clap(long="debug-http", global=true, multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub debug_http: bool,
+
+    /// Path to record requests to API platform, and their responses into as
+    /// HAR file at the end e.g. to share with support of explorer. API keys,
+    /// and credentials in headers are redacted.
+    #[clap(long="har", required=false, global=true)]
+    pub har_path: Option<String>,
+
+    /// Path to write log of what cleaning of CR/LF, canonicalization, and
+    /// formatting changed in each source file relative to response of API
+    /// platform as JSON at the end, with hashes of content before, and after
+    /// each pass.
+    #[clap(long="transform-log", required=false, global=true)]
+    pub transform_log_path: Option<String>,
+
+    /// Seconds to wait for output or fixture directory locked by another
+    /// tracpls process before giving up, 0 to give up immediately.
+    #[clap(long="wait-lock", required=false, global=true, default_value="30")]
+    pub wait_lock: u64,
+
+    /// CI mode; suppress progress, and print only a machine-parsable summary
+    /// line e.g. 'fetched=12 skipped=3 failed=1 duration=42s' at the end.
+    #[clap(long="ci", global=true, multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub ci: bool,
+
+    /// Emit machine-readable progress events to stderr.
+    /// Possible values are 'none', and 'ndjson' (one JSON object per line).
+    #[clap(long="progress", required=false, global=true, default_value="none")]
+    pub progress: String,
+
+    /// Send read-only calls one by one instead of batching them through
+    /// Multicall3
+    #[clap(long="no-multicall", global=true, multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub no_multicall: bool,
+
+    /// Read state e.g. storage slots, implementation of proxy, and results of
+    /// view calls as of this block instead of the latest one. Either block
+    /// number, or one of 'latest', 'earliest', 'pending', 'safe', and
+    /// 'finalized'.
+    #[clap(long="block", required=false, global=true)]
+    pub block: Option<String>,
+
+    /// Keep temporary workspaces e.g. sources laid out for linters instead
+    /// of removing them, and print where they are
+    #[clap(long="keep-temp", global=true, multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub keep_temp: bool,
+
+    /// Print where time of the run went to stderr at the end; waiting for API
+    /// platform, decoding, transforming, and writing, along with bytes
+    /// transferred, hit rate of cache, and peak memory.
+    #[clap(long="perf-report", global=true, multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub perf_report: bool,
 }
 
-/// Clean CR/LF as necessary as per platform running the application.
-///
-/// # Arguments
-/// * `text` - text to be cleaned if necessary
-///
-/// # Returned
-/// New instance of `String`.
-fn clean_crlf(text: &str) -> String {
-    let os = std::env::consts::OS;
-
-    // actually this would depend on the upstream source file itself
-    // for which platform developers edit file on, but we can clean it
-    // in (all) cases.
-
-    // on Linux/Unix uses only line feed (\n)
-    if os == "linux" {
-        let cleaned_text = str::replace(&str::replace(text, "\r\n", "\n"), "\r", "\n");
-        cleaned_text
-    }
-    // on macOS, it uses only carriage return (\r)
-    else if os == "macos" {
-        let cleaned_text = str::replace(&str::replace(text, "\r\n", "\r"), "\n", "\r");
-        cleaned_text
-    }
-    // otherwise don't clean anything
-    // e.g. Windows uses both CR/LF
-    else {
-        text.to_owned()
-    }
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// List internal transactions of the target address
+    #[clap(name="internal-txs")]
+    InternalTxs(internal_txs::InternalTxsArgs),
+
+    /// Report min/median/max gas used per method from recent transactions
+    #[clap(name="gas")]
+    Gas(gas::GasArgs),
+
+    /// Generate ready-to-edit files to interact with the target contract
+    #[clap(name="scaffold")]
+    Scaffold(scaffold::ScaffoldArgs),
+
+    /// Generate JSON Schema of every function's inputs and outputs from ABI
+    #[clap(name="schema")]
+    Schema(schema::SchemaArgs),
+
+    /// Print structured outline of the target contract
+    #[clap(name="explain")]
+    Explain(explain::ExplainArgs),
+
+    /// Dump compact AST JSON of the verified source compiled with solc
+    #[clap(name="ast")]
+    Ast(ast::AstArgs),
+
+    /// Run linters (solhint, or semgrep) over the verified source code
+    #[clap(name="analyze")]
+    Analyze(analyze::AnalyzeArgs),
+
+    /// Recompile the verified source with its verification settings and report compiler warnings
+    #[clap(name="build")]
+    Build(build::BuildArgs),
+
+    /// Fetch verified source code of contracts listed in a file, with license policy
+    #[clap(name="batch")]
+    Batch(batch::BatchArgs),
+
+    /// Serve a directory of canned contracts in the same API shape as API platforms
+    #[clap(name="mock-server")]
+    MockServer(mock_server::MockServerArgs),
+
+    /// Refresh tracked contracts listed in a manifest, re-downloading only changed ones
+    #[clap(name="sync")]
+    Sync(sync::SyncArgs),
+
+    /// Compare runtime bytecode of two contracts, ignoring metadata appended by compiler
+    #[clap(name="bytecode-diff")]
+    BytecodeDiff(bytecode_diff::BytecodeDiffArgs),
+
+    /// Report whether the target contract is deployed, selfdestructed, or metamorphic
+    #[clap(name="status")]
+    Status(status::StatusArgs),
+
+    /// List addresses hardcoded in source code, and bytecode of the target contract
+    #[clap(name="addresses")]
+    Addresses(addresses::AddressesArgs),
+
+    /// Decode values of immutable variables embedded in runtime bytecode of the target contract
+    #[clap(name="immutables")]
+    Immutables(immutables::ImmutablesArgs),
+
+    /// Emit call graph, or inheritance hierarchy of the verified source as DOT, or Mermaid
+    #[clap(name="graph")]
+    Graph(graph::GraphArgs),
+
+    /// Render class diagram of contracts, their state variables, and functions of the verified source
+    #[clap(name="diagram")]
+    Diagram(diagram::DiagramArgs),
+
+    /// Check storage layout of the target proxy against its implementation for collisions
+    #[clap(name="storage-check")]
+    StorageCheck(storage_check::StorageCheckArgs),
+
+    /// Validate proposed implementation of the target proxy with upgrade-safety rules
+    #[clap(name="upgrade-check")]
+    UpgradeCheck(upgrade_check::UpgradeCheckArgs),
+
+    /// Report who controls the target contract through owner, proxy admin, and roles, and with what delay
+    #[clap(name="owners")]
+    Owners(owners::OwnersArgs),
+
+    /// Flag common centralization, and trap patterns of the target contract as a heuristic pre-screen
+    #[clap(name="risk")]
+    Risk(risk::RiskArgs),
+
+    /// List chains selectable by name, or chain id, optionally refreshing them from chainlist
+    #[clap(name="chains")]
+    Chains(chains::ChainsArgs),
+
+    /// Export fetched contracts under a directory as a dataset of JSON Lines, CSV, or Parquet
+    #[clap(name="export")]
+    Export(export::ExportArgs),
+
+    /// Export local cache into a bundle, or import one, e.g. to hand it to another machine
+    #[clap(name="cache")]
+    Cache(cache::CacheArgs),
+
+    /// Export, import, or look up the local database of signatures indexed from fetched ABIs
+    #[clap(name="selectors")]
+    Selectors(selectors::SelectorsArgs),
+
+    /// Find contracts in local cache whose verified source code matches a pattern
+    #[clap(name="search")]
+    Search(search::SearchArgs),
+
+    /// Group cached contracts which are copies of each other by source code, or bytecode hash
+    #[clap(name="dupes")]
+    Dupes(dupes::DupesArgs),
+
+    /// Investigate contracts interactively, keeping current chain, and contract across commands
+    #[clap(name="repl")]
+    Repl(repl::ReplArgs),
+
+    /// List, or re-run past invocations recorded in history
+    #[clap(name="history")]
+    History(history::HistoryArgs),
+
+    /// Reconstruct pseudo-Solidity of unverified contract from its bytecode by heimdall
+    #[clap(name="decompile")]
+    Decompile(decompile::DecompileArgs),
+
+    /// Map program counter back to source line, or annotate disassembly with source lines
+    #[clap(name="pc")]
+    Pc(pc::PcArgs),
+
+    /// Decode raw data e.g. revert data into human readable form
+    #[clap(name="decode")]
+    Decode(decode::DecodeArgs),
+
+    /// Emit standard JSON input of verified contract to feed back into solc,
+    /// or a verification service
+    #[clap(name="standard-json")]
+    StandardJson(standard_json::StandardJsonArgs),
+
+    /// Compare compiler settings used for verification against foundry.toml
+    /// of local project
+    #[clap(name="settings-diff")]
+    SettingsDiff(settings_diff::SettingsDiffArgs),
+
+    /// Report compiler versions, and optimizer settings across contracts
+    /// tracked by a sync manifest, flagging ancient, and unoptimized builds
+    #[clap(name="audit-meta")]
+    AuditMeta(audit_meta::AuditMetaArgs),
+
+    /// Warn about known bugs of solc version used for verification relevant
+    /// to settings, and language features of contract
+    #[clap(name="advisory")]
+    Advisory(advisory::AdvisoryArgs),
+
+    /// Compare many contracts pairwise by source code, and bytecode, and
+    /// group similar ones e.g. forks of a token
+    #[clap(name="diff-matrix")]
+    DiffMatrix(diff_matrix::DiffMatrixArgs),
+
+    /// Diff two snapshots of contract taken with --snapshot by date, without
+    /// network access
+    #[clap(name="history-diff")]
+    HistoryDiff(history_diff::HistoryDiffArgs),
+
+    /// Watch an address for contracts it newly creates, and fetch source code
+    /// of each once verified
+    #[clap(name="watch-deployer")]
+    WatchDeployer(watch_deployer::WatchDeployerArgs),
+
+    /// List contracts created by a factory, and optionally fetch verified
+    /// ones
+    #[clap(name="children")]
+    Children(children::ChildrenArgs),
+
+    /// Show top holders of token with their labels, and how concentrated
+    /// its supply is
+    #[clap(name="holders")]
+    Holders(holders::HoldersArgs),
+
+    /// Call any function of contract read-only, and decode its return values,
+    /// or revert, without sending transaction
+    #[clap(name="simulate")]
+    Simulate(simulate::SimulateArgs),
+
+    /// List contracts touched by transaction, and fetch verified ones
+    #[clap(name="trace-deps")]
+    TraceDeps(trace_deps::TraceDepsArgs),
+
+    /// Show call tree of transaction with contracts, calls, and reverts
+    /// decoded
+    #[clap(name="trace")]
+    Trace(trace::TraceArgs),
+
+    /// Annotate addresses in text e.g. incident write-up with what they are
+    #[clap(name="enrich")]
+    Enrich(enrich::EnrichArgs),
+
+    /// Watch clipboard for copied addresses, and summarize each of them
+    #[clap(name="clipwatch")]
+    Clipwatch(clipwatch::ClipwatchArgs),
 }
 
-/// Combine two path components together and return str version of it.
+/// Exit code when the run stopped on error
+const EXIT_ERROR: i32 = 1;
+
+/// Exit code when the run completed but some contracts failed
+const EXIT_CONTRACTS_FAILED: i32 = 3;
+
+/// Execute the specified command.
 ///
 /// # Arguments
-/// * `path_a` - first path component
-/// * `path_b` - second path component
-fn combine_two_path_components(path_a: &str, path_b: &str) -> Result<String, String> {
-    let mut path = PathBuf::from(path_a);
-    path.push(path_b);
-
-    match path.as_path().to_str() {
-        Some(res) => Ok(res.to_owned()),
-        None => {
-            let err_msg = format!("Error converting PathBuf to str from result of concatenation of {} and {}", path_a, path_b);
-            return Err(err_msg);
-        }
+/// * `command` - command to execute
+fn run_command(command: &Command) -> Result<(), String> {
+    match command {
+        Command::InternalTxs(args) => internal_txs::run(args),
+        Command::Gas(args) => gas::run(args),
+        Command::Scaffold(args) => scaffold::run(args),
+        Command::Schema(args) => schema::run(args),
+        Command::Explain(args) => explain::run(args),
+        Command::Ast(args) => ast::run(args),
+        Command::Analyze(args) => analyze::run(args),
+        Command::Build(args) => build::run(args),
+        Command::Batch(args) => batch::run(args),
+        Command::MockServer(args) => mock_server::run(args),
+        Command::Sync(args) => sync::run(args),
+        Command::BytecodeDiff(args) => bytecode_diff::run(args),
+        Command::Status(args) => status::run(args),
+        Command::Addresses(args) => addresses::run(args),
+        Command::Immutables(args) => immutables::run(args),
+        Command::Graph(args) => graph::run(args),
+        Command::Diagram(args) => diagram::run(args),
+        Command::StorageCheck(args) => storage_check::run(args),
+        Command::UpgradeCheck(args) => upgrade_check::run(args),
+        Command::Owners(args) => owners::run(args),
+        Command::Risk(args) => risk::run(args),
+        Command::Chains(args) => chains::run(args),
+        Command::Export(args) => export::run(args),
+        Command::Cache(args) => cache::run(args),
+        Command::Selectors(args) => selectors::run(args),
+        Command::Search(args) => search::run(args),
+        Command::Dupes(args) => dupes::run(args),
+        Command::Repl(args) => repl::run(args),
+        Command::History(args) => history::run(args),
+        Command::Decompile(args) => decompile::run(args),
+        Command::Pc(args) => pc::run(args),
+        Command::Decode(args) => decode::run(args),
+        Command::StandardJson(args) => standard_json::run(args),
+        Command::SettingsDiff(args) => settings_diff::run(args),
+        Command::AuditMeta(args) => audit_meta::run(args),
+        Command::Advisory(args) => advisory::run(args),
+        Command::DiffMatrix(args) => diff_matrix::run(args),
+        Command::HistoryDiff(args) => history_diff::run(args),
+        Command::WatchDeployer(args) => watch_deployer::run(args),
+        Command::Children(args) => children::run(args),
+        Command::Holders(args) => holders::run(args),
+        Command::Simulate(args) => simulate::run(args),
+        Command::TraceDeps(args) => trace_deps::run(args),
+        Command::Trace(args) => trace::run(args),
+        Command::Enrich(args) => enrich::run(args),
+        Command::Clipwatch(args) => clipwatch::run(args),
     }
 }
 
-/// Create intermediate directories.
-/// It internally handles whether the path is file, or directory. So supplying
-/// the actual filepath here is fine.
+/// Fetch target contract as of flag options, and output it.
 ///
 /// # Arguments
-/// * `path` - path to create intermerdiate directories
-fn create_intermediate_dirs(path: &str) -> Result<(), String> {
-    let mut ppath = PathBuf::from(path);
-    // pop the last component out to get only directory path
-    if ppath.file_name().is_some() {
-        ppath.pop();
-    }
-
-    // get path string
-    let ppath_str = match ppath.as_path().to_str() {
-        Some(res) => res,
-        None => {
-            let err_msg = format!("Error getting path string from PathBuf ('{}')", path);
-            return Err(err_msg);
-        }
-    };
+/// * `cmd_args` - command line arguments
+fn run_default(cmd_args: &CommandlineArgs) -> Result<(), String> {
+    let address = cmd_args.address.clone().unwrap();
+    let has_out_dir_path = cmd_args.out_dir_path.is_some();
+    let has_archive_path = cmd_args.archive_path.is_some();
 
-    // create all directories leading up to what we will
-    match std::fs::create_dir_all(ppath_str) {
-        Ok(_) => (),
-        Err(e) => {
-            let err_msg = format!("Error creating intermediate directories; err={}", e);
-            return Err(err_msg);
-        }
+    // make sure flags are supplied and used only when it's proper
+    if !cmd_args.abi_only && cmd_args.no_abi_pretty_print {
+        return Err("Error --no-abi-pretty-print can ony be used when --abi-only exists".to_owned());
+    }
+    if !has_out_dir_path && !has_archive_path && cmd_args.layout.to_lowercase() != "flat" {
+        return Err("Error --layout can only be used when --out-dir or --archive exists".to_owned());
+    }
+    if cmd_args.recursive && (!has_out_dir_path || has_archive_path || cmd_args.abi_only) {
+        return Err("Error --recursive can only be used with --out-dir, but without --archive, and --abi-only".to_owned());
+    }
+    if !cmd_args.recursive && cmd_args.depth.is_some() {
+        return Err("Error --depth can only be used when --recursive exists".to_owned());
+    }
+    if cmd_args.snapshot && !has_out_dir_path {
+        return Err("Error --snapshot can only be used when --out-dir exists".to_owned());
+    }
+    if cmd_args.artifact_format.is_some() && ((!has_out_dir_path && !has_archive_path) || cmd_args.abi_only) {
+        return Err("Error --artifact-format can only be used when --out-dir or --archive exists, but without --abi-only".to_owned());
     }
+    if cmd_args.raw_response && ((!has_out_dir_path && !has_archive_path) || cmd_args.abi_only) {
+        return Err("Error --raw-response can only be used when --out-dir or --archive exists, but without --abi-only".to_owned());
+    }
+    if cmd_args.interfaces_only && (cmd_args.abi_only || cmd_args.artifact_format.is_some()) {
+        return Err("Error --interfaces-only can't be used with --abi-only, or --artifact-format".to_owned());
+    }
+    if cmd_args.prune_unreachable && cmd_args.abi_only {
+        return Err("Error --prune-unreachable can't be used with --abi-only".to_owned());
+    }
+    if cmd_args.contract.is_some() && cmd_args.abi_only {
+        return Err("Error --contract can't be used with --abi-only".to_owned());
+    }
+    let out_layout = layout::parse_layout(&cmd_args.layout)?;
+    let order = source::parse_order(&cmd_args.order)?;
+    let format = formatter::parse_format_options(cmd_args.fmt || cmd_args.canonical, cmd_args.tabs_to_spaces, cmd_args.indent_width)?;
+    let artifact_format = match cmd_args.artifact_format.as_ref() {
+        Some(value) => Some(artifact::parse_artifact_format(value)?),
+        None => None,
+    };
 
-    Ok(())
+    // validate value of chain flag option
+    let chain = match cmd_args.chain_id {
+        Some(chain_id) => parse_chain(&chain_id.to_string())?,
+        None => parse_chain(cmd_args.chain.as_ref().unwrap())?,
+    };
+
+    let options = fetch::FetchOptions {
+        abi_only: cmd_args.abi_only,
+        abi_pretty_print: !cmd_args.no_abi_pretty_print,
+        clean_crlf: !cmd_args.no_clean_crlf,
+        layout: out_layout,
+        project_files: has_out_dir_path || has_archive_path,
+        artifact_format,
+        order,
+        format,
+        canonical: cmd_args.canonical,
+        raw_response: cmd_args.raw_response,
+        interfaces_only: cmd_args.interfaces_only,
+        prune_unreachable: cmd_args.prune_unreachable,
+        contract: cmd_args.contract.clone(),
+    };
+    progress::emit("fetch_start", json!({ "address": address, "chain": chain.name }));
+
+    // each fetch goes into its own dated directory under out-dir
+    if let Some(base_dir) = cmd_args.out_dir_path.as_ref().filter(|_| cmd_args.snapshot) {
+        let snapshot_dir = snapshot::create(base_dir)?;
+        write_default(cmd_args, chain, &address, &options, Some(&snapshot_dir))?;
+        return snapshot::update_latest(&snapshot_dir);
+    }
+    write_default(cmd_args, chain, &address, &options, cmd_args.out_dir_path.as_ref())
 }
 
-/// Write content to file.
+/// Fetch target contract, and write it into output directory, archive, or
+/// print it to stdout.
 ///
 /// # Arguments
-/// * `filepath` - filepath to write file to, ensure path includes the filename
-/// * `content` - content of file
-fn write_file(filepath: &str, content: &str) -> Result<(), String> {
-    match std::fs::write(filepath, content) {
-        Ok(_) => (),
-        Err(e) => {
-            let err_msg = format!("Error writing file at '{}'; err={}", filepath, e);
-            return Err(err_msg);
+/// * `cmd_args` - command line arguments
+/// * `chain` - chain
+/// * `address` - contract address
+/// * `options` - fetch options
+/// * `out_dir_path` - output directory to write files into
+fn write_default(cmd_args: &CommandlineArgs, chain: &backend::Chain, address: &str, options: &fetch::FetchOptions, out_dir_path: Option<&String>) -> Result<(), String> {
+    let has_archive_path = cmd_args.archive_path.is_some();
+
+    // write files to out-dir one by one as they are decoded, unless all of
+    // them are needed at once anyway. Linked libraries are written along.
+    if !has_archive_path {
+        if let Some(out_dir_str) = out_dir_path.filter(|_| !cmd_args.abi_only) {
+            let depth = if cmd_args.recursive { cmd_args.depth.unwrap_or(1) } else { 0 };
+            return deps::fetch_recursive(chain, address, options, out_dir_str, depth, cmd_args.silence);
+        }
+        if let Some(out_dir_str) = out_dir_path {
+            let mut writer = manifest::OutDirWriter::open(out_dir_str, address, &chain.name, cmd_args.silence)?;
+            let count = fetch::fetch_each(chain, address, options, |path, content| writer.write(path, content))?;
+            writer.finish()?;
+            progress::emit("fetch_done", json!({ "address": address, "chain": chain.name, "files": count }));
+            return Ok(());
         }
     }
 
+    let fetch::Fetched { files, is_multi_file } = fetch::fetch(chain, address, options)?;
+    progress::emit("fetch_done", json!({ "address": address, "chain": chain.name, "files": files.len() }));
+
+    if let Some(archive_path) = cmd_args.archive_path.as_ref() {
+        archive::write_archive(archive_path, &files)?;
+        progress::emit("write_done", json!({ "path": archive_path, "unchanged": false }));
+        if !progress::silenced(cmd_args.silence) {
+            println!("{}", archive_path);
+        }
+
+        if let Some(out_dir_str) = out_dir_path {
+            let mut writer = manifest::OutDirWriter::open(out_dir_str, address, &chain.name, cmd_args.silence)?;
+            for (path, content) in files.iter() {
+                writer.write(path, content)?;
+            }
+            writer.finish()?;
+        }
+    }
+    else {
+        for (path, content) in files.iter() {
+            if is_multi_file {
+                println!("// ---------- {} ----------", path);
+            }
+            println!("{}", content);
+        }
+    }
     Ok(())
 }
 
-/// Select and return api key for selected chain type.
-/// The program needs environment variables as follows to be defined to cover
-/// all API platforms which one of them will be used at runtime depending on
-/// which chain has been selected.
-///
-/// * `bsc` - require environment variable `TRACPLS_BSCSCAN_APIKEY`
-/// * `ethereum` - require environment variable `TRACPLS_ETHERSCAN_APIKEY`
-/// * `polygon` - require environment variable `TRACPLS_POLYGONSCAN_APIKEY`
-///
-/// If such environment variable after selected has not defined yet, then
-/// this function will panic.
+/// Get settings of requests to API platform from flag options.
 ///
 /// # Arguments
-/// * `chain` - chain type
-fn select_apikey(chain: ChainType) -> String {
-    match chain {
-        ChainType::BSC => std::env::var("TRACPLS_BSCSCAN_APIKEY").expect("Required environment variable 'TRACPLS_BSCSCAN_APIKEY' to be defined"),
-        ChainType::Ethereum => std::env::var("TRACPLS_ETHERSCAN_APIKEY").expect("Required environment variable 'TRACPLS_ETHERSCAN_APIKEY' to be defined"),
-        ChainType::Polygon => std::env::var("TRACPLS_POLYGONSCAN_APIKEY").expect("Required environment variable 'TRACPLS_POLYGONSCAN_APIKEY' to be defined"),
+/// * `cmd_args` - command line arguments
+fn request_settings(cmd_args: &CommandlineArgs) -> Result<backend::RequestSettings, String> {
+    let mut headers = Vec::new();
+    for value in cmd_args.headers.iter() {
+        headers.push(backend::parse_header(value)?);
+    }
+    if let Some(token) = cmd_args.bearer_token.as_ref() {
+        headers.push(backend::Header { host: None, name: "Authorization".to_owned(), value: format!("Bearer {}", token) });
     }
+    Ok(backend::RequestSettings {
+        headers,
+        client_cert: cmd_args.client_cert.clone(),
+        client_key: cmd_args.client_key.clone(),
+        ca_cert: cmd_args.ca_cert.clone(),
+    })
 }
 
 fn main() {
-    let cmd_args = CommandlineArgs::parse();
-    let has_out_dir_path = cmd_args.out_dir_path.is_some();
-
-    // make sure flags are supplied and used only when it's proper
-    if !cmd_args.abi_only && cmd_args.no_abi_pretty_print {
-        eprintln!("Error --no-abi-pretty-print can ony be used when --abi-only exists");
+    let started = std::time::Instant::now();
+    let started_at = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+    let matches = CommandlineArgs::command().get_matches();
+    let cmd_args = CommandlineArgs::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let command_name = matches.subcommand_name().unwrap_or("fetch").to_owned();
+
+    if let Err(e) = init() {
+        eprintln!("{}", e);
         std::process::exit(1);
     }
 
-    // validate value of chain flag option
-    let chain_value = cmd_args.chain.to_lowercase();
+    lock::set_wait_secs(cmd_args.wait_lock);
+    match request_settings(&cmd_args) {
+        Ok(res) => backend::set_request_settings(res),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+    match progress::parse_progress(&cmd_args.progress) {
+        Ok(res) => progress::set_format(res),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
 
-    // use evmscan::types::evm_types::ChainType
-    let chain: Option<ChainType>;
-    if chain_value == "bsc" {
-        chain = Some(ChainType::BSC);
+    // only the final summary line is printed in CI mode
+    if cmd_args.ci {
+        progress::set_format(progress::ProgressFormat::None);
+        progress::set_quiet(true);
     }
-    else if chain_value == "ethereum" {
-        chain = Some(ChainType::Ethereum);
+
+    http_log::set_debug(cmd_args.debug_http);
+    multicall::set_enabled(!cmd_args.no_multicall);
+    workspace::set_keep(cmd_args.keep_temp);
+    match cmd_args.block.as_deref().map(explorer::parse_block_tag) {
+        Some(Ok(tag)) => explorer::set_block_tag(Some(tag)),
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        },
+        None => (),
     }
-    else if chain_value == "polygon" {
-        chain = Some(ChainType::Polygon);
+    if cmd_args.har_path.is_some() {
+        http_log::start_recording();
     }
-    else {
-        eprintln!("Error invalid value for --chain.
-Possible values are 'bsc', 'ethereum', or 'polygon'.");
-        std::process::exit(1);
+    if cmd_args.transform_log_path.is_some() {
+        transform_log::start_recording();
+    }
+    if cmd_args.perf_report {
+        perf::start_recording();
+    }
+
+    if let Some(dir) = cmd_args.record_dir.as_ref() {
+        explorer::set_fixture_mode(Some(explorer::FixtureMode::Record(dir.clone())));
+    }
+    else if let Some(dir) = cmd_args.replay_dir.as_ref() {
+        explorer::set_fixture_mode(Some(explorer::FixtureMode::Replay(dir.clone())));
+    }
+
+    if let Some(dir) = cmd_args.cache_dir.as_ref() {
+        cache::set_cache(Some(cache::CacheSettings { dir: dir.clone(), remote_url: cmd_args.remote_cache.clone() }));
     }
 
-    let ctx = Context::create(chain.unwrap(), select_apikey(chain.unwrap()));
-    let contracts = evmscan::contracts();
-
-    if cmd_args.abi_only {
-        match contracts.get_abi(&ctx, &cmd_args.address, !cmd_args.no_abi_pretty_print) {
-            Ok(abi) => {
-                if has_out_dir_path {
-                    let out_dir_str = cmd_args.out_dir_path.unwrap();
-                    let write_filepath = match combine_two_path_components(&out_dir_str, "abi.json") {
-                        Ok(res) => res,
-                        Err(e) => {
-                            eprintln!("{}", e);
-                            std::process::exit(1);
-                        }
-                    };
-
-                    match create_intermediate_dirs(&write_filepath) {
-                        Ok(_) => (),
-                        Err(e) => {
-                            eprintln!("{}", e);
-                            std::process::exit(1);
-                        }
-                    }
-
-                    let content = if !cmd_args.no_clean_crlf { clean_crlf(&abi) } else { abi };
-                    match write_file(&write_filepath, &content) {
-                        Ok(_) => if !cmd_args.silence { println!("{}", &write_filepath) },
-                        Err(e) => {
-                            eprintln!("{}", e);
-                            std::process::exit(1);
-                        }
-                    }
-                }
-                else {
-                    println!("{}", if !cmd_args.no_clean_crlf { clean_crlf(&abi) } else { abi });
-                }
-            },
-            Err(e) => {
-                eprintln!("{}", e);
-                std::process::exit(1);
-            },
+    let result = match cmd_args.command.as_ref() {
+        Some(command) => run_command(command),
+        None => run_default(&cmd_args),
+    };
+    if cmd_args.show_quota {
+        quota::print_summary();
+    }
+    if cmd_args.perf_report {
+        perf::print_report(started.elapsed());
+    }
+    if let Some(har_path) = cmd_args.har_path.as_ref() {
+        if let Err(e) = http_log::write_har(har_path) {
+            eprintln!("{}", e);
         }
     }
-    else {
-        match contracts.get_verified_source_code(&ctx, &cmd_args.address) {
-            Ok((contract_codes, is_submitted_as_json)) => {
-                if is_submitted_as_json {
-                    // we have more information about number of files, and
-                    // separate content of code for each file now. So there can
-                    // be options to handle this either
-                    // 1. output all files altogether as a whole
-                    // 2. output into target directory by writing into multiple files
-                    for i in 1..contract_codes.len() {
-                        if has_out_dir_path {
-                            let out_dir_str = cmd_args.out_dir_path.as_ref().unwrap();
-                            let write_filepath = match combine_two_path_components(&out_dir_str, &contract_codes[i].contract_name) {
-                                Ok(res) => res,
-                                Err(e) => {
-                                    eprintln!("{}", e);
-                                    std::process::exit(1);
-                                }
-                            };
-
-                            match create_intermediate_dirs(&write_filepath) {
-                                Ok(_) => (),
-                                Err(e) => {
-                                    eprintln!("{}", e);
-                                    std::process::exit(1);
-                                }
-                            }
-
-                            let content = if !cmd_args.no_clean_crlf { clean_crlf(&contract_codes[i].source_code) } else { contract_codes[i].source_code.clone() };
-                            match write_file(&write_filepath, &content) {
-                                Ok(_) => if !cmd_args.silence { println!("{}", &write_filepath) },
-                                Err(e) => {
-                                    eprintln!("{}", e);
-                                    std::process::exit(1);
-                                }
-                            }
-                        }
-                        else {
-                            println!("// ---------- {} ----------", contract_codes[i].contract_name);
-
-                            if !cmd_args.no_clean_crlf {
-                                println!("{}", clean_crlf(&contract_codes[i].source_code));
-                            }
-                            else {
-                                println!("{}", &contract_codes[i].source_code);
-                            }
-                        }
-                    }
-                }
-                else {
-                    if has_out_dir_path {
-                        let out_dir_str = cmd_args.out_dir_path.unwrap();
-                        // use contract name as the filename also append with .sol if necessary
-                        let mut filename = contract_codes[0].contract_name.clone();
-                        if !filename.ends_with(".sol") {
-                            filename.push_str(".sol");
-                        }
-                        let write_filepath = match combine_two_path_components(&out_dir_str, &filename) {
-                            Ok(res) => res,
-                            Err(e) => {
-                                eprintln!("{}", e);
-                                std::process::exit(1);
-                            }
-                        };
-
-                        match create_intermediate_dirs(&write_filepath) {
-                            Ok(_) => (),
-                            Err(e) => {
-                                eprintln!("{}", e);
-                                std::process::exit(1);
-                            }
-                        }
-
-                        let content = if !cmd_args.no_clean_crlf { clean_crlf(&contract_codes[0].source_code) } else { contract_codes[0].source_code.clone() };
-                        match write_file(&write_filepath, &content) {
-                            Ok(_) => if !cmd_args.silence { println!("{}", &write_filepath) },
-                            Err(e) => {
-                                eprintln!("{}", e);
-                                std::process::exit(1);
-                            }
-                        }
-                    }
-                    else {
-                        println!("{}", if !cmd_args.no_clean_crlf { clean_crlf(&contract_codes[0].source_code) } else { contract_codes[0].source_code.clone() });
-                    }
-                }
-            },
-            Err(e) => {
-                eprintln!("{}", e);
-                std::process::exit(1);
-            }
+    if let Some(path) = cmd_args.transform_log_path.as_ref() {
+        if let Err(e) = transform_log::write_log(path) {
+            eprintln!("{}", e);
         }
     }
+    if let Err(e) = selectors::flush() {
+        eprintln!("Warning: {}", e);
+    }
+    if command_name != "history" {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        history::record(&command_name, &args, started_at, result.as_ref().err().map(|e| e.as_str()));
+    }
+
+    let exit_code = match result {
+        Err(e) => {
+            // contracts failing along the way already reported themselves
+            if progress::tally().errors == 0 {
+                progress::error(cmd_args.address.as_deref(), &e);
+            }
+            eprintln!("{}", e);
+            EXIT_ERROR
+        },
+        Ok(_) if progress::tally().failed > 0 => EXIT_CONTRACTS_FAILED,
+        Ok(_) => 0,
+    };
+
+    if cmd_args.ci {
+        let tally = progress::tally();
+        println!("fetched={} skipped={} failed={} duration={}s", tally.fetched, tally.skipped, tally.failed, started.elapsed().as_secs());
+    }
+    if exit_code != 0 {
+        std::process::exit(exit_code);
+    }
 }
@@ -0,0 +1,317 @@
+//! Manifest of files written into output directory along with hashes of their
+//! content. Re-fetching into the same output directory compares against it,
+//! and skips rewriting files which haven't changed so their mtimes are kept
+//! stable, and downstream build tools don't rebuild unnecessarily. Files of
+//! the previous fetch which aren't written anymore e.g. after upgrade of
+//! contract are removed unless modified since.
+
+use crate::lock::DirLock;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Filename of manifest inside output directory
+pub const MANIFEST_FILENAME: &str = ".tracpls-manifest.json";
+
+/// Record of a single written file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Keccak256 hash of content as hex string
+    pub keccak256: String,
+
+    /// Size of content in bytes
+    pub size: u64,
+}
+
+/// Manifest of output directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub address: String,
+    pub chain: String,
+
+    /// Hash of verification metadata including source code the files were
+    /// generated from, if known. See `sync`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub source_keccak256: String,
+
+    /// Name of explorer backend which served the files e.g. `blockscout`, if
+    /// known. See `backend::with_failover`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub backend: String,
+
+    /// Written files by their path relative to output directory
+    pub files: BTreeMap<String, ManifestEntry>,
+}
+
+/// Read manifest of output directory if there is one.
+///
+/// # Arguments
+/// * `out_dir` - output directory
+pub fn read_manifest(out_dir: &str) -> Result<Option<Manifest>, String> {
+    let path = crate::combine_two_path_components(out_dir, MANIFEST_FILENAME)?;
+    if !std::path::Path::new(&path).exists() {
+        return Ok(None);
+    }
+
+    let content = match std::fs::read_to_string(&path) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error reading manifest at '{}'; err={}", path, e);
+            return Err(err_msg);
+        }
+    };
+    match serde_json::from_str(&content) {
+        Ok(res) => Ok(Some(res)),
+        Err(e) => {
+            let err_msg = format!("Error parsing manifest at '{}'; err={}", path, e);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Keccak256 hash of content of file on disk as hex string, or `None` if it
+/// can't be read e.g. it's removed.
+///
+/// # Arguments
+/// * `filepath` - path of file
+fn on_disk_keccak256(filepath: &str) -> Option<String> {
+    let content = std::fs::read(crate::safe_path::fs_path(filepath)).ok()?;
+    Some(crate::abi::to_hex(&crate::abi::keccak256(&content)))
+}
+
+/// Writer of files into output directory skipping unchanged ones as recorded
+/// in its manifest.
+#[derive(Debug)]
+pub struct OutDirWriter {
+    out_dir: String,
+    silence: bool,
+
+    /// Lock of output directory held while writing
+    _lock: DirLock,
+
+    /// Manifest as of before this run
+    previous: Option<Manifest>,
+
+    /// Manifest of files written, or kept during this run
+    current: Manifest,
+
+    /// Whether or not files of previous run not written in this run are
+    /// removed, see `keep_unwritten()`
+    prune: bool,
+
+    /// Number of files actually written
+    pub written: usize,
+
+    /// Number of files skipped as being up to date
+    pub unchanged: usize,
+}
+
+impl OutDirWriter {
+    /// Create writer for output directory, reading its manifest if any. The
+    /// directory is locked against other processes until writer is dropped.
+    ///
+    /// # Arguments
+    /// * `out_dir` - output directory
+    /// * `address` - contract address
+    /// * `chain` - name of chain
+    /// * `silence` - whether or not to print paths of files
+    pub fn open(out_dir: &str, address: &str, chain: &str, silence: bool) -> Result<Self, String> {
+        let lock = DirLock::acquire(out_dir)?;
+        Ok(Self {
+            out_dir: out_dir.to_owned(),
+            silence: crate::progress::silenced(silence),
+            _lock: lock,
+            previous: read_manifest(out_dir)?,
+            current: Manifest { address: address.to_owned(), chain: chain.to_owned(), ..Default::default() },
+            prune: true,
+            written: 0,
+            unchanged: 0,
+        })
+    }
+
+    /// Keep files of previous run which aren't written in this run, and their
+    /// entries in manifest, instead of removing them. It's for directories
+    /// written partially by each run e.g. ABIs of `batch --abi-only`.
+    pub fn keep_unwritten(&mut self) {
+        self.prune = false;
+    }
+
+    /// Record hash of verification metadata the files are generated from.
+    ///
+    /// # Arguments
+    /// * `hash` - hash as hex string
+    pub fn set_source_hash(&mut self, hash: &str) {
+        self.current.source_keccak256 = hash.to_owned();
+    }
+
+    /// Record name of explorer backend which served the files.
+    ///
+    /// # Arguments
+    /// * `name` - name of backend
+    pub fn set_backend(&mut self, name: &str) {
+        self.current.backend = name.to_owned();
+    }
+
+    /// Write file unless the same content is recorded in manifest, and is
+    /// still on disk, then print its path unless silenced.
+    ///
+    /// # Arguments
+    /// * `relative_path` - path of file relative to output directory
+    /// * `content` - content of file
+    pub fn write(&mut self, relative_path: &str, content: &str) -> Result<(), String> {
+        let filepath = crate::combine_two_path_components(&self.out_dir, relative_path)?;
+        let entry = ManifestEntry {
+            keccak256: crate::abi::to_hex(&crate::abi::keccak256(content.as_bytes())),
+            size: content.len() as u64,
+        };
+
+        // file might have been edited since, so what's on disk has to match
+        // too; size is checked first not to read files which surely changed
+        let recorded = self.previous.as_ref().and_then(|m| m.files.get(relative_path));
+        let recorded_same = recorded == Some(&entry)
+            && std::fs::metadata(crate::safe_path::fs_path(&filepath)).is_ok_and(|m| m.len() == entry.size)
+            && on_disk_keccak256(&filepath).as_ref() == Some(&entry.keccak256);
+        if recorded_same {
+            self.unchanged += 1;
+            if !self.silence {
+                println!("{} (up to date)", &filepath);
+            }
+        }
+        else {
+            crate::create_intermediate_dirs(&filepath)?;
+            crate::write_file(&filepath, content)?;
+            self.written += 1;
+            if !self.silence {
+                println!("{}", &filepath);
+            }
+        }
+
+        crate::progress::emit("write_done", serde_json::json!({ "path": filepath, "unchanged": recorded_same }));
+        self.current.files.insert(relative_path.to_owned(), entry);
+        Ok(())
+    }
+
+    /// Remove file of previous run which isn't written in this run, along with
+    /// directories left empty, unless it's modified since written. Returns
+    /// whether or not it's removed.
+    ///
+    /// # Arguments
+    /// * `relative_path` - path of file relative to output directory
+    /// * `entry` - record of file in manifest of previous run
+    fn remove_unwritten(&self, relative_path: &str, entry: &ManifestEntry) -> Result<bool, String> {
+        let filepath = crate::combine_two_path_components(&self.out_dir, relative_path)?;
+        let hash = match on_disk_keccak256(&filepath) {
+            Some(res) => res,
+            // already removed
+            None => return Ok(false),
+        };
+        if hash != entry.keccak256 {
+            eprintln!("Warning: {} is no longer fetched, but left in place as it is modified since written", filepath);
+            return Ok(false);
+        }
+        if let Err(e) = std::fs::remove_file(crate::safe_path::fs_path(&filepath)) {
+            let err_msg = format!("Error removing '{}' which is no longer fetched; err={}", filepath, e);
+            return Err(err_msg);
+        }
+
+        // only empty directories are removed, so stop at the first one which isn't
+        let out_dir = std::path::Path::new(&self.out_dir);
+        let mut dir = std::path::Path::new(&filepath).parent();
+        while let Some(d) = dir.filter(|d| *d != out_dir && d.starts_with(out_dir)) {
+            if std::fs::remove_dir(d).is_err() {
+                break;
+            }
+            dir = d.parent();
+        }
+        Ok(true)
+    }
+
+    /// Remove files of previous run not written in this run unless kept by
+    /// `keep_unwritten()`, then write manifest if it changed, and report if
+    /// nothing had to be written.
+    pub fn finish(mut self) -> Result<(), String> {
+        let unwritten: Vec<(String, ManifestEntry)> = self.previous.as_ref().map_or(Vec::new(), |m| m.files.iter()
+            .filter(|(path, _)| !self.current.files.contains_key(*path))
+            .map(|(path, entry)| (path.clone(), entry.clone()))
+            .collect());
+        let mut removed = 0;
+        for (path, entry) in unwritten {
+            if !self.prune {
+                self.current.files.insert(path, entry);
+            }
+            else if self.remove_unwritten(&path, &entry)? {
+                removed += 1;
+                if !self.silence {
+                    println!("{} (removed; no longer fetched)", crate::combine_two_path_components(&self.out_dir, &path)?);
+                }
+            }
+        }
+
+        if !self.silence && self.written == 0 && removed == 0 && self.unchanged > 0 {
+            println!("{} is up to date", self.out_dir);
+        }
+        if self.previous.as_ref() == Some(&self.current) {
+            return Ok(());
+        }
+
+        let content = match serde_json::to_string_pretty(&self.current) {
+            Ok(res) => res,
+            Err(e) => {
+                let err_msg = format!("Error serializing manifest to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        };
+        let filepath = crate::combine_two_path_components(&self.out_dir, MANIFEST_FILENAME)?;
+        crate::create_intermediate_dirs(&filepath)?;
+        crate::write_file(&filepath, &format!("{}\n", content))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::TempWorkspace;
+
+    /// Write files into output directory as a single run of fetching.
+    fn write_run(out_dir: &str, files: &[(&str, &str)]) -> (usize, usize) {
+        let mut writer = OutDirWriter::open(out_dir, "0xbeef", "bsc", true).unwrap();
+        for (path, content) in files {
+            writer.write(path, content).unwrap();
+        }
+        let counts = (writer.written, writer.unchanged);
+        writer.finish().unwrap();
+        counts
+    }
+
+    #[test]
+    fn out_dir_writer_skips_only_files_unchanged_on_disk() {
+        let workspace = TempWorkspace::new("test-manifest").unwrap();
+        let out_dir = workspace.path_str();
+        let files = [("src/A.sol", "contract A {}\n"), ("B.sol", "contract B {}\n")];
+        assert_eq!(write_run(out_dir, &files), (2, 0));
+        assert_eq!(write_run(out_dir, &files), (0, 2));
+
+        // edited in place keeping its size, then restored
+        let edited = workspace.path().join("src").join("A.sol");
+        std::fs::write(&edited, "contract Z {}\n").unwrap();
+        assert_eq!(write_run(out_dir, &files), (1, 1));
+        assert_eq!(std::fs::read_to_string(&edited).unwrap(), "contract A {}\n");
+
+        std::fs::remove_file(&edited).unwrap();
+        assert_eq!(write_run(out_dir, &files), (1, 1));
+        assert!(edited.exists());
+    }
+
+    #[test]
+    fn out_dir_writer_removes_unwritten_files_unless_modified() {
+        let workspace = TempWorkspace::new("test-manifest").unwrap();
+        let out_dir = workspace.path_str();
+        write_run(out_dir, &[("src/A.sol", "contract A {}\n"), ("src/B.sol", "contract B {}\n"), ("C.sol", "contract C {}\n")]);
+        std::fs::write(workspace.path().join("C.sol"), "contract Z {}\n").unwrap();
+
+        assert_eq!(write_run(out_dir, &[("src/A.sol", "contract A {}\n")]), (0, 1));
+        assert!(!workspace.path().join("src").join("B.sol").exists());
+        assert!(workspace.path().join("C.sol").exists());
+        let manifest = read_manifest(out_dir).unwrap().unwrap();
+        assert_eq!(manifest.files.keys().collect::<Vec<_>>(), vec!["src/A.sol"]);
+    }
+}
@@ -0,0 +1,312 @@
+//! `mock-server` command: serve a directory of canned contracts in the same
+//! API shape as the supported API platforms, so tracpls (or anything else
+//! speaking that API) can run without API key or network.
+//!
+//! Each contract is a sub-directory named by its address, holding its source
+//! files (`*.sol`, in any nested directories), and optionally
+//! * `abi.json` - contract ABI
+//! * `meta.json` - fields of verification metadata to override e.g.
+//!   `ContractName`, `CompilerVersion`, `OptimizationUsed`, `Runs`,
+//!   `EVMVersion`, or `LicenseType`
+//! * `bytecode.hex` - runtime bytecode as hex string
+//! * `storage.json` - values of storage slots keyed by slot e.g.
+//!   `{ "0x0": "0x1", "0x3608...2bbc": "0x...address" }`
+//! * `calls.json` - return data of `eth_call` keyed by calldata e.g.
+//!   `{ "0x8da5cb5b": "0x...owner" }`
+//! * `tags.json` - public name tag, and labels e.g.
+//!   `{ "nametag": "PancakeSwap: Router v2", "labels": ["DEX"] }`
+
+use clap::Args;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+
+#[derive(Debug, Args)]
+pub struct MockServerArgs {
+    /// Directory of canned contracts, one sub-directory named by address for
+    /// each contract
+    #[clap(long="dir", short='d', required=true)]
+    pub dir: String,
+
+    /// Port to listen on at 127.0.0.1
+    #[clap(long="port", short='p', required=false, default_value="8555")]
+    pub port: u16,
+}
+
+/// Decode percent-encoded text of query string.
+///
+/// # Arguments
+/// * `text` - text to decode
+fn decode_query_value(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                match std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(b) => {
+                        decoded.push(b);
+                        i += 3;
+                        continue;
+                    },
+                    None => decoded.push(b'%'),
+                }
+            },
+            b'+' => decoded.push(b' '),
+            b => decoded.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Find directory of contract by address, case-insensitive.
+///
+/// # Arguments
+/// * `dir` - directory of canned contracts
+/// * `address` - contract address
+fn find_contract_dir(dir: &str, address: &str) -> Option<std::path::PathBuf> {
+    let entries = std::fs::read_dir(dir).ok()?;
+    entries.filter_map(|e| e.ok())
+        .find(|e| e.path().is_dir() && e.file_name().to_string_lossy().eq_ignore_ascii_case(address))
+        .map(|e| e.path())
+}
+
+/// Read JSON file if it exists.
+///
+/// # Arguments
+/// * `path` - path of JSON file
+fn read_json(path: &Path) -> Result<Option<Value>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    match std::fs::read_to_string(path).map(|c| serde_json::from_str::<Value>(&c)) {
+        Ok(Ok(res)) => Ok(Some(res)),
+        Ok(Err(e)) => {
+            let err_msg = format!("Error parsing '{}'; err={}", path.display(), e);
+            Err(err_msg)
+        },
+        Err(e) => {
+            let err_msg = format!("Error reading file '{}'; err={}", path.display(), e);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Build verification metadata of canned contract as returned by
+/// `getsourcecode` endpoint.
+///
+/// # Arguments
+/// * `contract_dir` - directory of contract
+fn source_meta(contract_dir: &Path) -> Result<Value, String> {
+    let mut files = Vec::new();
+    crate::source::collect_local_sources(contract_dir, contract_dir, &mut files)?;
+    let abi = read_json(&contract_dir.join("abi.json"))?;
+    let overrides = read_json(&contract_dir.join("meta.json"))?;
+
+    // name the contract after its first source file unless specified
+    let default_name = files.first()
+        .map(|(path, _)| path.rsplit('/').next().unwrap_or(path).trim_end_matches(".sol").to_owned())
+        .unwrap_or_default();
+
+    let mut meta = json!({
+        "SourceCode": "",
+        "ABI": abi.map_or("Contract source code not verified".to_owned(), |a| a.to_string()),
+        "ContractName": default_name,
+        "CompilerVersion": "v0.8.17+commit.8df45f5f",
+        "OptimizationUsed": "0",
+        "Runs": "200",
+        "ConstructorArguments": "",
+        "EVMVersion": "Default",
+        "Library": "",
+        "LicenseType": "None",
+        "Proxy": "0",
+        "Implementation": "",
+        "SwarmSource": "",
+    });
+    if let Some(Value::Object(overrides)) = overrides {
+        for (key, value) in overrides {
+            meta[key] = value;
+        }
+    }
+
+    // single file is submitted as-is, multiple files as standard JSON input
+    // wrapped with an extra pair of braces
+    if files.len() == 1 {
+        meta["SourceCode"] = json!(files[0].1);
+    }
+    else if files.len() > 1 {
+        let mut sources = serde_json::Map::new();
+        for (path, content) in files.iter() {
+            sources.insert(path.clone(), json!({ "content": content }));
+        }
+        let input = json!({
+            "language": "Solidity",
+            "sources": sources,
+            "settings": {
+                "optimizer": {
+                    "enabled": meta["OptimizationUsed"] == "1",
+                    "runs": meta["Runs"].as_str().and_then(|r| r.parse::<u64>().ok()).unwrap_or(200),
+                },
+            },
+        });
+        meta["SourceCode"] = json!(format!("{{{}}}", input));
+    }
+    Ok(meta)
+}
+
+/// Build response body for query parameters of request.
+///
+/// # Arguments
+/// * `dir` - directory of canned contracts
+/// * `params` - query parameters as pairs of key and value
+fn respond(dir: &str, params: &[(String, String)]) -> Value {
+    let param = |name: &str| params.iter().find(|(key, _)| key == name).map_or("", |(_, value)| value.as_str());
+    let not_ok = |result: &str| json!({ "status": "0", "message": "NOTOK", "result": result });
+
+    let contract_dir = find_contract_dir(dir, param("address"));
+    match (param("module"), param("action")) {
+        ("contract", "getsourcecode") => {
+            let meta = match contract_dir {
+                Some(contract_dir) => source_meta(&contract_dir),
+                None => Ok(json!({ "SourceCode": "", "ABI": "Contract source code not verified", "ContractName": "" })),
+            };
+            match meta {
+                Ok(meta) => json!({ "status": "1", "message": "OK", "result": [meta] }),
+                Err(e) => not_ok(&e),
+            }
+        },
+        ("contract", "getabi") => {
+            match contract_dir.map(|d| read_json(&d.join("abi.json"))) {
+                Some(Ok(Some(abi))) => json!({ "status": "1", "message": "OK", "result": abi.to_string() }),
+                Some(Err(e)) => not_ok(&e),
+                _ => not_ok("Contract source code not verified"),
+            }
+        },
+        ("proxy", "eth_getCode") => {
+            // no contract at address has empty code
+            let code = contract_dir.and_then(|d| std::fs::read_to_string(d.join("bytecode.hex")).ok())
+                .map_or("0x".to_owned(), |c| format!("0x{}", c.trim().trim_start_matches("0x")));
+            json!({ "jsonrpc": "2.0", "id": 1, "result": code })
+        },
+        ("proxy", "eth_call") => {
+            // calls without canned return data behave as if function doesn't exist
+            let calls = find_contract_dir(dir, param("to")).and_then(|d| read_json(&d.join("calls.json")).ok().flatten());
+            let data = calls.as_ref().and_then(|c| c.get(param("data").to_lowercase()))
+                .and_then(|d| d.as_str())
+                .unwrap_or("0x");
+            json!({ "jsonrpc": "2.0", "id": 1, "result": data })
+        },
+        ("proxy", "eth_getStorageAt") => {
+            // slots are compared as numbers regardless of their zero padding
+            let number = |slot: &str| slot.trim().trim_start_matches("0x").trim_start_matches('0').to_lowercase();
+            let storage = contract_dir.and_then(|d| read_json(&d.join("storage.json")).ok().flatten());
+            let value = storage.as_ref().and_then(|s| s.as_object())
+                .and_then(|s| s.iter().find(|(slot, _)| number(slot) == number(param("position"))))
+                .and_then(|(_, value)| value.as_str())
+                .map_or(String::new(), number);
+            json!({ "jsonrpc": "2.0", "id": 1, "result": format!("0x{:0>64}", value) })
+        },
+        ("nametag", "getaddresstag") => {
+            match contract_dir.map(|d| read_json(&d.join("tags.json"))) {
+                Some(Ok(Some(tags))) => json!({ "status": "1", "message": "OK", "result": [{
+                    "address": param("address").to_lowercase(),
+                    "nametag": tags["nametag"].as_str().unwrap_or(""),
+                    "labels": tags.get("labels").cloned().unwrap_or(json!([])),
+                }] }),
+                Some(Err(e)) => not_ok(&e),
+                _ => json!({ "status": "0", "message": "No data found", "result": [] }),
+            }
+        },
+        (module, action) => not_ok(&format!("Unsupported module '{}' and action '{}' by mock server", module, action)),
+    }
+}
+
+/// Read HTTP request of connection. Returns path, and parameters of query
+/// string decoded.
+///
+/// # Arguments
+/// * `stream` - connection
+pub fn read_request(stream: &TcpStream) -> Result<(String, Vec<(String, String)>), String> {
+    let mut reader = BufReader::new(stream);
+
+    // e.g. "GET /api?module=contract&action=getabi&address=0x... HTTP/1.1"
+    let mut request_line = String::new();
+    if let Err(e) = reader.read_line(&mut request_line) {
+        let err_msg = format!("Error reading request; err={}", e);
+        return Err(err_msg);
+    }
+    // drain headers
+    let mut line = String::new();
+    while matches!(reader.read_line(&mut line), Ok(n) if n > 0) && line != "\r\n" && line != "\n" {
+        line.clear();
+    }
+
+    let target = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let params: Vec<(String, String)> = query.split('&')
+        .filter_map(|kv| kv.split_once('='))
+        .map(|(key, value)| (decode_query_value(key), decode_query_value(value)))
+        .collect();
+    Ok((decode_query_value(path), params))
+}
+
+/// Write JSON response to connection.
+///
+/// # Arguments
+/// * `stream` - connection
+/// * `status` - status line e.g. `200 OK`
+/// * `body` - JSON body
+pub fn write_json(mut stream: &TcpStream, status: &str, body: &str) -> Result<(), String> {
+    let response = format!("HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, body.len(), body);
+    if let Err(e) = stream.write_all(response.as_bytes()) {
+        let err_msg = format!("Error writing response; err={}", e);
+        return Err(err_msg);
+    }
+    Ok(())
+}
+
+/// Handle single HTTP request then close the connection.
+///
+/// # Arguments
+/// * `stream` - connection
+/// * `dir` - directory of canned contracts
+fn handle(stream: TcpStream, dir: &str) -> Result<(), String> {
+    let (_, params) = read_request(&stream)?;
+    write_json(&stream, "200 OK", &respond(dir, &params).to_string())
+}
+
+/// Execute `mock-server` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &MockServerArgs) -> Result<(), String> {
+    if !Path::new(&args.dir).is_dir() {
+        let err_msg = format!("Error '{}' is not a directory", args.dir);
+        return Err(err_msg);
+    }
+
+    let listener = match TcpListener::bind(("127.0.0.1", args.port)) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error listening on port {}; err={}", args.port, e);
+            return Err(err_msg);
+        }
+    };
+
+    println!("Serving contracts in '{}' at http://127.0.0.1:{}/api", args.dir, args.port);
+    println!("Use it with TRACPLS_API_URL=http://127.0.0.1:{}/api", args.port);
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(e) = handle(stream, &args.dir) {
+                    eprintln!("{}", e);
+                }
+            },
+            Err(e) => eprintln!("Error accepting connection; err={}", e),
+        }
+    }
+    Ok(())
+}
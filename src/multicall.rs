@@ -0,0 +1,175 @@
+//! Batching of read-only calls through Multicall3, deployed at the same
+//! address on most chains, so reading many addresses e.g. owners, and token
+//! names costs a request per batch instead of per call.
+//!
+//! Calls are sent one by one instead if Multicall3 isn't usable on chain, or
+//! batching is disabled with `--no-multicall`.
+
+use crate::abi;
+use crate::backend::Chain;
+use crate::explorer;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Address of Multicall3
+const MULTICALL3_ADDRESS: &str = "0xca11bde05977b3631167028862be2a173976ca11";
+
+/// Selector of `aggregate3((address,bool,bytes)[])`
+const AGGREGATE3_SELECTOR: &str = "0x82ad56cb";
+
+/// Maximum number of calls in a batch, keeping request within URL length
+/// limit of API platforms
+const MAX_CALLS_PER_BATCH: usize = 20;
+
+/// Whether or not calls are batched
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Chains whose Multicall3 failed, not to be tried again
+static UNSUPPORTED_CHAINS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Set whether or not calls are batched through Multicall3.
+///
+/// # Arguments
+/// * `enabled` - whether or not calls are batched
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Encode number as 32-byte word.
+///
+/// # Arguments
+/// * `value` - number
+fn word(value: usize) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&(value as u64).to_be_bytes());
+    word
+}
+
+/// Read number from 32-byte word at offset, or `None` if it's out of data,
+/// or too large to be offset, or length.
+///
+/// # Arguments
+/// * `data` - ABI-encoded data
+/// * `at` - byte offset of word
+fn read_word(data: &[u8], at: usize) -> Option<usize> {
+    let bytes = data.get(at..at.checked_add(32)?)?;
+    if bytes[..24].iter().any(|b| *b != 0) {
+        return None;
+    }
+    Some(bytes[24..].iter().fold(0usize, |acc, b| (acc << 8) | *b as usize))
+}
+
+/// Encode calldata of `aggregate3` allowing each call to fail.
+///
+/// # Arguments
+/// * `calls` - pairs of target address, and calldata
+fn encode_aggregate3(calls: &[(String, Vec<u8>)]) -> Result<String, String> {
+    // each tuple is address, allowFailure, offset of calldata, then calldata
+    let mut tuples: Vec<Vec<u8>> = Vec::new();
+    for (target, data) in calls.iter() {
+        let address = abi::from_hex(target)?;
+        if address.len() != 20 {
+            let err_msg = format!("Error invalid address {} to call", target);
+            return Err(err_msg);
+        }
+        let mut tuple = Vec::new();
+        tuple.extend_from_slice(&[0u8; 12]);
+        tuple.extend_from_slice(&address);
+        tuple.extend_from_slice(&word(1));
+        tuple.extend_from_slice(&word(96));
+        tuple.extend_from_slice(&word(data.len()));
+        tuple.extend_from_slice(data);
+        tuple.resize(tuple.len() + (32 - data.len() % 32) % 32, 0);
+        tuples.push(tuple);
+    }
+
+    let mut encoded = Vec::new();
+    encoded.extend_from_slice(&word(32));
+    encoded.extend_from_slice(&word(tuples.len()));
+    let mut offset = tuples.len() * 32;
+    for tuple in tuples.iter() {
+        encoded.extend_from_slice(&word(offset));
+        offset += tuple.len();
+    }
+    for tuple in tuples.iter() {
+        encoded.extend_from_slice(tuple);
+    }
+    Ok(format!("{}{}", AGGREGATE3_SELECTOR, abi::to_hex(&encoded)))
+}
+
+/// Decode return data of `aggregate3` into return data of each call, `None`
+/// for failed one. Returns `None` if it's malformed.
+///
+/// # Arguments
+/// * `data` - return data
+/// * `count` - number of calls
+fn decode_aggregate3(data: &[u8], count: usize) -> Option<Vec<Option<Vec<u8>>>> {
+    let array = read_word(data, 0)?;
+    if read_word(data, array)? != count {
+        return None;
+    }
+    let items = array + 32;
+    let mut results = Vec::with_capacity(count);
+    for i in 0..count {
+        let tuple = items + read_word(data, items + i * 32)?;
+        let success = read_word(data, tuple)? != 0;
+        let bytes = tuple + read_word(data, tuple + 32)?;
+        let len = read_word(data, bytes)?;
+        let returned = data.get(bytes + 32..bytes + 32 + len)?.to_vec();
+        results.push(Some(returned).filter(|_| success));
+    }
+    Some(results)
+}
+
+/// Whether or not Multicall3 is to be used for chain.
+///
+/// # Arguments
+/// * `chain` - chain
+fn is_usable(chain: &Chain) -> bool {
+    ENABLED.load(Ordering::Relaxed) && UNSUPPORTED_CHAINS.lock().is_ok_and(|u| !u.contains(&chain.name))
+}
+
+/// Remember that Multicall3 isn't usable on chain, warning once.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `reason` - why it isn't
+fn mark_unsupported(chain: &Chain, reason: &str) {
+    if let Ok(mut unsupported) = UNSUPPORTED_CHAINS.lock() {
+        if !unsupported.contains(&chain.name) {
+            eprintln!("Warning: Multicall3 is not usable on {}; calling one by one instead; {}", chain.name, reason);
+            unsupported.push(chain.name.clone());
+        }
+    }
+}
+
+/// Call functions read-only, batched through Multicall3 when possible.
+/// Returns return data of each call in order, or `None` for one which
+/// reverted, or failed.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `calls` - pairs of target address, and calldata as hex string with `0x`
+///   prefix
+pub fn call_many(chain: &Chain, calls: &[(String, String)]) -> Vec<Option<Vec<u8>>> {
+    let mut results: Vec<Option<Vec<u8>>> = Vec::with_capacity(calls.len());
+    for batch in calls.chunks(MAX_CALLS_PER_BATCH) {
+        if batch.len() > 1 && is_usable(chain) {
+            let batched = batch.iter()
+                .map(|(target, data)| abi::from_hex(data).map(|d| (target.clone(), d)))
+                .collect::<Result<Vec<(String, Vec<u8>)>, String>>()
+                .and_then(|decoded| encode_aggregate3(&decoded))
+                .and_then(|data| explorer::call(chain, MULTICALL3_ADDRESS, &data));
+            match batched.map(|data| decode_aggregate3(&data, batch.len())) {
+                Ok(Some(res)) => {
+                    results.extend(res);
+                    continue;
+                },
+                Ok(None) => mark_unsupported(chain, "unexpected return data"),
+                Err(e) => mark_unsupported(chain, &e),
+            }
+        }
+        results.extend(batch.iter().map(|(target, data)| explorer::call(chain, target, data).ok()));
+    }
+    results
+}
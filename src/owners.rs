@@ -0,0 +1,229 @@
+//! `owners` command: report who controls contract, and with what delay, from
+//! common control points read over RPC; `owner()`, proxy admin, members of
+//! AccessControl roles, and timelock delays, or multisig thresholds of those
+//! controllers.
+
+use crate::abi;
+use crate::backend::Chain;
+use crate::explorer;
+use crate::multicall;
+use crate::proxy;
+use crate::TargetArgs;
+use clap::Args;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Args)]
+pub struct OwnersArgs {
+    #[clap(flatten)]
+    pub target: TargetArgs,
+
+    /// Output as JSON instead of human readable table
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+}
+
+/// Roles of OpenZeppelin contracts tried when ABI doesn't list any
+const WELL_KNOWN_ROLES: [&str; 7] = [
+    "MINTER_ROLE",
+    "PAUSER_ROLE",
+    "UPGRADER_ROLE",
+    "PROPOSER_ROLE",
+    "EXECUTOR_ROLE",
+    "CANCELLER_ROLE",
+    "TIMELOCK_ADMIN_ROLE",
+];
+
+/// Maximum number of members listed for each role
+const MAX_ROLE_MEMBERS: u64 = 20;
+
+/// Call function, and return its return data, or `None` if it reverts, or
+/// returns nothing e.g. function doesn't exist.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+/// * `signature` - canonical signature of function e.g. `owner()`
+/// * `args` - ABI-encoded arguments as hex string without `0x` prefix
+fn try_call(chain: &Chain, address: &str, signature: &str, args: &str) -> Option<Vec<u8>> {
+    let data = format!("{}{}", abi::selector_of(signature), args);
+    explorer::call(chain, address, &data).ok().filter(|d| d.len() >= 32)
+}
+
+/// Get number held by 32-byte word, or `None` if it doesn't fit into `u64`.
+///
+/// # Arguments
+/// * `word` - 32-byte word
+fn word_to_u64(word: &[u8]) -> Option<u64> {
+    if word.len() < 32 || word[..24].iter().any(|b| *b != 0) {
+        return None;
+    }
+    Some(word[24..32].iter().fold(0u64, |acc, b| (acc << 8) | *b as u64))
+}
+
+/// Format duration in seconds e.g. `172800s (2d 0h)`.
+///
+/// # Arguments
+/// * `seconds` - duration in seconds
+fn format_delay(seconds: u64) -> String {
+    match seconds {
+        0 => "none".to_owned(),
+        s if s < 3600 => format!("{}s", s),
+        s => format!("{}s ({}d {}h)", s, s / 86400, (s % 86400) / 3600),
+    }
+}
+
+/// Describe what controlling addresses are e.g. account, multisig with its
+/// threshold, or timelock with its delay. Calls to all of them are batched.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `addresses` - controlling addresses
+fn describe_all(chain: &Chain, addresses: &[&String]) -> Result<BTreeMap<String, Value>, String> {
+    let mut described: BTreeMap<String, Value> = BTreeMap::new();
+    let mut contracts: Vec<&String> = Vec::new();
+    for address in addresses.iter() {
+        if explorer::get_code(chain, address)?.is_empty() {
+            described.insert(address.to_string(), json!({ "kind": "account", "detail": null, "delaySeconds": null }));
+        }
+        else {
+            contracts.push(address);
+        }
+    }
+
+    // Safe multisig, then OpenZeppelin TimelockController, or Compound Timelock
+    let signatures = ["getThreshold()", "getOwners()", "getMinDelay()", "delay()"];
+    let calls: Vec<(String, String)> = contracts.iter()
+        .flat_map(|a| signatures.iter().map(|s| (a.to_string(), abi::selector_of(s))))
+        .collect();
+    let results = multicall::call_many(chain, &calls);
+    for (address, returned) in contracts.iter().zip(results.chunks(signatures.len())) {
+        let returned: Vec<Option<&Vec<u8>>> = returned.iter().map(|r| r.as_ref().filter(|d| d.len() >= 32)).collect();
+        let threshold = returned[0].and_then(|w| word_to_u64(w));
+        let owners = returned[1].and_then(|d| word_to_u64(d.get(32..64)?));
+        let delay = returned[2].or(returned[3]).and_then(|w| word_to_u64(w));
+        let description = match (threshold, owners, delay) {
+            (Some(threshold), Some(owners), _) => json!({ "kind": "multisig", "detail": format!("{} of {}", threshold, owners), "delaySeconds": null }),
+            (_, _, Some(delay)) => json!({ "kind": "timelock", "detail": format!("delay {}", format_delay(delay)), "delaySeconds": delay }),
+            _ => json!({ "kind": "contract", "detail": null, "delaySeconds": null }),
+        };
+        described.insert(address.to_string(), description);
+    }
+    Ok(described)
+}
+
+/// Get roles of AccessControl contract as pairs of name, and role hash as hex
+/// string without `0x` prefix. Role getters listed in ABI are called, or well
+/// known ones are hashed if ABI isn't available.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+fn roles(chain: &Chain, address: &str) -> Vec<(String, String)> {
+    let mut roles = vec![("DEFAULT_ADMIN_ROLE".to_owned(), "00".repeat(32))];
+    match abi::fetch_abi(chain, address) {
+        Ok(contract_abi) => {
+            let getters: Vec<&abi::Entry> = contract_abi.functions()
+                .filter(|f| f.name.ends_with("_ROLE") && f.name != "DEFAULT_ADMIN_ROLE" && f.inputs.is_empty())
+                .collect();
+            let calls: Vec<(String, String)> = getters.iter().map(|g| (address.to_owned(), g.selector())).collect();
+            for (getter, returned) in getters.iter().zip(multicall::call_many(chain, &calls)) {
+                if let Some(word) = returned.filter(|w| w.len() >= 32) {
+                    roles.push((getter.name.clone(), abi::to_hex(&word[..32])));
+                }
+            }
+        },
+        Err(_) => {
+            for name in WELL_KNOWN_ROLES.iter() {
+                roles.push((name.to_string(), abi::to_hex(&abi::keccak256(name.as_bytes()))));
+            }
+        }
+    }
+    roles
+}
+
+/// Execute `owners` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &OwnersArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.target.chain)?;
+    let address = args.target.address.to_lowercase();
+    if explorer::get_code(chain, &address)?.is_empty() {
+        let err_msg = format!("Error no contract at {}", address);
+        return Err(err_msg);
+    }
+
+    // pairs of control point, and its holder
+    let mut controls: Vec<(String, String)> = Vec::new();
+    if let Some(owner) = try_call(chain, &address, "owner()", "").and_then(|w| proxy::word_to_address(&w)) {
+        controls.push(("owner".to_owned(), owner));
+    }
+    if let Some(admin) = proxy::resolve_admin(chain, &address)? {
+        // ProxyAdmin is itself owned
+        let owner = try_call(chain, &admin, "owner()", "").and_then(|w| proxy::word_to_address(&w));
+        controls.push(("proxy admin".to_owned(), admin));
+        if let Some(owner) = owner {
+            controls.push(("proxy admin owner".to_owned(), owner));
+        }
+    }
+    let roles = roles(chain, &address);
+    let count_calls: Vec<(String, String)> = roles.iter()
+        .map(|(_, role)| (address.clone(), format!("{}{}", abi::selector_of("getRoleMemberCount(bytes32)"), role)))
+        .collect();
+    let mut member_calls: Vec<(String, String)> = Vec::new();
+    let mut member_roles: Vec<&String> = Vec::new();
+    for ((name, role), returned) in roles.iter().zip(multicall::call_many(chain, &count_calls)) {
+        let count = match returned.and_then(|w| word_to_u64(&w)) {
+            Some(res) => res,
+            None => continue,
+        };
+        for i in 0..count.min(MAX_ROLE_MEMBERS) {
+            member_calls.push((address.clone(), format!("{}{}{:064x}", abi::selector_of("getRoleMember(bytes32,uint256)"), role, i)));
+            member_roles.push(name);
+        }
+        if count > MAX_ROLE_MEMBERS {
+            eprintln!("Warning: {} has {} members, only first {} are listed", name, count, MAX_ROLE_MEMBERS);
+        }
+    }
+    for (name, returned) in member_roles.iter().zip(multicall::call_many(chain, &member_calls)) {
+        if let Some(member) = returned.filter(|w| w.len() >= 32).and_then(|w| proxy::word_to_address(&w)) {
+            controls.push((name.to_string(), member));
+        }
+    }
+
+    let mut holders: Vec<&String> = controls.iter().map(|(_, holder)| holder).collect();
+    holders.sort();
+    holders.dedup();
+    let described = describe_all(chain, &holders)?;
+    let mut entries: Vec<Value> = Vec::new();
+    for (control, holder) in controls.iter() {
+        let mut entry = described[holder].clone();
+        entry["control"] = json!(control);
+        entry["address"] = json!(abi::to_checksum_address(holder));
+        entries.push(entry);
+    }
+
+    if args.json {
+        match serde_json::to_string_pretty(&entries) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing owners to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+        return Ok(());
+    }
+
+    if entries.is_empty() {
+        println!("No owner, admin, nor role members found");
+        return Ok(());
+    }
+
+    let text = |v: &Value| v.as_str().unwrap_or("-").to_owned();
+    println!("{:<22} {:<42} {:<9} DETAIL", "CONTROL", "ADDRESS", "KIND");
+    for entry in entries.iter() {
+        println!("{:<22} {:<42} {:<9} {}", text(&entry["control"]), text(&entry["address"]), text(&entry["kind"]), text(&entry["detail"]));
+    }
+    Ok(())
+}
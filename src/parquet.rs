@@ -0,0 +1,369 @@
+//! Minimal writer of Apache Parquet files for flat tables of required string,
+//! and integer columns.
+//!
+//! Whole table is written as a single row group with one uncompressed data
+//! page of PLAIN encoded values per column. Metadata is encoded with Thrift
+//! compact protocol as the format defines.
+
+/// Magic bytes at the start, and the end of file
+const MAGIC: &[u8] = b"PAR1";
+
+/// Physical type `INT64`
+const TYPE_INT64: i32 = 2;
+
+/// Physical type `BYTE_ARRAY`
+const TYPE_BYTE_ARRAY: i32 = 6;
+
+/// Converted type `UTF8` of byte array holding text
+const CONVERTED_UTF8: i32 = 0;
+
+/// Repetition type `REQUIRED`
+const REQUIRED: i32 = 0;
+
+/// Encoding `PLAIN`
+const ENCODING_PLAIN: i32 = 0;
+
+/// Encoding `RLE`, declared for levels although required columns have none
+const ENCODING_RLE: i32 = 3;
+
+/// Compression codec `UNCOMPRESSED`
+const UNCOMPRESSED: i32 = 0;
+
+/// Page type `DATA_PAGE`
+const DATA_PAGE: i32 = 0;
+
+/// Thrift compact protocol types of fields
+const T_I32: u8 = 5;
+const T_I64: u8 = 6;
+const T_BINARY: u8 = 8;
+const T_LIST: u8 = 9;
+const T_STRUCT: u8 = 12;
+
+/// Values of a column.
+#[derive(Debug, Clone)]
+pub enum Column {
+    Int64(Vec<i64>),
+    Utf8(Vec<String>),
+}
+
+impl Column {
+    fn len(&self) -> usize {
+        match self {
+            Column::Int64(values) => values.len(),
+            Column::Utf8(values) => values.len(),
+        }
+    }
+
+    fn physical_type(&self) -> i32 {
+        match self {
+            Column::Int64(_) => TYPE_INT64,
+            Column::Utf8(_) => TYPE_BYTE_ARRAY,
+        }
+    }
+
+    /// Encode values with PLAIN encoding.
+    fn plain(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        match self {
+            Column::Int64(values) => {
+                for value in values.iter() {
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                }
+            },
+            Column::Utf8(values) => {
+                for value in values.iter() {
+                    bytes.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                    bytes.extend_from_slice(value.as_bytes());
+                }
+            },
+        }
+        bytes
+    }
+}
+
+/// Encoder of Thrift compact protocol.
+#[derive(Debug)]
+struct Compact {
+    bytes: Vec<u8>,
+
+    /// Id of the last written field of each struct being written, innermost
+    /// last, as field headers hold delta from it
+    last_ids: Vec<i16>,
+}
+
+impl Compact {
+    fn new() -> Self {
+        Self { bytes: Vec::new(), last_ids: vec![0] }
+    }
+
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.bytes.push((value as u8 & 0x7f) | 0x80);
+            value >>= 7;
+        }
+        self.bytes.push(value as u8);
+    }
+
+    fn zigzag(&mut self, value: i64) {
+        self.varint(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    fn field(&mut self, id: i16, kind: u8) {
+        let last = self.last_ids.last().copied().unwrap_or(0);
+        let delta = id - last;
+        if delta > 0 && delta <= 15 {
+            self.bytes.push(((delta as u8) << 4) | kind);
+        }
+        else {
+            self.bytes.push(kind);
+            self.zigzag(id as i64);
+        }
+        if let Some(last) = self.last_ids.last_mut() {
+            *last = id;
+        }
+    }
+
+    fn i32_field(&mut self, id: i16, value: i32) {
+        self.field(id, T_I32);
+        self.zigzag(value as i64);
+    }
+
+    fn i64_field(&mut self, id: i16, value: i64) {
+        self.field(id, T_I64);
+        self.zigzag(value);
+    }
+
+    fn binary(&mut self, value: &[u8]) {
+        self.varint(value.len() as u64);
+        self.bytes.extend_from_slice(value);
+    }
+
+    fn binary_field(&mut self, id: i16, value: &[u8]) {
+        self.field(id, T_BINARY);
+        self.binary(value);
+    }
+
+    fn list_field(&mut self, id: i16, kind: u8, size: usize) {
+        self.field(id, T_LIST);
+        if size < 15 {
+            self.bytes.push(((size as u8) << 4) | kind);
+        }
+        else {
+            self.bytes.push(0xf0 | kind);
+            self.varint(size as u64);
+        }
+    }
+
+    /// Start struct as field, or with `None` as element of list.
+    fn begin_struct(&mut self, id: Option<i16>) {
+        if let Some(id) = id {
+            self.field(id, T_STRUCT);
+        }
+        self.last_ids.push(0);
+    }
+
+    fn end_struct(&mut self) {
+        self.bytes.push(0);
+        self.last_ids.pop();
+    }
+}
+
+/// Encode table as Parquet file.
+///
+/// # Arguments
+/// * `columns` - pairs of name, and values of each column all of the same length
+/// * `created_by` - name, and version of writer recorded in file
+pub fn encode(columns: &[(&str, Column)], created_by: &str) -> Result<Vec<u8>, String> {
+    let num_rows = columns.first().map_or(0, |(_, c)| c.len());
+    if let Some((name, _)) = columns.iter().find(|(_, c)| c.len() != num_rows) {
+        let err_msg = format!("Error column '{}' has different number of rows than others", name);
+        return Err(err_msg);
+    }
+
+    let mut file = MAGIC.to_vec();
+    // offset, and size of each column chunk
+    let mut chunks: Vec<(i64, i64)> = Vec::new();
+    for (name, column) in columns.iter() {
+        let data = column.plain();
+        if data.len() > i32::MAX as usize {
+            let err_msg = format!("Error column '{}' is too large to be written as a single page; size={}", name, data.len());
+            return Err(err_msg);
+        }
+
+        let mut header = Compact::new();
+        header.i32_field(1, DATA_PAGE);
+        header.i32_field(2, data.len() as i32);
+        header.i32_field(3, data.len() as i32);
+        header.begin_struct(Some(5));
+        header.i32_field(1, num_rows as i32);
+        header.i32_field(2, ENCODING_PLAIN);
+        header.i32_field(3, ENCODING_RLE);
+        header.i32_field(4, ENCODING_RLE);
+        header.end_struct();
+        header.end_struct();
+
+        let offset = file.len() as i64;
+        file.extend_from_slice(&header.bytes);
+        file.extend_from_slice(&data);
+        chunks.push((offset, file.len() as i64 - offset));
+    }
+
+    let mut meta = Compact::new();
+    meta.i32_field(1, 1);
+
+    meta.list_field(2, T_STRUCT, columns.len() + 1);
+    meta.begin_struct(None);
+    meta.binary_field(4, b"schema");
+    meta.i32_field(5, columns.len() as i32);
+    meta.end_struct();
+    for (name, column) in columns.iter() {
+        meta.begin_struct(None);
+        meta.i32_field(1, column.physical_type());
+        meta.i32_field(3, REQUIRED);
+        meta.binary_field(4, name.as_bytes());
+        if let Column::Utf8(_) = column {
+            meta.i32_field(6, CONVERTED_UTF8);
+        }
+        meta.end_struct();
+    }
+
+    meta.i64_field(3, num_rows as i64);
+
+    meta.list_field(4, T_STRUCT, 1);
+    meta.begin_struct(None);
+    meta.list_field(1, T_STRUCT, columns.len());
+    for ((name, column), (offset, size)) in columns.iter().zip(chunks.iter()) {
+        meta.begin_struct(None);
+        meta.i64_field(2, *offset);
+        meta.begin_struct(Some(3));
+        meta.i32_field(1, column.physical_type());
+        meta.list_field(2, T_I32, 2);
+        meta.zigzag(ENCODING_PLAIN as i64);
+        meta.zigzag(ENCODING_RLE as i64);
+        meta.list_field(3, T_BINARY, 1);
+        meta.binary(name.as_bytes());
+        meta.i32_field(4, UNCOMPRESSED);
+        meta.i64_field(5, num_rows as i64);
+        meta.i64_field(6, *size);
+        meta.i64_field(7, *size);
+        meta.i64_field(9, *offset);
+        meta.end_struct();
+        meta.end_struct();
+    }
+    meta.i64_field(2, chunks.iter().map(|(_, size)| size).sum());
+    meta.i64_field(3, num_rows as i64);
+    meta.end_struct();
+
+    meta.binary_field(6, created_by.as_bytes());
+    meta.end_struct();
+
+    file.extend_from_slice(&meta.bytes);
+    file.extend_from_slice(&(meta.bytes.len() as u32).to_le_bytes());
+    file.extend_from_slice(MAGIC);
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::parquet::basic::{ConvertedType, Type as PhysicalType};
+    use ::parquet::file::reader::{FileReader, SerializedFileReader};
+    use ::parquet::record::RowAccessor;
+    use crate::workspace::TempWorkspace;
+
+    /// Read Parquet file with the reference implementation.
+    fn read(bytes: Vec<u8>) -> SerializedFileReader<std::fs::File> {
+        let workspace = TempWorkspace::new("test-parquet").unwrap();
+        let path = workspace.path().join("table.parquet");
+        std::fs::write(&path, bytes).unwrap();
+        // still readable once workspace is removed, as it's kept open
+        SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn encodes_page_header_and_footer() {
+        let file = encode(&[("n", Column::Int64(vec![5]))], "t").unwrap();
+
+        let mut expected = b"PAR1".to_vec();
+        // page header: type, sizes, and data page header of num_values, and
+        // encodings of values, and levels
+        let header = [0x15, 0x00, 0x15, 0x10, 0x15, 0x10, 0x2c, 0x15, 0x02, 0x15, 0x00, 0x15, 0x06, 0x15, 0x06, 0x00, 0x00];
+        expected.extend_from_slice(&header);
+        expected.extend_from_slice(&5i64.to_le_bytes());
+
+        // footer: version, schema of root, and column
+        let mut footer = vec![0x15, 0x02, 0x19, 0x2c];
+        footer.extend_from_slice(&[0x48, 0x06]);
+        footer.extend_from_slice(b"schema");
+        footer.extend_from_slice(&[0x15, 0x02, 0x00]);
+        footer.extend_from_slice(&[0x15, 0x04, 0x25, 0x00, 0x18, 0x01, b'n', 0x00]);
+        // num_rows
+        footer.extend_from_slice(&[0x16, 0x02]);
+        // row group of column chunk at offset 4 of 25 bytes
+        footer.extend_from_slice(&[0x19, 0x1c, 0x19, 0x1c, 0x26, 0x08, 0x1c]);
+        footer.extend_from_slice(&[0x15, 0x04, 0x19, 0x25, 0x00, 0x06, 0x19, 0x18, 0x01, b'n', 0x15, 0x00]);
+        footer.extend_from_slice(&[0x16, 0x02, 0x16, 0x32, 0x16, 0x32, 0x26, 0x08, 0x00, 0x00]);
+        // total_byte_size, and num_rows of row group
+        footer.extend_from_slice(&[0x16, 0x32, 0x16, 0x02, 0x00]);
+        // created_by
+        footer.extend_from_slice(&[0x28, 0x01, b't', 0x00]);
+        expected.extend_from_slice(&footer);
+        expected.extend_from_slice(&(footer.len() as u32).to_le_bytes());
+        expected.extend_from_slice(b"PAR1");
+
+        assert_eq!(file, expected);
+    }
+
+    #[test]
+    fn round_trips_through_reference_reader() {
+        let addresses: Vec<String> = vec!["0xaa".to_owned(), String::new(), "名前".to_owned()];
+        let file = encode(&[
+            ("block", Column::Int64(vec![0, -1, i64::MAX])),
+            ("address", Column::Utf8(addresses.clone())),
+        ], "tracpls test").unwrap();
+
+        let reader = read(file);
+        let meta = reader.metadata();
+        assert_eq!(meta.file_metadata().num_rows(), 3);
+        assert_eq!(meta.file_metadata().created_by(), Some("tracpls test"));
+        assert_eq!(meta.num_row_groups(), 1);
+        let columns = meta.file_metadata().schema_descr().columns().to_vec();
+        assert_eq!(columns.iter().map(|c| c.name()).collect::<Vec<&str>>(), vec!["block", "address"]);
+        assert_eq!(columns[0].physical_type(), PhysicalType::INT64);
+        assert_eq!(columns[1].physical_type(), PhysicalType::BYTE_ARRAY);
+        assert_eq!(columns[1].converted_type(), ConvertedType::UTF8);
+        let row_group = meta.row_group(0);
+        assert_eq!(row_group.num_rows(), 3);
+        assert_eq!(row_group.total_byte_size(), row_group.columns().iter().map(|c| c.compressed_size()).sum::<i64>());
+
+        let rows: Vec<(i64, String)> = reader.get_row_iter(None).unwrap()
+            .map(|row| {
+                let row = row.unwrap();
+                (row.get_long(0).unwrap(), row.get_string(1).unwrap().clone())
+            })
+            .collect();
+        assert_eq!(rows, vec![(0, addresses[0].clone()), (-1, addresses[1].clone()), (i64::MAX, addresses[2].clone())]);
+    }
+
+    #[test]
+    fn round_trips_many_columns() {
+        // lists of 15 elements, or more have size in a varint of their own
+        let names: Vec<String> = (0..20).map(|i| format!("c{}", i)).collect();
+        let columns: Vec<(&str, Column)> = names.iter().enumerate().map(|(i, n)| (n.as_str(), Column::Int64(vec![i as i64, 300]))).collect();
+        let reader = read(encode(&columns, "t").unwrap());
+        assert_eq!(reader.metadata().file_metadata().schema_descr().num_columns(), 20);
+        let rows: Vec<Vec<i64>> = reader.get_row_iter(None).unwrap()
+            .map(|row| {
+                let row = row.unwrap();
+                (0..20).map(|i| row.get_long(i).unwrap()).collect()
+            })
+            .collect();
+        assert_eq!(rows, vec![(0..20).collect::<Vec<i64>>(), vec![300; 20]]);
+    }
+
+    #[test]
+    fn rejects_columns_of_different_lengths() {
+        let res = encode(&[("a", Column::Int64(vec![1, 2])), ("b", Column::Utf8(vec!["x".to_owned()]))], "t");
+        assert!(res.is_err_and(|e| e.contains("'b'")));
+    }
+}
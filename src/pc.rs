@@ -0,0 +1,263 @@
+//! `pc` command: map program counter of deployed bytecode e.g. from revert
+//! trace back to source file, and line, or annotate disassembly with source
+//! lines.
+//!
+//! Verified source is recompiled with its verification settings (see
+//! `build`), and source map of its runtime bytecode is decompressed into an
+//! entry per instruction. Program counter is resolved into index of
+//! instruction by disassembling deployed bytecode, which matches recompiled
+//! one as long as verification is exact.
+
+use crate::abi;
+use crate::build;
+use crate::bytecode::{self, Instruction};
+use crate::explorer;
+use crate::solc::{self, SourceMapEntry};
+use crate::source::SourceFile;
+use crate::TargetArgs;
+use clap::Args;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+#[derive(Debug, Args)]
+pub struct PcArgs {
+    #[clap(flatten)]
+    pub target: TargetArgs,
+
+    /// Program counter to map, in decimal, or hex with '0x' prefix
+    #[clap(long="pc", required_unless_present="annotate", conflicts_with="annotate")]
+    pub pc: Option<String>,
+
+    /// Print disassembly of deployed bytecode annotated with source lines
+    /// instead
+    #[clap(long="annotate", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub annotate: bool,
+
+    /// Path to solc binary to use instead of finding one matching the verified
+    /// compiler version
+    #[clap(long="solc", required=false)]
+    pub solc: Option<String>,
+
+    /// Output as JSON instead of human readable text
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+}
+
+/// Source location of instruction.
+struct Location<'a> {
+    file: &'a SourceFile,
+    line: usize,
+    column: usize,
+    entry: &'a SourceMapEntry,
+}
+
+impl Location<'_> {
+    /// Get source code of the first line of range, trimmed.
+    fn snippet(&self) -> &str {
+        self.file.content.lines().nth(self.line - 1).unwrap_or("").trim()
+    }
+
+    /// Describe kind of jump, or empty for regular instruction.
+    fn jump(&self) -> &'static str {
+        match self.entry.jump {
+            'i' => "jump into function",
+            'o' => "jump out of function",
+            _ => "",
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "file": self.file.path,
+            "line": self.line,
+            "column": self.column,
+            "start": self.entry.start,
+            "length": self.entry.length,
+            "jump": self.entry.jump.to_string(),
+            "snippet": self.snippet(),
+        })
+    }
+}
+
+/// Resolve source map entry into source location, or `None` if instruction
+/// is generated by compiler, not attributed to any of source files.
+///
+/// # Arguments
+/// * `entry` - source map entry
+/// * `files` - source files by their id
+fn locate<'a>(entry: &'a SourceMapEntry, files: &HashMap<i64, &'a SourceFile>) -> Option<Location<'a>> {
+    let file = *files.get(&entry.file)?;
+    let (line, column) = solc::offset_to_line_col(&file.content, entry.start);
+    Some(Location { file, line, column, entry })
+}
+
+/// Parse program counter in decimal, or hex with `0x` prefix.
+///
+/// # Arguments
+/// * `pc` - program counter as text
+fn parse_pc(pc: &str) -> Result<usize, String> {
+    let parsed = match pc.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => pc.parse::<usize>().ok(),
+    };
+    match parsed {
+        Some(res) => Ok(res),
+        None => {
+            let err_msg = format!("Error invalid value for --pc; '{}' is not a number", pc);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Print location of a single program counter.
+///
+/// # Arguments
+/// * `pc` - program counter
+/// * `instructions` - instructions of deployed bytecode
+/// * `entries` - source map entries
+/// * `files` - source files by their id
+/// * `as_json` - whether to output as JSON
+fn print_pc(pc: usize, instructions: &[Instruction], entries: &[SourceMapEntry], files: &HashMap<i64, &SourceFile>, as_json: bool) -> Result<(), String> {
+    let index = match instructions.iter().position(|i| i.offset + i.data.len() >= pc) {
+        Some(index) if instructions[index].offset == pc => index,
+        Some(index) => {
+            let err_msg = format!("Error pc {} is inside immediate data of {} at {}", pc, instructions[index].text(), instructions[index].offset);
+            return Err(err_msg);
+        },
+        None => {
+            let err_msg = format!("Error pc {} is out of deployed bytecode", pc);
+            return Err(err_msg);
+        }
+    };
+    let instruction = &instructions[index];
+    let location = match entries.get(index) {
+        Some(entry) => locate(entry, files),
+        None => {
+            let err_msg = format!("Error pc {} is out of source map; it's likely in metadata appended by compiler", pc);
+            return Err(err_msg);
+        }
+    };
+
+    if as_json {
+        let out = json!({
+            "pc": pc,
+            "instruction": index,
+            "opcode": instruction.text(),
+            "location": location.as_ref().map(|l| l.to_json()),
+        });
+        match serde_json::to_string_pretty(&out) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing location to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+        return Ok(());
+    }
+
+    println!("pc {} ({:#06x}) is instruction #{} {}", pc, pc, index, instruction.text());
+    match location {
+        Some(l) => {
+            println!("{}:{}:{} {}", l.file.path, l.line, l.column, l.jump());
+            println!("    {}", l.snippet());
+        },
+        None => println!("generated by compiler, not attributed to any source file"),
+    }
+    Ok(())
+}
+
+/// Print disassembly annotated with source line whenever it changes.
+///
+/// # Arguments
+/// * `instructions` - instructions of deployed bytecode
+/// * `entries` - source map entries
+/// * `files` - source files by their id
+/// * `as_json` - whether to output as JSON
+fn print_annotated(instructions: &[Instruction], entries: &[SourceMapEntry], files: &HashMap<i64, &SourceFile>, as_json: bool) -> Result<(), String> {
+    // bytes past source map are metadata appended by compiler
+    let mapped = &instructions[..entries.len().min(instructions.len())];
+
+    if as_json {
+        let out: Vec<Value> = mapped.iter().zip(entries.iter()).map(|(i, entry)| json!({
+            "pc": i.offset,
+            "opcode": i.text(),
+            "location": locate(entry, files).map(|l| l.to_json()),
+        })).collect();
+        match serde_json::to_string_pretty(&out) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing disassembly to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+        return Ok(());
+    }
+
+    let mut last: Option<Option<(String, usize)>> = None;
+    for (instruction, entry) in mapped.iter().zip(entries.iter()) {
+        let location = locate(entry, files);
+        let current = location.as_ref().map(|l| (l.file.path.clone(), l.line));
+        if last.as_ref() != Some(&current) {
+            match location.as_ref() {
+                Some(l) => println!("; {}:{}: {}", l.file.path, l.line, l.snippet()),
+                None => println!("; generated by compiler"),
+            }
+            last = Some(current);
+        }
+        println!("{:#06x} {}", instruction.offset, instruction.text());
+    }
+    Ok(())
+}
+
+/// Execute `pc` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &PcArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.target.chain)?;
+    let pc = match args.pc.as_ref() {
+        Some(pc) => Some(parse_pc(pc)?),
+        None => None,
+    };
+
+    let built = build::build(&args.target, args.solc.as_ref(), json!({
+        "*": { "*": ["evm.deployedBytecode.object", "evm.deployedBytecode.sourceMap"] }
+    }))?;
+    let contract = built.output["contracts"].as_object()
+        .and_then(|files| files.values().find_map(|f| f.get(&built.contract_name)));
+    let deployed = match contract {
+        Some(res) => &res["evm"]["deployedBytecode"],
+        None => {
+            let err_msg = format!("Error contract '{}' not found in compiled output", built.contract_name);
+            return Err(err_msg);
+        }
+    };
+    let entries = solc::parse_source_map(deployed["sourceMap"].as_str().unwrap_or(""));
+
+    // source files by id assigned by compiler
+    let mut files: HashMap<i64, &SourceFile> = HashMap::new();
+    for (path, unit) in built.output["sources"].as_object().into_iter().flatten() {
+        if let (Some(id), Some(file)) = (unit["id"].as_i64(), built.files.iter().find(|f| f.path == *path)) {
+            files.insert(id, file);
+        }
+    }
+
+    let code = explorer::get_code(chain, &args.target.address)?;
+    if code.is_empty() {
+        let err_msg = format!("Error no contract code at {}", args.target.address);
+        return Err(err_msg);
+    }
+    let instructions = bytecode::disassemble(&code);
+
+    // immutables differ in value only, so layout of instructions still matches
+    let recompiled = abi::from_hex(deployed["object"].as_str().unwrap_or("")).unwrap_or_default();
+    let recompiled_count = bytecode::disassemble(bytecode::strip_metadata(&recompiled)).len();
+    if recompiled_count != bytecode::disassemble(bytecode::strip_metadata(&code)).len() {
+        eprintln!("Warning: deployed bytecode doesn't match recompiled one; locations may be off");
+    }
+
+    match pc {
+        Some(pc) => print_pc(pc, &instructions, &entries, &files, args.json),
+        None => print_annotated(&instructions, &entries, &files, args.json),
+    }
+}
@@ -0,0 +1,240 @@
+//! Telemetry of where time, and memory of the run went, printed to stderr at
+//! the end with `--perf-report`, to tune `--jobs`, and `--write-jobs` of large
+//! batches, and to spot regressions between versions.
+//!
+//! Time is tallied by phase; waiting for responses of API platform, and node,
+//! decoding responses, transforming source code e.g. cleaning CR/LF, and
+//! formatting, and writing files. Phases run on worker threads concurrently
+//! are summed across them, so they may add up to more than wall time.
+
+use serde_json::json;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Phase of the run which time is tallied for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Phase {
+    /// Waiting for responses of API platform, and node
+    ApiWait,
+
+    /// Parsing responses, and decoding source code from them
+    Decode,
+
+    /// Cleaning, canonicalizing, and formatting source code
+    Transform,
+
+    /// Writing files, and creating their directories
+    Write,
+}
+
+/// Every phase in order of report
+const PHASES: [Phase; 4] = [Phase::ApiWait, Phase::Decode, Phase::Transform, Phase::Write];
+
+impl Phase {
+    /// Label of phase in report.
+    fn label(self) -> &'static str {
+        match self {
+            Phase::ApiWait => "API wait",
+            Phase::Decode => "decode",
+            Phase::Transform => "transform",
+            Phase::Write => "write",
+        }
+    }
+}
+
+/// Whether or not telemetry is recorded
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Nanoseconds spent in each phase, indexed as of `Phase`
+static PHASE_NANOS: [AtomicU64; 4] = [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)];
+
+/// Number of requests sent to API platform, and node
+static REQUESTS: AtomicU64 = AtomicU64::new(0);
+
+/// Bytes of requests sent
+static BYTES_SENT: AtomicU64 = AtomicU64::new(0);
+
+/// Bytes of responses received
+static BYTES_RECEIVED: AtomicU64 = AtomicU64::new(0);
+
+/// Verification metadata served from cache, locally or remotely
+static CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+
+/// Verification metadata which had to be fetched from API platform with cache
+/// enabled
+static CACHE_MISSES: AtomicU64 = AtomicU64::new(0);
+
+/// Start recording telemetry to be reported.
+pub fn start_recording() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Whether or not telemetry is recorded, so callers can skip measuring.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Time spent in phase from its creation until dropped, added to total of
+/// the phase then.
+pub struct PhaseTimer {
+    phase: Phase,
+    started: Option<Instant>,
+}
+
+impl Drop for PhaseTimer {
+    fn drop(&mut self) {
+        if let Some(started) = self.started {
+            PHASE_NANOS[self.phase as usize].fetch_add(started.elapsed().as_nanos() as u64, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Start timing phase until returned timer is dropped e.g. at the end of
+/// scope. Nothing is measured unless recording.
+///
+/// # Arguments
+/// * `phase` - phase
+pub fn time(phase: Phase) -> PhaseTimer {
+    PhaseTimer { phase, started: is_enabled().then(Instant::now) }
+}
+
+/// Record request sent to API platform, or node, and its response.
+///
+/// # Arguments
+/// * `elapsed` - time from sending request until whole response is read
+/// * `sent` - size of request in bytes
+/// * `received` - size of response body in bytes
+pub fn record_request(elapsed: Duration, sent: usize, received: usize) {
+    if !is_enabled() {
+        return;
+    }
+    PHASE_NANOS[Phase::ApiWait as usize].fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    REQUESTS.fetch_add(1, Ordering::Relaxed);
+    BYTES_SENT.fetch_add(sent as u64, Ordering::Relaxed);
+    BYTES_RECEIVED.fetch_add(received as u64, Ordering::Relaxed);
+}
+
+/// Record lookup of cache.
+///
+/// # Arguments
+/// * `hit` - whether it was served from cache
+pub fn record_cache(hit: bool) {
+    if !is_enabled() {
+        return;
+    }
+    if hit {
+        CACHE_HITS.fetch_add(1, Ordering::Relaxed);
+    }
+    else {
+        CACHE_MISSES.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Get peak resident memory of the process in bytes so far, or `None` if not
+/// available on this platform.
+fn peak_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmHWM:"))?;
+    let kb: u64 = line.trim_start_matches("VmHWM:").trim().trim_end_matches("kB").trim().parse().ok()?;
+    Some(kb * 1024)
+}
+
+/// Format size in bytes for humans e.g. `12.4 MB`.
+///
+/// # Arguments
+/// * `bytes` - size in bytes
+fn format_bytes(bytes: u64) -> String {
+    let units = ["KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = "B";
+    for u in units.iter() {
+        if size < 1000.0 {
+            break;
+        }
+        size /= 1000.0;
+        unit = u;
+    }
+    if unit == "B" {
+        format!("{} B", bytes)
+    }
+    else {
+        format!("{:.1} {}", size, unit)
+    }
+}
+
+/// Format duration for humans; milliseconds under a second e.g. `85ms`,
+/// otherwise seconds e.g. `12.3s`.
+///
+/// # Arguments
+/// * `seconds` - duration in seconds
+fn format_seconds(seconds: f64) -> String {
+    if seconds < 1.0 {
+        format!("{:.0}ms", seconds * 1000.0)
+    }
+    else {
+        format!("{:.1}s", seconds)
+    }
+}
+
+/// Print telemetry recorded so far to stderr, and emit it as `perf` progress
+/// event.
+///
+/// # Arguments
+/// * `wall` - wall time of the run
+pub fn print_report(wall: Duration) {
+    let seconds = |phase: Phase| PHASE_NANOS[phase as usize].load(Ordering::Relaxed) as f64 / 1e9;
+    let requests = REQUESTS.load(Ordering::Relaxed);
+    let sent = BYTES_SENT.load(Ordering::Relaxed);
+    let received = BYTES_RECEIVED.load(Ordering::Relaxed);
+    let hits = CACHE_HITS.load(Ordering::Relaxed);
+    let misses = CACHE_MISSES.load(Ordering::Relaxed);
+    let hit_rate = (hits + misses > 0).then(|| hits as f64 / (hits + misses) as f64);
+    let peak = peak_memory();
+
+    crate::progress::emit("perf", json!({
+        "wallMs": wall.as_millis() as u64,
+        "apiWaitMs": (seconds(Phase::ApiWait) * 1000.0) as u64,
+        "decodeMs": (seconds(Phase::Decode) * 1000.0) as u64,
+        "transformMs": (seconds(Phase::Transform) * 1000.0) as u64,
+        "writeMs": (seconds(Phase::Write) * 1000.0) as u64,
+        "requests": requests,
+        "bytesSent": sent,
+        "bytesReceived": received,
+        "cacheHits": hits,
+        "cacheMisses": misses,
+        "peakMemoryBytes": peak,
+    }));
+
+    eprintln!("perf report:");
+    eprintln!("  {:<12} {}", "wall time", format_seconds(wall.as_secs_f64()));
+    for phase in PHASES.iter() {
+        if *phase == Phase::ApiWait && requests > 0 {
+            eprintln!("  {:<12} {} in {} request(s), {} on average", phase.label(), format_seconds(seconds(*phase)), requests, format_seconds(seconds(*phase) / requests as f64));
+        }
+        else {
+            eprintln!("  {:<12} {}", phase.label(), format_seconds(seconds(*phase)));
+        }
+    }
+    eprintln!("  {:<12} {} received, {} sent", "transferred", format_bytes(received), format_bytes(sent));
+    match hit_rate {
+        Some(rate) => eprintln!("  {:<12} {} hit(s), {} miss(es); {:.1}% hit rate", "cache", hits, misses, rate * 100.0),
+        None => eprintln!("  {:<12} not used", "cache"),
+    }
+    match peak {
+        Some(bytes) => eprintln!("  {:<12} {}", "peak memory", format_bytes(bytes)),
+        None => eprintln!("  {:<12} not available on this platform", "peak memory"),
+    }
+    if PHASES.iter().map(|p| seconds(*p)).sum::<f64>() > wall.as_secs_f64() {
+        eprintln!("Phases overlap across worker threads, thus add up to more than wall time.");
+    }
+
+    // hint which knob to turn on the phase taking the most time
+    let slowest = PHASES.iter().copied().max_by(|a, b| seconds(*a).total_cmp(&seconds(*b)));
+    match slowest.filter(|p| seconds(*p) > 0.0) {
+        Some(Phase::ApiWait) if hits + misses == 0 => eprintln!("Most time is spent waiting for API platform; consider --cache-dir, or raising --jobs within rate limit."),
+        Some(Phase::ApiWait) => eprintln!("Most time is spent waiting for API platform; consider raising --jobs within rate limit."),
+        Some(Phase::Write) => eprintln!("Most time is spent writing files; consider raising --write-jobs."),
+        Some(phase) => eprintln!("Most time is spent in {}; consider raising --jobs up to number of CPU cores.", phase.label()),
+        None => (),
+    }
+}
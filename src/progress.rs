@@ -0,0 +1,138 @@
+//! Machine-readable progress events for tools wrapping tracpls e.g. GUIs and
+//! orchestrators, emitted to stderr as one JSON object per line with
+//! `--progress ndjson`.
+//!
+//! Events are
+//! * `fetch_start` - `address`, `chain`
+//! * `fetch_done` - `address`, `chain`, `files`
+//! * `write_done` - `path`, `unchanged`
+//! * `skipped` - `address`, `reason`
+//! * `error` - `address` if known, `message`
+//! * `perf` - telemetry of the run with `--perf-report`, see `perf`
+//!
+//! Outcomes of contracts are tallied from events regardless of format, for
+//! summary of the run.
+//!
+//! Status output of every command e.g. paths of files written is suppressed
+//! while quiet e.g. with `--ci`, as with `--silence` of the command.
+
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Format of progress events.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ProgressFormat {
+    /// No progress events
+    None,
+
+    /// Newline-delimited JSON
+    Ndjson,
+}
+
+/// Format of progress events of the running process
+static FORMAT: Mutex<ProgressFormat> = Mutex::new(ProgressFormat::None);
+
+/// Whether status output of the running process is suppressed
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Outcomes of contracts tallied from events.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Tally {
+    pub fetched: usize,
+    pub skipped: usize,
+
+    /// Contracts which failed
+    pub failed: usize,
+
+    /// All errors including ones not of particular contract
+    pub errors: usize,
+}
+
+/// Tally of the running process
+static TALLY: Mutex<Tally> = Mutex::new(Tally { fetched: 0, skipped: 0, failed: 0, errors: 0 });
+
+/// Get tally of outcomes of contracts so far.
+pub fn tally() -> Tally {
+    TALLY.lock().map_or(Tally::default(), |t| *t)
+}
+
+/// Parse value of `--progress` flag option.
+///
+/// # Arguments
+/// * `value` - value of flag option
+pub fn parse_progress(value: &str) -> Result<ProgressFormat, String> {
+    match value.to_lowercase().as_str() {
+        "none" => Ok(ProgressFormat::None),
+        "ndjson" => Ok(ProgressFormat::Ndjson),
+        _ => Err("Error invalid value for --progress.\nPossible values are 'none', or 'ndjson'.".to_owned()),
+    }
+}
+
+/// Set format of progress events of the running process.
+///
+/// # Arguments
+/// * `format` - format of progress events
+pub fn set_format(format: ProgressFormat) {
+    if let Ok(mut f) = FORMAT.lock() {
+        *f = format;
+    }
+}
+
+/// Set whether status output of the running process is suppressed
+/// regardless of `--silence` of command.
+///
+/// # Arguments
+/// * `quiet` - whether or not to suppress status output
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Whether status output is suppressed, either by `--silence` of command, or
+/// for the running process.
+///
+/// # Arguments
+/// * `silence` - value of `--silence` of command
+pub fn silenced(silence: bool) -> bool {
+    silence || QUIET.load(Ordering::Relaxed)
+}
+
+/// Emit progress event if enabled.
+///
+/// # Arguments
+/// * `event` - name of event
+/// * `fields` - fields of event as JSON object
+pub fn emit(event: &str, fields: Value) {
+    if let Ok(mut tally) = TALLY.lock() {
+        match event {
+            "fetch_done" => tally.fetched += 1,
+            "skipped" => tally.skipped += 1,
+            "error" => {
+                tally.errors += 1;
+                if !fields["address"].is_null() {
+                    tally.failed += 1;
+                }
+            },
+            _ => (),
+        }
+    }
+
+    if FORMAT.lock().map_or(true, |f| *f == ProgressFormat::None) {
+        return;
+    }
+
+    let mut object = json!({ "event": event });
+    if let (Some(object), Value::Object(fields)) = (object.as_object_mut(), fields) {
+        object.extend(fields);
+    }
+    eprintln!("{}", object);
+}
+
+/// Emit `error` event.
+///
+/// # Arguments
+/// * `address` - contract address if known
+/// * `message` - error message
+pub fn error(address: Option<&str>, message: &str) {
+    emit("error", json!({ "address": address, "message": message }));
+}
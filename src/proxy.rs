@@ -0,0 +1,99 @@
+//! Resolution of implementation behind proxy contract from well-known storage
+//! slots, and explorer's own proxy detection.
+
+use crate::backend::Chain;
+use crate::explorer;
+
+/// Storage slot of implementation as per EIP-1967
+const EIP1967_IMPLEMENTATION_SLOT: &str = "0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc";
+
+/// Storage slot of admin as per EIP-1967
+const EIP1967_ADMIN_SLOT: &str = "0xb53127684a568b3173ae13b9f8a6016e243e63b6e8ee1178d6a717850b5d6103";
+
+/// Storage slot of beacon as per EIP-1967
+const EIP1967_BEACON_SLOT: &str = "0xa3f0ad74e5423aebfd80d3ef4346578335a9a72aeee59ff6cb3582b35133d50";
+
+/// Storage slot of implementation used by OpenZeppelin proxies prior to
+/// EIP-1967
+const ZEPPELINOS_IMPLEMENTATION_SLOT: &str = "0x7050c9e0f4ca769c69bd3a8ef740bc37934f8e2c036a5a723fd8ee048ed3f8c3";
+
+/// Storage slot of implementation as per EIP-1822 (UUPS)
+const EIP1822_PROXIABLE_SLOT: &str = "0xc5f16f0fcc639fa48a6947836d9850f504798523bf8c9a3a87d5876cf622bcf7";
+
+/// Selector of `implementation()`
+const IMPLEMENTATION_SELECTOR: &str = "0x5c60da1b";
+
+/// Implementation behind proxy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Implementation {
+    /// Address in lowercase with `0x` prefix
+    pub address: String,
+
+    /// How it was found e.g. `eip-1967`, `eip-1967 beacon`, or `explorer`
+    pub source: String,
+}
+
+/// Get address held by 32-byte word in lowercase with `0x` prefix, or `None`
+/// if it's zero.
+///
+/// # Arguments
+/// * `word` - 32-byte word
+pub fn word_to_address(word: &[u8]) -> Option<String> {
+    if word.len() < 20 || word[word.len() - 20..].iter().all(|b| *b == 0) {
+        return None;
+    }
+    Some(format!("0x{}", crate::abi::to_hex(&word[word.len() - 20..])))
+}
+
+/// Resolve implementation of proxy contract, or `None` if it doesn't look
+/// like one.
+///
+/// Well-known storage slots are tried first, then explorer's own detection
+/// for proxies it has been told about unless reading state of past block.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - proxy contract address
+pub fn resolve_implementation(chain: &Chain, address: &str) -> Result<Option<Implementation>, String> {
+    let slots = [
+        (EIP1967_IMPLEMENTATION_SLOT, "eip-1967"),
+        (EIP1822_PROXIABLE_SLOT, "eip-1822"),
+        (ZEPPELINOS_IMPLEMENTATION_SLOT, "zeppelinos"),
+    ];
+    for (slot, source) in slots.iter() {
+        if let Some(implementation) = word_to_address(&explorer::get_storage_at(chain, address, slot)?) {
+            return Ok(Some(Implementation { address: implementation, source: source.to_string() }));
+        }
+    }
+
+    if let Some(beacon) = word_to_address(&explorer::get_storage_at(chain, address, EIP1967_BEACON_SLOT)?) {
+        return match word_to_address(&explorer::call(chain, &beacon, IMPLEMENTATION_SELECTOR)?) {
+            Some(implementation) => Ok(Some(Implementation { address: implementation, source: "eip-1967 beacon".to_owned() })),
+            None => {
+                let err_msg = format!("Error beacon {} of proxy {} returned no implementation", beacon, address);
+                Err(err_msg)
+            }
+        };
+    }
+
+    // explorer only knows the current implementation, not as of past block
+    if explorer::block_tag() != "latest" {
+        return Ok(None);
+    }
+    let meta = explorer::get_source_meta(chain, address)?;
+    let implementation = meta["Implementation"].as_str().unwrap_or("").trim().to_lowercase();
+    if meta["Proxy"].as_str() == Some("1") && implementation.len() == 42 {
+        return Ok(Some(Implementation { address: implementation, source: "explorer".to_owned() }));
+    }
+    Ok(None)
+}
+
+/// Get admin of proxy contract allowed to upgrade it as per EIP-1967, or
+/// `None` if there is none.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - proxy contract address
+pub fn resolve_admin(chain: &Chain, address: &str) -> Result<Option<String>, String> {
+    Ok(word_to_address(&explorer::get_storage_at(chain, address, EIP1967_ADMIN_SLOT)?))
+}
@@ -0,0 +1,87 @@
+//! Usage of requests to API platforms, and remaining quota where API platform
+//! exposes it through `getapilimit` endpoint.
+
+use crate::backend::Chain;
+use crate::explorer;
+
+/// Quota of API key as reported by API platform.
+#[derive(Debug, Clone)]
+pub struct Quota {
+    /// Credits used in the current interval
+    pub used: u64,
+
+    /// Credits still available in the current interval
+    pub available: u64,
+
+    /// Credits of each interval
+    pub limit: u64,
+
+    /// Interval which quota is reset e.g. `daily`
+    pub interval: String,
+}
+
+/// Get quota of API key from API platform. It fails if API platform doesn't
+/// expose it.
+///
+/// # Arguments
+/// * `chain` - chain
+pub fn get_quota(chain: &Chain) -> Result<Quota, String> {
+    let result = explorer::query(chain, &[
+        ("module", "getapilimit"),
+        ("action", "getapilimit"),
+    ])?;
+
+    // numbers may come either as number or string
+    let number = |key: &str| result[key].as_u64().or_else(|| result[key].as_str().and_then(|v| v.parse::<u64>().ok()));
+    match (number("creditsUsed"), number("creditsAvailable"), number("creditLimit")) {
+        (Some(used), Some(available), Some(limit)) => Ok(Quota {
+            used,
+            available,
+            limit,
+            interval: result["limitInterval"].as_str().unwrap_or("daily").to_owned(),
+        }),
+        _ => {
+            let err_msg = format!("Error API platform of {} doesn't expose quota", chain.name);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Print number of requests sent to each API platform during this run, and
+/// its remaining quota if exposed, to stderr.
+pub fn print_summary() {
+    let counts = explorer::request_counts();
+    if counts.is_empty() {
+        eprintln!("API requests: none");
+        return;
+    }
+
+    for (name, count) in counts.iter() {
+        let quota = match crate::backend::find(name).map(get_quota) {
+            Some(Ok(q)) => format!("{} of {} credits used {}, {} available", q.used, q.limit, q.interval, q.available),
+            _ => "quota not exposed".to_owned(),
+        };
+        eprintln!("API requests to {}: {} ({})", name, count, quota);
+    }
+}
+
+/// Warn on stderr if the projected number of requests would exceed remaining
+/// quota. Remaining quota is taken from API platform if exposed, otherwise
+/// from the supplied daily limit.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `projected` - projected number of requests
+/// * `daily_limit` - daily limit of requests known by user
+pub fn warn_if_exceeding(chain: &Chain, projected: u64, daily_limit: Option<u64>) {
+    let available = match get_quota(chain) {
+        Ok(q) => Some(q.available),
+        Err(_) => daily_limit,
+    };
+
+    if let Some(available) = available {
+        if projected > available {
+            eprintln!("Warning: about {} request(s) are needed but only {} remain in quota of {}; the run will likely hit the limit", projected, available, chain.name);
+        }
+    }
+}
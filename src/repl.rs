@@ -0,0 +1,293 @@
+//! `repl` command: interactive prompt for investigating contracts which keeps
+//! context across commands; current chain, and current contract whose source
+//! code, and ABI are fetched once then reused for the rest of session.
+//!
+//! Commands are read line by line from stdin, so they can also be piped in.
+//! Failing command reports its error, and the session goes on.
+
+use crate::abi::{self, Abi};
+use crate::backend::Chain;
+use crate::explorer;
+use crate::source::{self, ContractSource};
+use clap::Args;
+use regex::RegexBuilder;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+#[derive(Debug, Args)]
+pub struct ReplArgs {
+    /// Chain to start with, by name, or chain id
+    #[clap(long="chain", short='c', required=false, default_value="bsc")]
+    pub chain: String,
+
+    /// Contract to open at start
+    #[clap(long="address", short='a', required=false)]
+    pub address: Option<String>,
+}
+
+/// Help text listing commands
+const HELP: &str = "\
+chain [CHAIN]              show, or switch current chain
+open ADDRESS               switch current contract
+info                       show contract name, compiler, and name tag
+files                      list source files
+cat PATH                   print source file
+abi                        list functions, events, and errors
+grep PATTERN               search source files case-insensitively
+call FUNCTION [ARGS...]    call function read-only e.g. 'call balanceOf 0x...'
+help                       show this help
+exit                       end session";
+
+/// Contract fetched during session, each part fetched when first needed.
+#[derive(Debug, Default)]
+struct Contract {
+    source: Option<ContractSource>,
+    abi: Option<Abi>,
+}
+
+/// State of session.
+struct Session {
+    chain: &'static Chain,
+    address: Option<String>,
+
+    /// Fetched contracts keyed by chain name, and address
+    contracts: HashMap<(String, String), Contract>,
+}
+
+impl Session {
+    /// Get current address, or error if no contract is open.
+    fn address(&self) -> Result<String, String> {
+        match self.address.as_ref() {
+            Some(res) => Ok(res.clone()),
+            None => Err("Error no contract is open; use 'open ADDRESS' first".to_owned()),
+        }
+    }
+
+    /// Get current contract to be filled.
+    fn contract(&mut self) -> Result<&mut Contract, String> {
+        let key = (self.chain.name.clone(), self.address()?);
+        Ok(self.contracts.entry(key).or_default())
+    }
+
+    /// Get source code of current contract, fetching it if not yet.
+    fn source(&mut self) -> Result<&ContractSource, String> {
+        let (chain, address) = (self.chain, self.address()?);
+        let contract = self.contract()?;
+        let source = match contract.source.take() {
+            Some(res) => res,
+            None => source::fetch_sources(chain, &address)?,
+        };
+        Ok(contract.source.insert(source))
+    }
+
+    /// Get ABI of current contract, fetching it if not yet.
+    fn abi(&mut self) -> Result<&Abi, String> {
+        let (chain, address) = (self.chain, self.address()?);
+        let contract = self.contract()?;
+        let abi = match contract.abi.take() {
+            Some(res) => res,
+            None => abi::fetch_abi(chain, &address)?,
+        };
+        Ok(contract.abi.insert(abi))
+    }
+
+    /// Prompt showing context e.g. `bsc 0xabc> `.
+    fn prompt(&self) -> String {
+        match self.address.as_ref() {
+            Some(address) => format!("{} {}> ", self.chain.name, address),
+            None => format!("{}> ", self.chain.name),
+        }
+    }
+}
+
+/// Show contract name, compiler, and name tag of current contract.
+///
+/// # Arguments
+/// * `session` - session
+fn info(session: &mut Session) -> Result<(), String> {
+    let (chain, address) = (session.chain, session.address()?);
+    let source = session.source()?;
+    println!("{} at {} on {}", source.contract_name, address, chain.name);
+    println!("compiler {}, {} file(s)", source.compiler_version, source.files.len());
+    if let Some(tag) = explorer::get_name_tag(chain, &address) {
+        println!("name tag {}", tag.describe());
+    }
+    Ok(())
+}
+
+/// Print ABI of current contract.
+///
+/// # Arguments
+/// * `session` - session
+fn print_abi(session: &mut Session) -> Result<(), String> {
+    let abi = session.abi()?;
+    for f in abi.functions() {
+        println!("function {} {}", f.declaration(), f.mutability());
+    }
+    for e in abi.events() {
+        println!("event {}", e.declaration());
+    }
+    for e in abi.errors() {
+        println!("error {}", e.declaration());
+    }
+    Ok(())
+}
+
+/// Search source files of current contract for pattern.
+///
+/// # Arguments
+/// * `session` - session
+/// * `pattern` - regular expression
+fn grep(session: &mut Session, pattern: &str) -> Result<(), String> {
+    let regex = match RegexBuilder::new(pattern).case_insensitive(true).build() {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error invalid pattern; err={}", e);
+            return Err(err_msg);
+        }
+    };
+    for f in session.source()?.files.iter() {
+        for (i, line) in f.content.lines().enumerate().filter(|(_, l)| regex.is_match(l)) {
+            println!("{}:{}: {}", f.path, i + 1, line.trim());
+        }
+    }
+    Ok(())
+}
+
+/// Call function of current contract read-only, and print decoded return
+/// values.
+///
+/// # Arguments
+/// * `session` - session
+/// * `function` - function name, or signature e.g. `balanceOf(address)`
+/// * `args` - arguments as text
+fn call(session: &mut Session, function: &str, args: &[&str]) -> Result<(), String> {
+    let (chain, address) = (session.chain, session.address()?);
+    let abi = session.abi()?;
+    let candidates: Vec<&abi::Entry> = abi.functions()
+        .filter(|f| f.name == function || f.signature() == function)
+        .filter(|f| f.inputs.len() == args.len())
+        .collect();
+    let entry = match candidates.as_slice() {
+        [entry] => *entry,
+        [] => {
+            let err_msg = format!("Error no function '{}' taking {} argument(s)", function, args.len());
+            return Err(err_msg);
+        },
+        _ => {
+            let signatures: Vec<String> = candidates.iter().map(|f| f.signature()).collect();
+            let err_msg = format!("Error '{}' is ambiguous; use one of {}", function, signatures.join(", "));
+            return Err(err_msg);
+        }
+    };
+
+    let mut data = entry.selector();
+    for (param, arg) in entry.inputs.iter().zip(args.iter()) {
+        data.push_str(&abi::to_hex(&abi::encode_word(&param.canonical_type(), arg)?));
+    }
+    let output = explorer::call(chain, &address, &data)?;
+
+    let is_value_type = |kind: &str| !kind.ends_with(']') && !kind.starts_with('(') && kind != "string" && kind != "bytes";
+    match entry.outputs.as_slice() {
+        [single] if single.kind == "string" => println!("{}", abi::decode_string(&output).unwrap_or_else(|| format!("0x{}", abi::to_hex(&output)))),
+        outputs if outputs.iter().all(|o| is_value_type(&o.kind)) && output.len() >= outputs.len() * 32 => {
+            for (o, word) in outputs.iter().zip(output.chunks(32)) {
+                let name = if o.name.is_empty() { String::new() } else { format!(" {}", o.name) };
+                println!("{}{}: {}", o.kind, name, abi::decode_word(&o.kind, word));
+            }
+        },
+        _ => println!("0x{}", abi::to_hex(&output)),
+    }
+    Ok(())
+}
+
+/// Execute single command line. Returns whether session should go on.
+///
+/// # Arguments
+/// * `session` - session
+/// * `line` - command line
+fn execute(session: &mut Session, line: &str) -> Result<bool, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (command, rest) = match words.split_first() {
+        Some((command, rest)) => (*command, rest),
+        None => return Ok(true),
+    };
+
+    match (command, rest) {
+        ("exit" | "quit", _) => return Ok(false),
+        ("help", _) => println!("{}", HELP),
+        ("chain", []) => println!("{}", session.chain.name),
+        ("chain", [chain]) => {
+            session.chain = crate::parse_chain(chain)?;
+            session.address = None;
+        },
+        ("open", [address]) => {
+            session.address = Some(address.to_lowercase());
+            info(session)?;
+        },
+        ("info", []) => info(session)?,
+        ("files", []) => {
+            for f in session.source()?.files.iter() {
+                println!("{}", f.path);
+            }
+        },
+        ("cat", [path]) => {
+            match session.source()?.files.iter().find(|f| f.path == *path || f.path.ends_with(&format!("/{}", path))) {
+                Some(f) => println!("{}", f.content.trim_end()),
+                None => {
+                    let err_msg = format!("Error no source file '{}'; see 'files'", path);
+                    return Err(err_msg);
+                }
+            }
+        },
+        ("abi", []) => print_abi(session)?,
+        ("grep", [_, ..]) => grep(session, line.trim_start()[command.len()..].trim())?,
+        ("call", [function, args @ ..]) => call(session, function, args)?,
+        _ => {
+            let err_msg = format!("Error unknown command, or wrong number of arguments '{}'; see 'help'", line.trim());
+            return Err(err_msg);
+        }
+    }
+    Ok(true)
+}
+
+/// Execute `repl` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &ReplArgs) -> Result<(), String> {
+    let mut session = Session {
+        chain: crate::parse_chain(&args.chain)?,
+        address: None,
+        contracts: HashMap::new(),
+    };
+    if let Some(address) = args.address.as_ref() {
+        if let Err(e) = execute(&mut session, &format!("open {}", address)) {
+            eprintln!("{}", e);
+        }
+    }
+
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("{}", session.prompt());
+        let _ = std::io::stdout().flush();
+        let line = match lines.next() {
+            Some(Ok(res)) => res,
+            Some(Err(e)) => {
+                let err_msg = format!("Error reading command; err={}", e);
+                return Err(err_msg);
+            },
+            // end of input
+            None => {
+                println!();
+                return Ok(());
+            }
+        };
+        match execute(&mut session, &line) {
+            Ok(true) => (),
+            Ok(false) => return Ok(()),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+}
@@ -0,0 +1,199 @@
+//! `risk` command: flag common centralization, and trap patterns found in
+//! verified source code, and ABI of contract e.g. mint functions, blacklists,
+//! or uncapped fee setters.
+//!
+//! It's a heuristic pre-screen matching names, and shapes of code, so it
+//! misses renamed, or obfuscated patterns, and flags harmless ones. Findings
+//! point where to look at during manual review; they are not verdicts.
+
+use crate::abi;
+use crate::solidity::{self, ContractDef};
+use crate::source;
+use crate::TargetArgs;
+use clap::Args;
+use regex::Regex;
+use serde_json::{json, Value};
+
+#[derive(Debug, Args)]
+pub struct RiskArgs {
+    #[clap(flatten)]
+    pub target: TargetArgs,
+
+    /// Output as JSON instead of human readable table
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+}
+
+/// Risk pattern to look for in source code, and state-changing functions of
+/// ABI.
+struct Check {
+    name: &'static str,
+
+    /// `high`, `medium`, or `low`
+    severity: &'static str,
+
+    description: &'static str,
+
+    /// Pattern matched against body of contracts
+    source: &'static str,
+
+    /// Pattern matched against names of state-changing functions of ABI
+    function: &'static str,
+}
+
+const CHECKS: &[Check] = &[
+    Check {
+        name: "mint",
+        severity: "high",
+        description: "privileged party can mint new tokens",
+        source: r"\bfunction\s+mint\w*\s*\(",
+        function: r"(?i)^mint",
+    },
+    Check {
+        name: "blacklist",
+        severity: "high",
+        description: "addresses can be blocked from transferring",
+        source: r"(?i)(black|block|deny)list|\bisBot\b|\bbots\s*\[|\bfrozen\w*\s*\[",
+        function: r"(?i)(black|block|deny)list|bot|freeze",
+    },
+    Check {
+        name: "tx-origin-auth",
+        severity: "high",
+        description: "authorization by tx.origin can be phished through another contract",
+        source: r"\btx\.origin\s*==|==\s*tx\.origin\b",
+        function: r"^$",
+    },
+    Check {
+        name: "pausable",
+        severity: "medium",
+        description: "privileged party can pause transfers, or other functions",
+        source: r"\bwhenNotPaused\b|\b_pause\s*\(",
+        function: r"(?i)^(pause|unpause)$",
+    },
+    Check {
+        name: "upgradeable",
+        severity: "medium",
+        description: "code can be replaced by upgrading implementation",
+        source: r"\bUUPSUpgradeable\b|\bupgradeTo\w*\s*\(|_IMPLEMENTATION_SLOT",
+        function: r"^upgradeTo",
+    },
+];
+
+/// Finding of risk pattern.
+#[derive(Debug, Clone)]
+struct Finding {
+    check: String,
+    severity: String,
+    description: String,
+
+    /// Contracts, and functions the pattern is found in
+    found_in: Vec<String>,
+}
+
+/// Find setters of fee, or tax whose body doesn't bound the new value e.g.
+/// by `require`, or comparison. Returned as `Contract.function`.
+///
+/// # Arguments
+/// * `contracts` - contracts of source code
+fn uncapped_fee_setters(contracts: &[ContractDef]) -> Vec<String> {
+    let setter_re = Regex::new(r"(?i)\bfunction\s+(set\w*(fee|tax)\w*)\s*\(").unwrap();
+    let bound_re = Regex::new(r"\brequire\s*\(|\brevert\b|<=|>=|<|>|\bMAX_\w+|\bmin\s*\(").unwrap();
+
+    let mut found = Vec::new();
+    for contract in contracts.iter() {
+        for caps in setter_re.captures_iter(&contract.body) {
+            let start = caps.get(0).unwrap().end();
+            // skip parameters, and modifiers to reach body; declaration without body has none
+            let open = match contract.body[start..].find(['{', ';']) {
+                Some(i) if contract.body.as_bytes()[start + i] == b'{' => start + i,
+                _ => continue,
+            };
+            let close = solidity::find_matching_brace(&contract.body, open).unwrap_or(contract.body.len() - 1);
+            if !bound_re.is_match(&contract.body[open..close]) {
+                found.push(format!("{}.{}", contract.name, &caps[1]));
+            }
+        }
+    }
+    found
+}
+
+/// Execute `risk` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &RiskArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.target.chain)?;
+    let contract_source = source::fetch_sources(chain, &args.target.address)?;
+    let contract_abi = abi::fetch_abi(chain, &args.target.address)?;
+
+    // interfaces only declare what others implement
+    let contracts: Vec<ContractDef> = contract_source.files.iter()
+        .flat_map(|f| solidity::parse_contracts(&f.path, &f.content))
+        .filter(|c| c.kind != "interface")
+        .collect();
+    let functions: Vec<&abi::Entry> = contract_abi.functions().filter(|f| !f.is_read_only()).collect();
+
+    let mut findings: Vec<Finding> = Vec::new();
+    for check in CHECKS {
+        let source_re = Regex::new(check.source).unwrap();
+        let function_re = Regex::new(check.function).unwrap();
+        let mut found_in: Vec<String> = contracts.iter()
+            .filter(|c| source_re.is_match(&c.body))
+            .map(|c| c.name.clone())
+            .collect();
+        found_in.extend(functions.iter().filter(|f| function_re.is_match(&f.name)).map(|f| format!("{}()", f.name)));
+        found_in.dedup();
+        if !found_in.is_empty() {
+            findings.push(Finding {
+                check: check.name.to_owned(),
+                severity: check.severity.to_owned(),
+                description: check.description.to_owned(),
+                found_in,
+            });
+        }
+    }
+
+    let setters = uncapped_fee_setters(&contracts);
+    if !setters.is_empty() {
+        findings.push(Finding {
+            check: "uncapped-fee".to_owned(),
+            severity: "high".to_owned(),
+            description: "fee, or tax can be set without upper bound".to_owned(),
+            found_in: setters,
+        });
+    }
+
+    let rank = |severity: &str| match severity { "high" => 0, "medium" => 1, _ => 2 };
+    findings.sort_by_key(|f| rank(&f.severity));
+
+    if args.json {
+        let out = json!({
+            "heuristic": true,
+            "findings": findings.iter().map(|f| json!({
+                "check": f.check,
+                "severity": f.severity,
+                "description": f.description,
+                "foundIn": f.found_in,
+            })).collect::<Vec<Value>>(),
+        });
+        match serde_json::to_string_pretty(&out) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing risk findings to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+        return Ok(());
+    }
+
+    println!("Heuristic pre-screen; findings are hints for manual review, not verdicts.\n");
+    if findings.is_empty() {
+        println!("No risk patterns found");
+        return Ok(());
+    }
+    println!("{:<8} {:<15} {:<66} FOUND IN", "SEVERITY", "CHECK", "DESCRIPTION");
+    for finding in findings.iter() {
+        println!("{:<8} {:<15} {:<66} {}", finding.severity, finding.check, finding.description, finding.found_in.join(", "));
+    }
+    Ok(())
+}
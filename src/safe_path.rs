@@ -0,0 +1,147 @@
+//! Path safety for writing output on Windows, where deep paths e.g. of
+//! `@openzeppelin/contracts/...` exceed `MAX_PATH` of 260 characters, and
+//! names like `con.sol` are reserved for devices.
+//!
+//! Long paths are written through `\\?\` prefix lifting the limit, and path
+//! components of reserved names, or characters are escaped e.g. `con.sol` to
+//! `con_.sol`. Both are no-op on other platforms, so output there is kept as
+//! verified.
+
+use std::path::{Component, Path, PathBuf};
+
+/// Whether or not running on Windows
+const IS_WINDOWS: bool = cfg!(windows);
+
+/// Length of path from which `\\?\` prefix is applied; `MAX_PATH` minus room
+/// of 8.3 filename which directories need
+const LONG_PATH_THRESHOLD: usize = 248;
+
+/// Device names reserved on Windows regardless of extension
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Escape single path component to be valid on Windows; reserved characters
+/// are replaced with `_`, trailing dots, and spaces are replaced with `_`, and
+/// `_` is appended to stem of reserved name e.g. `con.sol` to `con_.sol`.
+///
+/// # Arguments
+/// * `component` - path component
+fn escape_component(component: &str) -> String {
+    let mut escaped: String = component.chars()
+        .map(|c| if "<>:\"|?*".contains(c) || (c as u32) < 0x20 { '_' } else { c })
+        .collect();
+    let trimmed_len = escaped.trim_end_matches(['.', ' ']).len();
+    if trimmed_len < escaped.len() && !matches!(escaped.as_str(), "." | "..") {
+        escaped.replace_range(trimmed_len.., &"_".repeat(escaped.len() - trimmed_len));
+    }
+
+    let stem_len = escaped.find('.').unwrap_or(escaped.len());
+    if RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(&escaped[..stem_len])) {
+        escaped.insert(stem_len, '_');
+    }
+    escaped
+}
+
+/// Escape relative path to write file at, so that each of its components is
+/// valid on Windows. It's returned as is on other platforms.
+///
+/// # Arguments
+/// * `relative_path` - path relative to output directory, separated by `/`
+pub fn escape_relative_path(relative_path: &str) -> String {
+    if !IS_WINDOWS {
+        return relative_path.to_owned();
+    }
+    let escaped = relative_path.split('/').map(escape_component).collect::<Vec<String>>().join("/");
+    if escaped != relative_path {
+        eprintln!("Warning: '{}' isn't valid on Windows; written as '{}'", relative_path, escaped);
+    }
+    escaped
+}
+
+/// Get path to access filesystem with; on Windows, long path is made
+/// absolute, and prefixed with `\\?\` to lift `MAX_PATH` limit. It's returned
+/// as is on other platforms, or if short enough.
+///
+/// # Arguments
+/// * `path` - path to access
+pub fn fs_path(path: &str) -> PathBuf {
+    if !IS_WINDOWS || path.len() < LONG_PATH_THRESHOLD || path.starts_with(r"\\?\") {
+        return PathBuf::from(path);
+    }
+    let absolute = match std::env::current_dir() {
+        Ok(cwd) => cwd.join(path),
+        Err(_) => return PathBuf::from(path),
+    };
+
+    // `\\?\` paths are taken literally, so `.`, `..`, and `/` must be resolved
+    let mut components: Vec<String> = Vec::new();
+    let mut prefix = String::new();
+    for component in Path::new(&absolute.to_string_lossy().replace('/', "\\")).components() {
+        match component {
+            Component::Prefix(p) => prefix = p.as_os_str().to_string_lossy().into_owned(),
+            Component::RootDir | Component::CurDir => (),
+            Component::ParentDir => {
+                components.pop();
+            },
+            Component::Normal(c) => components.push(c.to_string_lossy().into_owned()),
+        }
+    }
+    let prefixed = match prefix.strip_prefix(r"\\") {
+        // UNC path e.g. \\server\share
+        Some(unc) => format!(r"\\?\UNC\{}\{}", unc, components.join("\\")),
+        None => format!(r"\\?\{}\{}", prefix, components.join("\\")),
+    };
+    PathBuf::from(prefixed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_component_of_reserved_names() {
+        assert_eq!(escape_component("con.sol"), "con_.sol");
+        assert_eq!(escape_component("CON"), "CON_");
+        assert_eq!(escape_component("Aux.test.sol"), "Aux_.test.sol");
+        assert_eq!(escape_component("lpt9.sol"), "lpt9_.sol");
+        assert_eq!(escape_component("console.sol"), "console.sol");
+        assert_eq!(escape_component("com10.sol"), "com10.sol");
+    }
+
+    #[test]
+    fn escape_component_of_reserved_characters() {
+        assert_eq!(escape_component("a<b>:c\"d|e?f*.sol"), "a_b__c_d_e_f_.sol");
+        assert_eq!(escape_component("tab\there.sol"), "tab_here.sol");
+        assert_eq!(escape_component("trailing. ."), "trailing___");
+        assert_eq!(escape_component("nul. "), "nul__");
+        assert_eq!(escape_component("."), ".");
+        assert_eq!(escape_component(".."), "..");
+        assert_eq!(escape_component("Token.sol"), "Token.sol");
+    }
+
+    #[test]
+    fn escape_relative_path_per_platform() {
+        let path = "lib/con.sol/aux.sol";
+        let expected = if IS_WINDOWS { "lib/con_.sol/aux_.sol" } else { path };
+        assert_eq!(escape_relative_path(path), expected);
+        assert_eq!(escape_relative_path("@openzeppelin/contracts/token/ERC20/ERC20.sol"), "@openzeppelin/contracts/token/ERC20/ERC20.sol");
+    }
+
+    #[test]
+    fn fs_path_of_short_and_long_paths() {
+        assert_eq!(fs_path("out/Token.sol"), PathBuf::from("out/Token.sol"));
+        let long = format!("out/{}/Token.sol", "a".repeat(LONG_PATH_THRESHOLD));
+        let prefixed = fs_path(&long).to_string_lossy().into_owned();
+        if IS_WINDOWS {
+            assert!(prefixed.starts_with(r"\\?\"), "{}", prefixed);
+            assert!(prefixed.ends_with(r"\Token.sol"), "{}", prefixed);
+            assert!(!prefixed.contains('/'), "{}", prefixed);
+        }
+        else {
+            assert_eq!(prefixed, long);
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! `scaffold` command: generate ready-to-edit files to interact with a contract.
+
+mod cast;
+mod forge_test;
+
+use crate::TargetArgs;
+use clap::{Args, Subcommand};
+
+#[derive(Debug, Args)]
+pub struct ScaffoldArgs {
+    #[clap(subcommand)]
+    pub kind: ScaffoldKind,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ScaffoldKind {
+    /// Generate a shell script with `cast call`/`cast send` commands for every ABI function
+    #[clap(name="cast")]
+    Cast(ScaffoldOutputArgs),
+
+    /// Generate a Foundry fork-test contract with empty tests for every external function
+    #[clap(name="forge-test")]
+    ForgeTest(ScaffoldOutputArgs),
+}
+
+/// Arguments shared by all kinds of scaffold.
+#[derive(Debug, Args)]
+pub struct ScaffoldOutputArgs {
+    #[clap(flatten)]
+    pub target: TargetArgs,
+
+    /// Filepath to write generated content to. If not specified, it will be
+    /// printed to stdout.
+    #[clap(long="out", short='o', required=false)]
+    pub out_path: Option<String>,
+
+    /// Whether or not to print meta information during execution.
+    #[clap(long="silence", short='s', multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub silence: bool,
+}
+
+/// Build placeholder of parameter to be edited by users later.
+/// Tuple will be expanded into its components.
+///
+/// # Arguments
+/// * `param` - parameter of function
+/// * `index` - index of parameter, used as its name if parameter has no name
+fn placeholder(param: &crate::abi::Param, index: usize) -> String {
+    let name = if param.name.is_empty() { format!("arg{}", index) } else { param.name.clone() };
+
+    if param.kind.starts_with("tuple") && !param.kind.contains('[') {
+        let inner: Vec<String> = param.components.iter().enumerate().map(|(i, c)| placeholder(c, i)).collect();
+        format!("({})", inner.join(","))
+    }
+    else {
+        format!("<{}:{}>", name, param.canonical_type())
+    }
+}
+
+/// Write generated content to the output filepath, or print to stdout.
+///
+/// # Arguments
+/// * `args` - output arguments
+/// * `content` - generated content
+/// * `executable` - whether or not to mark the written file as executable
+fn output(args: &ScaffoldOutputArgs, content: &str, executable: bool) -> Result<(), String> {
+    let out_path = match args.out_path.as_ref() {
+        Some(res) => res,
+        None => {
+            print!("{}", content);
+            return Ok(());
+        }
+    };
+
+    crate::create_intermediate_dirs(out_path)?;
+    crate::write_file(out_path, content)?;
+
+    #[cfg(unix)]
+    if executable {
+        use std::os::unix::fs::PermissionsExt;
+        if let Err(e) = std::fs::set_permissions(out_path, std::fs::Permissions::from_mode(0o755)) {
+            let err_msg = format!("Error setting permissions of '{}'; err={}", out_path, e);
+            return Err(err_msg);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = executable;
+
+    if !crate::progress::silenced(args.silence) {
+        println!("{}", out_path);
+    }
+    Ok(())
+}
+
+/// Execute `scaffold` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &ScaffoldArgs) -> Result<(), String> {
+    match &args.kind {
+        ScaffoldKind::Cast(args) => {
+            let content = cast::generate(&args.target)?;
+            output(args, &content, true)
+        },
+        ScaffoldKind::ForgeTest(args) => {
+            let content = forge_test::generate(&args.target)?;
+            output(args, &content, false)
+        },
+    }
+}
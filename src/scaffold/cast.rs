@@ -0,0 +1,49 @@
+//! Generate shell script of `cast` commands.
+
+use crate::abi;
+use crate::TargetArgs;
+
+/// Generate shell script with `cast call`/`cast send` commands for every
+/// function of contract ABI.
+///
+/// Commands of functions which need arguments are commented out, so the script
+/// is still runnable before users fill in placeholders.
+///
+/// # Arguments
+/// * `target` - target contract address and chain
+pub fn generate(target: &TargetArgs) -> Result<String, String> {
+    let chain = crate::parse_chain(&target.chain)?;
+    let contract_abi = abi::fetch_abi(chain, &target.address)?;
+
+    let mut script = String::new();
+    script.push_str("#!/usr/bin/env bash\n");
+    script.push_str(&format!("# cast interaction script for {} on {}\n", target.address, target.chain.to_lowercase()));
+    script.push_str("# generated by tracpls; replace <name:type> placeholders then uncomment commands to use them\n");
+    script.push_str("set -euo pipefail\n\n");
+    script.push_str(&format!("ADDRESS={}\n", target.address));
+    script.push_str(&format!("RPC_URL=\"${{RPC_URL:?set RPC_URL to an RPC endpoint of {}}}\"\n", target.chain.to_lowercase()));
+    script.push_str("# PRIVATE_KEY is required by send commands\n");
+
+    let (reads, writes): (Vec<&abi::Entry>, Vec<&abi::Entry>) = contract_abi.functions().partition(|f| f.is_read_only());
+
+    script.push_str("\n# ---------- read-only functions ----------\n");
+    for f in reads.iter() {
+        let outputs: Vec<String> = f.outputs.iter().map(|p| p.canonical_type()).collect();
+        let args: Vec<String> = f.inputs.iter().enumerate().map(|(i, p)| format!(" \"{}\"", super::placeholder(p, i))).collect();
+        let prefix = if f.inputs.is_empty() { "" } else { "# " };
+
+        script.push_str(&format!("\n# {}\n", f.declaration()));
+        script.push_str(&format!("{}cast call \"$ADDRESS\" \"{}({})\"{} --rpc-url \"$RPC_URL\"\n", prefix, f.signature(), outputs.join(","), args.concat()));
+    }
+
+    script.push_str("\n# ---------- state-changing functions ----------\n");
+    for f in writes.iter() {
+        let args: Vec<String> = f.inputs.iter().enumerate().map(|(i, p)| format!(" \"{}\"", super::placeholder(p, i))).collect();
+        let value = if f.mutability() == "payable" { " --value \"<value:wei>\"" } else { "" };
+
+        script.push_str(&format!("\n# {} {}\n", f.declaration(), f.mutability()));
+        script.push_str(&format!("# cast send \"$ADDRESS\" \"{}\"{}{} --rpc-url \"$RPC_URL\" --private-key \"$PRIVATE_KEY\"\n", f.signature(), args.concat(), value));
+    }
+
+    Ok(script)
+}
@@ -0,0 +1,142 @@
+//! Generate Foundry test contract to fork-test against the fetched contract.
+
+use crate::abi;
+use crate::explorer;
+use crate::TargetArgs;
+use std::collections::BTreeMap;
+
+/// Get Solidity struct name of tuple parameter from its internal type
+/// e.g. `struct Pool.Order[]` gives `Order`.
+///
+/// # Arguments
+/// * `param` - tuple parameter
+/// * `structs` - already collected struct definitions, used to derive fallback name
+fn struct_name(param: &abi::Param, structs: &BTreeMap<String, String>) -> String {
+    let internal_type = param.internal_type.as_deref().unwrap_or("");
+    match internal_type.strip_prefix("struct ") {
+        Some(res) => {
+            let name = res.split('[').next().unwrap_or(res);
+            name.rsplit('.').next().unwrap_or(name).to_owned()
+        },
+        None => format!("Tuple{}", structs.len()),
+    }
+}
+
+/// Get Solidity type of parameter, collecting struct definitions of tuples
+/// along the way.
+///
+/// # Arguments
+/// * `param` - parameter
+/// * `structs` - collected struct definitions keyed by struct name
+fn solidity_type(param: &abi::Param, structs: &mut BTreeMap<String, String>) -> String {
+    let suffix = match param.kind.strip_prefix("tuple") {
+        Some(res) => res,
+        None => return param.kind.clone(),
+    };
+
+    let name = struct_name(param, structs);
+    if !structs.contains_key(&name) {
+        // reserve the name first to handle recursive reference
+        structs.insert(name.clone(), String::new());
+
+        let mut definition = format!("    struct {} {{\n", name);
+        for (i, c) in param.components.iter().enumerate() {
+            let field_name = if c.name.is_empty() { format!("field{}", i) } else { c.name.clone() };
+            definition.push_str(&format!("        {} {};\n", solidity_type(c, structs), field_name));
+        }
+        definition.push_str("    }\n");
+        structs.insert(name.clone(), definition);
+    }
+    format!("{}{}", name, suffix)
+}
+
+/// Get parameter list of function declaration.
+///
+/// # Arguments
+/// * `params` - parameters
+/// * `location` - data location for reference types i.e. `calldata`, or `memory`
+/// * `structs` - collected struct definitions keyed by struct name
+fn parameter_list(params: &[abi::Param], location: &str, structs: &mut BTreeMap<String, String>) -> String {
+    params.iter().map(|p| {
+        let kind = solidity_type(p, structs);
+        let is_reference = kind.ends_with(']') || kind == "bytes" || kind == "string" || p.kind.starts_with("tuple");
+        let mut declaration = kind;
+        if is_reference {
+            declaration.push(' ');
+            declaration.push_str(location);
+        }
+        if !p.name.is_empty() {
+            declaration.push(' ');
+            declaration.push_str(&p.name);
+        }
+        declaration
+    }).collect::<Vec<String>>().join(", ")
+}
+
+/// Generate Foundry test contract with an interface of target contract,
+/// fork-test setup, and empty test functions for every external function.
+///
+/// # Arguments
+/// * `target` - target contract address and chain
+pub fn generate(target: &TargetArgs) -> Result<String, String> {
+    let chain = crate::parse_chain(&target.chain)?;
+    let contract_abi = abi::fetch_abi(chain, &target.address)?;
+
+    let meta = explorer::get_source_meta(chain, &target.address)?;
+    let contract_name = match meta["ContractName"].as_str() {
+        Some(res) if !res.is_empty() => res.to_owned(),
+        _ => "Target".to_owned(),
+    };
+    let interface_name = format!("I{}", contract_name);
+
+    let mut structs: BTreeMap<String, String> = BTreeMap::new();
+    let mut declarations = String::new();
+    for f in contract_abi.functions() {
+        let mut declaration = format!("    function {}({}) external", f.name, parameter_list(&f.inputs, "calldata", &mut structs));
+        match f.mutability() {
+            "nonpayable" => (),
+            mutability => declaration.push_str(&format!(" {}", mutability)),
+        }
+        if !f.outputs.is_empty() {
+            declaration.push_str(&format!(" returns ({})", parameter_list(&f.outputs, "memory", &mut structs)));
+        }
+        declaration.push_str(";\n");
+        declarations.push_str(&declaration);
+    }
+
+    let mut content = String::new();
+    content.push_str("// SPDX-License-Identifier: UNLICENSED\n");
+    content.push_str("pragma solidity ^0.8.13;\n\n");
+    content.push_str("import \"forge-std/Test.sol\";\n\n");
+    content.push_str(&format!("/// Interface of {} generated from its verified ABI by tracpls\n", contract_name));
+    content.push_str(&format!("interface {} {{\n", interface_name));
+    for definition in structs.values() {
+        content.push_str(definition);
+        content.push('\n');
+    }
+    content.push_str(&declarations);
+    content.push_str("}\n\n");
+
+    content.push_str(&format!("contract {}Test is Test {{\n", contract_name));
+    content.push_str(&format!("    {} internal constant TARGET = {}({});\n\n", interface_name, interface_name, abi::to_checksum_address(&target.address)));
+    content.push_str("    function setUp() public {\n");
+    content.push_str(&format!("        // RPC_URL should point to an archive node of {}\n", target.chain.to_lowercase()));
+    content.push_str("        vm.createSelectFork(vm.envString(\"RPC_URL\"));\n");
+    content.push_str("    }\n");
+
+    // overloaded functions need distinct test names
+    let mut seen: BTreeMap<String, usize> = BTreeMap::new();
+    for f in contract_abi.functions() {
+        let count = seen.entry(f.name.clone()).or_insert(0);
+        let test_name = if *count == 0 { format!("test_{}", f.name) } else { format!("test_{}_{}", f.name, count) };
+        *count += 1;
+
+        content.push_str(&format!("\n    /// {}\n", f.declaration()));
+        content.push_str(&format!("    function {}() public {{\n", test_name));
+        content.push_str("        // TODO\n");
+        content.push_str("    }\n");
+    }
+    content.push_str("}\n");
+
+    Ok(content)
+}
@@ -0,0 +1,164 @@
+//! `schema` command: generate JSON Schema of function inputs/outputs from ABI.
+
+use crate::abi;
+use crate::TargetArgs;
+use clap::Args;
+use serde_json::{json, Map, Value};
+
+#[derive(Debug, Args)]
+pub struct SchemaArgs {
+    #[clap(flatten)]
+    pub target: TargetArgs,
+
+    /// Generate schema only for function of this name
+    #[clap(long="function", short='f', required=false)]
+    pub function: Option<String>,
+
+    /// Filepath to write generated schema to. If not specified, it will be
+    /// printed to stdout.
+    #[clap(long="out", short='o', required=false)]
+    pub out_path: Option<String>,
+}
+
+/// Get JSON Schema of a single ABI type.
+///
+/// Integers are represented as strings of decimal or hex digits as they
+/// generally exceed what JSON number can hold precisely.
+///
+/// # Arguments
+/// * `kind` - ABI type e.g. `uint256`, `address[]`, or `tuple[2]`
+/// * `components` - components of tuple type
+fn type_schema(kind: &str, components: &[abi::Param]) -> Value {
+    // array types are resolved from the outermost dimension
+    if kind.ends_with(']') {
+        let open = kind.rfind('[').unwrap_or(0);
+        let inner = &kind[..open];
+        let size = &kind[open + 1..kind.len() - 1];
+
+        let mut schema = json!({
+            "type": "array",
+            "items": type_schema(inner, components),
+        });
+        if let Ok(size) = size.parse::<u64>() {
+            schema["minItems"] = json!(size);
+            schema["maxItems"] = json!(size);
+        }
+        return schema;
+    }
+
+    if kind == "tuple" {
+        return params_schema(components);
+    }
+
+    if kind == "address" {
+        json!({ "type": "string", "pattern": "^0x[0-9a-fA-F]{40}$" })
+    }
+    else if kind == "bool" {
+        json!({ "type": "boolean" })
+    }
+    else if kind == "string" {
+        json!({ "type": "string" })
+    }
+    else if kind == "bytes" {
+        json!({ "type": "string", "pattern": "^0x([0-9a-fA-F]{2})*$" })
+    }
+    else if let Some(size) = kind.strip_prefix("bytes") {
+        let size = size.parse::<u64>().unwrap_or(32);
+        json!({ "type": "string", "pattern": format!("^0x[0-9a-fA-F]{{{}}}$", size * 2) })
+    }
+    else if kind.starts_with("uint") {
+        json!({ "type": "string", "pattern": "^([0-9]+|0x[0-9a-fA-F]+)$", "description": kind })
+    }
+    else if kind.starts_with("int") {
+        json!({ "type": "string", "pattern": "^-?([0-9]+|0x[0-9a-fA-F]+)$", "description": kind })
+    }
+    else {
+        // e.g. fixed point types which are not fully supported by solidity
+        json!({ "type": "string", "description": kind })
+    }
+}
+
+/// Get JSON Schema of list of parameters as an object keyed by parameter name.
+/// Unnamed parameters are keyed by their position e.g. `arg0`.
+///
+/// # Arguments
+/// * `params` - parameters
+fn params_schema(params: &[abi::Param]) -> Value {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for (i, p) in params.iter().enumerate() {
+        let name = if p.name.is_empty() { format!("arg{}", i) } else { p.name.clone() };
+        let mut schema = type_schema(&p.kind, &p.components);
+        if let Some(internal_type) = p.internal_type.as_ref() {
+            if internal_type != &p.kind {
+                schema["title"] = json!(internal_type);
+            }
+        }
+        properties.insert(name.clone(), schema);
+        required.push(json!(name));
+    }
+
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+/// Execute `schema` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &SchemaArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.target.chain)?;
+    let contract_abi = abi::fetch_abi(chain, &args.target.address)?;
+
+    let mut definitions = Map::new();
+    for f in contract_abi.functions() {
+        if let Some(name) = args.function.as_ref() {
+            if name != &f.name {
+                continue;
+            }
+        }
+
+        definitions.insert(f.signature(), json!({
+            "description": format!("{} ({})", f.declaration(), f.mutability()),
+            "selector": f.selector(),
+            "inputs": params_schema(&f.inputs),
+            "outputs": params_schema(&f.outputs),
+        }));
+    }
+
+    if definitions.is_empty() {
+        if let Some(name) = args.function.as_ref() {
+            let err_msg = format!("Error function '{}' not found in contract ABI", name);
+            return Err(err_msg);
+        }
+    }
+
+    let schema = json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": format!("Function parameters of {}", args.target.address),
+        "definitions": definitions,
+    });
+
+    let content = match serde_json::to_string_pretty(&schema) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error serializing JSON Schema; err={}", e);
+            return Err(err_msg);
+        }
+    };
+
+    match args.out_path.as_ref() {
+        Some(out_path) => {
+            crate::create_intermediate_dirs(out_path)?;
+            crate::write_file(out_path, &content)
+        },
+        None => {
+            println!("{}", content);
+            Ok(())
+        }
+    }
+}
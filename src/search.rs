@@ -0,0 +1,458 @@
+//! `search` command: find contracts in local cache (see `cache`) whose
+//! verified source code matches a pattern, ranked by number of matching lines
+//! along with snippets of them, or whose public name tag, or labels match
+//! (see `explorer::get_name_tag`).
+//!
+//! Words of `--query`, and words literal text of `--code` has to contain are
+//! looked up in index of cache (see `search_index`), so only contracts found
+//! are read; contracts found by `--query` are ranked by relevance. Pattern of
+//! `--code` without literal text e.g. `\w+\(` is matched by scanning the whole
+//! cache instead.
+//!
+//! With `--serve`, searches are served over HTTP as JSON the same as `--json`
+//! e.g. `GET /search?code=delegatecall&chain=bsc`.
+
+use crate::cache;
+use crate::explorer::{self, NameTag};
+use crate::search_index::{self, SearchIndex};
+use crate::source;
+use clap::Args;
+use regex::{Regex, RegexBuilder};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Maximum number of characters of snippet
+const MAX_SNIPPET_LEN: usize = 120;
+
+#[derive(Debug, Args)]
+pub struct SearchArgs {
+    /// Regular expression to search verified source code for e.g.
+    /// 'delegatecall'
+    #[clap(long="code", required_unless_present_any=&["tag", "query", "serve"])]
+    pub code: Option<String>,
+
+    /// Words to search index of verified source code for, ranked by
+    /// relevance e.g. 'delegatecall owner'. Contracts have to contain every
+    /// word; words in double quotes have to be in a row, and words prefixed
+    /// with '-' must not be contained. Index is kept in local cache, and
+    /// updated on each search.
+    #[clap(long="query", short='q', required=false)]
+    pub query: Option<String>,
+
+    /// Regular expression to search public name tag, and labels given by
+    /// explorer for e.g. 'binance'
+    #[clap(long="tag", required=false)]
+    pub tag: Option<String>,
+
+    /// Only search contracts of this chain, by name, or chain id
+    #[clap(long="chain", short='c', required=false)]
+    pub chain: Option<String>,
+
+    /// Match case-sensitively
+    #[clap(long="case-sensitive", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub case_sensitive: bool,
+
+    /// Maximum number of contracts to list
+    #[clap(long="limit", required=false, default_value="20")]
+    pub limit: usize,
+
+    /// Maximum number of matching lines shown for each contract
+    #[clap(long="snippets", required=false, default_value="3")]
+    pub snippets: usize,
+
+    /// Output as JSON instead of human readable text
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+
+    /// Serve searches over HTTP at 127.0.0.1 on this port instead e.g.
+    /// 'GET /search?code=delegatecall&chain=bsc&limit=5' with parameters of
+    /// the same names as flag options, answered as with --json
+    #[clap(long="serve", required=false, conflicts_with_all=&["code", "query", "tag"])]
+    pub serve: Option<u16>,
+}
+
+/// Search to run, parsed from flag options, or parameters of request.
+struct Search {
+    /// Name of chain to only search contracts of, if any
+    chain: Option<String>,
+
+    code: Option<Regex>,
+    tag: Option<Regex>,
+
+    /// Words to look up in index, if any; of `--query`, and of literal text
+    /// of `--code`
+    query: Option<search_index::Query>,
+
+    /// Whether contracts are ranked by relevance to `--query`
+    ranked: bool,
+
+    /// Patterns to search source code of contract for
+    patterns: Option<Patterns>,
+
+    limit: usize,
+    snippets: usize,
+}
+
+/// Contract matching the pattern.
+#[derive(Debug)]
+struct Hit {
+    chain: String,
+    address: String,
+    contract_name: String,
+    name_tag: Option<NameTag>,
+
+    /// Relevance to query if searched by it
+    score: Option<f64>,
+
+    /// Number of matching lines
+    matches: usize,
+
+    /// Matching lines as tuples of path, line number, and line
+    snippets: Vec<(String, usize, String)>,
+}
+
+/// Patterns to search source code of contract for.
+struct Patterns {
+    /// Patterns each of which has to match some line
+    required: Vec<Regex>,
+
+    /// Pattern of lines counted, and shown as snippets
+    lines: Regex,
+}
+
+/// Search cached contract. Returns `None` if nothing matches.
+///
+/// # Arguments
+/// * `entry` - cache entry
+/// * `patterns` - patterns to search source code for, or `None` to match any
+/// * `name_tag` - name tag of contract if any
+/// * `max_snippets` - maximum number of snippets to keep
+fn search_entry(entry: &cache::CachedEntry, patterns: Option<&Patterns>, name_tag: Option<&NameTag>, max_snippets: usize) -> Result<Option<Hit>, String> {
+    let body = entry.read()?;
+    let raw = explorer::parse_source_meta_raw(&entry.address, &body)?;
+
+    let mut matches = 0;
+    let mut snippets = Vec::new();
+    if let Some(patterns) = patterns {
+        let mut unmatched: Vec<&Regex> = patterns.required.iter().collect();
+        source::for_each_source_raw(&entry.address, &raw, |f| {
+            unmatched.retain(|p| !p.is_match(&f.content));
+            for (i, line) in f.content.lines().enumerate().filter(|(_, l)| patterns.lines.is_match(l)) {
+                matches += 1;
+                if snippets.len() < max_snippets {
+                    let trimmed = line.trim();
+                    let snippet: String = trimmed.chars().take(MAX_SNIPPET_LEN).collect();
+                    let ellipsis = if snippet.len() < trimmed.len() { "..." } else { "" };
+                    snippets.push((f.path.clone(), i + 1, format!("{}{}", snippet, ellipsis)));
+                }
+            }
+            Ok(())
+        })?;
+
+        if matches == 0 || !unmatched.is_empty() {
+            return Ok(None);
+        }
+    }
+    Ok(Some(Hit {
+        chain: entry.chain.clone(),
+        address: entry.address.clone(),
+        contract_name: raw.meta["ContractName"].as_str().unwrap_or("").to_owned(),
+        name_tag: name_tag.cloned(),
+        score: None,
+        matches,
+        snippets,
+    }))
+}
+
+/// Compile pattern of flag option.
+///
+/// # Arguments
+/// * `flag` - name of flag option
+/// * `pattern` - regular expression
+/// * `case_sensitive` - whether to match case-sensitively
+fn compile(flag: &str, pattern: &str, case_sensitive: bool) -> Result<Regex, String> {
+    match RegexBuilder::new(pattern).case_insensitive(!case_sensitive).build() {
+        Ok(res) => Ok(res),
+        Err(e) => {
+            let err_msg = format!("Error invalid value for --{}; err={}", flag, e);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Compile pattern matching words in a row, separated by anything but
+/// characters of words.
+///
+/// # Arguments
+/// * `words` - words
+/// * `case_sensitive` - whether to match case-sensitively
+fn words_pattern(words: &[String], case_sensitive: bool) -> Result<Regex, String> {
+    let escaped: Vec<String> = words.iter().map(|w| regex::escape(w)).collect();
+    compile("query", &format!(r"(^|[^A-Za-z0-9_$]){}($|[^A-Za-z0-9_$])", escaped.join(r"[^A-Za-z0-9_$]+")), case_sensitive)
+}
+
+impl Search {
+    /// Parse search.
+    ///
+    /// # Arguments
+    /// * `code` - value of `--code` if any
+    /// * `query` - value of `--query` if any
+    /// * `tag` - value of `--tag` if any
+    /// * `chain` - value of `--chain` if any
+    /// * `case_sensitive` - whether to match case-sensitively
+    /// * `limit` - maximum number of contracts to list
+    /// * `snippets` - maximum number of matching lines shown for each contract
+    #[allow(clippy::too_many_arguments)]
+    fn parse(code: Option<&str>, query: Option<&str>, tag: Option<&str>, chain: Option<&str>, case_sensitive: bool, limit: usize, snippets: usize) -> Result<Self, String> {
+        let chain = match chain {
+            Some(chain) => Some(crate::parse_chain(chain)?.name.clone()),
+            None => None,
+        };
+        let partials = code.and_then(search_index::pattern_partials);
+        let code = match code {
+            Some(code) => Some(compile("code", code, case_sensitive)?),
+            None => None,
+        };
+        let tag = match tag {
+            Some(tag) => Some(compile("tag", tag, case_sensitive)?),
+            None => None,
+        };
+        let query = match query {
+            Some(query) => Some(search_index::parse_query(query)?),
+            None => None,
+        };
+        if code.is_none() && query.is_none() && tag.is_none() {
+            return Err("Error one of --code, --query, or --tag is required".to_owned());
+        }
+
+        // words of query are matched as whole words, code is matched as is
+        let patterns = match (query.as_ref(), code.as_ref()) {
+            (Some(query), code) => {
+                let mut required = Vec::new();
+                for phrase in query.phrases.iter() {
+                    required.push(words_pattern(phrase, case_sensitive)?);
+                }
+                let any_word = compile("query", &format!(r"(^|[^A-Za-z0-9_$])({})($|[^A-Za-z0-9_$])", query.words.iter().map(|w| regex::escape(w)).collect::<Vec<String>>().join("|")), case_sensitive)?;
+                match code {
+                    Some(code) => {
+                        required.push(any_word);
+                        Some(Patterns { required, lines: code.clone() })
+                    },
+                    None => Some(Patterns { required, lines: any_word }),
+                }
+            },
+            (None, Some(code)) => Some(Patterns { required: Vec::new(), lines: code.clone() }),
+            (None, None) => None,
+        };
+
+        let ranked = query.is_some();
+        let query = match (query, partials) {
+            (Some(mut query), partials) => {
+                query.partials = partials.unwrap_or_default();
+                Some(query)
+            },
+            (None, Some(partials)) => Some(search_index::Query { partials, ..Default::default() }),
+            (None, None) => None,
+        };
+        Ok(Self { chain, code, tag, query, ranked, patterns, limit, snippets })
+    }
+
+    /// Run search. Returns contracts matching, the most relevant first, and
+    /// numbers of contracts matching, and searched.
+    ///
+    /// # Arguments
+    /// * `dir` - directory of local cache
+    /// * `index` - index of local cache up to date, required if there are
+    ///   words to look up
+    fn run(&self, dir: &str, index: Option<&SearchIndex>) -> Result<(Vec<Hit>, usize, usize), String> {
+        let name_tags = cache::name_tags(dir)?;
+        let mut entries = cache::cached_entries(dir)?;
+        entries.retain(|e| self.chain.as_ref().is_none_or(|c| *c == e.chain));
+        let searched = entries.len();
+
+        // only contracts found in index are read, in order of relevance
+        let mut scores: HashMap<String, f64> = HashMap::new();
+        if let (Some(query), Some(index)) = (self.query.as_ref(), index) {
+            let found = index.search(query, self.chain.as_deref())?;
+            let mut by_path: HashMap<String, cache::CachedEntry> = entries.into_iter().map(|e| (e.relative_path.clone(), e)).collect();
+            entries = found.iter().filter_map(|(path, _)| by_path.remove(path)).collect();
+            scores = found.into_iter().collect();
+        }
+
+        let mut hits = Vec::new();
+        for entry in entries.iter() {
+            let name_tag = name_tags.get(&format!("{}/{}", entry.chain, entry.address)).filter(|t| !t.is_empty());
+            if let Some(tag) = self.tag.as_ref() {
+                let matches_tag = name_tag.is_some_and(|t| tag.is_match(&t.name_tag) || t.labels.iter().any(|l| tag.is_match(l)));
+                if !matches_tag {
+                    continue;
+                }
+            }
+            match search_entry(entry, self.patterns.as_ref(), name_tag, self.snippets) {
+                Ok(Some(mut hit)) => {
+                    if self.ranked {
+                        hit.score = scores.get(&entry.relative_path).copied();
+                    }
+                    hits.push(hit);
+                },
+                Ok(None) => (),
+                Err(e) => eprintln!("Warning: skipped {}; {}", entry.relative_path, e),
+            }
+        }
+        let total = hits.len();
+        if self.ranked {
+            hits.sort_by(|a, b| b.score.unwrap_or(0.0).total_cmp(&a.score.unwrap_or(0.0)).then_with(|| a.address.cmp(&b.address)));
+        }
+        else {
+            hits.sort_by(|a, b| b.matches.cmp(&a.matches).then_with(|| a.address.cmp(&b.address)));
+        }
+        hits.truncate(self.limit);
+        Ok((hits, total, searched))
+    }
+}
+
+/// Convert contracts matching to JSON as output with `--json`.
+///
+/// # Arguments
+/// * `hits` - contracts matching
+fn hits_json(hits: &[Hit]) -> Value {
+    hits.iter().map(|h| json!({
+        "chain": h.chain,
+        "address": h.address,
+        "contractName": h.contract_name,
+        "nameTag": h.name_tag.as_ref().map(|t| t.name_tag.clone()),
+        "labels": h.name_tag.as_ref().map_or(Vec::new(), |t| t.labels.clone()),
+        "score": h.score,
+        "matches": h.matches,
+        "snippets": h.snippets.iter().map(|(path, line, text)| json!({ "path": path, "line": line, "text": text })).collect::<Vec<Value>>(),
+    })).collect()
+}
+
+/// Minimum interval between bringing index up to date with cache while
+/// serving, so bursts of requests don't each list the whole cache
+const SERVE_SYNC_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Answer request of search server. Returns status, and JSON body.
+///
+/// # Arguments
+/// * `dir` - directory of local cache
+/// * `index` - index of local cache
+/// * `path` - path of request
+/// * `params` - parameters of query string of request
+fn respond(dir: &str, index: &SearchIndex, path: &str, params: &[(String, String)]) -> (&'static str, Value) {
+    if path != "/search" {
+        return ("404 Not Found", json!({ "error": format!("Error unknown path '{}'; use /search", path) }));
+    }
+    let param = |name: &str| params.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str());
+    let number = |name: &str, default: usize| match param(name) {
+        Some(value) => match value.parse::<usize>() {
+            Ok(res) => Ok(res),
+            Err(_) => Err(format!("Error invalid value for {} '{}'", name, value)),
+        },
+        None => Ok(default),
+    };
+    let parsed = number("limit", 20).and_then(|limit| {
+        let snippets = number("snippets", 3)?;
+        let case_sensitive = param("case-sensitive").is_some_and(|v| v == "true" || v == "1");
+        Search::parse(param("code"), param("query"), param("tag"), param("chain"), case_sensitive, limit, snippets)
+    });
+    let search = match parsed {
+        Ok(res) => res,
+        Err(e) => return ("400 Bad Request", json!({ "error": e })),
+    };
+    match search.run(dir, Some(index)) {
+        Ok((hits, _, _)) => ("200 OK", hits_json(&hits)),
+        Err(e) => ("500 Internal Server Error", json!({ "error": e })),
+    }
+}
+
+/// Serve searches over HTTP until the process is stopped.
+///
+/// # Arguments
+/// * `dir` - directory of local cache
+/// * `port` - port to listen on at 127.0.0.1
+fn serve(dir: &str, port: u16) -> Result<(), String> {
+    let index = SearchIndex::update(dir)?;
+    let listener = match std::net::TcpListener::bind(("127.0.0.1", port)) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error listening on port {}; err={}", port, e);
+            return Err(err_msg);
+        }
+    };
+
+    println!("Serving search of '{}' at http://127.0.0.1:{}/search", dir, port);
+    let mut synced = std::time::Instant::now();
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(res) => res,
+            Err(e) => {
+                eprintln!("Error accepting connection; err={}", e);
+                continue;
+            }
+        };
+        if synced.elapsed() >= SERVE_SYNC_INTERVAL {
+            if let Err(e) = index.sync() {
+                eprintln!("{}", e);
+            }
+            synced = std::time::Instant::now();
+        }
+        let res = crate::mock_server::read_request(&stream).and_then(|(path, params)| {
+            let (status, body) = respond(dir, &index, &path, &params);
+            crate::mock_server::write_json(&stream, status, &body.to_string())
+        });
+        if let Err(e) = res {
+            eprintln!("{}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Execute `search` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &SearchArgs) -> Result<(), String> {
+    let dir = match cache::settings() {
+        Some(res) => res.dir,
+        None => return Err("Error --cache-dir is required to locate local cache".to_owned()),
+    };
+    if let Some(port) = args.serve {
+        return serve(&dir, port);
+    }
+    let search = Search::parse(args.code.as_deref(), args.query.as_deref(), args.tag.as_deref(), args.chain.as_deref(), args.case_sensitive, args.limit, args.snippets)?;
+    let index = match search.query {
+        Some(_) => Some(SearchIndex::update(&dir)?),
+        None => None,
+    };
+    let (hits, total, searched) = search.run(&dir, index.as_ref())?;
+
+    if args.json {
+        match serde_json::to_string_pretty(&hits_json(&hits)) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing search results to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+        return Ok(());
+    }
+
+    for hit in hits.iter() {
+        let name_tag = hit.name_tag.as_ref().map_or(String::new(), |t| format!(" ({})", t.describe()));
+        if let Some(score) = hit.score {
+            println!("{} ({}) {}{} - {} match(es), score {:.2}", hit.address, hit.chain, hit.contract_name, name_tag, hit.matches, score);
+        }
+        else if search.code.is_some() {
+            println!("{} ({}) {}{} - {} match(es)", hit.address, hit.chain, hit.contract_name, name_tag, hit.matches);
+        }
+        else {
+            println!("{} ({}) {}{}", hit.address, hit.chain, hit.contract_name, name_tag);
+        }
+        for (path, line, text) in hit.snippets.iter() {
+            println!("  {}:{}: {}", path, line, text);
+        }
+    }
+    eprintln!("{} of {} cached contract(s) match", total, searched);
+    Ok(())
+}
@@ -0,0 +1,638 @@
+//! Full-text index of verified source code of contracts in local cache (see
+//! `cache`) backed by tantivy, so `search` finds contracts without decoding
+//! every one of them, ranked by relevance with BM25.
+//!
+//! Index is kept in directory `search-index` at root of local cache, and
+//! brought up to date with cache on each search; only entries added, or
+//! changed since are read, and ones removed from cache are deleted. Only
+//! updating index takes lock of its directory, so searching never blocks
+//! processes writing into cache, and searching up to date index takes none.
+//!
+//! Words are identifiers, and numbers of source code lowercased e.g.
+//! `delegatecall`, so `msg.sender` is indexed as `msg`, and `sender`. Words
+//! longer than `MAX_TOKEN_LEN` of tantivy (64 KiB) are left out.
+
+use crate::cache;
+use crate::explorer;
+use crate::source;
+use regex_syntax::hir::{Hir, HirKind};
+use std::collections::HashMap;
+use std::time::UNIX_EPOCH;
+use tantivy::collector::TopDocs;
+use tantivy::directory::MmapDirectory;
+use tantivy::query::Query as IndexQuery;
+use tantivy::query::{BooleanQuery, ConstScoreQuery, Occur, PhraseQuery, RegexQuery, TermQuery};
+use tantivy::schema::{Field, IndexRecordOption, Schema, TextFieldIndexing, TextOptions, Value, STORED, STRING};
+use tantivy::tokenizer::{LowerCaser, RegexTokenizer, TextAnalyzer, MAX_TOKEN_LEN};
+use tantivy::{DocAddress, Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, Term};
+
+/// Filename of index of earlier version at root of local cache, which is
+/// removed
+const LEGACY_INDEX_FILENAME: &str = "search-index.json";
+
+/// Version of index recorded with each commit; index of other version is
+/// rebuilt
+const INDEX_VERSION: &str = "tracpls-search-index-2";
+
+/// Name of tokenizer splitting source code into words
+const TOKENIZER: &str = "tracpls_words";
+
+/// Bytes of memory index writer uses before flushing to disk
+const WRITER_MEMORY: usize = 50_000_000;
+
+/// Query of index.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Query {
+    /// Words every contract found has to contain
+    pub words: Vec<String>,
+
+    /// Phrases contracts found have to contain as words in a row; their
+    /// words are in `words` as well
+    pub phrases: Vec<Vec<String>>,
+
+    /// Words contracts found must not contain
+    pub excluded: Vec<String>,
+
+    /// Words contracts found have to contain, possibly as part of longer word
+    /// e.g. ones of literal text of pattern of `--code`
+    pub partials: Vec<Partial>,
+}
+
+/// Word contracts found have to contain, possibly as part of longer word.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Partial {
+    /// Word lowercased
+    pub word: String,
+
+    /// Whether word may be preceded by more characters of word
+    pub open_start: bool,
+
+    /// Whether word may be followed by more characters of word
+    pub open_end: bool,
+}
+
+/// Whether character is part of word.
+///
+/// # Arguments
+/// * `c` - character
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '$'
+}
+
+/// Split text into words to be indexed, lowercased.
+///
+/// # Arguments
+/// * `text` - text e.g. source code
+pub fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !is_word_char(c))
+        .filter(|w| !w.is_empty() && w.len() <= MAX_TOKEN_LEN)
+        .map(|w| w.to_ascii_lowercase())
+}
+
+/// Parse query of words; all of them have to be contained, words in double
+/// quotes e.g. `"only owner"` have to be in a row, and words prefixed with
+/// `-` must not be contained.
+///
+/// # Arguments
+/// * `text` - query
+pub fn parse_query(text: &str) -> Result<Query, String> {
+    let mut query = Query::default();
+    for (i, part) in text.split('"').enumerate() {
+        // odd parts are inside double quotes
+        if i % 2 == 1 {
+            let phrase: Vec<String> = words(part).collect();
+            query.words.extend(phrase.iter().cloned());
+            if phrase.len() > 1 {
+                query.phrases.push(phrase);
+            }
+            continue;
+        }
+        for term in part.split_whitespace() {
+            match term.strip_prefix('-') {
+                Some(excluded) => query.excluded.extend(words(excluded)),
+                None => query.words.extend(words(term)),
+            }
+        }
+    }
+    query.words.sort();
+    query.words.dedup();
+
+    if query.words.is_empty() {
+        let err_msg = format!("Error invalid value for --query '{}'; it has no words to search for", text);
+        return Err(err_msg);
+    }
+    Ok(query)
+}
+
+/// Collect literal text every match of pattern has to contain.
+///
+/// # Arguments
+/// * `hir` - parsed pattern
+/// * `literals` - literal text collected
+fn required_literals(hir: &Hir, literals: &mut Vec<String>) {
+    match hir.kind() {
+        HirKind::Literal(literal) => literals.push(String::from_utf8_lossy(&literal.0).into_owned()),
+        HirKind::Capture(capture) => required_literals(&capture.sub, literals),
+        HirKind::Repetition(repetition) if repetition.min > 0 => required_literals(&repetition.sub, literals),
+        HirKind::Concat(hirs) => {
+            // adjacent literals are text in a row
+            let mut run = String::new();
+            for hir in hirs.iter() {
+                match hir.kind() {
+                    HirKind::Literal(literal) => run.push_str(&String::from_utf8_lossy(&literal.0)),
+                    _ => {
+                        if !run.is_empty() {
+                            literals.push(std::mem::take(&mut run));
+                        }
+                        required_literals(hir, literals);
+                    }
+                }
+            }
+            if !run.is_empty() {
+                literals.push(run);
+            }
+        },
+        // alternatives, classes, and optional parts require nothing
+        _ => (),
+    }
+}
+
+/// Get words every match of pattern e.g. of `--code` has to contain, so
+/// contracts to match it against are looked up in index. Returns `None` if
+/// pattern requires no word e.g. `\w+\(`, or can't be parsed.
+///
+/// # Arguments
+/// * `pattern` - regular expression
+pub fn pattern_partials(pattern: &str) -> Option<Vec<Partial>> {
+    let hir = regex_syntax::Parser::new().parse(pattern).ok()?;
+    let mut literals = Vec::new();
+    required_literals(&hir, &mut literals);
+
+    let mut partials = Vec::new();
+    for literal in literals.iter() {
+        let mut start = 0;
+        for (i, piece) in literal.split(|c: char| !is_word_char(c)).enumerate() {
+            let end = start + piece.len();
+            if !piece.is_empty() && piece.len() <= MAX_TOKEN_LEN {
+                partials.push(Partial {
+                    word: piece.to_ascii_lowercase(),
+                    open_start: i == 0,
+                    open_end: end == literal.len(),
+                });
+            }
+            // separator is a single character, but not always a single byte
+            start = end + literal[end..].chars().next().map_or(0, |c| c.len_utf8());
+        }
+    }
+    if partials.is_empty() {
+        return None;
+    }
+    Some(partials)
+}
+
+/// Fields of index.
+#[derive(Debug, Clone, Copy)]
+struct Fields {
+    /// Path of cache entry relative to cache directory e.g. `bsc/0xabc.json`
+    path: Field,
+
+    chain: Field,
+
+    /// Size, and modification time in nanoseconds of cache entry when
+    /// indexed, to tell whether it changed since
+    size: Field,
+    modified: Field,
+
+    /// Source code
+    content: Field,
+}
+
+/// Build schema of index.
+fn schema() -> (Schema, Fields) {
+    let mut builder = Schema::builder();
+    let content_options = TextOptions::default().set_indexing_options(
+        TextFieldIndexing::default().set_tokenizer(TOKENIZER).set_index_option(IndexRecordOption::WithFreqsAndPositions));
+    let fields = Fields {
+        path: builder.add_text_field("path", STRING | STORED),
+        chain: builder.add_text_field("chain", STRING | STORED),
+        size: builder.add_u64_field("size", STORED),
+        modified: builder.add_u64_field("modified", STORED),
+        content: builder.add_text_field("content", content_options),
+    };
+    (builder.build(), fields)
+}
+
+/// Size, and modification time in nanoseconds of file
+type Stamp = (u64, u64);
+
+/// Entries of cache not indexed as they are now along with their stamps, and
+/// paths of indexed contracts changed, or removed since
+type Changes = (Vec<(cache::CachedEntry, Stamp)>, Vec<String>);
+
+/// Get stamp of file.
+///
+/// # Arguments
+/// * `path` - path of file
+fn stamp(path: &std::path::Path) -> Stamp {
+    let metadata = std::fs::metadata(path).ok();
+    let size = metadata.as_ref().map_or(0, |m| m.len());
+    let modified = metadata.and_then(|m| m.modified().ok())
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos() as u64);
+    (size, modified)
+}
+
+/// Format error of tantivy.
+///
+/// # Arguments
+/// * `what` - what failed e.g. `opening search index`
+/// * `e` - error
+fn index_error(what: &str, e: impl std::fmt::Display) -> String {
+    format!("Error {}; err={}", what, e)
+}
+
+/// Index of source code of cached contracts.
+pub struct SearchIndex {
+    /// Directory of local cache
+    dir: String,
+
+    /// Directory of index
+    index_dir: String,
+
+    index: Index,
+    reader: IndexReader,
+    fields: Fields,
+}
+
+impl SearchIndex {
+    /// Open index of local cache, creating it if missing, or rebuilding it if
+    /// it's of other version. It isn't brought up to date; see `sync()`.
+    ///
+    /// # Arguments
+    /// * `dir` - directory of local cache
+    pub fn open(dir: &str) -> Result<Self, String> {
+        let index_dir = crate::combine_two_path_components(dir, cache::SEARCH_INDEX_DIRNAME)?;
+        let _ = std::fs::remove_file(crate::safe_path::fs_path(&crate::combine_two_path_components(dir, LEGACY_INDEX_FILENAME)?));
+        let (index, fields) = match Self::open_index(&index_dir)? {
+            Some(res) => res,
+            None => {
+                let _lock = crate::lock::DirLock::acquire(&index_dir)?;
+                match Self::open_index(&index_dir)? {
+                    Some(res) => res,
+                    None => Self::rebuild(&index_dir)?,
+                }
+            }
+        };
+        let reader = match index.reader_builder().reload_policy(ReloadPolicy::Manual).try_into() {
+            Ok(res) => res,
+            Err(e) => return Err(index_error(&format!("reading search index at '{}'", index_dir), e)),
+        };
+        Ok(Self { dir: dir.to_owned(), index_dir, index, reader, fields })
+    }
+
+    /// Open index of directory creating it if missing. Returns `None` if
+    /// index is of other version.
+    ///
+    /// # Arguments
+    /// * `index_dir` - directory of index
+    fn open_index(index_dir: &str) -> Result<Option<(Index, Fields)>, String> {
+        if let Err(e) = std::fs::create_dir_all(crate::safe_path::fs_path(index_dir)) {
+            return Err(index_error(&format!("creating search index at '{}'", index_dir), e));
+        }
+        let directory = match MmapDirectory::open(crate::safe_path::fs_path(index_dir)) {
+            Ok(res) => res,
+            Err(e) => return Err(index_error(&format!("opening search index at '{}'", index_dir), e)),
+        };
+        let (schema, fields) = schema();
+        let index = match Index::open_or_create(directory, schema) {
+            Ok(res) => res,
+            // schema of other version
+            Err(tantivy::TantivyError::SchemaError(_)) => return Ok(None),
+            Err(e) => return Err(index_error(&format!("opening search index at '{}'", index_dir), e)),
+        };
+        let metas = match index.load_metas() {
+            Ok(res) => res,
+            Err(e) => return Err(index_error(&format!("reading search index at '{}'", index_dir), e)),
+        };
+        // never committed yet, or committed by this version
+        if !metas.segments.is_empty() && metas.payload.as_deref() != Some(INDEX_VERSION) {
+            return Ok(None);
+        }
+
+        let analyzer = match RegexTokenizer::new("[A-Za-z0-9_$]+") {
+            Ok(res) => TextAnalyzer::builder(res).filter(LowerCaser).build(),
+            Err(e) => return Err(index_error("creating tokenizer of search index", e)),
+        };
+        index.tokenizers().register(TOKENIZER, analyzer);
+        Ok(Some((index, fields)))
+    }
+
+    /// Remove index of other version, and create an empty one while holding
+    /// lock of its directory.
+    ///
+    /// # Arguments
+    /// * `index_dir` - directory of index
+    fn rebuild(index_dir: &str) -> Result<(Index, Fields), String> {
+        eprintln!("Rebuilding search index at '{}' as it's of other version", index_dir);
+        let entries = match std::fs::read_dir(crate::safe_path::fs_path(index_dir)) {
+            Ok(res) => res,
+            Err(e) => return Err(index_error(&format!("reading search index at '{}'", index_dir), e)),
+        };
+        for entry in entries.flatten().filter(|e| e.file_name() != crate::lock::LOCK_FILENAME) {
+            if let Err(e) = std::fs::remove_file(entry.path()) {
+                return Err(index_error(&format!("removing search index at '{}'", entry.path().display()), e));
+            }
+        }
+        match Self::open_index(index_dir)? {
+            Some(res) => Ok(res),
+            None => {
+                let err_msg = format!("Error creating search index at '{}'", index_dir);
+                Err(err_msg)
+            }
+        }
+    }
+
+    /// Open index of local cache, and bring it up to date with cache.
+    ///
+    /// # Arguments
+    /// * `dir` - directory of local cache
+    pub fn update(dir: &str) -> Result<Self, String> {
+        let index = Self::open(dir)?;
+        index.sync()?;
+        Ok(index)
+    }
+
+    /// Get stamps of indexed contracts by path of their cache entry.
+    fn indexed(&self) -> Result<HashMap<String, Stamp>, String> {
+        let searcher = self.reader.searcher();
+        let mut indexed = HashMap::new();
+        for (ord, segment) in searcher.segment_readers().iter().enumerate() {
+            for doc_id in segment.doc_ids_alive() {
+                let doc: TantivyDocument = match searcher.doc(DocAddress::new(ord as u32, doc_id)) {
+                    Ok(res) => res,
+                    Err(e) => return Err(index_error(&format!("reading search index at '{}'", self.index_dir), e)),
+                };
+                let path = doc.get_first(self.fields.path).and_then(|v| v.as_str()).unwrap_or_default().to_owned();
+                let size = doc.get_first(self.fields.size).and_then(|v| v.as_u64()).unwrap_or_default();
+                let modified = doc.get_first(self.fields.modified).and_then(|v| v.as_u64()).unwrap_or_default();
+                indexed.insert(path, (size, modified));
+            }
+        }
+        Ok(indexed)
+    }
+
+    /// Get changes of cache since it was indexed.
+    fn changes(&self) -> Result<Changes, String> {
+        let indexed = self.indexed()?;
+        let entries = cache::cached_entries(&self.dir)?;
+        let stamped: HashMap<&str, Stamp> = entries.iter().map(|e| (e.relative_path.as_str(), stamp(&e.full_path))).collect();
+        let mut stale: Vec<String> = indexed.iter()
+            .filter(|&(path, s)| stamped.get(path.as_str()) != Some(s))
+            .map(|(path, _)| path.clone())
+            .collect();
+        stale.sort();
+        let added = entries.iter()
+            .filter(|e| indexed.get(&e.relative_path) != stamped.get(e.relative_path.as_str()))
+            .map(|e| (e.clone(), stamped[e.relative_path.as_str()]))
+            .collect();
+        Ok((added, stale))
+    }
+
+    /// Bring index up to date with cache. Entries which can't be read are
+    /// warned about, and left out. Returns number of contracts indexed, and
+    /// ones dropped from index as they're changed, or removed since.
+    pub fn sync(&self) -> Result<(usize, usize), String> {
+        let reload = |reader: &IndexReader| match reader.reload() {
+            Ok(_) => Ok(()),
+            Err(e) => Err(index_error(&format!("reading search index at '{}'", self.index_dir), e)),
+        };
+        let (added, stale) = self.changes()?;
+        if added.is_empty() && stale.is_empty() {
+            return Ok((0, 0));
+        }
+
+        // checked again under lock as another process may have done it
+        let _lock = crate::lock::DirLock::acquire(&self.index_dir)?;
+        reload(&self.reader)?;
+        let (added, stale) = self.changes()?;
+        if added.is_empty() && stale.is_empty() {
+            return Ok((0, 0));
+        }
+
+        let mut writer: IndexWriter<TantivyDocument> = match self.index.writer_with_num_threads(1, WRITER_MEMORY) {
+            Ok(res) => res,
+            Err(e) => return Err(index_error(&format!("writing search index at '{}'", self.index_dir), e)),
+        };
+        for path in stale.iter() {
+            writer.delete_term(Term::from_field_text(self.fields.path, path));
+        }
+        let mut indexed = 0;
+        for (entry, (size, modified)) in added.iter() {
+            let mut doc = TantivyDocument::default();
+            doc.add_text(self.fields.path, &entry.relative_path);
+            doc.add_text(self.fields.chain, &entry.chain);
+            doc.add_u64(self.fields.size, *size);
+            doc.add_u64(self.fields.modified, *modified);
+            let read = entry.read().and_then(|body| {
+                let raw = explorer::parse_source_meta_raw(&entry.address, &body)?;
+                source::for_each_source_raw(&entry.address, &raw, |f| {
+                    doc.add_text(self.fields.content, &f.content);
+                    Ok(())
+                })
+            });
+            if let Err(e) = read {
+                eprintln!("Warning: skipped indexing {}; {}", entry.relative_path, e);
+                continue;
+            }
+            // replacing one indexed before, if any
+            writer.delete_term(Term::from_field_text(self.fields.path, &entry.relative_path));
+            if let Err(e) = writer.add_document(doc) {
+                return Err(index_error(&format!("writing search index at '{}'", self.index_dir), e));
+            }
+            indexed += 1;
+        }
+
+        let mut prepared = match writer.prepare_commit() {
+            Ok(res) => res,
+            Err(e) => return Err(index_error(&format!("writing search index at '{}'", self.index_dir), e)),
+        };
+        prepared.set_payload(INDEX_VERSION);
+        if let Err(e) = prepared.commit() {
+            return Err(index_error(&format!("writing search index at '{}'", self.index_dir), e));
+        }
+        if let Err(e) = writer.wait_merging_threads() {
+            return Err(index_error(&format!("writing search index at '{}'", self.index_dir), e));
+        }
+        reload(&self.reader)?;
+        Ok((indexed, stale.len()))
+    }
+
+    /// Find contracts containing every word of query, its phrases, and
+    /// partial words, and none of excluded words, ranked by BM25 of words of
+    /// query; the most relevant first. Returns pairs of path of cache entry
+    /// relative to cache directory, and score.
+    ///
+    /// # Arguments
+    /// * `query` - query
+    /// * `chain` - name of chain to only find contracts of, if any
+    pub fn search(&self, query: &Query, chain: Option<&str>) -> Result<Vec<(String, f64)>, String> {
+        let term = |word: &str| Term::from_field_text(self.fields.content, word);
+        let mut clauses: Vec<(Occur, Box<dyn IndexQuery>)> = Vec::new();
+        for word in query.words.iter() {
+            clauses.push((Occur::Must, Box::new(TermQuery::new(term(word), IndexRecordOption::WithFreqs))));
+        }
+        for phrase in query.phrases.iter() {
+            clauses.push((Occur::Must, Box::new(PhraseQuery::new(phrase.iter().map(|w| term(w)).collect()))));
+        }
+        for word in query.excluded.iter() {
+            clauses.push((Occur::MustNot, Box::new(TermQuery::new(term(word), IndexRecordOption::Basic))));
+        }
+        for partial in query.partials.iter() {
+            if !partial.open_start && !partial.open_end {
+                clauses.push((Occur::Must, Box::new(TermQuery::new(term(&partial.word), IndexRecordOption::WithFreqs))));
+                continue;
+            }
+            let pattern = format!("{}{}{}",
+                if partial.open_start { ".*" } else { "" },
+                regex::escape(&partial.word),
+                if partial.open_end { ".*" } else { "" });
+            match RegexQuery::from_pattern(&pattern, self.fields.content) {
+                Ok(res) => clauses.push((Occur::Must, Box::new(res))),
+                Err(e) => return Err(index_error("building query of search index", e)),
+            }
+            // ranked by the word itself, as words merely containing it score
+            // the same
+            clauses.push((Occur::Should, Box::new(TermQuery::new(term(&partial.word), IndexRecordOption::WithFreqs))));
+        }
+        if let Some(chain) = chain {
+            let filter = TermQuery::new(Term::from_field_text(self.fields.chain, chain), IndexRecordOption::Basic);
+            clauses.push((Occur::Must, Box::new(ConstScoreQuery::new(Box::new(filter), 0.0))));
+        }
+        if !clauses.iter().any(|(occur, _)| *occur == Occur::Must) {
+            return Ok(Vec::new());
+        }
+
+        let searcher = self.reader.searcher();
+        let limit = (searcher.num_docs() as usize).max(1);
+        let found = match searcher.search(&BooleanQuery::new(clauses), &TopDocs::with_limit(limit)) {
+            Ok(res) => res,
+            Err(e) => return Err(index_error(&format!("searching index at '{}'", self.index_dir), e)),
+        };
+        let mut ranked = Vec::with_capacity(found.len());
+        for (score, address) in found {
+            let doc: TantivyDocument = match searcher.doc(address) {
+                Ok(res) => res,
+                Err(e) => return Err(index_error(&format!("reading search index at '{}'", self.index_dir), e)),
+            };
+            let path = doc.get_first(self.fields.path).and_then(|v| v.as_str()).unwrap_or_default().to_owned();
+            ranked.push((path, score as f64));
+        }
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(ranked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::TempWorkspace;
+
+    /// Write contract of single source file into local cache.
+    fn write_contract(dir: &str, relative_path: &str, source: &str) {
+        let body = serde_json::json!({
+            "status": "1",
+            "message": "OK",
+            "result": [{ "SourceCode": source, "ContractName": "C", "ABI": "[]" }],
+        });
+        cache::write_entry(&crate::combine_two_path_components(dir, relative_path).unwrap(), &body.to_string()).unwrap();
+    }
+
+    /// Search index for query returning paths found in order.
+    fn paths(index: &SearchIndex, query: &Query, chain: Option<&str>) -> Vec<String> {
+        index.search(query, chain).unwrap().into_iter().map(|(path, _)| path).collect()
+    }
+
+    #[test]
+    fn parse_query_reads_phrases_and_exclusions() {
+        let query = parse_query(r#"Owner "only owner" -Proxy"#).unwrap();
+        assert_eq!(query.words, vec!["only", "owner"]);
+        assert_eq!(query.phrases, vec![vec!["only".to_owned(), "owner".to_owned()]]);
+        assert_eq!(query.excluded, vec!["proxy"]);
+
+        // a single word in double quotes is just a word
+        let query = parse_query(r#""msg.sender" transfer"#).unwrap();
+        assert_eq!(query.words, vec!["msg", "sender", "transfer"]);
+        assert_eq!(query.phrases, vec![vec!["msg".to_owned(), "sender".to_owned()]]);
+
+        assert!(parse_query("-proxy").is_err());
+        assert!(parse_query(" \"\" ").is_err());
+    }
+
+    #[test]
+    fn pattern_partials_are_words_literal_text_requires() {
+        let partial = |word: &str, open_start: bool, open_end: bool| Partial { word: word.to_owned(), open_start, open_end };
+        assert_eq!(pattern_partials("delegateCall"), Some(vec![partial("delegatecall", true, true)]));
+        assert_eq!(pattern_partials(r"msg\.sender\s*=="), Some(vec![partial("msg", true, false), partial("sender", false, true)]));
+        assert_eq!(pattern_partials(r"(transfer|send)From\("), Some(vec![partial("from", true, false)]));
+        assert_eq!(pattern_partials(r"selfdestruct+"), Some(vec![partial("selfdestruc", true, true), partial("t", true, true)]));
+        assert_eq!(pattern_partials(r"\w+\("), None);
+        assert_eq!(pattern_partials(r"(a|b)?"), None);
+        assert_eq!(pattern_partials(r"("), None);
+    }
+
+    #[test]
+    fn search_ranks_by_bm25() {
+        let workspace = TempWorkspace::new("test-search-index").unwrap();
+        let dir = workspace.path_str();
+        write_contract(dir, "bsc/0xaa.sol.json", "contract A { address owner; function f() { require(msg.sender == owner); owner = address(0); } }");
+        write_contract(dir, "bsc/0xbb.sol.json", "contract B { uint a; uint b; uint c; uint d; uint e; uint f; uint g; address owner; }");
+        write_contract(dir, "ethereum/0xcc.sol.json", "contract C is Proxy { address owner; function g() { owner.delegatecall(data); } }");
+        write_contract(dir, "ethereum/0xdd.sol.json", "contract D { uint total; }");
+        let index = SearchIndex::update(dir).unwrap();
+
+        let found = index.search(&parse_query("owner").unwrap(), None).unwrap();
+        assert_eq!(found.iter().map(|(p, _)| p.as_str()).collect::<Vec<&str>>(), vec!["bsc/0xaa.sol.json", "ethereum/0xcc.sol.json", "bsc/0xbb.sol.json"]);
+        assert!(found[0].1 > found[1].1 && found[1].1 > found[2].1);
+
+        assert_eq!(paths(&index, &parse_query("owner -proxy").unwrap(), None), vec!["bsc/0xaa.sol.json", "bsc/0xbb.sol.json"]);
+        assert_eq!(paths(&index, &parse_query("\"msg sender\"").unwrap(), None), vec!["bsc/0xaa.sol.json"]);
+        assert_eq!(paths(&index, &parse_query("\"sender msg\"").unwrap(), None), Vec::<String>::new());
+        assert_eq!(paths(&index, &parse_query("owner").unwrap(), Some("ethereum")), vec!["ethereum/0xcc.sol.json"]);
+
+        // parts of words of --code
+        let code = Query { partials: pattern_partials("legatecal").unwrap(), ..Default::default() };
+        assert_eq!(paths(&index, &code, None), vec!["ethereum/0xcc.sol.json"]);
+        let code = Query { partials: pattern_partials(r"sender\s*==").unwrap(), ..Default::default() };
+        assert_eq!(paths(&index, &code, None), vec!["bsc/0xaa.sol.json"]);
+        let code = Query { partials: pattern_partials(r"\.ender").unwrap(), ..Default::default() };
+        assert_eq!(paths(&index, &code, None), Vec::<String>::new());
+    }
+
+    #[test]
+    fn sync_reindexes_only_changed_entries() {
+        let workspace = TempWorkspace::new("test-search-index").unwrap();
+        let dir = workspace.path_str();
+        write_contract(dir, "bsc/0xaa.sol.json", "contract A { function pause() {} }");
+        write_contract(dir, "bsc/0xbb.sol.json", "contract B { function pause() {} }");
+        let index = SearchIndex::open(dir).unwrap();
+        assert_eq!(index.sync().unwrap(), (2, 0));
+        assert_eq!(index.sync().unwrap(), (0, 0));
+
+        // changed entry is dropped, and indexed again
+        write_contract(dir, "bsc/0xaa.sol.json", "contract A { function unpause() {} }");
+        assert_eq!(index.sync().unwrap(), (1, 1));
+        assert_eq!(paths(&index, &parse_query("pause").unwrap(), None), vec!["bsc/0xbb.sol.json"]);
+        assert_eq!(paths(&index, &parse_query("unpause").unwrap(), None), vec!["bsc/0xaa.sol.json"]);
+
+        // removed entry is dropped
+        std::fs::remove_file(workspace.path().join("bsc/0xbb.sol.json")).unwrap();
+        assert_eq!(index.sync().unwrap(), (0, 1));
+        assert_eq!(paths(&index, &parse_query("pause").unwrap(), None), Vec::<String>::new());
+
+        // index is kept across processes
+        drop(index);
+        let index = SearchIndex::open(dir).unwrap();
+        assert_eq!(index.sync().unwrap(), (0, 0));
+        assert_eq!(paths(&index, &parse_query("unpause").unwrap(), None), vec!["bsc/0xaa.sol.json"]);
+    }
+}
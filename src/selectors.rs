@@ -0,0 +1,482 @@
+//! Local database of function selectors, event topics, and error selectors
+//! mapped to their signatures, built from every ABI tracpls fetches. It names
+//! calls, and events of contracts whose ABI isn't known e.g. unverified ones.
+//!
+//! Database is at path of environment variable `TRACPLS_SELECTORS`, or
+//! `~/.config/tracpls/selectors.json`. Signatures seen during a run are
+//! merged into it once at the end. `selectors export`, and `selectors import`
+//! share it with others.
+//!
+//! `selectors topics` exports registry of event topics of ABIs on disk e.g.
+//! fetched by `batch --abi-only` for log-filtering infrastructure.
+
+use crate::abi::{self, Abi};
+use crate::export::csv_field;
+use clap::{Args, Subcommand};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Mutex;
+
+#[derive(Debug, Args)]
+pub struct SelectorsArgs {
+    #[clap(subcommand)]
+    pub action: SelectorsAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SelectorsAction {
+    /// Write the database as JSON to share it
+    #[clap(name="export")]
+    Export(SelectorsExportArgs),
+
+    /// Merge database exported by others into the local one
+    #[clap(name="import")]
+    Import(SelectorsImportArgs),
+
+    /// Print signatures known for a function selector, event topic, or error selector
+    #[clap(name="lookup")]
+    Lookup(SelectorsLookupArgs),
+
+    /// Export registry of topic0 of events to their signatures from ABIs on disk
+    #[clap(name="topics")]
+    Topics(SelectorsTopicsArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct SelectorsExportArgs {
+    /// Filepath to write database to. If not specified, it will be printed to
+    /// stdout.
+    #[clap(long="out", short='o', required=false)]
+    pub out_path: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct SelectorsImportArgs {
+    /// Path of database made by 'selectors export'
+    #[clap(value_name="FILE", required=true)]
+    pub path: String,
+}
+
+#[derive(Debug, Args)]
+pub struct SelectorsLookupArgs {
+    /// Selector, or topic as hex string e.g. '0xa9059cbb'
+    #[clap(value_name="SELECTOR", required=true)]
+    pub selector: String,
+}
+
+#[derive(Debug, Args)]
+pub struct SelectorsTopicsArgs {
+    /// ABI files, or directories searched recursively for them e.g. output
+    /// directory of 'batch --abi-only'. Hardhat, and Foundry artifacts
+    /// holding 'abi' are read as well.
+    #[clap(value_name="PATH", required=true, min_values=1)]
+    pub paths: Vec<String>,
+
+    /// Output format.
+    /// Possible values are 'json', and 'csv'.
+    #[clap(long="format", required=false, default_value="json")]
+    pub format: String,
+
+    /// Filepath to write registry to. If not specified, it will be printed to
+    /// stdout.
+    #[clap(long="out", short='o', required=false)]
+    pub out_path: Option<String>,
+}
+
+/// Signatures by their selector, or topic as hex string with `0x` prefix.
+type Signatures = BTreeMap<String, BTreeSet<String>>;
+
+/// Database of signatures.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelectorDb {
+    #[serde(default)]
+    pub functions: Signatures,
+
+    /// Signatures of events by their topic
+    #[serde(default)]
+    pub events: Signatures,
+
+    #[serde(default)]
+    pub errors: Signatures,
+}
+
+impl SelectorDb {
+    /// Add signatures of functions, events, and errors of ABI.
+    ///
+    /// # Arguments
+    /// * `abi` - contract ABI
+    pub fn add_abi(&mut self, abi: &Abi) {
+        for f in abi.functions() {
+            self.functions.entry(f.selector()).or_default().insert(f.signature());
+        }
+        for e in abi.events().filter(|e| !e.anonymous) {
+            let topic = format!("0x{}", abi::to_hex(&abi::keccak256(e.signature().as_bytes())));
+            self.events.entry(topic).or_default().insert(e.signature());
+        }
+        for e in abi.errors() {
+            self.errors.entry(e.selector()).or_default().insert(e.signature());
+        }
+    }
+
+    /// Merge other database into this one. Returns number of signatures not
+    /// known before.
+    ///
+    /// # Arguments
+    /// * `other` - database to merge
+    pub fn merge(&mut self, other: SelectorDb) -> usize {
+        let mut added = 0;
+        for (ours, theirs) in [(&mut self.functions, other.functions), (&mut self.events, other.events), (&mut self.errors, other.errors)] {
+            for (selector, signatures) in theirs {
+                let known = ours.entry(selector.to_lowercase()).or_default();
+                for signature in signatures {
+                    if known.insert(signature) {
+                        added += 1;
+                    }
+                }
+            }
+        }
+        added
+    }
+
+    /// Get signatures known for function selector, event topic, or error
+    /// selector.
+    ///
+    /// # Arguments
+    /// * `selector` - selector, or topic as hex string with `0x` prefix, case-insensitive
+    pub fn lookup(&self, selector: &str) -> Vec<&str> {
+        let selector = selector.to_lowercase();
+        [&self.functions, &self.events, &self.errors].iter()
+            .filter_map(|signatures| signatures.get(&selector))
+            .flat_map(|s| s.iter().map(|s| s.as_str()))
+            .collect()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.functions.is_empty() && self.events.is_empty() && self.errors.is_empty()
+    }
+}
+
+/// Signatures seen during this run not yet merged into database
+static PENDING: Mutex<Option<SelectorDb>> = Mutex::new(None);
+
+/// Index signatures of ABI to be merged into database at the end of run.
+/// ABI which can't be parsed e.g. "Contract source code not verified" is
+/// ignored.
+///
+/// # Arguments
+/// * `text` - ABI in JSON
+pub fn index_abi(text: &str) {
+    if let (Ok(abi), Ok(mut pending)) = (Abi::parse(text), PENDING.lock()) {
+        pending.get_or_insert_with(SelectorDb::default).add_abi(&abi);
+    }
+}
+
+/// Get path of database, or `None` if home directory isn't known.
+fn db_path() -> Option<String> {
+    if let Ok(path) = std::env::var("TRACPLS_SELECTORS") {
+        return Some(path);
+    }
+    let home = std::env::var("HOME").ok()?;
+    crate::combine_two_path_components(&home, ".config/tracpls/selectors.json").ok()
+}
+
+/// Read database from file.
+///
+/// # Arguments
+/// * `path` - path of database
+fn read_db(path: &str) -> Result<SelectorDb, String> {
+    if !std::path::Path::new(path).exists() {
+        return Ok(SelectorDb::default());
+    }
+    let content = match std::fs::read_to_string(path) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error reading selector database at '{}'; err={}", path, e);
+            return Err(err_msg);
+        }
+    };
+    match serde_json::from_str(&content) {
+        Ok(res) => Ok(res),
+        Err(e) => {
+            let err_msg = format!("Error parsing selector database at '{}'; err={}", path, e);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Serialize database as JSON text.
+///
+/// # Arguments
+/// * `db` - database
+fn to_json(db: &SelectorDb) -> Result<String, String> {
+    match serde_json::to_string_pretty(db) {
+        Ok(res) => Ok(format!("{}\n", res)),
+        Err(e) => {
+            let err_msg = format!("Error serializing selector database to JSON; err={}", e);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Merge signatures into database on disk while holding lock against other
+/// processes. Returns number of signatures not known before.
+///
+/// # Arguments
+/// * `path` - path of database
+/// * `other` - database to merge
+fn merge_into(path: &str, other: SelectorDb) -> Result<usize, String> {
+    let dir = std::path::Path::new(path).parent().map_or(".".to_owned(), |p| p.to_string_lossy().into_owned());
+    let _lock = crate::lock::DirLock::acquire(if dir.is_empty() { "." } else { &dir })?;
+    let mut db = read_db(path)?;
+    let added = db.merge(other);
+    if added > 0 {
+        crate::create_intermediate_dirs(path)?;
+        crate::write_file(path, &to_json(&db)?)?;
+    }
+    Ok(added)
+}
+
+/// Merge signatures seen during this run into database.
+pub fn flush() -> Result<(), String> {
+    let pending = match PENDING.lock().ok().and_then(|mut p| p.take()) {
+        Some(res) if !res.is_empty() => res,
+        _ => return Ok(()),
+    };
+    match db_path() {
+        Some(path) => merge_into(&path, pending).map(|_| ()),
+        None => Ok(()),
+    }
+}
+
+/// Get signatures known for selector, or topic from database, including ones
+/// seen during this run.
+///
+/// # Arguments
+/// * `selector` - selector, or topic as hex string with `0x` prefix
+pub fn lookup(selector: &str) -> Vec<String> {
+    let mut db = db_path().and_then(|p| read_db(&p).ok()).unwrap_or_default();
+    if let Some(pending) = PENDING.lock().ok().and_then(|p| p.clone()) {
+        db.merge(pending);
+    }
+    db.lookup(selector).into_iter().map(|s| s.to_owned()).collect()
+}
+
+/// Declaration of event with a particular set of indexed parameters. Events
+/// of the same signature share topic0, but their logs decode differently if
+/// different parameters are indexed e.g. `Transfer` of ERC-20, and ERC-721.
+#[derive(Debug)]
+struct EventVariant {
+    /// Positions of indexed parameters
+    indexed: Vec<usize>,
+
+    /// Declaration as first seen e.g. `event Transfer(address indexed from, ...)`
+    declaration: String,
+
+    /// Files declaring it
+    sources: BTreeSet<String>,
+}
+
+/// Collect ABI files under paths; directories are searched recursively for
+/// `.json` files.
+///
+/// # Arguments
+/// * `path` - file, or directory
+/// * `files` - collected files
+fn collect_json_files(path: &std::path::Path, files: &mut Vec<std::path::PathBuf>) -> Result<(), String> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let read_dir = match std::fs::read_dir(path) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error reading directory '{}'; err={}", path.display(), e);
+            return Err(err_msg);
+        }
+    };
+    let mut entries: Vec<std::path::PathBuf> = read_dir.flatten().map(|e| e.path()).collect();
+    entries.sort();
+    for entry in entries.iter() {
+        if entry.is_dir() || entry.extension().is_some_and(|e| e == "json") {
+            collect_json_files(entry, files)?;
+        }
+    }
+    Ok(())
+}
+
+/// Read ABI from file of plain ABI, or artifact holding it in `abi`. Returns
+/// `None` if it's neither e.g. manifest, or report of batch.
+///
+/// # Arguments
+/// * `path` - path of file
+fn read_abi_file(path: &std::path::Path) -> Option<Abi> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let json: serde_json::Value = serde_json::from_str(&content).ok()?;
+    let abi = if json.is_array() { &json } else { json.get("abi")? };
+    Abi::parse(&abi.to_string()).ok()
+}
+
+/// Get declaration of event e.g. `event Transfer(address indexed from, ...)`.
+///
+/// # Arguments
+/// * `event` - event entry of ABI
+fn declaration_of(event: &abi::Entry) -> String {
+    let params: Vec<String> = event.inputs.iter().map(|p| {
+        let mut param = p.canonical_type();
+        if p.indexed {
+            param.push_str(" indexed");
+        }
+        if !p.name.is_empty() {
+            param.push(' ');
+            param.push_str(&p.name);
+        }
+        param
+    }).collect();
+    format!("event {}({})", event.name, params.join(", "))
+}
+
+/// Export registry of topic0 of events declared in ABIs under paths, warning
+/// about topics declared with different indexed parameters.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+fn export_topics(args: &SelectorsTopicsArgs) -> Result<(), String> {
+    let format = args.format.to_lowercase();
+    if format != "json" && format != "csv" {
+        return Err("Error invalid value for --format.\nPossible values are 'json', or 'csv'.".to_owned());
+    }
+
+    let mut files = Vec::new();
+    for path in args.paths.iter() {
+        let path = std::path::Path::new(path);
+        if !path.exists() {
+            let err_msg = format!("Error '{}' doesn't exist", path.display());
+            return Err(err_msg);
+        }
+        collect_json_files(path, &mut files)?;
+    }
+
+    // signature, and its variants by topic0
+    let mut topics: BTreeMap<String, (String, Vec<EventVariant>)> = BTreeMap::new();
+    let mut read = 0;
+    for file in files.iter() {
+        let abi = match read_abi_file(file) {
+            Some(res) => res,
+            None => continue,
+        };
+        read += 1;
+        for event in abi.events().filter(|e| !e.anonymous) {
+            let signature = event.signature();
+            let topic = format!("0x{}", abi::to_hex(&abi::keccak256(signature.as_bytes())));
+            let indexed: Vec<usize> = event.inputs.iter().enumerate().filter(|(_, p)| p.indexed).map(|(i, _)| i).collect();
+            let (_, variants) = topics.entry(topic).or_insert_with(|| (signature, Vec::new()));
+            let index = match variants.iter().position(|v| v.indexed == indexed) {
+                Some(res) => res,
+                None => {
+                    variants.push(EventVariant { indexed, declaration: declaration_of(event), sources: BTreeSet::new() });
+                    variants.len() - 1
+                }
+            };
+            variants[index].sources.insert(file.to_string_lossy().into_owned());
+        }
+    }
+
+    for (topic, (signature, variants)) in topics.iter().filter(|(_, (_, v))| v.len() > 1) {
+        let described: Vec<String> = variants.iter().map(|v| format!("[{}] in {} file(s) e.g. {}", v.indexed.iter().map(|i| i.to_string()).collect::<Vec<String>>().join(","),
+            v.sources.len(), v.sources.iter().next().map_or("", |s| s.as_str()))).collect();
+        eprintln!("Warning: {} of {} is declared with different indexed parameters; {}", topic, signature, described.join("; "));
+    }
+    eprintln!("{} event topic(s) from {} ABI file(s)", topics.len(), read);
+
+    let content = if format == "json" {
+        let registry: serde_json::Map<String, serde_json::Value> = topics.iter().map(|(topic, (signature, variants))| (topic.clone(), serde_json::json!({
+            "signature": signature,
+            "conflict": variants.len() > 1,
+            "variants": variants.iter().map(|v| serde_json::json!({
+                "indexed": v.indexed,
+                "declaration": v.declaration,
+                "sources": v.sources,
+            })).collect::<Vec<serde_json::Value>>(),
+        }))).collect();
+        match serde_json::to_string_pretty(&registry) {
+            Ok(res) => format!("{}\n", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing topic registry to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+    }
+    else {
+        let mut csv = String::from("topic0,signature,indexed,declaration,sources\n");
+        for (topic, (signature, variants)) in topics.iter() {
+            for v in variants.iter() {
+                let indexed = v.indexed.iter().map(|i| i.to_string()).collect::<Vec<String>>().join(";");
+                csv.push_str(&format!("{},{},{},{},{}\n", topic, csv_field(signature), indexed, csv_field(&v.declaration), v.sources.len()));
+            }
+        }
+        csv
+    };
+
+    match args.out_path.as_ref() {
+        Some(out_path) => {
+            crate::create_intermediate_dirs(out_path)?;
+            crate::write_file(out_path, &content)
+        },
+        None => {
+            print!("{}", content);
+            Ok(())
+        }
+    }
+}
+
+/// Execute `selectors` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &SelectorsArgs) -> Result<(), String> {
+    // registry is built from ABIs on disk, not from database
+    if let SelectorsAction::Topics(args) = &args.action {
+        return export_topics(args);
+    }
+    let path = match db_path() {
+        Some(res) => res,
+        None => return Err("Error neither TRACPLS_SELECTORS, nor HOME is set to locate selector database".to_owned()),
+    };
+
+    match &args.action {
+        SelectorsAction::Export(args) => {
+            let content = to_json(&read_db(&path)?)?;
+            match args.out_path.as_ref() {
+                Some(out_path) => {
+                    crate::create_intermediate_dirs(out_path)?;
+                    crate::write_file(out_path, &content)
+                },
+                None => {
+                    print!("{}", content);
+                    Ok(())
+                }
+            }
+        },
+        SelectorsAction::Import(args) => {
+            if !std::path::Path::new(&args.path).exists() {
+                let err_msg = format!("Error selector database to import at '{}' doesn't exist", args.path);
+                return Err(err_msg);
+            }
+            let added = merge_into(&path, read_db(&args.path)?)?;
+            eprintln!("Imported {} new signature(s) into {}", added, path);
+            Ok(())
+        },
+        SelectorsAction::Lookup(args) => {
+            let signatures = lookup(&args.selector);
+            if signatures.is_empty() {
+                let err_msg = format!("Error no signature known for {}", args.selector);
+                return Err(err_msg);
+            }
+            for signature in signatures.iter() {
+                println!("{}", signature);
+            }
+            Ok(())
+        },
+        SelectorsAction::Topics(_) => Ok(()),
+    }
+}
@@ -0,0 +1,196 @@
+//! `settings-diff` command: compare compiler settings used for verification
+//! of contract against those of local Foundry project in `foundry.toml`, to
+//! catch local build drifting from what's deployed.
+//!
+//! Keys missing from the selected profile are taken from `default` profile as
+//! Foundry does, then from defaults of Foundry. Version of solc, and EVM
+//! version left unset are auto-detected by Foundry, so they're reported but
+//! not counted as discrepancy.
+
+use crate::explorer;
+use crate::solc;
+use crate::TargetArgs;
+use clap::Args;
+use serde_json::{json, Value};
+
+#[derive(Debug, Args)]
+pub struct SettingsDiffArgs {
+    #[clap(flatten)]
+    pub target: TargetArgs,
+
+    /// Path of foundry.toml of local project
+    #[clap(long="local", required=true)]
+    pub local: String,
+
+    /// Profile of foundry.toml to compare
+    #[clap(long="profile", required=false, default_value="default")]
+    pub profile: String,
+
+    /// Output as JSON instead of human readable text
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+}
+
+/// Comparison of a single setting.
+struct Comparison {
+    /// Name of setting as of foundry.toml
+    setting: &'static str,
+
+    on_chain: String,
+
+    /// Value in local project, or `None` if left unset to be auto-detected
+    local: Option<String>,
+}
+
+impl Comparison {
+    fn status(&self) -> &'static str {
+        match self.local.as_ref() {
+            None => "unset",
+            Some(local) if *local == self.on_chain => "same",
+            Some(_) => "differs",
+        }
+    }
+}
+
+/// Read profile of foundry.toml; keys missing from it are taken from
+/// `default` profile.
+///
+/// # Arguments
+/// * `path` - path of foundry.toml
+/// * `profile` - name of profile
+fn read_profile(path: &str, profile: &str) -> Result<toml::value::Table, String> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error reading foundry.toml at '{}'; err={}", path, e);
+            return Err(err_msg);
+        }
+    };
+    let config: toml::Value = match toml::from_str(&content) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error parsing foundry.toml at '{}'; err={}", path, e);
+            return Err(err_msg);
+        }
+    };
+
+    let table = |name: &str| config.get("profile").and_then(|p| p.get(name)).and_then(|t| t.as_table()).cloned();
+    let mut merged = table("default").unwrap_or_default();
+    if profile != "default" {
+        match table(profile) {
+            Some(selected) => merged.extend(selected),
+            None => {
+                let err_msg = format!("Error profile '{}' not found in '{}'", profile, path);
+                return Err(err_msg);
+            }
+        }
+    }
+    Ok(merged)
+}
+
+/// Get version of solc set in profile, or `None` if unset. Path to solc
+/// binary is asked for its version.
+///
+/// # Arguments
+/// * `profile` - profile of foundry.toml
+fn local_solc_version(profile: &toml::value::Table) -> Result<Option<String>, String> {
+    let value = match profile.get("solc").or_else(|| profile.get("solc_version")).and_then(|v| v.as_str()) {
+        Some(res) => res.trim(),
+        None => return Ok(None),
+    };
+    if value.contains('/') || value.contains('\\') {
+        return solc::binary_version(std::path::Path::new(value)).map(Some);
+    }
+    Ok(Some(value.trim_start_matches('=').trim_start_matches('v').to_owned()))
+}
+
+/// Compare verified settings against profile of foundry.toml.
+///
+/// # Arguments
+/// * `meta` - verification metadata
+/// * `profile` - profile of foundry.toml
+fn compare(meta: &Value, profile: &toml::value::Table) -> Result<Vec<Comparison>, String> {
+    let settings = solc::verified_settings(meta);
+    let version = solc::plain_version(meta["CompilerVersion"].as_str().unwrap_or(""));
+    let bool_of = |key: &str, default: bool| profile.get(key).and_then(|v| v.as_bool()).unwrap_or(default);
+
+    Ok(vec![
+        Comparison {
+            setting: "solc",
+            on_chain: version.clone(),
+            local: local_solc_version(profile)?,
+        },
+        Comparison {
+            setting: "optimizer",
+            on_chain: settings["optimizer"]["enabled"].as_bool().unwrap_or(false).to_string(),
+            local: Some(bool_of("optimizer", false).to_string()),
+        },
+        Comparison {
+            setting: "optimizer_runs",
+            on_chain: settings["optimizer"]["runs"].as_u64().unwrap_or(200).to_string(),
+            local: Some(profile.get("optimizer_runs").and_then(|v| v.as_integer()).unwrap_or(200).to_string()),
+        },
+        Comparison {
+            setting: "evm_version",
+            on_chain: settings["evmVersion"].as_str().unwrap_or(solc::default_evm_version(&version)).to_lowercase(),
+            local: profile.get("evm_version").and_then(|v| v.as_str()).map(|v| v.to_lowercase()),
+        },
+        Comparison {
+            setting: "via_ir",
+            on_chain: settings["viaIR"].as_bool().unwrap_or(false).to_string(),
+            local: Some(bool_of("via_ir", false).to_string()),
+        },
+    ])
+}
+
+/// Execute `settings-diff` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &SettingsDiffArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.target.chain)?;
+    let address = args.target.address.to_lowercase();
+    let profile = read_profile(&args.local, &args.profile)?;
+    let meta = explorer::get_source_meta(chain, &address)?;
+    if meta["CompilerVersion"].as_str().unwrap_or("").is_empty() {
+        let err_msg = format!("Error {} is not verified", address);
+        return Err(err_msg);
+    }
+
+    let comparisons = compare(&meta, &profile)?;
+    let differing = comparisons.iter().filter(|c| c.status() == "differs").count();
+
+    if args.json {
+        let out = json!({
+            "address": address,
+            "local": args.local,
+            "profile": args.profile,
+            "settings": comparisons.iter().map(|c| json!({
+                "setting": c.setting,
+                "onChain": c.on_chain,
+                "local": c.local,
+                "status": c.status(),
+            })).collect::<Vec<Value>>(),
+        });
+        match serde_json::to_string_pretty(&out) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing settings comparison to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+    }
+    else {
+        println!("{:<15} {:<12} {:<12} STATUS", "SETTING", "ON-CHAIN", "LOCAL");
+        for c in comparisons.iter() {
+            let local = c.local.as_deref().unwrap_or("(auto)");
+            println!("{:<15} {:<12} {:<12} {}", c.setting, c.on_chain, local, c.status());
+        }
+    }
+
+    if differing > 0 {
+        let err_msg = format!("Error {} setting(s) differ between {}, and profile '{}' of {}", differing, address, args.profile, args.local);
+        return Err(err_msg);
+    }
+    Ok(())
+}
@@ -0,0 +1,369 @@
+//! `simulate` command: call any function of contract read-only with
+//! `eth_call`, including state-changing ones, and decode what it returns, or
+//! why it reverts, using ABI of contract. Nothing is sent on chain.
+//!
+//! Function is taken by name, or signature from ABI of contract, or of its
+//! implementation if it's a proxy. Signature e.g. `swap(uint256,address)`
+//! works for unverified contracts as well, but return values are shown as
+//! hex then.
+//!
+//! `--from`, `--value`, and state overrides need node at `ETH_RPC_URL`
+//! serving the chain (see `foundry`) as `proxy` module of API platforms
+//! doesn't take them.
+
+use crate::abi::{self, Abi, Entry, Param};
+use crate::decode;
+use crate::explorer;
+use crate::foundry;
+use crate::proxy;
+use clap::Args;
+use serde_json::{json, Map, Value};
+
+#[derive(Debug, Args)]
+pub struct SimulateArgs {
+    /// Target contract address
+    #[clap(long="address", short='a', required=true)]
+    pub address: String,
+
+    /// Which chain to work with, by name e.g. 'bsc', 'ethereum', 'polygon',
+    /// and 'arbitrum', or chain id e.g. '56'. See 'tracpls chains' for all.
+    #[clap(long="chain", short='c', required=true, multiple_values=false)]
+    pub chain: String,
+
+    /// Function name e.g. 'swap', or signature e.g. 'swap(uint256,address)'
+    #[clap(value_name="FUNCTION", required=true)]
+    pub function: String,
+
+    /// Arguments of function
+    #[clap(value_name="ARGS", required=false)]
+    pub args: Vec<String>,
+
+    /// Address to call from
+    #[clap(long="from", required=false)]
+    pub from: Option<String>,
+
+    /// Native currency to send along in wei, decimal or hex
+    #[clap(long="value", required=false)]
+    pub value: Option<String>,
+
+    /// Override balance of address in wei as 'ADDRESS=WEI'
+    #[clap(long="balance-override", required=false, multiple_occurrences=true, value_name="ADDRESS=WEI")]
+    pub balance_overrides: Vec<String>,
+
+    /// Override storage slot of contract as 'ADDRESS:SLOT=VALUE'
+    #[clap(long="storage-override", required=false, multiple_occurrences=true, value_name="ADDRESS:SLOT=VALUE")]
+    pub storage_overrides: Vec<String>,
+
+    /// Override code of address as 'ADDRESS=BYTECODE'
+    #[clap(long="code-override", required=false, multiple_occurrences=true, value_name="ADDRESS=BYTECODE")]
+    pub code_overrides: Vec<String>,
+
+    /// Output as JSON instead of human readable text
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+}
+
+/// Outcome of call.
+enum Outcome {
+    Returned(Vec<u8>),
+    Reverted(Vec<u8>),
+}
+
+/// Convert integer in decimal, or hex into minimal hex quantity of JSON-RPC
+/// e.g. `0xde0b6b3a7640000`.
+///
+/// # Arguments
+/// * `value` - integer
+fn to_quantity(value: &str) -> Result<String, String> {
+    let word = abi::encode_word("uint256", value)?;
+    let hex = abi::to_hex(&word);
+    let trimmed = hex.trim_start_matches('0');
+    Ok(format!("0x{}", if trimmed.is_empty() { "0" } else { trimmed }))
+}
+
+/// Convert value into 32-byte hex word of storage slot, or value.
+///
+/// # Arguments
+/// * `value` - integer in decimal, or hex
+fn to_word(value: &str) -> Result<String, String> {
+    Ok(format!("0x{}", abi::to_hex(&abi::encode_word("uint256", value)?)))
+}
+
+/// Get override of address within state overrides, adding one if none.
+///
+/// # Arguments
+/// * `overrides` - state overrides
+/// * `address` - address to override
+fn override_of<'a>(overrides: &'a mut Map<String, Value>, address: &str) -> Result<&'a mut Map<String, Value>, String> {
+    abi::encode_word("address", address)?;
+    let value = overrides.entry(address.to_lowercase()).or_insert_with(|| json!({}));
+    match value.as_object_mut() {
+        Some(res) => Ok(res),
+        None => {
+            let err_msg = format!("Error invalid override of {}", address);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Build state overrides of `eth_call` from flag options.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+fn state_overrides(args: &SimulateArgs) -> Result<Map<String, Value>, String> {
+    let mut overrides: Map<String, Value> = Map::new();
+    let split = |text: &str, separator: char, form: &str| -> Result<(String, String), String> {
+        match text.split_once(separator) {
+            Some((a, b)) => Ok((a.trim().to_owned(), b.trim().to_owned())),
+            None => {
+                let err_msg = format!("Error invalid override '{}'; expected {}", text, form);
+                Err(err_msg)
+            }
+        }
+    };
+
+    for text in args.balance_overrides.iter() {
+        let (address, wei) = split(text, '=', "ADDRESS=WEI")?;
+        override_of(&mut overrides, &address)?.insert("balance".to_owned(), json!(to_quantity(&wei)?));
+    }
+    for text in args.storage_overrides.iter() {
+        let (target, value) = split(text, '=', "ADDRESS:SLOT=VALUE")?;
+        let (address, slot) = split(&target, ':', "ADDRESS:SLOT=VALUE")?;
+        let state_diff = override_of(&mut overrides, &address)?.entry("stateDiff").or_insert_with(|| json!({}));
+        state_diff[to_word(&slot)?] = json!(to_word(&value)?);
+    }
+    for text in args.code_overrides.iter() {
+        let (address, code) = split(text, '=', "ADDRESS=BYTECODE")?;
+        override_of(&mut overrides, &address)?.insert("code".to_owned(), json!(format!("0x{}", abi::to_hex(&abi::from_hex(&code)?))));
+    }
+    Ok(overrides)
+}
+
+/// Find function to call by name, or signature along with number of
+/// arguments in ABI.
+///
+/// # Arguments
+/// * `abi` - ABI of contract
+/// * `function` - function name, or signature
+/// * `arg_count` - number of arguments given
+fn find_function(abi: &Abi, function: &str, arg_count: usize) -> Result<Entry, String> {
+    let candidates: Vec<&Entry> = abi.functions()
+        .filter(|f| f.name == function || f.signature() == function)
+        .filter(|f| f.inputs.len() == arg_count)
+        .collect();
+    match candidates.as_slice() {
+        [entry] => Ok((*entry).clone()),
+        [] => {
+            let err_msg = format!("Error no function '{}' taking {} argument(s) in ABI", function, arg_count);
+            Err(err_msg)
+        },
+        _ => {
+            let signatures: Vec<String> = candidates.iter().map(|f| f.signature()).collect();
+            let err_msg = format!("Error '{}' is ambiguous; use one of {}", function, signatures.join(", "));
+            Err(err_msg)
+        }
+    }
+}
+
+/// Get function to call; from ABI of contract, or its implementation if it's
+/// a proxy, or from signature itself if ABI doesn't have it.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+/// * `function` - function name, or signature
+/// * `arg_count` - number of arguments given
+fn resolve_function(chain: &crate::backend::Chain, address: &str, function: &str, arg_count: usize) -> Result<(Entry, Option<String>), String> {
+    let mut targets = vec![address.to_owned()];
+    if let Ok(Some(implementation)) = proxy::resolve_implementation(chain, address) {
+        targets.push(implementation.address);
+    }
+
+    let mut last_err = String::new();
+    for target in targets.iter() {
+        let found = abi::fetch_abi(chain, target).and_then(|a| find_function(&a, function, arg_count));
+        match found {
+            Ok(entry) => return Ok((entry, Some(target.clone()))),
+            Err(e) => last_err = e,
+        }
+    }
+
+    // contract without ABI can still be called by signature
+    if !function.contains('(') {
+        return Err(last_err);
+    }
+    let inputs: Vec<Param> = decode::signature_types(function).into_iter().map(|kind| Param {
+        name: String::new(),
+        kind,
+        internal_type: None,
+        components: Vec::new(),
+        indexed: false,
+    }).collect();
+    if inputs.len() != arg_count {
+        let err_msg = format!("Error '{}' takes {} argument(s), but {} given", function, inputs.len(), arg_count);
+        return Err(err_msg);
+    }
+    let entry = Entry {
+        kind: "function".to_owned(),
+        name: function.split('(').next().unwrap_or("").to_owned(),
+        inputs,
+        outputs: Vec::new(),
+        state_mutability: String::new(),
+        constant: false,
+        payable: false,
+        anonymous: false,
+    };
+    Ok((entry, None))
+}
+
+/// Extract revert data from JSON-RPC error, empty if it has none.
+///
+/// # Arguments
+/// * `error` - JSON-RPC error object
+fn revert_data(error: &Value) -> Result<Vec<u8>, String> {
+    // nodes put it either directly, or nested e.g. `{ "data": { "data": ... } }`
+    let data = error["data"].as_str().or_else(|| error["data"]["data"].as_str());
+    match data {
+        Some(res) => abi::from_hex(res),
+        None if error["message"].as_str().unwrap_or("").contains("revert") => Ok(Vec::new()),
+        None => {
+            let err_msg = format!("Error from node; err={}", error);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Call contract read-only with `eth_call`.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `tx` - call object of `eth_call`
+/// * `overrides` - state overrides
+fn eth_call(chain: &crate::backend::Chain, tx: &Value, overrides: Map<String, Value>) -> Result<Outcome, String> {
+    let needs_node = tx.get("from").is_some() || tx.get("value").is_some() || !overrides.is_empty();
+    let response = match foundry::request(chain, "eth_call", json!([tx, explorer::block_tag(), overrides])) {
+        Some(res) => res?,
+        None if needs_node => {
+            let err_msg = format!("Error --from, --value, and overrides need node at {} serving {}", foundry::RPC_URL_ENV, chain.name);
+            return Err(err_msg);
+        },
+        None => {
+            let tag = explorer::block_tag();
+            let body = explorer::query_text(chain, &[
+                ("module", "proxy"),
+                ("action", "eth_call"),
+                ("to", tx["to"].as_str().unwrap_or("")),
+                ("data", tx["data"].as_str().unwrap_or("")),
+                ("tag", &tag),
+            ])?;
+            match serde_json::from_str(&body) {
+                Ok(res) => res,
+                Err(e) => {
+                    let err_msg = format!("Error parsing response from API platform as JSON; err={}", e);
+                    return Err(err_msg);
+                }
+            }
+        }
+    };
+
+    if let Some(error) = response.get("error") {
+        return Ok(Outcome::Reverted(revert_data(error)?));
+    }
+    match response["result"].as_str() {
+        Some(res) => Ok(Outcome::Returned(abi::from_hex(res)?)),
+        None => {
+            let err_msg = format!("Error unexpected response for call; response={}", response);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Execute `simulate` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &SimulateArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.chain)?;
+    let address = args.address.to_lowercase();
+    let (entry, abi_of) = resolve_function(chain, &address, &args.function, args.args.len())?;
+
+    let mut data = entry.selector();
+    for (param, arg) in entry.inputs.iter().zip(args.args.iter()) {
+        data.push_str(&abi::to_hex(&abi::encode_word(&param.canonical_type(), arg)?));
+    }
+    let mut tx = json!({ "to": address, "data": data });
+    if let Some(from) = args.from.as_ref() {
+        abi::encode_word("address", from)?;
+        tx["from"] = json!(from.to_lowercase());
+    }
+    if let Some(value) = args.value.as_ref() {
+        tx["value"] = json!(to_quantity(value)?);
+    }
+    let overrides = state_overrides(args)?;
+
+    let outcome = eth_call(chain, &tx, overrides)?;
+    let outputs: Vec<(String, String, String)> = match &outcome {
+        Outcome::Returned(output) if !entry.outputs.is_empty() => {
+            let kinds: Vec<String> = entry.outputs.iter().map(|o| o.canonical_type()).collect();
+            entry.outputs.iter().zip(kinds.iter()).zip(decode::decode_params(&kinds, output)).map(|((o, kind), value)| {
+                (o.name.clone(), kind.clone(), value.unwrap_or_else(|| "<missing>".to_owned()))
+            }).collect()
+        },
+        _ => Vec::new(),
+    };
+    let revert = match &outcome {
+        Outcome::Reverted(data) => Some(decode::decode_revert(data, abi_of.as_deref().map(|a| (a, args.chain.as_str())))?),
+        Outcome::Returned(_) => None,
+    };
+
+    if args.json {
+        let out = json!({
+            "address": address,
+            "function": entry.signature(),
+            "calldata": data,
+            "status": if revert.is_some() { "reverted" } else { "success" },
+            "returnData": match &outcome { Outcome::Returned(d) | Outcome::Reverted(d) => format!("0x{}", abi::to_hex(d)) },
+            "outputs": outputs.iter().map(|(name, kind, value)| json!({ "name": name, "type": kind, "value": value })).collect::<Vec<Value>>(),
+            "revert": revert.as_ref().map(|r| json!({
+                "kind": r.kind,
+                "signature": r.signature,
+                "message": r.message,
+                "args": r.args.iter().map(|(name, value)| json!({ "name": name, "value": value })).collect::<Vec<Value>>(),
+            })),
+        });
+        match serde_json::to_string_pretty(&out) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing simulation to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+        return Ok(());
+    }
+
+    println!("{} {}", entry.signature(), entry.mutability());
+    match (&outcome, revert) {
+        (Outcome::Reverted(_), Some(revert)) => {
+            match (revert.signature.is_empty(), revert.message.is_empty()) {
+                (true, _) => println!("reverted: {}", revert.message),
+                (false, true) => println!("reverted: {}", revert.signature),
+                (false, false) => println!("reverted: {}: {}", revert.signature, revert.message),
+            }
+            for (name, value) in revert.args.iter() {
+                println!("  {}: {}", name, value);
+            }
+        },
+        (Outcome::Returned(output), _) => {
+            println!("success");
+            if outputs.is_empty() && !output.is_empty() {
+                println!("0x{}", abi::to_hex(output));
+            }
+            for (name, kind, value) in outputs.iter() {
+                let name = if name.is_empty() { String::new() } else { format!(" {}", name) };
+                println!("{}{}: {}", kind, name, value);
+            }
+        },
+        _ => (),
+    }
+    Ok(())
+}
@@ -0,0 +1,139 @@
+//! Timestamped snapshots of output directory; each fetch is written into its
+//! own dated subdirectory e.g. `2024-06-01T12-30Z/` instead of overwriting
+//! the previous one, and `latest` points to the most recent one.
+
+use std::path::Path;
+
+/// Name of link to the most recent snapshot
+pub const LATEST_NAME: &str = "latest";
+
+/// Get name of snapshot taken at unix timestamp in form of
+/// `YYYY-MM-DDTHH-MMZ`, safe to use as directory name on every platform.
+///
+/// # Arguments
+/// * `secs` - unix timestamp in seconds
+pub fn snapshot_name(secs: i64) -> String {
+    let (year, month, day, hour, minute, _) = crate::display::civil_from_timestamp(secs);
+    format!("{:04}-{:02}-{:02}T{:02}-{:02}Z", year, month, day, hour, minute)
+}
+
+/// Create directory of a new snapshot under base directory, and return its
+/// path. Snapshot taken within the same minute as an existing one is
+/// suffixed with a number rather than written over it. Directory is claimed
+/// by creating it, so concurrent runs never pick the same one.
+///
+/// # Arguments
+/// * `base_dir` - directory holding snapshots
+pub fn create(base_dir: &str) -> Result<String, String> {
+    let secs = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+    let name = snapshot_name(secs);
+
+    if let Err(e) = std::fs::create_dir_all(base_dir) {
+        let err_msg = format!("Error creating directory '{}'; err={}", base_dir, e);
+        return Err(err_msg);
+    }
+
+    let mut path = crate::combine_two_path_components(base_dir, &name)?;
+    let mut n = 2;
+    loop {
+        match std::fs::create_dir(&path) {
+            Ok(_) => return Ok(path),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                path = crate::combine_two_path_components(base_dir, &format!("{}-{}", name, n))?;
+                n += 1;
+            },
+            Err(e) => {
+                let err_msg = format!("Error creating snapshot directory '{}'; err={}", path, e);
+                return Err(err_msg);
+            }
+        }
+    }
+}
+
+/// Point `latest` next to snapshot directory to it. It's a relative symlink
+/// on unix, and a text file holding name of snapshot elsewhere as creating
+/// symlinks may need extra privileges.
+///
+/// # Arguments
+/// * `snapshot_dir` - path of snapshot directory as returned by `create`
+pub fn update_latest(snapshot_dir: &str) -> Result<(), String> {
+    let path = Path::new(snapshot_dir);
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let latest = path.with_file_name(LATEST_NAME);
+
+    // symlink_metadata so that dangling link is also replaced
+    if latest.symlink_metadata().is_ok() {
+        if let Err(e) = std::fs::remove_file(&latest) {
+            let err_msg = format!("Error removing '{}'; err={}", latest.display(), e);
+            return Err(err_msg);
+        }
+    }
+
+    #[cfg(unix)]
+    let result = std::os::unix::fs::symlink(name, &latest);
+    #[cfg(not(unix))]
+    let result = std::fs::write(&latest, format!("{}\n", name));
+
+    if let Err(e) = result {
+        let err_msg = format!("Error pointing '{}' to snapshot {}; err={}", latest.display(), name, e);
+        return Err(err_msg);
+    }
+    Ok(())
+}
+
+/// Check whether name of directory is of a snapshot as of `snapshot_name`
+/// e.g. `2024-06-01T12-30Z`, or `2024-06-01T12-30Z-2`.
+///
+/// # Arguments
+/// * `name` - name of directory
+pub fn is_snapshot_name(name: &str) -> bool {
+    let re = regex::Regex::new(r"^\d{4}-\d{2}-\d{2}T\d{2}-\d{2}Z(-\d+)?$").unwrap();
+    re.is_match(name)
+}
+
+/// Find snapshots of contract under directory by their manifests, in order
+/// they were taken. Links to `latest` are skipped, so each snapshot is found
+/// once.
+///
+/// # Arguments
+/// * `dir` - directory to search
+/// * `address` - contract address
+/// * `depth` - levels of subdirectories to search
+/// * `found` - paths of snapshot directories found
+pub fn find(dir: &Path, address: &str, depth: u32, found: &mut Vec<std::path::PathBuf>) -> Result<(), String> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error reading directory '{}'; err={}", dir.display(), e);
+            return Err(err_msg);
+        }
+    };
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        if !entry.file_type().is_ok_and(|t| t.is_dir()) {
+            continue;
+        }
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if is_snapshot_name(&name) {
+            let manifest = crate::manifest::read_manifest(&path.to_string_lossy())?;
+            if manifest.is_some_and(|m| m.address.eq_ignore_ascii_case(address)) {
+                found.push(path);
+            }
+        }
+        else if depth > 0 {
+            find(&path, address, depth - 1, found)?;
+        }
+    }
+
+    // names sort in order of time taken, suffixed ones after the first
+    found.sort_by_key(|p| {
+        let name = p.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let (taken, n) = match name.get(17..).and_then(|s| s.strip_prefix('-')) {
+            Some(n) => (name[..17].to_owned(), n.parse::<u32>().unwrap_or(0)),
+            None => (name.clone(), 1),
+        };
+        (taken, n)
+    });
+    Ok(())
+}
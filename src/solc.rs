@@ -0,0 +1,416 @@
+//! Compile fetched sources with solc through its standard JSON interface.
+
+use crate::source::SourceFile;
+use serde_json::{json, Value};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Extract plain version from compiler version as reported by API platform
+/// e.g. `v0.8.17+commit.8df45f5f` gives `0.8.17`.
+///
+/// # Arguments
+/// * `compiler_version` - compiler version from verification metadata
+pub fn plain_version(compiler_version: &str) -> String {
+    let version = compiler_version.trim().trim_start_matches('v');
+    version.split('+').next().unwrap_or(version).to_owned()
+}
+
+/// Parse plain version e.g. `0.8.17` into tuple of major, minor, and patch
+/// for comparison. Missing, or malformed parts are taken as 0.
+///
+/// # Arguments
+/// * `version` - plain version
+pub fn version_tuple(version: &str) -> (u64, u64, u64) {
+    let parts: Vec<u64> = version.split('.').map(|p| p.parse().unwrap_or(0)).collect();
+    (parts.first().copied().unwrap_or(0), parts.get(1).copied().unwrap_or(0), parts.get(2).copied().unwrap_or(0))
+}
+
+/// Versions from inclusive lower bound up to exclusive upper bound, or
+/// unbounded above if `None`.
+pub type VersionInterval = ((u64, u64, u64), Option<(u64, u64, u64)>);
+
+/// Parse possibly partial version e.g. `0.8`, or `0.8.x` into tuple, along
+/// with number of parts given.
+///
+/// # Arguments
+/// * `version` - version
+fn partial_version(version: &str) -> Option<((u64, u64, u64), usize)> {
+    let mut parts: Vec<u64> = Vec::new();
+    for part in version.split('.') {
+        if part == "x" || part == "X" || part == "*" {
+            break;
+        }
+        parts.push(part.parse().ok()?);
+    }
+    if parts.len() > 3 {
+        return None;
+    }
+    let at = |i: usize| parts.get(i).copied().unwrap_or(0);
+    Some(((at(0), at(1), at(2)), parts.len()))
+}
+
+/// Get smallest version above all versions matching partial version e.g.
+/// `0.9.0` for `0.8`, or `None` if it matches any version.
+///
+/// # Arguments
+/// * `version` - version
+/// * `given` - number of parts given
+fn next_version(version: (u64, u64, u64), given: usize) -> Option<(u64, u64, u64)> {
+    match given {
+        0 => None,
+        1 => Some((version.0 + 1, 0, 0)),
+        2 => Some((version.0, version.1 + 1, 0)),
+        _ => Some((version.0, version.1, version.2 + 1)),
+    }
+}
+
+/// Get interval of versions matching single comparator e.g. `^0.8.0`.
+///
+/// # Arguments
+/// * `comparator` - comparator
+fn comparator_interval(comparator: &str) -> Result<VersionInterval, String> {
+    let op_len = comparator.find(|c: char| c.is_ascii_digit() || c == '*' || c == 'x' || c == 'X').unwrap_or(comparator.len());
+    let (op, version) = comparator.split_at(op_len);
+    let (v, given) = match partial_version(version.trim_start_matches('v')) {
+        Some(res) => res,
+        None => {
+            let err_msg = format!("Error parsing version of comparator '{}'", comparator);
+            return Err(err_msg);
+        }
+    };
+    let interval = match op {
+        "^" => {
+            let upper = if v.0 > 0 || given == 1 { (v.0 + 1, 0, 0) }
+                else if v.1 > 0 || given == 2 { (0, v.1 + 1, 0) }
+                else { (0, 0, v.2 + 1) };
+            (v, Some(upper))
+        },
+        "~" => (v, next_version(v, given.min(2))),
+        ">=" => (v, None),
+        ">" => (next_version(v, given).unwrap_or(v), None),
+        "<" => ((0, 0, 0), Some(v)),
+        "<=" => ((0, 0, 0), next_version(v, given)),
+        "=" | "" => (v, next_version(v, given)),
+        _ => {
+            let err_msg = format!("Error unknown operator of comparator '{}'", comparator);
+            return Err(err_msg);
+        }
+    };
+    Ok(interval)
+}
+
+/// Parse version range of pragma e.g. `>=0.6.2 <0.9.0`, or `^0.7.0 || ^0.8.0`
+/// into interval of each alternative. Empty intervals are left out.
+///
+/// # Arguments
+/// * `range` - version range
+pub fn version_range(range: &str) -> Result<Vec<VersionInterval>, String> {
+    let op_re = regex::Regex::new(r"(>=|<=|>|<|=|\^|~)\s+").unwrap();
+    let range = op_re.replace_all(range.trim(), "$1");
+
+    let mut intervals = Vec::new();
+    for alternative in range.split("||") {
+        let comparators: Vec<&str> = alternative.split_whitespace().collect();
+
+        // hyphen range e.g. `0.6.0 - 0.8.0` includes both ends
+        let bounds = if comparators.len() == 3 && comparators[1] == "-" {
+            let (lower, _) = comparator_interval(comparators[0])?;
+            let (_, upper) = comparator_interval(comparators[2])?;
+            vec![(lower, None), ((0, 0, 0), upper)]
+        }
+        else {
+            comparators.iter().map(|c| comparator_interval(c)).collect::<Result<Vec<VersionInterval>, String>>()?
+        };
+
+        let lower = bounds.iter().map(|b| b.0).max().unwrap_or((0, 0, 0));
+        let upper = bounds.iter().filter_map(|b| b.1).min();
+        if upper.is_none_or(|u| lower < u) {
+            intervals.push((lower, upper));
+        }
+    }
+    Ok(intervals)
+}
+
+/// Get EVM version solc targets by default, as verification left as
+/// `default` means that of its compiler.
+///
+/// # Arguments
+/// * `version` - version of solc e.g. `0.8.17`
+pub fn default_evm_version(version: &str) -> &'static str {
+    match version_tuple(version) {
+        v if v >= (0, 8, 30) => "prague",
+        v if v >= (0, 8, 25) => "cancun",
+        v if v >= (0, 8, 20) => "shanghai",
+        v if v >= (0, 8, 18) => "paris",
+        v if v >= (0, 8, 7) => "london",
+        v if v >= (0, 8, 5) => "berlin",
+        v if v >= (0, 5, 14) => "istanbul",
+        v if v >= (0, 5, 5) => "petersburg",
+        v if v >= (0, 4, 21) => "byzantium",
+        _ => "homestead",
+    }
+}
+
+/// Get version of solc binary in form of `0.8.17`.
+///
+/// # Arguments
+/// * `solc` - path to solc binary
+pub fn binary_version(solc: &Path) -> Result<String, String> {
+    let output = match Command::new(solc).arg("--version").output() {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error executing '{}'; err={}", solc.display(), e);
+            return Err(err_msg);
+        }
+    };
+
+    // last line is like "Version: 0.8.17+commit.8df45f5f.Linux.g++"
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    match stdout.lines().find_map(|l| l.strip_prefix("Version: ")) {
+        Some(res) => Ok(plain_version(res)),
+        None => {
+            let err_msg = format!("Error getting version of '{}'", solc.display());
+            Err(err_msg)
+        }
+    }
+}
+
+/// Find solc binary pinned to the specified compiler version.
+///
+/// In order, it checks
+/// * `solc_path` if supplied
+/// * environment variable `TRACPLS_SOLC`
+/// * binaries installed by svm (`~/.svm/<version>/solc-<version>`) or
+///   solc-select (`~/.solc-select/artifacts/solc-<version>/solc-<version>`)
+/// * `solc` on `PATH`
+///
+/// Version of found binary which doesn't match is reported on stderr, but not
+/// treated as an error.
+///
+/// # Arguments
+/// * `compiler_version` - compiler version from verification metadata
+/// * `solc_path` - user supplied path to solc binary
+pub fn find_solc(compiler_version: &str, solc_path: Option<&String>) -> Result<PathBuf, String> {
+    let version = plain_version(compiler_version);
+
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    if let Some(path) = solc_path {
+        candidates.push(PathBuf::from(path));
+    }
+    else if let Ok(path) = std::env::var("TRACPLS_SOLC") {
+        candidates.push(PathBuf::from(path));
+    }
+    else {
+        if let Ok(home) = std::env::var("HOME") {
+            let mut svm = PathBuf::from(&home);
+            svm.push(".svm");
+            svm.push(&version);
+            svm.push(format!("solc-{}", version));
+            candidates.push(svm);
+
+            let mut solc_select = PathBuf::from(&home);
+            solc_select.push(".solc-select");
+            solc_select.push("artifacts");
+            solc_select.push(format!("solc-{}", version));
+            solc_select.push(format!("solc-{}", version));
+            candidates.push(solc_select);
+        }
+        candidates.push(PathBuf::from("solc"));
+    }
+
+    for candidate in candidates {
+        // bare `solc` is resolved from PATH by executing it
+        if candidate.components().count() > 1 && !candidate.exists() {
+            continue;
+        }
+        if let Ok(found_version) = binary_version(&candidate) {
+            if found_version != version {
+                eprintln!("Warning: using solc {} at '{}' but contract was verified with {}", found_version, candidate.display(), version);
+            }
+            return Ok(candidate);
+        }
+    }
+
+    let err_msg = format!("Error cannot find solc {}. Install it (e.g. via svm or solc-select), or specify with --solc or TRACPLS_SOLC", version);
+    Err(err_msg)
+}
+
+/// Get compiler settings used for verification from metadata returned by
+/// `getsourcecode` endpoint.
+///
+/// Settings of standard JSON submission are taken as-is except its output
+/// selection. Otherwise they are constructed from optimizer and EVM version
+/// fields.
+///
+/// # Arguments
+/// * `meta` - verification metadata
+pub fn verified_settings(meta: &Value) -> Value {
+    let source_code = meta["SourceCode"].as_str().unwrap_or("").trim();
+
+    // standard JSON submission is wrapped with an extra pair of braces
+    let mut input_settings = None;
+    if source_code.starts_with("{{") && source_code.ends_with("}}") {
+        if let Ok(mut input) = serde_json::from_str::<Value>(&source_code[1..source_code.len() - 1]) {
+            input_settings = input.get_mut("settings").map(|s| s.take());
+        }
+    }
+    settings_of(meta, input_settings)
+}
+
+/// Get compiler settings used for verification, from settings of standard
+/// JSON submission if source code was submitted as one, otherwise from
+/// metadata returned by `getsourcecode` endpoint. See `verified_settings()`.
+///
+/// # Arguments
+/// * `meta` - verification metadata
+/// * `input_settings` - settings of standard JSON submission
+pub fn settings_of(meta: &Value, input_settings: Option<Value>) -> Value {
+    if let Some(Value::Object(mut settings)) = input_settings {
+        settings.remove("outputSelection");
+        return Value::Object(settings);
+    }
+
+    let mut settings = json!({
+        "optimizer": {
+            "enabled": meta["OptimizationUsed"].as_str() == Some("1"),
+            "runs": meta["Runs"].as_str().and_then(|r| r.parse::<u64>().ok()).unwrap_or(200),
+        }
+    });
+    if let Some(evm_version) = meta["EVMVersion"].as_str() {
+        if !evm_version.is_empty() && !evm_version.eq_ignore_ascii_case("default") {
+            settings["evmVersion"] = json!(evm_version.to_lowercase());
+        }
+    }
+    settings
+}
+
+/// Convert byte offset in source into 1-based line and column.
+///
+/// # Arguments
+/// * `content` - source content
+/// * `offset` - byte offset
+pub fn offset_to_line_col(content: &str, offset: usize) -> (usize, usize) {
+    let offset = offset.min(content.len());
+    let before = &content.as_bytes()[..offset];
+    let line = before.iter().filter(|&&b| b == b'\n').count() + 1;
+    let column = offset - before.iter().rposition(|&b| b == b'\n').map_or(0, |p| p + 1) + 1;
+    (line, column)
+}
+
+/// Entry of source map of solc, one for each instruction of bytecode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceMapEntry {
+    /// Byte offset of source range
+    pub start: usize,
+
+    /// Byte length of source range
+    pub length: usize,
+
+    /// Id of source file, or `-1` if instruction isn't attributed to any
+    pub file: i64,
+
+    /// Kind of jump; `i` into function, `o` out of function, or `-` for
+    /// regular one
+    pub jump: char,
+}
+
+/// Decompress source map of solc e.g. `26:5:0:-;;;:2:1:i`, where empty fields
+/// repeat those of the previous entry.
+///
+/// # Arguments
+/// * `map` - compressed source map
+pub fn parse_source_map(map: &str) -> Vec<SourceMapEntry> {
+    let mut entries: Vec<SourceMapEntry> = Vec::new();
+    let mut current = SourceMapEntry { start: 0, length: 0, file: -1, jump: '-' };
+    for item in map.split(';') {
+        let fields: Vec<&str> = item.split(':').collect();
+        let field = |i: usize| fields.get(i).copied().filter(|f| !f.is_empty());
+        if let Some(start) = field(0).and_then(|f| f.parse().ok()) {
+            current.start = start;
+        }
+        if let Some(length) = field(1).and_then(|f| f.parse().ok()) {
+            current.length = length;
+        }
+        if let Some(file) = field(2).and_then(|f| f.parse().ok()) {
+            current.file = file;
+        }
+        if let Some(jump) = field(3).and_then(|f| f.chars().next()) {
+            current.jump = jump;
+        }
+        entries.push(current.clone());
+    }
+    entries
+}
+
+/// Build standard JSON input from source files.
+///
+/// # Arguments
+/// * `files` - source files
+/// * `settings` - value of `settings` field e.g. to select outputs
+pub fn build_input(files: &[SourceFile], settings: Value) -> Value {
+    let mut sources = serde_json::Map::new();
+    for f in files.iter() {
+        sources.insert(f.path.clone(), json!({ "content": f.content }));
+    }
+
+    json!({
+        "language": "Solidity",
+        "sources": sources,
+        "settings": settings,
+    })
+}
+
+/// Compile standard JSON input with solc then return its standard JSON output.
+///
+/// Returned output can still contain warnings in its `errors` field, but any
+/// error with `error` severity is returned as `Err`.
+///
+/// # Arguments
+/// * `solc` - path to solc binary
+/// * `input` - standard JSON input
+pub fn compile(solc: &Path, input: &Value) -> Result<Value, String> {
+    let mut child = match Command::new(solc).arg("--standard-json").stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn() {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error executing '{}'; err={}", solc.display(), e);
+            return Err(err_msg);
+        }
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        if let Err(e) = stdin.write_all(input.to_string().as_bytes()) {
+            let err_msg = format!("Error writing input to solc; err={}", e);
+            return Err(err_msg);
+        }
+    }
+
+    let output = match child.wait_with_output() {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error waiting for solc to finish; err={}", e);
+            return Err(err_msg);
+        }
+    };
+
+    let json: Value = match serde_json::from_slice(&output.stdout) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error parsing solc output; err={}, stderr={}", e, String::from_utf8_lossy(&output.stderr));
+            return Err(err_msg);
+        }
+    };
+
+    let errors: Vec<String> = json["errors"].as_array().map_or(Vec::new(), |errors| {
+        errors.iter()
+            .filter(|e| e["severity"].as_str() == Some("error"))
+            .map(|e| e["formattedMessage"].as_str().or_else(|| e["message"].as_str()).unwrap_or("").to_owned())
+            .collect()
+    });
+    if !errors.is_empty() {
+        let err_msg = format!("Error compiling sources with solc;\n{}", errors.join("\n"));
+        return Err(err_msg);
+    }
+
+    Ok(json)
+}
@@ -0,0 +1,194 @@
+//! Lightweight scanner of Solidity source code.
+//!
+//! It doesn't fully parse the language, but recognizes enough of top-level
+//! structure (contracts and their members) to outline verified source code
+//! without requiring a compiler.
+
+use regex::Regex;
+
+/// Contract, abstract contract, interface, or library declared in source.
+#[derive(Debug, Clone)]
+pub struct ContractDef {
+    /// One of `contract`, `abstract contract`, `interface`, or `library`
+    pub kind: String,
+
+    pub name: String,
+
+    /// Base contracts in order of declaration
+    pub bases: Vec<String>,
+
+    /// Path of file this contract is declared in
+    pub file: String,
+
+    /// Body of contract with comments and string literals blanked out
+    pub body: String,
+
+    pub functions: Vec<String>,
+    pub modifiers: Vec<String>,
+}
+
+/// Blank out comments and string literals with spaces, preserving newlines
+/// and byte offsets, so later scanning won't match anything inside them.
+///
+/// # Arguments
+/// * `source` - Solidity source code
+pub fn strip_comments_and_strings(source: &str) -> String {
+    let bytes = source.as_bytes();
+    let mut out = bytes.to_vec();
+    let mut i = 0;
+
+    let blank = |out: &mut Vec<u8>, from: usize, to: usize| {
+        for b in out[from..to].iter_mut() {
+            if *b != b'\n' {
+                *b = b' ';
+            }
+        }
+    };
+
+    while i < bytes.len() {
+        if bytes[i] == b'/' && i + 1 < bytes.len() && bytes[i + 1] == b'/' {
+            let end = bytes[i..].iter().position(|&b| b == b'\n').map_or(bytes.len(), |p| i + p);
+            blank(&mut out, i, end);
+            i = end;
+        }
+        else if bytes[i] == b'/' && i + 1 < bytes.len() && bytes[i + 1] == b'*' {
+            let end = source[i + 2..].find("*/").map_or(bytes.len(), |p| i + 2 + p + 2);
+            blank(&mut out, i, end);
+            i = end;
+        }
+        else if bytes[i] == b'"' || bytes[i] == b'\'' {
+            let quote = bytes[i];
+            let mut end = i + 1;
+            while end < bytes.len() && bytes[end] != quote && bytes[end] != b'\n' {
+                if bytes[end] == b'\\' {
+                    end += 1;
+                }
+                end += 1;
+            }
+            let end = (end + 1).min(bytes.len());
+            // keep quotes so that empty string is still recognizable
+            blank(&mut out, i + 1, end - 1);
+            i = end;
+        }
+        else {
+            i += 1;
+        }
+    }
+
+    // every byte of multi-byte characters inside comments or strings is
+    // blanked altogether, so the result is still valid UTF-8
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Find index of matching closing brace of the opening brace at `open`.
+///
+/// # Arguments
+/// * `text` - text with comments and strings already stripped
+/// * `open` - byte index of opening brace
+pub fn find_matching_brace(text: &str, open: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, b) in text.as_bytes().iter().enumerate().skip(open) {
+        match b {
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            },
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Get paths imported by source file as written in import directives.
+///
+/// # Arguments
+/// * `source` - Solidity source code
+pub fn imports(source: &str) -> Vec<String> {
+    let stripped = strip_comments_and_strings(source);
+    let directive_re = Regex::new(r"\bimport\b[^;]*;").unwrap();
+    let path_re = Regex::new(r#"["']([^"']+)["']"#).unwrap();
+
+    // stripped text only locates directives; their paths are blanked in it
+    directive_re.find_iter(&stripped)
+        .filter_map(|m| path_re.captures(&source[m.start()..m.end()]).map(|c| c[1].to_owned()))
+        .collect()
+}
+
+/// Get version ranges of `pragma solidity` directives in source file e.g.
+/// `^0.8.0`, or `>=0.6.2 <0.9.0`.
+///
+/// # Arguments
+/// * `source` - Solidity source code
+pub fn pragmas(source: &str) -> Vec<String> {
+    let stripped = strip_comments_and_strings(source);
+    let pragma_re = Regex::new(r"\bpragma\s+solidity\s+([^;]+);").unwrap();
+    pragma_re.captures_iter(&stripped)
+        .map(|c| c[1].split_whitespace().collect::<Vec<&str>>().join(" "))
+        .collect()
+}
+
+/// Collect names captured by the first group of regex in text.
+fn capture_names(re: &Regex, text: &str) -> Vec<String> {
+    re.captures_iter(text).map(|c| c[1].to_owned()).collect()
+}
+
+/// Scan source file for contracts declared in it.
+///
+/// # Arguments
+/// * `path` - path of source file
+/// * `source` - content of source file
+pub fn parse_contracts(path: &str, source: &str) -> Vec<ContractDef> {
+    let stripped = strip_comments_and_strings(source);
+    let contract_re = Regex::new(r"\b(abstract\s+contract|contract|interface|library)\s+([A-Za-z_$][\w$]*)\s*(?:is\s+([^{]+))?\{").unwrap();
+    let function_re = Regex::new(r"\bfunction\s+([A-Za-z_$][\w$]*)\s*\(").unwrap();
+    let modifier_re = Regex::new(r"\bmodifier\s+([A-Za-z_$][\w$]*)").unwrap();
+    let base_re = Regex::new(r"^\s*([A-Za-z_$][\w$.]*)").unwrap();
+
+    let mut contracts = Vec::new();
+    let mut search_from = 0;
+    while let Some(caps) = contract_re.captures(&stripped[search_from..]) {
+        let open = search_from + caps.get(0).unwrap().end() - 1;
+        let close = find_matching_brace(&stripped, open).unwrap_or(stripped.len() - 1);
+
+        // base list may contain constructor arguments e.g. `ERC20("Name", "SYM")`
+        let mut bases = Vec::new();
+        if let Some(list) = caps.get(3) {
+            let mut depth = 0;
+            let mut current = String::new();
+            for c in list.as_str().chars() {
+                match c {
+                    '(' => { depth += 1; current.push(c); },
+                    ')' => { depth -= 1; current.push(c); },
+                    ',' if depth == 0 => {
+                        bases.push(current.clone());
+                        current.clear();
+                    },
+                    _ => current.push(c),
+                }
+            }
+            bases.push(current);
+            bases = bases.iter().filter_map(|b| base_re.captures(b).map(|c| c[1].to_owned())).collect();
+        }
+
+        let body = stripped[open + 1..close].to_owned();
+        contracts.push(ContractDef {
+            kind: caps[1].split_whitespace().collect::<Vec<&str>>().join(" "),
+            name: caps[2].to_owned(),
+            bases,
+            file: path.to_owned(),
+            functions: capture_names(&function_re, &body),
+            modifiers: capture_names(&modifier_re, &body),
+            body,
+        });
+
+        search_from = close + 1;
+        if search_from >= stripped.len() {
+            break;
+        }
+    }
+
+    contracts
+}
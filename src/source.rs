@@ -0,0 +1,897 @@
+//! Fetch verified source code as list of files.
+
+use crate::explorer::{self, RawSourceMeta};
+use crate::backend::Chain;
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde::Deserialize;
+use serde_json::Value;
+use std::cell::Cell;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use std::rc::Rc;
+
+/// Single source file of verified contract.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    /// Path of file as submitted for verification, or contract name with
+    /// `.sol` appended for single-file submission
+    pub path: String,
+
+    pub content: String,
+}
+
+/// Verified source code of contract.
+#[derive(Debug, Clone)]
+pub struct ContractSource {
+    /// Name of the primary contract
+    pub contract_name: String,
+
+    pub compiler_version: String,
+
+    /// Whether source code was submitted as multiple files
+    pub is_multi_file: bool,
+
+    pub files: Vec<SourceFile>,
+}
+
+/// Order of source files of multi-file submission in output.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Order {
+    /// Primary contract first, then the rest sorted by path
+    Path,
+
+    /// Primary contract first, then files it imports directly, or indirectly
+    /// breadth-first, then the rest sorted by path
+    Deps,
+}
+
+/// Parse value of `--order` flag option.
+///
+/// # Arguments
+/// * `value` - value of flag option
+pub fn parse_order(value: &str) -> Result<Order, String> {
+    match value.to_lowercase().as_str() {
+        "path" => Ok(Order::Path),
+        "deps" => Ok(Order::Deps),
+        _ => Err("Error invalid value for --order.\nPossible values are 'path', or 'deps'.".to_owned()),
+    }
+}
+
+/// Whether or not source code declares contract, library, or interface of
+/// the name.
+///
+/// # Arguments
+/// * `content` - source code
+/// * `name` - name of contract
+pub fn declares(content: &str, name: &str) -> bool {
+    declaration_regex(name).is_match(content)
+}
+
+/// Get regex matching declaration of contract, library, or interface of the
+/// name, to test many files against without compiling it for each.
+///
+/// # Arguments
+/// * `name` - name of contract
+pub fn declaration_regex(name: &str) -> regex::Regex {
+    regex::Regex::new(&format!(r"\b(contract|library|interface)\s+{}\b", regex::escape(name))).unwrap()
+}
+
+/// Split primary contract given by name, or fully qualified name e.g.
+/// `contracts/Vault.sol:Vault` into path if any, and name.
+///
+/// # Arguments
+/// * `primary` - primary contract
+fn split_qualified(primary: &str) -> (Option<&str>, &str) {
+    match primary.rsplit_once(':') {
+        Some((path, name)) => (Some(path), name),
+        None => (None, primary),
+    }
+}
+
+/// Get name of primary contract given by name, or fully qualified name.
+///
+/// # Arguments
+/// * `primary` - primary contract
+pub fn contract_of(primary: &str) -> &str {
+    split_qualified(primary).1
+}
+
+/// Get position of file declaring the primary contract.
+///
+/// # Arguments
+/// * `files` - source files
+/// * `primary` - primary contract by name, or fully qualified name
+pub fn primary_position(files: &[SourceFile], primary: &str) -> Option<usize> {
+    let (path, name) = split_qualified(primary);
+    files.iter().position(|f| path.is_none_or(|p| f.path == p) && declares(&f.content, name))
+}
+
+/// Select contract to treat as primary among ones declared in source files.
+/// Returns its name, or fully qualified name if the name is declared in more
+/// than one file.
+///
+/// # Arguments
+/// * `files` - source files
+/// * `selector` - name, or fully qualified name e.g.
+///   `contracts/Vault.sol:Vault`
+pub fn select_primary(files: &[SourceFile], selector: &str) -> Result<String, String> {
+    let (path, name) = split_qualified(selector);
+    let contracts: Vec<crate::solidity::ContractDef> = files.iter()
+        .flat_map(|f| crate::solidity::parse_contracts(&f.path, &f.content))
+        .collect();
+    let qualified = |c: &crate::solidity::ContractDef| format!("{}:{}", c.file, c.name);
+
+    let matches: Vec<&crate::solidity::ContractDef> = contracts.iter()
+        .filter(|c| c.name == name && path.is_none_or(|p| c.file == p))
+        .collect();
+    match matches.as_slice() {
+        [] => {
+            let candidates: Vec<String> = contracts.iter().filter(|c| c.kind == "contract").map(qualified).collect();
+            let err_msg = format!("Error contract '{}' not found; candidates are {}", selector, candidates.join(", "));
+            Err(err_msg)
+        },
+        [found] if contracts.iter().filter(|c| c.name == name).count() == 1 => Ok(found.name.clone()),
+        [found] => Ok(qualified(found)),
+        _ => {
+            let candidates: Vec<String> = matches.iter().map(|c| qualified(c)).collect();
+            let err_msg = format!("Error contract '{}' is ambiguous; select one of {}", selector, candidates.join(", "));
+            Err(err_msg)
+        }
+    }
+}
+
+/// Resolve path of import directive relative to the importing file. Paths
+/// not starting with `./`, or `../` are taken as they are.
+///
+/// # Arguments
+/// * `from` - path of importing file
+/// * `import` - path as written in import directive
+fn resolve_import(from: &str, import: &str) -> String {
+    if !import.starts_with("./") && !import.starts_with("../") {
+        return import.to_owned();
+    }
+    let mut components: Vec<&str> = from.split('/').collect();
+    components.pop();
+    for component in import.split('/') {
+        match component {
+            "." | "" => (),
+            ".." => { components.pop(); },
+            _ => components.push(component),
+        }
+    }
+    components.join("/")
+}
+
+/// Get indexes of files reachable from roots through import directives,
+/// roots first, then imported ones breadth-first.
+///
+/// # Arguments
+/// * `files` - source files
+/// * `roots` - indexes of files to start from
+pub fn reachable(files: &[SourceFile], roots: Vec<usize>) -> Vec<usize> {
+    let mut indexes = roots;
+
+    // indexes grows while being walked
+    let mut i = 0;
+    while i < indexes.len() {
+        let importer = &files[indexes[i]];
+        for import in crate::solidity::imports(&importer.content) {
+            let path = resolve_import(&importer.path, &import);
+            if let Some(j) = files.iter().position(|f| f.path == path) {
+                if !indexes.contains(&j) {
+                    indexes.push(j);
+                }
+            }
+        }
+        i += 1;
+    }
+    indexes
+}
+
+/// Put source files in stable order; file declaring the primary contract
+/// first, then the rest as of order.
+///
+/// # Arguments
+/// * `files` - source files
+/// * `primary` - primary contract by name, or fully qualified name
+/// * `order` - order of the rest of files
+pub fn order_files(files: &mut Vec<SourceFile>, primary: &str, order: Order) {
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut indexes: Vec<usize> = primary_position(files, primary).into_iter().collect();
+    if order == Order::Deps {
+        indexes = reachable(files, indexes);
+    }
+    for j in 0..files.len() {
+        if !indexes.contains(&j) {
+            indexes.push(j);
+        }
+    }
+
+    let mut taken: Vec<Option<SourceFile>> = files.drain(..).map(Some).collect();
+    files.extend(indexes.iter().filter_map(|j| taken[*j].take()));
+}
+
+/// Drop source files not reachable from file declaring the primary contract
+/// through import directives, keeping order of the rest. Files are kept as
+/// they are if the primary contract isn't found. Returns number of files
+/// dropped.
+///
+/// # Arguments
+/// * `files` - source files
+/// * `primary` - primary contract by name, or fully qualified name
+pub fn prune_unreachable(files: &mut Vec<SourceFile>, primary: &str) -> usize {
+    let primary = match primary_position(files, primary) {
+        Some(res) => res,
+        None => return 0,
+    };
+    let mut kept = reachable(files, vec![primary]);
+    kept.sort();
+
+    let dropped = files.len() - kept.len();
+    let mut taken: Vec<Option<SourceFile>> = files.drain(..).map(Some).collect();
+    files.extend(kept.iter().filter_map(|i| taken[*i].take()));
+    dropped
+}
+
+/// Get relative path to write source file to, so it can't escape the
+/// destination directory e.g. by being absolute or containing `..`.
+///
+/// # Arguments
+/// * `path` - path of source file as verified
+pub fn safe_relative_path(path: &str) -> String {
+    path.split(['/', '\\'])
+        .filter(|c| !c.is_empty() && *c != "." && *c != "..")
+        .collect::<Vec<&str>>()
+        .join("/")
+}
+
+/// Write source files into directory preserving their relative paths.
+///
+/// # Arguments
+/// * `dir` - destination directory
+/// * `files` - source files
+pub fn write_sources(dir: &str, files: &[SourceFile]) -> Result<(), String> {
+    for f in files.iter() {
+        let filepath = crate::combine_two_path_components(dir, &safe_relative_path(&f.path))?;
+        crate::create_intermediate_dirs(&filepath)?;
+        crate::write_file(&filepath, &f.content)?;
+    }
+    Ok(())
+}
+
+/// Collect `*.sol` files under local directory as pairs of relative path and content,
+/// sorted by path.
+///
+/// # Arguments
+/// * `root` - directory paths are relative to
+/// * `dir` - directory to collect from
+/// * `files` - collected files
+pub fn collect_local_sources(root: &Path, dir: &Path, files: &mut Vec<(String, String)>) -> Result<(), String> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error reading directory '{}'; err={}", dir.display(), e);
+            return Err(err_msg);
+        }
+    };
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_local_sources(root, &path, files)?;
+        }
+        else if path.extension() == Some(std::ffi::OsStr::new("sol")) {
+            let content = match std::fs::read_to_string(&path) {
+                Ok(res) => res,
+                Err(e) => {
+                    let err_msg = format!("Error reading file '{}'; err={}", path.display(), e);
+                    return Err(err_msg);
+                }
+            };
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            let relative = relative.components().map(|c| c.as_os_str().to_string_lossy().into_owned()).collect::<Vec<String>>().join("/");
+            files.push((relative, content));
+        }
+    }
+    files.sort();
+    Ok(())
+}
+
+/// Sink which each source file is handed to as soon as it is decoded
+type SourceSink<'a> = dyn FnMut(SourceFile) -> Result<(), String> + 'a;
+
+/// State kept while visiting standard JSON input.
+struct VisitState<'a> {
+    sink: &'a mut SourceSink<'a>,
+
+    /// Number of files handed to sink so far
+    count: usize,
+
+    /// Error returned from sink, kept apart from errors of parsing
+    sink_error: Option<String>,
+
+    /// Compiler settings of standard JSON input
+    settings: Option<Value>,
+
+    /// Number of bytes consumed by deserializer so far
+    consumed: Rc<Cell<usize>>,
+
+    /// Offset of entry of each file handed to sink in input of deserializer
+    offsets: Vec<usize>,
+}
+
+/// Single entry of `sources` object; only its content is of interest.
+#[derive(Deserialize)]
+struct SourceEntry {
+    content: Option<String>,
+}
+
+impl<'a> VisitState<'a> {
+    /// Hand file to sink.
+    ///
+    /// # Arguments
+    /// * `path` - path of file
+    /// * `entry` - entry of file
+    fn emit<E: serde::de::Error>(&mut self, path: String, entry: SourceEntry) -> Result<(), E> {
+        self.count += 1;
+        if let Err(e) = (self.sink)(SourceFile { path, content: entry.content.unwrap_or_default() }) {
+            self.sink_error = Some(e);
+            return Err(E::custom("sink failed"));
+        }
+        Ok(())
+    }
+}
+
+/// Visitor of standard JSON input, or just its `sources` object, handing each
+/// file to sink as soon as it is decoded.
+struct SourcesVisitor<'s, 'a> {
+    state: &'s mut VisitState<'a>,
+}
+
+impl<'de, 's, 'a> DeserializeSeed<'de> for SourcesVisitor<'s, 'a> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, 's, 'a> Visitor<'de> for SourcesVisitor<'s, 'a> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("standard JSON input, or its sources")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "sources" => map.next_value_seed(SourcesVisitor { state: &mut *self.state })?,
+                "settings" => self.state.settings = Some(map.next_value::<Value>()?),
+                "language" => { map.next_value::<IgnoredAny>()?; },
+                _ => {
+                    self.state.offsets.push(self.state.consumed.get());
+                    let entry = map.next_value::<SourceEntry>()?;
+                    self.state.emit(key, entry)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Multi-file source code visited by `visit_sources()`.
+struct VisitedSources {
+    /// Number of files handed to sink
+    count: usize,
+
+    /// Compiler settings of standard JSON input if any
+    settings: Option<Value>,
+
+    /// Offset of entry of each file in input of deserializer
+    offsets: Vec<usize>,
+}
+
+/// Visit multi-file source code, handing each file to sink.
+///
+/// Text merely looking like JSON is source code of single file, which is told
+/// by no files handed out. But files already handed out can't be taken back,
+/// so failing after that is an error.
+///
+/// # Arguments
+/// * `address` - contract address
+/// * `deserializer` - deserializer of standard JSON input, or its sources
+/// * `consumed` - number of bytes consumed by deserializer so far, kept up to
+///   date by its input to record offsets of entries
+/// * `sink` - function to receive each file
+fn visit_sources<'de, D: Deserializer<'de>>(address: &str, deserializer: D, consumed: Rc<Cell<usize>>, sink: &mut SourceSink) -> Result<VisitedSources, String> {
+    let mut state = VisitState { sink, count: 0, sink_error: None, settings: None, consumed, offsets: Vec::new() };
+    let result = SourcesVisitor { state: &mut state }.deserialize(deserializer);
+    if let Some(e) = state.sink_error {
+        return Err(e);
+    }
+    match result {
+        Ok(_) => Ok(VisitedSources { count: state.count, settings: state.settings, offsets: state.offsets }),
+        Err(e) if state.count > 0 => {
+            let err_msg = format!("Error parsing multi-file source code of {}; err={}", address, e);
+            Err(err_msg)
+        },
+        Err(_) => Ok(VisitedSources { count: 0, settings: None, offsets: Vec::new() }),
+    }
+}
+
+/// Get path of single-file source code.
+///
+/// # Arguments
+/// * `meta` - verification metadata
+fn single_file_path(meta: &Value) -> String {
+    let mut path = meta["ContractName"].as_str().unwrap_or("").to_owned();
+    if !path.ends_with(".sol") {
+        path.push_str(".sol");
+    }
+    path
+}
+
+/// Hand each file of verified source code to sink as soon as it is decoded
+/// from verification metadata returned by `getsourcecode` endpoint.
+/// Files of multi-file submission come in the order they were submitted.
+/// Returns whether source code was submitted as multiple files.
+///
+/// # Arguments
+/// * `address` - contract address
+/// * `meta` - verification metadata
+/// * `sink` - function to receive each file
+pub fn for_each_source<F: FnMut(SourceFile) -> Result<(), String>>(address: &str, meta: &Value, mut sink: F) -> Result<bool, String> {
+    let source_code = meta["SourceCode"].as_str().unwrap_or("");
+    if source_code.trim().is_empty() {
+        let err_msg = format!("Error no verified source code of {}", address);
+        return Err(err_msg);
+    }
+
+    // multi-file submission is either standard JSON input wrapped with an
+    // extra pair of braces, or just its sources
+    let trimmed = source_code.trim();
+    let json_text = if trimmed.starts_with("{{") && trimmed.ends_with("}}") {
+        Some(&trimmed[1..trimmed.len() - 1])
+    }
+    else if trimmed.starts_with('{') {
+        Some(trimmed)
+    }
+    else {
+        None
+    };
+
+    if let Some(json_text) = json_text {
+        let visited = visit_sources(address, &mut serde_json::Deserializer::from_str(json_text), Rc::default(), &mut sink)?;
+        if visited.count > 0 {
+            return Ok(true);
+        }
+    }
+
+    sink(SourceFile { path: single_file_path(meta), content: source_code.to_owned() })?;
+    Ok(false)
+}
+
+/// Reader decoding JSON string literal on the fly, so source code of tens of
+/// MB doesn't have to be decoded into memory as a whole.
+struct JsonStringReader<'a> {
+    /// Escaped text between the quotes
+    bytes: &'a [u8],
+
+    pos: usize,
+
+    /// Decoded bytes of the last escape sequence, and how many of them were
+    /// already read
+    pending: [u8; 4],
+    pending_len: usize,
+    pending_pos: usize,
+
+    /// Number of decoded bytes read so far
+    decoded: usize,
+
+    /// Pairs of number of decoded bytes read, and position in escaped text
+    /// at starts of reads, to resume decoding from without escape sequence
+    /// pending
+    checkpoints: Vec<(usize, usize)>,
+}
+
+impl<'a> JsonStringReader<'a> {
+    /// Create reader of JSON string literal.
+    ///
+    /// # Arguments
+    /// * `literal` - JSON string literal including its quotes
+    fn new(literal: &'a str) -> Self {
+        let bytes = literal.trim().as_bytes();
+        let bytes = if bytes.len() >= 2 { &bytes[1..bytes.len() - 1] } else { &[] };
+        Self { bytes, pos: 0, pending: [0; 4], pending_len: 0, pending_pos: 0, decoded: 0, checkpoints: Vec::new() }
+    }
+
+    /// Create reader of JSON string literal resuming from checkpoint of
+    /// another reader of it.
+    ///
+    /// # Arguments
+    /// * `literal` - JSON string literal including its quotes
+    /// * `checkpoint` - number of decoded bytes read, and position in escaped
+    ///   text
+    fn resume(literal: &'a str, checkpoint: (usize, usize)) -> Self {
+        let mut reader = Self::new(literal);
+        reader.decoded = checkpoint.0;
+        reader.pos = checkpoint.1.min(reader.bytes.len());
+        reader
+    }
+
+    /// Read 4 hex digits of `\u` escape sequence.
+    fn hex4(&mut self) -> std::io::Result<u32> {
+        let digits = self.bytes.get(self.pos..self.pos + 4).and_then(|d| std::str::from_utf8(d).ok());
+        match digits.and_then(|d| u32::from_str_radix(d, 16).ok()) {
+            Some(res) => {
+                self.pos += 4;
+                Ok(res)
+            },
+            None => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid \\u escape sequence")),
+        }
+    }
+
+    /// Decode escape sequence following a backslash into pending bytes.
+    fn decode_escape(&mut self) -> std::io::Result<()> {
+        let c = match self.bytes.get(self.pos) {
+            Some(res) => *res,
+            None => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "unterminated escape sequence")),
+        };
+        self.pos += 1;
+
+        let decoded = match c {
+            b'"' => '"',
+            b'\\' => '\\',
+            b'/' => '/',
+            b'b' => '\u{8}',
+            b'f' => '\u{c}',
+            b'n' => '\n',
+            b'r' => '\r',
+            b't' => '\t',
+            b'u' => {
+                let high = self.hex4()?;
+                if (0xD800..0xDC00).contains(&high) && self.bytes[self.pos..].starts_with(b"\\u") {
+                    self.pos += 2;
+                    let low = self.hex4()?;
+                    char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF)).unwrap_or('\u{FFFD}')
+                }
+                else {
+                    char::from_u32(high).unwrap_or('\u{FFFD}')
+                }
+            },
+            _ => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid escape sequence")),
+        };
+        self.pending_len = decoded.encode_utf8(&mut self.pending).len();
+        self.pending_pos = 0;
+        Ok(())
+    }
+}
+
+impl<'a> Read for JsonStringReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pending_pos >= self.pending_len {
+            self.checkpoints.push((self.decoded, self.pos));
+        }
+
+        let mut n = 0;
+        while n < buf.len() {
+            if self.pending_pos < self.pending_len {
+                buf[n] = self.pending[self.pending_pos];
+                self.pending_pos += 1;
+                n += 1;
+                continue;
+            }
+            if self.pos >= self.bytes.len() {
+                break;
+            }
+            if self.bytes[self.pos] == b'\\' {
+                self.pos += 1;
+                self.decode_escape()?;
+                continue;
+            }
+
+            // copy run of plain bytes as-is
+            let limit = self.bytes.len().min(self.pos + buf.len() - n);
+            let end = self.bytes[self.pos..limit].iter().position(|&b| b == b'\\').map_or(limit, |i| self.pos + i);
+            buf[n..n + end - self.pos].copy_from_slice(&self.bytes[self.pos..end]);
+            n += end - self.pos;
+            self.pos = end;
+        }
+        self.decoded += n;
+        Ok(n)
+    }
+}
+
+/// Reader counting bytes read through it.
+struct CountingReader<R> {
+    inner: R,
+    count: Rc<Cell<usize>>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.set(self.count.get() + n);
+        Ok(n)
+    }
+}
+
+/// Source code visited by `for_each_source_raw()`.
+#[derive(Debug)]
+pub struct VisitedSource {
+    /// Whether source code was submitted as multiple files
+    pub is_multi_file: bool,
+
+    /// Compiler settings of standard JSON submission if it was one
+    pub settings: Option<Value>,
+}
+
+/// Same as `for_each_source()` but decoding source code straight from the
+/// response body, so it holds content of only the file being handed to sink
+/// on top of the response body itself even for source code of tens of MB.
+/// Files held by sink e.g. to put them in order are up to the caller; see
+/// `index_sources_raw()` to avoid that.
+///
+/// # Arguments
+/// * `address` - contract address
+/// * `raw` - verification metadata with source code left undecoded
+/// * `sink` - function to receive each file
+pub fn for_each_source_raw<F: FnMut(SourceFile) -> Result<(), String>>(address: &str, raw: &RawSourceMeta, mut sink: F) -> Result<VisitedSource, String> {
+    visit_source_raw(address, raw, &mut sink).map(|v| v.visited)
+}
+
+/// Source code visited by `visit_source_raw()`, and where its files are.
+struct RawVisit {
+    visited: VisitedSource,
+
+    /// Offset of entry of each file in decoded source code; empty for
+    /// single-file source code
+    offsets: Vec<usize>,
+
+    /// Checkpoints of reader of source code
+    checkpoints: Vec<(usize, usize)>,
+}
+
+/// Decode source code straight from the response body, handing each file to
+/// sink, and recording where entries of files are in decoded source code.
+///
+/// # Arguments
+/// * `address` - contract address
+/// * `raw` - verification metadata with source code left undecoded
+/// * `sink` - function to receive each file
+fn visit_source_raw(address: &str, raw: &RawSourceMeta, sink: &mut SourceSink) -> Result<RawVisit, String> {
+    let read_err = |e: std::io::Error| format!("Error decoding source code of {}; err={}", address, e);
+
+    // look at the first non-whitespace characters to tell whether it's JSON
+    let mut reader = JsonStringReader::new(raw.source_code.get());
+    let mut head = [0u8; 2];
+    loop {
+        if reader.read(&mut head[..1]).map_err(read_err)? == 0 {
+            let err_msg = format!("Error no verified source code of {}", address);
+            return Err(err_msg);
+        }
+        if !head[0].is_ascii_whitespace() {
+            break;
+        }
+    }
+
+    if head[0] == b'{' {
+        let head_len = 1 + reader.read(&mut head[1..]).map_err(read_err)?;
+        // standard JSON input is wrapped with an extra pair of braces
+        let head = if head_len == 2 && head[1] == b'{' { &head[1..] } else { &head[..head_len] };
+        // input of deserializer starts with head, then the rest of reader
+        let base = reader.decoded - head.len();
+        let consumed = Rc::new(Cell::new(0));
+        let input = CountingReader { inner: BufReader::with_capacity(64 * 1024, head.chain(&mut reader)), count: consumed.clone() };
+        let visited = visit_sources(address, &mut serde_json::Deserializer::from_reader(input), consumed, sink)?;
+        if visited.count > 0 {
+            return Ok(RawVisit {
+                visited: VisitedSource { is_multi_file: true, settings: visited.settings },
+                offsets: visited.offsets.iter().map(|o| base + o).collect(),
+                checkpoints: reader.checkpoints,
+            });
+        }
+    }
+
+    let content: String = match serde_json::from_str(raw.source_code.get()) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error decoding source code of {}; err={}", address, e);
+            return Err(err_msg);
+        }
+    };
+    sink(SourceFile { path: single_file_path(&raw.meta), content })?;
+    Ok(RawVisit { visited: VisitedSource { is_multi_file: false, settings: None }, offsets: Vec::new(), checkpoints: Vec::new() })
+}
+
+/// Files of source code indexed by `index_sources_raw()`, so each can be
+/// decoded again on its own from the response body in any order.
+#[derive(Debug)]
+pub struct SourceIndex {
+    /// Paths of files in order they were submitted
+    pub paths: Vec<String>,
+
+    pub visited: VisitedSource,
+
+    /// Offset of entry of each file in decoded source code; empty for
+    /// single-file source code
+    offsets: Vec<usize>,
+
+    /// Checkpoints of reader of source code to resume decoding from
+    checkpoints: Vec<(usize, usize)>,
+}
+
+impl SourceIndex {
+    /// Decode file of source code again.
+    ///
+    /// # Arguments
+    /// * `address` - contract address
+    /// * `raw` - verification metadata source code was indexed from
+    /// * `i` - index of file in `paths`
+    pub fn read(&self, address: &str, raw: &RawSourceMeta, i: usize) -> Result<SourceFile, String> {
+        let read_err = |e: String| format!("Error decoding source code of {}; err={}", address, e);
+        let path = match self.paths.get(i) {
+            Some(res) => res.clone(),
+            None => return Err(read_err(format!("no file at index {}", i))),
+        };
+        let offset = match self.offsets.get(i) {
+            Some(res) => *res,
+            None => {
+                let content: String = serde_json::from_str(raw.source_code.get()).map_err(|e| read_err(e.to_string()))?;
+                return Ok(SourceFile { path, content });
+            }
+        };
+
+        // resume from the last checkpoint before entry, then skip to its value
+        let at = self.checkpoints.partition_point(|c| c.0 <= offset).saturating_sub(1);
+        let checkpoint = self.checkpoints.get(at).copied().unwrap_or((0, 0));
+        let mut reader = JsonStringReader::resume(raw.source_code.get(), checkpoint);
+        let skip = (offset - checkpoint.0) as u64;
+        std::io::copy(&mut (&mut reader).take(skip), &mut std::io::sink()).map_err(|e| read_err(e.to_string()))?;
+        let mut c = [0u8; 1];
+        loop {
+            if reader.read(&mut c).map_err(|e| read_err(e.to_string()))? == 0 {
+                return Err(read_err(format!("entry of {} not found", path)));
+            }
+            match c[0] {
+                b':' => break,
+                c if c.is_ascii_whitespace() => (),
+                _ => return Err(read_err(format!("entry of {} not found", path))),
+            }
+        }
+
+        let input = BufReader::with_capacity(64 * 1024, reader);
+        let entry = SourceEntry::deserialize(&mut serde_json::Deserializer::from_reader(input)).map_err(|e| read_err(e.to_string()))?;
+        Ok(SourceFile { path, content: entry.content.unwrap_or_default() })
+    }
+}
+
+/// Index files of source code decoding it straight from the response body,
+/// so they can be decoded again one by one in any order with
+/// `SourceIndex::read()` without holding all of them in memory. Each file is
+/// handed to `inspect` while indexing e.g. to find out the order.
+///
+/// # Arguments
+/// * `address` - contract address
+/// * `raw` - verification metadata with source code left undecoded
+/// * `inspect` - function to look at each file
+pub fn index_sources_raw<F: FnMut(&SourceFile)>(address: &str, raw: &RawSourceMeta, mut inspect: F) -> Result<SourceIndex, String> {
+    let mut paths = Vec::new();
+    let visit = visit_source_raw(address, raw, &mut |f: SourceFile| {
+        inspect(&f);
+        paths.push(f.path);
+        Ok(())
+    })?;
+    Ok(SourceIndex { paths, visited: visit.visited, offsets: visit.offsets, checkpoints: visit.checkpoints })
+}
+
+/// Get verified source code of contract as list of files sorted by path from
+/// verification metadata returned by `getsourcecode` endpoint.
+///
+/// # Arguments
+/// * `address` - contract address
+/// * `meta` - verification metadata
+pub fn sources_from_meta(address: &str, meta: &Value) -> Result<ContractSource, String> {
+    let mut files = Vec::new();
+    let is_multi_file = for_each_source(address, meta, |f| {
+        files.push(f);
+        Ok(())
+    })?;
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(ContractSource {
+        contract_name: meta["ContractName"].as_str().unwrap_or("").to_owned(),
+        compiler_version: meta["CompilerVersion"].as_str().unwrap_or("").to_owned(),
+        is_multi_file,
+        files,
+    })
+}
+
+/// Fetch verified source code of contract as list of files.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+pub fn fetch_sources(chain: &Chain, address: &str) -> Result<ContractSource, String> {
+    let meta = explorer::get_source_meta(chain, address)?;
+    sources_from_meta(address, &meta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Escaped JSON string literal of text with escape sequences of every kind
+    const LITERAL: &str = r#""pragma solidity ^0.8.0;\n\tstring s = \"a\\b\/c\";\r\n// café 😀 \u0008\u000c ü\n""#;
+
+    /// Read reader to the end through buffer of size.
+    fn read_all<R: Read>(mut reader: R, size: usize) -> std::io::Result<Vec<u8>> {
+        let mut decoded = Vec::new();
+        let mut buf = vec![0u8; size];
+        loop {
+            let n = reader.read(&mut buf)?;
+            if n == 0 {
+                return Ok(decoded);
+            }
+            decoded.extend_from_slice(&buf[..n]);
+        }
+    }
+
+    #[test]
+    fn json_string_reader_decodes_escape_sequences() {
+        let expected: String = serde_json::from_str(LITERAL).unwrap();
+        for size in [1, 2, 3, 7, 4096] {
+            let decoded = read_all(JsonStringReader::new(LITERAL), size).unwrap();
+            assert_eq!(String::from_utf8(decoded).unwrap(), expected, "buffer of {} byte(s)", size);
+        }
+    }
+
+    #[test]
+    fn json_string_reader_rejects_invalid_escape_sequences() {
+        for literal in [r#""a\x""#, r#""\u12""#, r#""\u12zz""#, r#""a\""#] {
+            let err = read_all(JsonStringReader::new(literal), 16).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "{}", literal);
+        }
+        assert_eq!(read_all(JsonStringReader::new(r#""""#), 16).unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn json_string_reader_resumes_from_checkpoints() {
+        let expected: String = serde_json::from_str(LITERAL).unwrap();
+        let mut reader = JsonStringReader::new(LITERAL);
+        let mut buf = [0u8; 5];
+        while reader.read(&mut buf).unwrap() > 0 {}
+        assert!(reader.checkpoints.len() > 1);
+        for checkpoint in reader.checkpoints.iter() {
+            let decoded = read_all(JsonStringReader::resume(LITERAL, *checkpoint), 3).unwrap();
+            assert_eq!(decoded, expected.as_bytes()[checkpoint.0..].to_vec(), "checkpoint {:?}", checkpoint);
+        }
+    }
+
+    #[test]
+    fn index_sources_raw_reads_files_as_decoded() {
+        // in order not sorted, as submitted
+        let input = r#"{"language":"Solidity","sources":{"src/B.sol":{"content":"import \"./A.sol\";\ncontract B is A {}\n"},"src/A.sol":{"content":"contract A { string s = \"\u00e9\\t\"; }\n"}},"settings":{"optimizer":{"enabled":true}}}"#;
+        let body = json!({
+            "status": "1",
+            "message": "OK",
+            "result": [{ "SourceCode": format!("{{{}}}", input), "ContractName": "B" }],
+        }).to_string();
+        let input: Value = serde_json::from_str(input).unwrap();
+        let raw = explorer::parse_source_meta_raw("0xbeef", &body).unwrap();
+
+        let mut inspected = Vec::new();
+        let index = index_sources_raw("0xbeef", &raw, |f| inspected.push((f.path.clone(), f.content.clone()))).unwrap();
+        assert!(index.visited.is_multi_file);
+        assert_eq!(index.paths, vec!["src/B.sol", "src/A.sol"]);
+        for (i, (path, content)) in inspected.iter().enumerate().rev() {
+            let file = index.read("0xbeef", &raw, i).unwrap();
+            assert_eq!(&file.path, path);
+            assert_eq!(&file.content, content);
+            assert_eq!(file.content, input["sources"][path]["content"].as_str().unwrap());
+        }
+    }
+}
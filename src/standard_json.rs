@@ -0,0 +1,121 @@
+//! `standard-json` command: emit standard JSON input of verified contract,
+//! which can be fed straight back into `solc --standard-json`, or a
+//! verification service to reproduce the build.
+//!
+//! Input of standard JSON submission is emitted byte for byte as submitted,
+//! including its remappings, and output selection. For single, and
+//! multi-file submissions, it's reconstructed from source files, compiler
+//! settings, and linked libraries of verification metadata.
+
+use crate::deps;
+use crate::explorer;
+use crate::solc;
+use crate::source::{self, SourceFile};
+use crate::TargetArgs;
+use clap::Args;
+use serde_json::{json, Value};
+
+#[derive(Debug, Args)]
+pub struct StandardJsonArgs {
+    #[clap(flatten)]
+    pub target: TargetArgs,
+
+    /// Filepath to write standard JSON input to. If not specified, it will
+    /// be printed to stdout.
+    #[clap(long="out", short='o', required=false)]
+    pub out_path: Option<String>,
+}
+
+/// Get input of standard JSON submission as submitted, or `None` if source
+/// code wasn't submitted as one.
+///
+/// # Arguments
+/// * `meta` - verification metadata
+fn submitted_input(meta: &Value) -> Option<&str> {
+    let source_code = meta["SourceCode"].as_str()?.trim();
+
+    // standard JSON submission is wrapped with an extra pair of braces
+    if source_code.starts_with("{{") && source_code.ends_with("}}") {
+        Some(&source_code[1..source_code.len() - 1])
+    }
+    else {
+        None
+    }
+}
+
+/// Reconstruct standard JSON input of single, or multi-file submission.
+/// Linked libraries are placed under the file declaring them.
+///
+/// # Arguments
+/// * `meta` - verification metadata
+/// * `files` - source files
+fn reconstruct_input(meta: &Value, files: &[SourceFile]) -> Value {
+    let mut settings = solc::settings_of(meta, None);
+
+    let mut libraries = serde_json::Map::new();
+    for library in deps::linked_libraries(meta, &settings).iter() {
+        let file = files.iter()
+            .find(|f| source::declares(&f.content, &library.name))
+            .or_else(|| files.first())
+            .map_or("", |f| f.path.as_str());
+        let by_name = libraries.entry(file.to_owned()).or_insert_with(|| json!({}));
+        by_name[&library.name] = json!(library.address);
+    }
+    if !libraries.is_empty() {
+        settings["libraries"] = Value::Object(libraries);
+    }
+
+    // what verification services, and tooling usually ask for
+    settings["outputSelection"] = json!({
+        "*": {
+            "*": ["abi", "evm.bytecode", "evm.deployedBytecode", "evm.methodIdentifiers", "metadata"],
+            "": ["ast"],
+        }
+    });
+
+    let mut input = solc::build_input(files, settings);
+    if meta["CompilerVersion"].as_str().is_some_and(|v| v.to_lowercase().starts_with("vyper")) {
+        input["language"] = json!("Vyper");
+    }
+    input
+}
+
+/// Execute `standard-json` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &StandardJsonArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.target.chain)?;
+    let address = args.target.address.to_lowercase();
+    let meta = explorer::get_source_meta(chain, &address)?;
+
+    let out = match submitted_input(&meta) {
+        Some(input) => format!("{}\n", input),
+        None => {
+            let contract_source = source::sources_from_meta(&address, &meta)?;
+            if contract_source.files.is_empty() {
+                let err_msg = format!("Error {} is not verified", address);
+                return Err(err_msg);
+            }
+            eprintln!("Warning: {} wasn't verified with standard JSON input; reconstructed from its source files, and settings", address);
+            match serde_json::to_string_pretty(&reconstruct_input(&meta, &contract_source.files)) {
+                Ok(res) => format!("{}\n", res),
+                Err(e) => {
+                    let err_msg = format!("Error serializing standard JSON input; err={}", e);
+                    return Err(err_msg);
+                }
+            }
+        }
+    };
+
+    match args.out_path.as_ref() {
+        Some(path) => {
+            crate::create_intermediate_dirs(path)?;
+            crate::write_file(path, &out)
+        },
+        None => {
+            print!("{}", out);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! `status` command: report whether contract still has code at its address,
+//! how it was created, and whether its code may change.
+//!
+//! Contract without code but with creation known to explorer has been
+//! selfdestructed. Contract deployed with CREATE2 which can selfdestruct is
+//! metamorphic; it can be destroyed, and redeployed at the same address with
+//! different code.
+
+use crate::bytecode;
+use crate::explorer::{self, Creation};
+use crate::TargetArgs;
+use clap::Args;
+use serde_json::{json, Value};
+
+#[derive(Debug, Args)]
+pub struct StatusArgs {
+    #[clap(flatten)]
+    pub target: TargetArgs,
+
+    /// Output as JSON instead of human readable text
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+}
+
+/// Query internal transactions by given parameter e.g. `address`, or `txhash`.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `key` - name of parameter
+/// * `value` - value of parameter
+fn internal_txs(chain: &crate::backend::Chain, key: &str, value: &str) -> Result<Vec<Value>, String> {
+    let result = explorer::query(chain, &[
+        ("module", "account"),
+        ("action", "txlistinternal"),
+        (key, value),
+        ("sort", "desc"),
+    ])?;
+    match result {
+        Value::Array(txs) => Ok(txs),
+        _ => {
+            let err_msg = format!("Error unexpected response for internal transactions; result={}", result);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Get opcode contract was created with i.e. `CREATE`, or `CREATE2`.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+/// * `creation` - creation of contract
+fn creation_opcode(chain: &crate::backend::Chain, address: &str, creation: &Creation) -> Result<String, String> {
+    let txs = internal_txs(chain, "txhash", &creation.tx_hash)?;
+    let created = txs.iter().find(|tx| tx["contractAddress"].as_str().unwrap_or("").eq_ignore_ascii_case(address));
+    match created {
+        Some(tx) if tx["type"].as_str().unwrap_or("").eq_ignore_ascii_case("create2") => Ok("CREATE2".to_owned()),
+        // either by factory with CREATE, or by account directly
+        _ => Ok("CREATE".to_owned()),
+    }
+}
+
+/// Find hash of transaction which selfdestructed contract, or `None` if not
+/// found.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - contract address
+fn destroying_tx(chain: &crate::backend::Chain, address: &str) -> Result<Option<String>, String> {
+    // explorers list selfdestruct as internal transaction from contract
+    let txs = internal_txs(chain, "address", address)?;
+    let destroyed = txs.iter().find(|tx| {
+        let kind = tx["type"].as_str().unwrap_or("").to_lowercase();
+        (kind == "suicide" || kind == "selfdestruct") && tx["from"].as_str().unwrap_or("").eq_ignore_ascii_case(address)
+    });
+    Ok(destroyed.and_then(|tx| tx["hash"].as_str()).map(|h| h.to_owned()))
+}
+
+/// Execute `status` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &StatusArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.target.chain)?;
+    let address = args.target.address.to_lowercase();
+
+    let code = explorer::get_code(chain, &address)?;
+    let creation = explorer::get_creation(chain, &address)?;
+    let opcode = match creation.as_ref() {
+        Some(creation) => Some(creation_opcode(chain, &address, creation)?),
+        None => None,
+    };
+
+    let instructions = bytecode::disassemble(bytecode::strip_metadata(&code));
+    let can_selfdestruct = instructions.iter().any(|i| i.opcode == 0xff);
+    let can_delegatecall = instructions.iter().any(|i| i.opcode == 0xf4);
+    let is_create2 = opcode.as_deref() == Some("CREATE2");
+
+    let (state, destroyed_in) = if !code.is_empty() {
+        ("deployed", None)
+    }
+    else if creation.is_some() {
+        ("selfdestructed", destroying_tx(chain, &address)?)
+    }
+    else {
+        ("no contract", None)
+    };
+
+    // code which can selfdestruct at CREATE2 address can be replaced; through
+    // delegatecall it may be able to, as well
+    let metamorphic = is_create2 && (state == "selfdestructed" || can_selfdestruct);
+    let possibly_metamorphic = is_create2 && !metamorphic && can_delegatecall;
+
+    let status = json!({
+        "address": address,
+        "state": state,
+        "codeSize": code.len(),
+        "destroyedIn": destroyed_in,
+        "creator": creation.as_ref().map(|c| c.creator.clone()),
+        "creationTx": creation.as_ref().map(|c| c.tx_hash.clone()),
+        "createdWith": opcode,
+        "canSelfdestruct": can_selfdestruct,
+        "metamorphic": metamorphic,
+        "possiblyMetamorphic": possibly_metamorphic,
+    });
+
+    if args.json {
+        match serde_json::to_string_pretty(&status) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing status to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+        return Ok(());
+    }
+
+    match state {
+        "deployed" => println!("state: deployed ({} bytes of code)", code.len()),
+        "selfdestructed" => {
+            match destroyed_in.as_ref() {
+                Some(hash) => println!("state: selfdestructed in tx {}", hash),
+                None => println!("state: selfdestructed; destroying tx not found"),
+            }
+        },
+        _ => println!("state: no contract at address"),
+    }
+    if let (Some(creation), Some(opcode)) = (creation.as_ref(), opcode.as_ref()) {
+        println!("created: by {} in tx {} with {}", creation.creator, creation.tx_hash, opcode);
+    }
+    if metamorphic {
+        println!("metamorphic: yes; deployed with CREATE2, and can selfdestruct, so different code may be redeployed at the address");
+    }
+    else if possibly_metamorphic {
+        println!("metamorphic: possibly; deployed with CREATE2, and can DELEGATECALL which may selfdestruct it");
+    }
+    Ok(())
+}
@@ -0,0 +1,265 @@
+//! `storage-check` command: compare storage layout of proxy contract against
+//! its implementation, and report slots both of them use for different
+//! variables, and misuse of storage gaps.
+//!
+//! Implementation code runs on storage of proxy, so any state variable proxy
+//! declares itself must not overlap with the ones of implementation. Storage
+//! layouts are taken from `storageLayout` of recompiled verified source.
+
+use crate::build;
+use crate::explorer;
+use crate::proxy;
+use crate::TargetArgs;
+use clap::Args;
+use serde_json::{json, Value};
+
+#[derive(Debug, Args)]
+pub struct StorageCheckArgs {
+    #[clap(flatten)]
+    pub target: TargetArgs,
+
+    /// Implementation contract address. It is resolved from the proxy if not
+    /// supplied.
+    #[clap(long="implementation", short='i', required=false)]
+    pub implementation: Option<String>,
+
+    /// Path to solc binary to use instead of finding one matching the verified
+    /// compiler version
+    #[clap(long="solc", required=false)]
+    pub solc: Option<String>,
+
+    /// Output as JSON instead of human readable text
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+}
+
+/// State variable placed in storage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageVariable {
+    /// Contract declaring it e.g. `contracts/Vault.sol:Vault`
+    pub contract: String,
+
+    pub label: String,
+    pub slot: u128,
+
+    /// Offset in bytes within slot
+    pub offset: u64,
+
+    /// Size in bytes
+    pub size: u64,
+
+    /// Type as written in source e.g. `mapping(address => uint256)`
+    pub type_label: String,
+}
+
+impl StorageVariable {
+    /// Position of its first byte counting from start of slot 0
+    pub fn start(&self) -> u128 {
+        self.slot * 32 + self.offset as u128
+    }
+
+    /// Position after its last byte counting from start of slot 0
+    pub fn end(&self) -> u128 {
+        self.start() + self.size as u128
+    }
+
+    /// Whether or not it is a storage gap reserved for future variables
+    pub fn is_gap(&self) -> bool {
+        self.label.starts_with("__gap")
+    }
+
+    /// Whether or not it occupies any byte of other variable
+    ///
+    /// # Arguments
+    /// * `other` - other variable
+    pub fn overlaps(&self, other: &StorageVariable) -> bool {
+        self.start() < other.end() && other.start() < self.end()
+    }
+
+    /// Whether or not it is the same variable as other, at the same position
+    ///
+    /// # Arguments
+    /// * `other` - other variable
+    pub fn same_as(&self, other: &StorageVariable) -> bool {
+        self.label == other.label && self.type_label == other.type_label && self.start() == other.start() && self.size == other.size
+    }
+}
+
+/// Problem found in storage layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    /// `conflict`, or `gap`
+    pub kind: String,
+
+    pub slot: u128,
+    pub message: String,
+}
+
+/// Get storage layout of contract from standard JSON output of solc ordered by
+/// position of variables.
+///
+/// # Arguments
+/// * `output` - standard JSON output of solc with `storageLayout` selected
+/// * `contract_name` - name of contract
+pub fn layout_of(output: &Value, contract_name: &str) -> Result<Vec<StorageVariable>, String> {
+    let layout = match output["contracts"].as_object()
+        .and_then(|files| files.values().find_map(|f| f.get(contract_name)))
+        .map(|c| &c["storageLayout"]) {
+        Some(res) if res.is_object() => res,
+        _ => {
+            let err_msg = format!("Error storage layout of contract '{}' not found in compiled output", contract_name);
+            return Err(err_msg);
+        }
+    };
+
+    let text = |v: &Value| v.as_str().unwrap_or("").to_owned();
+    let mut variables = Vec::new();
+    for entry in layout["storage"].as_array().map_or(&[][..], |s| s.as_slice()).iter() {
+        let kind = &layout["types"][entry["type"].as_str().unwrap_or("")];
+        variables.push(StorageVariable {
+            contract: text(&entry["contract"]),
+            label: text(&entry["label"]),
+            slot: entry["slot"].as_str().and_then(|s| s.parse().ok()).unwrap_or(0),
+            offset: entry["offset"].as_u64().unwrap_or(0),
+            size: kind["numberOfBytes"].as_str().and_then(|s| s.parse().ok()).unwrap_or(32),
+            type_label: text(&kind["label"]),
+        });
+    }
+    variables.sort_by_key(|v| v.start());
+    Ok(variables)
+}
+
+/// Get storage layout of primary contract of verified source ordered by
+/// position of variables, or `None` if there is no verified source code.
+///
+/// # Arguments
+/// * `target` - target contract address and chain
+/// * `solc_path` - user supplied path to solc binary
+pub fn storage_layout(target: &TargetArgs, solc_path: Option<&String>) -> Result<Option<Vec<StorageVariable>>, String> {
+    let chain = crate::parse_chain(&target.chain)?;
+    let meta = explorer::get_source_meta(chain, &target.address)?;
+    if meta["SourceCode"].as_str().unwrap_or("").is_empty() {
+        return Ok(None);
+    }
+
+    let built = build::build(target, solc_path, json!({ "*": { "*": ["storageLayout"] } }))?;
+    layout_of(&built.output, &built.contract_name).map(Some)
+}
+
+/// Find misuse of storage gaps i.e. gap which is not a fixed-size `uint256`
+/// array, or isn't the last variable of contract declaring it.
+///
+/// # Arguments
+/// * `layout` - storage layout
+pub fn gap_issues(layout: &[StorageVariable]) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    for gap in layout.iter().filter(|v| v.is_gap()) {
+        if !gap.type_label.starts_with("uint256[") || gap.type_label.ends_with("[]") {
+            issues.push(Issue {
+                kind: "gap".to_owned(),
+                slot: gap.slot,
+                message: format!("{} of {} is {} instead of fixed-size uint256 array", gap.label, gap.contract, gap.type_label),
+            });
+        }
+        for after in layout.iter().filter(|v| v.contract == gap.contract && !v.is_gap() && v.start() > gap.start()) {
+            issues.push(Issue {
+                kind: "gap".to_owned(),
+                slot: after.slot,
+                message: format!("{} of {} is declared after {}; new variables must take space of gap instead", after.label, after.contract, gap.label),
+            });
+        }
+    }
+    issues
+}
+
+/// Find variables of proxy overlapping with different variables of
+/// implementation.
+///
+/// # Arguments
+/// * `proxy` - storage layout of proxy
+/// * `implementation` - storage layout of implementation
+fn collision_issues(proxy: &[StorageVariable], implementation: &[StorageVariable]) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    for p in proxy.iter() {
+        // the same variable inherited by both is shared on purpose
+        for i in implementation.iter().filter(|i| p.overlaps(i) && !p.same_as(i)) {
+            issues.push(Issue {
+                kind: "conflict".to_owned(),
+                slot: i.slot.max(p.slot),
+                message: format!("proxy's {} ({}) overlaps implementation's {} ({})", p.label, p.type_label, i.label, i.type_label),
+            });
+        }
+    }
+    issues
+}
+
+/// Execute `storage-check` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &StorageCheckArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.target.chain)?;
+    let (implementation, source) = match args.implementation.as_ref() {
+        Some(res) => (res.to_lowercase(), "supplied".to_owned()),
+        None => match proxy::resolve_implementation(chain, &args.target.address)? {
+            Some(res) => (res.address, res.source),
+            None => {
+                let err_msg = format!("Error implementation of {} not found; supply it with --implementation", args.target.address);
+                return Err(err_msg);
+            }
+        },
+    };
+
+    // minimal proxies are often not verified, and have no variables anyway
+    let proxy_layout = storage_layout(&args.target, args.solc.as_ref())?;
+    let implementation_target = TargetArgs { address: implementation.clone(), chain: args.target.chain.clone() };
+    let implementation_layout = match storage_layout(&implementation_target, args.solc.as_ref())? {
+        Some(res) => res,
+        None => {
+            let err_msg = format!("Error no verified source code of implementation {}", implementation);
+            return Err(err_msg);
+        }
+    };
+
+    let mut issues = collision_issues(proxy_layout.as_deref().unwrap_or(&[]), &implementation_layout);
+    issues.extend(gap_issues(&implementation_layout));
+    issues.sort_by_key(|i| i.slot);
+
+    if args.json {
+        let out = json!({
+            "proxy": args.target.address.to_lowercase(),
+            "proxyVerified": proxy_layout.is_some(),
+            "implementation": implementation,
+            "implementationSource": source,
+            "issues": issues.iter().map(|i| json!({ "kind": i.kind, "slot": i.slot.to_string(), "message": i.message })).collect::<Vec<Value>>(),
+        });
+        match serde_json::to_string_pretty(&out) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing storage check to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+    }
+    else {
+        println!("implementation: {} ({})", implementation, source);
+        if proxy_layout.is_none() {
+            println!("proxy has no verified source code; assuming it declares no state variables");
+        }
+        if issues.is_empty() {
+            println!("No storage collisions found");
+        }
+        else {
+            println!("{:<8} {:<6} DETAIL", "KIND", "SLOT");
+            for issue in issues.iter() {
+                println!("{:<8} {:<6} {}", issue.kind, issue.slot, issue.message);
+            }
+        }
+    }
+
+    if !issues.is_empty() {
+        let err_msg = format!("Error {} storage issue(s) found", issues.len());
+        return Err(err_msg);
+    }
+    Ok(())
+}
@@ -0,0 +1,347 @@
+//! `sync` command: keep a local mirror of tracked contracts up to date.
+//!
+//! Tracked contracts are listed in a TOML manifest.
+//!
+//! ```toml
+//! # output directory relative to the manifest, '.' by default
+//! out_dir = "deps"
+//! # default chain of contracts
+//! chain = "bsc"
+//! # directory layout, 'flat' by default
+//! layout = "lib"
+//!
+//! [[contracts]]
+//! address = "0x1befe6f3f0e8edd2d4d15cae97baee01e51ea4a4"
+//! # directory name under output directory, address by default
+//! name = "lp-migration"
+//!
+//! [[contracts]]
+//! address = "0x..."
+//! chain = "ethereum"
+//! ```
+//!
+//! Each contract is written to `<out_dir>/<name>/`. Verification metadata is
+//! fetched first, and compared against hash recorded in manifest of its
+//! output directory (see `manifest`). Source code is only decoded, and
+//! written when it differs.
+//!
+//! With `--abi-combine`, ABIs of all tracked contracts e.g. router, factory,
+//! and pairs of a protocol are also written as a single JSON file. Entries
+//! shared by contracts are kept once, and constructors are dropped. With
+//! `--abi-namespace`, it's instead an object of ABI of each contract keyed by
+//! its name in manifest.
+
+use crate::abi;
+use crate::backend;
+use crate::explorer;
+use crate::fetch;
+use crate::layout;
+use crate::manifest::{self, OutDirWriter};
+use crate::progress;
+use clap::Args;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+#[derive(Debug, Args)]
+pub struct SyncArgs {
+    /// TOML manifest listing tracked contracts
+    #[clap(required=true, value_name="MANIFEST")]
+    pub manifest: String,
+
+    /// Whether or not to print status of each contract, and totals.
+    #[clap(long="silence", short='s', multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub silence: bool,
+
+    /// Also write ABIs of all tracked contracts as a single deduplicated ABI
+    /// JSON file at this path
+    #[clap(long="abi-combine", required=false, value_name="PATH")]
+    pub abi_combine: Option<String>,
+
+    /// Key combined ABI by name of each contract in manifest instead of
+    /// merging entries into one array
+    #[clap(long="abi-namespace", multiple_values=false, default_missing_value="true", takes_value=false, requires="abi-combine")]
+    pub abi_namespace: bool,
+}
+
+/// Tracked contract in manifest.
+#[derive(Debug, Deserialize)]
+pub struct TrackedContract {
+    pub address: String,
+    pub chain: Option<String>,
+    pub name: Option<String>,
+}
+
+/// Manifest of tracked contracts.
+#[derive(Debug, Deserialize)]
+pub struct SyncManifest {
+    pub out_dir: Option<String>,
+    pub chain: Option<String>,
+    pub layout: Option<String>,
+    #[serde(default)]
+    pub contracts: Vec<TrackedContract>,
+}
+
+/// Status of tracked contract after syncing.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum SyncStatus {
+    New,
+    Changed,
+    Unchanged,
+}
+
+/// Read manifest of tracked contracts.
+///
+/// # Arguments
+/// * `path` - path of manifest
+pub fn read_sync_manifest(path: &str) -> Result<SyncManifest, String> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error reading manifest at '{}'; err={}", path, e);
+            return Err(err_msg);
+        }
+    };
+    match toml::from_str(&content) {
+        Ok(res) => Ok(res),
+        Err(e) => {
+            let err_msg = format!("Error parsing manifest at '{}'; err={}", path, e);
+            Err(err_msg)
+        }
+    }
+}
+
+/// Compute hash of verification metadata including source code, along with
+/// options affecting generated files.
+///
+/// # Arguments
+/// * `raw` - verification metadata with source code left undecoded
+/// * `options` - fetch options
+fn source_hash(raw: &explorer::RawSourceMeta, options: &fetch::FetchOptions) -> String {
+    let key = format!("{:?}\n{}\n{}\n{}", options.layout, options.clean_crlf, raw.meta, raw.source_code.get());
+    crate::abi::to_hex(&crate::abi::keccak256(key.as_bytes()))
+}
+
+/// Sync a single tracked contract into its directory. Returns its status,
+/// along with its ABI in JSON.
+///
+/// # Arguments
+/// * `contract_dir` - output directory of contract
+/// * `chain` - chain
+/// * `address` - contract address
+/// * `options` - fetch options
+fn sync_contract(contract_dir: &str, chain: &crate::backend::Chain, address: &str, options: &fetch::FetchOptions) -> Result<(SyncStatus, String), String> {
+    progress::emit("fetch_start", json!({ "address": address, "chain": chain.name }));
+    let previous = manifest::read_manifest(contract_dir)?;
+
+    // cheap status check before decoding, and writing anything. The first
+    // backend having contract verified serves it.
+    let (body, serving) = backend::with_failover(chain, |c| {
+        let body = explorer::get_source_meta_text(c, address)?;
+        if !explorer::parse_source_meta_raw(address, &body)?.is_verified() {
+            let err_msg = format!("Error {} is not verified", address);
+            return Err(err_msg);
+        }
+        Ok(body)
+    })?;
+    let raw = explorer::parse_source_meta_raw(address, &body)?;
+    let abi_text = raw.meta["ABI"].as_str().unwrap_or("[]").to_owned();
+    let hash = source_hash(&raw, options);
+    if let Some(previous) = previous.as_ref() {
+        let all_exist = previous.files.keys().all(|p| matches!(crate::combine_two_path_components(contract_dir, p), Ok(f) if std::path::Path::new(&f).exists()));
+        if previous.source_keccak256 == hash && all_exist {
+            progress::emit("fetch_done", json!({ "address": address, "chain": chain.name, "files": 0 }));
+            return Ok((SyncStatus::Unchanged, abi_text));
+        }
+    }
+
+    let mut writer = OutDirWriter::open(contract_dir, address, &chain.name, true)?;
+    writer.set_source_hash(&hash);
+    writer.set_backend(serving.backend.name());
+    let count = fetch::fetch_each_raw(serving, address, &raw, options, |path, content| writer.write(path, content))?.count;
+    let written = writer.written;
+    writer.finish()?;
+    progress::emit("fetch_done", json!({ "address": address, "chain": chain.name, "files": count }));
+
+    let status = match previous {
+        None => SyncStatus::New,
+        Some(_) if written > 0 => SyncStatus::Changed,
+        Some(_) => SyncStatus::Unchanged,
+    };
+    Ok((status, abi_text))
+}
+
+/// Key identifying ABI entry regardless of parameter names; kind, and
+/// canonical signature, along with which parameters are indexed for event.
+///
+/// # Arguments
+/// * `entry` - ABI entry
+fn entry_key(entry: &abi::Entry) -> String {
+    match entry.kind.as_str() {
+        "fallback" | "receive" => entry.kind.clone(),
+        "event" => {
+            let indexed: Vec<&str> = entry.inputs.iter().map(|p| if p.indexed { "1" } else { "0" }).collect();
+            format!("event {} {}", entry.signature(), indexed.join(""))
+        },
+        _ => format!("{} {}", entry.kind, entry.signature()),
+    }
+}
+
+/// Combine ABIs of contracts into a single one. Entries shared by contracts
+/// are kept once as of the first contract, and constructors are dropped.
+/// Functions of the same signature but different outputs, or state
+/// mutability are warned about, as well as contracts whose ABI can't be
+/// parsed, which are left out.
+///
+/// # Arguments
+/// * `abis` - pairs of name of contract, and its ABI in JSON
+fn combine_abis(abis: &[(String, String)]) -> Vec<Value> {
+    let mut combined: Vec<Value> = Vec::new();
+    let mut seen: Vec<(String, String, abi::Entry)> = Vec::new();
+    for (name, text) in abis.iter() {
+        let values: Vec<(Value, abi::Entry)> = match serde_json::from_str::<Vec<Value>>(text) {
+            Ok(res) => match res.into_iter().map(|v| serde_json::from_value(v.clone()).map(|e| (v, e))).collect() {
+                Ok(res) => res,
+                Err(e) => {
+                    eprintln!("Warning: left out ABI of {} from combined ABI; err={}", name, e);
+                    continue;
+                }
+            },
+            Err(e) => {
+                eprintln!("Warning: left out ABI of {} from combined ABI; err={}", name, e);
+                continue;
+            }
+        };
+        for (value, entry) in values.into_iter() {
+            if entry.kind == "constructor" {
+                continue;
+            }
+            let key = entry_key(&entry);
+            match seen.iter().find(|(k, _, _)| *k == key) {
+                Some((_, first, kept)) => {
+                    let outputs = |e: &abi::Entry| e.outputs.iter().map(|p| p.canonical_type()).collect::<Vec<String>>();
+                    if entry.kind == "function" && (outputs(kept) != outputs(&entry) || kept.mutability() != entry.mutability()) {
+                        eprintln!("Warning: {} of {} differs from the one of {}; keeping the one of {}", entry.declaration(), name, first, first);
+                    }
+                },
+                None => {
+                    seen.push((key, name.clone(), entry));
+                    combined.push(value);
+                },
+            }
+        }
+    }
+    combined
+}
+
+/// Write combined ABI of contracts into file.
+///
+/// # Arguments
+/// * `path` - path of output file
+/// * `abis` - pairs of name of contract, and its ABI in JSON
+/// * `namespace` - whether to key ABI by name of each contract instead of
+///   merging them
+fn write_combined_abi(path: &str, abis: &[(String, String)], namespace: bool) -> Result<usize, String> {
+    let (out, count) = if namespace {
+        let mut by_name = serde_json::Map::new();
+        let mut count = 0;
+        for (name, text) in abis.iter() {
+            let entries = combine_abis(&[(name.clone(), text.clone())]);
+            count += entries.len();
+            by_name.insert(name.clone(), Value::Array(entries));
+        }
+        (Value::Object(by_name), count)
+    }
+    else {
+        let entries = combine_abis(abis);
+        let count = entries.len();
+        (Value::Array(entries), count)
+    };
+
+    let content = match serde_json::to_string_pretty(&out) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error serializing combined ABI; err={}", e);
+            return Err(err_msg);
+        }
+    };
+    crate::create_intermediate_dirs(path)?;
+    if let Err(e) = std::fs::write(path, format!("{}\n", content)) {
+        let err_msg = format!("Error writing combined ABI to '{}'; err={}", path, e);
+        return Err(err_msg);
+    }
+    Ok(count)
+}
+
+/// Execute `sync` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &SyncArgs) -> Result<(), String> {
+    let manifest = read_sync_manifest(&args.manifest)?;
+    let out_layout = layout::parse_layout(manifest.layout.as_deref().unwrap_or("flat"))?;
+
+    // output directory is relative to the manifest
+    let base_dir = std::path::Path::new(&args.manifest).parent().and_then(|p| p.to_str()).unwrap_or("");
+    let base_dir = if base_dir.is_empty() { "." } else { base_dir };
+    let out_dir = crate::combine_two_path_components(base_dir, manifest.out_dir.as_deref().unwrap_or("."))?;
+
+    let options = fetch::FetchOptions {
+        abi_only: false,
+        abi_pretty_print: true,
+        clean_crlf: true,
+        layout: out_layout,
+        project_files: true,
+        artifact_format: None,
+        order: crate::source::Order::Path,
+        format: None,
+        canonical: false,
+        raw_response: false,
+        interfaces_only: false,
+        prune_unreachable: false,
+        contract: None,
+    };
+
+    let (mut new, mut changed, mut unchanged, mut failed) = (0, 0, 0, 0);
+    let mut abis: Vec<(String, String)> = Vec::new();
+    for contract in manifest.contracts.iter() {
+        let result = contract.chain.as_ref().or(manifest.chain.as_ref())
+            .ok_or_else(|| format!("Error no chain specified for {}", contract.address))
+            .and_then(|c| crate::parse_chain(c))
+            .and_then(|chain| {
+                let name = crate::source::safe_relative_path(contract.name.as_deref().unwrap_or(&contract.address));
+                let contract_dir = crate::combine_two_path_components(&out_dir, &name)?;
+                sync_contract(&contract_dir, chain, &contract.address, &options)
+            });
+
+        if let Ok((_, abi_text)) = result.as_ref() {
+            abis.push((contract.name.clone().unwrap_or_else(|| contract.address.clone()), abi_text.clone()));
+        }
+        let status = match result {
+            Ok((SyncStatus::New, _)) => { new += 1; "new".to_owned() },
+            Ok((SyncStatus::Changed, _)) => { changed += 1; "changed".to_owned() },
+            Ok((SyncStatus::Unchanged, _)) => { unchanged += 1; "unchanged".to_owned() },
+            Err(e) => {
+                failed += 1;
+                progress::error(Some(&contract.address), &e);
+                format!("failed; {}", e)
+            },
+        };
+        if !crate::progress::silenced(args.silence) {
+            println!("{} {}", contract.address, status);
+        }
+    }
+
+    // failed contracts are reported via exit code, see `progress::tally()`
+    if !crate::progress::silenced(args.silence) {
+        println!("changed: {}, unchanged: {}, new: {}, failed: {}", changed, unchanged, new, failed);
+    }
+
+    // failed contracts are left out, and already warned about
+    if let Some(path) = args.abi_combine.as_ref() {
+        let count = write_combined_abi(path, &abis, args.abi_namespace)?;
+        if !crate::progress::silenced(args.silence) {
+            println!("combined ABI of {} contract(s), {} entries written to {}", abis.len(), count, path);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,422 @@
+//! `trace` command: render call tree of transaction with target of each
+//! frame resolved to contract name, and its call, return values, and revert
+//! decoded.
+//!
+//! Trace comes from node at `ETH_RPC_URL`, or internal transactions of API
+//! platform, see `call_trace`. Calls are decoded against ABI of verified
+//! target, or of implementation it delegates the same call to e.g. proxy,
+//! otherwise against signatures in the local database of selectors (see
+//! `selectors`).
+//!
+//! With `--gas`, each frame shows gas it used with, and without its subcalls,
+//! followed by where gas went by contract, and function. Self gas of the
+//! transaction itself includes its intrinsic gas.
+
+use crate::abi::{self, Abi, Param};
+use crate::call_trace::{self, Frame};
+use crate::decode;
+use crate::display;
+use crate::explorer;
+use crate::selectors;
+use clap::Args;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// Precompiled contracts, and their names
+const PRECOMPILES: [(&str, &str); 10] = [
+    ("0x0000000000000000000000000000000000000001", "ecrecover"),
+    ("0x0000000000000000000000000000000000000002", "sha256"),
+    ("0x0000000000000000000000000000000000000003", "ripemd160"),
+    ("0x0000000000000000000000000000000000000004", "identity"),
+    ("0x0000000000000000000000000000000000000005", "modexp"),
+    ("0x0000000000000000000000000000000000000006", "ecAdd"),
+    ("0x0000000000000000000000000000000000000007", "ecMul"),
+    ("0x0000000000000000000000000000000000000008", "ecPairing"),
+    ("0x0000000000000000000000000000000000000009", "blake2f"),
+    ("0x000000000000000000000000000000000000000a", "pointEvaluation"),
+];
+
+#[derive(Debug, Args)]
+pub struct TraceArgs {
+    /// Hash of transaction
+    #[clap(long="tx", required=true)]
+    pub tx: String,
+
+    /// Which chain to work with, by name e.g. 'bsc', 'ethereum', 'polygon',
+    /// and 'arbitrum', or chain id e.g. '56'. See 'tracpls chains' for all.
+    #[clap(long="chain", short='c', required=true, multiple_values=false)]
+    pub chain: String,
+
+    /// Show frames only down to this depth; the transaction itself is depth 0
+    #[clap(long="max-depth", required=false)]
+    pub max_depth: Option<usize>,
+
+    /// Show gas used by each frame, with and without its subcalls, and
+    /// summary of where gas went by contract, and function
+    #[clap(long="gas", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub gas: bool,
+
+    /// Output as JSON instead of human readable tree
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+}
+
+/// Target of frames resolved.
+struct Contract {
+    /// Name of verified contract, or its public name tag, or precompile,
+    /// empty if none
+    name: String,
+
+    /// ABI of verified contract
+    abi: Option<Abi>,
+}
+
+/// Call of frame decoded.
+struct Decoded {
+    /// Signature of function e.g. `transfer(address,uint256)`, or what the
+    /// call is if it has none e.g. `receive()`, or selector if not known
+    signature: String,
+
+    /// Arguments as pairs of name, and value
+    args: Vec<(String, String)>,
+
+    /// Return values as pairs of name, and value
+    outputs: Vec<(String, String)>,
+
+    /// Revert, or error of frame in one line, `None` if it succeeded
+    revert: Option<String>,
+}
+
+/// Resolve target of frame into contract name, and ABI. Failures are not
+/// fatal as they only leave the frame undecoded.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `address` - address of target
+fn resolve(chain: &crate::backend::Chain, address: &str) -> Contract {
+    if let Some((_, name)) = PRECOMPILES.iter().find(|(a, _)| *a == address) {
+        return Contract { name: format!("precompile {}", name), abi: None };
+    }
+    let meta = explorer::get_source_meta(chain, address).unwrap_or_default();
+    let name = meta["ContractName"].as_str().unwrap_or("");
+    if !name.is_empty() {
+        let abi_text = meta["ABI"].as_str().unwrap_or("");
+        selectors::index_abi(abi_text);
+        return Contract { name: name.to_owned(), abi: Abi::parse(abi_text).ok() };
+    }
+    let name = explorer::get_name_tag(chain, address).map_or_else(String::new, |t| t.describe());
+    Contract { name, abi: None }
+}
+
+/// Decode ABI-encoded values against parameters as pairs of name, and value.
+///
+/// # Arguments
+/// * `params` - parameters as pairs of name, and canonical type
+/// * `data` - ABI-encoded values
+fn decode_values(params: &[(String, String)], data: &[u8]) -> Vec<(String, String)> {
+    let kinds: Vec<&str> = params.iter().map(|(_, kind)| kind.as_str()).collect();
+    params.iter().zip(decode::decode_params(&kinds, data))
+        .map(|((name, _), value)| (name.clone(), value.unwrap_or_else(|| "<missing>".to_owned())))
+        .collect()
+}
+
+/// Get names, and canonical types of parameters.
+///
+/// # Arguments
+/// * `params` - parameters
+fn named_types(params: &[Param]) -> Vec<(String, String)> {
+    params.iter().map(|p| (p.name.clone(), p.canonical_type())).collect()
+}
+
+/// Decode call, return values, and revert of frame.
+///
+/// # Arguments
+/// * `chain` - chain
+/// * `frame` - frame
+/// * `contracts` - targets of frames resolved
+/// * `input_known` - whether input, and output of frame are known
+fn decode_frame(chain: &crate::backend::Chain, frame: &Frame, contracts: &BTreeMap<String, Contract>, input_known: bool) -> Decoded {
+    let abi_of = |address: &str| contracts.get(address).and_then(|c| c.abi.as_ref());
+    let revert = frame.error.as_ref().map(|error| {
+        if frame.output.is_empty() {
+            return error.clone();
+        }
+        let target = abi_of(&frame.to).map(|_| (frame.to.as_str(), chain.name.as_str()));
+        match decode::decode_revert(&frame.output, target) {
+            Ok(r) if !r.args.is_empty() => {
+                let args: Vec<String> = r.args.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+                format!("{}: {}", r.signature, args.join(", "))
+            },
+            Ok(r) if r.signature.is_empty() => r.message,
+            Ok(r) if r.message.is_empty() => r.signature,
+            Ok(r) => format!("{}: {}", r.signature, r.message),
+            Err(_) => format!("{} 0x{}", error, abi::to_hex(&frame.output)),
+        }
+    });
+    let decoded = |signature: String| Decoded { signature, args: Vec::new(), outputs: Vec::new(), revert: revert.clone() };
+
+    if frame.kind.starts_with("CREATE") {
+        return decoded(format!("new ({} byte(s) of creation code)", frame.input.len()));
+    }
+    if frame.kind == "SELFDESTRUCT" {
+        return decoded("selfdestruct".to_owned());
+    }
+    if !input_known {
+        return decoded("(input unknown)".to_owned());
+    }
+    if frame.input.len() < 4 {
+        return decoded(if frame.value != "0" { "receive()".to_owned() } else { "fallback()".to_owned() });
+    }
+    if contracts.get(&frame.to).is_some_and(|c| c.name.starts_with("precompile")) {
+        return decoded(format!("({} byte(s) of input)", frame.input.len()));
+    }
+
+    // proxy doesn't declare functions it delegates to its implementation
+    let selector = format!("0x{}", abi::to_hex(&frame.input[..4]));
+    let entry = std::iter::once(&frame.to)
+        .chain(frame.calls.iter().filter(|c| c.kind == "DELEGATECALL" && c.input == frame.input).map(|c| &c.to))
+        .find_map(|address| abi_of(address).and_then(|a| a.function_by_selector(&selector)));
+    let (signature, inputs, outputs) = match entry {
+        Some(e) => (e.signature(), named_types(&e.inputs), named_types(&e.outputs)),
+        None => match selectors::lookup(&selector).into_iter().next() {
+            Some(signature) => {
+                let inputs = decode::signature_types(&signature).into_iter().map(|kind| (String::new(), kind)).collect();
+                (signature, inputs, Vec::new())
+            },
+            None => return decoded(selector),
+        },
+    };
+    Decoded {
+        signature,
+        args: decode_values(&inputs, &frame.input[4..]),
+        outputs: if frame.error.is_none() && !frame.output.is_empty() { decode_values(&outputs, &frame.output) } else { Vec::new() },
+        revert,
+    }
+}
+
+/// What to render of trace, shared by frames.
+struct View<'a> {
+    chain: &'a crate::backend::Chain,
+
+    /// Targets of frames resolved
+    contracts: BTreeMap<String, Contract>,
+
+    /// Whether trace came from node, thus with input of every frame
+    from_node: bool,
+
+    /// Depth to render frames down to
+    max_depth: usize,
+
+    /// Whether to show gas used by each frame
+    gas: bool,
+}
+
+/// Gas used by frame itself excluding its subcalls. Subcalls beyond max depth
+/// are folded into frame at max depth.
+///
+/// # Arguments
+/// * `frame` - frame
+/// * `depth` - depth of frame
+/// * `max_depth` - depth frames are rendered down to
+fn self_gas(frame: &Frame, depth: usize, max_depth: usize) -> u64 {
+    if depth >= max_depth {
+        return frame.gas_used;
+    }
+    frame.gas_used.saturating_sub(frame.calls.iter().map(|c| c.gas_used).sum())
+}
+
+/// Describe target of frame e.g. `Router@0x...`, or its address if name isn't
+/// known.
+///
+/// # Arguments
+/// * `frame` - frame
+/// * `contracts` - targets of frames resolved
+fn target_of(frame: &Frame, contracts: &BTreeMap<String, Contract>) -> String {
+    match contracts.get(&frame.to).map(|c| c.name.as_str()) {
+        Some(name) if !name.is_empty() => format!("{}@{}", name, frame.to),
+        _ => frame.to.clone(),
+    }
+}
+
+/// Describe call of frame in one line e.g. `Router@0x...::swap(amount=5, ...)`.
+///
+/// # Arguments
+/// * `view` - what to render
+/// * `frame` - frame
+/// * `decoded` - call of frame decoded
+fn describe(view: &View, frame: &Frame, decoded: &Decoded) -> String {
+    let call = if decoded.args.is_empty() {
+        decoded.signature.clone()
+    }
+    else {
+        let name = decoded.signature.split('(').next().unwrap_or("");
+        let args: Vec<String> = decoded.args.iter().enumerate()
+            .map(|(i, (n, v))| if n.is_empty() { format!("arg{}={}", i, v) } else { format!("{}={}", n, v) })
+            .collect();
+        format!("{}({})", name, args.join(", "))
+    };
+    let mut line = format!("[{}] {}::{}", frame.kind, target_of(frame, &view.contracts), call);
+    if frame.value != "0" {
+        line.push_str(&format!(" value: {} {}", display::format_units(&frame.value, 18), display::native_symbol(view.chain)));
+    }
+    match decoded.revert.as_ref() {
+        Some(revert) => line.push_str(&format!(" [reverted: {}]", revert)),
+        None if !decoded.outputs.is_empty() => {
+            let outputs: Vec<String> = decoded.outputs.iter()
+                .map(|(n, v)| if n.is_empty() { v.clone() } else { format!("{}={}", n, v) })
+                .collect();
+            line.push_str(&format!(" => {}", outputs.join(", ")));
+        },
+        None => (),
+    }
+    line
+}
+
+/// Print frame, and its subcalls as tree, adding self gas of each to profile
+/// by contract, and function.
+///
+/// # Arguments
+/// * `view` - what to render
+/// * `frame` - frame
+/// * `depth` - depth of frame
+/// * `prefix` - prefix of lines of subcalls drawing their ancestors
+/// * `profile` - self gas, and number of frames by contract, and function
+fn print_frame(view: &View, frame: &Frame, depth: usize, prefix: &str, profile: &mut BTreeMap<String, (u64, usize)>) {
+    let decoded = decode_frame(view.chain, frame, &view.contracts, view.from_node || depth == 0);
+    let own_gas = self_gas(frame, depth, view.max_depth);
+    let label = match decoded.signature.split('(').next().unwrap_or("") {
+        "" => target_of(frame, &view.contracts),
+        function => format!("{}::{}", target_of(frame, &view.contracts), function),
+    };
+    let entry = profile.entry(label).or_insert((0, 0));
+    entry.0 += own_gas;
+    entry.1 += 1;
+
+    // gas columns lead lines so tree stays aligned
+    let gas_columns = |used: Option<(u64, u64)>| match (view.gas, used) {
+        (false, _) => String::new(),
+        (true, Some((total, own))) => format!("{:>10} {:>10}  ", total, own),
+        (true, None) => format!("{:>10} {:>10}  ", "", ""),
+    };
+    println!("{}", describe(view, frame, &decoded));
+    if depth == view.max_depth {
+        if !frame.calls.is_empty() {
+            println!("{}{}└─ ... {} subcall(s)", gas_columns(None), prefix, frame.calls.len());
+        }
+        return;
+    }
+    for (i, call) in frame.calls.iter().enumerate() {
+        let last = i + 1 == frame.calls.len();
+        print!("{}{}{}", gas_columns(Some((call.gas_used, self_gas(call, depth + 1, view.max_depth)))), prefix, if last { "└─ " } else { "├─ " });
+        let prefix = format!("{}{}", prefix, if last { "   " } else { "│  " });
+        print_frame(view, call, depth + 1, &prefix, profile);
+    }
+}
+
+/// Print where gas went by contract, and function as bars in share of gas
+/// used by transaction, largest first.
+///
+/// # Arguments
+/// * `profile` - self gas, and number of frames by contract, and function
+/// * `total` - gas used by transaction
+fn print_profile(profile: &BTreeMap<String, (u64, usize)>, total: u64) {
+    const BAR_WIDTH: u64 = 30;
+    let mut entries: Vec<(&String, &(u64, usize))> = profile.iter().collect();
+    entries.sort_by(|a, b| b.1.0.cmp(&a.1.0).then(a.0.cmp(b.0)));
+    println!("\ngas used: {} by self gas of contract, and function, subcalls excluded\n", total);
+    println!("{:>10} {:>7} {:>6}  {:<width$}  FUNCTION", "GAS", "SHARE", "CALLS", "", width = BAR_WIDTH as usize);
+    for (label, (gas, count)) in entries {
+        let share = if total == 0 { 0.0 } else { 100.0 * *gas as f64 / total as f64 };
+        let bar = "█".repeat(gas.saturating_mul(BAR_WIDTH).checked_div(total).unwrap_or(0) as usize);
+        println!("{:>10} {:>6.2}% {:>6}  {:<width$}  {}", gas, share, count, bar, label, width = BAR_WIDTH as usize);
+    }
+}
+
+/// Convert frame, and its subcalls into JSON.
+///
+/// # Arguments
+/// * `view` - what to render
+/// * `frame` - frame
+/// * `depth` - depth of frame
+fn to_json(view: &View, frame: &Frame, depth: usize) -> Value {
+    let decoded = decode_frame(view.chain, frame, &view.contracts, view.from_node || depth == 0);
+    let pairs = |values: &[(String, String)]| values.iter().map(|(name, value)| json!({ "name": name, "value": value })).collect::<Vec<Value>>();
+    json!({
+        "kind": frame.kind,
+        "from": frame.from,
+        "to": frame.to,
+        "contract": view.contracts.get(&frame.to).map(|c| c.name.as_str()).filter(|n| !n.is_empty()),
+        "value": frame.value,
+        "function": decoded.signature,
+        "args": pairs(&decoded.args),
+        "outputs": pairs(&decoded.outputs),
+        "input": format!("0x{}", abi::to_hex(&frame.input)),
+        "output": format!("0x{}", abi::to_hex(&frame.output)),
+        "gas": frame.gas,
+        "gasUsed": frame.gas_used,
+        "selfGas": self_gas(frame, depth, view.max_depth),
+        "error": frame.error,
+        "revert": decoded.revert,
+        "calls": if depth < view.max_depth { frame.calls.iter().map(|c| to_json(view, c, depth + 1)).collect() } else { Vec::new() },
+    })
+}
+
+/// Execute `trace` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &TraceArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.chain)?;
+    let tx_hash = args.tx.to_lowercase();
+    let max_depth = args.max_depth.unwrap_or(usize::MAX);
+
+    let trace = call_trace::get_trace(chain, &tx_hash)?;
+    let mut contracts: BTreeMap<String, Contract> = BTreeMap::new();
+    let mut targets: Vec<&str> = Vec::new();
+    trace.root.walk(0, &mut |frame: &Frame, depth| {
+        // implementations delegated to just beyond max depth decode calls to proxies at it
+        let delegated = frame.kind == "DELEGATECALL" && depth.checked_sub(1) == Some(max_depth);
+        if (depth <= max_depth || delegated) && !frame.to.is_empty() {
+            targets.push(&frame.to);
+        }
+    });
+    for address in targets {
+        if !contracts.contains_key(address) {
+            contracts.insert(address.to_owned(), resolve(chain, address));
+        }
+    }
+    let view = View { chain, contracts, from_node: trace.from_node, max_depth, gas: args.gas };
+
+    if args.json {
+        let out = json!({
+            "tx": tx_hash,
+            "complete": trace.from_node,
+            "trace": to_json(&view, &trace.root, 0),
+        });
+        match serde_json::to_string_pretty(&out) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing trace to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+        return Ok(());
+    }
+
+    if trace.from_node {
+        println!("trace of {} from node at {}\n", tx_hash, crate::foundry::RPC_URL_ENV);
+    }
+    else {
+        println!("partial trace of {} from internal transactions of API platform; input, and output of internal calls are unknown\n", tx_hash);
+    }
+    let mut profile: BTreeMap<String, (u64, usize)> = BTreeMap::new();
+    if args.gas {
+        println!("{:>10} {:>10}", "GAS USED", "SELF");
+        print!("{:>10} {:>10}  ", trace.root.gas_used, self_gas(&trace.root, 0, max_depth));
+    }
+    print_frame(&view, &trace.root, 0, "", &mut profile);
+    if args.gas {
+        print_profile(&profile, trace.root.gas_used);
+    }
+    Ok(())
+}
@@ -0,0 +1,178 @@
+//! `trace-deps` command: list every contract touched by a transaction, and
+//! optionally fetch verified ones, to scope code surface of an incident.
+//!
+//! Contracts are found from call trace of transaction, see `call_trace`, along
+//! with its access list. Trace is complete only with node at `ETH_RPC_URL`
+//! supporting `debug_traceTransaction`. With `--out-dir`, source code of each
+//! verified contract is written to `<out-dir>/<address>/`.
+
+use crate::call_trace::{self, Frame};
+use crate::explorer;
+use crate::fetch;
+use crate::progress;
+use clap::Args;
+use serde_json::{json, Value};
+
+#[derive(Debug, Args)]
+pub struct TraceDepsArgs {
+    /// Hash of transaction
+    #[clap(long="tx", required=true)]
+    pub tx: String,
+
+    /// Which chain to work with, by name e.g. 'bsc', 'ethereum', 'polygon',
+    /// and 'arbitrum', or chain id e.g. '56'. See 'tracpls chains' for all.
+    #[clap(long="chain", short='c', required=true, multiple_values=false)]
+    pub chain: String,
+
+    /// Fetch source code of verified contracts into this directory
+    #[clap(long="out-dir", short='o', required=false)]
+    pub out_dir: Option<String>,
+
+    /// Output as JSON instead of human readable table
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+}
+
+/// Contract touched by transaction.
+struct Dependency {
+    address: String,
+
+    /// How it was touched e.g. `CALL`, `DELEGATECALL`, or `access-list`
+    kinds: Vec<String>,
+
+    /// Number of frames calling, or creating it
+    calls: usize,
+
+    /// Name of verified contract, empty if not verified
+    name: String,
+
+    /// Number of files fetched, if fetched
+    files: Option<usize>,
+}
+
+/// Record address touched by transaction in order of first touch.
+///
+/// # Arguments
+/// * `deps` - contracts touched so far
+/// * `address` - address touched
+/// * `kind` - how it was touched
+fn touch(deps: &mut Vec<Dependency>, address: &str, kind: &str) {
+    if address.is_empty() {
+        return;
+    }
+    let index = match deps.iter().position(|d| d.address == address) {
+        Some(res) => res,
+        None => {
+            deps.push(Dependency { address: address.to_owned(), kinds: Vec::new(), calls: 0, name: String::new(), files: None });
+            deps.len() - 1
+        }
+    };
+    let dep = &mut deps[index];
+    if kind != "access-list" {
+        dep.calls += 1;
+    }
+    if !dep.kinds.iter().any(|k| k == kind) {
+        dep.kinds.push(kind.to_owned());
+    }
+}
+
+/// Execute `trace-deps` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &TraceDepsArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.chain)?;
+    let tx_hash = args.tx.to_lowercase();
+    let options = fetch::FetchOptions {
+        abi_only: false,
+        abi_pretty_print: true,
+        clean_crlf: true,
+        layout: crate::layout::Layout::Flat,
+        project_files: true,
+        artifact_format: None,
+        order: crate::source::Order::Path,
+        format: None,
+        canonical: false,
+        raw_response: false,
+        interfaces_only: false,
+        prune_unreachable: false,
+        contract: None,
+    };
+
+    let trace = call_trace::get_trace(chain, &tx_hash)?;
+    if !trace.from_node {
+        eprintln!("Warning: no node at {} supporting debug_traceTransaction; contracts only read, or called without value may be missing", crate::foundry::RPC_URL_ENV);
+    }
+    let mut touched: Vec<Dependency> = Vec::new();
+    trace.root.walk(0, &mut |frame: &Frame, _| touch(&mut touched, &frame.to, &frame.kind));
+    for (address, _) in trace.access_list.iter() {
+        touch(&mut touched, address, "access-list");
+    }
+
+    // accounts, and precompiles have no code, thus no source code to fetch
+    let mut deps: Vec<Dependency> = Vec::new();
+    let mut failed = 0;
+    for mut dep in touched {
+        let checked = explorer::get_code(chain, &dep.address)
+            .and_then(|code| if code.is_empty() { Ok(None) } else { fetch::check_verified(chain, &dep.address, args.out_dir.as_ref(), &options).map(Some) });
+        match checked {
+            Ok(Some((name, files))) => {
+                if let Some(count) = files.filter(|_| !args.json) {
+                    eprintln!("fetched {} {} file(s) of {}", name, count, dep.address);
+                }
+                dep.name = name;
+                dep.files = files;
+                deps.push(dep);
+            },
+            Ok(None) => (),
+            Err(e) => {
+                // failed contracts are reported via exit code, see `progress::tally()`
+                failed += 1;
+                progress::error(Some(&dep.address), &e);
+                eprintln!("{} failed; {}", dep.address, e);
+            }
+        }
+    }
+
+    if args.json {
+        let out = json!({
+            "tx": tx_hash,
+            "complete": trace.from_node,
+            "contracts": deps.iter().map(|d| json!({
+                "address": d.address,
+                "touchedBy": d.kinds,
+                "calls": d.calls,
+                "verified": !d.name.is_empty(),
+                "name": Some(&d.name).filter(|n| !n.is_empty()),
+                "files": d.files,
+            })).collect::<Vec<Value>>(),
+        });
+        match serde_json::to_string_pretty(&out) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing contracts touched to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+        return Ok(());
+    }
+
+    if deps.is_empty() {
+        println!("No contracts touched by {} found", tx_hash);
+        return Ok(());
+    }
+    println!("{:<42} {:>5} {:<28} NAME", "ADDRESS", "CALLS", "TOUCHED BY");
+    for d in deps.iter() {
+        let name = if d.name.is_empty() { "(not verified)" } else { &d.name };
+        println!("{:<42} {:>5} {:<28} {}", d.address, d.calls, d.kinds.join(","), name);
+    }
+    let verified = deps.iter().filter(|d| !d.name.is_empty()).count();
+    if args.out_dir.is_some() {
+        let fetched = deps.iter().filter(|d| d.files.is_some()).count();
+        println!("\ncontracts: {}, verified: {}, fetched: {}, failed: {}", deps.len(), verified, fetched, failed);
+    }
+    else {
+        println!("\ncontracts: {}, verified: {}, failed: {}", deps.len(), verified, failed);
+    }
+    Ok(())
+}
@@ -0,0 +1,139 @@
+//! Log of what the clean, and transform pipeline changed in each source file
+//! relative to what API platform responded, written with `--transform-log`.
+//!
+//! Each file is recorded with keccak256 hash, and size of its content as
+//! decoded from the response, then of each pass run on it i.e. cleaning of
+//! CR/LF, canonicalization, and formatting, so that evidence-grade workflows
+//! can prove output is otherwise untouched. Files generated by tracpls e.g.
+//! `remappings.txt` aren't recorded as they don't come from API platform.
+
+use crate::abi;
+use serde_json::{json, Value};
+use std::sync::Mutex;
+
+/// Recorded files, or `None` if not recording
+static FILES: Mutex<Option<Vec<Value>>> = Mutex::new(None);
+
+/// Start recording transforms to be written as log.
+pub fn start_recording() {
+    if let Ok(mut files) = FILES.lock() {
+        *files = Some(Vec::new());
+    }
+}
+
+/// Whether or not transforms are recorded, so callers can skip collecting
+/// them.
+pub fn is_enabled() -> bool {
+    FILES.lock().is_ok_and(|f| f.is_some())
+}
+
+/// Get hash, and size of content.
+///
+/// # Arguments
+/// * `content` - content
+fn digest(content: &str) -> Value {
+    json!({
+        "keccak256": format!("0x{}", abi::to_hex(&abi::keccak256(content.as_bytes()))),
+        "size": content.len(),
+    })
+}
+
+/// Get 1-based numbers of lines which differ between before, and after pass.
+/// Lines are split by LF only, so changes of line ending show up as well.
+///
+/// # Arguments
+/// * `before` - content before pass
+/// * `after` - content after pass
+fn changed_lines(before: &str, after: &str) -> Vec<usize> {
+    let before: Vec<&str> = before.split('\n').collect();
+    let after: Vec<&str> = after.split('\n').collect();
+    (0..before.len().max(after.len()))
+        .filter(|i| before.get(*i) != after.get(*i))
+        .map(|i| i + 1)
+        .collect()
+}
+
+/// Transforms applied to a single file, recorded pass by pass.
+pub struct FileTransforms {
+    address: String,
+    path: String,
+    original: Value,
+    passes: Vec<Value>,
+}
+
+impl FileTransforms {
+    /// Start recording transforms of file.
+    ///
+    /// # Arguments
+    /// * `address` - contract address
+    /// * `path` - path of file as verified
+    /// * `original` - content as decoded from response of API platform
+    pub fn new(address: &str, path: &str, original: &str) -> FileTransforms {
+        FileTransforms {
+            address: address.to_owned(),
+            path: path.to_owned(),
+            original: digest(original),
+            passes: Vec::new(),
+        }
+    }
+
+    /// Record pass run on file.
+    ///
+    /// # Arguments
+    /// * `name` - name of pass
+    /// * `before` - content before pass
+    /// * `after` - content after pass
+    pub fn pass(&mut self, name: &str, before: &str, after: &str) {
+        let lines = if before == after { Vec::new() } else { changed_lines(before, after) };
+        self.passes.push(json!({
+            "name": name,
+            "changed": before != after,
+            "changedLines": lines,
+            "output": digest(after),
+        }));
+    }
+
+    /// Finish recording with final content, and path written at.
+    ///
+    /// # Arguments
+    /// * `placed` - path of file as written relative to output directory
+    /// * `output` - final content
+    pub fn finish(self, placed: &str, output: &str) {
+        let untouched = self.passes.iter().all(|p| p["changed"] == false);
+        let entry = json!({
+            "address": self.address,
+            "path": self.path,
+            "outputPath": placed,
+            "original": self.original,
+            "passes": self.passes,
+            "output": digest(output),
+            "untouched": untouched,
+        });
+        if let Ok(mut files) = FILES.lock() {
+            if let Some(files) = files.as_mut() {
+                files.push(entry);
+            }
+        }
+    }
+}
+
+/// Write recorded transforms as JSON file.
+///
+/// # Arguments
+/// * `path` - path of log file
+pub fn write_log(path: &str) -> Result<(), String> {
+    let files = FILES.lock().ok().and_then(|f| f.clone()).unwrap_or_default();
+    let log = json!({
+        "creator": { "name": "tracpls", "version": env!("CARGO_PKG_VERSION") },
+        "files": files,
+    });
+    let content = match serde_json::to_string_pretty(&log) {
+        Ok(res) => res,
+        Err(e) => {
+            let err_msg = format!("Error serializing transform log; err={}", e);
+            return Err(err_msg);
+        }
+    };
+    crate::create_intermediate_dirs(path)?;
+    crate::write_file(path, &format!("{}\n", content))
+}
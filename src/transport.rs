@@ -0,0 +1,282 @@
+//! HTTP transport which requests to API platforms, nodes, and remote cache are
+//! sent through. Requests, and responses are plain data, so building requests
+//! (see `ExplorerBackend::request()`), and parsing responses (see
+//! `explorer::parse_body()`) don't depend on how they're sent.
+//!
+//! Natively requests are sent with isahc by `IsahcTransport`. isahc doesn't
+//! build for wasm32, where hosts only offer asynchronous `fetch()`; there
+//! `AsyncTransport` is implemented by the host bindings (see `bindings/wasm`),
+//! and passed to `fetch_source_with()`, and `fetch_abi_with()`.
+
+use std::future::Future;
+use std::sync::Mutex;
+
+/// HTTP request.
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    /// Method e.g. `GET`
+    pub method: &'static str,
+
+    pub url: String,
+
+    /// Headers as pairs of name, and value
+    pub headers: Vec<(String, String)>,
+
+    /// Body, empty for `GET`
+    pub body: String,
+}
+
+impl HttpRequest {
+    /// Create request without headers, nor body.
+    ///
+    /// # Arguments
+    /// * `method` - method e.g. `GET`
+    /// * `url` - url
+    pub fn new(method: &'static str, url: &str) -> Self {
+        Self { method, url: url.to_owned(), headers: Vec::new(), body: String::new() }
+    }
+
+    /// Add header to request.
+    ///
+    /// # Arguments
+    /// * `name` - name of header
+    /// * `value` - value of header
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Set body of request.
+    ///
+    /// # Arguments
+    /// * `body` - body
+    pub fn body(mut self, body: String) -> Self {
+        self.body = body;
+        self
+    }
+}
+
+/// HTTP response.
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: u16,
+
+    /// Headers as pairs of name, and value
+    pub headers: Vec<(String, String)>,
+
+    pub body: String,
+}
+
+impl HttpResponse {
+    /// Whether status is 2xx.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// Transport sending requests synchronously.
+pub trait Transport: Send + Sync {
+    /// Send request then return its response whatever its status is. Error is
+    /// the cause of failing to send request, or to read response, for caller
+    /// to report along with what the request was for.
+    ///
+    /// # Arguments
+    /// * `request` - request
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse, String>;
+}
+
+/// Transport sending requests asynchronously e.g. with `fetch()` of the host
+/// on wasm32.
+pub trait AsyncTransport {
+    /// Same as `Transport::send()`.
+    ///
+    /// # Arguments
+    /// * `request` - request
+    fn send(&self, request: HttpRequest) -> impl Future<Output = Result<HttpResponse, String>>;
+}
+
+/// Transport set to send requests with instead of the default one. It lives
+/// until the end of process.
+static TRANSPORT: Mutex<Option<&'static dyn Transport>> = Mutex::new(None);
+
+/// Set transport to send requests with e.g. to embed tracpls in program
+/// with HTTP client of its own.
+///
+/// # Arguments
+/// * `transport` - transport
+pub fn set_transport(transport: Box<dyn Transport>) {
+    if let Ok(mut current) = TRANSPORT.lock() {
+        *current = Some(Box::leak(transport));
+    }
+}
+
+/// Send request through transport set, or the default one of the platform.
+///
+/// # Arguments
+/// * `request` - request
+pub fn send(request: &HttpRequest) -> Result<HttpResponse, String> {
+    let transport = TRANSPORT.lock().ok().and_then(|t| *t);
+    match transport {
+        Some(transport) => transport.send(request),
+        None => default_send(request),
+    }
+}
+
+/// Send request with isahc.
+///
+/// # Arguments
+/// * `request` - request
+#[cfg(not(target_arch = "wasm32"))]
+fn default_send(request: &HttpRequest) -> Result<HttpResponse, String> {
+    IsahcTransport.send(request)
+}
+
+/// There is no synchronous transport on wasm32 unless one is set.
+///
+/// # Arguments
+/// * `request` - request
+#[cfg(target_arch = "wasm32")]
+fn default_send(_request: &HttpRequest) -> Result<HttpResponse, String> {
+    Err("no synchronous transport on wasm32; set one with transport::set_transport(), or use fetch_source_with(), and fetch_abi_with()".to_owned())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use native::IsahcTransport;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use super::{HttpRequest, HttpResponse, Transport};
+    use isahc::config::{CaCertificate, ClientCertificate, Configurable, PrivateKey, VersionNegotiation};
+    use isahc::{HttpClient, Request};
+    use std::io::Read;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    /// Most bytes allocated for response body as of its Content-Length before
+    /// reading it; the rest is allocated as it's actually read
+    const MAX_PREALLOCATED_BODY: u64 = 64 * 1024 * 1024;
+
+    /// HTTP client shared by all requests, so connections are kept alive,
+    /// and reused across requests e.g. in batch mode instead of a TLS
+    /// handshake each.
+    static HTTP_CLIENT: Mutex<Option<HttpClient>> = Mutex::new(None);
+
+    /// Get HTTP client shared by all requests, creating it on first use with
+    /// request settings of backends (see `backend::set_request_settings()`).
+    fn http_client() -> Result<HttpClient, String> {
+        let mut client = match HTTP_CLIENT.lock() {
+            Ok(res) => res,
+            Err(_) => return Err("Error HTTP client is poisoned".to_owned()),
+        };
+        if let Some(client) = client.as_ref() {
+            return Ok(client.clone());
+        }
+
+        let settings = crate::backend::request_settings();
+        let mut builder = HttpClient::builder()
+            .version_negotiation(VersionNegotiation::latest_compatible())
+            .automatic_decompression(true)
+            .tcp_keepalive(Duration::from_secs(60))
+            .connect_timeout(Duration::from_secs(30));
+        if let Some(cert) = settings.client_cert.as_ref() {
+            let key = settings.client_key.as_ref().map(|k| PrivateKey::pem_file(k, None));
+            builder = builder.ssl_client_certificate(ClientCertificate::pem_file(cert, key));
+        }
+        if let Some(ca) = settings.ca_cert.as_ref() {
+            builder = builder.ssl_ca_certificate(CaCertificate::file(ca));
+        }
+        let built = builder.build();
+        match built {
+            Ok(res) => {
+                *client = Some(res.clone());
+                Ok(res)
+            },
+            Err(e) => {
+                let err_msg = format!("Error creating HTTP client; err={}", e);
+                Err(err_msg)
+            }
+        }
+    }
+
+    /// Transport sending requests with isahc through the shared HTTP client.
+    pub struct IsahcTransport;
+
+    impl Transport for IsahcTransport {
+        fn send(&self, request: &HttpRequest) -> Result<HttpResponse, String> {
+            let mut builder = Request::builder().method(request.method).uri(request.url.as_str());
+            for (name, value) in request.headers.iter() {
+                builder = builder.header(name.as_str(), value.as_str());
+            }
+            let built = match builder.body(request.body.clone()) {
+                Ok(res) => res,
+                Err(e) => return Err(e.to_string()),
+            };
+            let mut response = match http_client()?.send(built) {
+                Ok(res) => res,
+                Err(e) => return Err(e.to_string()),
+            };
+
+            // allocate buffer upfront as of Content-Length so large payloads
+            // don't get copied while growing, nor again while decoding; only
+            // up to a cap as the header may be wrong, or hostile
+            let capacity = response.body().len().unwrap_or(0).min(MAX_PREALLOCATED_BODY) as usize;
+            let mut bytes: Vec<u8> = Vec::with_capacity(capacity);
+            if let Err(e) = response.body_mut().read_to_end(&mut bytes) {
+                return Err(e.to_string());
+            }
+            let body = match String::from_utf8(bytes) {
+                Ok(res) => res,
+                Err(e) => return Err(format!("response is not valid UTF-8; {}", e)),
+            };
+            Ok(HttpResponse {
+                status: response.status().as_u16(),
+                headers: response.headers().iter().map(|(n, v)| (n.to_string(), v.to_str().unwrap_or("").to_owned())).collect(),
+                body,
+            })
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// Serve a single raw HTTP response at 127.0.0.1, returning its url.
+    ///
+    /// # Arguments
+    /// * `response` - response as sent over the wire
+    fn serve_once(response: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+                line.clear();
+            }
+            let _ = (&stream).write_all(response.as_bytes());
+        });
+        url
+    }
+
+    #[test]
+    fn isahc_transport_returns_response_whatever_its_status() {
+        let url = serve_once("HTTP/1.1 404 Not Found\r\nContent-Length: 9\r\nX-Test: yes\r\nConnection: close\r\n\r\nnot found");
+        let response = IsahcTransport.send(&HttpRequest::new("GET", &url)).unwrap();
+        assert_eq!(response.status, 404);
+        assert!(!response.is_success());
+        assert_eq!(response.body, "not found");
+        assert!(response.headers.iter().any(|(n, v)| n == "x-test" && v == "yes"));
+    }
+
+    #[test]
+    fn isahc_transport_does_not_trust_content_length_to_allocate() {
+        // advertising petabytes, but sending only a few bytes
+        let url = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 1125899906842624\r\nConnection: close\r\n\r\n{}");
+        let result = IsahcTransport.send(&HttpRequest::new("GET", &url));
+        assert!(result.is_err_and(|e| !e.is_empty()));
+    }
+}
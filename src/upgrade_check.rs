@@ -0,0 +1,272 @@
+//! `upgrade-check` command: validate proposed implementation of proxy
+//! against its current implementation with upgrade-safety rules in the way
+//! of OpenZeppelin Upgrades.
+//!
+//! * constructor must not set state, nor state variables be initialized at
+//!   declaration; neither ends up in storage of proxy
+//! * implementation must not use `selfdestruct`, nor `delegatecall` which
+//!   can destroy it, leaving proxy without code to delegate to
+//! * storage must be append-only; existing variables keep their positions,
+//!   and types, and storage gaps only shrink by what new variables take
+
+use crate::ast::collect;
+use crate::build::{self, BuildOutput};
+use crate::proxy;
+use crate::solc;
+use crate::source::{self, SourceFile};
+use crate::storage_check::{self, StorageVariable};
+use crate::TargetArgs;
+use clap::Args;
+use serde_json::{json, Value};
+
+#[derive(Debug, Args)]
+pub struct UpgradeCheckArgs {
+    #[clap(flatten)]
+    pub target: TargetArgs,
+
+    /// Proposed implementation, either address of deployed contract, or local
+    /// directory of its source files
+    #[clap(long="new", required=true)]
+    pub new: String,
+
+    /// Name of proposed implementation contract. Defaults to name of verified
+    /// contract for address, or name of current implementation for local
+    /// directory.
+    #[clap(long="contract", required=false)]
+    pub contract: Option<String>,
+
+    /// Path to solc binary to use instead of finding one matching the verified
+    /// compiler version
+    #[clap(long="solc", required=false)]
+    pub solc: Option<String>,
+
+    /// Output as JSON instead of human readable text
+    #[clap(long="json", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub json: bool,
+}
+
+/// Violation of upgrade-safety rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// `constructor`, `selfdestruct`, `delegatecall`, or `storage`
+    pub rule: String,
+
+    pub message: String,
+}
+
+/// Compile proposed implementation from local directory with the same
+/// compiler version, and settings as the current one.
+///
+/// # Arguments
+/// * `dir` - directory of source files
+/// * `current` - build of current implementation
+/// * `solc_path` - user supplied path to solc binary
+/// * `output_selection` - value of `outputSelection` of standard JSON input
+fn build_local(dir: &str, current: &BuildOutput, solc_path: Option<&String>, output_selection: Value) -> Result<Value, String> {
+    let root = std::path::Path::new(dir);
+    let mut files = Vec::new();
+    source::collect_local_sources(root, root, &mut files)?;
+    if files.is_empty() {
+        let err_msg = format!("Error no .sol files found in '{}'", dir);
+        return Err(err_msg);
+    }
+    let files: Vec<SourceFile> = files.into_iter().map(|(path, content)| SourceFile { path, content }).collect();
+
+    let solc_path = solc::find_solc(&current.solc_version, solc_path)?;
+    let mut settings = current.settings.clone();
+    settings["outputSelection"] = output_selection;
+    solc::compile(&solc_path, &solc::build_input(&files, settings))
+}
+
+/// Get `ContractDefinition` nodes of contract, and all contracts it inherits
+/// from.
+///
+/// # Arguments
+/// * `output` - standard JSON output of solc with AST selected
+/// * `contract_name` - name of contract
+fn linearized_contracts<'a>(output: &'a Value, contract_name: &str) -> Result<Vec<&'a Value>, String> {
+    let mut contracts = Vec::new();
+    for unit in output["sources"].as_object().map_or(Vec::new(), |s| s.values().collect()) {
+        collect(&unit["ast"], "ContractDefinition", &mut contracts);
+    }
+
+    let contract = match contracts.iter().find(|c| c["name"].as_str() == Some(contract_name)) {
+        Some(res) => res,
+        None => {
+            let err_msg = format!("Error contract '{}' not found in AST of proposed implementation", contract_name);
+            return Err(err_msg);
+        }
+    };
+    let ids: Vec<u64> = contract["linearizedBaseContracts"].as_array().map_or(Vec::new(), |b| b.iter().filter_map(|id| id.as_u64()).collect());
+    Ok(ids.iter().filter_map(|id| contracts.iter().find(|c| c["id"].as_u64() == Some(*id)).copied()).collect())
+}
+
+/// Check that constructors don't set state, and state variables aren't
+/// initialized at declaration. Constructor only disabling initializers is
+/// allowed.
+///
+/// # Arguments
+/// * `contracts` - `ContractDefinition` nodes
+fn constructor_violations(contracts: &[&Value]) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    for contract in contracts.iter() {
+        let name = contract["name"].as_str().unwrap_or("");
+        for node in contract["nodes"].as_array().map_or(&[][..], |n| n.as_slice()).iter() {
+            match node["nodeType"].as_str().unwrap_or("") {
+                "FunctionDefinition" if node["kind"].as_str() == Some("constructor") => {
+                    let statements = node["body"]["statements"].as_array().map_or(&[][..], |s| s.as_slice());
+                    let disables_only = statements.iter().all(|s| s["expression"]["expression"]["name"].as_str() == Some("_disableInitializers"));
+                    if !disables_only {
+                        violations.push(Violation {
+                            rule: "constructor".to_owned(),
+                            message: format!("constructor of {} sets state; move it into initializer", name),
+                        });
+                    }
+                },
+                "VariableDeclaration" if node["mutability"].as_str() == Some("mutable") && !node["value"].is_null() => {
+                    violations.push(Violation {
+                        rule: "constructor".to_owned(),
+                        message: format!("{}.{} is initialized at declaration; set it in initializer instead", name, node["name"].as_str().unwrap_or("")),
+                    });
+                },
+                _ => (),
+            }
+        }
+    }
+    violations
+}
+
+/// Check that contracts don't use `selfdestruct`, nor `delegatecall`.
+///
+/// # Arguments
+/// * `contracts` - `ContractDefinition` nodes
+fn opcode_violations(contracts: &[&Value]) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    for contract in contracts.iter() {
+        let name = contract["name"].as_str().unwrap_or("");
+        let mut calls = Vec::new();
+        collect(contract, "FunctionCall", &mut calls);
+
+        let uses = |rule: &str| calls.iter().any(|c| {
+            let expression = &c["expression"];
+            match rule {
+                "selfdestruct" => matches!(expression["name"].as_str(), Some("selfdestruct") | Some("suicide")),
+                _ => expression["memberName"].as_str() == Some(rule),
+            }
+        });
+        for rule in ["selfdestruct", "delegatecall"] {
+            if uses(rule) {
+                violations.push(Violation { rule: rule.to_owned(), message: format!("{} uses {}", name, rule) });
+            }
+        }
+    }
+    violations
+}
+
+/// Check that storage layout of proposed implementation only appends to the
+/// current one.
+///
+/// # Arguments
+/// * `current` - storage layout of current implementation
+/// * `proposed` - storage layout of proposed implementation
+fn storage_violations(current: &[StorageVariable], proposed: &[StorageVariable]) -> Vec<Violation> {
+    let violation = |message: String| Violation { rule: "storage".to_owned(), message };
+    let mut violations = Vec::new();
+    for old in current.iter() {
+        if old.is_gap() {
+            // new variables may take space of gap as long as it ends at the same place
+            let crosses_end = proposed.iter().any(|n| n.start() < old.end() && n.end() > old.end());
+            let ends_at_same = proposed.iter().any(|n| n.start() >= old.start() && n.end() == old.end());
+            if crosses_end || !ends_at_same {
+                violations.push(violation(format!("{} of {} at slot {} no longer ends at the same slot", old.label, old.contract, old.slot)));
+            }
+            continue;
+        }
+
+        let at_same = proposed.iter().filter(|n| n.start() == old.start() && !n.is_gap());
+        match at_same.clone().find(|n| n.label == old.label).or(at_same.clone().next()) {
+            None => violations.push(violation(format!("{} ({}) of {} at slot {} was removed, or moved", old.label, old.type_label, old.contract, old.slot))),
+            Some(new) if new.type_label != old.type_label => {
+                violations.push(violation(format!("{} at slot {} changed type from {} to {}", old.label, old.slot, old.type_label, new.type_label)));
+            },
+            Some(new) if new.label != old.label => {
+                violations.push(violation(format!("{} at slot {} was renamed to {}", old.label, old.slot, new.label)));
+            },
+            Some(_) => (),
+        }
+    }
+    violations
+}
+
+/// Execute `upgrade-check` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &UpgradeCheckArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.target.chain)?;
+    let implementation = match proxy::resolve_implementation(chain, &args.target.address)? {
+        Some(res) => res,
+        None => {
+            let err_msg = format!("Error implementation of {} not found; make sure it is a proxy", args.target.address);
+            return Err(err_msg);
+        }
+    };
+
+    let current_target = TargetArgs { address: implementation.address.clone(), chain: args.target.chain.clone() };
+    let current = build::build(&current_target, args.solc.as_ref(), json!({ "*": { "*": ["storageLayout"] } }))?;
+    let current_layout = storage_check::layout_of(&current.output, &current.contract_name)?;
+
+    let output_selection = json!({ "*": { "*": ["storageLayout"], "": ["ast"] } });
+    let (proposed, contract_name) = if std::path::Path::new(&args.new).is_dir() {
+        let output = build_local(&args.new, &current, args.solc.as_ref(), output_selection)?;
+        (output, args.contract.clone().unwrap_or_else(|| current.contract_name.clone()))
+    }
+    else {
+        let new_target = TargetArgs { address: args.new.clone(), chain: args.target.chain.clone() };
+        let built = build::build(&new_target, args.solc.as_ref(), output_selection)?;
+        (built.output, args.contract.clone().unwrap_or(built.contract_name))
+    };
+    let proposed_layout = storage_check::layout_of(&proposed, &contract_name)?;
+    let contracts = linearized_contracts(&proposed, &contract_name)?;
+
+    let mut violations = constructor_violations(&contracts);
+    violations.extend(opcode_violations(&contracts));
+    violations.extend(storage_violations(&current_layout, &proposed_layout));
+    violations.extend(storage_check::gap_issues(&proposed_layout).into_iter().map(|i| Violation { rule: "storage".to_owned(), message: i.message }));
+
+    if args.json {
+        let out = json!({
+            "proxy": args.target.address.to_lowercase(),
+            "current": implementation.address,
+            "new": args.new,
+            "contract": contract_name,
+            "violations": violations.iter().map(|v| json!({ "rule": v.rule, "message": v.message })).collect::<Vec<Value>>(),
+        });
+        match serde_json::to_string_pretty(&out) {
+            Ok(res) => println!("{}", res),
+            Err(e) => {
+                let err_msg = format!("Error serializing upgrade check to JSON; err={}", e);
+                return Err(err_msg);
+            }
+        }
+    }
+    else {
+        println!("current: {} ({})", implementation.address, implementation.source);
+        println!("new: {} ({})", args.new, contract_name);
+        if violations.is_empty() {
+            println!("No upgrade-safety violations found");
+        }
+        else {
+            println!("{:<13} DETAIL", "RULE");
+            for violation in violations.iter() {
+                println!("{:<13} {}", violation.rule, violation.message);
+            }
+        }
+    }
+
+    if !violations.is_empty() {
+        let err_msg = format!("Error {} upgrade-safety violation(s) found", violations.len());
+        return Err(err_msg);
+    }
+    Ok(())
+}
@@ -0,0 +1,129 @@
+//! `watch-deployer` command: poll explorer for contracts newly created by an
+//! address e.g. of a protocol team, or an attacker, and fetch source code of
+//! each as soon as it gets verified.
+//!
+//! Each contract is written to `<out-dir>/<address>/`. Contracts already
+//! fetched there are skipped, so watching can be stopped, and resumed, or run
+//! periodically with `--once`. Contracts not verified yet are checked again
+//! on every poll.
+
+use crate::display;
+use crate::explorer::{self, Created};
+use crate::fetch;
+use crate::manifest;
+use crate::progress;
+use clap::Args;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Args)]
+pub struct WatchDeployerArgs {
+    /// Address of deployer to watch, either account, or factory contract
+    #[clap(required=true, value_name="DEPLOYER")]
+    pub deployer: String,
+
+    /// Which chain to work with, by name e.g. 'bsc', 'ethereum', 'polygon',
+    /// and 'arbitrum', or chain id e.g. '56'. See 'tracpls chains' for all.
+    #[clap(long="chain", short='c', required=true, multiple_values=false)]
+    pub chain: String,
+
+    /// Directory to write source code of each verified contract into
+    #[clap(long="out-dir", short='o', required=true)]
+    pub out_dir: String,
+
+    /// Seconds to wait between polls
+    #[clap(long="interval", required=false, default_value="60")]
+    pub interval: u64,
+
+    /// Block number to look for contracts created from. All contracts ever
+    /// created are looked for by default.
+    #[clap(long="start-block", required=false)]
+    pub start_block: Option<u64>,
+
+    /// Poll once, then exit e.g. to run periodically by cron
+    #[clap(long="once", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub once: bool,
+}
+
+/// Print line of what happened to contract prefixed with current time.
+///
+/// # Arguments
+/// * `message` - what happened
+fn log(message: &str) {
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    println!("[{}] {}", display::format_timestamp(&now.to_string()), message);
+}
+
+/// Execute `watch-deployer` command.
+///
+/// # Arguments
+/// * `args` - arguments of the command
+pub fn run(args: &WatchDeployerArgs) -> Result<(), String> {
+    let chain = crate::parse_chain(&args.chain)?;
+    let deployer = args.deployer.to_lowercase();
+    let options = fetch::FetchOptions {
+        abi_only: false,
+        abi_pretty_print: true,
+        clean_crlf: true,
+        layout: crate::layout::Layout::Flat,
+        project_files: true,
+        artifact_format: None,
+        order: crate::source::Order::Path,
+        format: None,
+        canonical: false,
+        raw_response: false,
+        interfaces_only: false,
+        prune_unreachable: false,
+        contract: None,
+    };
+
+    let mut next_block = args.start_block.unwrap_or(0);
+    let mut pending: BTreeMap<String, Created> = BTreeMap::new();
+    log(&format!("watching contracts created by {} on {} from block {}", deployer, chain.name, next_block));
+    loop {
+        match explorer::get_created(chain, &deployer, next_block) {
+            Ok(created) => {
+                for c in created {
+                    next_block = next_block.max(c.block_number + 1);
+                    let contract_dir = crate::combine_two_path_components(&args.out_dir, &c.address)?;
+                    if manifest::read_manifest(&contract_dir)?.is_some() || pending.contains_key(&c.address) {
+                        continue;
+                    }
+                    log(&format!("new contract {} ({}) at block {} by tx {}", c.address, c.kind, c.block_number, c.tx_hash));
+                    pending.insert(c.address.clone(), c);
+                }
+            },
+            Err(e) => {
+                // transient failure shouldn't stop watching; retried next poll
+                progress::error(Some(&deployer), &e);
+                eprintln!("Warning: failed to list contracts created by {}; {}", deployer, e);
+            }
+        }
+
+        let mut fetched: Vec<String> = Vec::new();
+        for address in pending.keys() {
+            let contract_dir = crate::combine_two_path_components(&args.out_dir, address)?;
+            match fetch::fetch_verified_into(chain, address, &contract_dir, &options) {
+                Ok(Some((name, count))) => {
+                    log(&format!("fetched {} {} file(s) of {} into {}", name, count, address, contract_dir));
+                    fetched.push(address.clone());
+                },
+                Ok(None) => (),
+                Err(e) => {
+                    progress::error(Some(address), &e);
+                    eprintln!("Warning: failed to fetch {}; {}", address, e);
+                }
+            }
+        }
+        for address in fetched.iter() {
+            pending.remove(address);
+        }
+
+        if args.once {
+            if !pending.is_empty() {
+                println!("{} contract(s) not verified yet: {}", pending.len(), pending.keys().cloned().collect::<Vec<String>>().join(", "));
+            }
+            return Ok(());
+        }
+        std::thread::sleep(std::time::Duration::from_secs(args.interval));
+    }
+}
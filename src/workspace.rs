@@ -0,0 +1,137 @@
+//! Temporary workspaces for scratch space of commands e.g. sources laid out
+//! as a project for linters, or output of external decompiler.
+//!
+//! Each workspace is a directory under the system temporary directory named
+//! after what it's for, and removed when dropped, so it's cleaned up after
+//! failure as well. Use `--keep-temp` to retain them for inspection.
+//! Workspaces left behind by killed processes are swept; only ones holding
+//! marker with pid of their owner which no longer runs, so directories of
+//! other tools, and of long-running processes are never touched.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Prefix of directory name of workspaces
+const PREFIX: &str = "tracpls-";
+
+/// Filename of marker inside workspace holding pid of its owner
+const MARKER_FILENAME: &str = ".tracpls-workspace";
+
+/// Age after which workspaces left behind are swept where it can't be told
+/// whether their owner still runs
+const STALE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Whether or not workspaces are retained instead of removed
+static KEEP: AtomicBool = AtomicBool::new(false);
+
+/// Set whether or not workspaces are retained instead of removed.
+///
+/// # Arguments
+/// * `keep` - whether or not to retain workspaces
+pub fn set_keep(keep: bool) {
+    KEEP.store(keep, Ordering::Relaxed);
+}
+
+/// Temporary directory removed when dropped unless retained by
+/// `--keep-temp`.
+pub struct TempWorkspace {
+    path: PathBuf,
+}
+
+impl TempWorkspace {
+    /// Create a new empty workspace.
+    ///
+    /// # Arguments
+    /// * `purpose` - what it's for e.g. `analyze`, part of directory name
+    pub fn new(purpose: &str) -> Result<TempWorkspace, String> {
+        sweep_stale();
+
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.subsec_nanos());
+        let path = std::env::temp_dir().join(format!("{}{}-{}-{}", PREFIX, purpose, std::process::id(), nanos));
+        if path.to_str().is_none() {
+            let err_msg = format!("Error getting path string of temporary directory ('{}')", path.display());
+            return Err(err_msg);
+        }
+        if let Err(e) = std::fs::create_dir_all(&path) {
+            let err_msg = format!("Error creating temporary directory at '{}'; err={}", path.display(), e);
+            return Err(err_msg);
+        }
+        if let Err(e) = std::fs::write(path.join(MARKER_FILENAME), format!("{}\n", std::process::id())) {
+            let _ = std::fs::remove_dir_all(&path);
+            let err_msg = format!("Error writing marker of temporary directory at '{}'; err={}", path.display(), e);
+            return Err(err_msg);
+        }
+        Ok(TempWorkspace { path })
+    }
+
+    /// Get path of workspace.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get path of workspace as string.
+    pub fn path_str(&self) -> &str {
+        // checked to be valid UTF-8 on creation
+        self.path.to_str().unwrap_or_default()
+    }
+}
+
+impl Drop for TempWorkspace {
+    fn drop(&mut self) {
+        if KEEP.load(Ordering::Relaxed) {
+            // unmarked so it's never swept, it's up to user to remove it
+            let _ = std::fs::remove_file(self.path.join(MARKER_FILENAME));
+            eprintln!("Kept temporary workspace at {}", self.path.display());
+        }
+        else if let Err(e) = std::fs::remove_dir_all(&self.path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                eprintln!("Warning: failed to remove temporary workspace at {}; err={}", self.path.display(), e);
+            }
+        }
+    }
+}
+
+/// Whether or not workspace is left behind by a process which no longer
+/// runs. Directory without marker isn't a workspace of tracpls. Where it
+/// can't be told whether owner runs, workspace older than `STALE_AFTER` is
+/// stale.
+///
+/// # Arguments
+/// * `dir` - directory under the system temporary directory
+fn is_stale(dir: &Path) -> bool {
+    let marker = dir.join(MARKER_FILENAME);
+    let pid = match std::fs::read_to_string(&marker).ok().and_then(|c| c.trim().parse::<u32>().ok()) {
+        Some(res) => res,
+        None => return false,
+    };
+    if pid == std::process::id() {
+        return false;
+    }
+    match crate::lock::process_alive(pid) {
+        Some(alive) => !alive,
+        None => std::fs::metadata(&marker)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > STALE_AFTER),
+    }
+}
+
+/// Remove workspaces left behind e.g. by killed processes. Ones retained by
+/// `--keep-temp` are unmarked, thus left alone.
+fn sweep_stale() {
+    let entries = match std::fs::read_dir(std::env::temp_dir()) {
+        Ok(res) => res,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        if !entry.file_name().to_string_lossy().starts_with(PREFIX) {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() && is_stale(&path) {
+            let _ = std::fs::remove_dir_all(&path);
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Output of tracpls in CI mode (`--ci`), run against `mock-server`.
+
+use std::net::{TcpListener, TcpStream};
+use std::process::{Child, Command, Output};
+use std::time::{Duration, Instant};
+use tracpls::workspace::TempWorkspace;
+
+/// Address of contract served by mock server
+const ADDRESS: &str = "0x00000000000000000000000000000000000000aa";
+
+/// Mock server killed when dropped.
+struct MockServer {
+    child: Child,
+    port: u16,
+    _contracts: TempWorkspace,
+}
+
+impl MockServer {
+    /// Start mock server serving a single verified contract at `ADDRESS`.
+    fn start() -> Self {
+        let contracts = TempWorkspace::new("test-ci").unwrap();
+        let contract_dir = contracts.path().join(ADDRESS);
+        std::fs::create_dir_all(&contract_dir).unwrap();
+        std::fs::write(contract_dir.join("A.sol"), "pragma solidity ^0.8.0;\ncontract A { function f() external {} }\n").unwrap();
+        std::fs::write(contract_dir.join("abi.json"), r#"[{"type":"function","name":"f","inputs":[],"outputs":[],"stateMutability":"nonpayable"}]"#).unwrap();
+
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let child = Command::new(env!("CARGO_BIN_EXE_tracpls"))
+            .args(["mock-server", "--dir", contracts.path_str(), "--port", &port.to_string()])
+            .stdout(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+        let started = Instant::now();
+        while TcpStream::connect(("127.0.0.1", port)).is_err() {
+            assert!(started.elapsed() < Duration::from_secs(10), "mock server didn't start");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        Self { child, port, _contracts: contracts }
+    }
+
+    /// Run tracpls against mock server.
+    ///
+    /// # Arguments
+    /// * `args` - arguments
+    fn run(&self, args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_tracpls"))
+            .args(args)
+            .env("TRACPLS_API_URL", format!("http://127.0.0.1:{}/api", self.port))
+            .output()
+            .unwrap()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Assert stdout is exactly summary line of CI mode with counts given.
+///
+/// # Arguments
+/// * `output` - output of tracpls
+/// * `counts` - expected counts e.g. `fetched=1 skipped=0 failed=0`
+fn assert_summary_only(output: &Output, counts: &str) {
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let duration = stdout.strip_prefix(&format!("{} duration=", counts)).and_then(|rest| rest.strip_suffix("s\n"));
+    assert!(duration.is_some_and(|d| d.parse::<u64>().is_ok()), "unexpected stdout {:?}", stdout);
+}
+
+#[test]
+fn ci_prints_only_summary_line() {
+    let server = MockServer::start();
+    let out = TempWorkspace::new("test-ci").unwrap();
+    let out_dir = out.path().join("out").to_string_lossy().into_owned();
+
+    let output = server.run(&["--ci", "-c", "bsc", "-a", ADDRESS, "--out-dir", &out_dir]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_summary_only(&output, "fetched=1 skipped=0 failed=0");
+    assert!(out.path().join("out/A.sol").is_file());
+
+    // commands printing paths of files written unless silenced
+    let script = out.path().join("cast.sh").to_string_lossy().into_owned();
+    let output = server.run(&["--ci", "scaffold", "cast", "-c", "bsc", "-a", ADDRESS, "-o", &script]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_summary_only(&output, "fetched=0 skipped=0 failed=0");
+    assert!(out.path().join("cast.sh").is_file());
+
+    let output = server.run(&["scaffold", "cast", "-c", "bsc", "-a", ADDRESS, "-o", &script]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), format!("{}\n", script));
+}
+
+#[test]
+fn ci_prints_summary_line_on_failure() {
+    let server = MockServer::start();
+    let out = TempWorkspace::new("test-ci").unwrap();
+    let out_dir = out.path().join("out").to_string_lossy().into_owned();
+
+    let output = server.run(&["--ci", "-c", "bsc", "-a", "0x00000000000000000000000000000000000000bb", "--out-dir", &out_dir]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_summary_only(&output, "fetched=0 skipped=0 failed=1");
+}